@@ -0,0 +1,242 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use realfft::num_complex::Complex;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+
+use game_stream_common::AudioFilter;
+use crate::audio_mixer::db_to_linear;
+
+/// 作用于交错采样的滤镜，输出与输入等长
+trait Filter: Send {
+    fn process(&mut self, samples: &mut [f32]);
+}
+
+/// 一个输入上按顺序执行的滤镜
+pub struct FilterChain {
+    filters: Vec<Box<dyn Filter>>,
+}
+
+impl FilterChain {
+    pub fn new(filters: &[AudioFilter], sample_rate: u32, channels: u32) -> Self {
+        let sample_rate = sample_rate.max(1);
+        let channels = channels.max(1) as usize;
+        Self {
+            filters: filters.iter()
+                .map(|filter| -> Box<dyn Filter> {
+                    match filter {
+                        AudioFilter::NoiseSuppression { strength } => {
+                            Box::new(NoiseSuppressor::new(*strength, sample_rate, channels))
+                        }
+                        AudioFilter::Gate { threshold_db, attack_ms, release_ms } => {
+                            Box::new(NoiseGate::new(*threshold_db, *attack_ms, *release_ms, sample_rate, channels))
+                        }
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for filter in &mut self.filters {
+            filter.process(samples);
+        }
+    }
+}
+
+/// 频谱减法降噪 - 持续跟踪每个频段的噪声底，按信噪比衰减频段
+///
+/// 分析窗约 20ms (50% 重叠的 sqrt-Hann 窗)，引入半个分析窗的延迟
+struct NoiseSuppressor {
+    strength: f32,
+    floor: f32,        // 频段的最低增益
+    noise_rise: f32,   // 噪声估计每次分析允许上升的比例
+    size: usize,
+    hop: usize,
+    window: Vec<f32>,
+    forward: Arc<dyn RealToComplex<f32>>,
+    inverse: Arc<dyn ComplexToReal<f32>>,
+    channels: Vec<SuppressorChannel>,
+}
+
+struct SuppressorChannel {
+    pending: Vec<f32>,       // 未满一个 hop 的输入
+    frame: Vec<f32>,         // 最近 size 个输入采样
+    overlap: Vec<f32>,       // 上一次合成的后半段
+    output: VecDeque<f32>,
+    power: Vec<f32>,         // 平滑后的频段能量
+    average: Vec<f32>,       // 长时间平滑的频段能量，用于跟踪噪声底
+    noise: Vec<f32>,         // 噪声底估计
+    gain: Vec<f32>,
+}
+
+impl NoiseSuppressor {
+    fn new(strength: f32, sample_rate: u32, channels: usize) -> Self {
+        let size = (sample_rate as usize / 50).next_power_of_two();
+        let hop = size / 2;
+        let bins = size / 2 + 1;
+        let mut planner = RealFftPlanner::<f32>::new();
+        let strength = strength.clamp(0.0, 1.0);
+
+        Self {
+            strength,
+            floor: db_to_linear(-30.0 * strength),
+            // 噪声底每秒最多上升约 6dB，语音停顿时能跟上环境噪声的变化
+            noise_rise: db_to_linear(6.0 * hop as f32 / sample_rate as f32),
+            size,
+            hop,
+            window: (0..size)
+                .map(|i| (std::f32::consts::PI * i as f32 / size as f32).sin())
+                .collect(),
+            forward: planner.plan_fft_forward(size),
+            inverse: planner.plan_fft_inverse(size),
+            channels: (0..channels)
+                .map(|_| SuppressorChannel {
+                    pending: Vec::with_capacity(hop),
+                    frame: vec![0.0; size],
+                    overlap: vec![0.0; hop],
+                    output: VecDeque::from(vec![0.0; hop]),
+                    power: vec![0.0; bins],
+                    average: vec![0.0; bins],
+                    noise: Vec::new(),
+                    gain: vec![1.0; bins],
+                })
+                .collect(),
+        }
+    }
+
+    fn analyze(&self, channel: &mut SuppressorChannel, spectrum: &mut [Complex<f32>], buffer: &mut [f32]) {
+        channel.frame.copy_within(self.hop.., 0);
+        channel.frame[self.size - self.hop..].copy_from_slice(&channel.pending);
+        channel.pending.clear();
+
+        for ((sample, input), window) in buffer.iter_mut().zip(&channel.frame).zip(&self.window) {
+            *sample = input * window;
+        }
+        if self.forward.process(buffer, spectrum).is_err() {
+            return;
+        }
+
+        if channel.noise.is_empty() {
+            channel.average = spectrum.iter().map(|bin| bin.norm_sqr()).collect();
+            channel.noise = channel.average.iter().map(|power| power.max(f32::MIN_POSITIVE)).collect();
+        }
+        for (bin, value) in spectrum.iter_mut().enumerate() {
+            let power = value.norm_sqr();
+            channel.power[bin] = channel.power[bin] * 0.5 + power * 0.5;
+            channel.average[bin] = channel.average[bin] * 0.9 + power * 0.1;
+            // 平滑能量的最小值低于噪声的平均能量，乘以 1.5 补偿
+            channel.noise[bin] = (channel.noise[bin] * self.noise_rise)
+                .min(channel.average[bin] * 1.5)
+                .max(f32::MIN_POSITIVE);
+
+            // 过减因子为 3；增益在相邻两次分析间平滑，减少残留噪声的"音乐噪声"
+            let snr_gain = 1.0 - 3.0 * self.strength * channel.noise[bin] / channel.power[bin].max(f32::MIN_POSITIVE);
+            let gain = channel.gain[bin] * 0.4 + snr_gain.max(self.floor) * 0.6;
+            channel.gain[bin] = gain;
+            *value *= gain;
+        }
+        // 逆变换要求直流和奈奎斯特频段为实数
+        spectrum[0].im = 0.0;
+        if let Some(last) = spectrum.last_mut() {
+            last.im = 0.0;
+        }
+
+        if self.inverse.process(spectrum, buffer).is_err() {
+            return;
+        }
+        let scale = 1.0 / self.size as f32;
+        for (i, (sample, window)) in buffer.iter().zip(&self.window).enumerate() {
+            let sample = sample * window * scale;
+            if i < self.hop {
+                channel.output.push_back(channel.overlap[i] + sample);
+            } else {
+                channel.overlap[i - self.hop] = sample;
+            }
+        }
+    }
+}
+
+impl Filter for NoiseSuppressor {
+    fn process(&mut self, samples: &mut [f32]) {
+        if self.strength <= 0.0 {
+            return;
+        }
+
+        let count = self.channels.len();
+        let mut spectrum = self.forward.make_output_vec();
+        let mut buffer = self.forward.make_input_vec();
+        let mut channels = std::mem::take(&mut self.channels);
+
+        for (index, channel) in channels.iter_mut().enumerate() {
+            for &sample in samples.iter().skip(index).step_by(count) {
+                channel.pending.push(sample);
+                if channel.pending.len() == self.hop {
+                    self.analyze(channel, &mut spectrum, &mut buffer);
+                }
+            }
+        }
+
+        // 输出队列预置了 hop 个静音采样，总是不少于已输入的采样数
+        for (index, channel) in channels.iter_mut().enumerate() {
+            for sample in samples.iter_mut().skip(index).step_by(count) {
+                *sample = channel.output.pop_front().unwrap_or(0.0);
+            }
+        }
+        self.channels = channels;
+    }
+}
+
+/// 噪声门 - 电平低于阈值时按释放时间关闭，超过阈值时按启动时间打开
+struct NoiseGate {
+    open_threshold: f32,
+    close_threshold: f32, // 比打开阈值低 6dB，避免在阈值附近反复开关
+    attack_step: f32,     // 每个采样帧的增益变化量
+    release_step: f32,
+    envelope_decay: f32,
+    envelope: f32,
+    open: bool,
+    gain: f32,
+    channels: usize,
+}
+
+impl NoiseGate {
+    fn new(threshold_db: f32, attack_ms: u32, release_ms: u32, sample_rate: u32, channels: usize) -> Self {
+        let samples = |ms: u32| (ms.max(1) as f32 / 1000.0 * sample_rate as f32).max(1.0);
+        Self {
+            open_threshold: db_to_linear(threshold_db),
+            close_threshold: db_to_linear(threshold_db - 6.0),
+            attack_step: 1.0 / samples(attack_ms),
+            release_step: 1.0 / samples(release_ms),
+            // 包络约 10ms 衰减到 1/e，波形过零时门不会关闭
+            envelope_decay: (-1.0 / samples(10)).exp(),
+            envelope: 0.0,
+            open: false,
+            gain: 0.0,
+            channels,
+        }
+    }
+}
+
+impl Filter for NoiseGate {
+    fn process(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(self.channels) {
+            let peak = frame.iter().fold(0f32, |peak, sample| peak.max(sample.abs()));
+            self.envelope = peak.max(self.envelope * self.envelope_decay);
+
+            if self.envelope >= self.open_threshold {
+                self.open = true;
+            } else if self.envelope < self.close_threshold {
+                self.open = false;
+            }
+            self.gain = if self.open {
+                (self.gain + self.attack_step).min(1.0)
+            } else {
+                (self.gain - self.release_step).max(0.0)
+            };
+
+            for sample in frame {
+                *sample *= self.gain;
+            }
+        }
+    }
+}
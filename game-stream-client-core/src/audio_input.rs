@@ -0,0 +1,333 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(feature = "audio-capture")]
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+#[cfg(feature = "audio-capture")]
+use cpal::{FromSample, SizedSample};
+use tokio::sync::mpsc;
+use tracing::{info, error};
+
+use game_stream_common::{AudioSource, StreamResult, StreamError};
+use crate::test_source::ToneGenerator;
+
+/// 设备回调送来的一段采样 (交错的 f32)
+pub struct AudioChunk {
+    pub samples: Vec<f32>,
+}
+
+/// 音频输入流 - cpal 的 Stream 不能跨线程移动，放在单独的线程中运行
+///
+/// 丢弃时停止输入流
+pub struct AudioInput {
+    pub sample_rate: u32,
+    pub channels: u32,
+    pub receiver: mpsc::UnboundedReceiver<AudioChunk>,
+    running: Arc<AtomicBool>,
+}
+
+impl AudioInput {
+    pub fn open(source: &AudioSource) -> StreamResult<Self> {
+        match source {
+            AudioSource::System => return Self::open_loopback(),
+            AudioSource::TestTone => return Ok(Self::open_test_tone()),
+            _ => {}
+        }
+        #[cfg(feature = "audio-capture")]
+        return Self::open_device(find_device(source)?, false);
+        #[cfg(not(feature = "audio-capture"))]
+        Err(StreamError::Capture(
+            "Audio device capture is not available, the client was built without the audio-capture feature".to_string()
+        ))
+    }
+
+    /// 打开 cpal 设备；loopback 为 true 时在输出设备上建立输入流 (WASAPI 环回)
+    #[cfg(feature = "audio-capture")]
+    fn open_device(device: cpal::Device, loopback: bool) -> StreamResult<Self> {
+        let name = device.name().unwrap_or_else(|_| "unknown".to_string());
+        let config = if loopback { device.default_output_config() } else { device.default_input_config() }
+            .map_err(|e| StreamError::Capture(format!("No input config for audio device {}: {}", name, e)))?;
+        let (sample_rate, channels) = (config.sample_rate().0, config.channels() as u32);
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+
+        let thread_running = running.clone();
+        std::thread::Builder::new()
+            .name("audio-input".to_string())
+            .spawn(move || {
+                let stream = match build_stream(&device, &config, sender, thread_running.clone()) {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e));
+                        return;
+                    }
+                };
+                let _ = ready_tx.send(Ok(()));
+
+                while thread_running.load(Ordering::Relaxed) {
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                drop(stream);
+            })
+            .map_err(|e| StreamError::Capture(format!("Failed to start audio thread: {}", e)))?;
+
+        ready_rx.recv()
+            .map_err(|_| StreamError::Capture("Audio thread exited unexpectedly".to_string()))??;
+        info!("Capturing audio from {} ({} Hz, {} channels)", name, sample_rate, channels);
+
+        Ok(Self { sample_rate, channels, receiver, running })
+    }
+}
+
+impl AudioInput {
+    /// 内置测试音，按墙上时钟每 10ms 生成一段
+    fn open_test_tone() -> Self {
+        const SAMPLE_RATE: u32 = 48000;
+        const CHANNELS: u32 = 2;
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let task_running = running.clone();
+        tokio::spawn(async move {
+            let mut generator = ToneGenerator::new(SAMPLE_RATE, CHANNELS, chrono::Utc::now().timestamp_millis() as u64);
+            let mut ticker = tokio::time::interval(Duration::from_millis(10));
+            while task_running.load(Ordering::Relaxed) {
+                ticker.tick().await;
+                let chunk = AudioChunk {
+                    samples: generator.generate_until(chrono::Utc::now().timestamp_millis() as u64),
+                };
+                if sender.send(chunk).is_err() {
+                    break;
+                }
+            }
+        });
+
+        info!("Generating a test tone ({} Hz, {} channels)", SAMPLE_RATE, CHANNELS);
+        Self { sample_rate: SAMPLE_RATE, channels: CHANNELS, receiver, running }
+    }
+
+    /// WASAPI 可以直接在输出设备上建立环回输入流
+    #[cfg(all(target_os = "windows", feature = "audio-capture"))]
+    fn open_loopback() -> StreamResult<Self> {
+        let device = cpal::default_host().default_output_device()
+            .ok_or_else(|| StreamError::Capture("No default audio output device".to_string()))?;
+        Self::open_device(device, true)
+    }
+
+    /// Windows 的环回输入需要 cpal (WASAPI)
+    #[cfg(all(target_os = "windows", not(feature = "audio-capture")))]
+    fn open_loopback() -> StreamResult<Self> {
+        Err(StreamError::Capture(
+            "System audio capture is not available, the client was built without the audio-capture feature".to_string()
+        ))
+    }
+
+    /// 通过 parec 录制默认输出设备的监视器，PulseAudio 和 PipeWire (pipewire-pulse) 都支持
+    #[cfg(target_os = "linux")]
+    fn open_loopback() -> StreamResult<Self> {
+        use std::io::Read;
+        use std::process::{Command, Stdio};
+
+        const SAMPLE_RATE: u32 = 48000;
+        const CHANNELS: u32 = 2;
+        /// 每次读取 10ms 的采样
+        const CHUNK_BYTES: usize = (SAMPLE_RATE / 100 * CHANNELS) as usize * 4;
+
+        let mut child = Command::new("parec")
+            .args(["--device=@DEFAULT_MONITOR@", "--format=float32le", "--raw", "--latency-msec=20"])
+            .arg(format!("--rate={}", SAMPLE_RATE))
+            .arg(format!("--channels={}", CHANNELS))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| StreamError::Capture(format!(
+                "Failed to start parec for system audio (install pulseaudio-utils): {}", e
+            )))?;
+        let mut stdout = child.stdout.take()
+            .ok_or_else(|| StreamError::Capture("parec has no output".to_string()))?;
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = running.clone();
+        std::thread::Builder::new()
+            .name("audio-loopback".to_string())
+            .spawn(move || {
+                let mut buffer = vec![0u8; CHUNK_BYTES];
+                while thread_running.load(Ordering::Relaxed) {
+                    if let Err(e) = stdout.read_exact(&mut buffer) {
+                        error!("System audio capture stopped: {}", e);
+                        break;
+                    }
+                    let chunk = AudioChunk {
+                        samples: buffer.chunks_exact(4).map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).collect(),
+                    };
+                    if sender.send(chunk).is_err() {
+                        break;
+                    }
+                }
+                let _ = child.kill();
+                let _ = child.wait();
+            })
+            .map_err(|e| StreamError::Capture(format!("Failed to start audio thread: {}", e)))?;
+
+        info!("Capturing system audio from the default output monitor ({} Hz, {} channels)", SAMPLE_RATE, CHANNELS);
+        Ok(Self { sample_rate: SAMPLE_RATE, channels: CHANNELS, receiver, running })
+    }
+
+    /// macOS 没有系统级环回，需要虚拟声卡把输出转为输入设备
+    #[cfg(target_os = "macos")]
+    fn open_loopback() -> StreamResult<Self> {
+        Err(StreamError::Capture(
+            "System audio capture is not available on macOS. Install a virtual audio device such as BlackHole, \
+             create a Multi-Output Device with your speakers and BlackHole in Audio MIDI Setup, \
+             then use Device = { device_name = \"BlackHole 2ch\" }".to_string()
+        ))
+    }
+}
+
+impl Drop for AudioInput {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "audio-capture")]
+fn find_device(source: &AudioSource) -> StreamResult<cpal::Device> {
+    let host = cpal::default_host();
+    match source {
+        AudioSource::Device { device_name } => host.input_devices()
+            .map_err(|e| StreamError::Capture(format!("Failed to enumerate audio devices: {}", e)))?
+            .find(|device| device.name().is_ok_and(|name| &name == device_name))
+            .ok_or_else(|| StreamError::Capture(format!("Audio device {:?} not found", device_name))),
+        _ => host.default_input_device()
+            .ok_or_else(|| StreamError::Capture("No default audio input device".to_string())),
+    }
+}
+
+#[cfg(feature = "audio-capture")]
+fn build_stream(
+    device: &cpal::Device,
+    config: &cpal::SupportedStreamConfig,
+    sender: mpsc::UnboundedSender<AudioChunk>,
+    running: Arc<AtomicBool>,
+) -> StreamResult<cpal::Stream> {
+    let stream = match config.sample_format() {
+        cpal::SampleFormat::I8 => build_typed_stream::<i8>(device, config, sender, running),
+        cpal::SampleFormat::I16 => build_typed_stream::<i16>(device, config, sender, running),
+        cpal::SampleFormat::I32 => build_typed_stream::<i32>(device, config, sender, running),
+        cpal::SampleFormat::U8 => build_typed_stream::<u8>(device, config, sender, running),
+        cpal::SampleFormat::U16 => build_typed_stream::<u16>(device, config, sender, running),
+        cpal::SampleFormat::U32 => build_typed_stream::<u32>(device, config, sender, running),
+        cpal::SampleFormat::F32 => build_typed_stream::<f32>(device, config, sender, running),
+        cpal::SampleFormat::F64 => build_typed_stream::<f64>(device, config, sender, running),
+        format => return Err(StreamError::Capture(format!("Unsupported audio sample format {:?}", format))),
+    }
+    .map_err(|e| StreamError::Capture(format!("Failed to open audio input stream: {}", e)))?;
+
+    stream.play().map_err(|e| StreamError::Capture(format!("Failed to start audio input stream: {}", e)))?;
+    Ok(stream)
+}
+
+#[cfg(feature = "audio-capture")]
+fn build_typed_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::SupportedStreamConfig,
+    sender: mpsc::UnboundedSender<AudioChunk>,
+    running: Arc<AtomicBool>,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let error_running = running.clone();
+    device.build_input_stream(
+        &config.config(),
+        move |data: &[T], _: &cpal::InputCallbackInfo| {
+            let chunk = AudioChunk {
+                samples: data.iter().map(|sample| sample.to_sample::<f32>()).collect(),
+            };
+            // 接收端已停止
+            if sender.send(chunk).is_err() {
+                running.store(false, Ordering::Relaxed);
+            }
+        },
+        move |e| {
+            // 设备被拔出等错误后停止，由捕获器重新打开
+            error!("Audio input stream error: {}", e);
+            error_running.store(false, Ordering::Relaxed);
+        },
+        None,
+    )
+}
+
+/// 声道和采样率转换 (线性插值)，跨多段输入保持插值状态
+pub struct Resampler {
+    input_rate: u32,
+    input_channels: usize,
+    output_rate: u32,
+    output_channels: usize,
+    position: f64,       // 下一个输出采样在输入中的位置 (相对 previous)
+    previous: Vec<f32>,  // 上一段输入的最后一帧
+}
+
+impl Resampler {
+    pub fn new(input_rate: u32, input_channels: u32, output_rate: u32, output_channels: u32) -> Self {
+        Self {
+            input_rate: input_rate.max(1),
+            input_channels: input_channels.max(1) as usize,
+            output_rate: output_rate.max(1),
+            output_channels: output_channels.max(1) as usize,
+            position: 1.0,
+            previous: vec![0.0; output_channels.max(1) as usize],
+        }
+    }
+
+    /// 转换一段交错采样，输出交错的 f32
+    pub fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        // 先转换声道：单声道复制到所有声道，多声道到单声道取平均，其他情况按序号对应
+        let frames: Vec<f32> = input.chunks_exact(self.input_channels)
+            .flat_map(|frame| {
+                let mixed: Vec<f32> = match (self.input_channels, self.output_channels) {
+                    (input, output) if input == output => frame.to_vec(),
+                    (1, output) => vec![frame[0]; output],
+                    (input, 1) => vec![frame.iter().sum::<f32>() / input as f32],
+                    (input, output) => (0..output).map(|channel| frame[channel % input]).collect(),
+                };
+                mixed
+            })
+            .collect();
+
+        if self.input_rate == self.output_rate {
+            return frames;
+        }
+
+        // 输入帧序列：上一段的最后一帧 (序号 0) 加上这一段 (序号 1..=count)
+        let channels = self.output_channels;
+        let count = frames.len() / channels;
+        let step = self.input_rate as f64 / self.output_rate as f64;
+        let frame_at = |index: usize| -> &[f32] {
+            if index == 0 { &self.previous } else { &frames[(index - 1) * channels..index * channels] }
+        };
+
+        let mut output = Vec::with_capacity(((count as f64 / step) as usize + 1) * channels);
+        let mut position = self.position;
+        while position <= count as f64 {
+            let index = position.floor() as usize;
+            let fraction = (position - index as f64) as f32;
+            let current = frame_at(index);
+            let next = if index < count { frame_at(index + 1) } else { current };
+            output.extend(current.iter().zip(next).map(|(a, b)| a + (b - a) * fraction));
+            position += step;
+        }
+
+        if count > 0 {
+            let last = frame_at(count).to_vec();
+            self.previous = last;
+        }
+        self.position = position - count as f64;
+        output
+    }
+}
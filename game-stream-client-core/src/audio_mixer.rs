@@ -0,0 +1,169 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use tracing::info;
+
+use game_stream_common::{AudioMixInput, LimiterConfig, StreamResult, StreamError};
+
+/// 电平表的下限 (dBFS)，更小的值和完全静音都显示为这个值
+pub const LEVEL_FLOOR_DB: f32 = -100.0;
+
+/// 峰值电平保持后每秒下降的分贝数
+const PEAK_DECAY_DB_PER_SECOND: f32 = 20.0;
+
+/// RMS 电平的平滑时间常数 (秒)
+const RMS_TIME_CONSTANT: f32 = 0.3;
+
+/// 混音输入的音量、静音状态和电平
+struct MixChannel {
+    name: String,
+    gain_db: AtomicU32, // f32 的位表示
+    muted: AtomicBool,
+    rms: AtomicU32,     // 线性值，f32 的位表示
+    peak: AtomicU32,
+}
+
+/// 混音输入的电平 (dBFS)，在音量和静音之后测量，即推流中实际的声音
+#[derive(Debug, Clone)]
+pub struct InputLevel {
+    pub name: String,
+    pub rms_db: f32,
+    pub peak_db: f32,
+}
+
+/// 混音控制句柄，推流过程中调整各输入的音量和静音，读取各输入的电平
+#[derive(Clone)]
+pub struct MixerControl {
+    channels: Arc<Vec<MixChannel>>,
+}
+
+impl MixerControl {
+    pub fn new(inputs: &[AudioMixInput]) -> Self {
+        Self {
+            channels: Arc::new(inputs.iter().map(|input| MixChannel {
+                name: input.name.clone(),
+                gain_db: AtomicU32::new(input.gain_db.to_bits()),
+                muted: AtomicBool::new(input.muted),
+                rms: AtomicU32::new(0f32.to_bits()),
+                peak: AtomicU32::new(0f32.to_bits()),
+            }).collect()),
+        }
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.channels.iter().map(|channel| channel.name.as_str()).collect()
+    }
+
+    pub fn set_gain_db(&self, name: &str, gain_db: f32) -> StreamResult<()> {
+        let channel = self.channel(name)?;
+        channel.gain_db.store(gain_db.to_bits(), Ordering::Relaxed);
+        info!("Set audio input {:?} gain to {:.1} dB", name, gain_db);
+        Ok(())
+    }
+
+    pub fn set_muted(&self, name: &str, muted: bool) -> StreamResult<()> {
+        self.channel(name)?.muted.store(muted, Ordering::Relaxed);
+        info!("{} audio input {:?}", if muted { "Muted" } else { "Unmuted" }, name);
+        Ok(())
+    }
+
+    /// 切换静音状态，返回切换后是否静音
+    pub fn toggle_muted(&self, name: &str) -> StreamResult<bool> {
+        let muted = !self.channel(name)?.muted.fetch_xor(true, Ordering::Relaxed);
+        info!("{} audio input {:?}", if muted { "Muted" } else { "Unmuted" }, name);
+        Ok(muted)
+    }
+
+    /// 各输入当前的 (名称, 音量 dB, 是否静音)
+    pub fn status(&self) -> Vec<(String, f32, bool)> {
+        self.channels.iter()
+            .map(|channel| (
+                channel.name.clone(),
+                f32::from_bits(channel.gain_db.load(Ordering::Relaxed)),
+                channel.muted.load(Ordering::Relaxed),
+            ))
+            .collect()
+    }
+
+    /// 各输入当前的电平
+    pub fn levels(&self) -> Vec<InputLevel> {
+        self.channels.iter()
+            .map(|channel| InputLevel {
+                name: channel.name.clone(),
+                rms_db: linear_to_db(f32::from_bits(channel.rms.load(Ordering::Relaxed))),
+                peak_db: linear_to_db(f32::from_bits(channel.peak.load(Ordering::Relaxed))),
+            })
+            .collect()
+    }
+
+    /// 记录第 index 个输入在一个混音帧 (时长 seconds) 内的 RMS 和峰值，峰值保持后缓慢下降
+    pub(crate) fn record_level(&self, index: usize, rms: f32, peak: f32, seconds: f32) {
+        let channel = &self.channels[index];
+        let previous_rms = f32::from_bits(channel.rms.load(Ordering::Relaxed));
+        let rms = previous_rms + (rms - previous_rms) * (1.0 - (-seconds / RMS_TIME_CONSTANT).exp());
+        channel.rms.store(rms.to_bits(), Ordering::Relaxed);
+
+        let decayed = f32::from_bits(channel.peak.load(Ordering::Relaxed)) * db_to_linear(-PEAK_DECAY_DB_PER_SECOND * seconds);
+        channel.peak.store(peak.max(decayed).to_bits(), Ordering::Relaxed);
+    }
+
+    /// 第 index 个输入的线性增益，静音时为 0
+    pub(crate) fn linear_gain(&self, index: usize) -> f32 {
+        let channel = &self.channels[index];
+        if channel.muted.load(Ordering::Relaxed) {
+            return 0.0;
+        }
+        db_to_linear(f32::from_bits(channel.gain_db.load(Ordering::Relaxed)))
+    }
+
+    fn channel(&self, name: &str) -> StreamResult<&MixChannel> {
+        self.channels.iter()
+            .find(|channel| channel.name == name)
+            .ok_or_else(|| StreamError::Config(format!(
+                "Audio input {:?} does not exist ({})", name, self.names().join(", ")
+            )))
+    }
+}
+
+/// 峰值限幅器 - 超过阈值时立即压低增益，之后按释放时间恢复
+pub struct Limiter {
+    threshold: f32,
+    release: f32, // 每个采样帧的恢复系数
+    gain: f32,
+    channels: usize,
+}
+
+impl Limiter {
+    pub fn new(config: &LimiterConfig, sample_rate: u32, channels: u32) -> Self {
+        let release_samples = config.release_ms.max(1) as f32 / 1000.0 * sample_rate as f32;
+        Self {
+            threshold: db_to_linear(config.threshold_db.min(0.0)),
+            release: 1.0 - (-1.0 / release_samples).exp(),
+            gain: 1.0,
+            channels: channels.max(1) as usize,
+        }
+    }
+
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(self.channels) {
+            let peak = frame.iter().fold(0f32, |peak, sample| peak.max(sample.abs()));
+            let target = if peak > self.threshold { self.threshold / peak } else { 1.0 };
+
+            if target < self.gain {
+                self.gain = target;
+            } else {
+                self.gain += (target - self.gain) * self.release;
+            }
+            for sample in frame {
+                *sample *= self.gain;
+            }
+        }
+    }
+}
+
+pub fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+pub fn linear_to_db(linear: f32) -> f32 {
+    (20.0 * linear.log10()).max(LEVEL_FLOOR_DB)
+}
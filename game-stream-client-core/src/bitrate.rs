@@ -0,0 +1,131 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use game_stream_common::{AdaptiveBitrateConfig, VideoEncodingConfig, RateControl};
+
+/// 发送队列中等待的包超过这个数量时认为上行带宽不足 (60fps 视频加音频约 0.3 秒)
+const QUEUE_HIGH: usize = 32;
+
+/// 单个包的写入超过这个时间视为写入阻塞
+const WRITE_STALL: Duration = Duration::from_millis(250);
+
+/// RTT 超过最低值的 2 倍且至少高出这么多时认为链路拥塞
+const RTT_MARGIN: Duration = Duration::from_millis(100);
+
+/// 每次降低到当前码率的比例，以及两次降低之间的最短间隔 (等待已堆积的数据发完)
+const DECREASE_FACTOR: f64 = 0.7;
+const DECREASE_HOLD: Duration = Duration::from_secs(2);
+
+/// 每次提高的比例
+const INCREASE_FACTOR: f64 = 1.1;
+
+/// 编码器的目标码率 (kbps)，推流端根据网络状况修改，编码端在下一帧生效
+#[derive(Clone)]
+pub struct BitrateControl {
+    target: Arc<AtomicU32>,
+}
+
+impl BitrateControl {
+    pub fn new(bitrate: u32) -> Self {
+        Self { target: Arc::new(AtomicU32::new(bitrate)) }
+    }
+
+    pub fn target(&self) -> u32 {
+        self.target.load(Ordering::Relaxed)
+    }
+
+    /// 修改目标码率；开启自适应码率时之后仍会按网络状况调整
+    pub fn set_target(&self, bitrate: u32) {
+        self.target.store(bitrate, Ordering::Relaxed);
+    }
+}
+
+/// 推送一个包时观察到的网络状况
+pub struct LinkSample {
+    pub queue_depth: usize,     // 发送队列中等待的包
+    pub write_time: Duration,   // 本次写入耗时
+    pub rtt: Option<Duration>,  // 推流协议能测量时的往返时间
+}
+
+/// 自适应码率 - 拥塞时立即降一档，网络持续正常 increase_interval 后才升一档，避免码率来回振荡
+pub struct AdaptiveBitrate {
+    control: BitrateControl,
+    min_bitrate: u32,
+    max_bitrate: u32,
+    increase_interval: Duration,
+    min_rtt: Option<Duration>,
+    last_decrease: Option<Instant>,
+    stable_since: Instant,
+}
+
+impl AdaptiveBitrate {
+    /// 未开启或使用固定 QP (码率不受控制) 时返回 None
+    pub fn new(config: &AdaptiveBitrateConfig, video: &VideoEncodingConfig, control: BitrateControl) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        if video.rate_control == RateControl::Cqp {
+            warn!("Adaptive bitrate has no effect with constant QP, disabled");
+            return None;
+        }
+        let min_bitrate = config.min_bitrate.min(video.bitrate);
+        info!("Adaptive bitrate enabled ({} ~ {} kbps)", min_bitrate, video.bitrate);
+
+        Some(Self {
+            control,
+            min_bitrate,
+            max_bitrate: video.bitrate,
+            increase_interval: Duration::from_secs(config.increase_interval.max(1)),
+            min_rtt: None,
+            last_decrease: None,
+            stable_since: Instant::now(),
+        })
+    }
+
+    pub fn observe(&mut self, sample: &LinkSample) {
+        let now = Instant::now();
+        if let Some(rtt) = sample.rtt {
+            self.min_rtt = Some(self.min_rtt.map_or(rtt, |min| min.min(rtt)));
+        }
+
+        let congestion = if sample.queue_depth > QUEUE_HIGH {
+            Some(format!("{} packets queued", sample.queue_depth))
+        } else if sample.write_time >= WRITE_STALL {
+            Some(format!("write stalled for {} ms", sample.write_time.as_millis()))
+        } else {
+            match (sample.rtt, self.min_rtt) {
+                (Some(rtt), Some(min)) if rtt > min * 2 && rtt > min + RTT_MARGIN => {
+                    Some(format!("rtt {} ms, normally {} ms", rtt.as_millis(), min.as_millis()))
+                }
+                _ => None,
+            }
+        };
+
+        let current = self.control.target();
+        match congestion {
+            Some(reason) => {
+                self.stable_since = now;
+                if self.last_decrease.is_some_and(|at| now - at < DECREASE_HOLD) {
+                    return;
+                }
+                self.last_decrease = Some(now);
+                let bitrate = ((current as f64 * DECREASE_FACTOR) as u32).max(self.min_bitrate);
+                if bitrate < current {
+                    warn!("Network congested ({}), lowering video bitrate from {} to {} kbps", reason, current, bitrate);
+                    self.control.set_target(bitrate);
+                }
+            }
+            None if now - self.stable_since >= self.increase_interval => {
+                self.stable_since = now;
+                let bitrate = ((current as f64 * INCREASE_FACTOR) as u32).min(self.max_bitrate);
+                if bitrate > current {
+                    info!("Network stable, raising video bitrate from {} to {} kbps", current, bitrate);
+                    self.control.set_target(bitrate);
+                }
+            }
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,823 @@
+use anyhow::Result;
+use tokio::sync::mpsc;
+use tracing::{info, warn, error, debug};
+use std::time::Duration;
+use bytes::Bytes;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+#[cfg(target_os = "linux")]
+use std::os::fd::AsRawFd;
+
+use game_stream_common::{CaptureConfig, EncodingConfig, AudioEncodingConfig, AudioMixInput, LimiterConfig, VideoSource, AudioSource, MosaicLayout, VideoPixelFormat, GpuSurface, StreamResult, StreamError};
+#[cfg(target_os = "linux")]
+use game_stream_common::PortalSourceType;
+#[cfg(target_os = "linux")]
+use crate::portal::{self, PortalStream};
+use crate::window_capture::{WindowMatcher, WindowTracker};
+use crate::cursor::CursorCompositor;
+use crate::game_capture::GameCapture;
+use crate::mosaic;
+use crate::pacing::FramePacer;
+use crate::test_source::TestPattern;
+use crate::audio_filter::FilterChain;
+use crate::audio_input::{AudioInput, Resampler};
+use crate::audio_mixer::{Limiter, MixerControl};
+
+/// 每个音频帧的采样数 (每声道)
+const AUDIO_FRAME_SIZE: u32 = 1024;
+
+/// 音频设备打开失败或断开后重试的间隔
+const AUDIO_RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 每个混音输入最多缓冲的音频帧数，超过时丢弃最旧的采样
+const AUDIO_MAX_BUFFERED_FRAMES: usize = 4;
+
+/// 捕获的帧数据
+#[derive(Debug, Clone)]
+pub struct CapturedFrame {
+    pub frame_type: FrameType,
+    pub data: Bytes,
+    pub timestamp: u64,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub pixel_format: Option<VideoPixelFormat>, // 视频帧的像素格式
+}
+
+#[derive(Debug, Clone)]
+pub enum FrameType {
+    Video,
+    /// 零拷贝路径中显存里的画面，帧不携带像素数据
+    GpuVideo(GpuSurface),
+    Audio,
+}
+
+/// 捕获管理器
+#[derive(Clone)]
+pub struct CaptureManager {
+    config: CaptureConfig,
+    video_capturer: Option<VideoCapturer>,
+    audio_capturer: Option<AudioCapturer>,
+}
+
+impl CaptureManager {
+    pub async fn new(config: &CaptureConfig, encoding: &EncodingConfig) -> Result<Self> {
+        info!("Initializing capture manager...");
+        
+        // 初始化视频捕获器，按编码帧率捕获
+        let video_capturer = Some(VideoCapturer::new(config, encoding.video.fps).await?);
+        
+        // 初始化音频捕获器
+        let audio_enabled = if config.audio_mixer.inputs.is_empty() {
+            !matches!(config.audio_source, AudioSource::Disabled)
+        } else {
+            config.audio_mixer.inputs.iter().any(|input| !matches!(input.source, AudioSource::Disabled))
+        };
+        let audio_capturer = if audio_enabled {
+            Some(AudioCapturer::new(config, &encoding.audio).await?)
+        } else {
+            None
+        };
+        
+        Ok(Self {
+            config: config.clone(),
+            video_capturer,
+            audio_capturer,
+        })
+    }
+    
+    /// 屏幕捕获时切换显示器的句柄
+    pub fn display_switch(&self) -> Option<DisplaySwitch> {
+        self.video_capturer.as_ref().and_then(VideoCapturer::display_switch)
+    }
+    
+    /// 可以在显卡上抓取的画面 (零拷贝路径)，确认编码器能直接编码后调用 enable_gpu_capture 启用
+    pub fn gpu_surface(&self) -> Option<GpuSurface> {
+        self.video_capturer.as_ref().and_then(|capturer| capturer.gpu_surface.clone())
+    }
+    
+    /// 改为输出显存中的画面，不再下载到内存
+    pub fn enable_gpu_capture(&mut self) {
+        if let Some(capturer) = &mut self.video_capturer {
+            if capturer.gpu_surface.is_some() {
+                capturer.zero_copy = true;
+            }
+            if capturer.capture_cursor {
+                warn!("Zero-copy capture does not include the cursor plane, set capture_cursor = false or disable capture.zero_copy");
+            }
+        }
+    }
+    
+    /// 由 flag 开关视频捕获：关闭时不再截图，按帧率输出编码分辨率的透明画面
+    pub fn set_video_enabled(&mut self, flag: Arc<AtomicBool>, encoding: &EncodingConfig) {
+        if let Some(capturer) = &mut self.video_capturer {
+            capturer.set_enabled_flag(flag, Some((encoding.video.width, encoding.video.height)));
+        }
+    }
+    
+    /// 音频混音的控制句柄
+    pub fn mixer_control(&self) -> Option<MixerControl> {
+        self.audio_capturer.as_ref().map(AudioCapturer::mixer_control)
+    }
+    
+    /// 立即捕获一帧画面，不启动捕获任务，用于推流前的检查
+    pub async fn capture_video_frame(&self) -> StreamResult<CapturedFrame> {
+        self.video_capturer.as_ref()
+            .ok_or_else(|| StreamError::Capture("Video capture is not initialized".to_string()))?
+            .capture_frame().await
+    }
+    
+    pub async fn start_capture(&mut self, frame_sender: mpsc::UnboundedSender<CapturedFrame>) -> StreamResult<()> {
+        info!("Starting capture...");
+        
+        let mut tasks = Vec::new();
+        
+        // 启动视频捕获
+        if let Some(video_capturer) = &mut self.video_capturer {
+            let mut capturer = video_capturer.clone();
+            let sender = frame_sender.clone();
+            
+            let task = tokio::spawn(async move {
+                capturer.start_capture(sender).await
+            });
+            tasks.push(task);
+        }
+        
+        // 启动音频捕获
+        if let Some(audio_capturer) = &mut self.audio_capturer {
+            let mut capturer = audio_capturer.clone();
+            let sender = frame_sender.clone();
+            
+            let task = tokio::spawn(async move {
+                capturer.start_capture(sender).await
+            });
+            tasks.push(task);
+        }
+        
+        // 等待所有捕获任务
+        for task in tasks {
+            if let Err(e) = task.await {
+                error!("Capture task failed: {}", e);
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+/// 屏幕捕获的显示器切换句柄，推流过程中切换不需要重建捕获管线
+#[derive(Clone)]
+pub struct DisplaySwitch {
+    current: Arc<AtomicU32>,
+    display_count: usize, // 为 0 时无法枚举显示器，不检查序号
+}
+
+impl DisplaySwitch {
+    pub fn current(&self) -> u32 {
+        self.current.load(Ordering::Relaxed)
+    }
+    
+    pub fn switch_to(&self, index: u32) -> StreamResult<()> {
+        if self.display_count > 0 && index as usize >= self.display_count {
+            return Err(StreamError::Capture(format!(
+                "Display {} does not exist ({} displays)", index, self.display_count
+            )));
+        }
+        
+        let previous = self.current.swap(index, Ordering::Relaxed);
+        if previous != index {
+            info!("Switched capture from display {} to {}", previous, index);
+        }
+        Ok(())
+    }
+    
+    /// 切换到下一个显示器，返回新的序号
+    pub fn next(&self) -> StreamResult<u32> {
+        let next = (self.current() + 1) % self.display_count.max(1) as u32;
+        self.switch_to(next)?;
+        Ok(next)
+    }
+}
+
+/// 视频捕获器
+#[derive(Clone)]
+pub struct VideoCapturer {
+    source: VideoSource,
+    capture_cursor: bool,
+    target_fps: f64,
+    window_tracker: Option<Arc<Mutex<WindowTracker>>>,
+    cursor: Option<Arc<Mutex<CursorCompositor>>>,
+    game_capture: Option<Arc<Mutex<GameCapture>>>,
+    test_pattern: Option<Arc<TestPattern>>,
+    display: DisplaySwitch,
+    display_origins: Vec<(i32, i32)>, // 各显示器左上角的屏幕坐标
+    gpu_surface: Option<GpuSurface>, // 可以在显卡上抓取的画面
+    zero_copy: bool,                 // 是否已启用零拷贝路径
+    enabled: Arc<AtomicBool>,        // 关闭时不截图
+    blank_size: Option<(u32, u32)>,  // 关闭时输出的透明画面大小，为 None 时不输出
+    #[cfg(target_os = "linux")]
+    portal: Option<Arc<PortalStream>>,
+}
+
+impl VideoCapturer {
+    pub async fn new(config: &CaptureConfig, target_fps: f64) -> Result<Self> {
+        let source = resolve_source(&config.video_source);
+        info!("Initializing video capturer for source: {:?}", source);
+        
+        // 门户会话在启动时建立，必要时由用户在系统对话框中选择源
+        #[cfg(target_os = "linux")]
+        let portal = match &source {
+            VideoSource::Portal { source_type } => {
+                Some(Arc::new(PortalStream::open(&config.portal, *source_type, config.capture_cursor).await?))
+            }
+            _ => None,
+        };
+        #[cfg(not(target_os = "linux"))]
+        if matches!(source, VideoSource::Portal { .. }) {
+            return Err(StreamError::Capture("Portal capture is only supported on Linux".to_string()).into());
+        }
+        
+        let window_tracker = match &source {
+            VideoSource::Window { window_title, title_regex, process_name, pid } => {
+                let matcher = WindowMatcher::new(window_title, title_regex.as_deref(), process_name.as_deref(), *pid)?;
+                Some(Arc::new(Mutex::new(WindowTracker::new(matcher, &config.window))))
+            }
+            VideoSource::Region { follow_window: Some(selector), .. } => {
+                let matcher = WindowMatcher::new(&selector.window_title, selector.title_regex.as_deref(),
+                                                 selector.process_name.as_deref(), selector.pid)?;
+                Some(Arc::new(Mutex::new(WindowTracker::new(matcher, &config.window))))
+            }
+            _ => None,
+        };
+        
+        let game_capture = match &source {
+            VideoSource::Game { process_name, pid, api } => {
+                Some(Arc::new(Mutex::new(GameCapture::new(process_name.as_deref(), *pid, *api, &config.game, &config.window)?)))
+            }
+            _ => None,
+        };
+        
+        let test_pattern = match &source {
+            VideoSource::TestPattern { width, height } => Some(Arc::new(TestPattern::new(*width, *height, target_fps))),
+            _ => None,
+        };
+        
+        // 门户捕获由合成器直接嵌入光标，其他方式的截图不包含光标；游戏画面在合成前获取，光标只绘制到回退的窗口画面上
+        let cursor = if config.capture_cursor && !matches!(source, VideoSource::Portal { .. } | VideoSource::TestPattern { .. }) {
+            Some(Arc::new(Mutex::new(CursorCompositor::new(&config.cursor))))
+        } else {
+            None
+        };
+        #[cfg(feature = "screen-capture")]
+        let display_origins: Vec<_> = xcap::Monitor::all()
+            .map(|monitors| monitors.iter().map(|monitor| (monitor.x(), monitor.y())).collect())
+            .unwrap_or_default();
+        #[cfg(not(feature = "screen-capture"))]
+        let display_origins: Vec<(i32, i32)> = Vec::new();
+        let display = DisplaySwitch {
+            current: Arc::new(AtomicU32::new(match &source {
+                VideoSource::Screen { display_index } => *display_index,
+                _ => 0,
+            })),
+            display_count: display_origins.len(),
+        };
+        
+        // 只有屏幕捕获可以在显卡上进行 (KMS 抓取整个显示输出)
+        let gpu_surface = (cfg!(target_os = "linux") && config.zero_copy.enabled && matches!(source, VideoSource::Screen { .. }))
+            .then(|| GpuSurface::DrmPrime {
+                device: config.zero_copy.drm_device.clone(),
+                crtc_id: config.zero_copy.crtc_id,
+            });
+        
+        Ok(Self {
+            source,
+            capture_cursor: config.capture_cursor,
+            target_fps,
+            window_tracker,
+            cursor,
+            game_capture,
+            test_pattern,
+            display,
+            display_origins,
+            gpu_surface,
+            zero_copy: false,
+            enabled: Arc::new(AtomicBool::new(true)),
+            blank_size: None,
+            #[cfg(target_os = "linux")]
+            portal,
+        })
+    }
+
+    /// flag 关闭时暂停截图；blank_size 不为 None 时按帧率输出该大小的透明画面，下游不会断帧
+    pub fn set_enabled_flag(&mut self, flag: Arc<AtomicBool>, blank_size: Option<(u32, u32)>) {
+        self.enabled = flag;
+        self.blank_size = blank_size;
+    }
+
+    pub async fn start_capture(&mut self, frame_sender: mpsc::UnboundedSender<CapturedFrame>) -> StreamResult<()> {
+        info!("Starting video capture...");
+        
+        let mut pacer = FramePacer::new(self.target_fps);
+        let blank = self.blank_size.map(|(width, height)| CapturedFrame {
+            frame_type: FrameType::Video,
+            data: Bytes::from(vec![0u8; (width * height * 4) as usize]),
+            timestamp: 0,
+            width: Some(width),
+            height: Some(height),
+            pixel_format: Some(VideoPixelFormat::Rgba32),
+        });
+        
+        loop {
+            // 时间戳取自帧的截止时间，帧间隔不受捕获耗时抖动影响
+            let tick = pacer.wait().await;
+            if !self.enabled.load(Ordering::Relaxed) {
+                if let Some(blank) = &blank {
+                    if frame_sender.send(CapturedFrame { timestamp: tick.timestamp, ..blank.clone() }).is_err() {
+                        break;
+                    }
+                }
+                continue;
+            }
+            match self.capture_frame().await {
+                Ok(mut frame) => {
+                    frame.timestamp = tick.timestamp;
+                    if let Err(_) = frame_sender.send(frame) {
+                        warn!("Failed to send video frame, receiver dropped");
+                        break;
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to capture video frame {}: {}", tick.index, e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    pacer.resync();
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    async fn capture_frame(&self) -> StreamResult<CapturedFrame> {
+        // 零拷贝路径由编码进程在显卡上抓取，这里只按帧率产生时间戳
+        if let Some(surface) = self.gpu_surface.as_ref().filter(|_| self.zero_copy) {
+            return Ok(CapturedFrame {
+                frame_type: FrameType::GpuVideo(surface.clone()),
+                data: Bytes::new(),
+                timestamp: chrono::Utc::now().timestamp_millis() as u64,
+                width: None,
+                height: None,
+                pixel_format: None,
+            });
+        }
+        
+        // 使用 xcap 进行屏幕捕获
+        match &self.source {
+            VideoSource::Screen { .. } => {
+                let display_index = self.display.current();
+                let frame = self.capture_screen(display_index).await?;
+                self.draw_cursor(frame, self.display_origin(display_index)).await
+            }
+            VideoSource::Mosaic { displays, layout, columns } => {
+                self.capture_mosaic(displays, *layout, *columns).await
+            }
+            VideoSource::Window { .. } => {
+                self.capture_window().await
+            }
+            VideoSource::Region { follow_window: Some(_), padding, .. } => {
+                self.capture_followed_region(*padding).await
+            }
+            VideoSource::Region { x, y, width, height, .. } => {
+                let frame = self.capture_region(*x, *y, *width, *height).await?;
+                self.draw_cursor(frame, (*x as i32, *y as i32)).await
+            }
+            VideoSource::Portal { .. } => {
+                self.capture_portal().await
+            }
+            VideoSource::Game { .. } => {
+                self.capture_game().await
+            }
+            VideoSource::TestPattern { .. } => {
+                self.capture_test_pattern().await
+            }
+        }
+    }
+    
+    async fn capture_screen(&self, display_index: u32) -> StreamResult<CapturedFrame> {
+        // 实际的屏幕捕获实现
+        // 这里需要使用 xcap 库进行实际的屏幕捕获
+        debug!("Capturing screen {}", display_index);
+        
+        // 模拟捕获的屏幕数据
+        let width = 1920;
+        let height = 1080;
+        let data_size = width * height * 4; // RGBA
+        let mock_data = vec![0u8; data_size as usize];
+        
+        Ok(CapturedFrame {
+            frame_type: FrameType::Video,
+            data: Bytes::from(mock_data),
+            timestamp: chrono::Utc::now().timestamp_millis() as u64,
+            width: Some(width),
+            height: Some(height),
+            pixel_format: Some(VideoPixelFormat::Rgba32),
+        })
+    }
+    
+    async fn capture_window(&self) -> StreamResult<CapturedFrame> {
+        let tracker = self.window_tracker.clone()
+            .ok_or_else(|| StreamError::Capture("Window tracker not initialized".to_string()))?;
+        
+        // 窗口枚举和截图是阻塞调用；窗口不可用时跟踪器返回黑帧或占位图
+        let window_frame = tokio::task::spawn_blocking(move || {
+            tracker.lock().unwrap_or_else(|e| e.into_inner()).capture()
+        })
+        .await
+        .map_err(|e| StreamError::Capture(format!("Window capture task failed: {}", e)))?;
+        
+        let frame = CapturedFrame {
+            frame_type: FrameType::Video,
+            data: Bytes::from(window_frame.data),
+            timestamp: chrono::Utc::now().timestamp_millis() as u64,
+            width: Some(window_frame.width),
+            height: Some(window_frame.height),
+            pixel_format: Some(window_frame.pixel_format),
+        };
+        
+        // 占位画面上不绘制光标
+        match window_frame.origin {
+            Some(origin) => self.draw_cursor(frame, origin).await,
+            None => Ok(frame),
+        }
+    }
+    
+    fn display_switch(&self) -> Option<DisplaySwitch> {
+        // 零拷贝路径抓取的显示输出由 capture.zero_copy.crtc_id 决定，不能切换
+        (matches!(self.source, VideoSource::Screen { .. }) && !self.zero_copy).then(|| self.display.clone())
+    }
+    
+    fn display_origin(&self, display_index: u32) -> (i32, i32) {
+        self.display_origins.get(display_index as usize).copied().unwrap_or((0, 0))
+    }
+    
+    /// 分别捕获各显示器后拼接
+    async fn capture_mosaic(&self, displays: &[u32], layout: MosaicLayout, columns: Option<u32>) -> StreamResult<CapturedFrame> {
+        let displays: Vec<u32> = if displays.is_empty() {
+            (0..self.display_origins.len().max(1) as u32).collect()
+        } else {
+            displays.to_vec()
+        };
+        
+        let mut tiles = Vec::with_capacity(displays.len());
+        for display_index in displays {
+            let frame = self.capture_screen(display_index).await?;
+            let frame = self.draw_cursor(frame, self.display_origin(display_index)).await?;
+            tiles.push((frame.width.unwrap_or(0), frame.height.unwrap_or(0), frame.data));
+        }
+        
+        let composed = tokio::task::spawn_blocking(move || {
+            let tiles: Vec<_> = tiles.iter().map(|(width, height, data)| (*width, *height, data.as_ref())).collect();
+            mosaic::compose(&tiles, layout, columns)
+        })
+        .await
+        .map_err(|e| StreamError::Capture(format!("Mosaic compositing task failed: {}", e)))?;
+        
+        Ok(CapturedFrame {
+            frame_type: FrameType::Video,
+            data: Bytes::from(composed.data),
+            timestamp: chrono::Utc::now().timestamp_millis() as u64,
+            width: Some(composed.width),
+            height: Some(composed.height),
+            pixel_format: Some(VideoPixelFormat::Rgba32),
+        })
+    }
+    
+    async fn capture_game(&self) -> StreamResult<CapturedFrame> {
+        let game_capture = self.game_capture.clone()
+            .ok_or_else(|| StreamError::Capture("Game capture not initialized".to_string()))?;
+        
+        let game_frame = tokio::task::spawn_blocking(move || {
+            game_capture.lock().unwrap_or_else(|e| e.into_inner()).capture()
+        })
+        .await
+        .map_err(|e| StreamError::Capture(format!("Game capture task failed: {}", e)))?;
+        
+        let frame = CapturedFrame {
+            frame_type: FrameType::Video,
+            data: Bytes::from(game_frame.data),
+            timestamp: chrono::Utc::now().timestamp_millis() as u64,
+            width: Some(game_frame.width),
+            height: Some(game_frame.height),
+            pixel_format: Some(game_frame.pixel_format),
+        };
+        
+        match game_frame.origin {
+            Some(origin) => self.draw_cursor(frame, origin).await,
+            None => Ok(frame),
+        }
+    }
+    
+    async fn capture_test_pattern(&self) -> StreamResult<CapturedFrame> {
+        let pattern = self.test_pattern.clone()
+            .ok_or_else(|| StreamError::Capture("Test pattern not initialized".to_string()))?;
+        let timestamp = chrono::Utc::now().timestamp_millis() as u64;
+        
+        let data = tokio::task::spawn_blocking(move || pattern.render(timestamp))
+            .await
+            .map_err(|e| StreamError::Capture(format!("Test pattern task failed: {}", e)))?;
+        
+        let VideoSource::TestPattern { width, height } = self.source else {
+            return Err(StreamError::Capture("Test pattern not initialized".to_string()));
+        };
+        Ok(CapturedFrame {
+            frame_type: FrameType::Video,
+            data: Bytes::from(data),
+            timestamp,
+            width: Some(width.max(16)),
+            height: Some(height.max(16)),
+            pixel_format: Some(VideoPixelFormat::Rgba32),
+        })
+    }
+    
+    /// 把光标绘制到画面上，origin 为画面左上角的屏幕坐标
+    async fn draw_cursor(&self, frame: CapturedFrame, origin: (i32, i32)) -> StreamResult<CapturedFrame> {
+        let (Some(cursor), Some(width), Some(height)) = (self.cursor.clone(), frame.width, frame.height) else {
+            return Ok(frame);
+        };
+        
+        let mut data = Vec::from(frame.data);
+        let data = tokio::task::spawn_blocking(move || {
+            cursor.lock().unwrap_or_else(|e| e.into_inner()).composite(&mut data, width, height, origin);
+            data
+        })
+        .await
+        .map_err(|e| StreamError::Capture(format!("Cursor compositing task failed: {}", e)))?;
+        
+        Ok(CapturedFrame {
+            data: Bytes::from(data),
+            ..frame
+        })
+    }
+    
+    /// 捕获跟随窗口的区域，窗口不可用时输出占位画面
+    async fn capture_followed_region(&self, padding: u32) -> StreamResult<CapturedFrame> {
+        let tracker = self.window_tracker.clone()
+            .ok_or_else(|| StreamError::Capture("Window tracker not initialized".to_string()))?;
+        let tracker_handle = tracker.clone();
+        
+        let bounds = tokio::task::spawn_blocking(move || {
+            tracker_handle.lock().unwrap_or_else(|e| e.into_inner()).bounds(padding)
+        })
+        .await
+        .map_err(|e| StreamError::Capture(format!("Window tracking task failed: {}", e)))?;
+        
+        match bounds {
+            Some((x, y, width, height)) => {
+                // 区域不能超出屏幕左上角
+                let (left, top) = (x.max(0), y.max(0));
+                let width = width.saturating_sub(left.abs_diff(x));
+                let height = height.saturating_sub(top.abs_diff(y));
+                let frame = self.capture_region(left as u32, top as u32, width, height).await?;
+                self.draw_cursor(frame, (left, top)).await
+            }
+            None => {
+                let placeholder = tracker.lock().unwrap_or_else(|e| e.into_inner()).placeholder_frame();
+                Ok(CapturedFrame {
+                    frame_type: FrameType::Video,
+                    data: Bytes::from(placeholder.data),
+                    timestamp: chrono::Utc::now().timestamp_millis() as u64,
+                    width: Some(placeholder.width),
+                    height: Some(placeholder.height),
+                    pixel_format: Some(placeholder.pixel_format),
+                })
+            }
+        }
+    }
+    
+    async fn capture_region(&self, x: u32, y: u32, width: u32, height: u32) -> StreamResult<CapturedFrame> {
+        debug!("Capturing region: {}x{} at ({}, {})", width, height, x, y);
+        
+        // 实际的区域捕获实现
+        let data_size = width * height * 4; // RGBA
+        let mock_data = vec![0u8; data_size as usize];
+        
+        Ok(CapturedFrame {
+            frame_type: FrameType::Video,
+            data: Bytes::from(mock_data),
+            timestamp: chrono::Utc::now().timestamp_millis() as u64,
+            width: Some(width),
+            height: Some(height),
+            pixel_format: Some(VideoPixelFormat::Rgba32),
+        })
+    }
+}
+
+impl VideoCapturer {
+    #[cfg(target_os = "linux")]
+    async fn capture_portal(&self) -> StreamResult<CapturedFrame> {
+        let portal = self.portal.as_ref()
+            .ok_or_else(|| StreamError::Capture("Portal session not started".to_string()))?;
+        debug!("Capturing PipeWire node {} (remote fd {})", portal.node_id, portal.remote.as_raw_fd());
+        
+        // 实际的门户捕获实现
+        // 这里需要用 PipeWire 连接门户提供的远端 (portal.remote)，订阅节点的视频帧
+        let (width, height) = portal.size.unwrap_or((1920, 1080));
+        let data_size = width * height * 4; // RGBA
+        let mock_data = vec![0u8; data_size as usize];
+        
+        Ok(CapturedFrame {
+            frame_type: FrameType::Video,
+            data: Bytes::from(mock_data),
+            timestamp: chrono::Utc::now().timestamp_millis() as u64,
+            width: Some(width),
+            height: Some(height),
+            pixel_format: Some(VideoPixelFormat::Rgba32),
+        })
+    }
+    
+    #[cfg(not(target_os = "linux"))]
+    async fn capture_portal(&self) -> StreamResult<CapturedFrame> {
+        Err(StreamError::Capture("Portal capture is only supported on Linux".to_string()))
+    }
+}
+
+/// Wayland 会话中 X11 方式的屏幕和窗口捕获不可用，改用桌面门户
+fn resolve_source(source: &VideoSource) -> VideoSource {
+    #[cfg(target_os = "linux")]
+    if portal::is_wayland_session() {
+        let source_type = match source {
+            // 游戏钩子和测试图案不依赖 X11
+            VideoSource::Portal { .. } | VideoSource::Game { .. } | VideoSource::TestPattern { .. } => return source.clone(),
+            VideoSource::Window { .. } => PortalSourceType::Window,
+            VideoSource::Screen { .. } | VideoSource::Region { .. } | VideoSource::Mosaic { .. } => PortalSourceType::Monitor,
+        };
+        warn!("Wayland session detected, capturing through xdg-desktop-portal instead of {:?}", source);
+        return VideoSource::Portal { source_type };
+    }
+    
+    source.clone()
+}
+
+/// 音频捕获器 - 采集一个或多个音频源，混音为编码格式
+#[derive(Clone)]
+pub struct AudioCapturer {
+    inputs: Vec<AudioMixInput>,
+    control: MixerControl,
+    limiter: LimiterConfig,
+    sample_rate: u32, // 输出采样率和声道数，与编码配置一致
+    channels: u32,
+}
+
+impl AudioCapturer {
+    pub async fn new(config: &CaptureConfig, encoding: &AudioEncodingConfig) -> Result<Self> {
+        // 没有配置混音输入时只采集 audio_source
+        let inputs = if config.audio_mixer.inputs.is_empty() {
+            vec![AudioMixInput {
+                name: "default".to_string(),
+                source: config.audio_source.clone(),
+                gain_db: 0.0,
+                muted: false,
+                filters: config.audio_filters.clone(),
+            }]
+        } else {
+            config.audio_mixer.inputs.iter()
+                .filter(|input| !matches!(input.source, AudioSource::Disabled))
+                .cloned()
+                .collect()
+        };
+        for input in &inputs {
+            info!("Initializing audio capturer for {:?}: {:?}", input.name, input.source);
+        }
+        
+        Ok(Self {
+            control: MixerControl::new(&inputs),
+            inputs,
+            limiter: config.audio_mixer.limiter.clone(),
+            sample_rate: encoding.effective_sample_rate(),
+            channels: encoding.channels,
+        })
+    }
+    
+    /// 推流过程中调整音量和静音的句柄
+    pub fn mixer_control(&self) -> MixerControl {
+        self.control.clone()
+    }
+    
+    /// 按音频帧的时长定时混音，时间戳由已输出的采样数推算
+    ///
+    /// 各输入的设备时钟可能有偏差，缓冲不足时补静音，积压过多时丢弃最旧的采样
+    pub async fn start_capture(&mut self, frame_sender: mpsc::UnboundedSender<CapturedFrame>) -> StreamResult<()> {
+        info!("Starting audio capture...");
+        
+        let frame_samples = AUDIO_FRAME_SIZE as usize * self.channels as usize;
+        let buffers: Vec<Arc<Mutex<VecDeque<f32>>>> = self.inputs.iter()
+            .map(|_| Arc::new(Mutex::new(VecDeque::with_capacity(frame_samples * AUDIO_MAX_BUFFERED_FRAMES))))
+            .collect();
+        let input_tasks: Vec<_> = self.inputs.iter().zip(&buffers)
+            .map(|(input, buffer)| {
+                tokio::spawn(capture_audio_input(input.clone(), buffer.clone(), self.sample_rate, self.channels))
+            })
+            .collect();
+        
+        let mut limiter = self.limiter.enabled.then(|| Limiter::new(&self.limiter, self.sample_rate, self.channels));
+        let frame_seconds = AUDIO_FRAME_SIZE as f32 / self.sample_rate.max(1) as f32;
+        let mut ticker = tokio::time::interval(Duration::from_secs_f64(AUDIO_FRAME_SIZE as f64 / self.sample_rate.max(1) as f64));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Burst);
+        let start_time = chrono::Utc::now().timestamp_millis() as u64;
+        let mut frames_sent: u64 = 0;
+        
+        loop {
+            ticker.tick().await;
+            
+            let mut mixed = vec![0f32; frame_samples];
+            for (index, buffer) in buffers.iter().enumerate() {
+                let gain = self.control.linear_gain(index);
+                let mut buffer = buffer.lock().unwrap_or_else(|e| e.into_inner());
+                let available = buffer.len().min(frame_samples);
+                let (mut sum_squares, mut peak) = (0f32, 0f32);
+                for (target, sample) in mixed.iter_mut().zip(buffer.drain(..available)) {
+                    let sample = sample * gain;
+                    sum_squares += sample * sample;
+                    peak = peak.max(sample.abs());
+                    *target += sample;
+                }
+                // 缓冲不足补的静音也计入电平
+                self.control.record_level(index, (sum_squares / frame_samples as f32).sqrt(), peak, frame_seconds);
+            }
+            if let Some(limiter) = &mut limiter {
+                limiter.process(&mut mixed);
+            }
+            
+            let data: Vec<u8> = mixed.iter()
+                .flat_map(|sample| ((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes())
+                .collect();
+            let frame = CapturedFrame {
+                frame_type: FrameType::Audio,
+                data: Bytes::from(data),
+                timestamp: start_time + frames_sent * AUDIO_FRAME_SIZE as u64 * 1000 / self.sample_rate.max(1) as u64,
+                width: None,
+                height: None,
+                pixel_format: None,
+            };
+            frames_sent += 1;
+            
+            if frame_sender.send(frame).is_err() {
+                warn!("Failed to send audio frame, receiver dropped");
+                break;
+            }
+        }
+        
+        for task in input_tasks {
+            task.abort();
+        }
+        Ok(())
+    }
+}
+
+/// 采集一个音频输入，转换为输出格式后写入缓冲；设备打开失败或断开时重试
+async fn capture_audio_input(input: AudioMixInput, buffer: Arc<Mutex<VecDeque<f32>>>, sample_rate: u32, channels: u32) {
+    let max_buffered = AUDIO_FRAME_SIZE as usize * channels as usize * AUDIO_MAX_BUFFERED_FRAMES;
+    let mut open_failed = false;
+    let mut filters = FilterChain::new(&input.filters, sample_rate, channels);
+    
+    loop {
+        let source = input.source.clone();
+        let mut audio_input = match tokio::task::spawn_blocking(move || AudioInput::open(&source)).await {
+            Ok(Ok(audio_input)) => audio_input,
+            Ok(Err(e)) => {
+                // 只报告第一次失败，之后持续重试直到设备可用
+                if !open_failed {
+                    error!("Failed to open audio input {:?}, retrying every {:?}: {}", input.name, AUDIO_RETRY_INTERVAL, e);
+                    open_failed = true;
+                } else {
+                    debug!("Failed to open audio input {:?}: {}", input.name, e);
+                }
+                tokio::time::sleep(AUDIO_RETRY_INTERVAL).await;
+                continue;
+            }
+            Err(e) => {
+                error!("Audio input task for {:?} failed: {}", input.name, e);
+                return;
+            }
+        };
+        open_failed = false;
+        
+        let mut resampler = Resampler::new(audio_input.sample_rate, audio_input.channels, sample_rate, channels);
+        while let Some(chunk) = audio_input.receiver.recv().await {
+            let mut samples = resampler.process(&chunk.samples);
+            filters.process(&mut samples);
+            let mut buffer = buffer.lock().unwrap_or_else(|e| e.into_inner());
+            buffer.extend(samples);
+            if buffer.len() > max_buffered {
+                // 保持整帧对齐，避免声道错位
+                let excess = (buffer.len() - max_buffered).div_ceil(channels as usize) * channels as usize;
+                let excess = excess.min(buffer.len());
+                buffer.drain(..excess);
+            }
+        }
+        
+        // 设备断开，稍后重新打开
+        buffer.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        warn!("Audio input {:?} stopped, reopening in {:?}", input.name, AUDIO_RETRY_INTERVAL);
+        tokio::time::sleep(AUDIO_RETRY_INTERVAL).await;
+    }
+}
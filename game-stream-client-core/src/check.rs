@@ -0,0 +1,134 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use std::time::Duration;
+
+use game_stream_common::{ClientConfig, VideoSource};
+use crate::bitrate::BitrateControl;
+use crate::capture::CaptureManager;
+use crate::encoder::{EncoderManager, KeyframeRequest};
+use crate::pusher::PusherManager;
+use crate::schedule::Schedule;
+use crate::stats::StatsRecorder;
+
+/// 连接超时之外再等待握手完成的时间
+const HANDSHAKE_MARGIN: Duration = Duration::from_secs(5);
+
+/// 推流前的检查结果
+struct Report {
+    failed: usize,
+}
+
+impl Report {
+    fn record(&mut self, name: &str, result: Result<String>) {
+        match result {
+            Ok(detail) => println!("[PASS] {:<14} {}", name, detail),
+            Err(e) => {
+                self.failed += 1;
+                println!("[FAIL] {:<14} {:#}", name, e);
+            }
+        }
+    }
+
+    fn skip(&self, name: &str, reason: &str) {
+        println!("[SKIP] {:<14} {}", name, reason);
+    }
+}
+
+/// 不推流地检查配置、捕获源、编码器和 (connect 为 true 时) 服务器，打印每一项的结果，有失败项时返回错误
+pub async fn run(config: &ClientConfig, connect: bool) -> Result<()> {
+    let mut report = Report { failed: 0 };
+
+    report.record("Configuration", validate(config));
+
+    let capture = match CaptureManager::new(&config.capture, &config.encoding).await {
+        Ok(capture) => Some(capture),
+        Err(e) => {
+            report.record("Capture", Err(e));
+            None
+        }
+    };
+    if let Some(capture) = &capture {
+        report.record("Capture", probe_capture(config, capture).await);
+        report.record("Audio", Ok(match capture.mixer_control() {
+            Some(mixer) => format!("{} input(s): {}", mixer.names().len(), mixer.names().join(", ")),
+            None => "disabled".to_string(),
+        }));
+    }
+
+    let bitrate_control = BitrateControl::new(config.encoding.video.bitrate);
+    let stats = StatsRecorder::new();
+    let encoder = EncoderManager::new(&config.encoding, bitrate_control.clone(), KeyframeRequest::default(), stats.clone()).await;
+    report.record("Encoder", encoder.map(|_| format!(
+        "{:?} {}x{} @ {} fps, {:?} audio",
+        config.encoding.video.codec, config.encoding.video.width, config.encoding.video.height,
+        config.encoding.video.fps, config.encoding.audio.codec,
+    )));
+
+    if connect {
+        report.record("Server", probe_server(config, bitrate_control, stats).await);
+    } else {
+        report.skip("Server", "pass --connect to test the connection");
+    }
+
+    if report.failed > 0 {
+        return Err(anyhow!("{} check(s) failed", report.failed));
+    }
+    println!("All checks passed");
+    Ok(())
+}
+
+/// 推流前就能发现的配置错误
+fn validate(config: &ClientConfig) -> Result<String> {
+    let video = &config.encoding.video;
+    if video.width == 0 || video.height == 0 || !video.width.is_multiple_of(2) || !video.height.is_multiple_of(2) {
+        return Err(anyhow!("Encoding size {}x{} must be non-zero and even", video.width, video.height));
+    }
+    if video.fps.is_nan() || video.fps <= 0.0 {
+        return Err(anyhow!("Encoding frame rate {} must be positive", video.fps));
+    }
+    if video.bitrate == 0 {
+        return Err(anyhow!("Video bitrate must be positive"));
+    }
+    if config.server.host.is_empty() {
+        return Err(anyhow!("Server host is empty"));
+    }
+    if config.server.stream_key.is_empty() {
+        return Err(anyhow!("Stream key is empty"));
+    }
+
+    Schedule::new(&config.stream.schedule)?;
+
+    let mut scenes = HashSet::new();
+    if let Some(scene) = config.scenes.iter().find(|scene| !scenes.insert(scene.name.as_str())) {
+        return Err(anyhow!("Scene {:?} is defined more than once", scene.name));
+    }
+
+    Ok(format!("{:?} to {}:{}", config.server.protocol, config.server.host, config.server.port))
+}
+
+/// 确认捕获源存在并能捕获一帧画面
+async fn probe_capture(config: &ClientConfig, capture: &CaptureManager) -> Result<String> {
+    if let VideoSource::Screen { display_index } = &config.capture.video_source {
+        if let Some(display) = capture.display_switch() {
+            display.switch_to(*display_index)?;
+        }
+    }
+
+    let frame = capture.capture_video_frame().await?;
+    match (frame.width, frame.height) {
+        (Some(width), Some(height)) => Ok(format!("{:?}, captured a {}x{} frame", config.capture.video_source, width, height)),
+        _ => Ok(format!("{:?}, capturing on the GPU", config.capture.video_source)),
+    }
+}
+
+/// 与服务器完成握手后断开
+async fn probe_server(config: &ClientConfig, bitrate_control: BitrateControl, stats: StatsRecorder) -> Result<String> {
+    let mut pusher = PusherManager::new(
+        &config.server, &config.network, &config.encoding, bitrate_control, stats, false,
+    ).await?;
+
+    let timeout = Duration::from_secs(config.network.connection_timeout) + HANDSHAKE_MARGIN;
+    tokio::time::timeout(timeout, pusher.probe()).await
+        .map_err(|_| anyhow!("No response within {} s", timeout.as_secs()))??;
+    Ok(format!("connected to {}:{}", config.server.host, config.server.port))
+}
@@ -0,0 +1,590 @@
+use anyhow::Result;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tracing::{info, warn, error};
+use std::sync::Arc;
+use std::time::Duration;
+
+use game_stream_common::{ClientConfig, ClientStats, StreamError, StreamProtocol, StreamResult};
+use crate::capture::{CaptureManager, CapturedFrame, DisplaySwitch};
+use crate::compositor::{Compositor, SceneSwitch};
+use crate::audio_mixer::MixerControl;
+use crate::encoder::{EncoderManager, KeyframeRequest};
+use crate::pusher::PusherManager;
+use crate::sink::{SinkFactory, StreamSink};
+use crate::bitrate::BitrateControl;
+use crate::stats::{self, StatsRecorder};
+use crate::stream_control::{self, StreamControl};
+use crate::preview::{self, Preview};
+use crate::replay::{self, ReplayBuffer};
+use crate::stats_overlay::{StatsOverlay, StatsOverlayRenderer};
+use crate::snapshot::Snapshot;
+use crate::source_control::{self, SourceControl};
+use crate::remote_input::RemoteInputControl;
+
+/// 停止推流时等待推流任务关闭连接的最长时间
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 推流过程中可调整的控制句柄，不支持的功能为 None
+#[derive(Clone)]
+pub struct ClientControls {
+    pub display: Option<DisplaySwitch>,
+    pub scene: Option<SceneSwitch>,
+    pub mixer: Option<MixerControl>,
+    pub stream: StreamControl,
+    pub replay: Option<ReplayBuffer>,
+    pub stats_overlay: StatsOverlay,
+    pub bitrate: BitrateControl,
+    pub stats: StatsRecorder,
+    pub snapshot: Snapshot,
+    pub sources: SourceControl,
+    pub remote_input: Option<RemoteInputControl>,
+}
+
+/// 主要的流媒体客户端
+pub struct StreamingClient {
+    config: ClientConfig,
+    capture_manager: CaptureManager,
+    encoder_manager: EncoderManager,
+    pusher_manager: PusherManager,
+    scene_switch: Option<SceneSwitch>,
+    bitrate_control: BitrateControl, // 自适应码率的目标在多次重连之间保持
+    stats: StatsRecorder,
+    stream_control: StreamControl,
+    keyframe_request: KeyframeRequest,
+    slate: CapturedFrame, // 暂停时代替捕获画面
+    preview: Option<Preview>, // 预览窗口在多次重连之间保持
+    replay: Option<ReplayBuffer>,
+    stats_overlay: StatsOverlay, // 显示状态在多次重连之间保持
+    source_control: SourceControl, // 视频源开关在多次重连之间保持
+    remote_input: Option<RemoteInputControl>, // 观看者授权在多次重连之间保持
+    transport: Option<SinkFactory>, // 代替 [server] 推流协议的主推流
+    outputs: Vec<SinkFactory>, // 每次开始推流时创建附加的输出
+    shutdown: Arc<watch::Sender<bool>>, // 嵌入的程序要求退出，停止推流后 start 返回
+}
+
+type StatsCallback = Box<dyn Fn(&ClientStats) + Send + Sync>;
+type StreamingCallback = Box<dyn Fn(bool) + Send + Sync>;
+
+/// 嵌入客户端的程序 (图形前端、启动器) 用来创建 StreamingClient，可注册统计和推流状态的回调
+///
+/// ```ignore
+/// let handle = StreamingClient::builder(config)
+///     .on_stats(|stats| println!("{:.0} kbps", stats.send_bitrate))
+///     .on_streaming_changed(|streaming| println!("streaming: {}", streaming))
+///     .build().await?
+///     .spawn();
+/// handle.stop_streaming();
+/// handle.shutdown().await?;
+/// ```
+pub struct StreamingClientBuilder {
+    config: ClientConfig,
+    stopped: bool,
+    stats_callbacks: Vec<StatsCallback>,
+    streaming_callbacks: Vec<StreamingCallback>,
+    transport: Option<SinkFactory>,
+    outputs: Vec<SinkFactory>,
+}
+
+impl StreamingClientBuilder {
+    /// 以停止推流的状态启动，之后调用 start_streaming 或通过快捷键、终端命令开始
+    pub fn start_stopped(mut self, stopped: bool) -> Self {
+        self.stopped = stopped;
+        self
+    }
+
+    /// 每次汇总统计 ([stats] interval) 后调用
+    pub fn on_stats(mut self, callback: impl Fn(&ClientStats) + Send + Sync + 'static) -> Self {
+        self.stats_callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// 开始或停止推流时调用，参数为之后是否在推流
+    pub fn on_streaming_changed(mut self, callback: impl Fn(bool) + Send + Sync + 'static) -> Self {
+        self.streaming_callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// 用嵌入程序自己的传输 (或测试中的回环传输) 代替 [server] 配置的推流协议作为主推流；
+    /// factory 在每次开始推流时调用，server.protocol 为 Custom 时必须提供
+    pub fn transport(mut self, factory: impl Fn() -> StreamResult<Box<dyn StreamSink>> + Send + Sync + 'static) -> Self {
+        self.transport = Some(Arc::new(factory));
+        self
+    }
+
+    /// 附加一个输出 (另一个推流平台、本地录制等)，与主推流接收相同的编码数据；
+    /// factory 在每次开始推流时调用，停止推流时输出随之关闭
+    pub fn add_output(mut self, factory: impl Fn() -> StreamResult<Box<dyn StreamSink>> + Send + Sync + 'static) -> Self {
+        self.outputs.push(Arc::new(factory));
+        self
+    }
+
+    pub async fn build(self) -> Result<StreamingClient> {
+        let mut client = StreamingClient::with_transport(self.config, self.transport).await?;
+        if self.stopped {
+            client.start_stopped();
+        }
+        client.outputs = self.outputs;
+
+        // 回调在后台任务中调用，客户端释放后任务随通道关闭结束
+        if !self.stats_callbacks.is_empty() {
+            let mut stats = client.stats.subscribe();
+            let callbacks = self.stats_callbacks;
+            tokio::spawn(async move {
+                while stats.changed().await.is_ok() {
+                    let latest = stats.borrow_and_update().clone();
+                    if let Some(latest) = latest {
+                        callbacks.iter().for_each(|callback| callback(&latest));
+                    }
+                }
+            });
+        }
+        if !self.streaming_callbacks.is_empty() {
+            let mut streaming = client.stream_control.subscribe();
+            let callbacks = self.streaming_callbacks;
+            tokio::spawn(async move {
+                while streaming.changed().await.is_ok() {
+                    let state = *streaming.borrow_and_update();
+                    callbacks.iter().for_each(|callback| callback(state));
+                }
+            });
+        }
+        Ok(client)
+    }
+}
+
+/// 在后台运行的客户端，嵌入的程序通过它开始/停止推流、读取统计和退出
+pub struct ClientHandle {
+    controls: ClientControls,
+    shutdown: Arc<watch::Sender<bool>>,
+    task: JoinHandle<Result<()>>,
+}
+
+impl ClientHandle {
+    /// 推流过程中的控制句柄，与快捷键、终端命令和本地控制接口使用的相同
+    pub fn controls(&self) -> &ClientControls {
+        &self.controls
+    }
+
+    pub fn start_streaming(&self) {
+        self.controls.stream.set_streaming(true);
+    }
+
+    pub fn stop_streaming(&self) {
+        self.controls.stream.set_streaming(false);
+    }
+
+    pub fn is_streaming(&self) -> bool {
+        self.controls.stream.is_streaming()
+    }
+
+    /// 最近一次汇总的统计，尚未汇总时为 None
+    pub fn stats(&self) -> Option<ClientStats> {
+        self.controls.stats.subscribe().borrow().clone()
+    }
+
+    /// 客户端是否已经退出 (出错、重连次数用完或 shutdown)
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// 停止推流并等待连接关闭后退出，返回客户端的运行结果；
+    /// 停止期间仍在运行的回放缓存等不到重新开始，超时后直接结束
+    pub async fn shutdown(mut self) -> Result<()> {
+        self.shutdown.send_replace(true);
+        self.controls.stream.set_streaming(false);
+        match tokio::time::timeout(STOP_TIMEOUT * 2, &mut self.task).await {
+            Ok(result) => result?,
+            Err(_) => {
+                self.task.abort();
+                Ok(())
+            }
+        }
+    }
+}
+
+impl StreamingClient {
+    pub fn builder(config: ClientConfig) -> StreamingClientBuilder {
+        StreamingClientBuilder {
+            config,
+            stopped: false,
+            stats_callbacks: Vec::new(),
+            streaming_callbacks: Vec::new(),
+            transport: None,
+            outputs: Vec::new(),
+        }
+    }
+
+    pub async fn new(config: ClientConfig) -> Result<Self> {
+        Self::with_transport(config, None).await
+    }
+
+    async fn with_transport(config: ClientConfig, transport: Option<SinkFactory>) -> Result<Self> {
+        info!("Initializing streaming client...");
+        
+        // 初始化捕获管理器
+        let mut capture_manager = CaptureManager::new(&config.capture, &config.encoding).await?;
+        
+        // 初始化编码管理器
+        let bitrate_control = BitrateControl::new(config.encoding.video.bitrate);
+        let stats = StatsRecorder::new();
+        let keyframe_request = KeyframeRequest::default();
+        let encoder_manager = EncoderManager::new(&config.encoding, bitrate_control.clone(), keyframe_request.clone(), stats.clone()).await?;
+        
+        // 初始化推流管理器
+        let remote_input = RemoteInputControl::new(&config.remote_input, stats.clone());
+        if remote_input.is_some() && config.server.protocol != StreamProtocol::Rtmp {
+            warn!("Remote input needs an RTMP connection, {:?} cannot receive viewer input", config.server.protocol);
+        }
+        let mut pusher_manager = create_pusher_manager(&config, transport.as_ref(), bitrate_control.clone(), stats.clone()).await?;
+        if let Some(remote_input) = &remote_input {
+            pusher_manager.set_remote_input(remote_input.sender());
+        }
+        
+        let slate = stream_control::slate_frame(&config.stream.pause, config.encoding.video.width, config.encoding.video.height);
+        
+        // 场景在多次重连之间保持
+        let scene_switch = SceneSwitch::new(&config.scenes);
+        let source_control = SourceControl::new(&config.scenes);
+        if let Some(flag) = source_control.flag(source_control::CAPTURE_SOURCE) {
+            capture_manager.set_video_enabled(flag, &config.encoding);
+        }
+        
+        let preview = Preview::open(
+            &config.preview, &config.encoding.video.hdr, (config.encoding.video.width, config.encoding.video.height),
+            capture_manager.mixer_control(),
+        );
+        
+        // 捕获和编码都能在显卡上进行时使用零拷贝路径；场景合成、预览和统计叠加需要内存中的画面
+        if let Some(surface) = capture_manager.gpu_surface() {
+            if scene_switch.is_some() {
+                info!("Scenes are configured, zero-copy capture disabled");
+            } else if preview.is_some() {
+                info!("Preview window is open, zero-copy capture disabled");
+            } else if config.stats.overlay.enabled {
+                info!("Stats overlay is enabled, zero-copy capture disabled");
+            } else if encoder_manager.supports_gpu_surface(&surface) {
+                info!("Using zero-copy capture from {:?}", surface);
+                capture_manager.enable_gpu_capture();
+            } else {
+                info!("Encoder cannot read {:?} directly, capturing through system memory", surface);
+            }
+        }
+        
+        let replay = ReplayBuffer::new(&config.replay, &config.encoding);
+        let stats_overlay = StatsOverlay::new(config.stats.overlay.enabled);
+        
+        Ok(Self {
+            config,
+            capture_manager,
+            encoder_manager,
+            pusher_manager,
+            scene_switch,
+            bitrate_control,
+            stats,
+            stream_control: StreamControl::new(true),
+            keyframe_request,
+            slate,
+            preview,
+            replay,
+            stats_overlay,
+            source_control,
+            remote_input,
+            transport,
+            outputs: Vec::new(),
+            shutdown: Arc::new(watch::Sender::new(false)),
+        })
+    }
+    
+    /// 推流过程中的控制句柄：显示器切换 (仅屏幕捕获)、场景切换 (配置了场景时)、混音、开始/停止、暂停、保存回放、统计叠加、码率、统计、截图、视频源开关和远程输入
+    pub fn controls(&self) -> ClientControls {
+        ClientControls {
+            display: self.capture_manager.display_switch(),
+            scene: self.scene_switch.clone(),
+            mixer: self.capture_manager.mixer_control(),
+            stream: self.stream_control.clone(),
+            replay: self.replay.clone(),
+            stats_overlay: self.stats_overlay.clone(),
+            bitrate: self.bitrate_control.clone(),
+            stats: self.stats.clone(),
+            snapshot: Snapshot::new(self.capture_manager.clone(), &self.config.encoding.video.hdr),
+            sources: self.source_control.clone(),
+            remote_input: self.remote_input.clone(),
+        }
+    }
+    
+    /// 以停止推流的状态启动，只运行回放缓存，之后通过快捷键或终端命令开始推流；需要在获取控制句柄之前调用
+    pub fn start_stopped(&mut self) {
+        self.stream_control = StreamControl::new(false);
+    }
+    
+    /// 在后台运行 start，返回控制句柄
+    pub fn spawn(mut self) -> ClientHandle {
+        let (controls, shutdown) = (self.controls(), self.shutdown.clone());
+        let task = tokio::spawn(async move { self.start().await });
+        ClientHandle { controls, shutdown, task }
+    }
+    
+    pub async fn start(&mut self) -> Result<()> {
+        info!("Starting streaming client...");
+        
+        let mut reconnect_attempts = 0;
+        stats::spawn_reporter(&self.config.stats, self.stats.clone(), self.bitrate_control.clone()).await;
+        
+        loop {
+            // 通过快捷键或终端命令停止后等待重新开始，重新开始时重新计算重连次数；
+            // 开启回放缓存时停止期间继续捕获和编码
+            if *self.shutdown.borrow() {
+                break;
+            }
+            if !self.stream_control.is_streaming() {
+                if self.replay.is_some() {
+                    info!("Streaming stopped, keeping the replay buffer running until started again");
+                    if let Err(e) = self.run_streaming_loop(false).await {
+                        error!("Replay buffer error: {}", e);
+                    }
+                } else {
+                    info!("Streaming stopped, waiting to be started again");
+                }
+                let mut shutdown = self.shutdown.subscribe();
+                tokio::select! {
+                    _ = self.stream_control.wait_until(true) => {}
+                    _ = shutdown.wait_for(|shutdown| *shutdown) => break,
+                }
+                reconnect_attempts = 0;
+            }
+            
+            let result = self.run_streaming_loop(true).await;
+            if !self.stream_control.is_streaming() {
+                continue;
+            }
+            match result {
+                Ok(_) => {
+                    info!("Streaming completed successfully");
+                    break;
+                }
+                Err(e) => {
+                    error!("Streaming error: {}", e);
+                    
+                    if !self.config.stream.auto_reconnect {
+                        return Err(e.into());
+                    }
+                    
+                    reconnect_attempts += 1;
+                    if reconnect_attempts > self.config.stream.max_reconnect_attempts {
+                        error!("Max reconnection attempts reached, giving up");
+                        return Err(e.into());
+                    }
+                    
+                    warn!("Attempting to reconnect in {} seconds... (attempt {}/{})", 
+                          self.config.stream.reconnect_interval,
+                          reconnect_attempts,
+                          self.config.stream.max_reconnect_attempts);
+                    
+                    tokio::select! {
+                        _ = tokio::time::sleep(Duration::from_secs(self.config.stream.reconnect_interval)) => {}
+                        _ = self.stream_control.wait_until(false) => continue,
+                    }
+                    self.stats.record_reconnect();
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// 只捕获 (和合成) 画面并显示在预览窗口中，不连接服务器；关闭预览窗口后返回
+    pub async fn preview(&mut self) -> Result<()> {
+        let preview = self.preview.clone()
+            .ok_or_else(|| anyhow::anyhow!("Cannot open the preview window"))?;
+        info!("Previewing capture without streaming, close the preview window to exit");
+        
+        let (_capture_handle, _compositing_handle, mut frame_rx) = self.start_sources().await?;
+        while let Some(frame) = frame_rx.recv().await {
+            if preview.is_closed() {
+                break;
+            }
+            preview.show(&frame);
+        }
+        Ok(())
+    }
+    
+    /// 启动捕获任务，配置了场景时接上合成任务；返回 (捕获任务, 合成任务, 画面通道)
+    async fn start_sources(&self) -> StreamResult<(JoinHandle<()>, Option<JoinHandle<()>>, mpsc::UnboundedReceiver<CapturedFrame>)> {
+        let (frame_tx, mut frame_rx) = mpsc::unbounded_channel::<CapturedFrame>();
+        
+        // 启动捕获任务
+        let capture_handle = {
+            let mut capture_manager = self.capture_manager.clone();
+            tokio::spawn(async move {
+                if let Err(e) = capture_manager.start_capture(frame_tx).await {
+                    error!("Capture error: {}", e);
+                }
+            })
+        };
+
+        // 配置了场景时在捕获和编码之间插入合成任务
+        let compositing_handle = match &self.scene_switch {
+            Some(scene_switch) => {
+                let mut compositor = Compositor::new(&self.config, scene_switch.clone(), &self.source_control).await?;
+                let (composited_tx, composited_rx) = mpsc::unbounded_channel::<CapturedFrame>();
+                let captured_rx = std::mem::replace(&mut frame_rx, composited_rx);
+                Some(tokio::spawn(async move {
+                    if let Err(e) = compositor.start_compositing(captured_rx, composited_tx).await {
+                        error!("Compositing error: {}", e);
+                    }
+                }))
+            }
+            None => None,
+        };
+        
+        Ok((capture_handle, compositing_handle, frame_rx))
+    }
+    
+    /// 运行一次捕获、编码和推流管线；live 为 false 时不连接服务器，只为回放缓存编码，开始推流时返回
+    async fn run_streaming_loop(&mut self, live: bool) -> StreamResult<()> {
+        info!("{}", if live { "Starting streaming loop..." } else { "Starting replay buffer..." });
+        
+        // 创建数据流通道
+        let (encoded_tx, mut encoded_rx) = mpsc::unbounded_channel::<game_stream_common::MediaPacket>();
+        
+        let (capture_handle, compositing_handle, mut frame_rx) = self.start_sources().await?;
+
+        // 开启预览时在编码之前显示画面 (暂停时仍显示捕获的画面)
+        if let Some(preview) = self.preview.clone() {
+            let (previewed_tx, previewed_rx) = mpsc::unbounded_channel::<CapturedFrame>();
+            let captured_rx = std::mem::replace(&mut frame_rx, previewed_rx);
+            tokio::spawn(preview::tap_frames(preview, captured_rx, previewed_tx));
+        }
+
+        // 编码之前应用暂停和停止
+        let gating_handle = {
+            let (gated_tx, gated_rx) = mpsc::unbounded_channel::<CapturedFrame>();
+            let captured_rx = std::mem::replace(&mut frame_rx, gated_rx);
+            tokio::spawn(stream_control::gate_frames(
+                self.stream_control.clone(), live, self.slate.clone(), self.keyframe_request.clone(), captured_rx, gated_tx,
+            ))
+        };
+
+        // 编码之前绘制统计叠加，暂停时绘制在占位画面上
+        {
+            let (overlaid_tx, overlaid_rx) = mpsc::unbounded_channel::<CapturedFrame>();
+            let gated_rx = std::mem::replace(&mut frame_rx, overlaid_rx);
+            let renderer = StatsOverlayRenderer::new(
+                self.stats_overlay.clone(), &self.config.stats.overlay, self.stats.clone(),
+                self.bitrate_control.clone(), self.config.encoding.max_queued_frames,
+            );
+            tokio::spawn(renderer.run(gated_rx, overlaid_tx));
+        }
+
+        // 启动编码任务
+        let encoding_handle = {
+            // 重新创建编码管理器
+            let encoder_manager = EncoderManager::new(
+                &self.config.encoding, self.bitrate_control.clone(), self.keyframe_request.clone(), self.stats.clone(),
+            ).await
+                .map_err(|e| StreamError::Internal(format!("Failed to create encoder: {}", e)))?;
+            tokio::spawn(async move {
+                if let Err(e) = encoder_manager.start_encoding(frame_rx, encoded_tx).await {
+                    error!("Encoding error: {}", e);
+                }
+            })
+        };
+
+        // 开启回放缓存时在编码之后缓存
+        if let Some(replay) = &self.replay {
+            let (replayed_tx, replayed_rx) = mpsc::unbounded_channel::<game_stream_common::MediaPacket>();
+            let encoded = std::mem::replace(&mut encoded_rx, replayed_rx);
+            tokio::spawn(replay::tap_packets(replay.clone(), encoded, replayed_tx));
+        }
+
+        // 启动推流任务，不推流时只消费编码数据
+        let mut pushing_handle = if !live {
+            tokio::spawn(async move { while encoded_rx.recv().await.is_some() {} })
+        } else {
+            // 重新创建推流管理器
+            let mut pusher_manager = create_pusher_manager(
+                &self.config, self.transport.as_ref(), self.bitrate_control.clone(), self.stats.clone(),
+            ).await
+                .map_err(|e| StreamError::Internal(format!("Failed to create pusher: {}", e)))?;
+            if let Some(remote_input) = &self.remote_input {
+                pusher_manager.set_remote_input(remote_input.sender());
+            }
+            // 创建失败的输出跳过，不影响主推流
+            for factory in &self.outputs {
+                match factory() {
+                    Ok(output) => pusher_manager.add_output(output),
+                    Err(e) => warn!("Failed to create output: {}", e),
+                }
+            }
+            tokio::spawn(async move {
+                if let Err(e) = pusher_manager.start_pushing(encoded_rx).await {
+                    error!("Pushing error: {}", e);
+                }
+            })
+        };
+        
+        // 等待任何一个任务完成或出错
+        tokio::select! {
+            result = capture_handle => {
+                match result {
+                    Ok(_) => info!("Capture task completed"),
+                    Err(e) => error!("Capture task failed: {}", e),
+                }
+            }
+            result = encoding_handle => {
+                match result {
+                    Ok(_) => info!("Encoding task completed"),
+                    Err(e) => error!("Encoding task failed: {}", e),
+                }
+            }
+            result = &mut pushing_handle => {
+                match result {
+                    Ok(_) => info!("Pushing task completed"),
+                    Err(e) => error!("Pushing task failed: {}", e),
+                }
+            }
+            _ = gating_handle => {
+                // 停止推流：等待推流任务发送完已编码的数据并关闭连接，正在重连时直接放弃
+                if live && !self.stream_control.is_streaming()
+                    && tokio::time::timeout(STOP_TIMEOUT, &mut pushing_handle).await.is_err()
+                {
+                    pushing_handle.abort();
+                }
+            }
+            Some(result) = async { match compositing_handle { Some(handle) => Some(handle.await), None => None } } => {
+                match result {
+                    Ok(_) => info!("Compositing task completed"),
+                    Err(e) => error!("Compositing task failed: {}", e),
+                }
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+impl Drop for StreamingClient {
+    fn drop(&mut self) {
+        info!("Streaming client shutting down...");
+    }
+}
+
+/// 创建推流管理器，嵌入程序提供了传输时代替 [server] 配置的推流协议
+async fn create_pusher_manager(
+    config: &ClientConfig,
+    transport: Option<&SinkFactory>,
+    bitrate_control: BitrateControl,
+    stats: StatsRecorder,
+) -> Result<PusherManager> {
+    match transport {
+        Some(transport) => Ok(PusherManager::with_pusher(
+            transport()?, &config.server, &config.network, &config.encoding,
+            bitrate_control, stats, config.stats.report_to_server,
+        )),
+        None => PusherManager::new(
+            &config.server, &config.network, &config.encoding,
+            bitrate_control, stats, config.stats.report_to_server,
+        ).await,
+    }
+}
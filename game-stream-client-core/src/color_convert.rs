@@ -0,0 +1,170 @@
+use game_stream_common::{VideoPixelFormat, StreamResult, StreamError};
+
+/// 每个线程至少处理的行数，画面太小时不值得拆分
+const MIN_ROWS_PER_THREAD: usize = 64;
+
+/// 最多使用的线程数
+const MAX_THREADS: usize = 8;
+
+/// BT.709 有限范围 (Y 16~235，UV 16~240) 的定点系数，放大 256 倍
+const Y_COEFFICIENTS: [u16; 3] = [47, 157, 16];
+const U_COEFFICIENTS: [i32; 3] = [-26, -86, 112];
+const V_COEFFICIENTS: [i32; 3] = [112, -102, -10];
+
+/// 转换相邻两行：(上一行, 下一行, 上一行亮度, 下一行亮度, U, V)
+type Kernel = fn(&[u8], &[u8], &mut [u8], &mut [u8], &mut [u8], &mut [u8]);
+
+/// RGB 到 YUV 的颜色转换 - 把捕获的 RGBA/BGRA 画面转换为编码器需要的 I420 或 NV12
+///
+/// 按 BT.709 有限范围转换 (编码器按此标记色彩空间)，画面按行分段在多个线程上并行转换。
+/// 内层循环由编译器向量化，x86_64 上检测到 AVX2 时使用以 AVX2 编译的版本
+pub struct ColorConverter {
+    threads: usize,
+    kernels: [Kernel; 2], // RGBA、BGRA
+}
+
+impl ColorConverter {
+    pub fn new() -> Self {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get()).min(MAX_THREADS);
+        Self { threads, kernels: select_kernels() }
+    }
+
+    /// 是否支持该转换 (4:2:0 要求宽高为偶数)
+    pub fn supports(from: VideoPixelFormat, to: VideoPixelFormat, width: u32, height: u32) -> bool {
+        matches!(from, VideoPixelFormat::Rgba32 | VideoPixelFormat::Bgra32)
+            && matches!(to, VideoPixelFormat::Yuv420p | VideoPixelFormat::Nv12)
+            && width > 0 && height > 0
+            && width.is_multiple_of(2) && height.is_multiple_of(2)
+    }
+
+    /// 转换一帧画面
+    pub fn convert(&self, data: &[u8], width: u32, height: u32, from: VideoPixelFormat, to: VideoPixelFormat) -> StreamResult<Vec<u8>> {
+        if !Self::supports(from, to, width, height) {
+            return Err(StreamError::Codec(format!("Cannot convert {}x{} {:?} to {:?}", width, height, from, to)));
+        }
+        let (width, height) = (width as usize, height as usize);
+        let size = width * height * 4;
+        if data.len() < size {
+            return Err(StreamError::Codec(format!(
+                "Video frame has {} bytes, expected {} for {}x{} {:?}", data.len(), size, width, height, from
+            )));
+        }
+
+        let kernel = self.kernels[(from == VideoPixelFormat::Bgra32) as usize];
+        let mut output = vec![0u8; width * height * 3 / 2];
+        let (luma, chroma) = output.split_at_mut(width * height);
+
+        // 按偶数行分段，每段对应连续的亮度行和色度行
+        let threads = self.threads.min(height / MIN_ROWS_PER_THREAD).max(1);
+        let rows_per_band = (height / 2).div_ceil(threads) * 2;
+        let sources = data[..size].chunks(rows_per_band * width * 4);
+        let lumas = luma.chunks_mut(rows_per_band * width);
+
+        std::thread::scope(|scope| {
+            if to == VideoPixelFormat::Yuv420p {
+                let (u, v) = chroma.split_at_mut(width * height / 4);
+                let chroma_band = rows_per_band * width / 4;
+                for (((source, luma), u), v) in sources.zip(lumas).zip(u.chunks_mut(chroma_band)).zip(v.chunks_mut(chroma_band)) {
+                    scope.spawn(move || convert_band(kernel, source, width, luma, Chroma::Planar(u, v)));
+                }
+            } else {
+                for ((source, luma), uv) in sources.zip(lumas).zip(chroma.chunks_mut(rows_per_band * width / 2)) {
+                    scope.spawn(move || convert_band(kernel, source, width, luma, Chroma::Interleaved(uv)));
+                }
+            }
+        });
+
+        Ok(output)
+    }
+}
+
+impl Default for ColorConverter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 一段画面的色度输出
+enum Chroma<'a> {
+    Planar(&'a mut [u8], &'a mut [u8]), // I420：U、V 平面
+    Interleaved(&'a mut [u8]),          // NV12：UV 交错
+}
+
+fn convert_band(kernel: Kernel, source: &[u8], width: usize, luma: &mut [u8], chroma: Chroma) {
+    let stride = width * 4;
+    let sources = source.chunks_exact(stride * 2);
+    let lumas = luma.chunks_exact_mut(width * 2);
+
+    match chroma {
+        Chroma::Planar(u, v) => {
+            for (((source, luma), u), v) in sources.zip(lumas).zip(u.chunks_exact_mut(width / 2)).zip(v.chunks_exact_mut(width / 2)) {
+                let (top, bottom) = source.split_at(stride);
+                let (luma_top, luma_bottom) = luma.split_at_mut(width);
+                kernel(top, bottom, luma_top, luma_bottom, u, v);
+            }
+        }
+        Chroma::Interleaved(uv) => {
+            let (mut u, mut v) = (vec![0u8; width / 2], vec![0u8; width / 2]);
+            for ((source, luma), uv) in sources.zip(lumas).zip(uv.chunks_exact_mut(width)) {
+                let (top, bottom) = source.split_at(stride);
+                let (luma_top, luma_bottom) = luma.split_at_mut(width);
+                kernel(top, bottom, luma_top, luma_bottom, &mut u, &mut v);
+                for ((pair, &u), &v) in uv.chunks_exact_mut(2).zip(&u).zip(&v) {
+                    pair[0] = u;
+                    pair[1] = v;
+                }
+            }
+        }
+    }
+}
+
+/// 转换两行像素，R 和 B 为通道在像素中的位置；色度取 2x2 块的平均值
+#[inline(always)]
+fn convert_rows<const R: usize, const B: usize>(
+    top: &[u8], bottom: &[u8], luma_top: &mut [u8], luma_bottom: &mut [u8], u: &mut [u8], v: &mut [u8],
+) {
+    let [yr, yg, yb] = Y_COEFFICIENTS;
+    for (source, luma) in [(top, luma_top), (bottom, luma_bottom)] {
+        for (pixel, y) in source.chunks_exact(4).zip(luma.iter_mut()) {
+            let (r, g, b) = (pixel[R] as u16, pixel[1] as u16, pixel[B] as u16);
+            *y = ((r * yr + g * yg + b * yb + (16 << 8) + 128) >> 8) as u8;
+        }
+    }
+
+    let blocks = top.chunks_exact(8).zip(bottom.chunks_exact(8));
+    for ((top, bottom), (u, v)) in blocks.zip(u.iter_mut().zip(v.iter_mut())) {
+        let sum = |channel: usize| {
+            top[channel] as i32 + top[channel + 4] as i32 + bottom[channel] as i32 + bottom[channel + 4] as i32
+        };
+        let (r, g, b) = (sum(R), sum(1), sum(B));
+        // 四个像素之和，再除以 4 × 256
+        let chroma = |[cr, cg, cb]: [i32; 3]| (((r * cr + g * cg + b * cb + 512) >> 10) + 128).clamp(0, 255) as u8;
+        *u = chroma(U_COEFFICIENTS);
+        *v = chroma(V_COEFFICIENTS);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn convert_rows_avx2<const R: usize, const B: usize>(
+    top: &[u8], bottom: &[u8], luma_top: &mut [u8], luma_bottom: &mut [u8], u: &mut [u8], v: &mut [u8],
+) {
+    convert_rows::<R, B>(top, bottom, luma_top, luma_bottom, u, v)
+}
+
+#[cfg(target_arch = "x86_64")]
+fn rows_avx2<const R: usize, const B: usize>(
+    top: &[u8], bottom: &[u8], luma_top: &mut [u8], luma_bottom: &mut [u8], u: &mut [u8], v: &mut [u8],
+) {
+    // 只有在检测到 AVX2 后才会选用这个版本
+    unsafe { convert_rows_avx2::<R, B>(top, bottom, luma_top, luma_bottom, u, v) }
+}
+
+/// 按 CPU 特性选择 RGBA 和 BGRA 的转换函数
+fn select_kernels() -> [Kernel; 2] {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        return [rows_avx2::<0, 2>, rows_avx2::<2, 0>];
+    }
+    [convert_rows::<0, 2>, convert_rows::<2, 0>]
+}
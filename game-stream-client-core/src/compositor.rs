@@ -0,0 +1,500 @@
+use std::io::Read;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use bytes::Bytes;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{info, warn, error, debug};
+
+use game_stream_common::{ClientConfig, SceneConfig, LayerConfig, LayerSource, VideoPixelFormat, StreamResult, StreamError};
+use crate::capture::{CapturedFrame, FrameType, VideoCapturer};
+use crate::tonemap::ToneMapper;
+use crate::overlay::{self, TextOverlay};
+use crate::source_control::SourceControl;
+
+/// 场景切换句柄，推流过程中切换场景不需要重建管线
+#[derive(Clone)]
+pub struct SceneSwitch {
+    names: Arc<Vec<String>>,
+    current: Arc<AtomicUsize>,
+}
+
+impl SceneSwitch {
+    /// 没有配置场景时返回 None
+    pub fn new(scenes: &[SceneConfig]) -> Option<Self> {
+        if scenes.is_empty() {
+            return None;
+        }
+        Some(Self {
+            names: Arc::new(scenes.iter().map(|scene| scene.name.clone()).collect()),
+            current: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn current(&self) -> &str {
+        &self.names[self.current.load(Ordering::Relaxed)]
+    }
+
+    pub fn switch_to(&self, name: &str) -> StreamResult<()> {
+        let index = self.names.iter().position(|scene| scene == name)
+            .ok_or_else(|| StreamError::Config(format!("Scene {:?} does not exist", name)))?;
+
+        let previous = self.current.swap(index, Ordering::Relaxed);
+        if previous != index {
+            info!("Switched from scene {:?} to {:?}", self.names[previous], name);
+        }
+        Ok(())
+    }
+
+    fn index(&self) -> usize {
+        self.current.load(Ordering::Relaxed)
+    }
+}
+
+/// 图层使用的 RGBA 画面
+pub(crate) struct LayerFrame {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) data: Bytes,
+}
+
+/// 图层画面的来源
+enum LayerInput {
+    Capture,
+    Latest(Arc<Mutex<Option<Arc<LayerFrame>>>>), // 其他捕获源或摄像头的最新一帧
+    Image(Arc<LayerFrame>),
+    Color([u8; 4]),
+    Text(Mutex<TextOverlay>),
+}
+
+struct Layer {
+    config: LayerConfig,
+    input: LayerInput,
+    visible: Arc<AtomicBool>, // 有名称的图层由 SourceControl 开关
+}
+
+struct Scene {
+    layers: Vec<Layer>, // 按 z_index 从下到上排列
+}
+
+/// 场景合成器 - 位于捕获和编码之间，把当前场景的各图层合成到编码分辨率的画布上
+///
+/// 每收到一帧主捕获画面合成一次；其他图层使用各自来源的最新一帧。
+/// 合成输出为 SDR 的 RGBA，HDR 捕获画面先做色调映射
+pub struct Compositor {
+    scenes: Arc<Vec<Scene>>,
+    switch: SceneSwitch,
+    width: u32,
+    height: u32,
+    tone_mapper: Arc<ToneMapper>,
+    sources: Vec<JoinHandle<()>>,
+    running: Arc<AtomicBool>,
+    fps: f32, // 合成输出的帧率，用于 {fps} 占位符
+    fps_frames: u32,
+    fps_since: Instant,
+}
+
+impl Compositor {
+    pub async fn new(config: &ClientConfig, switch: SceneSwitch, source_control: &SourceControl) -> StreamResult<Self> {
+        let tone_mapper = Arc::new(ToneMapper::new(&config.encoding.video.hdr));
+        let running = Arc::new(AtomicBool::new(true));
+        let mut sources = Vec::new();
+        let mut scenes = Vec::with_capacity(config.scenes.len());
+
+        for scene in &config.scenes {
+            let mut layers = Vec::with_capacity(scene.layers.len());
+            for layer in &scene.layers {
+                let visible = source_control.flag(&layer.name)
+                    .unwrap_or_else(|| Arc::new(AtomicBool::new(layer.visible)));
+                let input = match &layer.source {
+                    LayerSource::Capture => LayerInput::Capture,
+                    LayerSource::Video { source } => {
+                        let mut capture_config = config.capture.clone();
+                        capture_config.video_source = source.clone();
+                        let mut capturer = VideoCapturer::new(&capture_config, config.encoding.video.fps).await
+                            .map_err(|e| StreamError::Capture(format!("Layer {:?}: {}", layer.name, e)))?;
+                        // 图层隐藏时不截图
+                        capturer.set_enabled_flag(visible.clone(), None);
+
+                        let latest = Arc::new(Mutex::new(None));
+                        let (frame_tx, frame_rx) = mpsc::unbounded_channel();
+                        sources.push(tokio::spawn(async move {
+                            if let Err(e) = capturer.start_capture(frame_tx).await {
+                                error!("Layer capture error: {}", e);
+                            }
+                        }));
+                        sources.push(tokio::spawn(store_latest(frame_rx, latest.clone(), tone_mapper.clone())));
+                        LayerInput::Latest(latest)
+                    }
+                    LayerSource::Webcam { device, size, fps } => {
+                        let latest = Arc::new(Mutex::new(None));
+                        let (device, size, fps) = (device.clone(), *size, *fps);
+                        let (latest_handle, running) = (latest.clone(), running.clone());
+                        sources.push(tokio::task::spawn_blocking(move || {
+                            read_webcam(&device, size, fps, &latest_handle, &running);
+                        }));
+                        LayerInput::Latest(latest)
+                    }
+                    LayerSource::Image { path } => {
+                        let image = image::open(path)
+                            .map_err(|e| StreamError::Config(format!("Failed to load layer image {}: {}", path, e)))?
+                            .to_rgba8();
+                        LayerInput::Image(Arc::new(LayerFrame {
+                            width: image.width(),
+                            height: image.height(),
+                            data: Bytes::from(image.into_raw()),
+                        }))
+                    }
+                    LayerSource::Color { color } => LayerInput::Color(*color),
+                    LayerSource::Text { text, font, font_size, color, background } => {
+                        LayerInput::Text(Mutex::new(TextOverlay::new(text, font.as_deref(), *font_size, *color, *background)?))
+                    }
+                    LayerSource::Browser { url, size, refresh_interval, executable } => {
+                        let browser = overlay::find_browser(executable.as_deref())?;
+                        let latest = Arc::new(Mutex::new(None));
+                        let (url, size, refresh_interval) = (url.clone(), *size, Duration::from_secs((*refresh_interval).max(1)));
+                        let (latest_handle, running) = (latest.clone(), running.clone());
+                        sources.push(tokio::task::spawn_blocking(move || {
+                            overlay::capture_web_page(&browser, &url, size, refresh_interval, &latest_handle, &running);
+                        }));
+                        LayerInput::Latest(latest)
+                    }
+                };
+                layers.push(Layer { config: layer.clone(), input, visible });
+            }
+            // 稳定排序，z_index 相同时保持配置顺序
+            layers.sort_by_key(|layer| layer.config.z_index);
+            scenes.push(Scene { layers });
+        }
+
+        info!("Compositing {} scenes at {}x{}, starting with {:?}",
+              scenes.len(), config.encoding.video.width, config.encoding.video.height, switch.current());
+
+        Ok(Self {
+            scenes: Arc::new(scenes),
+            switch,
+            width: config.encoding.video.width,
+            height: config.encoding.video.height,
+            tone_mapper,
+            sources,
+            running,
+            fps: 0.0,
+            fps_frames: 0,
+            fps_since: Instant::now(),
+        })
+    }
+
+    pub async fn start_compositing(
+        &mut self,
+        mut frame_receiver: mpsc::UnboundedReceiver<CapturedFrame>,
+        frame_sender: mpsc::UnboundedSender<CapturedFrame>,
+    ) -> StreamResult<()> {
+        info!("Starting scene compositing...");
+
+        while let Some(frame) = frame_receiver.recv().await {
+            let frame = match frame.frame_type {
+                // 配置了场景时不使用零拷贝路径，不会收到显存中的画面
+                FrameType::Audio | FrameType::GpuVideo(_) => frame,
+                FrameType::Video => match self.composite(frame).await {
+                    Ok(frame) => frame,
+                    Err(e) => {
+                        error!("Failed to composite frame: {}", e);
+                        continue;
+                    }
+                },
+            };
+
+            if frame_sender.send(frame).is_err() {
+                warn!("Failed to send composited frame, receiver dropped");
+                break;
+            }
+        }
+
+        info!("Scene compositing finished");
+        Ok(())
+    }
+
+    async fn composite(&mut self, frame: CapturedFrame) -> StreamResult<CapturedFrame> {
+        self.fps_frames += 1;
+        if self.fps_since.elapsed() >= Duration::from_secs(1) {
+            self.fps = self.fps_frames as f32 / self.fps_since.elapsed().as_secs_f32();
+            self.fps_frames = 0;
+            self.fps_since = Instant::now();
+        }
+
+        let scenes = self.scenes.clone();
+        let scene_index = self.switch.index();
+        let tone_mapper = self.tone_mapper.clone();
+        let (width, height, fps) = (self.width, self.height, self.fps);
+        let timestamp = frame.timestamp;
+
+        let data = tokio::task::spawn_blocking(move || {
+            let capture = to_rgba(&frame, &tone_mapper).map(Arc::new);
+            render(&scenes[scene_index], capture.as_deref(), width, height, fps)
+        })
+        .await
+        .map_err(|e| StreamError::Capture(format!("Compositing task failed: {}", e)))?;
+
+        Ok(CapturedFrame {
+            frame_type: FrameType::Video,
+            data: Bytes::from(data),
+            timestamp,
+            width: Some(width),
+            height: Some(height),
+            pixel_format: Some(VideoPixelFormat::Rgba32),
+        })
+    }
+}
+
+impl Drop for Compositor {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        for source in &self.sources {
+            source.abort();
+        }
+    }
+}
+
+/// 保存其他捕获源的最新一帧
+async fn store_latest(
+    mut frame_receiver: mpsc::UnboundedReceiver<CapturedFrame>,
+    latest: Arc<Mutex<Option<Arc<LayerFrame>>>>,
+    tone_mapper: Arc<ToneMapper>,
+) {
+    while let Some(frame) = frame_receiver.recv().await {
+        if let Some(frame) = to_rgba(&frame, &tone_mapper) {
+            *latest.lock().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(frame));
+        }
+    }
+}
+
+/// 把捕获画面转换为 RGBA，不支持的格式返回 None
+fn to_rgba(frame: &CapturedFrame, tone_mapper: &ToneMapper) -> Option<LayerFrame> {
+    let (width, height) = (frame.width?, frame.height?);
+    let format = frame.pixel_format.unwrap_or(VideoPixelFormat::Rgba32);
+
+    let data = match format {
+        VideoPixelFormat::Rgba32 => frame.data.clone(),
+        VideoPixelFormat::Bgra32 => {
+            let mut data = Vec::from(frame.data.clone());
+            for pixel in data.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+            Bytes::from(data)
+        }
+        format if format.is_hdr() => match tone_mapper.tone_map(&frame.data, width, height, format) {
+            Ok(data) => Bytes::from(data),
+            Err(e) => {
+                warn!("Failed to tone map layer frame: {}", e);
+                return None;
+            }
+        },
+        format => {
+            debug!("Skipping {:?} frame, layers need RGBA", format);
+            return None;
+        }
+    };
+
+    if data.len() < width as usize * height as usize * 4 {
+        debug!("Skipping truncated {}x{} layer frame", width, height);
+        return None;
+    }
+    Some(LayerFrame { width, height, data })
+}
+
+/// 在黑色画布上按顺序绘制场景的各图层
+fn render(scene: &Scene, capture: Option<&LayerFrame>, width: u32, height: u32, fps: f32) -> Vec<u8> {
+    let mut canvas = [0u8, 0, 0, 255].repeat(width as usize * height as usize);
+
+    for layer in scene.layers.iter().filter(|layer| layer.visible.load(Ordering::Relaxed)) {
+        let opacity = layer.config.opacity.clamp(0.0, 1.0);
+        if opacity <= 0.0 {
+            continue;
+        }
+
+        match &layer.input {
+            LayerInput::Color(color) => {
+                let size = (layer.config.width.unwrap_or(width), layer.config.height.unwrap_or(height));
+                fill(&mut canvas, width, height, &layer.config, size, *color, opacity);
+            }
+            LayerInput::Capture => {
+                if let Some(frame) = capture {
+                    draw(&mut canvas, width, height, &layer.config, frame, opacity);
+                }
+            }
+            LayerInput::Image(frame) => draw(&mut canvas, width, height, &layer.config, frame, opacity),
+            LayerInput::Text(text) => {
+                if let Some(frame) = text.lock().unwrap_or_else(|e| e.into_inner()).render(fps) {
+                    draw(&mut canvas, width, height, &layer.config, &frame, opacity);
+                }
+            }
+            LayerInput::Latest(latest) => {
+                let frame = latest.lock().unwrap_or_else(|e| e.into_inner()).clone();
+                if let Some(frame) = frame {
+                    draw(&mut canvas, width, height, &layer.config, &frame, opacity);
+                }
+            }
+        }
+    }
+
+    canvas
+}
+
+/// 裁剪、缩放 (最近邻) 后按透明度混合到画布上
+fn draw(canvas: &mut [u8], canvas_width: u32, canvas_height: u32, layer: &LayerConfig, frame: &LayerFrame, opacity: f32) {
+    let crop = &layer.crop;
+    let source_width = frame.width.saturating_sub(crop.left + crop.right);
+    let source_height = frame.height.saturating_sub(crop.top + crop.bottom);
+    if source_width == 0 || source_height == 0 {
+        return;
+    }
+
+    // 只设置宽或高时按比例计算另一边
+    let scale = layer.scale.max(0.0);
+    let (output_width, output_height) = match (layer.width, layer.height) {
+        (Some(width), Some(height)) => (width, height),
+        (Some(width), None) => (width, (source_height as u64 * width as u64 / source_width as u64) as u32),
+        (None, Some(height)) => ((source_width as u64 * height as u64 / source_height as u64) as u32, height),
+        (None, None) => ((source_width as f32 * scale) as u32, (source_height as f32 * scale) as u32),
+    };
+    if output_width == 0 || output_height == 0 {
+        return;
+    }
+
+    let Some((columns, rows)) = visible_area(canvas_width, canvas_height, layer.x, layer.y, output_width, output_height) else {
+        return;
+    };
+    let source_columns: Vec<usize> = columns.clone()
+        .map(|column| (crop.left + (column as u64 * source_width as u64 / output_width as u64) as u32) as usize)
+        .collect();
+
+    for row in rows {
+        let source_row = crop.top as usize + (row as u64 * source_height as u64 / output_height as u64) as usize;
+        let source_offset = source_row * frame.width as usize;
+        let canvas_offset = (layer.y + row as i32) as usize * canvas_width as usize;
+
+        for (column, source_column) in columns.clone().zip(&source_columns) {
+            let source = (source_offset + source_column) * 4;
+            let target = (canvas_offset + (layer.x + column as i32) as usize) * 4;
+            blend(&mut canvas[target..target + 4], &frame.data[source..source + 4], opacity);
+        }
+    }
+}
+
+/// 纯色图层
+fn fill(canvas: &mut [u8], canvas_width: u32, canvas_height: u32, layer: &LayerConfig, size: (u32, u32), color: [u8; 4], opacity: f32) {
+    let Some((columns, rows)) = visible_area(canvas_width, canvas_height, layer.x, layer.y, size.0, size.1) else {
+        return;
+    };
+
+    for row in rows {
+        let canvas_offset = (layer.y + row as i32) as usize * canvas_width as usize;
+        for column in columns.clone() {
+            let target = (canvas_offset + (layer.x + column as i32) as usize) * 4;
+            blend(&mut canvas[target..target + 4], &color, opacity);
+        }
+    }
+}
+
+/// 图层在画布内可见部分的列和行 (相对图层左上角)
+fn visible_area(
+    canvas_width: u32,
+    canvas_height: u32,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+) -> Option<(std::ops::Range<u32>, std::ops::Range<u32>)> {
+    let clip = |position: i32, size: u32, limit: u32| {
+        let start = (-position).max(0) as i64;
+        let end = (limit as i64 - position as i64).min(size as i64);
+        (start < end).then_some(start as u32..end as u32)
+    };
+    Some((clip(x, width, canvas_width)?, clip(y, height, canvas_height)?))
+}
+
+/// 源像素 (非预乘 RGBA) 叠加到不透明的画布像素上
+fn blend(target: &mut [u8], source: &[u8], opacity: f32) {
+    let alpha = (source[3] as f32 / 255.0 * opacity * 255.0).round() as u32;
+    if alpha == 0 {
+        return;
+    }
+    for channel in 0..3 {
+        target[channel] = ((source[channel] as u32 * alpha + target[channel] as u32 * (255 - alpha) + 127) / 255) as u8;
+    }
+    target[3] = 255;
+}
+
+/// 通过 ffmpeg 读取摄像头画面，直到合成器停止
+fn read_webcam(
+    device: &str,
+    size: (u32, u32),
+    fps: u32,
+    latest: &Mutex<Option<Arc<LayerFrame>>>,
+    running: &AtomicBool,
+) {
+    let (width, height) = size;
+    let frame_size = width as usize * height as usize * 4;
+
+    while running.load(Ordering::Relaxed) {
+        let mut child = match spawn_webcam(device, size, fps) {
+            Ok(child) => child,
+            Err(e) => {
+                error!("Failed to start webcam capture for {}: {}", device, e);
+                return;
+            }
+        };
+        info!("Capturing webcam {} at {}x{}", device, width, height);
+
+        let Some(mut stdout) = child.stdout.take() else {
+            let _ = child.kill();
+            return;
+        };
+        let mut buffer = vec![0u8; frame_size];
+        while running.load(Ordering::Relaxed) {
+            if let Err(e) = stdout.read_exact(&mut buffer) {
+                warn!("Webcam {} stopped: {}", device, e);
+                break;
+            }
+            *latest.lock().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(LayerFrame {
+                width,
+                height,
+                data: Bytes::copy_from_slice(&buffer),
+            }));
+        }
+
+        let _ = child.kill();
+        let _ = child.wait();
+        *latest.lock().unwrap_or_else(|e| e.into_inner()) = None;
+
+        // 摄像头被拔出或占用时稍后重试
+        if running.load(Ordering::Relaxed) {
+            std::thread::sleep(std::time::Duration::from_secs(2));
+        }
+    }
+}
+
+fn spawn_webcam(device: &str, (width, height): (u32, u32), fps: u32) -> std::io::Result<Child> {
+    #[cfg(target_os = "linux")]
+    let input = ["-f", "v4l2", "-i", device].map(String::from);
+    #[cfg(target_os = "windows")]
+    let input = ["-f", "dshow", "-i", &format!("video={}", device)].map(String::from);
+    #[cfg(target_os = "macos")]
+    let input = ["-f", "avfoundation", "-i", device].map(String::from);
+
+    Command::new("ffmpeg")
+        .args(["-loglevel", "error", "-nostdin"])
+        .args(["-framerate", &fps.to_string(), "-video_size", &format!("{}x{}", width, height)])
+        .args(input)
+        .args(["-vf", &format!("scale={}:{}", width, height), "-pix_fmt", "rgba", "-f", "rawvideo", "-"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+}
@@ -0,0 +1,200 @@
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tracing::{info, warn};
+
+use game_stream_common::{StreamResult, StreamError};
+use crate::audio_mixer::MixerControl;
+use crate::capture::DisplaySwitch;
+use crate::client::ClientControls;
+use crate::source_control;
+use crate::remote_input::{self, InputGrant, RemoteInputControl};
+
+const HELP: &str = "\
+Commands:
+  <index> | next         switch the captured display
+  scene <name>           switch to a scene
+  mute <input>           mute an audio mixer input
+  unmute <input>         unmute an audio mixer input
+  gain <input> <dB>      set the gain of an audio mixer input
+  mix                    show audio mixer inputs and levels
+  enable <source>        show a video source or unmute an audio input
+  disable <source>       hide a video source or mute an audio input
+  sources                show video sources
+  start | stop           start or stop streaming
+  pause | resume         show the pause slate and silence audio, or continue
+  replay                 save the replay buffer as an MP4
+  overlay [on|off]       toggle the stats overlay drawn into the video
+  input [on|off]         show remote input grants, or turn remote input on or off
+  input grant <viewer> [keyboard,mouse,gamepad]
+                         let a viewer control the game, all input types by default
+  input revoke <viewer>  stop injecting a viewer's input
+  help                   show this help";
+
+/// 从终端读取命令，推流过程中切换显示器、场景、调整混音、开关视频源、开始/停止、暂停推流、保存回放、切换统计叠加或授权远程输入
+pub async fn read_commands(controls: ClientControls) {
+    info!("Type \"help\" and press Enter to list console commands");
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let command = line.trim();
+        if command.is_empty() {
+            continue;
+        }
+
+        match execute(&controls, command) {
+            Ok(Some(output)) => println!("{}", output),
+            Ok(None) => {}
+            Err(e) => warn!("{}", e),
+        }
+    }
+}
+
+/// 执行一条命令，返回需要显示的内容
+pub fn execute(controls: &ClientControls, command: &str) -> StreamResult<Option<String>> {
+    let mut words = command.split_whitespace();
+    let Some(name) = words.next() else {
+        return Ok(None);
+    };
+    let argument = command[name.len()..].trim();
+
+    match name {
+        "help" => Ok(Some(HELP.to_string())),
+        "scene" => {
+            let scene = controls.scene.as_ref()
+                .ok_or_else(|| StreamError::Config("No scenes are configured".to_string()))?;
+            if argument.is_empty() {
+                return Ok(Some(format!("Scene: {} ({})", scene.current(), scene.names().join(", "))));
+            }
+            scene.switch_to(argument)?;
+            Ok(None)
+        }
+        "mute" | "unmute" => {
+            mixer(controls)?.set_muted(argument, name == "mute")?;
+            Ok(None)
+        }
+        "gain" => {
+            // 输入名称可能包含空格，音量为最后一个参数
+            let (input, gain) = argument.rsplit_once(char::is_whitespace)
+                .ok_or_else(|| StreamError::Config("Usage: gain <input> <dB>".to_string()))?;
+            let gain: f32 = gain.trim_end_matches("dB").parse()
+                .map_err(|_| StreamError::Config(format!("Invalid gain {:?}", gain)))?;
+            mixer(controls)?.set_gain_db(input.trim(), gain)?;
+            Ok(None)
+        }
+        "mix" => {
+            let mixer = mixer(controls)?;
+            let status = mixer.status().into_iter().zip(mixer.levels())
+                .map(|((input, gain, muted), level)| format!(
+                    "  {:<16} {:>+6.1} dB  peak {:>4.0} dBFS{}", input, gain, level.peak_db, if muted { "  (muted)" } else { "" },
+                ))
+                .collect::<Vec<_>>()
+                .join("\n");
+            Ok(Some(format!("Audio inputs:\n{}", status)))
+        }
+        "enable" | "disable" => {
+            source_control::set_enabled(controls, argument, Some(name == "enable"))?;
+            Ok(None)
+        }
+        "sources" => {
+            let status = controls.sources.status().into_iter()
+                .map(|(source, enabled)| format!("  {:<16} {}", source, if enabled { "on" } else { "off" }))
+                .collect::<Vec<_>>()
+                .join("\n");
+            Ok(Some(format!("Video sources:\n{}", status)))
+        }
+        "start" | "stop" => {
+            controls.stream.set_streaming(name == "start");
+            Ok(None)
+        }
+        "pause" | "resume" => {
+            controls.stream.set_paused(name == "pause");
+            Ok(None)
+        }
+        "replay" => {
+            controls.replay.as_ref()
+                .ok_or_else(|| StreamError::Config("Replay buffer is disabled, enable it in [replay]".to_string()))?
+                .save();
+            Ok(None)
+        }
+        "overlay" => {
+            match argument {
+                "" => { controls.stats_overlay.toggle(); }
+                "on" | "off" => controls.stats_overlay.set_visible(argument == "on"),
+                _ => return Err(StreamError::Config("Usage: overlay [on|off]".to_string())),
+            }
+            Ok(None)
+        }
+        "input" => {
+            let remote_input = remote_input(controls)?;
+            let (action, rest) = argument.split_once(char::is_whitespace).unwrap_or((argument, ""));
+            let (viewer, kinds) = rest.trim().split_once(char::is_whitespace).unwrap_or((rest.trim(), ""));
+            let viewer = || viewer.parse::<uuid::Uuid>()
+                .map_err(|_| StreamError::Config(format!("Invalid viewer ID {:?}", viewer)));
+            match action {
+                "" => {
+                    let grants = remote_input.grants().into_iter()
+                        .map(|(viewer, grant)| format!("  {}  {}", viewer, remote_input::describe(&grant)))
+                        .collect::<Vec<_>>();
+                    let state = if remote_input.is_enabled() { "on" } else { "off" };
+                    if grants.is_empty() {
+                        return Ok(Some(format!("Remote input is {}, no viewers are granted", state)));
+                    }
+                    let mut text = format!("Remote input is {}, granted viewers:\n{}", state, grants.join("\n"));
+                    let gamepads = remote_input.gamepad_reports();
+                    if !gamepads.is_empty() {
+                        text.push_str("\nGamepads:");
+                        for report in gamepads {
+                            text.push_str(&format!(
+                                "\n  {} #{}  latency {:.0} ms, jitter {:.1} ms, buffer {:.0} ms, received {}, lost {}, late {}",
+                                report.viewer_id, report.pad, report.latency_ms, report.jitter_ms, report.buffer_ms,
+                                report.received, report.lost, report.late,
+                            ));
+                        }
+                    }
+                    Ok(Some(text))
+                }
+                "on" | "off" => {
+                    remote_input.set_enabled(action == "on");
+                    Ok(None)
+                }
+                "grant" => {
+                    remote_input.grant(viewer()?, InputGrant::parse(kinds)?);
+                    Ok(None)
+                }
+                "revoke" => {
+                    let viewer = viewer()?;
+                    if !remote_input.revoke(viewer) {
+                        return Err(StreamError::Config(format!("Viewer {} has no remote input grant", viewer)));
+                    }
+                    Ok(None)
+                }
+                _ => Err(StreamError::Config("Usage: input [on|off|grant <viewer> [types]|revoke <viewer>]".to_string())),
+            }
+        }
+        "n" | "next" => {
+            display(controls)?.next()?;
+            Ok(None)
+        }
+        _ => match command.parse::<u32>() {
+            Ok(index) => {
+                display(controls)?.switch_to(index)?;
+                Ok(None)
+            }
+            Err(_) => Err(StreamError::Config(format!("Unknown command {:?}, type \"help\" for commands", command))),
+        },
+    }
+}
+
+fn display(controls: &ClientControls) -> StreamResult<&DisplaySwitch> {
+    controls.display.as_ref()
+        .ok_or_else(|| StreamError::Config("Display switching is only available for screen capture".to_string()))
+}
+
+fn mixer(controls: &ClientControls) -> StreamResult<&MixerControl> {
+    controls.mixer.as_ref()
+        .ok_or_else(|| StreamError::Config("Audio capture is disabled".to_string()))
+}
+
+fn remote_input(controls: &ClientControls) -> StreamResult<&RemoteInputControl> {
+    controls.remote_input.as_ref()
+        .ok_or_else(|| StreamError::Config("Remote input is disabled, enable it in [remote_input]".to_string()))
+}
@@ -0,0 +1,413 @@
+use axum::{
+    extract::{Path, State, WebSocketUpgrade},
+    extract::ws::{Message, WebSocket},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+use tracing::{info, warn, debug};
+
+use game_stream_common::{ClientStats, ControlConfig, GamepadReport, StreamError};
+use crate::client::ClientControls;
+use crate::console;
+use crate::source_control;
+use crate::remote_input::{InputGrant, RemoteInputControl};
+
+/// 码率的调整范围 (kbps)
+const MIN_BITRATE: u32 = 100;
+const MAX_BITRATE: u32 = 100_000;
+
+/// 开启时在后台启动本地控制接口
+///
+/// HTTP 接口：
+/// - GET  /api/status                 推流状态、场景、显示器、码率、混音和各输入的电平
+/// - GET  /api/stats                  最近一次汇总的统计，尚未汇总时返回 503
+/// - POST /api/stream/<action>        start、stop、pause 或 resume
+/// - POST /api/scene                  {"name": "..."}
+/// - POST /api/display                {"index": 1}，不带 index 时切换到下一个显示器
+/// - POST /api/bitrate                {"kbps": 4000}
+/// - POST /api/audio/<input>          {"muted": true, "gain_db": -6.0}，两项都可省略
+/// - POST /api/sources/<name>         {"enabled": false}，视频源或混音输入，不带 enabled 时切换
+/// - POST /api/input                  {"enabled": false}，开启或关闭远程输入
+/// - POST /api/input/<viewer>         {"keyboard": true, "mouse": true, "gamepad": false}，授权观看者，省略的项为 true
+/// - DELETE /api/input/<viewer>       撤销观看者的授权
+/// - POST /api/command                终端命令，如 "scene 游戏"
+/// - GET  /api/snapshot               从捕获源截取一帧，返回 PNG
+///
+/// WebSocket 接口 /api/ws：每次汇总统计时推送 {"status": ..., "stats": ...}，
+/// 收到的文本消息按终端命令执行，回复 {"ok": true, "output": ...} 或 {"ok": false, "error": ...}
+pub async fn spawn(config: &ControlConfig, controls: ClientControls) {
+    if !config.enabled {
+        return;
+    }
+    let listener = match TcpListener::bind(&config.listen).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("Cannot listen for control requests on {}: {}", config.listen, e);
+            return;
+        }
+    };
+    if listener.local_addr().is_ok_and(|address| !address.ip().is_loopback()) {
+        warn!("Control API on {} is reachable from other hosts and has no authentication", config.listen);
+    }
+    info!("Serving the control API on http://{}", config.listen);
+
+    let router = Router::new()
+        .route("/api/status", get(get_status))
+        .route("/api/stats", get(get_stats))
+        .route("/api/stream/:action", post(stream_action))
+        .route("/api/scene", post(switch_scene))
+        .route("/api/display", post(switch_display))
+        .route("/api/bitrate", post(set_bitrate))
+        .route("/api/audio/:input", post(update_audio_input))
+        .route("/api/sources/:name", post(set_source_enabled))
+        .route("/api/input", post(set_remote_input_enabled))
+        .route("/api/input/:viewer", post(grant_remote_input).delete(revoke_remote_input))
+        .route("/api/command", post(run_command))
+        .route("/api/snapshot", get(get_snapshot))
+        .route("/api/ws", get(websocket))
+        .with_state(controls);
+
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, router).await {
+            warn!("Control API stopped: {}", e);
+        }
+    });
+}
+
+/// 当前的推流状态
+#[derive(Debug, Clone, Serialize)]
+struct ControlStatus {
+    streaming: bool,
+    paused: bool,
+    scene: Option<String>,
+    scenes: Vec<String>,
+    display: Option<u32>,
+    bitrate: u32, // kbps，开启自适应码率时为当前的目标
+    stats_overlay: bool,
+    replay_enabled: bool,
+    audio_inputs: Vec<AudioInputStatus>,
+    sources: Vec<SourceStatus>,
+    remote_input: Option<RemoteInputStatus>, // 未开启 [remote_input] 时为 None
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct AudioInputStatus {
+    name: String,
+    gain_db: f32,
+    muted: bool,
+    rms_db: f32,  // 音量和静音之后的电平 (dBFS)
+    peak_db: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct SourceStatus {
+    name: String,
+    enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct RemoteInputStatus {
+    enabled: bool,
+    grants: Vec<RemoteInputGrant>,
+    gamepads: Vec<GamepadReport>, // 正在操作的观看者手柄的延迟报告
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct RemoteInputGrant {
+    viewer_id: uuid::Uuid,
+    #[serde(flatten)]
+    grant: InputGrant,
+}
+
+fn status(controls: &ClientControls) -> ControlStatus {
+    ControlStatus {
+        streaming: controls.stream.is_streaming(),
+        paused: controls.stream.is_paused(),
+        scene: controls.scene.as_ref().map(|scene| scene.current().to_string()),
+        scenes: controls.scene.as_ref().map(|scene| scene.names().to_vec()).unwrap_or_default(),
+        display: controls.display.as_ref().map(|display| display.current()),
+        bitrate: controls.bitrate.target(),
+        stats_overlay: controls.stats_overlay.is_visible(),
+        replay_enabled: controls.replay.is_some(),
+        audio_inputs: controls.mixer.as_ref()
+            .map(|mixer| mixer.status().into_iter().zip(mixer.levels())
+                .map(|((name, gain_db, muted), level)| AudioInputStatus {
+                    name, gain_db, muted, rms_db: level.rms_db, peak_db: level.peak_db,
+                })
+                .collect())
+            .unwrap_or_default(),
+        sources: controls.sources.status().into_iter()
+            .map(|(name, enabled)| SourceStatus { name, enabled })
+            .collect(),
+        remote_input: controls.remote_input.as_ref().map(|remote_input| RemoteInputStatus {
+            enabled: remote_input.is_enabled(),
+            grants: remote_input.grants().into_iter()
+                .map(|(viewer_id, grant)| RemoteInputGrant { viewer_id, grant })
+                .collect(),
+            gamepads: remote_input.gamepad_reports(),
+        }),
+    }
+}
+
+async fn get_status(State(controls): State<ClientControls>) -> Json<ControlStatus> {
+    Json(status(&controls))
+}
+
+async fn get_stats(State(controls): State<ClientControls>) -> Result<Json<ClientStats>, ControlError> {
+    let stats = controls.stats.subscribe().borrow().clone();
+    stats.map(Json).ok_or(ControlError::Unavailable("stats not available yet"))
+}
+
+async fn stream_action(
+    Path(action): Path<String>,
+    State(controls): State<ClientControls>,
+) -> Result<Json<ControlStatus>, ControlError> {
+    match action.as_str() {
+        "start" | "stop" => controls.stream.set_streaming(action == "start"),
+        "pause" | "resume" => controls.stream.set_paused(action == "pause"),
+        _ => return Err(StreamError::Config(format!("Unknown stream action {:?}", action)).into()),
+    }
+    Ok(Json(status(&controls)))
+}
+
+#[derive(Debug, Deserialize)]
+struct SceneRequest {
+    name: String,
+}
+
+async fn switch_scene(
+    State(controls): State<ClientControls>,
+    Json(request): Json<SceneRequest>,
+) -> Result<Json<ControlStatus>, ControlError> {
+    controls.scene.as_ref()
+        .ok_or_else(|| StreamError::Config("No scenes are configured".to_string()))?
+        .switch_to(&request.name)?;
+    Ok(Json(status(&controls)))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct DisplayRequest {
+    #[serde(default)]
+    index: Option<u32>,
+}
+
+async fn switch_display(
+    State(controls): State<ClientControls>,
+    request: Option<Json<DisplayRequest>>,
+) -> Result<Json<ControlStatus>, ControlError> {
+    let display = controls.display.as_ref()
+        .ok_or_else(|| StreamError::Config("Display switching is only available for screen capture".to_string()))?;
+    match request.and_then(|Json(request)| request.index) {
+        Some(index) => display.switch_to(index)?,
+        None => {
+            display.next()?;
+        }
+    }
+    Ok(Json(status(&controls)))
+}
+
+#[derive(Debug, Deserialize)]
+struct BitrateRequest {
+    kbps: u32,
+}
+
+async fn set_bitrate(
+    State(controls): State<ClientControls>,
+    Json(request): Json<BitrateRequest>,
+) -> Result<Json<ControlStatus>, ControlError> {
+    if !(MIN_BITRATE..=MAX_BITRATE).contains(&request.kbps) {
+        return Err(StreamError::Config(format!(
+            "Bitrate must be between {} and {} kbps", MIN_BITRATE, MAX_BITRATE
+        )).into());
+    }
+    let previous = controls.bitrate.target();
+    controls.bitrate.set_target(request.kbps);
+    info!("Video bitrate changed from {} to {} kbps by the control API", previous, request.kbps);
+    Ok(Json(status(&controls)))
+}
+
+#[derive(Debug, Deserialize)]
+struct AudioInputRequest {
+    #[serde(default)]
+    muted: Option<bool>,
+    #[serde(default)]
+    gain_db: Option<f32>,
+}
+
+async fn update_audio_input(
+    Path(input): Path<String>,
+    State(controls): State<ClientControls>,
+    Json(request): Json<AudioInputRequest>,
+) -> Result<Json<ControlStatus>, ControlError> {
+    let mixer = controls.mixer.as_ref()
+        .ok_or_else(|| StreamError::Config("Audio capture is disabled".to_string()))?;
+    if let Some(muted) = request.muted {
+        mixer.set_muted(&input, muted)?;
+    }
+    if let Some(gain_db) = request.gain_db {
+        mixer.set_gain_db(&input, gain_db)?;
+    }
+    Ok(Json(status(&controls)))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SourceRequest {
+    #[serde(default)]
+    enabled: Option<bool>,
+}
+
+async fn set_source_enabled(
+    Path(name): Path<String>,
+    State(controls): State<ClientControls>,
+    request: Option<Json<SourceRequest>>,
+) -> Result<Json<ControlStatus>, ControlError> {
+    source_control::set_enabled(&controls, &name, request.and_then(|Json(request)| request.enabled))?;
+    Ok(Json(status(&controls)))
+}
+
+fn remote_input(controls: &ClientControls) -> Result<&RemoteInputControl, StreamError> {
+    controls.remote_input.as_ref()
+        .ok_or_else(|| StreamError::Config("Remote input is disabled, enable it in [remote_input]".to_string()))
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoteInputRequest {
+    enabled: bool,
+}
+
+async fn set_remote_input_enabled(
+    State(controls): State<ClientControls>,
+    Json(request): Json<RemoteInputRequest>,
+) -> Result<Json<ControlStatus>, ControlError> {
+    remote_input(&controls)?.set_enabled(request.enabled);
+    Ok(Json(status(&controls)))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct GrantRequest {
+    #[serde(default)]
+    keyboard: Option<bool>,
+    #[serde(default)]
+    mouse: Option<bool>,
+    #[serde(default)]
+    gamepad: Option<bool>,
+}
+
+async fn grant_remote_input(
+    Path(viewer): Path<uuid::Uuid>,
+    State(controls): State<ClientControls>,
+    request: Option<Json<GrantRequest>>,
+) -> Result<Json<ControlStatus>, ControlError> {
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    remote_input(&controls)?.grant(viewer, InputGrant {
+        keyboard: request.keyboard.unwrap_or(true),
+        mouse: request.mouse.unwrap_or(true),
+        gamepad: request.gamepad.unwrap_or(true),
+    });
+    Ok(Json(status(&controls)))
+}
+
+async fn revoke_remote_input(
+    Path(viewer): Path<uuid::Uuid>,
+    State(controls): State<ClientControls>,
+) -> Result<Json<ControlStatus>, ControlError> {
+    if !remote_input(&controls)?.revoke(viewer) {
+        return Err(StreamError::Config(format!("Viewer {} has no remote input grant", viewer)).into());
+    }
+    Ok(Json(status(&controls)))
+}
+
+/// 命令的执行结果
+#[derive(Debug, Serialize)]
+struct CommandResult {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+fn execute(controls: &ClientControls, command: &str) -> CommandResult {
+    match console::execute(controls, command.trim()) {
+        Ok(output) => CommandResult { ok: true, output, error: None },
+        Err(e) => CommandResult { ok: false, output: None, error: Some(e.to_string()) },
+    }
+}
+
+async fn run_command(State(controls): State<ClientControls>, command: String) -> Response {
+    let result = execute(&controls, &command);
+    let status = if result.ok { StatusCode::OK } else { StatusCode::BAD_REQUEST };
+    (status, Json(result)).into_response()
+}
+
+async fn get_snapshot(State(controls): State<ClientControls>) -> Result<Response, ControlError> {
+    let png = controls.snapshot.capture_png().await?;
+    Ok(([(header::CONTENT_TYPE, "image/png")], png).into_response())
+}
+
+/// WebSocket 推送的内容
+#[derive(Debug, Serialize)]
+struct ControlUpdate {
+    status: ControlStatus,
+    stats: Option<ClientStats>,
+}
+
+async fn websocket(ws: WebSocketUpgrade, State(controls): State<ClientControls>) -> Response {
+    ws.on_upgrade(|socket| handle_websocket(socket, controls))
+}
+
+async fn handle_websocket(mut socket: WebSocket, controls: ClientControls) {
+    debug!("New control WebSocket connection");
+
+    let mut stats = controls.stats.subscribe();
+    stats.mark_changed();
+    loop {
+        let reply = tokio::select! {
+            changed = stats.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                let update = ControlUpdate { status: status(&controls), stats: stats.borrow_and_update().clone() };
+                serde_json::to_string(&update)
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(command))) => serde_json::to_string(&execute(&controls, &command)),
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+        };
+
+        let Ok(reply) = reply else { continue };
+        if socket.send(Message::Text(reply)).await.is_err() {
+            break;
+        }
+    }
+
+    debug!("Control WebSocket connection closed");
+}
+
+/// 控制接口的错误
+enum ControlError {
+    Rejected(StreamError),
+    Unavailable(&'static str),
+}
+
+impl From<StreamError> for ControlError {
+    fn from(error: StreamError) -> Self {
+        Self::Rejected(error)
+    }
+}
+
+impl IntoResponse for ControlError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ControlError::Rejected(e) => (StatusCode::BAD_REQUEST, e.to_string()),
+            ControlError::Unavailable(message) => (StatusCode::SERVICE_UNAVAILABLE, message.to_string()),
+        };
+        (status, Json(serde_json::json!({ "error": message }))).into_response()
+    }
+}
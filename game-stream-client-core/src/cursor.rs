@@ -0,0 +1,195 @@
+use tracing::{info, debug, warn};
+
+use game_stream_common::CursorConfig;
+
+/// 高亮圆环的线宽
+const HIGHLIGHT_RING_WIDTH: f32 = 4.0;
+
+/// 光标形状 (预乘 alpha 的 RGBA)
+struct CursorShape {
+    serial: u32,
+    width: u32,
+    height: u32,
+    hotspot: (i32, i32),
+    pixels: Vec<u8>,
+}
+
+/// 光标合成器 - 把鼠标光标绘制到本身不包含光标的捕获画面上
+pub struct CursorCompositor {
+    config: CursorConfig,
+    source: Option<CursorSource>,
+    shape: Option<CursorShape>,
+}
+
+impl CursorCompositor {
+    pub fn new(config: &CursorConfig) -> Self {
+        let source = match CursorSource::connect() {
+            Ok(source) => {
+                info!("Compositing mouse cursor into captured frames");
+                Some(source)
+            }
+            Err(e) => {
+                warn!("Mouse cursor will not be drawn: {}", e);
+                None
+            }
+        };
+
+        Self {
+            config: config.clone(),
+            source,
+            shape: None,
+        }
+    }
+
+    /// 在 RGBA 画面上绘制光标，origin 为画面左上角在屏幕上的坐标
+    pub fn composite(&mut self, data: &mut [u8], width: u32, height: u32, origin: (i32, i32)) {
+        let Some(source) = &self.source else {
+            return;
+        };
+
+        // 每帧查询位置，形状序号变化时才更新光标图像
+        let position = match source.query(&mut self.shape) {
+            Ok(position) => position,
+            Err(e) => {
+                debug!("Failed to query mouse cursor: {}", e);
+                return;
+            }
+        };
+
+        let (x, y) = (position.0 - origin.0, position.1 - origin.1);
+        let mut frame = Frame { data, width: width as i32, height: height as i32 };
+
+        if self.config.highlight {
+            draw_ring(&mut frame, (x, y), self.config.highlight_radius as f32, self.config.highlight_color);
+        }
+        if let Some(shape) = &self.shape {
+            draw_shape(&mut frame, shape, (x - shape.hotspot.0, y - shape.hotspot.1));
+        }
+    }
+}
+
+struct Frame<'a> {
+    data: &'a mut [u8],
+    width: i32,
+    height: i32,
+}
+
+impl Frame<'_> {
+    /// 用预乘 alpha 的颜色叠加一个像素
+    fn blend(&mut self, x: i32, y: i32, src: [u8; 4]) {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return;
+        }
+        let offset = ((y * self.width + x) * 4) as usize;
+        let Some(dst) = self.data.get_mut(offset..offset + 4) else {
+            return;
+        };
+
+        let inverse = 255 - src[3] as u32;
+        for channel in 0..3 {
+            dst[channel] = (src[channel] as u32 + dst[channel] as u32 * inverse / 255).min(255) as u8;
+        }
+        dst[3] = 255;
+    }
+}
+
+fn draw_shape(frame: &mut Frame, shape: &CursorShape, top_left: (i32, i32)) {
+    for row in 0..shape.height as i32 {
+        for column in 0..shape.width as i32 {
+            let offset = ((row * shape.width as i32 + column) * 4) as usize;
+            let pixel = &shape.pixels[offset..offset + 4];
+            if pixel[3] > 0 {
+                frame.blend(top_left.0 + column, top_left.1 + row, [pixel[0], pixel[1], pixel[2], pixel[3]]);
+            }
+        }
+    }
+}
+
+/// 以光标热点为圆心绘制抗锯齿的圆环
+fn draw_ring(frame: &mut Frame, center: (i32, i32), radius: f32, color: [u8; 4]) {
+    let extent = (radius + HIGHLIGHT_RING_WIDTH).ceil() as i32;
+
+    for dy in -extent..=extent {
+        for dx in -extent..=extent {
+            let distance = ((dx * dx + dy * dy) as f32).sqrt();
+            let coverage = (HIGHLIGHT_RING_WIDTH / 2.0 + 0.5 - (distance - radius).abs()).clamp(0.0, 1.0);
+            if coverage <= 0.0 {
+                continue;
+            }
+
+            let alpha = color[3] as f32 / 255.0 * coverage;
+            let premultiplied = [
+                (color[0] as f32 * alpha) as u8,
+                (color[1] as f32 * alpha) as u8,
+                (color[2] as f32 * alpha) as u8,
+                (alpha * 255.0) as u8,
+            ];
+            frame.blend(center.0 + dx, center.1 + dy, premultiplied);
+        }
+    }
+}
+
+/// X11 上通过 XFixes 扩展获取光标位置和图像 (X11 截图不包含光标)
+#[cfg(target_os = "linux")]
+struct CursorSource {
+    connection: xcb::Connection,
+}
+
+#[cfg(target_os = "linux")]
+impl CursorSource {
+    fn connect() -> Result<Self, String> {
+        use xcb::xfixes;
+
+        let (connection, _) = xcb::Connection::connect_with_extensions(None, &[xcb::Extension::XFixes], &[])
+            .map_err(|e| format!("cannot connect to X server: {}", e))?;
+
+        // 使用 XFixes 请求前必须先协商版本
+        let cookie = connection.send_request(&xfixes::QueryVersion {
+            client_major_version: 4,
+            client_minor_version: 0,
+        });
+        connection.wait_for_reply(cookie).map_err(|e| format!("XFixes is not available: {}", e))?;
+
+        Ok(Self { connection })
+    }
+
+    /// 返回光标热点在屏幕上的坐标
+    fn query(&self, shape: &mut Option<CursorShape>) -> Result<(i32, i32), String> {
+        let cookie = self.connection.send_request(&xcb::xfixes::GetCursorImage {});
+        let reply = self.connection.wait_for_reply(cookie).map_err(|e| e.to_string())?;
+
+        if shape.as_ref().is_none_or(|shape| shape.serial != reply.cursor_serial()) {
+            // XFixes 返回预乘 alpha 的 ARGB
+            let pixels = reply.cursor_image().iter()
+                .flat_map(|argb| {
+                    let [b, g, r, a] = argb.to_le_bytes();
+                    [r, g, b, a]
+                })
+                .collect();
+
+            *shape = Some(CursorShape {
+                serial: reply.cursor_serial(),
+                width: reply.width() as u32,
+                height: reply.height() as u32,
+                hotspot: (reply.xhot() as i32, reply.yhot() as i32),
+                pixels,
+            });
+        }
+
+        Ok((reply.x() as i32, reply.y() as i32))
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+struct CursorSource;
+
+#[cfg(not(target_os = "linux"))]
+impl CursorSource {
+    fn connect() -> Result<Self, String> {
+        Err("cursor compositing is only supported on X11".to_string())
+    }
+
+    fn query(&self, _shape: &mut Option<CursorShape>) -> Result<(i32, i32), String> {
+        Err("cursor compositing is only supported on X11".to_string())
+    }
+}
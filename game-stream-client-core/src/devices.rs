@@ -0,0 +1,76 @@
+use anyhow::Result;
+#[cfg(any(feature = "screen-capture", feature = "audio-capture"))]
+use anyhow::Context;
+#[cfg(not(all(feature = "screen-capture", feature = "audio-capture")))]
+use anyhow::bail;
+#[cfg(feature = "audio-capture")]
+use cpal::traits::{DeviceTrait, HostTrait};
+
+#[cfg(feature = "screen-capture")]
+use crate::window_capture::window_pid;
+
+/// 列出显示器，序号对应 `Screen = { display_index = N }`
+#[cfg(feature = "screen-capture")]
+pub fn list_displays() -> Result<()> {
+    let monitors = xcap::Monitor::all().context("Failed to enumerate displays")?;
+
+    println!("{:<6} {:<12} {:>8} {:>8}  NAME", "INDEX", "RESOLUTION", "REFRESH", "SCALE");
+    for (index, monitor) in monitors.iter().enumerate() {
+        let primary = if monitor.is_primary() { " (primary)" } else { "" };
+        println!("{:<6} {:<12} {:>6.0}Hz {:>7.2}x  {}{}",
+                 index, format!("{}x{}", monitor.width(), monitor.height()),
+                 monitor.frequency(), monitor.scale_factor(), monitor.name(), primary);
+    }
+
+    Ok(())
+}
+
+/// 列出可捕获的窗口，标题和进程可用于 `Window` 的匹配条件
+#[cfg(feature = "screen-capture")]
+pub fn list_windows() -> Result<()> {
+    let windows = xcap::Window::all().context("Failed to enumerate windows")?;
+
+    println!("{:<8} {:<24} {:<12}  TITLE", "PID", "PROCESS", "SIZE");
+    for window in windows.iter().filter(|window| !window.title().is_empty()) {
+        let pid = window_pid(window).map_or_else(|| "-".to_string(), |pid| pid.to_string());
+        let minimized = if window.is_minimized() { " (minimized)" } else { "" };
+        println!("{:<8} {:<24} {:<12}  {}{}",
+                 pid, window.app_name(), format!("{}x{}", window.width(), window.height()),
+                 window.title(), minimized);
+    }
+
+    Ok(())
+}
+
+/// 列出音频输入设备，名称对应 `Device = { device_name = "..." }`
+#[cfg(feature = "audio-capture")]
+pub fn list_audio_devices() -> Result<()> {
+    let host = cpal::default_host();
+    let default_name = host.default_input_device().and_then(|device| device.name().ok());
+
+    println!("{:<8}  NAME", "DEFAULT");
+    for device in host.input_devices().context("Failed to enumerate audio devices")? {
+        let Ok(name) = device.name() else {
+            continue;
+        };
+        let default = if Some(&name) == default_name.as_ref() { "*" } else { "" };
+        println!("{:<8}  {}", default, name);
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "screen-capture"))]
+pub fn list_displays() -> Result<()> {
+    bail!("Listing displays is not available, the client was built without the screen-capture feature")
+}
+
+#[cfg(not(feature = "screen-capture"))]
+pub fn list_windows() -> Result<()> {
+    bail!("Listing windows is not available, the client was built without the screen-capture feature")
+}
+
+#[cfg(not(feature = "audio-capture"))]
+pub fn list_audio_devices() -> Result<()> {
+    bail!("Listing audio devices is not available, the client was built without the audio-capture feature")
+}
@@ -0,0 +1,442 @@
+use anyhow::Result;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use bytes::Bytes;
+use tokio::sync::mpsc;
+use tracing::{info, warn, error, debug};
+
+use game_stream_common::{
+    EncodingConfig, MediaPacket, StreamResult, StreamError,
+    VideoFrame, AudioFrame, VideoPixelFormat, AudioSampleFormat,
+    EncoderFactory, VideoEncoderConfig, AudioEncoderConfig, BUILTIN_ENCODER,
+    VideoEncoder, AudioEncoder, VideoCodec, AudioCodec, HdrMode, StaticFrameMode, GpuSurface
+};
+use crate::capture::{CapturedFrame, FrameType};
+use crate::tonemap::ToneMapper;
+use crate::color_convert::ColorConverter;
+use crate::frame_queue::FrameQueue;
+use crate::bitrate::BitrateControl;
+use crate::stats::StatsRecorder;
+
+/// 编码跟不上时汇报丢帧数的间隔
+const DROP_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 请求从某个时间戳的视频帧开始编码关键帧，例如暂停结束时让观众立即看到完整的画面
+#[derive(Clone, Default)]
+pub struct KeyframeRequest(Arc<AtomicU64>); // 0 表示没有请求
+
+impl KeyframeRequest {
+    pub fn request(&self, timestamp: u64) {
+        self.0.store(timestamp.max(1), Ordering::Relaxed);
+    }
+    
+    /// 帧的时间戳已到达请求的时间戳时取出请求；之前的帧可能已被丢弃，不要求相等
+    fn take(&self, timestamp: u64) -> bool {
+        let requested = self.0.load(Ordering::Relaxed);
+        requested != 0 && timestamp >= requested
+            && self.0.compare_exchange(requested, 0, Ordering::Relaxed, Ordering::Relaxed).is_ok()
+    }
+}
+
+/// 编码管理器
+pub struct EncoderManager {
+    config: EncodingConfig,
+    video_encoder: Option<Box<dyn VideoEncoder>>,
+    audio_encoder: Option<Box<dyn AudioEncoder>>,
+    tone_mapper: Arc<ToneMapper>,
+    hdr_passthrough: bool, // HDR 画面直接以 10 位编码
+    hdr_detected: bool,
+    color_converter: Arc<ColorConverter>,
+    negotiated_format: Option<(VideoPixelFormat, VideoPixelFormat)>, // 上一帧的 (输入格式, 编码格式)
+    last_frame: Option<CapturedFrame>, // 用于检测画面是否变化
+    last_encoded_at: u64,              // 上一次完整编码的帧时间戳
+    static_count: u64,                 // 连续未变化的帧数
+    bitrate_control: BitrateControl,
+    bitrate: u32, // 视频编码器当前的目标码率
+    keyframe_request: KeyframeRequest,
+    stats: StatsRecorder,
+}
+
+impl EncoderManager {
+    pub async fn new(
+        config: &EncodingConfig,
+        bitrate_control: BitrateControl,
+        keyframe_request: KeyframeRequest,
+        stats: StatsRecorder,
+    ) -> Result<Self> {
+        info!("Initializing encoder manager...");
+        
+        // 创建视频编码器
+        let video_encoder_config = VideoEncoderConfig {
+            codec: config.video.codec.clone(),
+            width: config.video.width,
+            height: config.video.height,
+            fps: config.video.fps.round().max(1.0) as u32, // 关键帧间隔按整数帧计算
+            bitrate: config.video.bitrate,
+            keyframe_interval: config.video.keyframe_interval,
+            preset: config.video.preset.clone(),
+            hardware_acceleration: config.hardware_acceleration,
+            vaapi_device: config.vaapi_device.clone(),
+            rate_control: config.video.rate_control,
+            max_bitrate: config.video.max_bitrate,
+            buffer_size: config.video.buffer_size,
+            qp: config.video.qp,
+            profile: config.video.profile.clone(),
+            level: config.video.level.clone(),
+            // 推流路径的数据包只有一个时间戳，没有 composition time，暂不能携带重排序的 B 帧
+            b_frames: 0,
+            low_latency: config.video.low_latency,
+        };
+        if config.video.b_frames > 0 {
+            warn!("B-frames are not supported on the push path yet (packets carry no composition time), encoding without them");
+        }
+        
+        let video_encoder = EncoderFactory::create_video_encoder_with(config.video.encoder.as_deref().unwrap_or(BUILTIN_ENCODER), video_encoder_config)
+            .map_err(|e| anyhow::anyhow!("Failed to create video encoder: {}", e))?;
+        
+        // 创建音频编码器
+        let audio_encoder_config = AudioEncoderConfig {
+            codec: config.audio.codec.clone(),
+            sample_rate: config.audio.effective_sample_rate(),
+            channels: config.audio.channels,
+            bitrate: config.audio.bitrate,
+            vbr: config.audio.vbr,
+        };
+        
+        let audio_encoder = EncoderFactory::create_audio_encoder_with(config.audio.encoder.as_deref().unwrap_or(BUILTIN_ENCODER), audio_encoder_config)
+            .map_err(|e| anyhow::anyhow!("Failed to create audio encoder: {}", e))?;
+        
+        // 只有支持 10 位的编码格式才能直通 HDR，目前只有 H.265 编码器支持 HDR10
+        let hdr_passthrough = config.video.hdr.mode == HdrMode::Passthrough
+            && matches!(config.video.codec, VideoCodec::H265);
+        if config.video.hdr.mode == HdrMode::Passthrough && !hdr_passthrough {
+            warn!("{:?} cannot carry HDR, HDR frames will be tone mapped to SDR", config.video.codec);
+        }
+        
+        Ok(Self {
+            config: config.clone(),
+            video_encoder: Some(video_encoder),
+            audio_encoder: Some(audio_encoder),
+            tone_mapper: Arc::new(ToneMapper::new(&config.video.hdr)),
+            hdr_passthrough,
+            hdr_detected: false,
+            color_converter: Arc::new(ColorConverter::new()),
+            negotiated_format: None,
+            last_frame: None,
+            last_encoded_at: 0,
+            static_count: 0,
+            bitrate_control,
+            bitrate: config.video.bitrate,
+            keyframe_request,
+            stats,
+        })
+    }
+    
+    pub async fn start_encoding(
+        mut self,
+        mut frame_receiver: mpsc::UnboundedReceiver<CapturedFrame>,
+        packet_sender: mpsc::UnboundedSender<MediaPacket>,
+    ) -> StreamResult<()> {
+        info!("Starting encoding (up to {} queued video frames)...", self.config.max_queued_frames);
+        
+        // 编码在独立的线程上进行，这里只把帧转入有界队列，通道不会因为编码变慢而堆积
+        let queue = Arc::new(FrameQueue::new(self.config.max_queued_frames));
+        let stats = self.stats.clone();
+        let worker = {
+            let queue = queue.clone();
+            let runtime = tokio::runtime::Handle::current();
+            tokio::task::spawn_blocking(move || {
+                while let Some(frame) = queue.pop() {
+                    match runtime.block_on(self.encode_frame(frame)) {
+                        Ok(packets) => {
+                            self.stats.record_encoded(
+                                packets.iter().filter(|packet| matches!(packet, MediaPacket::Video { .. })).count() as u64
+                            );
+                            if packets.into_iter().any(|packet| packet_sender.send(packet).is_err()) {
+                                error!("Failed to send encoded packet, receiver dropped");
+                                break;
+                            }
+                        }
+                        Err(e) => error!("Failed to encode frame: {}", e),
+                    }
+                }
+                queue.close();
+            })
+        };
+        
+        let mut report = tokio::time::interval(DROP_REPORT_INTERVAL);
+        let mut total_dropped = 0;
+        loop {
+            tokio::select! {
+                frame = frame_receiver.recv() => {
+                    let Some(frame) = frame else { break };
+                    if !queue.push(frame) {
+                        break;
+                    }
+                    stats.record_encoder_queue(queue.video_len());
+                }
+                _ = report.tick() => {
+                    let dropped = queue.take_dropped();
+                    if dropped > 0 {
+                        stats.record_dropped(dropped);
+                        total_dropped += dropped;
+                        warn!("Encoder is falling behind, dropped {} video frames in the last {}s ({} total)",
+                              dropped, DROP_REPORT_INTERVAL.as_secs(), total_dropped);
+                    }
+                }
+            }
+        }
+        
+        queue.close();
+        if let Err(e) = worker.await {
+            error!("Encoding worker failed: {}", e);
+        }
+        info!("Encoding finished");
+        Ok(())
+    }
+    
+    async fn encode_frame(&mut self, frame: CapturedFrame) -> StreamResult<Vec<MediaPacket>> {
+        let mut packets = match frame.frame_type {
+            FrameType::Audio => Vec::new(),
+            _ => self.apply_bitrate()?,
+        };
+        if !matches!(frame.frame_type, FrameType::Audio) && self.keyframe_request.take(frame.timestamp) {
+            packets.extend(self.request_keyframe()?);
+        }
+        packets.extend(match frame.frame_type {
+            FrameType::Video => self.encode_video_frame(frame).await?,
+            FrameType::GpuVideo(ref surface) => self.encode_gpu_frame(surface, frame.timestamp)?,
+            FrameType::Audio => self.encode_audio_frame(frame).await?,
+        });
+        Ok(packets)
+    }
+    
+    /// 推流端调整了目标码率时重新配置视频编码器，返回切换前已编码的包
+    fn apply_bitrate(&mut self) -> StreamResult<Vec<MediaPacket>> {
+        let target = self.bitrate_control.target();
+        if target == self.bitrate {
+            return Ok(Vec::new());
+        }
+        // 不支持时也只尝试一次
+        self.bitrate = target;
+        let encoder = self.video_encoder.as_mut()
+            .ok_or_else(|| StreamError::Codec("Video encoder not initialized".to_string()))?;
+        let encoded_packets = tokio::task::block_in_place(|| encoder.set_bitrate(target))?;
+        debug!("Video encoder restarted at {} kbps", target);
+        
+        Ok(encoded_packets.into_iter().map(|packet| {
+            MediaPacket::Video {
+                data: packet.data,
+                timestamp: packet.timestamp,
+                is_keyframe: packet.is_keyframe,
+                capture_time: Some(packet.timestamp as i64),
+            }
+        }).collect())
+    }
+    
+    /// 下一帧编码为关键帧，返回此前已编码的包；这一帧不作为静止画面跳过
+    fn request_keyframe(&mut self) -> StreamResult<Vec<MediaPacket>> {
+        let encoder = self.video_encoder.as_mut()
+            .ok_or_else(|| StreamError::Codec("Video encoder not initialized".to_string()))?;
+        let encoded_packets = tokio::task::block_in_place(|| encoder.request_keyframe())?;
+        self.last_frame = None;
+        debug!("Keyframe requested");
+        
+        Ok(encoded_packets.into_iter().map(|packet| {
+            MediaPacket::Video {
+                data: packet.data,
+                timestamp: packet.timestamp,
+                is_keyframe: packet.is_keyframe,
+                capture_time: Some(packet.timestamp as i64),
+            }
+        }).collect())
+    }
+    
+    async fn encode_video_frame(&mut self, frame: CapturedFrame) -> StreamResult<Vec<MediaPacket>> {
+        if let Some(packets) = self.encode_static_frame(&frame)? {
+            return Ok(packets);
+        }
+        debug!("Encoding video frame");
+        self.last_encoded_at = frame.timestamp;
+        
+        let capture_time = Some(frame.timestamp as i64);
+        
+        let width = frame.width.unwrap_or(1920);
+        let height = frame.height.unwrap_or(1080);
+        let format = frame.pixel_format.unwrap_or(VideoPixelFormat::Rgba32);
+        
+        // scRGB 画面需要先转换为 PQ，暂时仍做色调映射
+        let passthrough = self.hdr_passthrough && format == VideoPixelFormat::Rgb10a2;
+        if format.is_hdr() && !self.hdr_detected {
+            self.hdr_detected = true;
+            if passthrough {
+                info!("HDR frames detected ({:?}), encoding as HDR", format);
+            } else {
+                info!("HDR frames detected ({:?}), tone mapping to SDR with {:?}", format, self.config.video.hdr.tone_mapping);
+            }
+        }
+        
+        let (data, format) = if format.is_hdr() && !passthrough {
+            let tone_mapper = self.tone_mapper.clone();
+            let data = tokio::task::spawn_blocking(move || tone_mapper.tone_map(&frame.data, width, height, format))
+                .await
+                .map_err(|e| StreamError::Codec(format!("Tone mapping task failed: {}", e)))??;
+            (Bytes::from(data), VideoPixelFormat::Rgba32)
+        } else {
+            (frame.data, format)
+        };
+        
+        // 与编码器协商像素格式，能转换时先在客户端转换为 YUV
+        let target = self.video_encoder.as_ref()
+            .and_then(|encoder| encoder.preferred_input_format())
+            .filter(|&target| target != format && ColorConverter::supports(format, target, width, height));
+        let negotiated = (format, target.unwrap_or(format));
+        if self.negotiated_format != Some(negotiated) {
+            self.negotiated_format = Some(negotiated);
+            match target {
+                Some(target) => info!("Converting {:?} frames to {:?} before encoding", format, target),
+                None => info!("Encoding {:?} frames without conversion", format),
+            }
+        }
+        let (data, format) = match target {
+            Some(target) => {
+                let converter = self.color_converter.clone();
+                let data = tokio::task::spawn_blocking(move || converter.convert(&data, width, height, format, target))
+                    .await
+                    .map_err(|e| StreamError::Codec(format!("Color conversion task failed: {}", e)))??;
+                (Bytes::from(data), target)
+            }
+            None => (data, format),
+        };
+        
+        let video_frame = VideoFrame {
+            data,
+            width,
+            height,
+            format,
+            timestamp: frame.timestamp,
+        };
+        
+        if let Some(encoder) = &mut self.video_encoder {
+            // 编码器通过管道与 ffmpeg 交换数据，可能阻塞
+            let encoded_packets = tokio::task::block_in_place(|| encoder.encode_frame(&video_frame))?;
+            
+            let media_packets = encoded_packets.into_iter().map(|packet| {
+                MediaPacket::Video {
+                    data: packet.data,
+                    timestamp: packet.timestamp,
+                    is_keyframe: packet.is_keyframe,
+                    capture_time,
+                }
+            }).collect();
+            
+            Ok(media_packets)
+        } else {
+            Err(StreamError::Codec("Video encoder not initialized".to_string()))
+        }
+    }
+    
+    /// 视频编码器能否直接编码显存中的画面
+    pub fn supports_gpu_surface(&self, surface: &GpuSurface) -> bool {
+        self.video_encoder.as_ref().is_some_and(|encoder| encoder.supports_gpu_surface(surface))
+    }
+    
+    /// 编码显存中的画面；画面无法比较，不做静止画面检测
+    fn encode_gpu_frame(&mut self, surface: &GpuSurface, timestamp: u64) -> StreamResult<Vec<MediaPacket>> {
+        let encoder = self.video_encoder.as_mut()
+            .ok_or_else(|| StreamError::Codec("Video encoder not initialized".to_string()))?;
+        let encoded_packets = tokio::task::block_in_place(|| encoder.encode_gpu_frame(surface, timestamp))?;
+        
+        Ok(encoded_packets.into_iter().map(|packet| {
+            MediaPacket::Video {
+                data: packet.data,
+                timestamp: packet.timestamp,
+                is_keyframe: packet.is_keyframe,
+                capture_time: Some(timestamp as i64),
+            }
+        }).collect())
+    }
+    
+    /// 画面与上一帧相同时跳过编码或输出重复帧；需要完整编码时返回 None
+    ///
+    /// 画面静止超过 refresh_interval 时仍完整编码一帧，让编码器刷新画质
+    fn encode_static_frame(&mut self, frame: &CapturedFrame) -> StreamResult<Option<Vec<MediaPacket>>> {
+        let config = &self.config.video.static_frames;
+        if config.mode == StaticFrameMode::Off {
+            return Ok(None);
+        }
+        
+        // 捕获未变化时常常直接复用同一块缓冲，先比较指针再逐字节比较
+        let unchanged = self.last_frame.as_ref().is_some_and(|last| {
+            last.width == frame.width
+                && last.height == frame.height
+                && last.pixel_format == frame.pixel_format
+                && (last.data.as_ptr() == frame.data.as_ptr() || last.data == frame.data)
+        });
+        if !unchanged {
+            if self.static_count > 0 {
+                debug!("Frame changed after {} unchanged frames", self.static_count);
+                self.static_count = 0;
+            }
+            self.last_frame = Some(frame.clone());
+            return Ok(None);
+        }
+        if frame.timestamp.saturating_sub(self.last_encoded_at) >= config.refresh_interval {
+            return Ok(None);
+        }
+        
+        self.static_count += 1;
+        if self.static_count == 1 {
+            debug!("Frame unchanged, using {:?} frames until the content changes", config.mode);
+        }
+        match config.mode {
+            StaticFrameMode::Skip => Ok(Some(Vec::new())),
+            _ => {
+                let encoder = self.video_encoder.as_mut()
+                    .ok_or_else(|| StreamError::Codec("Video encoder not initialized".to_string()))?;
+                let capture_time = Some(frame.timestamp as i64);
+                Ok(encoder.encode_repeat(frame.timestamp)?.map(|packets| {
+                    packets.into_iter().map(|packet| MediaPacket::Video {
+                        data: packet.data,
+                        timestamp: packet.timestamp,
+                        is_keyframe: packet.is_keyframe,
+                        capture_time,
+                    }).collect()
+                }))
+            }
+        }
+    }
+    
+    async fn encode_audio_frame(&mut self, frame: CapturedFrame) -> StreamResult<Vec<MediaPacket>> {
+        debug!("Encoding audio frame");
+        
+        let capture_time = Some(frame.timestamp as i64);
+        
+        let audio_frame = AudioFrame {
+            data: frame.data,
+            sample_rate: self.config.audio.effective_sample_rate(),
+            channels: self.config.audio.channels,
+            format: AudioSampleFormat::S16, // 假设捕获的是16位采样
+            timestamp: frame.timestamp,
+        };
+        
+        if let Some(encoder) = &mut self.audio_encoder {
+            let encoded_packets = encoder.encode_frame(&audio_frame)?;
+            
+            let media_packets = encoded_packets.into_iter().map(|packet| {
+                MediaPacket::Audio {
+                    data: packet.data,
+                    timestamp: packet.timestamp,
+                    capture_time,
+                }
+            }).collect();
+            
+            Ok(media_packets)
+        } else {
+            Err(StreamError::Codec("Audio encoder not initialized".to_string()))
+        }
+    }
+}
+
+// 注意：EncoderManager 不实现 Clone，因为编码器状态不应该被复制
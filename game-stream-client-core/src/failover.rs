@@ -0,0 +1,132 @@
+use std::path::PathBuf;
+use std::time::Instant;
+use tokio::fs::{self, File};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tracing::{info, warn};
+
+use game_stream_common::{EncodingConfig, FailoverConfig, MediaPacket, StreamResult};
+use crate::pusher::TsPackager;
+
+/// 断线录制 - 推流中断期间把编码数据写入本地 TS 文件，网络恢复前的画面不会丢失
+///
+/// 每次断线写一个文件，从断线后的第一个视频关键帧开始，到重连后连接稳定为止；
+/// TS 没有文件尾，进程意外退出时已写入的部分仍可播放
+pub struct FailoverRecorder {
+    directory: PathBuf,
+    stream_key: String,
+    encoding_config: EncodingConfig,
+    recording: Option<Recording>,
+    failed: bool, // 本次断线写入失败，不再重试
+}
+
+struct Recording {
+    path: PathBuf,
+    file: BufWriter<File>,
+    packager: TsPackager,
+    started: Instant,
+    bytes: u64,
+}
+
+impl FailoverRecorder {
+    /// 未开启断线录制，或者编码格式不能封装在 TS 中时返回 None
+    pub fn new(config: &FailoverConfig, stream_key: &str, encoding_config: &EncodingConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        if let Err(e) = TsPackager::new(encoding_config) {
+            warn!("Failover recording disabled: {}", e);
+            return None;
+        }
+
+        // stream_key 可能带有 SRT 访问控制等字符，文件名只保留安全字符
+        let stream_key = stream_key.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        Some(Self {
+            directory: PathBuf::from(&config.directory),
+            stream_key,
+            encoding_config: encoding_config.clone(),
+            recording: None,
+            failed: false,
+        })
+    }
+
+    /// 写入断线期间的一个包，收到第一个视频关键帧时创建文件
+    pub async fn write(&mut self, packet: &MediaPacket) {
+        if self.failed {
+            return;
+        }
+        if self.recording.is_none() {
+            if !matches!(packet, MediaPacket::Video { is_keyframe: true, .. }) {
+                return;
+            }
+            match self.open().await {
+                Ok(recording) => {
+                    warn!("Connection lost, recording locally to {}", recording.path.display());
+                    self.recording = Some(recording);
+                }
+                Err(e) => {
+                    warn!("Cannot start failover recording in {}: {}", self.directory.display(), e);
+                    self.failed = true;
+                    return;
+                }
+            }
+        }
+
+        self.append(packet).await;
+    }
+
+    /// 重连后连接稳定之前，已发送的包也继续写入录制，不开始新的录制
+    pub async fn continue_recording(&mut self, packet: &MediaPacket) {
+        self.append(packet).await;
+    }
+
+    async fn append(&mut self, packet: &MediaPacket) {
+        let Some(recording) = &mut self.recording else {
+            return;
+        };
+        let Some(data) = recording.packager.packetize(packet.clone()) else {
+            return;
+        };
+        let mut result = recording.file.write_all(&data).await;
+        // 每个关键帧刷新一次，进程意外退出时最多丢失一个 GOP
+        if result.is_ok() && matches!(packet, MediaPacket::Video { is_keyframe: true, .. }) {
+            result = recording.file.flush().await;
+        }
+        match result {
+            Ok(()) => recording.bytes += data.len() as u64,
+            Err(e) => {
+                warn!("Failover recording to {} failed: {}", recording.path.display(), e);
+                self.recording = None;
+                self.failed = true;
+            }
+        }
+    }
+
+    /// 连接恢复或推流结束，关闭本次断线的录制文件；下次断线重新开始录制
+    pub async fn finish(&mut self) {
+        self.failed = false;
+        let Some(mut recording) = self.recording.take() else {
+            return;
+        };
+        if let Err(e) = recording.file.flush().await {
+            warn!("Failed to flush failover recording {}: {}", recording.path.display(), e);
+        }
+        info!("Failover recording saved to {} ({:.1} MB, {}s)", recording.path.display(),
+              recording.bytes as f64 / 1024.0 / 1024.0, recording.started.elapsed().as_secs());
+    }
+
+    async fn open(&self) -> StreamResult<Recording> {
+        fs::create_dir_all(&self.directory).await?;
+        let name = format!("{}-{}.ts", self.stream_key, chrono::Local::now().format("%Y%m%d-%H%M%S"));
+        let path = self.directory.join(name);
+        let file = File::create(&path).await?;
+        Ok(Recording {
+            path,
+            file: BufWriter::new(file),
+            packager: TsPackager::new(&self.encoding_config)?,
+            started: Instant::now(),
+            bytes: 0,
+        })
+    }
+}
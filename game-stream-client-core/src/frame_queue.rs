@@ -0,0 +1,90 @@
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex};
+
+use crate::capture::{CapturedFrame, FrameType};
+
+/// 捕获和编码之间的有界队列 - 编码跟不上时丢弃最旧的视频帧，而不是让帧在内存中无限堆积
+///
+/// 容量只计算视频帧；音频帧很小且中断会听到爆音，从不丢弃
+pub struct FrameQueue {
+    state: Mutex<QueueState>,
+    available: Condvar,
+    capacity: usize,
+}
+
+#[derive(Default)]
+struct QueueState {
+    frames: VecDeque<CapturedFrame>,
+    video_frames: usize,
+    dropped: u64, // 上次取出统计后丢弃的视频帧数
+    closed: bool,
+}
+
+impl FrameQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(QueueState::default()),
+            available: Condvar::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// 放入一帧，视频帧超过容量时丢弃最旧的视频帧；队列已关闭时返回 false
+    pub fn push(&self, frame: CapturedFrame) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.closed {
+            return false;
+        }
+
+        if is_video(&frame) {
+            if state.video_frames >= self.capacity {
+                if let Some(index) = state.frames.iter().position(is_video) {
+                    state.frames.remove(index);
+                    state.video_frames -= 1;
+                    state.dropped += 1;
+                }
+            }
+            state.video_frames += 1;
+        }
+        state.frames.push_back(frame);
+        self.available.notify_one();
+        true
+    }
+
+    /// 取出最早的一帧，队列为空时等待；队列关闭且已取完时返回 None
+    pub fn pop(&self) -> Option<CapturedFrame> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if let Some(frame) = state.frames.pop_front() {
+                if is_video(&frame) {
+                    state.video_frames -= 1;
+                }
+                return Some(frame);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self.available.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// 关闭队列，已放入的帧仍可取出
+    pub fn close(&self) {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).closed = true;
+        self.available.notify_all();
+    }
+
+    /// 队列中的视频帧数
+    pub fn video_len(&self) -> usize {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).video_frames
+    }
+
+    /// 取出上次调用以来丢弃的视频帧数
+    pub fn take_dropped(&self) -> u64 {
+        std::mem::take(&mut self.state.lock().unwrap_or_else(|e| e.into_inner()).dropped)
+    }
+}
+
+fn is_video(frame: &CapturedFrame) -> bool {
+    matches!(frame.frame_type, FrameType::Video | FrameType::GpuVideo(_))
+}
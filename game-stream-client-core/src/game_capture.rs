@@ -0,0 +1,333 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tracing::{info, debug, warn};
+
+use game_stream_common::{GameCaptureConfig, WindowCaptureConfig, GraphicsApi, VideoPixelFormat, StreamResult, StreamError};
+use crate::window_capture::{WindowFrame, WindowMatcher, WindowTracker};
+#[cfg(not(target_os = "linux"))]
+use crate::window_capture::{window_pid, Window};
+
+/// 钩子共享画面文件的魔数
+const FRAME_MAGIC: &[u8; 8] = b"GSHOOK01";
+
+/// 共享画面文件头的大小，像素数据从这里开始
+const HEADER_SIZE: usize = 64;
+
+/// 没有钩子画面时重新查找进程和画面文件的间隔
+const RETRY_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 超过这个时间没有新帧时认为游戏已退出或挂起
+const STALE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 还没有任何画面时输出的尺寸
+const DEFAULT_FRAME_SIZE: (u32, u32) = (1280, 720);
+
+/// 钩子画面允许的最大宽高，超出时视为文件损坏
+const MAX_FRAME_DIMENSION: u32 = 16384;
+
+/// 游戏捕获 - 读取图形 API 钩子在 Present 时写出的画面
+///
+/// 钩子 (Vulkan 层，或注入进程的 D3D11/D3D12/OpenGL 钩子) 不属于客户端本身，目前也没有随客户端发布，
+/// 没有钩子时这个源在 hook_timeout 后改为捕获游戏进程的窗口 (fallback_to_window)。
+/// 钩子在交换链呈现前复制后备缓冲区，写入 `{frame_dir}/game-stream-capture-{pid}.frame`。
+/// 文件格式 (小端序)：
+///
+/// | 偏移 | 类型 | 内容 |
+/// |------|------|------|
+/// | 0 | `[u8; 8]` | 魔数 `GSHOOK01` |
+/// | 8 | u32 | 宽 |
+/// | 12 | u32 | 高 |
+/// | 16 | u32 | 每行字节数 |
+/// | 20 | u32 | 像素格式 (0 = BGRA8, 1 = RGBA8, 2 = HDR10 R10G10B10A2, 3 = scRGB RGBA16F) |
+/// | 24 | u32 | 图形 API (1 = Vulkan, 2 = D3D11, 3 = D3D12, 4 = OpenGL) |
+/// | 32 | u64 | 帧序号，写入像素期间为奇数 |
+/// | 64 | | 像素数据 |
+///
+/// HDR 交换链由钩子以 10 位或浮点格式原样写出，之后由编码阶段做色调映射或直通
+pub struct GameCapture {
+    process_name: Option<String>,
+    configured_pid: Option<u32>,
+    api: GraphicsApi,
+    frame_dir: PathBuf,
+    hook_timeout: Duration,
+    pid: Option<u32>,
+    file: Option<File>,
+    last_attempt: Option<Instant>,
+    started: Instant,
+    sequence: u64,
+    last_frame: Option<WindowFrame>,
+    last_frame_at: Instant,
+    hooked: bool,
+    fallback: Option<WindowTracker>,
+}
+
+struct FrameHeader {
+    width: u32,
+    height: u32,
+    stride: u32,
+    format: u32,
+    api: GraphicsApi,
+    sequence: u64,
+}
+
+impl GameCapture {
+    pub fn new(
+        process_name: Option<&str>,
+        pid: Option<u32>,
+        api: GraphicsApi,
+        config: &GameCaptureConfig,
+        window_config: &WindowCaptureConfig,
+    ) -> StreamResult<Self> {
+        if process_name.is_none() && pid.is_none() {
+            return Err(StreamError::Config("Game source needs a process name or PID".to_string()));
+        }
+
+        // 没有钩子时 (游戏未加载钩子、反作弊拦截等) 捕获该进程的窗口
+        let fallback = if config.fallback_to_window {
+            let matcher = WindowMatcher::new("", None, process_name, pid)?;
+            Some(WindowTracker::new(matcher, window_config))
+        } else {
+            None
+        };
+
+        let frame_dir = config.frame_dir.as_ref().map(PathBuf::from).unwrap_or_else(default_frame_dir);
+        info!("Waiting for {:?} game capture hook frames in {}", api, frame_dir.display());
+
+        Ok(Self {
+            process_name: process_name.map(str::to_string),
+            configured_pid: pid,
+            api,
+            frame_dir,
+            hook_timeout: Duration::from_secs(config.hook_timeout),
+            pid,
+            file: None,
+            last_attempt: None,
+            started: Instant::now(),
+            sequence: 0,
+            last_frame: None,
+            last_frame_at: Instant::now(),
+            hooked: false,
+            fallback,
+        })
+    }
+
+    /// 捕获一帧画面，游戏没有出新帧时重复上一帧，没有钩子时改用窗口捕获或黑帧
+    pub fn capture(&mut self) -> WindowFrame {
+        match self.read_hook_frame() {
+            Ok(Some(frame)) => {
+                if !self.hooked {
+                    info!("Game capture hook attached to process {:?} ({}x{} {:?})",
+                          self.pid, frame.width, frame.height, frame.pixel_format);
+                    self.hooked = true;
+                }
+                self.last_frame = Some(frame);
+                self.last_frame_at = Instant::now();
+            }
+            Ok(None) if self.hooked && self.last_frame_at.elapsed() >= STALE_TIMEOUT => {
+                warn!("Game capture hook for process {:?} stopped sending frames", self.pid);
+                self.hooked = false;
+                self.last_frame = None;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                if self.hooked {
+                    warn!("Lost game capture hook for process {:?}: {}", self.pid, e);
+                    self.hooked = false;
+                } else {
+                    debug!("No game capture hook frame: {}", e);
+                }
+                self.file = None;
+                self.last_frame = None;
+            }
+        }
+
+        if let Some(frame) = &self.last_frame {
+            return WindowFrame {
+                width: frame.width,
+                height: frame.height,
+                data: frame.data.clone(),
+                pixel_format: frame.pixel_format,
+                origin: None,
+            };
+        }
+
+        if self.started.elapsed() >= self.hook_timeout {
+            if let Some(fallback) = &mut self.fallback {
+                return fallback.capture();
+            }
+        }
+
+        let (width, height) = DEFAULT_FRAME_SIZE;
+        WindowFrame {
+            width,
+            height,
+            data: [0u8, 0, 0, 255].repeat((width * height) as usize),
+            pixel_format: VideoPixelFormat::Rgba32,
+            origin: None,
+        }
+    }
+
+    /// 读取钩子写出的新画面，没有新帧时返回 None
+    fn read_hook_frame(&mut self) -> std::io::Result<Option<WindowFrame>> {
+        if self.file.is_none() && !self.open_frame_file() {
+            return Ok(None);
+        }
+        let Some(file) = &mut self.file else {
+            return Ok(None);
+        };
+
+        let header = read_header(file)?;
+        if self.api != GraphicsApi::Auto && header.api != self.api {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData,
+                format!("hook provides {:?} frames, expected {:?}", header.api, self.api)));
+        }
+        // 钩子正在写入或没有新帧
+        if header.sequence % 2 == 1 || header.sequence == self.sequence {
+            return Ok(None);
+        }
+
+        let (pixel_format, bytes_per_pixel) = match header.format {
+            0 => (VideoPixelFormat::Bgra32, 4),
+            1 => (VideoPixelFormat::Rgba32, 4),
+            2 => (VideoPixelFormat::Rgb10a2, 4),
+            3 => (VideoPixelFormat::Rgba16f, 8),
+            format => {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("unknown pixel format {}", format)));
+            }
+        };
+        if header.width == 0 || header.height == 0
+            || header.width > MAX_FRAME_DIMENSION || header.height > MAX_FRAME_DIMENSION
+        {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData,
+                format!("invalid frame size {}x{}", header.width, header.height)));
+        }
+        let row_bytes = header.width as usize * bytes_per_pixel;
+        if (header.stride as usize) < row_bytes {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "stride smaller than row"));
+        }
+        // 像素数据必须都在文件里，避免按损坏的文件头分配过大的缓冲区
+        let file_len = file.metadata()?.len();
+        let frame_bytes = (header.stride as u64).checked_mul(header.height as u64)
+            .filter(|frame_bytes| HEADER_SIZE as u64 + frame_bytes <= file_len)
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData,
+                format!("frame file too short for {} rows of {} bytes", header.height, header.stride)))?;
+        let mut pixels = vec![0u8; frame_bytes as usize];
+        file.seek(SeekFrom::Start(HEADER_SIZE as u64))?;
+        file.read_exact(&mut pixels)?;
+
+        // 读取期间钩子写入了新帧，丢弃这次读取
+        if read_header(file)?.sequence != header.sequence {
+            return Ok(None);
+        }
+        self.sequence = header.sequence;
+
+        let mut data = Vec::with_capacity(row_bytes * header.height as usize);
+        for row in pixels.chunks_exact(header.stride as usize) {
+            data.extend_from_slice(&row[..row_bytes]);
+        }
+        let pixel_format = if pixel_format == VideoPixelFormat::Bgra32 {
+            for pixel in data.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+            VideoPixelFormat::Rgba32
+        } else {
+            pixel_format
+        };
+
+        Ok(Some(WindowFrame {
+            width: header.width,
+            height: header.height,
+            data,
+            pixel_format,
+            origin: None,
+        }))
+    }
+
+    /// 查找目标进程和它的画面文件，按间隔重试
+    fn open_frame_file(&mut self) -> bool {
+        if self.last_attempt.is_some_and(|last| last.elapsed() < RETRY_INTERVAL) {
+            return false;
+        }
+        self.last_attempt = Some(Instant::now());
+
+        // 进程可能重启，按进程名匹配时每次重新查找
+        if self.configured_pid.is_none() {
+            self.pid = self.process_name.as_deref().and_then(find_process);
+        }
+        let Some(pid) = self.pid else {
+            return false;
+        };
+
+        let path = self.frame_dir.join(format!("game-stream-capture-{}.frame", pid));
+        match File::open(&path) {
+            Ok(file) => {
+                debug!("Opened game capture frames {}", path.display());
+                self.file = Some(file);
+                self.sequence = 0;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+fn read_header(file: &mut File) -> std::io::Result<FrameHeader> {
+    let mut header = [0u8; HEADER_SIZE];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut header)?;
+
+    if &header[0..8] != FRAME_MAGIC {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "not a game capture frame file"));
+    }
+
+    let u32_at = |offset: usize| u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap());
+    let api = match u32_at(24) {
+        1 => GraphicsApi::Vulkan,
+        2 => GraphicsApi::D3D11,
+        3 => GraphicsApi::D3D12,
+        4 => GraphicsApi::OpenGL,
+        _ => GraphicsApi::Auto,
+    };
+
+    Ok(FrameHeader {
+        width: u32_at(8),
+        height: u32_at(12),
+        stride: u32_at(16),
+        format: u32_at(20),
+        api,
+        sequence: u64::from_le_bytes(header[32..40].try_into().unwrap()),
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn default_frame_dir() -> PathBuf {
+    PathBuf::from("/dev/shm")
+}
+
+#[cfg(not(target_os = "linux"))]
+fn default_frame_dir() -> PathBuf {
+    std::env::temp_dir()
+}
+
+/// 按进程名查找进程
+#[cfg(target_os = "linux")]
+fn find_process(name: &str) -> Option<u32> {
+    std::fs::read_dir("/proc").ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .find(|pid| {
+            std::fs::read_to_string(format!("/proc/{}/comm", pid))
+                .is_ok_and(|comm| comm.trim().eq_ignore_ascii_case(name.trim_end_matches(".exe")))
+        })
+}
+
+/// 按进程名查找进程 (通过进程的窗口)
+#[cfg(not(target_os = "linux"))]
+fn find_process(name: &str) -> Option<u32> {
+    Window::all().ok()?
+        .iter()
+        .filter(|window| window.app_name().eq_ignore_ascii_case(name) || window.app_name().eq_ignore_ascii_case(name.trim_end_matches(".exe")))
+        .find_map(window_pid)
+}
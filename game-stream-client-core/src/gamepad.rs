@@ -0,0 +1,414 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use game_stream_common::{GamepadReport, GamepadState, InputEvent, RemoteInputConfig, GAMEPAD_AXES, GAMEPAD_BUTTONS};
+
+/// 手柄超过这么久没有收到输入时回到中立状态，防止观看者断线后按键一直按住
+const IDLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// 最小单程延迟的统计窗口，时钟漂移时基准延迟随之更新
+const BASE_WINDOW: Duration = Duration::from_secs(10);
+
+/// 延迟和抖动的平滑系数 (同 RFC 3550)
+const SMOOTHING: f64 = 1.0 / 16.0;
+
+/// 观看者手柄的接收端：按序列号丢弃旧快照，经抖动缓冲平滑后输出到虚拟手柄，并统计延迟
+///
+/// 每个观看者的每个手柄对应一个虚拟手柄，首次收到输入时创建，撤销授权时移除
+pub struct Gamepads {
+    max_pads: usize,
+    max_buffer_ms: f64,
+    pads: HashMap<(Uuid, u8), Pad>,
+    warned: bool,
+}
+
+struct Pad {
+    device: Option<VirtualGamepad>,
+    output: GamepadState,                     // 已输出的状态
+    queue: VecDeque<(Instant, GamepadState)>, // 等待输出的快照和输出时间
+    last_seq: Option<u32>,
+    last_input: Instant,
+    last_transit: Option<f64>,
+    base: Option<f64>,          // 最小单程延迟，抖动缓冲以此为基准
+    window: (Instant, f64),     // 当前统计窗口的开始时间和最小单程延迟
+    latency_ms: f64,
+    jitter_ms: f64,
+    buffer_ms: f64,
+    received: u64,
+    lost: u64,
+    late: u64,
+}
+
+impl Gamepads {
+    pub fn new(config: &RemoteInputConfig) -> Self {
+        Self {
+            max_pads: config.max_gamepads,
+            max_buffer_ms: config.gamepad_jitter_buffer_ms as f64,
+            pads: HashMap::new(),
+            warned: false,
+        }
+    }
+
+    /// 收到 "gamepad" 数据通道的状态快照，sent_at 已由服务器换算为本机时钟
+    pub fn receive(&mut self, viewer: Uuid, state: GamepadState) {
+        let now = Instant::now();
+        let max_buffer_ms = self.max_buffer_ms;
+        let Some(pad) = self.pad(viewer, state.pad) else {
+            return;
+        };
+        pad.last_input = now;
+
+        // 序列号回绕时按差值判断新旧
+        if let Some(last) = pad.last_seq {
+            let gap = state.seq.wrapping_sub(last) as i32;
+            if gap <= 0 {
+                pad.late += 1;
+                return;
+            }
+            pad.lost += gap as u64 - 1;
+        }
+        pad.last_seq = Some(state.seq);
+        pad.received += 1;
+
+        // 单程延迟包含两段时钟同步的误差，抖动缓冲只使用它相对最小值的变化
+        let transit = (chrono::Utc::now().timestamp_millis() - state.sent_at) as f64;
+        match pad.last_transit {
+            Some(last) => {
+                pad.jitter_ms += ((transit - last).abs() - pad.jitter_ms) * SMOOTHING;
+                pad.latency_ms += (transit - pad.latency_ms) * SMOOTHING;
+            }
+            None => pad.latency_ms = transit,
+        }
+        pad.last_transit = Some(transit);
+        if now.duration_since(pad.window.0) >= BASE_WINDOW {
+            pad.base = Some(pad.window.1);
+            pad.window = (now, transit);
+        }
+        pad.window.1 = pad.window.1.min(transit);
+        let base = pad.base.map_or(transit, |base| base.min(transit));
+        pad.base = Some(base);
+        pad.buffer_ms = (pad.jitter_ms * 2.0).min(max_buffer_ms);
+
+        // 快照在发送后 基准延迟 + 缓冲 时输出，已经超过的立即输出
+        let delay = base + pad.buffer_ms - transit;
+        if delay < 0.0 {
+            pad.late += 1;
+        }
+        let due = now + Duration::from_secs_f64(delay.max(0.0) / 1000.0);
+        // 缓冲缩短时新快照可能早于排队的旧快照，旧快照不再输出
+        while pad.queue.back().is_some_and(|(at, _)| *at > due) {
+            pad.queue.pop_back();
+        }
+        pad.queue.push_back((due, state));
+    }
+
+    /// 旧的 GamepadButton、GamepadAxis 事件：修改观看者第一个手柄的状态后立即输出
+    pub fn receive_event(&mut self, viewer: Uuid, event: &InputEvent) {
+        let Some(pad) = self.pad(viewer, 0) else {
+            return;
+        };
+        pad.last_input = Instant::now();
+        let mut state = pad.queue.back().map_or_else(|| pad.output.clone(), |(_, state)| state.clone());
+        pad.queue.clear();
+        match event {
+            InputEvent::GamepadButton { button, pressed } => {
+                match GAMEPAD_BUTTONS.iter().position(|name| name == button) {
+                    // 扳机作为按键时全按或全松
+                    Some(index @ (6 | 7)) => state.triggers[index - 6] = if *pressed { 1.0 } else { 0.0 },
+                    Some(index) if *pressed => state.buttons |= 1 << index,
+                    Some(index) => state.buttons &= !(1 << index),
+                    None => debug!("Unknown gamepad button {:?}", button),
+                }
+            }
+            InputEvent::GamepadAxis { axis, value } => {
+                match GAMEPAD_AXES.iter().position(|name| name == axis) {
+                    Some(index @ 0..=3) => state.axes[index] = value.clamp(-1.0, 1.0),
+                    Some(index) => state.triggers[index - 4] = value.clamp(0.0, 1.0),
+                    None => debug!("Unknown gamepad axis {:?}", axis),
+                }
+            }
+            _ => return,
+        }
+        pad.apply(state);
+    }
+
+    /// 下一次需要调用 tick 的时间：最早的排队快照，或最早闲置超时的手柄
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pads.values()
+            .filter_map(|pad| match pad.queue.front() {
+                Some((due, _)) => Some(*due),
+                None => (pad.output != GamepadState::neutral(pad.output.pad)).then(|| pad.last_input + IDLE_TIMEOUT),
+            })
+            .min()
+    }
+
+    /// 按顺序输出到期的快照，闲置超时的手柄回到中立状态
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        for ((viewer, index), pad) in &mut self.pads {
+            while pad.queue.front().is_some_and(|(due, _)| *due <= now) {
+                let (_, state) = pad.queue.pop_front().unwrap();
+                pad.apply(state);
+            }
+            if pad.queue.is_empty() && now.duration_since(pad.last_input) >= IDLE_TIMEOUT
+                && pad.output != GamepadState::neutral(*index)
+            {
+                debug!("Gamepad {} of viewer {} is idle, releasing it", index, viewer);
+                pad.apply(GamepadState::neutral(*index));
+            }
+        }
+    }
+
+    /// 移除不再满足条件 (失去授权) 的观看者的手柄，虚拟手柄回到中立状态后删除
+    pub fn retain(&mut self, mut keep: impl FnMut(&Uuid) -> bool) {
+        self.pads.retain(|(viewer, index), pad| {
+            if keep(viewer) {
+                return true;
+            }
+            pad.apply(GamepadState::neutral(*index));
+            info!("Removed gamepad {} of viewer {}", index, viewer);
+            false
+        });
+    }
+
+    /// 各手柄的延迟报告，按观看者和手柄排序
+    pub fn reports(&self) -> Vec<GamepadReport> {
+        let mut reports: Vec<GamepadReport> = self.pads.iter()
+            .map(|((viewer_id, pad), state)| GamepadReport {
+                viewer_id: *viewer_id,
+                pad: *pad,
+                latency_ms: state.latency_ms,
+                jitter_ms: state.jitter_ms,
+                buffer_ms: state.buffer_ms,
+                received: state.received,
+                lost: state.lost,
+                late: state.late,
+            })
+            .collect();
+        reports.sort_by_key(|report| (report.viewer_id, report.pad));
+        reports
+    }
+
+    fn pad(&mut self, viewer: Uuid, index: u8) -> Option<&mut Pad> {
+        if !self.pads.contains_key(&(viewer, index)) {
+            if self.pads.len() >= self.max_pads {
+                if !self.warned {
+                    warn!("Ignoring gamepad {} of viewer {}, {} gamepads are already connected", index, viewer, self.max_pads);
+                    self.warned = true;
+                }
+                return None;
+            }
+            // 创建失败时仍然统计延迟，只是不输出
+            let device = match VirtualGamepad::create(&format!("Remote Gamepad {}", self.pads.len() + 1)) {
+                Ok(device) => {
+                    info!("Created virtual gamepad for gamepad {} of viewer {}", index, viewer);
+                    Some(device)
+                }
+                Err(e) => {
+                    if !self.warned {
+                        warn!("Cannot create virtual gamepad: {}", e);
+                        self.warned = true;
+                    }
+                    None
+                }
+            };
+            let now = Instant::now();
+            self.pads.insert((viewer, index), Pad {
+                device,
+                output: GamepadState::neutral(index),
+                queue: VecDeque::new(),
+                last_seq: None,
+                last_input: now,
+                last_transit: None,
+                base: None,
+                window: (now, f64::MAX),
+                latency_ms: 0.0,
+                jitter_ms: 0.0,
+                buffer_ms: 0.0,
+                received: 0,
+                lost: 0,
+                late: 0,
+            });
+        }
+        self.pads.get_mut(&(viewer, index))
+    }
+}
+
+impl Pad {
+    fn apply(&mut self, state: GamepadState) {
+        if let Some(device) = &self.device {
+            if let Err(e) = device.update(&self.output, &state) {
+                debug!("Failed to update virtual gamepad: {}", e);
+            }
+        }
+        self.output = state;
+    }
+}
+
+/// Linux 上通过 uinput 创建的虚拟 Xbox 360 手柄，游戏和 SDL 按 xpad 驱动的布局识别
+///
+/// 需要 /dev/uinput 的写权限 (如把用户加入 input 组或添加 udev 规则)
+#[cfg(target_os = "linux")]
+struct VirtualGamepad {
+    file: std::fs::File,
+}
+
+#[cfg(target_os = "linux")]
+impl VirtualGamepad {
+    // uinput 的 ioctl 请求
+    const UI_DEV_CREATE: u64 = 0x5501;
+    const UI_DEV_DESTROY: u64 = 0x5502;
+    const UI_DEV_SETUP: u64 = 0x405c5503;
+    const UI_ABS_SETUP: u64 = 0x401c5504;
+    const UI_SET_EVBIT: u64 = 0x40045564;
+    const UI_SET_KEYBIT: u64 = 0x40045565;
+    const UI_SET_ABSBIT: u64 = 0x40045567;
+
+    // 事件类型
+    const EV_SYN: u16 = 0x00;
+    const EV_KEY: u16 = 0x01;
+    const EV_ABS: u16 = 0x03;
+
+    /// GAMEPAD_BUTTONS 对应的按键码，扳机和方向键使用 ABS_Z/ABS_RZ 和 ABS_HAT0X/ABS_HAT0Y
+    const BUTTONS: [Option<u16>; 17] = [
+        Some(0x130), Some(0x131), Some(0x133), Some(0x134), // BTN_A、BTN_B、BTN_X、BTN_Y
+        Some(0x136), Some(0x137), None, None,               // BTN_TL、BTN_TR
+        Some(0x13a), Some(0x13b), Some(0x13d), Some(0x13e), // BTN_SELECT、BTN_START、BTN_THUMBL、BTN_THUMBR
+        None, None, None, None,
+        Some(0x13c),                                        // BTN_MODE
+    ];
+    const DPAD: [usize; 4] = [12, 13, 14, 15]; // DPadUp、DPadDown、DPadLeft、DPadRight
+    const STICKS: [u16; 4] = [0x00, 0x01, 0x03, 0x04]; // ABS_X、ABS_Y、ABS_RX、ABS_RY
+    const TRIGGERS: [u16; 2] = [0x02, 0x05]; // ABS_Z、ABS_RZ
+    const HAT: [u16; 2] = [0x10, 0x11]; // ABS_HAT0X、ABS_HAT0Y
+
+    fn create(name: &str) -> Result<Self, String> {
+        use std::os::fd::AsRawFd;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open("/dev/uinput")
+            .map_err(|e| format!("cannot open /dev/uinput: {}", e))?;
+        let fd = file.as_raw_fd();
+        let ioctl = |request: u64, value: libc::c_ulong| -> Result<(), String> {
+            // 请求号和参数与内核 uinput 接口一致
+            match unsafe { libc::ioctl(fd, request as _, value) } {
+                -1 => Err(format!("uinput ioctl {:#x} failed: {}", request, std::io::Error::last_os_error())),
+                _ => Ok(()),
+            }
+        };
+
+        for kind in [Self::EV_SYN, Self::EV_KEY, Self::EV_ABS] {
+            ioctl(Self::UI_SET_EVBIT, kind as _)?;
+        }
+        for code in Self::BUTTONS.into_iter().flatten() {
+            ioctl(Self::UI_SET_KEYBIT, code as _)?;
+        }
+        let axes = Self::STICKS.map(|code| (code, -32768, 32767, 16, 128))
+            .into_iter()
+            .chain(Self::TRIGGERS.map(|code| (code, 0, 255, 0, 0)))
+            .chain(Self::HAT.map(|code| (code, -1, 1, 0, 0)));
+        for (code, minimum, maximum, fuzz, flat) in axes {
+            ioctl(Self::UI_SET_ABSBIT, code as _)?;
+            let setup = libc::uinput_abs_setup {
+                code,
+                absinfo: libc::input_absinfo { value: 0, minimum, maximum, fuzz, flat, resolution: 0 },
+            };
+            ioctl(Self::UI_ABS_SETUP, &setup as *const _ as _)?;
+        }
+
+        // 使用 Xbox 360 手柄的 ID，游戏和 SDL 自带它的按键映射
+        let mut setup = libc::uinput_setup {
+            id: libc::input_id { bustype: 0x03, vendor: 0x045e, product: 0x028e, version: 0x0110 }, // BUS_USB
+            name: [0; libc::UINPUT_MAX_NAME_SIZE],
+            ff_effects_max: 0,
+        };
+        for (target, byte) in setup.name.iter_mut().zip(name.bytes().take(libc::UINPUT_MAX_NAME_SIZE - 1)) {
+            *target = byte as libc::c_char;
+        }
+        ioctl(Self::UI_DEV_SETUP, &setup as *const _ as _)?;
+        ioctl(Self::UI_DEV_CREATE, 0)?;
+        Ok(Self { file })
+    }
+
+    fn event(kind: u16, code: u16, value: i32) -> libc::input_event {
+        // input_event 是纯数据结构，全零是合法值 (时间由内核填写)
+        let mut event: libc::input_event = unsafe { std::mem::zeroed() };
+        (event.type_, event.code, event.value) = (kind, code, value);
+        event
+    }
+
+    /// 写入与上次输出相比变化的按键和摇杆
+    fn update(&self, old: &GamepadState, new: &GamepadState) -> Result<(), String> {
+        use std::io::Write;
+
+        let mut events = Vec::new();
+        let mut push = |kind: u16, code: u16, value: i32| events.push(Self::event(kind, code, value));
+
+        for (index, code) in Self::BUTTONS.iter().enumerate() {
+            if let Some(code) = code {
+                if old.is_pressed(index) != new.is_pressed(index) {
+                    push(Self::EV_KEY, *code, new.is_pressed(index) as i32);
+                }
+            }
+        }
+        let stick = |value: f32| (value.clamp(-1.0, 1.0) * 32767.0).round() as i32;
+        for (index, code) in Self::STICKS.iter().enumerate() {
+            if stick(old.axes[index]) != stick(new.axes[index]) {
+                push(Self::EV_ABS, *code, stick(new.axes[index]));
+            }
+        }
+        let trigger = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as i32;
+        for (index, code) in Self::TRIGGERS.iter().enumerate() {
+            if trigger(old.triggers[index]) != trigger(new.triggers[index]) {
+                push(Self::EV_ABS, *code, trigger(new.triggers[index]));
+            }
+        }
+        let hat = |state: &GamepadState, negative: usize, positive: usize| {
+            state.is_pressed(positive) as i32 - state.is_pressed(negative) as i32
+        };
+        let [up, down, left, right] = Self::DPAD;
+        for (code, (negative, positive)) in Self::HAT.into_iter().zip([(left, right), (up, down)]) {
+            if hat(old, negative, positive) != hat(new, negative, positive) {
+                push(Self::EV_ABS, code, hat(new, negative, positive));
+            }
+        }
+        if events.is_empty() {
+            return Ok(());
+        }
+        events.push(Self::event(Self::EV_SYN, 0, 0)); // SYN_REPORT
+
+        // 按字节写出连续的 input_event 数组
+        let bytes = unsafe {
+            std::slice::from_raw_parts(events.as_ptr() as *const u8, std::mem::size_of_val(events.as_slice()))
+        };
+        (&self.file).write_all(bytes).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for VirtualGamepad {
+    fn drop(&mut self) {
+        use std::os::fd::AsRawFd;
+        // 文件仍然打开；关闭文件时内核也会删除设备
+        unsafe { libc::ioctl(self.file.as_raw_fd(), Self::UI_DEV_DESTROY as _) };
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+struct VirtualGamepad;
+
+#[cfg(not(target_os = "linux"))]
+impl VirtualGamepad {
+    fn create(_name: &str) -> Result<Self, String> {
+        Err("virtual gamepads are not supported on this platform".to_string())
+    }
+
+    fn update(&self, _old: &GamepadState, _new: &GamepadState) -> Result<(), String> {
+        Ok(())
+    }
+}
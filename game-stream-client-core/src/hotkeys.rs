@@ -0,0 +1,380 @@
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use game_stream_common::{AudioSource, CaptureConfig, HotkeyConfig, StreamResult, StreamError};
+use crate::client::ClientControls;
+use crate::source_control;
+
+/// 同一快捷键在这个间隔内的重复触发只处理一次 (X11 按住不放时会自动重复按键)
+const REPEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// X11 修饰键掩码
+const MOD_SHIFT: u32 = 0x01;
+const MOD_LOCK: u32 = 0x02; // Caps Lock
+const MOD_CONTROL: u32 = 0x04;
+const MOD_ALT: u32 = 0x08; // Mod1
+const MOD_NUM_LOCK: u32 = 0x10; // Mod2
+const MOD_SUPER: u32 = 0x40; // Mod4
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HotkeyAction {
+    StartStop,
+    Pause,
+    Mute,
+    SaveReplay,
+    ToggleSource(usize), // Hotkeys::sources 中的序号
+}
+
+impl HotkeyAction {
+    fn id(self) -> String {
+        match self {
+            HotkeyAction::StartStop => "start-stop".to_string(),
+            HotkeyAction::Pause => "pause".to_string(),
+            HotkeyAction::Mute => "mute".to_string(),
+            HotkeyAction::SaveReplay => "save-replay".to_string(),
+            HotkeyAction::ToggleSource(index) => format!("toggle-source-{}", index),
+        }
+    }
+
+    fn description(self, sources: &[String]) -> String {
+        match self {
+            HotkeyAction::StartStop => "Start or stop streaming".to_string(),
+            HotkeyAction::Pause => "Pause or resume the stream".to_string(),
+            HotkeyAction::Mute => "Mute or unmute the microphone".to_string(),
+            HotkeyAction::SaveReplay => "Save the replay buffer".to_string(),
+            HotkeyAction::ToggleSource(index) => format!("Enable or disable {}", sources[index]),
+        }
+    }
+
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn from_id(id: &str) -> Option<Self> {
+        if let Some(index) = id.strip_prefix("toggle-source-") {
+            return index.parse().ok().map(HotkeyAction::ToggleSource);
+        }
+        [HotkeyAction::StartStop, HotkeyAction::Pause, HotkeyAction::Mute, HotkeyAction::SaveReplay].into_iter()
+            .find(|action| action.id() == id)
+    }
+}
+
+/// 解析后的组合键
+#[derive(Debug, Clone)]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+struct Hotkey {
+    text: String,     // 配置中的写法，用于日志
+    description: String,
+    modifiers: u32,   // X11 修饰键掩码
+    keysym: u32,
+    trigger: String,  // xdg 快捷键规范的写法，如 "CTRL+SHIFT+F9"，作为门户的建议按键
+}
+
+impl Hotkey {
+    fn parse(text: &str) -> StreamResult<Self> {
+        let invalid = |reason: String| StreamError::Config(format!("Invalid hotkey {:?}: {}", text, reason));
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let key = parts.pop().filter(|key| !key.is_empty())
+            .ok_or_else(|| invalid("missing key".to_string()))?;
+
+        let mut modifiers = 0;
+        let mut trigger = String::new();
+        for modifier in parts {
+            let (mask, name) = match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => (MOD_CONTROL, "CTRL"),
+                "shift" => (MOD_SHIFT, "SHIFT"),
+                "alt" => (MOD_ALT, "ALT"),
+                "super" | "win" | "meta" | "logo" => (MOD_SUPER, "LOGO"),
+                _ => return Err(invalid(format!("unknown modifier {:?}", modifier))),
+            };
+            modifiers |= mask;
+            trigger.push_str(name);
+            trigger.push('+');
+        }
+
+        let (keysym, name) = keysym(key).ok_or_else(|| invalid(format!("unknown key {:?}", key)))?;
+        trigger.push_str(&name);
+        Ok(Self { text: text.to_string(), description: String::new(), modifiers, keysym, trigger })
+    }
+}
+
+/// 按键名称对应的 X11 keysym 和 xkb 名称，远程输入也使用同样的按键名称
+pub fn keysym(key: &str) -> Option<(u32, String)> {
+    let lower = key.to_ascii_lowercase();
+    if let [c] = lower.as_bytes() {
+        if c.is_ascii_lowercase() || c.is_ascii_digit() {
+            return Some((*c as u32, (*c as char).to_string()));
+        }
+    }
+    if let Some(number) = lower.strip_prefix('f').and_then(|n| n.parse::<u32>().ok()) {
+        if (1..=24).contains(&number) {
+            return Some((0xffbe + number - 1, format!("F{}", number)));
+        }
+    }
+    let (keysym, name) = match lower.as_str() {
+        "space" => (0x0020, "space"),
+        "backspace" => (0xff08, "BackSpace"),
+        "tab" => (0xff09, "Tab"),
+        "enter" | "return" => (0xff0d, "Return"),
+        "escape" | "esc" => (0xff1b, "Escape"),
+        "left" => (0xff51, "Left"),
+        "up" => (0xff52, "Up"),
+        "right" => (0xff53, "Right"),
+        "down" => (0xff54, "Down"),
+        "shift" => (0xffe1, "Shift_L"),
+        "ctrl" | "control" => (0xffe3, "Control_L"),
+        "capslock" => (0xffe5, "Caps_Lock"),
+        "alt" => (0xffe9, "Alt_L"),
+        "super" | "win" | "meta" | "logo" => (0xffeb, "Super_L"),
+        "pause" => (0xff13, "Pause"),
+        "scrolllock" => (0xff14, "Scroll_Lock"),
+        "home" => (0xff50, "Home"),
+        "pageup" => (0xff55, "Prior"),
+        "pagedown" => (0xff56, "Next"),
+        "end" => (0xff57, "End"),
+        "print" | "printscreen" => (0xff61, "Print"),
+        "insert" => (0xff63, "Insert"),
+        "delete" => (0xffff, "Delete"),
+        _ => return None,
+    };
+    Some((keysym, name.to_string()))
+}
+
+/// 全局快捷键 - 注册系统级组合键，游戏全屏时也能开始/停止、暂停推流、静音麦克风、保存回放和开关视频源
+///
+/// X11 下在根窗口上抓取按键；Wayland 不允许应用直接抓取按键，通过 xdg-desktop-portal
+/// 的 GlobalShortcuts 接口注册，首次使用时由桌面环境弹出对话框确认按键
+pub struct Hotkeys {
+    bindings: Vec<(HotkeyAction, Hotkey)>,
+    mute_input: String,
+    sources: Vec<String>, // toggle_sources 中的源，按名称排序
+}
+
+impl Hotkeys {
+    /// 未开启或没有有效的快捷键时返回 None，无效的快捷键跳过并警告
+    pub fn new(config: &HotkeyConfig, capture: &CaptureConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+
+        // 排序后序号稳定，门户保存的快捷键在重启后仍对应同一个源
+        let mut sources: Vec<String> = config.toggle_sources.keys().cloned().collect();
+        sources.sort();
+
+        let mut bindings = Vec::new();
+        let toggles = sources.iter().enumerate()
+            .map(|(index, source)| (HotkeyAction::ToggleSource(index), &config.toggle_sources[source]));
+        for (action, text) in [
+            (HotkeyAction::StartStop, &config.start_stop),
+            (HotkeyAction::Pause, &config.pause),
+            (HotkeyAction::Mute, &config.mute),
+            (HotkeyAction::SaveReplay, &config.save_replay),
+        ].into_iter().chain(toggles) {
+            if text.trim().is_empty() {
+                continue;
+            }
+            match Hotkey::parse(text) {
+                Ok(hotkey) => bindings.push((action, Hotkey { description: action.description(&sources), ..hotkey })),
+                Err(e) => warn!("{}", e),
+            }
+        }
+        if bindings.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            bindings,
+            mute_input: config.mute_input.clone().unwrap_or_else(|| microphone_input(capture)),
+            sources,
+        })
+    }
+
+    /// 在后台注册快捷键，按下时操作 controls
+    pub fn spawn(self, controls: ClientControls) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        listen(self.bindings, sender);
+        tokio::spawn(dispatch(receiver, controls, self.mute_input, self.sources));
+    }
+}
+
+/// 静音快捷键默认控制的输入：第一个采集麦克风 (默认或指定输入设备) 的混音输入
+fn microphone_input(capture: &CaptureConfig) -> String {
+    let inputs = &capture.audio_mixer.inputs;
+    inputs.iter()
+        .find(|input| matches!(input.source, AudioSource::Default | AudioSource::Device { .. }))
+        .or(inputs.first())
+        .map_or_else(|| "default".to_string(), |input| input.name.clone())
+}
+
+async fn dispatch(
+    mut receiver: mpsc::UnboundedReceiver<HotkeyAction>,
+    controls: ClientControls,
+    mute_input: String,
+    sources: Vec<String>,
+) {
+    let mut last: Option<(HotkeyAction, Instant)> = None;
+    while let Some(action) = receiver.recv().await {
+        // 按住不放时持续刷新时间，只在第一次按下时切换
+        let repeated = last.is_some_and(|(previous, at)| previous == action && at.elapsed() < REPEAT_INTERVAL);
+        last = Some((action, Instant::now()));
+        if repeated {
+            continue;
+        }
+
+        match action {
+            HotkeyAction::StartStop => {
+                controls.stream.toggle_streaming();
+            }
+            HotkeyAction::Pause => {
+                controls.stream.toggle_paused();
+            }
+            HotkeyAction::Mute => match &controls.mixer {
+                Some(mixer) => {
+                    if let Err(e) = mixer.toggle_muted(&mute_input) {
+                        warn!("{}", e);
+                    }
+                }
+                None => warn!("Audio capture is disabled, nothing to mute"),
+            },
+            HotkeyAction::SaveReplay => match &controls.replay {
+                Some(replay) => replay.save(),
+                None => warn!("Replay buffer is disabled, enable it in [replay]"),
+            },
+            HotkeyAction::ToggleSource(index) => {
+                // 门户可能送来以前的配置中注册的序号
+                let Some(source) = sources.get(index) else {
+                    continue;
+                };
+                if let Err(e) = source_control::set_enabled(&controls, source, None) {
+                    warn!("{}", e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn listen(bindings: Vec<(HotkeyAction, Hotkey)>, sender: mpsc::UnboundedSender<HotkeyAction>) {
+    if crate::portal::is_wayland_session() {
+        tokio::spawn(async move {
+            if let Err(e) = listen_portal(bindings, sender).await {
+                warn!("Global hotkeys unavailable: {}", e);
+            }
+        });
+    } else {
+        // xcb 的事件等待是阻塞的，使用单独的线程
+        std::thread::spawn(move || {
+            if let Err(e) = listen_x11(bindings, sender) {
+                warn!("Global hotkeys unavailable: {}", e);
+            }
+        });
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn listen(_bindings: Vec<(HotkeyAction, Hotkey)>, _sender: mpsc::UnboundedSender<HotkeyAction>) {
+    warn!("Global hotkeys are not supported on this platform, use the console commands instead");
+}
+
+#[cfg(target_os = "linux")]
+fn listen_x11(bindings: Vec<(HotkeyAction, Hotkey)>, sender: mpsc::UnboundedSender<HotkeyAction>) -> Result<(), String> {
+    use xcb::x;
+
+    let (connection, screen) = xcb::Connection::connect(None)
+        .map_err(|e| format!("cannot connect to X server: {}", e))?;
+    let setup = connection.get_setup();
+    let root = setup.roots().nth(screen as usize).ok_or("X server has no screen")?.root();
+    let min_keycode = setup.min_keycode();
+    let max_keycode = setup.max_keycode();
+
+    let cookie = connection.send_request(&x::GetKeyboardMapping {
+        first_keycode: min_keycode,
+        count: max_keycode - min_keycode + 1,
+    });
+    let mapping = connection.wait_for_reply(cookie).map_err(|e| e.to_string())?;
+    let keysyms_per_keycode = mapping.keysyms_per_keycode().max(1) as usize;
+
+    let mut grabbed = Vec::new();
+    for (action, hotkey) in bindings {
+        let Some(index) = mapping.keysyms().chunks(keysyms_per_keycode).position(|keysyms| keysyms.contains(&hotkey.keysym)) else {
+            warn!("Hotkey {} is not available on the current keyboard layout", hotkey.text);
+            continue;
+        };
+        let keycode = min_keycode + index as u8;
+
+        // Caps Lock 和 Num Lock 打开时按键带有额外的修饰位，每种组合都要抓取
+        let result = [0, MOD_LOCK, MOD_NUM_LOCK, MOD_LOCK | MOD_NUM_LOCK].into_iter().try_for_each(|locks| {
+            connection.send_and_check_request(&x::GrabKey {
+                owner_events: false,
+                grab_window: root,
+                modifiers: x::ModMask::from_bits_truncate(hotkey.modifiers | locks),
+                key: keycode,
+                pointer_mode: x::GrabMode::Async,
+                keyboard_mode: x::GrabMode::Async,
+            })
+        });
+        match result {
+            Ok(()) => {
+                info!("Registered hotkey {} to {}", hotkey.text, hotkey.description.to_lowercase());
+                grabbed.push((action, keycode, hotkey.modifiers));
+            }
+            Err(e) => warn!("Cannot register hotkey {}, it may be taken by another application: {}", hotkey.text, e),
+        }
+    }
+    if grabbed.is_empty() {
+        return Ok(());
+    }
+
+    loop {
+        let event = connection.wait_for_event().map_err(|e| e.to_string())?;
+        let xcb::Event::X(x::Event::KeyPress(event)) = event else {
+            continue;
+        };
+        let modifiers = event.state().bits() & (MOD_SHIFT | MOD_CONTROL | MOD_ALT | MOD_SUPER);
+        let action = grabbed.iter()
+            .find(|(_, keycode, mask)| *keycode == event.detail() && *mask == modifiers)
+            .map(|(action, ..)| *action);
+        if let Some(action) = action {
+            if sender.send(action).is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+async fn listen_portal(bindings: Vec<(HotkeyAction, Hotkey)>, sender: mpsc::UnboundedSender<HotkeyAction>) -> StreamResult<()> {
+    use ashpd::desktop::global_shortcuts::{GlobalShortcuts, NewShortcut};
+    use futures::StreamExt;
+
+    let proxy = GlobalShortcuts::new().await.map_err(portal_error)?;
+    let session = proxy.create_session().await.map_err(portal_error)?;
+
+    let shortcuts: Vec<_> = bindings.iter()
+        .map(|(action, hotkey)| NewShortcut::new(action.id(), hotkey.description.as_str()).preferred_trigger(hotkey.trigger.as_str()))
+        .collect();
+    // 没有保存的绑定时门户在这里弹出对话框，用户可以确认或修改按键
+    let bound = proxy.bind_shortcuts(&session, &shortcuts, None).await
+        .map_err(portal_error)?
+        .response()
+        .map_err(portal_error)?;
+    for shortcut in bound.shortcuts() {
+        info!("Registered hotkey {} to {}", shortcut.trigger_description(), shortcut.description().to_lowercase());
+    }
+
+    let mut activated = std::pin::pin!(proxy.receive_activated().await.map_err(portal_error)?);
+    while let Some(event) = activated.next().await {
+        if let Some(action) = HotkeyAction::from_id(event.shortcut_id()) {
+            if sender.send(action).is_err() {
+                break;
+            }
+        }
+    }
+
+    // 会话关闭后快捷键随之注销
+    drop(session);
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn portal_error(e: ashpd::Error) -> StreamError {
+    StreamError::Internal(format!("Global shortcuts portal error: {}", e))
+}
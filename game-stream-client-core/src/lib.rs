@@ -0,0 +1,54 @@
+pub mod client;
+pub mod capture;
+pub mod audio_mixer;
+pub mod compositor;
+pub mod stream_control;
+pub mod bitrate;
+pub mod stats;
+pub mod stats_overlay;
+pub mod replay;
+pub mod snapshot;
+pub mod source_control;
+pub mod remote_input;
+pub mod gamepad;
+pub mod devices;
+pub mod profiles;
+pub mod region_picker;
+pub mod secrets;
+pub mod check;
+pub mod hotkeys;
+pub mod schedule;
+pub mod silence;
+#[cfg(feature = "control-api")]
+pub mod control_api;
+pub mod console;
+pub mod sink;
+#[cfg(feature = "testing")]
+pub mod loopback;
+mod encoder;
+mod pusher;
+mod proxy;
+mod failover;
+mod tls;
+#[cfg(target_os = "linux")]
+mod portal;
+mod window_capture;
+mod cursor;
+mod game_capture;
+mod tonemap;
+mod color_convert;
+mod frame_queue;
+mod packet_buffer;
+mod send_pacer;
+mod mosaic;
+mod pacing;
+mod overlay;
+mod audio_filter;
+mod audio_input;
+mod preview;
+mod test_source;
+
+pub use client::{ClientControls, ClientHandle, StreamingClient, StreamingClientBuilder};
+pub use sink::{FileSink, StreamSink};
+#[cfg(feature = "testing")]
+pub use loopback::LoopbackSink;
@@ -0,0 +1,134 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use game_stream_common::{
+    AudioConfig, ClientStats, EncodingConfig, LiveStream, MediaPacket, StreamError, StreamInfo, StreamManager,
+    StreamResult, StreamStatus, VideoConfig,
+};
+use crate::pusher::FlvPackager;
+use crate::sink::StreamSink;
+
+/// 测试用的回环传输：不经过网络，把编码数据直接发布到同一进程中的 StreamManager
+///
+/// 与 RTMP 推流一样按 FLV 标签体发布，服务器的 HLS 切片、WebRTC 等观看者收到的数据与真实推流相同；
+/// 不做流密钥认证和过载检查
+///
+/// ```ignore
+/// let stream_manager = server.stream_manager();
+/// let encoding = config.encoding.clone();
+/// let handle = StreamingClient::builder(config)
+///     .transport(move || Ok(Box::new(LoopbackSink::new(stream_manager.clone(), "test", &encoding)) as Box<dyn StreamSink>))
+///     .build().await?
+///     .spawn();
+/// ```
+pub struct LoopbackSink {
+    stream_manager: Arc<StreamManager>,
+    stream_key: String,
+    video_config: VideoConfig,
+    audio_config: AudioConfig,
+    packager: FlvPackager,
+    stream: Option<Arc<LiveStream>>,
+}
+
+impl LoopbackSink {
+    pub fn new(stream_manager: Arc<StreamManager>, stream_key: impl Into<String>, encoding_config: &EncodingConfig) -> Self {
+        let (video, audio) = (&encoding_config.video, &encoding_config.audio);
+        Self {
+            stream_manager,
+            stream_key: stream_key.into(),
+            video_config: VideoConfig {
+                width: video.width,
+                height: video.height,
+                fps: video.fps.round() as u32,
+                bitrate: video.bitrate,
+                codec: video.codec.clone(),
+            },
+            audio_config: AudioConfig {
+                sample_rate: audio.effective_sample_rate(),
+                channels: audio.channels,
+                bitrate: audio.bitrate,
+                codec: audio.codec.clone(),
+            },
+            packager: FlvPackager::new(encoding_config),
+            stream: None,
+        }
+    }
+}
+
+#[async_trait]
+impl StreamSink for LoopbackSink {
+    fn name(&self) -> &str {
+        "loopback"
+    }
+
+    async fn connect(&mut self) -> StreamResult<()> {
+        let stream_info = StreamInfo {
+            stream_id: Uuid::new_v4(),
+            stream_key: self.stream_key.clone(),
+            title: None,
+            description: None,
+            created_at: chrono::Utc::now(),
+            is_live: false,
+            viewer_count: 0,
+            video_config: self.video_config.clone(),
+            audio_config: self.audio_config.clone(),
+            thumbnail_url: None,
+            tags: Default::default(),
+        };
+        let stream = self.stream_manager.create_stream(self.stream_key.clone(), stream_info).await?;
+        stream.set_status(StreamStatus::Live).await;
+        self.stream = Some(stream);
+        self.packager.restart();
+        info!("Loopback stream {} published", self.stream_key);
+        Ok(())
+    }
+
+    async fn send(&mut self, packet: MediaPacket) -> StreamResult<()> {
+        let Some(stream) = &self.stream else {
+            return Err(StreamError::Network("Loopback stream is not published".to_string()));
+        };
+        match packet {
+            MediaPacket::Video { data, timestamp, is_keyframe, capture_time } => {
+                let messages = self.packager.video_messages(data, is_keyframe);
+                if messages.is_empty() {
+                    return Ok(());
+                }
+                let timestamp = self.packager.timestamp(timestamp) as u64;
+                for data in messages {
+                    stream.send_media_packet(MediaPacket::Video { data, timestamp, is_keyframe, capture_time }).await?;
+                }
+                Ok(())
+            }
+            MediaPacket::Audio { data, timestamp, capture_time } => {
+                let messages = self.packager.audio_messages(data);
+                if messages.is_empty() {
+                    return Ok(());
+                }
+                let timestamp = self.packager.timestamp(timestamp) as u64;
+                for data in messages {
+                    stream.send_media_packet(MediaPacket::Audio { data, timestamp, capture_time }).await?;
+                }
+                Ok(())
+            }
+            // 推流过程中的元数据包只有推流端统计，与 RTMP 的 onClientStats 一样交给直播流
+            MediaPacket::Metadata { data } => {
+                match serde_json::from_slice::<ClientStats>(&data) {
+                    Ok(stats) => stream.set_client_stats(stats).await,
+                    Err(e) => debug!("Ignoring loopback metadata: {}", e),
+                }
+                Ok(())
+            }
+        }
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        if let Some(stream) = self.stream.take() {
+            stream.set_status(StreamStatus::Stopped).await;
+            self.stream_manager.remove_stream(&self.stream_key).await;
+            info!("Loopback stream {} stopped", self.stream_key);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+use game_stream_common::MosaicLayout;
+
+/// 拼接后的画面 (RGBA)
+pub struct MosaicFrame {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+/// 把多个 RGBA 画面 (宽, 高, 数据) 拼接成一个画面，空白处为黑色
+///
+/// 每一列取该列最宽的画面，每一行取该行最高的画面，画面贴在单元格左上角
+pub fn compose(tiles: &[(u32, u32, &[u8])], layout: MosaicLayout, columns: Option<u32>) -> MosaicFrame {
+    let columns = match layout {
+        MosaicLayout::SideBySide => tiles.len().max(1),
+        MosaicLayout::Grid => columns
+            .map(|columns| columns.max(1) as usize)
+            .unwrap_or_else(|| (tiles.len() as f64).sqrt().ceil().max(1.0) as usize),
+    };
+    let rows = tiles.len().div_ceil(columns).max(1);
+
+    let mut column_widths = vec![0u32; columns];
+    let mut row_heights = vec![0u32; rows];
+    for (index, (width, height, _)) in tiles.iter().enumerate() {
+        column_widths[index % columns] = column_widths[index % columns].max(*width);
+        row_heights[index / columns] = row_heights[index / columns].max(*height);
+    }
+
+    let width: u32 = column_widths.iter().sum::<u32>().max(1);
+    let height: u32 = row_heights.iter().sum::<u32>().max(1);
+    let mut data = [0u8, 0, 0, 255].repeat((width * height) as usize);
+
+    for (index, (tile_width, tile_height, tile)) in tiles.iter().enumerate() {
+        let left: u32 = column_widths[..index % columns].iter().sum();
+        let top: u32 = row_heights[..index / columns].iter().sum();
+        let row_bytes = *tile_width as usize * 4;
+
+        for (row, source) in tile.chunks_exact(row_bytes).take(*tile_height as usize).enumerate() {
+            let offset = (((top as usize + row) * width as usize) + left as usize) * 4;
+            data[offset..offset + row_bytes].copy_from_slice(source);
+        }
+    }
+
+    MosaicFrame { width, height, data }
+}
@@ -0,0 +1,227 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use ab_glyph::{point, Font, FontVec, PxScale, ScaleFont};
+use bytes::Bytes;
+use tracing::{info, warn, debug};
+
+use game_stream_common::{StreamResult, StreamError};
+use crate::compositor::LayerFrame;
+
+/// 未指定字体时依次尝试的系统字体，优先选择包含中文字形的字体
+#[cfg(target_os = "linux")]
+const SYSTEM_FONTS: &[&str] = &[
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+];
+#[cfg(target_os = "windows")]
+const SYSTEM_FONTS: &[&str] = &[
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\simhei.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+#[cfg(target_os = "macos")]
+const SYSTEM_FONTS: &[&str] = &[
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/Helvetica.ttc",
+    "/Library/Fonts/Arial.ttf",
+];
+
+/// 未指定浏览器时在 PATH 中查找的可执行文件
+const BROWSERS: &[&str] = &["chromium", "chromium-browser", "google-chrome", "google-chrome-stable", "chrome", "msedge"];
+
+/// 文字叠加层 - 文字内容变化时重新绘制
+pub struct TextOverlay {
+    template: String,
+    font: FontVec,
+    scale: PxScale,
+    color: [u8; 4],
+    background: Option<[u8; 4]>,
+    text: String,
+    frame: Option<Arc<LayerFrame>>,
+}
+
+impl TextOverlay {
+    pub fn new(template: &str, font: Option<&str>, font_size: f32, color: [u8; 4], background: Option<[u8; 4]>) -> StreamResult<Self> {
+        let (path, data) = match font {
+            Some(path) => (path.to_string(), std::fs::read(path)
+                .map_err(|e| StreamError::Config(format!("Failed to read font {}: {}", path, e)))?),
+            None => SYSTEM_FONTS.iter()
+                .find_map(|path| Some((path.to_string(), std::fs::read(path).ok()?)))
+                .ok_or_else(|| StreamError::Config("No system font found for text overlay, set `font` in the layer".to_string()))?,
+        };
+        let font = FontVec::try_from_vec(data)
+            .map_err(|e| StreamError::Config(format!("Invalid font {}: {}", path, e)))?;
+        debug!("Text overlay {:?} uses font {}", template, path);
+
+        Ok(Self {
+            template: template.to_string(),
+            font,
+            scale: PxScale::from(font_size.max(1.0)),
+            color,
+            background,
+            text: String::new(),
+            frame: None,
+        })
+    }
+
+    /// 替换占位符后返回文字画面，文字为空时返回 None
+    pub fn render(&mut self, fps: f32) -> Option<Arc<LayerFrame>> {
+        let now = chrono::Local::now();
+        let text = self.template
+            .replace("{time}", &now.format("%H:%M:%S").to_string())
+            .replace("{date}", &now.format("%Y-%m-%d").to_string())
+            .replace("{fps}", &format!("{:.0}", fps));
+        self.render_text(text)
+    }
+
+    /// 返回给定文字的画面 (不使用模板)，文字不变时复用上次的结果
+    pub fn render_text(&mut self, text: String) -> Option<Arc<LayerFrame>> {
+        if text != self.text || self.frame.is_none() {
+            self.frame = self.rasterize(&text).map(Arc::new);
+            self.text = text;
+        }
+        self.frame.clone()
+    }
+
+    fn rasterize(&self, text: &str) -> Option<LayerFrame> {
+        let font = self.font.as_scaled(self.scale);
+        let line_height = font.height() + font.line_gap();
+        let padding = if self.background.is_some() { (self.scale.y / 4.0).ceil() } else { 0.0 };
+
+        // 先排版得到每个字形的位置
+        let mut glyphs = Vec::new();
+        let mut text_width = 0f32;
+        let lines: Vec<&str> = text.lines().collect();
+        for (row, line) in lines.iter().enumerate() {
+            let baseline = padding + font.ascent() + row as f32 * line_height;
+            let mut caret = padding;
+            let mut previous = None;
+            for character in line.chars() {
+                let id = font.glyph_id(character);
+                if let Some(previous) = previous {
+                    caret += font.kern(previous, id);
+                }
+                glyphs.push(id.with_scale_and_position(self.scale, point(caret, baseline)));
+                caret += font.h_advance(id);
+                previous = Some(id);
+            }
+            text_width = text_width.max(caret - padding);
+        }
+
+        let width = (text_width + padding * 2.0).ceil() as u32;
+        let height = (lines.len() as f32 * line_height + padding * 2.0).ceil() as u32;
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let mut coverage = vec![0f32; width as usize * height as usize];
+        for glyph in glyphs {
+            let Some(outlined) = self.font.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outlined.px_bounds();
+            outlined.draw(|x, y, value| {
+                let (x, y) = (bounds.min.x as i32 + x as i32, bounds.min.y as i32 + y as i32);
+                if x >= 0 && y >= 0 && (x as u32) < width && (y as u32) < height {
+                    let index = y as usize * width as usize + x as usize;
+                    coverage[index] = coverage[index].max(value.min(1.0));
+                }
+            });
+        }
+
+        let background = self.background.unwrap_or([0, 0, 0, 0]);
+        let mut data = Vec::with_capacity(coverage.len() * 4);
+        for value in coverage {
+            data.extend_from_slice(&over(self.color, value, background));
+        }
+
+        Some(LayerFrame { width, height, data: Bytes::from(data) })
+    }
+}
+
+/// 文字颜色按覆盖率叠加到背景上 (非预乘 RGBA)
+fn over(color: [u8; 4], coverage: f32, background: [u8; 4]) -> [u8; 4] {
+    let text_alpha = color[3] as f32 / 255.0 * coverage;
+    let background_alpha = background[3] as f32 / 255.0;
+    let alpha = text_alpha + background_alpha * (1.0 - text_alpha);
+    if alpha <= 0.0 {
+        return [0, 0, 0, 0];
+    }
+
+    let channel = |index: usize| {
+        ((color[index] as f32 * text_alpha + background[index] as f32 * background_alpha * (1.0 - text_alpha)) / alpha).round() as u8
+    };
+    [channel(0), channel(1), channel(2), (alpha * 255.0).round() as u8]
+}
+
+/// 查找用于截取网页的浏览器
+pub fn find_browser(executable: Option<&str>) -> StreamResult<PathBuf> {
+    if let Some(executable) = executable {
+        return Ok(PathBuf::from(executable));
+    }
+
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    std::env::split_paths(&path)
+        .flat_map(|dir| BROWSERS.iter().map(move |name| dir.join(name).with_extension(std::env::consts::EXE_EXTENSION)))
+        .find(|candidate| candidate.is_file())
+        .ok_or_else(|| StreamError::Config(format!(
+            "No browser found for web page overlay (tried {}), set `executable` in the layer", BROWSERS.join(", ")
+        )))
+}
+
+/// 用无头浏览器定期截取网页，直到合成器停止
+///
+/// 网页背景为透明，截图按图层的 Alpha 叠加到画面上
+pub fn capture_web_page(
+    browser: &Path,
+    url: &str,
+    size: (u32, u32),
+    refresh_interval: Duration,
+    latest: &Mutex<Option<Arc<LayerFrame>>>,
+    running: &AtomicBool,
+) {
+    let screenshot = std::env::temp_dir().join(format!(
+        "game-stream-overlay-{}-{:x}.png", std::process::id(), latest as *const _ as usize
+    ));
+    info!("Rendering web page overlay {} at {}x{}", url, size.0, size.1);
+
+    while running.load(Ordering::Relaxed) {
+        let started = Instant::now();
+        let status = Command::new(browser)
+            .args(["--headless", "--disable-gpu", "--hide-scrollbars", "--mute-audio"])
+            .arg("--default-background-color=00000000")
+            .arg(format!("--window-size={},{}", size.0, size.1))
+            .arg(format!("--screenshot={}", screenshot.display()))
+            .arg(url)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+
+        match status.map_err(|e| e.to_string()).and_then(|_| image::open(&screenshot).map_err(|e| e.to_string())) {
+            Ok(page) => {
+                let page = page.to_rgba8();
+                *latest.lock().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(LayerFrame {
+                    width: page.width(),
+                    height: page.height(),
+                    data: Bytes::from(page.into_raw()),
+                }));
+                debug!("Rendered {} in {:?}", url, started.elapsed());
+            }
+            Err(e) => warn!("Failed to render web page overlay {}: {}", url, e),
+        }
+
+        // 分段等待，停止时尽快退出
+        while running.load(Ordering::Relaxed) && started.elapsed() < refresh_interval {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    let _ = std::fs::remove_file(&screenshot);
+}
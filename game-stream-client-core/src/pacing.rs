@@ -0,0 +1,141 @@
+use std::time::{Duration, Instant};
+use tracing::{info, warn, debug};
+
+/// 统计迟到和丢帧的周期
+const REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 落后超过该时长时认为时钟发生了跳变 (系统休眠、调试暂停等)，重新对齐而不计为丢帧
+const RESYNC_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// 一帧的截止时间
+pub struct FrameTick {
+    pub index: u64,     // 从开始捕获起的帧序号，跳过的帧也计数
+    pub timestamp: u64, // 按帧序号推算的时间戳 (毫秒)，帧间隔均匀
+}
+
+/// 帧调度器 - 按绝对截止时间捕获，避免相对睡眠带来的累积漂移
+///
+/// 第 n 帧的截止时间为 start + n / fps，帧率以分数表示，59.94 等 NTSC 帧率没有舍入误差。
+/// 捕获耗时超过一帧时跳过错过的截止时间，计为丢帧，而不是连续补帧
+pub struct FramePacer {
+    numerator: u64,   // 帧率 = numerator / denominator
+    denominator: u64,
+    start: Instant,
+    start_timestamp: u64,
+    start_index: u64, // start 对应的帧序号
+    next_index: u64,
+    late: u64,
+    dropped: u64,
+    report_since: Instant,
+    report_frames: u64,
+}
+
+impl FramePacer {
+    pub fn new(fps: f64) -> Self {
+        let (numerator, denominator) = frame_rate_ratio(fps);
+        info!("Frame pacing at {:.3} fps ({}/{})", numerator as f64 / denominator as f64, numerator, denominator);
+
+        let now = Instant::now();
+        Self {
+            numerator,
+            denominator,
+            start: now,
+            start_timestamp: chrono::Utc::now().timestamp_millis() as u64,
+            start_index: 0,
+            next_index: 0,
+            late: 0,
+            dropped: 0,
+            report_since: now,
+            report_frames: 0,
+        }
+    }
+
+    /// 等到下一帧的截止时间
+    pub async fn wait(&mut self) -> FrameTick {
+        let now = Instant::now();
+        let mut deadline = self.deadline(self.next_index);
+
+        if now > deadline {
+            let behind = now - deadline;
+            if behind >= RESYNC_THRESHOLD {
+                warn!("Capture fell {:?} behind schedule, resynchronizing", behind);
+                self.resync();
+                deadline = self.start;
+            } else {
+                // 已错过的截止时间直接跳过，只捕获最近的一帧
+                let missed = (behind.as_nanos() * self.numerator as u128 / (1_000_000_000 * self.denominator as u128)) as u64;
+                if missed > 0 {
+                    self.dropped += missed;
+                    self.next_index += missed;
+                    deadline = self.deadline(self.next_index);
+                }
+                if now > deadline {
+                    self.late += 1;
+                }
+            }
+        }
+
+        tokio::time::sleep_until(deadline.into()).await;
+
+        let index = self.next_index;
+        self.next_index += 1;
+        self.report_frames += 1;
+        self.report();
+
+        FrameTick {
+            index,
+            timestamp: self.start_timestamp + (self.offset(index).as_millis() as u64),
+        }
+    }
+
+    /// 长时间中断 (如捕获出错) 后从当前时刻重新开始计时，中断期间不计为丢帧
+    pub fn resync(&mut self) {
+        self.start = Instant::now();
+        self.start_timestamp = chrono::Utc::now().timestamp_millis() as u64;
+        self.start_index = self.next_index;
+    }
+
+    fn deadline(&self, index: u64) -> Instant {
+        self.start + self.offset(index)
+    }
+
+    /// 第 index 帧相对开始时刻的偏移，用整数运算避免浮点累积误差
+    fn offset(&self, index: u64) -> Duration {
+        let frames = (index - self.start_index) as u128;
+        let nanos = frames * 1_000_000_000 * self.denominator as u128 / self.numerator as u128;
+        Duration::from_nanos(nanos as u64)
+    }
+
+    fn report(&mut self) {
+        let elapsed = self.report_since.elapsed();
+        if elapsed < REPORT_INTERVAL {
+            return;
+        }
+
+        let fps = self.report_frames as f64 / elapsed.as_secs_f64();
+        if self.late > 0 || self.dropped > 0 {
+            warn!("Video capture: {:.2} fps, {} frames late, {} dropped in the last {:?}",
+                  fps, self.late, self.dropped, REPORT_INTERVAL);
+        } else {
+            debug!("Video capture: {:.2} fps", fps);
+        }
+        self.late = 0;
+        self.dropped = 0;
+        self.report_frames = 0;
+        self.report_since = Instant::now();
+    }
+}
+
+/// 把帧率转换为分数；接近 N * 1000/1001 的帧率 (23.976、29.97、59.94 等) 按 NTSC 帧率处理
+fn frame_rate_ratio(fps: f64) -> (u64, u64) {
+    let fps = if fps.is_finite() && fps > 0.0 { fps.clamp(1.0, 1000.0) } else { 30.0 };
+
+    let ntsc = (fps * 1.001).round();
+    if (fps - ntsc * 1000.0 / 1001.0).abs() < 0.005 && (fps - fps.round()).abs() > 0.005 {
+        return (ntsc as u64 * 1000, 1001);
+    }
+    if (fps - fps.round()).abs() < 1e-6 {
+        return (fps.round() as u64, 1);
+    }
+    ((fps * 1000.0).round() as u64, 1000)
+}
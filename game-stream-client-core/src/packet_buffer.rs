@@ -0,0 +1,76 @@
+use std::collections::VecDeque;
+
+use game_stream_common::MediaPacket;
+
+/// 推流断线期间缓存的编码数据 - 重连后从这里继续发送，观看端的画面不会中断
+///
+/// 超过容量时从最旧的数据开始丢弃到下一个视频关键帧 (音频一并丢弃)，
+/// 缓存中已没有关键帧时之后的视频帧也丢弃，直到新的关键帧到来，保证恢复后的第一帧视频可以解码
+pub struct PacketBuffer {
+    packets: VecDeque<MediaPacket>,
+    bytes: usize,
+    capacity: usize, // 字节
+    waiting_for_keyframe: bool,
+    dropped: u64, // 上次取出统计后丢弃的视频帧数
+}
+
+impl PacketBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            packets: VecDeque::new(),
+            bytes: 0,
+            capacity: capacity.max(1),
+            waiting_for_keyframe: false,
+            dropped: 0,
+        }
+    }
+
+    /// 缓存一个包，超过容量时丢弃最旧的 GOP
+    pub fn push(&mut self, packet: MediaPacket) {
+        if self.waiting_for_keyframe && matches!(packet, MediaPacket::Video { is_keyframe: false, .. }) {
+            self.dropped += 1;
+            return;
+        }
+        if is_keyframe(&packet) {
+            self.waiting_for_keyframe = false;
+        }
+        self.bytes += packet.size();
+        self.packets.push_back(packet);
+
+        while self.bytes > self.capacity {
+            let end = self.packets.iter().skip(1).position(is_keyframe).map_or(self.packets.len(), |index| index + 1);
+            for packet in self.packets.drain(..end) {
+                self.bytes -= packet.size();
+                if matches!(packet, MediaPacket::Video { .. }) {
+                    self.dropped += 1;
+                }
+            }
+            self.waiting_for_keyframe |= self.packets.is_empty();
+        }
+    }
+
+    /// 发送失败的包放回开头，重连后首先重发
+    pub fn push_front(&mut self, packet: MediaPacket) {
+        self.bytes += packet.size();
+        self.packets.push_front(packet);
+    }
+
+    pub fn pop(&mut self) -> Option<MediaPacket> {
+        let packet = self.packets.pop_front()?;
+        self.bytes -= packet.size();
+        Some(packet)
+    }
+
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    /// 取出上次调用以来丢弃的视频帧数
+    pub fn take_dropped(&mut self) -> u64 {
+        std::mem::take(&mut self.dropped)
+    }
+}
+
+fn is_keyframe(packet: &MediaPacket) -> bool {
+    matches!(packet, MediaPacket::Video { is_keyframe: true, .. })
+}
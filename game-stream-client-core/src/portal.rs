@@ -0,0 +1,93 @@
+use std::os::fd::OwnedFd;
+use ashpd::desktop::PersistMode;
+use ashpd::desktop::Session;
+use ashpd::desktop::screencast::{CursorMode, Screencast, SourceType};
+use ashpd::enumflags2::BitFlags;
+use tracing::{info, debug, warn};
+
+use game_stream_common::{PortalConfig, PortalSourceType, StreamResult, StreamError};
+
+/// 通过 xdg-desktop-portal 建立的屏幕录制会话
+///
+/// 门户弹出系统对话框由用户选择屏幕或窗口，之后画面以 PipeWire 流的形式提供
+pub struct PortalStream {
+    pub node_id: u32, // PipeWire 节点
+    pub size: Option<(u32, u32)>,
+    pub remote: OwnedFd, // PipeWire 远端连接
+    _session: Session<'static, Screencast<'static>>,
+    _proxy: Screencast<'static>,
+}
+
+impl PortalStream {
+    pub async fn open(config: &PortalConfig, source_type: PortalSourceType, capture_cursor: bool) -> StreamResult<Self> {
+        info!("Requesting screencast through xdg-desktop-portal ({:?})...", source_type);
+
+        let proxy = Screencast::new().await.map_err(portal_error)?;
+        let session = proxy.create_session().await.map_err(portal_error)?;
+
+        let types: BitFlags<SourceType> = match source_type {
+            PortalSourceType::Monitor => SourceType::Monitor.into(),
+            PortalSourceType::Window => SourceType::Window.into(),
+            PortalSourceType::Any => SourceType::Monitor | SourceType::Window,
+        };
+        let cursor_mode = if capture_cursor { CursorMode::Embedded } else { CursorMode::Hidden };
+        let (persist_mode, restore_token) = if config.persist {
+            (PersistMode::ExplicitlyRevoked, read_restore_token(&config.restore_token_file).await)
+        } else {
+            (PersistMode::DoNot, None)
+        };
+
+        proxy.select_sources(&session, cursor_mode, types, false, restore_token.as_deref(), persist_mode).await
+            .map_err(portal_error)?;
+
+        // 没有可用的授权时，门户在这里弹出选择对话框
+        let response = proxy.start(&session, None).await
+            .map_err(portal_error)?
+            .response()
+            .map_err(portal_error)?;
+
+        if config.persist {
+            if let Some(token) = response.restore_token() {
+                if let Err(e) = tokio::fs::write(&config.restore_token_file, token).await {
+                    warn!("Failed to save portal restore token to {}: {}", config.restore_token_file, e);
+                }
+            }
+        }
+
+        let stream = response.streams().first()
+            .ok_or_else(|| StreamError::Capture("No source selected in screencast portal".to_string()))?;
+        let node_id = stream.pipe_wire_node_id();
+        let size = stream.size().map(|(width, height)| (width.max(0) as u32, height.max(0) as u32));
+        let remote = proxy.open_pipe_wire_remote(&session).await.map_err(portal_error)?;
+
+        info!("Screencast portal granted PipeWire node {} ({:?})", node_id, size);
+
+        Ok(Self {
+            node_id,
+            size,
+            remote,
+            _session: session,
+            _proxy: proxy,
+        })
+    }
+}
+
+/// 当前是否为 Wayland 会话 (X11 方式的屏幕捕获不可用)
+pub fn is_wayland_session() -> bool {
+    std::env::var("XDG_SESSION_TYPE").is_ok_and(|session_type| session_type == "wayland")
+        || std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+async fn read_restore_token(path: &str) -> Option<String> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(token) => Some(token.trim().to_string()).filter(|token| !token.is_empty()),
+        Err(e) => {
+            debug!("No portal restore token at {}: {}", path, e);
+            None
+        }
+    }
+}
+
+fn portal_error(e: ashpd::Error) -> StreamError {
+    StreamError::Capture(format!("Screencast portal error: {}", e))
+}
@@ -0,0 +1,364 @@
+#[cfg(feature = "preview")]
+use std::num::NonZeroU32;
+#[cfg(feature = "preview")]
+use std::rc::Rc;
+#[cfg(feature = "preview")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "preview")]
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+#[cfg(feature = "preview")]
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+#[cfg(feature = "preview")]
+use tracing::info;
+use tracing::warn;
+#[cfg(feature = "preview")]
+use winit::application::ApplicationHandler;
+#[cfg(feature = "preview")]
+use winit::dpi::LogicalSize;
+#[cfg(feature = "preview")]
+use winit::event::WindowEvent;
+#[cfg(feature = "preview")]
+use winit::event_loop::{ActiveEventLoop, EventLoop, EventLoopProxy};
+#[cfg(feature = "preview")]
+use winit::window::{Window, WindowId};
+
+use game_stream_common::{HdrConfig, PreviewConfig};
+#[cfg(feature = "preview")]
+use game_stream_common::VideoPixelFormat;
+use crate::audio_mixer::MixerControl;
+#[cfg(feature = "preview")]
+use crate::audio_mixer::LEVEL_FLOOR_DB;
+use crate::capture::CapturedFrame;
+#[cfg(feature = "preview")]
+use crate::capture::FrameType;
+#[cfg(feature = "preview")]
+use crate::tonemap::ToneMapper;
+
+/// 标题栏中帧率和延迟的刷新间隔
+#[cfg(feature = "preview")]
+const READOUT_INTERVAL: Duration = Duration::from_secs(1);
+
+#[cfg(feature = "preview")]
+const TITLE: &str = "Game Stream Preview";
+
+#[cfg(feature = "preview")]
+type PreviewSurface = softbuffer::Surface<Rc<Window>, Rc<Window>>;
+
+/// 本地预览窗口 - 显示即将编码的画面 (场景合成之后)，推流前确认捕获的是正确的窗口
+///
+/// 窗口在单独线程的事件循环中绘制，只保留最新的一帧，绘制跟不上时跳过中间的帧；
+/// 关闭窗口不影响推流
+#[cfg(feature = "preview")]
+#[derive(Clone)]
+pub struct Preview {
+    shared: Arc<Shared>,
+    proxy: EventLoopProxy<()>,
+}
+
+#[cfg(feature = "preview")]
+struct Shared {
+    latest: Mutex<Option<CapturedFrame>>, // 等待绘制的帧
+    frames: AtomicU64, // 收到的视频帧数，用于计算帧率
+    closed: AtomicBool,
+}
+
+#[cfg(feature = "preview")]
+impl Preview {
+    /// 未开启预览或无法创建窗口 (如没有图形环境) 时返回 None；有混音时标题栏同时显示各输入的峰值电平
+    pub fn open(config: &PreviewConfig, hdr: &HdrConfig, frame_size: (u32, u32), mixer: Option<MixerControl>) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        if cfg!(target_os = "macos") {
+            warn!("The preview window is not supported on macOS yet");
+            return None;
+        }
+
+        let shared = Arc::new(Shared {
+            latest: Mutex::new(None),
+            frames: AtomicU64::new(0),
+            closed: AtomicBool::new(false),
+        });
+        let (width, height) = frame_size;
+        let window_size = LogicalSize::new(config.width.max(1), (config.width as u64 * height as u64 / width.max(1) as u64).max(1) as u32);
+        let tone_mapper = ToneMapper::new(hdr);
+
+        // 主线程运行 tokio，事件循环放在单独的线程
+        let (proxy_sender, proxy_receiver) = std::sync::mpsc::channel();
+        let thread_shared = shared.clone();
+        let spawned = std::thread::Builder::new().name("preview".to_string()).spawn(move || {
+            let event_loop = match build_event_loop() {
+                Ok(event_loop) => event_loop,
+                Err(e) => {
+                    warn!("Cannot open the preview window: {}", e);
+                    return;
+                }
+            };
+            let _ = proxy_sender.send(event_loop.create_proxy());
+
+            let mut app = PreviewApp {
+                shared: thread_shared.clone(),
+                window_size,
+                tone_mapper,
+                window: None,
+                current: None,
+                readout_since: Instant::now(),
+                readout_frames: 0,
+                latency: None,
+                mixer,
+            };
+            if let Err(e) = event_loop.run_app(&mut app) {
+                warn!("Preview window failed: {}", e);
+            }
+            thread_shared.closed.store(true, Ordering::Relaxed);
+        });
+        if let Err(e) = spawned {
+            warn!("Cannot start the preview thread: {}", e);
+            return None;
+        }
+
+        let proxy = proxy_receiver.recv().ok()?;
+        Some(Self { shared, proxy })
+    }
+
+    /// 窗口已被用户关闭
+    pub fn is_closed(&self) -> bool {
+        self.shared.closed.load(Ordering::Relaxed)
+    }
+
+    /// 显示一帧画面，音频帧和显存中的画面忽略
+    pub fn show(&self, frame: &CapturedFrame) {
+        if !matches!(frame.frame_type, FrameType::Video) || self.is_closed() {
+            return;
+        }
+        self.shared.frames.fetch_add(1, Ordering::Relaxed);
+        let previous = self.shared.latest.lock().unwrap_or_else(|e| e.into_inner()).replace(frame.clone());
+        // 上一帧还未绘制时已有待处理的重绘请求
+        if previous.is_none() {
+            let _ = self.proxy.send_event(());
+        }
+    }
+}
+
+/// 把经过的视频帧交给预览窗口，所有帧原样转发
+pub async fn tap_frames(
+    preview: Preview,
+    mut frame_receiver: mpsc::UnboundedReceiver<CapturedFrame>,
+    frame_sender: mpsc::UnboundedSender<CapturedFrame>,
+) {
+    while let Some(frame) = frame_receiver.recv().await {
+        preview.show(&frame);
+        if frame_sender.send(frame).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(feature = "preview")]
+fn build_event_loop() -> Result<EventLoop<()>, winit::error::EventLoopError> {
+    let mut builder = EventLoop::builder();
+    #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "openbsd", target_os = "netbsd", target_os = "dragonfly"))]
+    winit::platform::x11::EventLoopBuilderExtX11::with_any_thread(&mut builder, true);
+    #[cfg(target_os = "windows")]
+    winit::platform::windows::EventLoopBuilderExtWindows::with_any_thread(&mut builder, true);
+    builder.build()
+}
+
+#[cfg(feature = "preview")]
+struct PreviewApp {
+    shared: Arc<Shared>,
+    window_size: LogicalSize<u32>,
+    tone_mapper: ToneMapper,
+    window: Option<(Rc<Window>, PreviewSurface)>,
+    current: Option<CapturedFrame>, // 窗口大小变化时重新绘制
+    readout_since: Instant,
+    readout_frames: u64,
+    latency: Option<i64>, // 最近一帧从捕获到显示的毫秒数
+    mixer: Option<MixerControl>,
+}
+
+#[cfg(feature = "preview")]
+impl ApplicationHandler for PreviewApp {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.window.is_some() {
+            return;
+        }
+        let attributes = Window::default_attributes()
+            .with_title(TITLE)
+            .with_inner_size(self.window_size);
+        match create_window(event_loop, attributes) {
+            Ok(window) => {
+                info!("Preview window opened");
+                self.window = Some(window);
+            }
+            Err(e) => {
+                warn!("Cannot open the preview window: {}", e);
+                event_loop.exit();
+            }
+        }
+    }
+
+    fn user_event(&mut self, _event_loop: &ActiveEventLoop, _event: ()) {
+        if let Some((window, _)) = &self.window {
+            window.request_redraw();
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _window_id: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => {
+                info!("Preview window closed");
+                self.window = None;
+                event_loop.exit();
+            }
+            WindowEvent::RedrawRequested => self.redraw(),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(feature = "preview")]
+impl PreviewApp {
+    fn redraw(&mut self) {
+        if let Some(frame) = self.shared.latest.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            self.latency = Some(chrono::Utc::now().timestamp_millis() - frame.timestamp as i64);
+            self.current = Some(frame);
+        }
+        self.update_readout();
+
+        let (Some((window, surface)), Some(frame)) = (&mut self.window, &self.current) else {
+            return;
+        };
+        let size = window.inner_size();
+        let (Some(width), Some(height)) = (NonZeroU32::new(size.width), NonZeroU32::new(size.height)) else {
+            return;
+        };
+        if let Err(e) = surface.resize(width, height) {
+            warn!("Failed to resize the preview window: {}", e);
+            return;
+        }
+        let mut buffer = match surface.buffer_mut() {
+            Ok(buffer) => buffer,
+            Err(e) => {
+                warn!("Failed to draw the preview window: {}", e);
+                return;
+            }
+        };
+        render(frame, &self.tone_mapper, &mut buffer, size.width, size.height);
+        if let Err(e) = buffer.present() {
+            warn!("Failed to draw the preview window: {}", e);
+        }
+    }
+
+    /// 每秒在标题栏更新一次分辨率、帧率、延迟和声音电平
+    fn update_readout(&mut self) {
+        let elapsed = self.readout_since.elapsed();
+        if elapsed < READOUT_INTERVAL {
+            return;
+        }
+        let frames = self.shared.frames.load(Ordering::Relaxed);
+        let fps = (frames - self.readout_frames) as f64 / elapsed.as_secs_f64();
+        self.readout_frames = frames;
+        self.readout_since = Instant::now();
+
+        let (Some((window, _)), Some(frame)) = (&self.window, &self.current) else {
+            return;
+        };
+        let levels = self.mixer.as_ref().map_or(String::new(), |mixer| {
+            let levels: Vec<String> = mixer.levels().into_iter()
+                .map(|level| match level.peak_db {
+                    peak if peak <= LEVEL_FLOOR_DB => format!("{} silent", level.name),
+                    peak => format!("{} {:.0} dB", level.name, peak),
+                })
+                .collect();
+            format!(" | {}", levels.join(", "))
+        });
+        window.set_title(&format!(
+            "{} - {}x{}, {:.1} fps, {} ms latency{}", TITLE,
+            frame.width.unwrap_or_default(), frame.height.unwrap_or_default(), fps, self.latency.unwrap_or_default(), levels,
+        ));
+    }
+}
+
+#[cfg(feature = "preview")]
+fn create_window(event_loop: &ActiveEventLoop, attributes: winit::window::WindowAttributes) -> Result<(Rc<Window>, PreviewSurface), String> {
+    let window = Rc::new(event_loop.create_window(attributes).map_err(|e| e.to_string())?);
+    let context = softbuffer::Context::new(window.clone()).map_err(|e| e.to_string())?;
+    let surface = softbuffer::Surface::new(&context, window.clone()).map_err(|e| e.to_string())?;
+    Ok((window, surface))
+}
+
+/// 把画面按比例缩放 (最近邻) 到窗口中央，其余部分为黑色；YUV 画面只显示亮度
+#[cfg(feature = "preview")]
+fn render(frame: &CapturedFrame, tone_mapper: &ToneMapper, buffer: &mut [u32], width: u32, height: u32) {
+    buffer.fill(0);
+    let (Some(frame_width), Some(frame_height)) = (frame.width, frame.height) else {
+        return;
+    };
+    let mut format = frame.pixel_format.unwrap_or(VideoPixelFormat::Rgba32);
+    if frame_width == 0 || frame_height == 0 || frame.data.len() < format.frame_size(frame_width, frame_height) {
+        return;
+    }
+
+    let tone_mapped;
+    let data: &[u8] = if format.is_hdr() {
+        match tone_mapper.tone_map(&frame.data, frame_width, frame_height, format) {
+            Ok(data) => {
+                tone_mapped = data;
+                format = VideoPixelFormat::Rgba32;
+                &tone_mapped
+            }
+            Err(_) => return,
+        }
+    } else {
+        &frame.data
+    };
+
+    let scale = (width as f64 / frame_width as f64).min(height as f64 / frame_height as f64);
+    let scaled_width = ((frame_width as f64 * scale) as u32).clamp(1, width);
+    let scaled_height = ((frame_height as f64 * scale) as u32).clamp(1, height);
+    let (left, top) = ((width - scaled_width) / 2, (height - scaled_height) / 2);
+
+    for y in 0..scaled_height {
+        let source_y = (y as u64 * frame_height as u64 / scaled_height as u64) as usize;
+        let row = &mut buffer[((top + y) * width + left) as usize..][..scaled_width as usize];
+        for (x, pixel) in row.iter_mut().enumerate() {
+            let source_x = (x as u64 * frame_width as u64 / scaled_width as u64) as usize;
+            let index = source_y * frame_width as usize + source_x;
+            let (red, green, blue) = match format {
+                VideoPixelFormat::Rgba32 => (data[index * 4], data[index * 4 + 1], data[index * 4 + 2]),
+                VideoPixelFormat::Bgra32 => (data[index * 4 + 2], data[index * 4 + 1], data[index * 4]),
+                VideoPixelFormat::Rgb24 => (data[index * 3], data[index * 3 + 1], data[index * 3 + 2]),
+                VideoPixelFormat::Bgr24 => (data[index * 3 + 2], data[index * 3 + 1], data[index * 3]),
+                // 亮度平面在最前面
+                VideoPixelFormat::Yuv420p | VideoPixelFormat::Nv12 => (data[index], data[index], data[index]),
+                VideoPixelFormat::Rgb10a2 | VideoPixelFormat::Rgba16f => (0, 0, 0),
+            };
+            *pixel = (red as u32) << 16 | (green as u32) << 8 | blue as u32;
+        }
+    }
+}
+
+/// 未启用 preview 特性时没有预览窗口，开启预览的配置只输出警告
+#[cfg(not(feature = "preview"))]
+#[derive(Clone)]
+pub enum Preview {}
+
+#[cfg(not(feature = "preview"))]
+impl Preview {
+    pub fn open(config: &PreviewConfig, _hdr: &HdrConfig, _frame_size: (u32, u32), _mixer: Option<MixerControl>) -> Option<Self> {
+        if config.enabled {
+            warn!("The preview window is not available, the client was built without the preview feature");
+        }
+        None
+    }
+
+    pub fn is_closed(&self) -> bool {
+        match *self {}
+    }
+
+    pub fn show(&self, _frame: &CapturedFrame) {
+        match *self {}
+    }
+}
@@ -0,0 +1,67 @@
+use anyhow::{anyhow, Result};
+use toml::{Table, Value};
+use tracing::info;
+
+/// 配置文件中命名配置的表名
+const PROFILES_KEY: &str = "profiles";
+
+/// 未指定 --profile 时使用的配置名
+const DEFAULT_PROFILE_KEY: &str = "profile";
+
+/// 取出配置文件中的命名配置，把选中的配置合并到顶层
+///
+/// 命名配置写在 [profiles.<名称>] 下，结构与顶层相同，如 [profiles.720p30-lowcpu.encoding.video]，
+/// 只需写出与顶层不同的项：表逐项合并，其他值直接替换。name 为 None 时使用顶层的 profile 项，
+/// 也未设置时不应用命名配置
+pub fn apply(config: &mut Table, name: Option<&str>) -> Result<()> {
+    let profiles = take_profiles(config)?;
+    let default = config.remove(DEFAULT_PROFILE_KEY);
+    let name = match (name, &default) {
+        (Some(name), _) => name,
+        (None, Some(Value::String(name))) => name.as_str(),
+        (None, Some(_)) => return Err(anyhow!("\"{}\" must be the name of a profile", DEFAULT_PROFILE_KEY)),
+        (None, None) => return Ok(()),
+    };
+
+    let profile = profiles.get(name).ok_or_else(|| {
+        let names = profiles.keys().map(String::as_str).collect::<Vec<_>>();
+        if names.is_empty() {
+            anyhow!("Profile {:?} does not exist, no profiles are configured", name)
+        } else {
+            anyhow!("Profile {:?} does not exist (available: {})", name, names.join(", "))
+        }
+    })?;
+    let Value::Table(profile) = profile else {
+        return Err(anyhow!("Profile {:?} must be a table", name));
+    };
+
+    merge(config, profile.clone());
+    info!("Using profile {:?}", name);
+    Ok(())
+}
+
+/// 配置文件中的命名配置名称，以及 profile 项指定的默认配置
+pub fn names(config: &mut Table) -> Result<(Vec<String>, Option<String>)> {
+    let profiles = take_profiles(config)?;
+    let default = config.get(DEFAULT_PROFILE_KEY).and_then(Value::as_str).map(str::to_string);
+    Ok((profiles.keys().cloned().collect(), default))
+}
+
+fn take_profiles(config: &mut Table) -> Result<Table> {
+    match config.remove(PROFILES_KEY) {
+        Some(Value::Table(profiles)) => Ok(profiles),
+        Some(_) => Err(anyhow!("[{}] must be a table of named profiles", PROFILES_KEY)),
+        None => Ok(Table::new()),
+    }
+}
+
+fn merge(target: &mut Table, source: Table) {
+    for (key, value) in source {
+        match (target.get_mut(&key), value) {
+            (Some(Value::Table(target)), Value::Table(source)) => merge(target, source),
+            (_, value) => {
+                target.insert(key, value);
+            }
+        }
+    }
+}
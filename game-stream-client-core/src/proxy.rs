@@ -0,0 +1,152 @@
+use std::time::Duration;
+use base64::Engine;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::debug;
+
+use game_stream_common::{ProxyConfig, ProxyProtocol, StreamResult, StreamError};
+
+/// SOCKS5 协议常量 (RFC 1928、RFC 1929)
+const SOCKS_VERSION: u8 = 0x05;
+const SOCKS_AUTH_NONE: u8 = 0x00;
+const SOCKS_AUTH_PASSWORD: u8 = 0x02;
+const SOCKS_AUTH_UNACCEPTABLE: u8 = 0xff;
+const SOCKS_CMD_CONNECT: u8 = 0x01;
+const SOCKS_ATYP_IPV4: u8 = 0x01;
+const SOCKS_ATYP_DOMAIN: u8 = 0x03;
+const SOCKS_ATYP_IPV6: u8 = 0x04;
+
+/// 代理的 HTTP CONNECT 响应头部的长度上限
+const MAX_HTTP_RESPONSE: usize = 8192;
+
+/// 建立到 host:port 的 TCP 连接，配置了代理时经由代理建立隧道；整个过程不超过 timeout
+pub async fn connect(proxy: Option<&ProxyConfig>, host: &str, port: u16, timeout: Duration) -> StreamResult<TcpStream> {
+    let result = tokio::time::timeout(timeout, async {
+        let Some(proxy) = proxy else {
+            return Ok(TcpStream::connect((host, port)).await?);
+        };
+        debug!("Connecting to {}:{} through {:?} proxy {}:{}", host, port, proxy.protocol, proxy.host, proxy.port);
+        let mut stream = TcpStream::connect((proxy.host.as_str(), proxy.port)).await
+            .map_err(|e| StreamError::Network(format!("Cannot connect to proxy {}:{}: {}", proxy.host, proxy.port, e)))?;
+        match proxy.protocol {
+            ProxyProtocol::Socks5 => socks5_connect(&mut stream, proxy, host, port).await?,
+            ProxyProtocol::Http => http_connect(&mut stream, proxy, host, port).await?,
+        }
+        Ok(stream)
+    }).await;
+
+    match result {
+        Ok(result) => result,
+        Err(_) => Err(StreamError::Network(format!("Timed out connecting to {}:{}", host, port))),
+    }
+}
+
+/// SOCKS5 CONNECT，有用户名时提供用户名/密码认证，目标地址按域名发送 (由代理解析)
+async fn socks5_connect(stream: &mut TcpStream, proxy: &ProxyConfig, host: &str, port: u16) -> StreamResult<()> {
+    let methods: &[u8] = if proxy.username.is_some() { &[SOCKS_AUTH_NONE, SOCKS_AUTH_PASSWORD] } else { &[SOCKS_AUTH_NONE] };
+    let mut greeting = vec![SOCKS_VERSION, methods.len() as u8];
+    greeting.extend_from_slice(methods);
+    stream.write_all(&greeting).await?;
+
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != SOCKS_VERSION {
+        return Err(StreamError::Network("Proxy is not a SOCKS5 server".to_string()));
+    }
+    match reply[1] {
+        SOCKS_AUTH_NONE => {}
+        SOCKS_AUTH_PASSWORD => {
+            let username = proxy.username.as_deref().unwrap_or_default();
+            let password = proxy.password.as_deref().unwrap_or_default();
+            if username.len() > 255 || password.len() > 255 {
+                return Err(StreamError::Config("SOCKS5 username and password must be at most 255 bytes".to_string()));
+            }
+            let mut request = vec![0x01, username.len() as u8];
+            request.extend_from_slice(username.as_bytes());
+            request.push(password.len() as u8);
+            request.extend_from_slice(password.as_bytes());
+            stream.write_all(&request).await?;
+
+            stream.read_exact(&mut reply).await?;
+            if reply[1] != 0x00 {
+                return Err(StreamError::Network("SOCKS5 proxy rejected the username or password".to_string()));
+            }
+        }
+        SOCKS_AUTH_UNACCEPTABLE => {
+            return Err(StreamError::Network("SOCKS5 proxy requires an authentication method we do not support".to_string()));
+        }
+        method => return Err(StreamError::Network(format!("SOCKS5 proxy chose unknown authentication method {}", method))),
+    }
+
+    if host.len() > 255 {
+        return Err(StreamError::Config(format!("Host name too long for SOCKS5: {}", host)));
+    }
+    let mut request = vec![SOCKS_VERSION, SOCKS_CMD_CONNECT, 0x00, SOCKS_ATYP_DOMAIN, host.len() as u8];
+    request.extend_from_slice(host.as_bytes());
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    // 响应：VER REP RSV ATYP BND.ADDR BND.PORT
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header).await?;
+    if header[1] != 0x00 {
+        return Err(StreamError::Network(format!("SOCKS5 proxy could not connect to {}:{}: {}", host, port, socks5_error(header[1]))));
+    }
+    let address_size = match header[3] {
+        SOCKS_ATYP_IPV4 => 4,
+        SOCKS_ATYP_IPV6 => 16,
+        SOCKS_ATYP_DOMAIN => {
+            let mut size = [0u8; 1];
+            stream.read_exact(&mut size).await?;
+            size[0] as usize
+        }
+        atyp => return Err(StreamError::Network(format!("SOCKS5 proxy returned unknown address type {}", atyp))),
+    };
+    let mut bound = vec![0u8; address_size + 2];
+    stream.read_exact(&mut bound).await?;
+    Ok(())
+}
+
+fn socks5_error(code: u8) -> &'static str {
+    match code {
+        0x01 => "general failure",
+        0x02 => "connection not allowed by ruleset",
+        0x03 => "network unreachable",
+        0x04 => "host unreachable",
+        0x05 => "connection refused",
+        0x06 => "TTL expired",
+        0x07 => "command not supported",
+        0x08 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+/// HTTP CONNECT 隧道，有用户名时使用 Basic 认证
+async fn http_connect(stream: &mut TcpStream, proxy: &ProxyConfig, host: &str, port: u16) -> StreamResult<()> {
+    let authority = if host.contains(':') { format!("[{}]:{}", host, port) } else { format!("{}:{}", host, port) };
+    let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", authority);
+    if let Some(username) = &proxy.username {
+        let credentials = format!("{}:{}", username, proxy.password.as_deref().unwrap_or_default());
+        request.push_str(&format!(
+            "Proxy-Authorization: Basic {}\r\n", base64::engine::general_purpose::STANDARD.encode(credentials)
+        ));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // 逐字节读取响应头部，隧道建立后的数据属于 RTMP，不能多读
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_HTTP_RESPONSE {
+            return Err(StreamError::Network("HTTP proxy response too long".to_string()));
+        }
+        response.push(stream.read_u8().await?);
+    }
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+    if !status.starts_with('2') {
+        return Err(StreamError::Network(format!("HTTP proxy refused CONNECT to {}: {}", authority, status_line)));
+    }
+    Ok(())
+}
@@ -0,0 +1,1517 @@
+use anyhow::Result;
+use tokio::sync::mpsc;
+use tracing::{info, error, debug, warn};
+#[cfg(feature = "srt")]
+use std::sync::Arc;
+#[cfg(feature = "srt")]
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use rml_rtmp::chunk_io::ChunkSerializer;
+use rml_rtmp::handshake::{Handshake, HandshakeProcessResult, PeerType};
+use rml_rtmp::messages::RtmpMessage;
+use rml_rtmp::rml_amf0::Amf0Value;
+use rml_rtmp::sessions::{
+    ClientSession, ClientSessionConfig, ClientSessionError, ClientSessionEvent, ClientSessionResult,
+    PublishRequestType, StreamMetadata,
+};
+use rml_rtmp::time::RtmpTimestamp;
+use game_stream_common::{
+    ServerEndpoint, NetworkConfig, ReconnectConfig, EncodingConfig, StreamProtocol, MediaPacket, VideoCodec, AudioCodec,
+    RemoteInput, StreamResult, StreamError
+};
+#[cfg(feature = "srt")]
+use game_stream_common::{LossRecoveryConfig, FecLayout, FecArq};
+use game_stream_common::{aac, av1, flv, h264, hevc, ts};
+use crate::bitrate::{AdaptiveBitrate, BitrateControl, LinkSample};
+use crate::stats::StatsRecorder;
+use crate::packet_buffer::PacketBuffer;
+use crate::proxy;
+use crate::failover::FailoverRecorder;
+use crate::tls::TlsConnector;
+use crate::send_pacer::SendPacer;
+use crate::sink::{self, StreamSink};
+use async_trait::async_trait;
+use futures::FutureExt;
+use rand::Rng;
+
+/// 推流管理器
+pub struct PusherManager {
+    server_config: ServerEndpoint,
+    network_config: NetworkConfig,
+    pusher: Option<Box<dyn StreamSink>>,
+    outputs: Vec<Box<dyn StreamSink>>, // 附加的输出，与主推流收到相同的数据
+    adaptive_bitrate: Option<AdaptiveBitrate>,
+    stats: StatsRecorder,
+    report_stats: bool, // 把汇总的统计作为元数据包推送给服务器
+    failover: Option<FailoverRecorder>, // 断线录制
+}
+
+impl PusherManager {
+    pub async fn new(
+        server_config: &ServerEndpoint,
+        network_config: &NetworkConfig,
+        encoding_config: &EncodingConfig,
+        bitrate_control: BitrateControl,
+        stats: StatsRecorder,
+        report_stats: bool,
+    ) -> Result<Self> {
+        info!("Initializing pusher manager...");
+
+        let pusher = create_pusher(server_config, network_config, encoding_config, &stats).await?;
+        Ok(Self::with_pusher(pusher, server_config, network_config, encoding_config, bitrate_control, stats, report_stats))
+    }
+
+    /// 使用嵌入程序提供的传输代替 [server] 配置的推流协议，重连、自适应码率和断线录制与内置协议相同
+    pub fn with_pusher(
+        pusher: Box<dyn StreamSink>,
+        server_config: &ServerEndpoint,
+        network_config: &NetworkConfig,
+        encoding_config: &EncodingConfig,
+        bitrate_control: BitrateControl,
+        stats: StatsRecorder,
+        report_stats: bool,
+    ) -> Self {
+        Self {
+            server_config: server_config.clone(),
+            network_config: network_config.clone(),
+            pusher: Some(pusher),
+            outputs: Vec::new(),
+            adaptive_bitrate: AdaptiveBitrate::new(&network_config.adaptive_bitrate, &encoding_config.video, bitrate_control),
+            stats,
+            report_stats,
+            failover: FailoverRecorder::new(&network_config.failover, &server_config.stream_key, encoding_config),
+        }
+    }
+    
+    /// 把服务器转发来的观看者输入交给 sender，只有 RTMP 有回传通道
+    pub fn set_remote_input(&mut self, sender: mpsc::UnboundedSender<RemoteInput>) {
+        if let Some(pusher) = &mut self.pusher {
+            pusher.set_remote_input(sender);
+        }
+    }
+    
+    /// 附加一个输出 (如另一个推流平台或本地录制)，需要在 start_pushing 之前调用
+    pub fn add_output(&mut self, sink: Box<dyn StreamSink>) {
+        info!("Added output: {}", sink.name());
+        self.outputs.push(sink);
+    }
+    
+    /// 连接服务器后立即断开，用于推流前的检查；RTMP 会短暂发布一次
+    pub async fn probe(&mut self) -> StreamResult<()> {
+        let pusher = self.pusher.as_mut()
+            .ok_or_else(|| StreamError::Network("Pusher is not initialized".to_string()))?;
+        pusher.connect().await?;
+        pusher.close().await
+    }
+    
+    pub async fn start_pushing(
+        &mut self,
+        packet_receiver: mpsc::UnboundedReceiver<MediaPacket>,
+    ) -> StreamResult<()> {
+        info!("Starting pushing...");
+        let mut packet_receiver = sink::tee_outputs(std::mem::take(&mut self.outputs), packet_receiver);
+        
+        // 连接到服务器
+        if let Some(pusher) = &mut self.pusher {
+            let mut buffer = PacketBuffer::new(self.network_config.reconnect.buffer_size * 1024);
+            let mut outage = None;
+            match pusher.connect().await {
+                Ok(()) => info!("Connected to streaming server"),
+                // 开启断线录制时，一开始就连不上也先在本地录制，同时继续重连
+                Err(e) if self.failover.is_some() => {
+                    error!("Failed to connect to streaming server: {}", e);
+                    let current = outage.insert(Outage::new());
+                    current.attempts = 1;
+                    let result = recover(
+                        pusher.as_mut(), current, &self.network_config.reconnect, &mut packet_receiver, &mut buffer,
+                        &mut self.failover, &self.stats,
+                    ).await;
+                    if let Err(recover_err) = result {
+                        error!("Failed to reconnect: {}", recover_err);
+                        return Err(e);
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+
+            // 开始推流，开启上报时统计汇总后作为元数据包插入；断线期间缓存的数据优先发送
+            let mut stats_updates = self.stats.subscribe();
+            loop {
+                let resent = buffer.len() > 0; // 缓存中的包断线期间已写入断线录制
+                let packet = match buffer.pop() {
+                    Some(packet) => packet,
+                    None => tokio::select! {
+                        packet = packet_receiver.recv() => match packet {
+                            Some(packet) => packet,
+                            None => break,
+                        },
+                        Ok(()) = stats_updates.changed(), if self.report_stats => {
+                            let stats = stats_updates.borrow_and_update().clone();
+                            match stats.and_then(|stats| serde_json::to_vec(&stats).ok()) {
+                                Some(data) => MediaPacket::Metadata { data: data.into() },
+                                None => continue,
+                            }
+                        }
+                    },
+                };
+
+                let size = packet.size();
+                let started = Instant::now();
+                match pusher.send(packet.clone()).await {
+                    Ok(_) => {
+                        debug!("Packet pushed successfully");
+                        // 重连后连接稳定之前继续录制，推流进程反复断开时录制文件保持连续
+                        if let Some(failover) = &mut self.failover {
+                            if outage.as_ref().is_none_or(Outage::is_stable) {
+                                failover.finish().await;
+                            } else if !resent {
+                                failover.continue_recording(&packet).await;
+                            }
+                        }
+                        let sample = LinkSample {
+                            queue_depth: packet_receiver.len(),
+                            write_time: started.elapsed(),
+                            rtt: pusher.rtt(),
+                        };
+                        self.stats.record_sent(size, sample.queue_depth, sample.rtt);
+                        if let Some(adaptive_bitrate) = &mut self.adaptive_bitrate {
+                            adaptive_bitrate.observe(&sample);
+                        }
+                    }
+                    Err(e) => {
+                        error!("Failed to push packet: {}", e);
+
+                        // 重连期间继续缓存编码数据，连上后从发送失败的包开始继续发送
+                        if let Some(failover) = &mut self.failover {
+                            failover.write(&packet).await;
+                        }
+                        buffer.push_front(packet);
+                        let previous = outage.take();
+                        let current = outage.insert(Outage::resume(previous));
+                        let result = recover(
+                            pusher.as_mut(), current, &self.network_config.reconnect, &mut packet_receiver, &mut buffer,
+                            &mut self.failover, &self.stats,
+                        ).await;
+                        if let Err(reconnect_err) = result {
+                            error!("Failed to reconnect: {}", reconnect_err);
+                            return Err(e);
+                        }
+                    }
+                }
+            }
+
+            if let Some(failover) = &mut self.failover {
+                failover.finish().await;
+            }
+
+            // 断开连接
+            pusher.flush().await?;
+            pusher.close().await?;
+            info!("Disconnected from streaming server");
+        }
+        
+        Ok(())
+    }
+}
+
+/// 重连后连接保持这么久才算恢复，在此之前再次失败 (如推流进程连上后立即退出) 视为同一次断线，继续退避
+const STABLE_CONNECTION: Duration = Duration::from_secs(10);
+
+/// 一次断线的重连状态
+struct Outage {
+    started: Instant,
+    attempts: u32,
+    reconnected_at: Option<Instant>,
+}
+
+impl Outage {
+    fn new() -> Self {
+        Self { started: Instant::now(), attempts: 0, reconnected_at: None }
+    }
+
+    /// 连接刚恢复不久时继续上一次断线，否则开始新的断线
+    fn resume(previous: Option<Outage>) -> Self {
+        match previous {
+            Some(outage) if !outage.is_stable() => outage,
+            _ => Self::new(),
+        }
+    }
+
+    /// 重连后连接已经保持了 STABLE_CONNECTION
+    fn is_stable(&self) -> bool {
+        self.reconnected_at.is_some_and(|at| at.elapsed() >= STABLE_CONNECTION)
+    }
+}
+
+/// 断线后重连，结束后统计缓存溢出丢弃的帧；重连失败时关闭断线录制
+async fn recover(
+    pusher: &mut dyn StreamSink,
+    outage: &mut Outage,
+    config: &ReconnectConfig,
+    packet_receiver: &mut mpsc::UnboundedReceiver<MediaPacket>,
+    buffer: &mut PacketBuffer,
+    failover: &mut Option<FailoverRecorder>,
+    stats: &StatsRecorder,
+) -> StreamResult<()> {
+    let result = reconnect_with_backoff(pusher, outage, config, packet_receiver, buffer, failover).await;
+    let dropped = buffer.take_dropped();
+    if dropped > 0 {
+        warn!("Reconnect buffer overflowed, dropped {} video frames", dropped);
+        stats.record_dropped(dropped);
+    }
+    if result.is_err() {
+        if let Some(failover) = failover {
+            failover.finish().await;
+        }
+    }
+    result?;
+
+    stats.record_reconnect();
+    warn!("Reconnected to server, resuming with {} buffered packets", buffer.len());
+    Ok(())
+}
+
+/// 按指数退避 (带 ±25% 的随机抖动，避免多个客户端同时重连) 反复重连，直到成功或断线超过 timeout
+///
+/// 第一次立即重连，等待期间接收的编码数据放入缓存 (并写入断线录制)；
+/// 开启断线录制时超过 timeout 也不放弃，按 max_reconnect_interval 继续重连，直到推流结束
+async fn reconnect_with_backoff(
+    pusher: &mut dyn StreamSink,
+    outage: &mut Outage,
+    config: &ReconnectConfig,
+    packet_receiver: &mut mpsc::UnboundedReceiver<MediaPacket>,
+    buffer: &mut PacketBuffer,
+    failover: &mut Option<FailoverRecorder>,
+) -> StreamResult<()> {
+    let deadline = outage.started + Duration::from_secs(config.timeout);
+    let mut offline = false;
+    loop {
+        if outage.attempts > 0 {
+            let backoff = config.reconnect_interval
+                .saturating_mul(1u64 << (outage.attempts - 1).min(16))
+                .min(config.max_reconnect_interval);
+            let backoff = Duration::from_millis((backoff as f64 * rand::thread_rng().gen_range(0.75..1.25)) as u64);
+            if !offline && Instant::now() + backoff > deadline {
+                if failover.is_none() {
+                    return Err(StreamError::Network(format!(
+                        "Could not reconnect within {}s ({} attempts)", config.timeout, outage.attempts
+                    )));
+                }
+                warn!("Could not reconnect within {}s, recording locally and retrying every {} ms",
+                      config.timeout, config.max_reconnect_interval);
+                offline = true;
+            }
+
+            debug!("Reconnecting in {}ms", backoff.as_millis());
+            let sleep = tokio::time::sleep(backoff);
+            tokio::pin!(sleep);
+            loop {
+                tokio::select! {
+                    _ = &mut sleep => break,
+                    packet = packet_receiver.recv() => match packet {
+                        Some(packet) => stash(packet, buffer, failover).await,
+                        // 离线期间推流结束，数据已在本地录制中
+                        None if offline => return Err(StreamError::Network("Stream ended while offline".to_string())),
+                        None => {
+                            sleep.as_mut().await;
+                            break;
+                        }
+                    },
+                }
+            }
+        }
+
+        // 连接可能阻塞到超时，期间积压在通道中的数据先转入缓存
+        while let Ok(packet) = packet_receiver.try_recv() {
+            stash(packet, buffer, failover).await;
+        }
+        outage.attempts += 1;
+        match pusher.reconnect().await {
+            Ok(()) => {
+                outage.reconnected_at = Some(Instant::now());
+                return Ok(());
+            }
+            Err(e) => warn!("Reconnect attempt {} failed: {}", outage.attempts, e),
+        }
+    }
+}
+
+/// 断线期间收到的包放入缓存，开启断线录制时同时写入本地文件
+async fn stash(packet: MediaPacket, buffer: &mut PacketBuffer, failover: &mut Option<FailoverRecorder>) {
+    if let Some(failover) = failover {
+        failover.write(&packet).await;
+    }
+    buffer.push(packet);
+}
+
+/// RTMP 推流器
+///
+/// 由 rml_rtmp 完成握手、connect/createStream/publish 和消息分块，视频按 FLV 标签体封装：
+/// H.264 为 AVC (avcC 序列头 + 长度前缀的 NAL 单元)，AV1 和 HEVC 为 Enhanced RTMP；音频为 AAC
+pub struct RtmpPusher {
+    host: String,
+    port: u16,
+    stream_key: String,
+    app_name: String,
+    network_config: NetworkConfig,
+    metadata: StreamMetadata,
+    connection: Option<RtmpConnection>,
+    packager: FlvPackager,
+    last_timestamp: RtmpTimestamp, // 最近发送的时间戳，重连后的序列头使用
+    tls: Option<TlsConnector>, // RTMPS
+    pacer: SendPacer,
+    remote_input: Option<mpsc::UnboundedSender<RemoteInput>>,
+}
+
+impl RtmpPusher {
+    pub fn new(server_config: &ServerEndpoint, network_config: &NetworkConfig, encoding_config: &EncodingConfig) -> StreamResult<Self> {
+        let app_name = server_config.app_name.clone().unwrap_or_else(|| "live".to_string());
+        let tls = if server_config.tls { Some(TlsConnector::new(server_config)?) } else { None };
+        let packager = FlvPackager::new(encoding_config);
+        if !packager.has_audio() {
+            let audio = &encoding_config.audio;
+            warn!("{:?} audio at {} Hz cannot be sent over RTMP, the stream will have no audio",
+                  audio.codec, audio.effective_sample_rate());
+        }
+
+        Ok(Self {
+            host: server_config.host.clone(),
+            port: server_config.port,
+            stream_key: server_config.stream_key.clone(),
+            app_name,
+            network_config: network_config.clone(),
+            metadata: metadata(encoding_config, packager.has_audio()),
+            connection: None,
+            packager,
+            last_timestamp: RtmpTimestamp::new(0),
+            tls,
+            pacer: SendPacer::new(network_config, encoding_config.video.fps),
+            remote_input: None,
+        })
+    }
+
+    fn tc_url(&self) -> String {
+        let scheme = if self.tls.is_some() { "rtmps" } else { "rtmp" };
+        format!("{}://{}:{}/{}", scheme, self.host, self.port, self.app_name)
+    }
+}
+
+/// 把媒体包封装为 FLV 标签体，供 RTMP 推流器和回环传输使用
+///
+/// H.264 为 AVC (avcC 序列头 + 长度前缀的 NAL 单元)，AV1 和 HEVC 为 Enhanced RTMP；音频为 AAC
+#[derive(Clone)]
+pub(crate) struct FlvPackager {
+    video_codec: VideoCodec,
+    video_config: Option<Bytes>, // 已发送的视频解码器配置 (avcC/hvcC/av1C)
+    audio_config: Option<Bytes>, // AAC 的 AudioSpecificConfig，其他音频编码为 None
+    audio_header_sent: bool,
+    base_timestamp: Option<u64>, // 第一个包的时间戳，输出的时间戳从 0 开始
+}
+
+impl FlvPackager {
+    pub(crate) fn new(encoding_config: &EncodingConfig) -> Self {
+        let audio = &encoding_config.audio;
+        let audio_config = match audio.codec {
+            AudioCodec::Aac => aac::audio_specific_config(audio.effective_sample_rate(), audio.channels),
+            _ => None,
+        };
+        Self {
+            video_codec: encoding_config.video.codec.clone(),
+            video_config: None,
+            audio_config,
+            audio_header_sent: false,
+            base_timestamp: None,
+        }
+    }
+
+    /// 音频能否封装为 FLV (只支持 AAC)
+    pub(crate) fn has_audio(&self) -> bool {
+        self.audio_config.is_some()
+    }
+
+    /// 新连接需要重新发送序列头，时间戳重新从 0 开始
+    pub(crate) fn restart(&mut self) {
+        self.video_config = None;
+        self.audio_header_sent = false;
+        self.base_timestamp = None;
+    }
+
+    /// 断线前后的帧是连续的：保留时间戳起点和已发送的视频解码器配置，返回需要在新连接上补发的视频序列头；
+    /// AAC 序列头在下一个音频帧之前发送
+    pub(crate) fn resume(&mut self) -> Option<Bytes> {
+        self.audio_header_sent = false;
+        let config = self.video_config.as_ref()?;
+        Some(match self.video_codec {
+            VideoCodec::Av1 => flv::enhanced_video_sequence_start(flv::FOURCC_AV1, config),
+            VideoCodec::H265 => flv::enhanced_video_sequence_start(flv::FOURCC_HEVC, config),
+            _ => flv::avc_sequence_header(config),
+        })
+    }
+
+    /// 把编码后的视频包封装为 FLV 视频标签体
+    ///
+    /// 关键帧带来新的解码器配置 (avcC/hvcC/av1C) 时先发送序列头，在此之前的帧无法解码，直接丢弃
+    pub(crate) fn video_messages(&mut self, data: Bytes, is_keyframe: bool) -> Vec<Bytes> {
+        let (fourcc, config, frame) = match self.video_codec {
+            VideoCodec::H264 => (
+                None,
+                if is_keyframe { h264::codec_config_record(&data) } else { None },
+                h264::to_length_prefixed(&data),
+            ),
+            VideoCodec::Av1 => (
+                Some(flv::FOURCC_AV1),
+                av1::sequence_header(&data).and_then(av1::codec_config_record),
+                av1::remove_temporal_delimiters(&data),
+            ),
+            VideoCodec::H265 => (
+                Some(flv::FOURCC_HEVC),
+                if is_keyframe { hevc::codec_config_record(&data) } else { None },
+                hevc::to_length_prefixed(&data),
+            ),
+            _ => return Vec::new(),
+        };
+
+        let mut messages = Vec::new();
+        if let Some(config) = config {
+            if self.video_config.as_ref() != Some(&config) {
+                messages.push(match fourcc {
+                    Some(fourcc) => flv::enhanced_video_sequence_start(fourcc, &config),
+                    None => flv::avc_sequence_header(&config),
+                });
+                self.video_config = Some(config);
+            }
+        }
+        if self.video_config.is_some() {
+            messages.push(match fourcc {
+                Some(fourcc) => flv::enhanced_video_frame(fourcc, is_keyframe, &frame),
+                None => flv::avc_video_frame(is_keyframe, &frame),
+            });
+        }
+        messages
+    }
+
+    /// 把 AAC 帧封装为 FLV 音频标签体，第一帧前先发送 AAC sequence header
+    pub(crate) fn audio_messages(&mut self, data: Bytes) -> Vec<Bytes> {
+        let Some(config) = &self.audio_config else {
+            return Vec::new();
+        };
+        let mut messages = Vec::new();
+        if !self.audio_header_sent {
+            messages.push(flv::aac_sequence_header(config));
+            self.audio_header_sent = true;
+        }
+        messages.push(flv::aac_audio_frame(&data));
+        messages
+    }
+
+    /// 相对第一个包的时间戳 (毫秒，32 位回绕)
+    pub(crate) fn timestamp(&mut self, timestamp: u64) -> u32 {
+        let base = *self.base_timestamp.get_or_insert(timestamp);
+        timestamp.saturating_sub(base) as u32
+    }
+}
+
+/// onMetaData 中 AVC 和 AAC 的编码 ID
+const FLV_CODEC_AVC: u32 = 7;
+const FLV_CODEC_AAC: u32 = 10;
+
+/// onMetaData：分辨率、帧率、码率和编码 ID (Enhanced RTMP 的编码 ID 为 FourCC 的数值)
+fn metadata(encoding_config: &EncodingConfig, has_audio: bool) -> StreamMetadata {
+    let video = &encoding_config.video;
+    let audio = &encoding_config.audio;
+    let mut metadata = StreamMetadata::new();
+    metadata.video_width = Some(video.width);
+    metadata.video_height = Some(video.height);
+    metadata.video_frame_rate = Some(video.fps as f32);
+    metadata.video_bitrate_kbps = Some(video.bitrate);
+    metadata.video_codec_id = match video.codec {
+        VideoCodec::H264 => Some(FLV_CODEC_AVC),
+        VideoCodec::H265 => Some(u32::from_be_bytes(flv::FOURCC_HEVC)),
+        VideoCodec::Av1 => Some(u32::from_be_bytes(flv::FOURCC_AV1)),
+        _ => None,
+    };
+    if has_audio {
+        metadata.audio_codec_id = Some(FLV_CODEC_AAC);
+        metadata.audio_bitrate_kbps = Some(audio.bitrate);
+        metadata.audio_sample_rate = Some(audio.effective_sample_rate());
+        metadata.audio_channels = Some(audio.channels);
+        metadata.audio_is_stereo = Some(audio.channels == 2);
+    }
+    metadata.encoder = Some(format!("game-stream-client/{}", env!("CARGO_PKG_VERSION")));
+    metadata
+}
+
+/// 等待服务器响应时每次读取的缓冲区大小
+const READ_BUFFER_SIZE: usize = 4096;
+
+/// 发送 ping 测量 RTT 的间隔
+const RTMP_PING_INTERVAL: Duration = Duration::from_secs(2);
+
+/// RTMP 连接的底层流：TCP，或者 RTMPS 的 TLS
+trait RtmpIo: AsyncRead + AsyncWrite + Unpin + Send + Sync {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + Sync> RtmpIo for T {}
+
+/// 一条已进入发布状态的 RTMP 连接
+struct RtmpConnection {
+    stream: Box<dyn RtmpIo>,
+    session: ClientSession,
+    read_timeout: Duration,
+    pacer: SendPacer, // 写入的节奏和超时
+    ping: Option<(u32, Instant)>, // 等待响应的 ping 时间戳和发送时间
+    last_ping: Instant,
+    rtt: Option<Duration>,
+    data_serializer: ChunkSerializer, // 发送会话不支持的数据消息
+    remote_input: Option<mpsc::UnboundedSender<RemoteInput>>, // 服务器转发来的观看者输入
+}
+
+impl RtmpConnection {
+    /// 建立 TCP 连接 (RTMPS 再完成 TLS 握手) 并完成 RTMP 握手，握手后多读到的字节交给会话处理
+    async fn open(
+        host: &str,
+        port: u16,
+        network_config: &NetworkConfig,
+        tls: Option<&TlsConnector>,
+        pacer: SendPacer,
+        tc_url: String,
+    ) -> StreamResult<Self> {
+        let connect_timeout = Duration::from_secs(network_config.connection_timeout);
+        let stream = proxy::connect(network_config.proxy.as_ref(), host, port, connect_timeout).await?;
+        stream.set_nodelay(true)?;
+        let stream: Box<dyn RtmpIo> = match tls {
+            Some(tls) => Box::new(tls.connect(stream, connect_timeout).await?),
+            None => Box::new(stream),
+        };
+
+        let mut config = ClientSessionConfig::new();
+        config.tc_url = Some(tc_url);
+        // 会话在 connect 后把块大小设置为 chunk_size，这里的设置消息不需要再发送
+        let mut data_serializer = ChunkSerializer::new();
+        data_serializer.set_max_chunk_size(config.chunk_size, RtmpTimestamp::new(0))
+            .map_err(|e| StreamError::Network(format!("RTMP chunk size error: {}", e)))?;
+
+        let mut connection = Self {
+            stream,
+            session: ClientSession::new(config).map_err(session_error)?.0,
+            read_timeout: Duration::from_secs(network_config.read_timeout),
+            pacer,
+            ping: None,
+            last_ping: Instant::now(),
+            rtt: None,
+            data_serializer,
+            remote_input: None,
+        };
+
+        let mut handshake = Handshake::new(PeerType::Client);
+        let p0_and_p1 = handshake.generate_outbound_p0_and_p1()
+            .map_err(|e| StreamError::Network(format!("RTMP handshake failed: {}", e)))?;
+        connection.write(&p0_and_p1).await?;
+
+        let mut buffer = [0u8; READ_BUFFER_SIZE];
+        loop {
+            let size = connection.read(&mut buffer).await?;
+            match handshake.process_bytes(&buffer[..size])
+                .map_err(|e| StreamError::Network(format!("RTMP handshake failed: {}", e)))?
+            {
+                HandshakeProcessResult::InProgress { response_bytes } => connection.write(&response_bytes).await?,
+                HandshakeProcessResult::Completed { response_bytes, remaining_bytes } => {
+                    connection.write(&response_bytes).await?;
+                    let results = connection.session.handle_input(&remaining_bytes).map_err(session_error)?;
+                    connection.send(results).await?;
+                    return Ok(connection);
+                }
+            }
+        }
+    }
+
+    /// connect 到应用，再 createStream 并以 live 方式 publish
+    async fn publish(&mut self, app_name: &str, stream_key: &str) -> StreamResult<()> {
+        let result = self.session.request_connection(app_name.to_string()).map_err(session_error)?;
+        self.send(vec![result]).await?;
+        self.wait_for(|event| matches!(event, ClientSessionEvent::ConnectionRequestAccepted)).await?;
+
+        let result = self.session.request_publishing(stream_key.to_string(), PublishRequestType::Live)
+            .map_err(session_error)?;
+        self.send(vec![result]).await?;
+        self.wait_for(|event| matches!(event, ClientSessionEvent::PublishRequestAccepted)).await
+    }
+
+    /// 读取服务器消息直到出现期望的事件，connect 或 publish 被拒绝时返回错误
+    async fn wait_for(&mut self, expected: impl Fn(&ClientSessionEvent) -> bool) -> StreamResult<()> {
+        let mut buffer = [0u8; READ_BUFFER_SIZE];
+        loop {
+            let size = self.read(&mut buffer).await?;
+            let results = self.session.handle_input(&buffer[..size]).map_err(session_error)?;
+            for event in self.send(results).await? {
+                if let ClientSessionEvent::ConnectionRequestRejected { description } = &event {
+                    return Err(StreamError::Network(format!("RTMP connect rejected: {}", description)));
+                }
+                // 会话自己处理 NetStream.Publish.Start，其他发布状态 (如服务器过载时的 Rejected) 都是失败
+                if let ClientSessionEvent::UnhandleableOnStatusCode { code } = &event {
+                    if code.starts_with("NetStream.Publish.") {
+                        return Err(StreamError::Network(format!("RTMP publish rejected: {}", code)));
+                    }
+                }
+                if expected(&event) {
+                    return Ok(());
+                }
+                debug!("RTMP event while waiting: {:?}", event);
+            }
+        }
+    }
+
+    /// 处理服务器发来的确认、ping 等消息，不等待；定期发送 ping 测量 RTT
+    async fn poll_input(&mut self) -> StreamResult<()> {
+        let mut buffer = [0u8; READ_BUFFER_SIZE];
+        // 每次只轮询一次读取，没有数据时立即返回 (TLS 流没有 try_read)
+        while let Some(result) = self.stream.read(&mut buffer).now_or_never() {
+            match result {
+                Ok(0) => return Err(StreamError::Network("RTMP server closed the connection".to_string())),
+                Ok(size) => {
+                    let results = self.session.handle_input(&buffer[..size]).map_err(session_error)?;
+                    for event in self.send(results).await? {
+                        match event {
+                            ClientSessionEvent::PingResponseReceived { timestamp } => self.ping_response(timestamp),
+                            ClientSessionEvent::UnhandleableAmf0Command { command_name, additional_values, .. }
+                                if command_name == "onRemoteInput" => self.remote_input(&additional_values),
+                            ClientSessionEvent::UnhandleableAmf0Command { command_name, additional_values, .. }
+                                if command_name == "onClockSync" => self.clock_sync(&additional_values).await?,
+                            event => debug!("RTMP event: {:?}", event),
+                        }
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        // 服务器不响应 ping 时，间隔过后重新发送
+        if self.last_ping.elapsed() >= RTMP_PING_INTERVAL {
+            let (packet, timestamp) = self.session.send_ping_request().map_err(session_error)?;
+            self.write(&packet.bytes).await?;
+            self.ping = Some((timestamp.value, Instant::now()));
+            self.last_ping = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// 把推流端统计 (JSON) 作为连接级 (消息流 0) 的 onClientStats 数据消息发送
+    async fn send_client_stats(&mut self, stats: &[u8]) -> StreamResult<()> {
+        let stats: serde_json::Value = serde_json::from_slice(stats)?;
+        self.send_data(RtmpTimestamp::new(0), vec![Amf0Value::Utf8String("onClientStats".to_string()), amf0_value(&stats)]).await
+    }
+
+    /// 关键帧前发送 onCaptureTime 数据消息 [RTMP 时间戳, 采集时间 (Unix 毫秒)]，
+    /// 服务器据此换算之后每个包的采集时间，统计端到端延迟
+    async fn send_capture_time(&mut self, timestamp: RtmpTimestamp, capture_time: i64) -> StreamResult<()> {
+        self.send_data(timestamp, vec![
+            Amf0Value::Utf8String("onCaptureTime".to_string()),
+            Amf0Value::Number(timestamp.value as f64),
+            Amf0Value::Number(capture_time as f64),
+        ]).await
+    }
+
+    /// 服务器的时钟同步请求：onClockSync 命令，参数为服务器时间，
+    /// 立即以 onClockSync 数据消息回复 [服务器时间, 本机时间]，服务器据此计算两端的时钟差
+    async fn clock_sync(&mut self, values: &[Amf0Value]) -> StreamResult<()> {
+        let Some(Amf0Value::Number(server_time)) = values.first() else {
+            debug!("Ignoring onClockSync without server time");
+            return Ok(());
+        };
+        self.send_data(RtmpTimestamp::new(0), vec![
+            Amf0Value::Utf8String("onClockSync".to_string()),
+            Amf0Value::Number(*server_time),
+            Amf0Value::Number(chrono::Utc::now().timestamp_millis() as f64),
+        ]).await
+    }
+
+    /// 发送连接级 (消息流 0) 的 AMF0 数据消息
+    ///
+    /// 会话没有发送自定义数据消息的接口，由独立的序列化器以完整的块头发送；
+    /// 数据消息所在的块流只在连接时发送过 onMetaData，不会与会话的头部压缩冲突
+    async fn send_data(&mut self, timestamp: RtmpTimestamp, values: Vec<Amf0Value>) -> StreamResult<()> {
+        let payload = RtmpMessage::Amf0Data { values }.into_message_payload(timestamp, 0)
+            .map_err(|e| StreamError::Network(format!("RTMP message error: {}", e)))?;
+        let packet = self.data_serializer.serialize(&payload, true, false)
+            .map_err(|e| StreamError::Network(format!("RTMP message error: {}", e)))?;
+        self.write(&packet.bytes).await
+    }
+
+    /// 服务器转发的观看者输入：onRemoteInput 命令，参数为 RemoteInput 的 JSON
+    fn remote_input(&self, values: &[Amf0Value]) {
+        let Some(sender) = &self.remote_input else {
+            return;
+        };
+        let Some(Amf0Value::Utf8String(payload)) = values.first() else {
+            debug!("Ignoring onRemoteInput without payload");
+            return;
+        };
+        match serde_json::from_str::<RemoteInput>(payload) {
+            Ok(input) => {
+                let _ = sender.send(input);
+            }
+            Err(e) => debug!("Invalid remote input from server: {}", e),
+        }
+    }
+
+    fn ping_response(&mut self, timestamp: RtmpTimestamp) {
+        if let Some((sent, at)) = self.ping {
+            if sent == timestamp.value {
+                self.rtt = Some(at.elapsed());
+                self.ping = None;
+            }
+        }
+    }
+
+    /// 发送会话产生的数据包，返回其中的事件
+    async fn send(&mut self, results: Vec<ClientSessionResult>) -> StreamResult<Vec<ClientSessionEvent>> {
+        let mut events = Vec::new();
+        for result in results {
+            match result {
+                ClientSessionResult::OutboundResponse(packet) => self.write(&packet.bytes).await?,
+                ClientSessionResult::RaisedEvent(event) => events.push(event),
+                ClientSessionResult::UnhandleableMessageReceived(payload) => {
+                    debug!("Ignoring RTMP message type {}", payload.type_id);
+                }
+            }
+        }
+        Ok(events)
+    }
+
+    async fn read(&mut self, buffer: &mut [u8]) -> StreamResult<usize> {
+        match tokio::time::timeout(self.read_timeout, self.stream.read(buffer)).await {
+            Ok(Ok(0)) => Err(StreamError::Network("RTMP server closed the connection".to_string())),
+            Ok(result) => Ok(result?),
+            Err(_) => Err(StreamError::Network("Timed out waiting for RTMP server".to_string())),
+        }
+    }
+
+    async fn write(&mut self, data: &[u8]) -> StreamResult<()> {
+        self.pacer.write_all(&mut self.stream, data, "RTMP server").await
+    }
+}
+
+/// JSON 转换为 AMF0，对象转为 Object，数组转为 StrictArray
+fn amf0_value(value: &serde_json::Value) -> Amf0Value {
+    match value {
+        serde_json::Value::Null => Amf0Value::Null,
+        serde_json::Value::Bool(value) => Amf0Value::Boolean(*value),
+        serde_json::Value::Number(value) => Amf0Value::Number(value.as_f64().unwrap_or_default()),
+        serde_json::Value::String(value) => Amf0Value::Utf8String(value.clone()),
+        serde_json::Value::Array(values) => Amf0Value::StrictArray(values.iter().map(amf0_value).collect()),
+        serde_json::Value::Object(fields) => Amf0Value::Object(
+            fields.iter().map(|(key, value)| (key.clone(), amf0_value(value))).collect()
+        ),
+    }
+}
+
+fn session_error(error: ClientSessionError) -> StreamError {
+    StreamError::Network(format!("RTMP session error: {}", error))
+}
+
+#[async_trait]
+impl StreamSink for RtmpPusher {
+    fn name(&self) -> &str {
+        "RTMP"
+    }
+
+    async fn connect(&mut self) -> StreamResult<()> {
+        info!("Connecting to RTMP server: {}", self.tc_url());
+
+        let mut connection = RtmpConnection::open(
+            &self.host, self.port, &self.network_config, self.tls.as_ref(), self.pacer, self.tc_url(),
+        ).await?;
+        connection.publish(&self.app_name, &self.stream_key).await?;
+        connection.remote_input = self.remote_input.clone();
+        let result = connection.session.publish_metadata(&self.metadata).map_err(session_error)?;
+        connection.send(vec![result]).await?;
+
+        // 新连接需要重新发送序列头，时间戳重新从 0 开始
+        self.connection = Some(connection);
+        self.packager.restart();
+        info!("RTMP connection established, publishing to {}", self.app_name);
+        Ok(())
+    }
+    
+    async fn send(&mut self, packet: MediaPacket) -> StreamResult<()> {
+        let (messages, timestamp, is_video, keyframe_capture_time) = match packet {
+            MediaPacket::Video { data, timestamp, is_keyframe, capture_time } => {
+                debug!("Pushing video packet: {} bytes, ts: {}, keyframe: {}, captured: {:?}", 
+                       data.len(), timestamp, is_keyframe, capture_time);
+                (self.packager.video_messages(data, is_keyframe), timestamp, true, capture_time.filter(|_| is_keyframe))
+            }
+            MediaPacket::Audio { data, timestamp, capture_time } => {
+                debug!("Pushing audio packet: {} bytes, ts: {}, captured: {:?}", data.len(), timestamp, capture_time);
+                (self.packager.audio_messages(data), timestamp, false, None)
+            }
+            MediaPacket::Metadata { data } => {
+                // onMetaData 在连接时由编码配置生成，推流过程中的元数据包只有推流端统计
+                let Some(connection) = &mut self.connection else {
+                    return Err(StreamError::Network("Not connected to server".to_string()));
+                };
+                return connection.send_client_stats(&data).await;
+            }
+        };
+        if messages.is_empty() {
+            return Ok(());
+        }
+
+        let timestamp = RtmpTimestamp::new(self.packager.timestamp(timestamp));
+        self.last_timestamp = timestamp;
+        let Some(connection) = &mut self.connection else {
+            return Err(StreamError::Network("Not connected to server".to_string()));
+        };
+        connection.poll_input().await?;
+        if let Some(capture_time) = keyframe_capture_time {
+            connection.send_capture_time(timestamp, capture_time).await?;
+        }
+        for message in messages {
+            let result = if is_video {
+                connection.session.publish_video_data(message, timestamp, false)
+            } else {
+                connection.session.publish_audio_data(message, timestamp, false)
+            };
+            connection.send(vec![result.map_err(session_error)?]).await?;
+        }
+        
+        Ok(())
+    }
+    
+    fn rtt(&self) -> Option<Duration> {
+        self.connection.as_ref().and_then(|connection| connection.rtt)
+    }
+
+    async fn reconnect(&mut self) -> StreamResult<()> {
+        info!("Reconnecting to RTMP server...");
+        
+        let packager = self.packager.clone();
+        self.close().await?;
+        self.connect().await?;
+        
+        // 断线前后的帧是连续的：时间戳接着之前的继续，新的发布会话先补发视频序列头，
+        // 缓存的非关键帧也能直接发送
+        self.packager = packager;
+        if let Some(header) = self.packager.resume() {
+            if let Some(connection) = &mut self.connection {
+                let result = connection.session.publish_video_data(header, self.last_timestamp, false)
+                    .map_err(session_error)?;
+                connection.send(vec![result]).await?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    async fn close(&mut self) -> StreamResult<()> {
+        if let Some(mut connection) = self.connection.take() {
+            info!("Disconnecting from RTMP server");
+            
+            // 连接可能已经断开，尽力发送 deleteStream
+            if let Ok(results) = connection.session.stop_publishing() {
+                let _ = connection.send(results).await;
+            }
+            let _ = connection.stream.shutdown().await;
+            
+            info!("RTMP connection closed");
+        }
+        
+        Ok(())
+    }
+    fn set_remote_input(&mut self, sender: mpsc::UnboundedSender<RemoteInput>) {
+        self.remote_input = Some(sender);
+    }
+}
+
+/// 把媒体包封装为 MPEG-TS，供 SRT 等传输 TS 的推流器和断线录制使用
+///
+/// 视频为 Annex-B 的 H.264/HEVC，AAC 原始帧加上 ADTS 头部；时间戳改为相对第一个包
+pub struct TsPackager {
+    muxer: ts::TsMuxer,
+    video_type: u8,
+    audio_config: Option<Bytes>, // AAC 的 AudioSpecificConfig，其他音频编码为 None
+    base_timestamp: Option<u64>,
+}
+
+impl TsPackager {
+    pub fn new(encoding_config: &EncodingConfig) -> StreamResult<Self> {
+        let video_type = match encoding_config.video.codec {
+            VideoCodec::H264 => ts::STREAM_TYPE_H264,
+            VideoCodec::H265 => ts::STREAM_TYPE_HEVC,
+            ref codec => return Err(StreamError::Codec(format!("{:?} cannot be carried in MPEG-TS, use H264 or H265", codec))),
+        };
+        let audio = &encoding_config.audio;
+        let audio_config = match audio.codec {
+            AudioCodec::Aac => aac::audio_specific_config(audio.effective_sample_rate(), audio.channels),
+            _ => None,
+        };
+        if audio_config.is_none() {
+            warn!("{:?} audio at {} Hz cannot be carried in MPEG-TS, the stream will have no audio",
+                  audio.codec, audio.effective_sample_rate());
+        }
+
+        Ok(Self {
+            muxer: ts::TsMuxer::new(Some(video_type), audio_config.as_ref().map(|_| ts::STREAM_TYPE_AAC)),
+            video_type,
+            audio_config,
+            base_timestamp: None,
+        })
+    }
+
+    /// 新连接从 PAT/PMT 和 0 时间戳重新开始
+    pub(crate) fn restart(&mut self) {
+        self.muxer = ts::TsMuxer::new(Some(self.video_type), self.audio_config.as_ref().map(|_| ts::STREAM_TYPE_AAC));
+        self.base_timestamp = None;
+    }
+
+    /// 封装一个媒体包，没有可发送的数据时返回 None
+    pub fn packetize(&mut self, packet: MediaPacket) -> Option<Bytes> {
+        match packet {
+            MediaPacket::Video { data, timestamp, is_keyframe, .. } => {
+                let timestamp = self.relative_timestamp(timestamp);
+                Some(self.muxer.write_video(&data, timestamp, is_keyframe))
+            }
+            MediaPacket::Audio { data, timestamp, .. } => {
+                let frame = aac::to_adts(self.audio_config.as_ref()?, &data)?;
+                let timestamp = self.relative_timestamp(timestamp);
+                Some(self.muxer.write_audio(&frame, timestamp))
+            }
+            MediaPacket::Metadata { .. } => None,
+        }
+    }
+
+    fn relative_timestamp(&mut self, timestamp: u64) -> u64 {
+        let base = *self.base_timestamp.get_or_insert(timestamp);
+        timestamp.saturating_sub(base)
+    }
+}
+
+/// 负责网络传输的外部程序 (srt-live-transmit、ffmpeg)，TS 流写入它的标准输入
+struct TsSink {
+    child: tokio::process::Child,
+    stdin: tokio::process::ChildStdin,
+    pacer: SendPacer,
+    _secrets: Vec<SecretFile>, // 程序运行期间保留口令和流密钥文件
+}
+
+impl TsSink {
+    fn spawn(
+        mut command: tokio::process::Command,
+        install_hint: &str,
+        pacer: SendPacer,
+        secrets: Vec<SecretFile>,
+    ) -> StreamResult<Self> {
+        let program = command.as_std().get_program().to_string_lossy().into_owned();
+        let mut child = command
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| StreamError::Network(format!("Failed to start {} ({}): {}", program, install_hint, e)))?;
+        let stdin = child.stdin.take()
+            .ok_or_else(|| StreamError::Network(format!("{} has no input", program)))?;
+        Ok(Self { child, stdin, pacer, _secrets: secrets })
+    }
+
+    /// 写入 TS 数据，程序已经退出 (连接断开) 时返回错误
+    async fn write(&mut self, data: &[u8]) -> StreamResult<()> {
+        if let Some(status) = self.child.try_wait()? {
+            return Err(StreamError::Network(format!("Transport process exited ({})", status)));
+        }
+        self.pacer.write_all(&mut self.stdin, data, "transport process").await
+    }
+
+    /// 关闭标准输入，等待程序发送完缓冲的数据后退出，超时则结束进程
+    async fn close(self, timeout: Duration) {
+        let Self { mut child, stdin, .. } = self;
+        drop(stdin);
+        if tokio::time::timeout(timeout, child.wait()).await.is_err() {
+            let _ = child.kill().await;
+        }
+    }
+}
+
+/// 传给外部程序的口令或流密钥文件 (只有当前用户可读)
+///
+/// 命令行参数对本机所有用户可见，口令和流密钥通过 ffmpeg 的 `-/选项 文件` 从文件读取；丢弃时删除
+struct SecretFile {
+    path: std::path::PathBuf,
+}
+
+impl SecretFile {
+    fn create(secret: &str) -> StreamResult<Self> {
+        use std::io::Write;
+
+        let path = std::env::temp_dir().join(format!("game-stream-{}.secret", uuid::Uuid::new_v4()));
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let secret_file = Self { path };
+        options.open(&secret_file.path)?.write_all(secret.as_bytes())?;
+        Ok(secret_file)
+    }
+}
+
+impl Drop for SecretFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// URI 查询参数值的百分号编码，只保留 RFC 3986 的非保留字符
+#[cfg(feature = "srt")]
+fn encode_query_value(value: &str) -> String {
+    value.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// 把外部程序的输出逐行记录到日志 (程序只输出错误)
+fn spawn_log_reader(name: &'static str, output: impl tokio::io::AsyncRead + Unpin + Send + 'static) {
+    tokio::spawn(async move {
+        let mut lines = tokio::io::BufReader::new(output).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if !line.trim().is_empty() {
+                warn!("{}: {}", name, line.trim());
+            }
+        }
+    });
+}
+
+/// srt-live-transmit 每次从标准输入读取的字节数 (7 个 TS 包，SRT 的默认负载大小)
+#[cfg(feature = "srt")]
+const SRT_CHUNK_SIZE: u64 = 1316;
+
+/// 链路统计的输出间隔 (秒)
+#[cfg(feature = "srt")]
+const SRT_STATS_INTERVAL: u64 = 5;
+
+/// SRT 推流器 - caller 模式，TS 流通过 srt-live-transmit (libsrt 自带的工具) 发送
+///
+/// streamid 为推流密钥，接收端缓冲和加密口令来自 [server] 的 latency 和 passphrase。
+/// srt-live-transmit 定期输出的 JSON 统计 (RTT、带宽、丢包、重传) 记录到日志。
+/// srt-live-transmit 只能从命令行的 URI 读取流密钥和口令，设置了流密钥或口令时改用 ffmpeg (libsrt) 发送，
+/// 从临时文件读取，此时没有链路统计；FEC 只有 srt-live-transmit 支持，开启 FEC 时流密钥仍在命令行中
+#[cfg(feature = "srt")]
+pub struct SrtPusher {
+    server_url: String,
+    stream_key: String,
+    latency: u32,
+    passphrase: Option<String>,
+    network_config: NetworkConfig,
+    stats_chunks: u64, // 每发送多少个数据块输出一次统计
+    rtt: Arc<AtomicU32>, // 统计中最近的 RTT (微秒)，0 表示尚未测得
+    packet_filter: Option<String>, // FEC 包过滤器配置
+    stats: StatsRecorder,
+    packager: TsPackager,
+    pacer: SendPacer,
+    sink: Option<TsSink>,
+}
+
+#[cfg(feature = "srt")]
+impl SrtPusher {
+    pub fn new(
+        server_config: &ServerEndpoint,
+        network_config: &NetworkConfig,
+        encoding_config: &EncodingConfig,
+        stats: StatsRecorder,
+    ) -> StreamResult<Self> {
+        let server_url = format!("srt://{}:{}", server_config.host, server_config.port);
+        let packet_filter = srt_packet_filter(&network_config.loss_recovery)?;
+        if let Some(passphrase) = &server_config.passphrase {
+            if !(10..=79).contains(&passphrase.len()) {
+                return Err(StreamError::Config("SRT passphrase must be 10 to 79 characters".to_string()));
+            }
+            if packet_filter.is_some() {
+                return Err(StreamError::Config("SRT FEC cannot be combined with a passphrase".to_string()));
+            }
+        }
+        if packet_filter.is_some() && !server_config.stream_key.is_empty() {
+            warn!("SRT FEC needs srt-live-transmit, which reads the stream key from its command line (visible to other local users)");
+        }
+        let bitrate = (encoding_config.video.bitrate + encoding_config.audio.bitrate) as u64 * 1000 / 8;
+        
+        Ok(Self {
+            server_url,
+            stream_key: server_config.stream_key.clone(),
+            latency: server_config.latency,
+            passphrase: server_config.passphrase.clone(),
+            network_config: network_config.clone(),
+            stats_chunks: (bitrate * SRT_STATS_INTERVAL / SRT_CHUNK_SIZE).max(1),
+            rtt: Arc::new(AtomicU32::new(0)),
+            packet_filter,
+            stats,
+            packager: TsPackager::new(encoding_config)?,
+            pacer: SendPacer::new(network_config, encoding_config.video.fps),
+            sink: None,
+        })
+    }
+
+    /// srt-live-transmit 使用的 caller 模式 URI，参数名与 libsrt 的套接字选项相同，不包含口令
+    fn uri(&self) -> String {
+        let mut uri = format!(
+            "{}?mode=caller&transtype=live&streamid={}&latency={}&conntimeo={}",
+            self.server_url, encode_query_value(&self.stream_key), self.latency, self.network_config.connection_timeout * 1000,
+        );
+        if let Some(packet_filter) = &self.packet_filter {
+            uri.push_str(&format!("&packetfilter={}", encode_query_value(packet_filter)));
+        }
+        uri
+    }
+
+    /// ffmpeg 使用的 URI，参数名与 ffmpeg 的 libsrt 选项相同 (latency 单位为微秒)，不包含流密钥和口令
+    fn ffmpeg_uri(&self) -> String {
+        format!(
+            "{}?mode=caller&transtype=live&latency={}&connect_timeout={}",
+            self.server_url, self.latency as u64 * 1000, self.network_config.connection_timeout * 1000,
+        )
+    }
+
+    /// 是否用 srt-live-transmit 发送：没有需要保密的参数，或者开启了 FEC
+    fn use_srt_live_transmit(&self) -> bool {
+        self.passphrase.is_none() && (self.stream_key.is_empty() || self.packet_filter.is_some())
+    }
+}
+
+/// libsrt 内置 FEC 过滤器的配置串，未开启 FEC 时为 None
+#[cfg(feature = "srt")]
+fn srt_packet_filter(config: &LossRecoveryConfig) -> StreamResult<Option<String>> {
+    if !config.fec {
+        return Ok(None);
+    }
+    if config.fec_columns == 0 || config.fec_rows == 0 {
+        return Err(StreamError::Config("fec_columns and fec_rows must be at least 1".to_string()));
+    }
+    let layout = match config.fec_layout {
+        FecLayout::Even => "even",
+        FecLayout::Staircase => "staircase",
+    };
+    let arq = match config.fec_arq {
+        FecArq::Always => "always",
+        FecArq::OnReq => "onreq",
+        FecArq::Never => "never",
+    };
+    Ok(Some(format!("fec,cols:{},rows:{},layout:{},arq:{}", config.fec_columns, config.fec_rows, layout, arq)))
+}
+
+/// srt-live-transmit 的一次统计输出 (-pf:json)，计数为上次输出以来的增量
+#[cfg(feature = "srt")]
+#[derive(Debug, Default, serde::Deserialize)]
+struct SrtStats {
+    #[serde(default)]
+    link: SrtLinkStats,
+    #[serde(default)]
+    send: SrtSendStats,
+}
+
+#[cfg(feature = "srt")]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct SrtLinkStats {
+    rtt: f64,         // 毫秒
+    bandwidth: f64,   // 估计的链路带宽 (Mbps)
+}
+
+#[cfg(feature = "srt")]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct SrtSendStats {
+    packets: u64,
+    packets_unique: u64,       // 不含重传
+    packets_filter_extra: u64, // FEC 校验包
+    packets_lost: u64,
+    packets_retransmitted: u64,
+    packets_dropped: u64,
+    mbit_rate: f64,
+    ms_buf: u64,
+}
+
+/// 逐行读取 srt-live-transmit 的输出：JSON 为链路统计，其他为日志
+#[cfg(feature = "srt")]
+fn spawn_srt_output_reader(
+    output: impl tokio::io::AsyncRead + Unpin + Send + 'static,
+    latency: u32,
+    rtt: Arc<AtomicU32>,
+    stats_recorder: StatsRecorder,
+) {
+    tokio::spawn(async move {
+        let mut lines = tokio::io::BufReader::new(output).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let line = line.trim();
+            if !line.starts_with('{') {
+                if !line.is_empty() {
+                    debug!("srt-live-transmit: {}", line);
+                }
+                continue;
+            }
+            let Ok(stats) = serde_json::from_str::<SrtStats>(line) else {
+                continue;
+            };
+            let (link, send) = (&stats.link, &stats.send);
+            rtt.store((link.rtt * 1000.0) as u32, Ordering::Relaxed);
+            info!("SRT link: rtt {:.1} ms, bandwidth {:.1} Mbps, sending {:.2} Mbps, {} packets, {} lost, {} retransmitted, {} FEC, {} dropped, {} ms buffered",
+                  link.rtt, link.bandwidth, send.mbit_rate, send.packets, send.packets_lost,
+                  send.packets_retransmitted, send.packets_filter_extra, send.packets_dropped, send.ms_buf);
+
+            // 开销相对于不含重传的数据包，旧版 srt-live-transmit 没有 packetsUnique 时按总数估算
+            let unique = match send.packets_unique {
+                0 => send.packets.saturating_sub(send.packets_retransmitted),
+                unique => unique,
+            };
+            if unique > 0 {
+                stats_recorder.record_link_overhead(
+                    send.packets_filter_extra as f64 * 100.0 / unique as f64,
+                    send.packets_retransmitted as f64 * 100.0 / unique as f64,
+                );
+            }
+            if send.packets_dropped > 0 {
+                warn!("SRT dropped {} packets that could not be retransmitted within the {} ms latency",
+                      send.packets_dropped, latency);
+            }
+        }
+    });
+}
+
+#[cfg(feature = "srt")]
+#[async_trait]
+impl StreamSink for SrtPusher {
+    fn name(&self) -> &str {
+        "SRT"
+    }
+
+    async fn connect(&mut self) -> StreamResult<()> {
+        info!("Connecting to SRT server: {} (latency {} ms{}{})", self.server_url,
+              self.latency, if self.passphrase.is_some() { ", encrypted" } else { "" },
+              if self.packet_filter.is_some() { ", FEC" } else { "" });
+        
+        let sink = if self.use_srt_live_transmit() {
+            let mut command = tokio::process::Command::new("srt-live-transmit");
+            command
+                .arg(format!("-chunk:{}", SRT_CHUNK_SIZE))
+                .arg(format!("-s:{}", self.stats_chunks))
+                .args(["-pf:json", "-a:no", "-loglevel:error", "file://con"])
+                .arg(self.uri());
+            let mut sink = TsSink::spawn(command, "install the srt-tools package", self.pacer, Vec::new())?;
+            if let Some(stdout) = sink.child.stdout.take() {
+                spawn_srt_output_reader(stdout, self.latency, self.rtt.clone(), self.stats.clone());
+            }
+            if let Some(stderr) = sink.child.stderr.take() {
+                spawn_srt_output_reader(stderr, self.latency, self.rtt.clone(), self.stats.clone());
+            }
+            sink
+        } else {
+            // 输入已经是封装好的 TS，ffmpeg 只做复制
+            let mut command = tokio::process::Command::new("ffmpeg");
+            command
+                .args(["-hide_banner", "-nostdin", "-loglevel", "error"])
+                .args(["-f", "mpegts", "-i", "pipe:0", "-map", "0", "-c", "copy", "-f", "mpegts"]);
+            let mut secrets = Vec::new();
+            for (option, secret) in [("-/streamid", Some(self.stream_key.as_str())), ("-/passphrase", self.passphrase.as_deref())] {
+                let Some(secret) = secret.filter(|secret| !secret.is_empty()) else {
+                    continue;
+                };
+                let secret = SecretFile::create(secret)?;
+                command.arg(option).arg(&secret.path);
+                secrets.push(secret);
+            }
+            command.arg(self.ffmpeg_uri());
+            let mut sink = TsSink::spawn(
+                command, "SRT with a stream key or passphrase needs ffmpeg 7.1 or newer built with libsrt", self.pacer, secrets,
+            )?;
+            if let Some(stderr) = sink.child.stderr.take() {
+                spawn_log_reader("ffmpeg (SRT)", stderr);
+            }
+            sink
+        };
+        
+        self.packager.restart();
+        self.rtt.store(0, Ordering::Relaxed);
+        self.sink = Some(sink);
+        info!("SRT connection started");
+        Ok(())
+    }
+    
+    async fn send(&mut self, packet: MediaPacket) -> StreamResult<()> {
+        let Some(sink) = &mut self.sink else {
+            return Err(StreamError::Network("Not connected to server".to_string()));
+        };
+        if let Some(data) = self.packager.packetize(packet) {
+            sink.write(&data).await?;
+        }
+        Ok(())
+    }
+    
+    fn rtt(&self) -> Option<Duration> {
+        match self.rtt.load(Ordering::Relaxed) {
+            0 => None,
+            micros => Some(Duration::from_micros(micros as u64)),
+        }
+    }
+    
+    async fn reconnect(&mut self) -> StreamResult<()> {
+        self.close().await?;
+        self.connect().await?;
+        Ok(())
+    }
+    
+    async fn close(&mut self) -> StreamResult<()> {
+        if let Some(sink) = self.sink.take() {
+            info!("Disconnecting from SRT server");
+            sink.close(Duration::from_secs(self.network_config.write_timeout)).await;
+        }
+        Ok(())
+    }
+}
+
+/// RIST 推流器 - Main Profile，与 SRT 共用 TS 封装，TS 流通过带 librist 的 ffmpeg 发送
+///
+/// 丢包由接收端按 NACK 请求重传，[server] 的 latency 为重传缓冲 (毫秒)，
+/// 设置了 passphrase 时使用 AES-128 加密，口令通过口令文件传给 ffmpeg
+pub struct RistPusher {
+    server_url: String,
+    latency: u32,
+    passphrase: Option<String>,
+    network_config: NetworkConfig,
+    packager: TsPackager,
+    pacer: SendPacer,
+    sink: Option<TsSink>,
+}
+
+impl RistPusher {
+    pub fn new(server_config: &ServerEndpoint, network_config: &NetworkConfig, encoding_config: &EncodingConfig) -> StreamResult<Self> {
+        // librist 从地址的查询参数读取重传参数
+        let recovery = &network_config.loss_recovery;
+        let parameters: Vec<String> = [
+            ("rtt-min", recovery.rist_rtt_min),
+            ("rtt-max", recovery.rist_rtt_max),
+            ("reorder-buffer", recovery.rist_reorder_buffer),
+        ].into_iter()
+            .filter_map(|(name, value)| value.map(|value| format!("{}={}", name, value)))
+            .collect();
+        let mut server_url = format!("rist://{}:{}", server_config.host, server_config.port);
+        if !parameters.is_empty() {
+            server_url = format!("{}?{}", server_url, parameters.join("&"));
+        }
+
+        Ok(Self {
+            server_url,
+            latency: server_config.latency,
+            passphrase: server_config.passphrase.clone(),
+            network_config: network_config.clone(),
+            packager: TsPackager::new(encoding_config)?,
+            pacer: SendPacer::new(network_config, encoding_config.video.fps),
+            sink: None,
+        })
+    }
+}
+
+#[async_trait]
+impl StreamSink for RistPusher {
+    fn name(&self) -> &str {
+        "RIST"
+    }
+
+    async fn connect(&mut self) -> StreamResult<()> {
+        info!("Connecting to RIST receiver: {} (buffer {} ms{})", self.server_url, self.latency,
+              if self.passphrase.is_some() { ", encrypted" } else { "" });
+        
+        // 输入已经是封装好的 TS，ffmpeg 只做复制
+        let mut command = tokio::process::Command::new("ffmpeg");
+        command
+            .args(["-hide_banner", "-nostdin", "-loglevel", "error"])
+            .args(["-f", "mpegts", "-i", "pipe:0", "-map", "0", "-c", "copy", "-f", "mpegts"])
+            .args(["-rist_profile", "main", "-buffer_size"])
+            .arg(self.latency.to_string());
+        let secret = self.passphrase.as_deref().map(SecretFile::create).transpose()?;
+        if let Some(secret) = &secret {
+            command.arg("-/secret").arg(&secret.path).args(["-encryption", "128"]);
+        }
+        command.arg(&self.server_url);
+        let install_hint = if secret.is_some() {
+            "encrypted RIST needs ffmpeg 7.1 or newer built with librist"
+        } else {
+            "ffmpeg must be built with librist"
+        };
+        let mut sink = TsSink::spawn(command, install_hint, self.pacer, secret.into_iter().collect())?;
+        if let Some(stderr) = sink.child.stderr.take() {
+            spawn_log_reader("ffmpeg (RIST)", stderr);
+        }
+        
+        self.packager.restart();
+        self.sink = Some(sink);
+        info!("RIST connection started");
+        Ok(())
+    }
+    
+    async fn send(&mut self, packet: MediaPacket) -> StreamResult<()> {
+        let Some(sink) = &mut self.sink else {
+            return Err(StreamError::Network("Not connected to server".to_string()));
+        };
+        if let Some(data) = self.packager.packetize(packet) {
+            sink.write(&data).await?;
+        }
+        Ok(())
+    }
+    
+    async fn reconnect(&mut self) -> StreamResult<()> {
+        self.close().await?;
+        self.connect().await?;
+        Ok(())
+    }
+    
+    async fn close(&mut self) -> StreamResult<()> {
+        if let Some(sink) = self.sink.take() {
+            info!("Disconnecting from RIST receiver");
+            sink.close(Duration::from_secs(self.network_config.write_timeout)).await;
+        }
+        Ok(())
+    }
+}
+
+/// 创建推流器
+#[cfg_attr(not(feature = "srt"), allow(unused_variables))]
+async fn create_pusher(
+    server_config: &ServerEndpoint,
+    network_config: &NetworkConfig,
+    encoding_config: &EncodingConfig,
+    stats: &StatsRecorder,
+) -> Result<Box<dyn StreamSink>> {
+    // SRT/RIST 基于 UDP，SOCKS5 (CONNECT) 和 HTTP CONNECT 代理都只能转发 TCP
+    if network_config.proxy.is_some() && matches!(server_config.protocol, StreamProtocol::Srt | StreamProtocol::Rist) {
+        warn!("[network.proxy] only applies to RTMP, {:?} connects directly", server_config.protocol);
+    }
+    if server_config.tls && server_config.protocol != StreamProtocol::Rtmp {
+        warn!("tls only applies to RTMP, {:?} uses its own encryption (passphrase)", server_config.protocol);
+    }
+    if network_config.loss_recovery.fec && server_config.protocol != StreamProtocol::Srt {
+        warn!("[network.loss_recovery] fec only applies to SRT");
+    }
+    match server_config.protocol {
+        StreamProtocol::Rtmp => {
+            let pusher = RtmpPusher::new(server_config, network_config, encoding_config)?;
+            Ok(Box::new(pusher))
+        }
+        #[cfg(feature = "srt")]
+        StreamProtocol::Srt => {
+            let pusher = SrtPusher::new(server_config, network_config, encoding_config, stats.clone())?;
+            Ok(Box::new(pusher))
+        }
+        #[cfg(not(feature = "srt"))]
+        StreamProtocol::Srt => {
+            Err(anyhow::anyhow!("SRT support is not enabled in this build (srt feature)"))
+        }
+        StreamProtocol::Rist => {
+            let pusher = RistPusher::new(server_config, network_config, encoding_config)?;
+            Ok(Box::new(pusher))
+        }
+        StreamProtocol::Custom => {
+            Err(anyhow::anyhow!("Custom protocol needs a transport from the embedding program (StreamingClientBuilder::transport)"))
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use anyhow::{anyhow, Context, Result};
+use toml_edit::{DocumentMut, InlineTable, Item, Table};
+
+use game_stream_common::ClientConfig;
+
+/// 框选的屏幕区域
+#[derive(Debug, Clone, Copy)]
+pub struct PickedRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 把区域写入配置文件的 [capture.video_source]，保留文件中的其他内容和注释
+pub fn save_region(path: &str, region: PickedRegion) -> Result<()> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml::to_string(&ClientConfig::default())?,
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path)),
+    };
+    let mut document: DocumentMut = content.parse().with_context(|| format!("Failed to parse {}", path))?;
+
+    let mut source = InlineTable::new();
+    source.insert("x", (region.x as i64).into());
+    source.insert("y", (region.y as i64).into());
+    source.insert("width", (region.width as i64).into());
+    source.insert("height", (region.height as i64).into());
+
+    let capture = document.entry("capture").or_insert_with(|| Item::Table(Table::new()))
+        .as_table_like_mut()
+        .ok_or_else(|| anyhow!("[capture] in {} is not a table", path))?;
+
+    // 替换原有的视频源，保留表头前的注释
+    match capture.get_mut("video_source").and_then(Item::as_table_mut) {
+        Some(video_source) => video_source.clear(),
+        None => {
+            capture.insert("video_source", Item::Table(Table::new()));
+        }
+    }
+    capture.get_mut("video_source")
+        .and_then(Item::as_table_mut)
+        .ok_or_else(|| anyhow!("[capture.video_source] in {} is not a table", path))?
+        .insert("Region", Item::Value(source.into()));
+
+    std::fs::write(path, document.to_string()).with_context(|| format!("Failed to write {}", path))?;
+    Ok(())
+}
+
+/// 抓取鼠标，按住左键拖出一个矩形，右键取消
+///
+/// X11 上直接在根窗口上用异或方式绘制选框，不需要创建覆盖窗口
+#[cfg(target_os = "linux")]
+pub fn pick_region() -> Result<Option<PickedRegion>> {
+    use xcb::{x, Xid};
+
+    /// 光标字体中的十字光标 (XC_crosshair)
+    const XC_CROSSHAIR: u16 = 34;
+
+    let (connection, screen_num) = xcb::Connection::connect(None).context("Cannot connect to X server")?;
+    let root = connection.get_setup().roots().nth(screen_num as usize)
+        .ok_or_else(|| anyhow!("X screen {} not found", screen_num))?
+        .root();
+
+    let font: x::Font = connection.generate_id();
+    connection.send_request(&x::OpenFont { fid: font, name: b"cursor" });
+    let cursor: x::Cursor = connection.generate_id();
+    connection.send_request(&x::CreateGlyphCursor {
+        cid: cursor,
+        source_font: font,
+        mask_font: font,
+        source_char: XC_CROSSHAIR,
+        mask_char: XC_CROSSHAIR + 1,
+        fore_red: 0xffff, fore_green: 0xffff, fore_blue: 0xffff,
+        back_red: 0, back_green: 0, back_blue: 0,
+    });
+
+    let gc: x::Gcontext = connection.generate_id();
+    connection.send_request(&x::CreateGc {
+        cid: gc,
+        drawable: x::Drawable::Window(root),
+        value_list: &[
+            x::Gc::Function(x::Gx::Xor),
+            x::Gc::Foreground(0xffffff),
+            x::Gc::LineWidth(2),
+            x::Gc::SubwindowMode(x::SubwindowMode::IncludeInferiors),
+        ],
+    });
+
+    let cookie = connection.send_request(&x::GrabPointer {
+        owner_events: false,
+        grab_window: root,
+        event_mask: x::EventMask::BUTTON_PRESS | x::EventMask::BUTTON_RELEASE | x::EventMask::POINTER_MOTION,
+        pointer_mode: x::GrabMode::Async,
+        keyboard_mode: x::GrabMode::Async,
+        confine_to: x::Window::none(),
+        cursor,
+        time: x::CURRENT_TIME,
+    });
+    let grab = connection.wait_for_reply(cookie)?;
+    if grab.status() != x::GrabStatus::Success {
+        return Err(anyhow!("Cannot grab the pointer ({:?})", grab.status()));
+    }
+
+    let draw = |rectangle: &x::Rectangle| {
+        connection.send_request(&x::PolyRectangle {
+            drawable: x::Drawable::Window(root),
+            gc,
+            rectangles: std::slice::from_ref(rectangle),
+        });
+        connection.flush()
+    };
+
+    let mut start: Option<(i16, i16)> = None;
+    let mut drawn: Option<x::Rectangle> = None;
+    let result = loop {
+        let event = connection.wait_for_event()?;
+        let (x, y) = match &event {
+            xcb::Event::X(x::Event::ButtonPress(event)) if event.detail() == 1 => {
+                start = Some((event.root_x(), event.root_y()));
+                continue;
+            }
+            xcb::Event::X(x::Event::ButtonPress(_)) => break None,
+            xcb::Event::X(x::Event::MotionNotify(event)) => (event.root_x(), event.root_y()),
+            xcb::Event::X(x::Event::ButtonRelease(event)) if event.detail() == 1 => {
+                let Some(start) = start else {
+                    continue;
+                };
+                break Some(normalize(start, (event.root_x(), event.root_y())));
+            }
+            _ => continue,
+        };
+
+        let Some(start) = start else {
+            continue;
+        };
+        // 异或绘制：再画一次同样的矩形即可擦除
+        if let Some(previous) = drawn.take() {
+            draw(&previous)?;
+        }
+        let rectangle = normalize(start, (x, y));
+        draw(&rectangle)?;
+        drawn = Some(rectangle);
+    };
+
+    if let Some(previous) = drawn {
+        draw(&previous)?;
+    }
+    connection.send_request(&x::UngrabPointer { time: x::CURRENT_TIME });
+    connection.send_request(&x::FreeGc { gc });
+    connection.send_request(&x::FreeCursor { cursor });
+    connection.send_request(&x::CloseFont { font });
+    connection.flush()?;
+
+    Ok(result
+        .filter(|rectangle| rectangle.width > 0 && rectangle.height > 0)
+        .map(|rectangle| PickedRegion {
+            x: rectangle.x.max(0) as u32,
+            y: rectangle.y.max(0) as u32,
+            width: rectangle.width as u32,
+            height: rectangle.height as u32,
+        }))
+}
+
+#[cfg(target_os = "linux")]
+fn normalize(a: (i16, i16), b: (i16, i16)) -> xcb::x::Rectangle {
+    xcb::x::Rectangle {
+        x: a.0.min(b.0),
+        y: a.1.min(b.1),
+        width: a.0.abs_diff(b.0),
+        height: a.1.abs_diff(b.1),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pick_region() -> Result<Option<PickedRegion>> {
+    Err(anyhow!("Interactive region selection is only supported on X11, set the Region coordinates in the config file"))
+}
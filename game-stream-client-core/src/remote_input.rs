@@ -0,0 +1,431 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use tokio::sync::{mpsc, Notify};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use game_stream_common::{GamepadReport, InputEvent, RemoteInput, RemoteInputConfig, StreamError, StreamResult};
+use crate::gamepad::Gamepads;
+use crate::hotkeys;
+use crate::stats::StatsRecorder;
+
+/// 更新手柄延迟报告的间隔
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 观看者获得授权的输入类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct InputGrant {
+    pub keyboard: bool,
+    pub mouse: bool,
+    pub gamepad: bool,
+}
+
+impl InputGrant {
+    /// 解析 "keyboard,mouse,gamepad" 形式的列表，为空时授予全部
+    pub fn parse(text: &str) -> StreamResult<Self> {
+        if text.trim().is_empty() {
+            return Ok(Self { keyboard: true, mouse: true, gamepad: true });
+        }
+        let mut grant = Self { keyboard: false, mouse: false, gamepad: false };
+        for kind in text.split(',').map(str::trim).filter(|kind| !kind.is_empty()) {
+            match kind.to_ascii_lowercase().as_str() {
+                "keyboard" => grant.keyboard = true,
+                "mouse" => grant.mouse = true,
+                "gamepad" => grant.gamepad = true,
+                _ => return Err(StreamError::Config(format!(
+                    "Unknown input type {:?} (keyboard, mouse, gamepad)", kind,
+                ))),
+            }
+        }
+        Ok(grant)
+    }
+
+    fn allows(&self, event: &InputEvent) -> bool {
+        match event {
+            InputEvent::Key { .. } => self.keyboard,
+            InputEvent::MouseMove { .. } | InputEvent::MouseButton { .. } | InputEvent::Scroll { .. } => self.mouse,
+            InputEvent::GamepadButton { .. } | InputEvent::GamepadAxis { .. } | InputEvent::Gamepad(_) => self.gamepad,
+        }
+    }
+}
+
+/// 远程输入的开关和观看者授权句柄
+///
+/// 服务器转发来的观看者输入只有在开启且观看者获得授权时注入系统，手柄输入输出到虚拟手柄；
+/// 授权按观看者 ID 保存，断线重连后保留，撤销授权或关闭时松开观看者按住的按键并移除其手柄
+#[derive(Clone)]
+pub struct RemoteInputControl {
+    enabled: Arc<AtomicBool>,
+    grants: Arc<Mutex<HashMap<Uuid, InputGrant>>>,
+    filter: Arc<InputFilter>,
+    changed: Arc<Notify>,
+    sender: mpsc::UnboundedSender<RemoteInput>,
+    stats: StatsRecorder,
+}
+
+impl RemoteInputControl {
+    /// 未开启时返回 None；开启时在后台注入获得授权的观看者的输入
+    pub fn new(config: &RemoteInputConfig, stats: StatsRecorder) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let control = Self {
+            enabled: Arc::new(AtomicBool::new(true)),
+            grants: Arc::new(Mutex::new(HashMap::new())),
+            filter: Arc::new(InputFilter::new(config)),
+            changed: Arc::new(Notify::new()),
+            sender,
+            stats,
+        };
+        tokio::spawn(run(control.clone(), Gamepads::new(config), receiver));
+        info!("Remote input enabled, grant viewers with `input grant <viewer>`");
+        Some(control)
+    }
+
+    /// 推流器把服务器转发来的输入发到这里
+    pub fn sender(&self) -> mpsc::UnboundedSender<RemoteInput> {
+        self.sender.clone()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        if self.enabled.swap(enabled, Ordering::Relaxed) != enabled {
+            info!("Remote input {}", if enabled { "enabled" } else { "disabled" });
+            self.changed.notify_one();
+        }
+    }
+
+    pub fn grant(&self, viewer: Uuid, grant: InputGrant) {
+        self.grants.lock().unwrap().insert(viewer, grant);
+        info!("Granted remote input to viewer {}: {}", viewer, describe(&grant));
+        self.changed.notify_one();
+    }
+
+    /// 撤销观看者的授权，观看者没有授权时返回 false
+    pub fn revoke(&self, viewer: Uuid) -> bool {
+        let revoked = self.grants.lock().unwrap().remove(&viewer).is_some();
+        if revoked {
+            info!("Revoked remote input from viewer {}", viewer);
+            self.changed.notify_one();
+        }
+        revoked
+    }
+
+    /// 当前的授权，按观看者 ID 排序
+    pub fn grants(&self) -> Vec<(Uuid, InputGrant)> {
+        let mut grants: Vec<(Uuid, InputGrant)> = self.grants.lock().unwrap()
+            .iter()
+            .map(|(viewer, grant)| (*viewer, *grant))
+            .collect();
+        grants.sort_by_key(|(viewer, _)| *viewer);
+        grants
+    }
+
+    /// 正在操作的观看者手柄的延迟报告，按观看者和手柄排序
+    pub fn gamepad_reports(&self) -> Vec<GamepadReport> {
+        self.stats.remote_gamepads()
+    }
+
+    /// 是否注入这个输入：开启远程输入，观看者获得了这类输入的授权，且在 [remote_input] 允许的范围内
+    pub fn admits(&self, input: &RemoteInput) -> bool {
+        self.grant_for(&input.viewer_id).is_some_and(|grant| grant.allows(&input.event)) && self.filter.allows(&input.event)
+    }
+
+    fn grant_for(&self, viewer: &Uuid) -> Option<InputGrant> {
+        self.enabled.load(Ordering::Relaxed)
+            .then(|| self.grants.lock().unwrap().get(viewer).copied())
+            .flatten()
+    }
+}
+
+/// 授权的输入类型，如 "keyboard, mouse"
+pub fn describe(grant: &InputGrant) -> String {
+    let kinds: Vec<&str> = [(grant.keyboard, "keyboard"), (grant.mouse, "mouse"), (grant.gamepad, "gamepad")]
+        .into_iter()
+        .filter_map(|(granted, kind)| granted.then_some(kind))
+        .collect();
+    if kinds.is_empty() { "nothing".to_string() } else { kinds.join(", ") }
+}
+
+/// 配置中允许的输入类型和按键
+struct InputFilter {
+    allowed: InputGrant,
+    allowed_keys: Vec<u32>, // keysym，为空时允许所有按键
+    blocked_keys: Vec<u32>,
+}
+
+impl InputFilter {
+    fn new(config: &RemoteInputConfig) -> Self {
+        let keysyms = |keys: &[String]| -> Vec<u32> {
+            keys.iter()
+                .filter_map(|key| match hotkeys::keysym(key) {
+                    Some((keysym, _)) => Some(keysym),
+                    None => {
+                        warn!("Unknown key {:?} in [remote_input]", key);
+                        None
+                    }
+                })
+                .collect()
+        };
+        Self {
+            allowed: InputGrant { keyboard: config.keyboard, mouse: config.mouse, gamepad: config.gamepad },
+            allowed_keys: keysyms(&config.allowed_keys),
+            blocked_keys: keysyms(&config.blocked_keys),
+        }
+    }
+
+    fn allows(&self, event: &InputEvent) -> bool {
+        if !self.allowed.allows(event) {
+            return false;
+        }
+        let InputEvent::Key { key, .. } = event else {
+            return true;
+        };
+        let Some((keysym, _)) = hotkeys::keysym(key) else {
+            return false;
+        };
+        (self.allowed_keys.is_empty() || self.allowed_keys.contains(&keysym)) && !self.blocked_keys.contains(&keysym)
+    }
+}
+
+async fn run(
+    control: RemoteInputControl,
+    mut gamepads: Gamepads,
+    mut receiver: mpsc::UnboundedReceiver<RemoteInput>,
+) {
+    let injector = match Injector::connect() {
+        Ok(injector) => Some(injector),
+        Err(e) => {
+            warn!("Remote input cannot be injected: {}", e);
+            None
+        }
+    };
+    let mut held: HashMap<Uuid, Vec<InputEvent>> = HashMap::new(); // 观看者按住的按键和鼠标按键
+    let mut ungranted = HashSet::new();
+    let mut report = tokio::time::interval(REPORT_INTERVAL);
+
+    loop {
+        let input = tokio::select! {
+            input = receiver.recv() => match input {
+                Some(input) => input,
+                None => break,
+            },
+            _ = control.changed.notified() => {
+                // 失去授权的观看者按住的按键全部松开
+                held.retain(|viewer, events| {
+                    let grant = control.grant_for(viewer);
+                    events.retain(|event| {
+                        let keep = grant.is_some_and(|grant| grant.allows(event));
+                        if !keep {
+                            inject(injector.as_ref(), &released(event));
+                        }
+                        keep
+                    });
+                    !events.is_empty()
+                });
+                gamepads.retain(|viewer| control.grant_for(viewer).is_some_and(|grant| grant.gamepad));
+                continue;
+            }
+            _ = gamepad_deadline(gamepads.next_deadline()) => {
+                gamepads.tick();
+                continue;
+            }
+            _ = report.tick() => {
+                control.stats.record_remote_gamepads(gamepads.reports());
+                continue;
+            }
+        };
+
+        if control.grant_for(&input.viewer_id).is_none() {
+            if control.is_enabled() && ungranted.insert(input.viewer_id) {
+                info!("Viewer {} sent remote input without a grant, allow with `input grant {}`", input.viewer_id, input.viewer_id);
+            }
+            continue;
+        }
+        if !control.admits(&input) {
+            debug!("Dropped remote input from viewer {}: {:?}", input.viewer_id, input.event);
+            continue;
+        }
+        let RemoteInput { viewer_id, event } = input;
+        match event {
+            InputEvent::Gamepad(state) => {
+                gamepads.receive(viewer_id, state);
+                continue;
+            }
+            InputEvent::GamepadButton { .. } | InputEvent::GamepadAxis { .. } => {
+                gamepads.receive_event(viewer_id, &event);
+                continue;
+            }
+            _ => {}
+        }
+
+        match &event {
+            InputEvent::Key { pressed: true, .. } | InputEvent::MouseButton { pressed: true, .. } => {
+                let events = held.entry(viewer_id).or_default();
+                if !events.contains(&event) {
+                    events.push(event.clone());
+                }
+            }
+            InputEvent::Key { .. } | InputEvent::MouseButton { .. } => {
+                if let Some(events) = held.get_mut(&viewer_id) {
+                    events.retain(|pressed| released(pressed) != event);
+                }
+            }
+            _ => {}
+        }
+        inject(injector.as_ref(), &event);
+    }
+}
+
+/// 等到手柄需要输出快照或检查闲置的时间，没有时一直等待
+async fn gamepad_deadline(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// 按下事件对应的松开事件
+fn released(event: &InputEvent) -> InputEvent {
+    match event {
+        InputEvent::Key { key, .. } => InputEvent::Key { key: key.clone(), pressed: false },
+        InputEvent::MouseButton { button, .. } => InputEvent::MouseButton { button: *button, pressed: false },
+        event => event.clone(),
+    }
+}
+
+fn inject(injector: Option<&Injector>, event: &InputEvent) {
+    if let Some(injector) = injector {
+        if let Err(e) = injector.inject(event) {
+            debug!("Failed to inject remote input {:?}: {}", event, e);
+        }
+    }
+}
+
+/// X11 上通过 XTest 扩展注入按键和鼠标事件；Wayland 下只能送达 XWayland 中的窗口
+///
+/// 鼠标位置按整个屏幕换算，捕获窗口或区域时与画面中的位置不完全对应
+#[cfg(target_os = "linux")]
+struct Injector {
+    connection: xcb::Connection,
+    root: xcb::x::Window,
+    size: (u16, u16),
+    min_keycode: u8,
+    keysyms_per_keycode: usize,
+    keysyms: Vec<u32>,
+}
+
+#[cfg(target_os = "linux")]
+impl Injector {
+    // X11 事件类型
+    const KEY_PRESS: u8 = 2;
+    const KEY_RELEASE: u8 = 3;
+    const BUTTON_PRESS: u8 = 4;
+    const BUTTON_RELEASE: u8 = 5;
+    const MOTION_NOTIFY: u8 = 6;
+
+    fn connect() -> Result<Self, String> {
+        use xcb::{x, xtest};
+
+        if crate::portal::is_wayland_session() {
+            warn!("Remote input is injected through XWayland, native Wayland windows will not receive it");
+        }
+        let (connection, screen) = xcb::Connection::connect_with_extensions(None, &[xcb::Extension::Test], &[])
+            .map_err(|e| format!("cannot connect to X server: {}", e))?;
+        let cookie = connection.send_request(&xtest::GetVersion { major_version: 2, minor_version: 2 });
+        connection.wait_for_reply(cookie).map_err(|e| format!("XTest is not available: {}", e))?;
+
+        let setup = connection.get_setup();
+        let screen = setup.roots().nth(screen as usize).ok_or("X server has no screen")?;
+        let (root, size) = (screen.root(), (screen.width_in_pixels(), screen.height_in_pixels()));
+        let (min_keycode, max_keycode) = (setup.min_keycode(), setup.max_keycode());
+
+        let cookie = connection.send_request(&x::GetKeyboardMapping {
+            first_keycode: min_keycode,
+            count: max_keycode - min_keycode + 1,
+        });
+        let mapping = connection.wait_for_reply(cookie).map_err(|e| e.to_string())?;
+        let keysyms_per_keycode = mapping.keysyms_per_keycode().max(1) as usize;
+        let keysyms = mapping.keysyms().to_vec();
+
+        Ok(Self { connection, root, size, min_keycode, keysyms_per_keycode, keysyms })
+    }
+
+    fn inject(&self, event: &InputEvent) -> Result<(), String> {
+        match event {
+            InputEvent::Key { key, pressed } => {
+                let (keysym, _) = hotkeys::keysym(key).ok_or_else(|| format!("unknown key {:?}", key))?;
+                let index = self.keysyms.chunks(self.keysyms_per_keycode)
+                    .position(|keysyms| keysyms.contains(&keysym))
+                    .ok_or_else(|| format!("key {:?} is not on the current keyboard layout", key))?;
+                let kind = if *pressed { Self::KEY_PRESS } else { Self::KEY_RELEASE };
+                self.fake(kind, self.min_keycode + index as u8, (0, 0))
+            }
+            InputEvent::MouseMove { x, y } => {
+                let position = |value: f32, size: u16| (value.clamp(0.0, 1.0) * (size.max(1) - 1) as f32).round() as i16;
+                self.fake(Self::MOTION_NOTIFY, 0, (position(*x, self.size.0), position(*y, self.size.1)))
+            }
+            InputEvent::MouseButton { button, pressed } => {
+                let detail = match button {
+                    game_stream_common::MouseButton::Left => 1,
+                    game_stream_common::MouseButton::Middle => 2,
+                    game_stream_common::MouseButton::Right => 3,
+                };
+                self.fake(if *pressed { Self::BUTTON_PRESS } else { Self::BUTTON_RELEASE }, detail, (0, 0))
+            }
+            InputEvent::Scroll { dx, dy } => {
+                // X11 的滚轮是按钮 4-7 (上、下、左、右)，每一格按下再松开一次
+                let vertical = if *dy < 0 { 4 } else { 5 };
+                let horizontal = if *dx < 0 { 6 } else { 7 };
+                for (button, count) in [(vertical, dy.unsigned_abs()), (horizontal, dx.unsigned_abs())] {
+                    for _ in 0..count.min(MAX_SCROLL_STEPS) {
+                        self.fake(Self::BUTTON_PRESS, button, (0, 0))?;
+                        self.fake(Self::BUTTON_RELEASE, button, (0, 0))?;
+                    }
+                }
+                Ok(())
+            }
+            InputEvent::GamepadButton { .. } | InputEvent::GamepadAxis { .. } | InputEvent::Gamepad(_) => {
+                Err("gamepad input goes to the virtual gamepad".to_string())
+            }
+        }
+    }
+
+    fn fake(&self, kind: u8, detail: u8, (root_x, root_y): (i16, i16)) -> Result<(), String> {
+        self.connection.send_and_check_request(&xcb::xtest::FakeInput {
+            r#type: kind,
+            detail,
+            time: 0, // 立即生效
+            root: self.root,
+            root_x,
+            root_y,
+            deviceid: 0,
+        }).map_err(|e| e.to_string())
+    }
+}
+
+/// 单个滚动事件最多注入的格数
+#[cfg(target_os = "linux")]
+const MAX_SCROLL_STEPS: u32 = 20;
+
+#[cfg(not(target_os = "linux"))]
+struct Injector;
+
+#[cfg(not(target_os = "linux"))]
+impl Injector {
+    fn connect() -> Result<Self, String> {
+        Err("injecting input is not supported on this platform".to_string())
+    }
+
+    fn inject(&self, _event: &InputEvent) -> Result<(), String> {
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use game_stream_common::{EncodingConfig, MediaPacket, ReplayConfig, StreamResult, StreamError};
+use crate::pusher::TsPackager;
+
+/// 回放缓存 - 在内存中保留最近一段时间的编码数据，按快捷键或终端命令保存为 MP4
+///
+/// 缓存从视频关键帧开始，长度不少于 duration；编码数据经由 TS 封装交给 ffmpeg 复制为 MP4，
+/// 不重新编码。断线重连期间和停止推流之后捕获和编码继续，缓存照常更新
+#[derive(Clone)]
+pub struct ReplayBuffer {
+    packets: Arc<Mutex<VecDeque<MediaPacket>>>,
+    duration: u64, // 毫秒
+    directory: PathBuf,
+    encoding_config: EncodingConfig,
+}
+
+impl ReplayBuffer {
+    /// 未开启回放缓存，或者编码格式不能封装在 TS 中时返回 None
+    pub fn new(config: &ReplayConfig, encoding_config: &EncodingConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        if let Err(e) = TsPackager::new(encoding_config) {
+            warn!("Replay buffer disabled: {}", e);
+            return None;
+        }
+
+        info!("Replay buffer enabled, keeping the last {}s", config.duration);
+        Some(Self {
+            packets: Arc::new(Mutex::new(VecDeque::new())),
+            duration: config.duration.max(1) * 1000,
+            directory: PathBuf::from(&config.directory),
+            encoding_config: encoding_config.clone(),
+        })
+    }
+
+    /// 加入一个编码包，收到视频关键帧时丢弃超出时长的部分
+    pub fn push(&self, packet: &MediaPacket) {
+        let keyframe_timestamp = match packet {
+            MediaPacket::Video { timestamp, is_keyframe: true, .. } => Some(*timestamp),
+            MediaPacket::Metadata { .. } => return,
+            _ => None,
+        };
+
+        let mut packets = self.packets.lock().unwrap_or_else(|e| e.into_inner());
+        packets.push_back(packet.clone());
+
+        // 保留最后一个不晚于截止时间的关键帧及之后的数据，保存的文件从关键帧开始
+        if let Some(newest) = keyframe_timestamp {
+            let cutoff = newest.saturating_sub(self.duration);
+            let start = packets.iter()
+                .take_while(|packet| packet.timestamp().is_none_or(|timestamp| timestamp <= cutoff))
+                .enumerate()
+                .filter(|(_, packet)| matches!(packet, MediaPacket::Video { is_keyframe: true, .. }))
+                .map(|(index, _)| index)
+                .last();
+            if let Some(start) = start {
+                packets.drain(..start);
+            }
+        }
+    }
+
+    /// 在后台把当前缓存的内容保存为 MP4
+    pub fn save(&self) {
+        let packets: Vec<MediaPacket> = self.packets.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect();
+        if !packets.iter().any(|packet| matches!(packet, MediaPacket::Video { .. })) {
+            warn!("Replay buffer is empty, nothing to save");
+            return;
+        }
+
+        let replay = self.clone();
+        tokio::spawn(async move {
+            let path = replay.directory.join(format!("replay-{}.mp4", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+            match replay.write_mp4(&packets, &path).await {
+                Ok(seconds) => info!("Replay saved to {} ({}s)", path.display(), seconds),
+                Err(e) => warn!("Failed to save replay to {}: {}", path.display(), e),
+            }
+        });
+    }
+
+    /// 通过 ffmpeg 把编码数据复制到 MP4，返回保存的秒数
+    async fn write_mp4(&self, packets: &[MediaPacket], path: &Path) -> StreamResult<u64> {
+        tokio::fs::create_dir_all(&self.directory).await?;
+
+        let mut child = tokio::process::Command::new("ffmpeg")
+            .args(["-hide_banner", "-nostdin", "-loglevel", "error", "-y"])
+            .args(["-f", "mpegts", "-i", "pipe:0", "-map", "0", "-c", "copy", "-movflags", "+faststart"])
+            .arg(path)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| StreamError::Codec(format!("Failed to start ffmpeg (is it installed?): {}", e)))?;
+        let mut stdin = child.stdin.take()
+            .ok_or_else(|| StreamError::Codec("ffmpeg has no input".to_string()))?;
+
+        let mut packager = TsPackager::new(&self.encoding_config)?;
+        for packet in packets {
+            if let Some(data) = packager.packetize(packet.clone()) {
+                // ffmpeg 出错退出时写入失败，错误信息在下面读取
+                if stdin.write_all(&data).await.is_err() {
+                    break;
+                }
+            }
+        }
+        drop(stdin);
+
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            let message = String::from_utf8_lossy(&output.stderr);
+            return Err(StreamError::Codec(format!("ffmpeg exited ({}): {}", output.status, message.trim())));
+        }
+
+        let timestamps = packets.iter().filter_map(MediaPacket::timestamp);
+        let seconds = timestamps.clone().max().unwrap_or_default().saturating_sub(timestamps.min().unwrap_or_default()) / 1000;
+        Ok(seconds)
+    }
+}
+
+/// 把经过的编码包加入回放缓存，所有包原样转发
+pub async fn tap_packets(
+    replay: ReplayBuffer,
+    mut packet_receiver: mpsc::UnboundedReceiver<MediaPacket>,
+    packet_sender: mpsc::UnboundedSender<MediaPacket>,
+) {
+    while let Some(packet) = packet_receiver.recv().await {
+        replay.push(&packet);
+        if packet_sender.send(packet).is_err() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, NaiveDateTime, Timelike};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use game_stream_common::{ScheduleConfig, StreamError, StreamResult};
+use crate::stream_control::StreamControl;
+
+/// start_at 的时间格式
+const START_AT_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// 等待时每隔这么久重新对照系统时间，系统时间调整或夏令时切换后不会错过开始时间
+const CLOCK_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 向后查找 cron 下一次开始时间的范围
+const CRON_SEARCH_DAYS: i64 = 366;
+
+/// 定时开始和停止推流
+pub struct Schedule {
+    start: Option<Start>,
+    stop_after: Option<Duration>,
+}
+
+enum Start {
+    Once(DateTime<Local>),
+    Cron(Cron),
+}
+
+impl Schedule {
+    /// 没有配置定时时返回 None，时间格式错误时返回配置错误
+    pub fn new(config: &ScheduleConfig) -> StreamResult<Option<Self>> {
+        let start = match (&config.start_at, &config.cron) {
+            (Some(_), Some(_)) => {
+                return Err(StreamError::Config("Set either stream.schedule.start_at or cron, not both".to_string()));
+            }
+            (Some(start_at), None) => {
+                let time = NaiveDateTime::parse_from_str(start_at.trim(), START_AT_FORMAT).ok()
+                    .and_then(|time| time.and_local_timezone(Local).earliest())
+                    .ok_or_else(|| StreamError::Config(format!(
+                        "Invalid stream.schedule.start_at {:?}, expected local time as YYYY-MM-DD HH:MM", start_at,
+                    )))?;
+                Some(Start::Once(time))
+            }
+            (None, Some(cron)) => Some(Start::Cron(Cron::parse(cron)?)),
+            (None, None) => None,
+        };
+        let stop_after = config.stop_after.map(Duration::from_secs);
+
+        if start.is_none() && stop_after.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Self { start, stop_after }))
+    }
+
+    /// 是否需要以停止推流的状态启动，等到开始时间
+    pub fn waits_for_start(&self) -> bool {
+        self.start.is_some()
+    }
+
+    /// 按计划开始和停止推流，一次性的计划执行完后返回
+    pub async fn run(self, control: StreamControl) {
+        let mut after = Local::now();
+        let mut first = true;
+        loop {
+            let start = match &self.start {
+                Some(Start::Once(time)) if first => *time,
+                Some(Start::Cron(cron)) => match cron.next_after(after) {
+                    Some(time) => time,
+                    None => {
+                        warn!("Streaming schedule has no start time in the next {} days", CRON_SEARCH_DAYS);
+                        return;
+                    }
+                },
+                // 没有开始时间时客户端启动即开始推流
+                None if first => Local::now(),
+                _ => break,
+            };
+            first = false;
+
+            // 错过的一次性开始时间：如果按计划仍在推流时段内，立即开始推流剩余的时间
+            let late = (Local::now() - start).to_std().unwrap_or_default();
+            if self.stop_after.is_some_and(|duration| late >= duration) {
+                warn!("Scheduled stream at {} has already ended, not starting", start.format(START_AT_FORMAT));
+                after = Local::now();
+                continue;
+            }
+
+            if !late.is_zero() || self.start.is_none() {
+                control.set_streaming(true);
+            } else {
+                info!("Streaming scheduled to start at {}", start.format(START_AT_FORMAT));
+                sleep_until(start).await;
+                info!("Starting scheduled stream");
+                control.set_streaming(true);
+            }
+
+            if let Some(duration) = self.stop_after {
+                let stop = start + ChronoDuration::from_std(duration).unwrap_or(ChronoDuration::MAX);
+                info!("Streaming scheduled to stop at {}", stop.format(START_AT_FORMAT));
+                sleep_until(stop).await;
+                info!("Stopping scheduled stream");
+                control.set_streaming(false);
+            }
+            after = Local::now();
+        }
+
+        info!("Streaming schedule finished");
+    }
+}
+
+async fn sleep_until(time: DateTime<Local>) {
+    while let Ok(remaining) = (time - Local::now()).to_std() {
+        if remaining.is_zero() {
+            break;
+        }
+        tokio::time::sleep(remaining.min(CLOCK_CHECK_INTERVAL)).await;
+    }
+}
+
+/// crontab 格式的时间："分 时 日 月 星期"，每项可为 *、数字、范围 a-b、列表 a,b 和步长 */n、a-b/n；
+/// 星期中 0 和 7 都表示星期日；日和星期都不是 * 时满足其一即可
+struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    fn parse(expression: &str) -> StreamResult<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(StreamError::Config(format!(
+                "Invalid stream.schedule.cron {:?}, expected 5 fields: minute hour day month weekday", expression,
+            )));
+        };
+        let parse = |field: &str, min: u32, max: u32| {
+            parse_field(field, min, max).ok_or_else(|| StreamError::Config(format!(
+                "Invalid field {:?} in stream.schedule.cron {:?} (allowed {}-{})", field, expression, min, max,
+            )))
+        };
+
+        let mut weekday_bits = parse(weekdays, 0, 7)?;
+        if weekday_bits & (1 << 7) != 0 {
+            weekday_bits |= 1;
+        }
+        Ok(Self {
+            minutes: parse(minutes, 0, 59)?,
+            hours: parse(hours, 0, 23)?,
+            days: parse(days, 1, 31)?,
+            months: parse(months, 1, 12)?,
+            weekdays: weekday_bits,
+            any_day: days == "*",
+            any_weekday: weekdays == "*",
+        })
+    }
+
+    /// time 之后 (不含) 第一个匹配的整分钟
+    fn next_after(&self, time: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = time.naive_local().with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        (0..CRON_SEARCH_DAYS * 24 * 60)
+            .map(|minute| start + ChronoDuration::minutes(minute))
+            .filter(|candidate| self.matches(candidate))
+            // 夏令时切换时不存在的本地时间跳过
+            .find_map(|candidate| candidate.and_local_timezone(Local).earliest())
+    }
+
+    fn matches(&self, time: &NaiveDateTime) -> bool {
+        let bit = |bits: u64, value: u32| bits & (1 << value) != 0;
+        let day = bit(self.days, time.day());
+        let weekday = bit(self.weekdays, time.weekday().num_days_from_sunday());
+        let day_matches = match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        };
+
+        bit(self.minutes, time.minute()) && bit(self.hours, time.hour()) && bit(self.months, time.month()) && day_matches
+    }
+}
+
+/// 把一项解析为位集合，第 n 位表示值 n
+fn parse_field(field: &str, min: u32, max: u32) -> Option<u64> {
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|step| *step > 0)?),
+            None => (part, 1),
+        };
+        let (first, last) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((first, last)) => (first.parse().ok()?, last.parse().ok()?),
+                None => {
+                    let value = range.parse().ok()?;
+                    // "5/10" 表示从 5 开始每 10 个
+                    (value, if step > 1 { max } else { value })
+                }
+            },
+        };
+        if first < min || last > max || first > last {
+            return None;
+        }
+        for value in (first..=last).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Some(bits)
+}
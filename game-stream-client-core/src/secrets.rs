@@ -0,0 +1,59 @@
+use anyhow::{anyhow, Context, Result};
+use std::process::Command;
+
+use game_stream_common::ServerEndpoint;
+
+/// 系统钥匙串中保存密钥使用的服务名
+const KEYCHAIN_SERVICE: &str = "game-stream-client";
+
+/// 把流密钥和 SRT/RIST 口令中的 "env:<变量名>" 和 "keychain:<名称>" 替换为实际的值，其他值原样保留
+pub fn resolve_server_secrets(server: &mut ServerEndpoint) -> Result<()> {
+    server.stream_key = resolve(&server.stream_key).context("Cannot read the stream key")?;
+    if let Some(passphrase) = &server.passphrase {
+        server.passphrase = Some(resolve(passphrase).context("Cannot read the passphrase")?);
+    }
+    Ok(())
+}
+
+fn resolve(value: &str) -> Result<String> {
+    if let Some(variable) = value.strip_prefix("env:") {
+        return std::env::var(variable)
+            .map_err(|e| anyhow!("Environment variable {} is not usable: {}", variable, e));
+    }
+    if let Some(name) = value.strip_prefix("keychain:") {
+        return read_keychain(name);
+    }
+    Ok(value.to_string())
+}
+
+/// 通过 libsecret 的 secret-tool 读取，保存方法：
+/// secret-tool store --label="game-stream-client <名称>" service game-stream-client account <名称>
+#[cfg(all(unix, not(target_os = "macos")))]
+fn read_keychain(name: &str) -> Result<String> {
+    run_keychain_tool(Command::new("secret-tool").args(["lookup", "service", KEYCHAIN_SERVICE, "account", name]), name)
+}
+
+/// 通过 security 读取登录钥匙串，保存方法：
+/// security add-generic-password -s game-stream-client -a <名称> -w
+#[cfg(target_os = "macos")]
+fn read_keychain(name: &str) -> Result<String> {
+    run_keychain_tool(Command::new("security").args(["find-generic-password", "-s", KEYCHAIN_SERVICE, "-a", name, "-w"]), name)
+}
+
+#[cfg(not(unix))]
+fn read_keychain(_name: &str) -> Result<String> {
+    Err(anyhow!("Keychain secrets are only supported on Linux (libsecret) and macOS, use env:<variable> instead"))
+}
+
+#[cfg(unix)]
+fn run_keychain_tool(command: &mut Command, name: &str) -> Result<String> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command.output()
+        .with_context(|| format!("Cannot run {} to read the keychain", program))?;
+    // 命令行工具在值的末尾加换行
+    let secret = String::from_utf8(output.stdout)?.trim_end_matches(['\r', '\n']).to_string();
+    if !output.status.success() || secret.is_empty() {
+        return Err(anyhow!("No secret named {:?} for service {} in the keychain", name, KEYCHAIN_SERVICE));
+    }
+    Ok(secret)
+}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::time::Instant;
+
+use game_stream_common::{NetworkConfig, StreamResult, StreamError};
+
+/// 平滑发送时每块的最大字节数，buffer_size 更小时以 buffer_size 为准
+const MAX_PACING_CHUNK: usize = 16 * 1024;
+
+/// 一个包最多分散到帧间隔的这个比例内，留出余量给同一帧间隔内的音频和下一帧
+const PACING_WINDOW: f64 = 0.8;
+
+/// 发送节奏控制 - 关键帧等大包一次性写出会在带宽受限的上行链路上形成突发，造成丢包
+///
+/// 超过一块的包拆成等长的小块，在帧间隔内均匀写出；每次写入不超过 buffer_size，
+/// 整个包 (含等待) 在 write_timeout 内写完
+#[derive(Debug, Clone, Copy)]
+pub struct SendPacer {
+    chunk_size: usize,
+    window: Duration, // 一个包的发送时长上限
+    write_timeout: Duration,
+}
+
+impl SendPacer {
+    pub fn new(network_config: &NetworkConfig, fps: f64) -> Self {
+        let max_chunk = network_config.buffer_size.max(1);
+        let (chunk_size, window) = if network_config.pacing && fps > 0.0 {
+            (max_chunk.min(MAX_PACING_CHUNK), Duration::from_secs_f64(PACING_WINDOW / fps))
+        } else {
+            (max_chunk, Duration::ZERO)
+        };
+
+        Self {
+            chunk_size,
+            window,
+            write_timeout: Duration::from_secs(network_config.write_timeout),
+        }
+    }
+
+    /// 按节奏写出整个包，target 用于超时的错误信息
+    pub async fn write_all<W: AsyncWrite + Unpin + ?Sized>(&self, writer: &mut W, data: &[u8], target: &str) -> StreamResult<()> {
+        let started = Instant::now();
+        let deadline = started + self.write_timeout;
+        let chunks = data.len().div_ceil(self.chunk_size).max(1);
+        let chunk_size = data.len().div_ceil(chunks).max(1);
+        let spacing = self.window / chunks as u32;
+
+        for (index, chunk) in data.chunks(chunk_size).enumerate() {
+            if index > 0 && !spacing.is_zero() {
+                tokio::time::sleep_until((started + spacing * index as u32).min(deadline)).await;
+            }
+            tokio::time::timeout_at(deadline, writer.write_all(chunk)).await
+                .map_err(|_| StreamError::Network(format!("Timed out writing to {}", target)))??;
+        }
+        Ok(())
+    }
+}
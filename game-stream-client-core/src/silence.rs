@@ -0,0 +1,79 @@
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use game_stream_common::SilenceDetectionConfig;
+use crate::client::ClientControls;
+
+/// 读取电平的间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 一个被检测的混音输入
+struct Watched {
+    name: String,
+    silent_since: Option<Instant>,
+    warned: bool,
+}
+
+/// 在后台检测推流中持续无声的混音输入：超过时长时警告一次，声音恢复时记录；
+/// 无声的输入写入统计，开启 stats.report_to_server 时随统计上报给服务器
+pub fn spawn_monitor(config: &SilenceDetectionConfig, controls: ClientControls) {
+    let (true, Some(mixer)) = (config.enabled, controls.mixer) else {
+        return;
+    };
+    let (stream, stats) = (controls.stream, controls.stats);
+    let names = mixer.names();
+    for name in config.inputs.iter().filter(|name| !names.contains(&name.as_str())) {
+        warn!("Silence detection input {:?} does not exist ({})", name, names.join(", "));
+    }
+    let mut watched: Vec<Watched> = names.iter()
+        .filter(|name| config.inputs.is_empty() || config.inputs.iter().any(|input| input == *name))
+        .map(|name| Watched { name: name.to_string(), silent_since: None, warned: false })
+        .collect();
+    if watched.is_empty() {
+        return;
+    }
+
+    let (threshold_db, duration) = (config.threshold_db, Duration::from_secs(config.duration.max(1)));
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+
+            // 停止或暂停推流时不检测，继续推流后重新计时
+            let active = stream.is_streaming() && !stream.is_paused();
+            let status = mixer.status();
+            let mut changed = false;
+            for level in mixer.levels() {
+                let Some(input) = watched.iter_mut().find(|input| input.name == level.name) else {
+                    continue;
+                };
+                if active && level.peak_db < threshold_db {
+                    let since = *input.silent_since.get_or_insert_with(Instant::now);
+                    if !input.warned && since.elapsed() >= duration {
+                        let muted = status.iter().any(|(name, _, muted)| *name == input.name && *muted);
+                        warn!("Audio input {:?} has been silent for {} s{}", input.name, since.elapsed().as_secs(),
+                              if muted { ", it is muted" } else { "" });
+                        input.warned = true;
+                        changed = true;
+                    }
+                } else {
+                    input.silent_since = None;
+                    if input.warned {
+                        if active {
+                            info!("Audio input {:?} is no longer silent", input.name);
+                        }
+                        input.warned = false;
+                        changed = true;
+                    }
+                }
+            }
+
+            if changed {
+                stats.record_silent_audio_inputs(watched.iter()
+                    .filter(|input| input.warned)
+                    .map(|input| input.name.clone())
+                    .collect());
+            }
+        }
+    });
+}
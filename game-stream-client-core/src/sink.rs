@@ -0,0 +1,179 @@
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::fs::File;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use game_stream_common::{EncodingConfig, MediaPacket, RemoteInput, StreamError, StreamResult};
+use crate::pusher::TsPackager;
+
+/// 编码数据的去向 - 推流协议 (RTMP/SRT/RIST)、本地文件或嵌入程序自己的传输都实现这个特征
+///
+/// 编码管线只把媒体包交给 PusherManager，主推流和附加的输出 (多平台推流、本地录制) 都是 StreamSink
+#[async_trait]
+pub trait StreamSink: Send + Sync {
+    /// 名称，用于日志
+    fn name(&self) -> &str;
+
+    /// 连接到服务器或打开文件
+    async fn connect(&mut self) -> StreamResult<()>;
+
+    /// 发送一个媒体包
+    async fn send(&mut self, packet: MediaPacket) -> StreamResult<()>;
+
+    /// 把缓冲的数据写出
+    async fn flush(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    /// 断开连接或关闭文件
+    async fn close(&mut self) -> StreamResult<()>;
+
+    /// 断线后重新连接
+    async fn reconnect(&mut self) -> StreamResult<()> {
+        self.close().await?;
+        self.connect().await
+    }
+
+    /// 最近测得的往返时间，协议无法测量时为 None
+    fn rtt(&self) -> Option<Duration> {
+        None
+    }
+
+    /// 把服务器转发来的观看者输入交给 sender，只有带回传通道的协议 (RTMP) 需要实现
+    fn set_remote_input(&mut self, _sender: mpsc::UnboundedSender<RemoteInput>) {}
+}
+
+/// 每次开始推流时创建一个附加输出，见 StreamingClientBuilder::add_output
+pub type SinkFactory = Arc<dyn Fn() -> StreamResult<Box<dyn StreamSink>> + Send + Sync>;
+
+/// 录制到本地 MPEG-TS 文件的输出，封装与 SRT/RIST 推流相同
+///
+/// TS 没有文件尾，进程意外退出时已写入的部分仍可播放
+pub struct FileSink {
+    path: PathBuf,
+    packager: TsPackager,
+    file: Option<BufWriter<File>>,
+}
+
+impl FileSink {
+    /// 编码格式不能封装在 TS 中时返回错误
+    pub fn new(path: impl Into<PathBuf>, encoding_config: &EncodingConfig) -> StreamResult<Self> {
+        Ok(Self {
+            path: path.into(),
+            packager: TsPackager::new(encoding_config)?,
+            file: None,
+        })
+    }
+}
+
+#[async_trait]
+impl StreamSink for FileSink {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    async fn connect(&mut self) -> StreamResult<()> {
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let file = File::create(&self.path).await?;
+        self.packager.restart();
+        self.file = Some(BufWriter::new(file));
+        info!("Recording to {}", self.path.display());
+        Ok(())
+    }
+
+    async fn send(&mut self, packet: MediaPacket) -> StreamResult<()> {
+        let Some(file) = &mut self.file else {
+            return Err(StreamError::Internal(format!("{} is not open", self.path.display())));
+        };
+        let keyframe = matches!(packet, MediaPacket::Video { is_keyframe: true, .. });
+        if let Some(data) = self.packager.packetize(packet) {
+            file.write_all(&data).await?;
+        }
+        // 每个关键帧刷新一次，进程意外退出时最多丢失一个 GOP
+        if keyframe {
+            file.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> StreamResult<()> {
+        if let Some(file) = &mut self.file {
+            file.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        if let Some(mut file) = self.file.take() {
+            file.flush().await?;
+            info!("Recording saved to {}", self.path.display());
+        }
+        Ok(())
+    }
+
+    /// 文件不会断开，重新打开会清空已录制的内容
+    async fn reconnect(&mut self) -> StreamResult<()> {
+        Err(StreamError::Internal(format!("Cannot write to {}", self.path.display())))
+    }
+}
+
+/// 把编码数据同时交给附加的输出，返回主推流使用的接收端
+///
+/// 每个输出在自己的任务中发送，慢的或断开的输出不影响主推流；输出失败时重连一次，仍失败则停止该输出
+pub(crate) fn tee_outputs(
+    outputs: Vec<Box<dyn StreamSink>>,
+    mut receiver: mpsc::UnboundedReceiver<MediaPacket>,
+) -> mpsc::UnboundedReceiver<MediaPacket> {
+    if outputs.is_empty() {
+        return receiver;
+    }
+
+    let senders: Vec<_> = outputs.into_iter()
+        .map(|sink| {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            tokio::spawn(run_output(sink, receiver));
+            sender
+        })
+        .collect();
+    let (primary, primary_receiver) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Some(packet) = receiver.recv().await {
+            for sender in &senders {
+                let _ = sender.send(packet.clone());
+            }
+            if primary.send(packet).is_err() {
+                break;
+            }
+        }
+    });
+    primary_receiver
+}
+
+async fn run_output(mut sink: Box<dyn StreamSink>, mut receiver: mpsc::UnboundedReceiver<MediaPacket>) {
+    if let Err(e) = sink.connect().await {
+        warn!("Output {} failed to start: {}", sink.name(), e);
+        return;
+    }
+    while let Some(packet) = receiver.recv().await {
+        let Err(e) = sink.send(packet).await else {
+            continue;
+        };
+        warn!("Output {} failed: {}, reconnecting", sink.name(), e);
+        if let Err(e) = sink.reconnect().await {
+            warn!("Output {} stopped: {}", sink.name(), e);
+            return;
+        }
+    }
+    if let Err(e) = sink.flush().await {
+        warn!("Failed to flush output {}: {}", sink.name(), e);
+    }
+    if let Err(e) = sink.close().await {
+        warn!("Failed to close output {}: {}", sink.name(), e);
+    }
+}
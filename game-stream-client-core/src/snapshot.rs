@@ -0,0 +1,84 @@
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+use image::{ImageFormat, RgbImage};
+
+use game_stream_common::{HdrConfig, StreamError, StreamResult, VideoPixelFormat};
+use crate::capture::{CaptureManager, CapturedFrame, FrameType};
+use crate::tonemap::ToneMapper;
+
+/// 从配置的捕获源截取一帧保存为 PNG，用于确认窗口或区域的选择是否正确
+///
+/// 直接调用捕获源，与推流管线无关，截取的是场景合成之前的画面
+#[derive(Clone)]
+pub struct Snapshot {
+    capture: CaptureManager,
+    tone_mapper: Arc<ToneMapper>, // HDR 画面先映射到 SDR
+}
+
+impl Snapshot {
+    pub fn new(capture: CaptureManager, hdr: &HdrConfig) -> Self {
+        Self {
+            capture,
+            tone_mapper: Arc::new(ToneMapper::new(hdr)),
+        }
+    }
+
+    /// 截取一帧，返回 PNG 数据
+    pub async fn capture_png(&self) -> StreamResult<Vec<u8>> {
+        let frame = self.capture.capture_video_frame().await?;
+        let image = self.to_image(&frame)?;
+
+        let mut png = Cursor::new(Vec::new());
+        image.write_to(&mut png, ImageFormat::Png)
+            .map_err(|e| StreamError::Capture(format!("Failed to encode snapshot: {}", e)))?;
+        Ok(png.into_inner())
+    }
+
+    /// 截取一帧保存到 path
+    pub async fn save(&self, path: &Path) -> StreamResult<()> {
+        let png = self.capture_png().await?;
+        std::fs::write(path, png)?;
+        Ok(())
+    }
+
+    fn to_image(&self, frame: &CapturedFrame) -> StreamResult<RgbImage> {
+        if matches!(frame.frame_type, FrameType::GpuVideo(_)) {
+            return Err(StreamError::Capture("Snapshots are not available with zero-copy capture".to_string()));
+        }
+        let (Some(width), Some(height)) = (frame.width, frame.height) else {
+            return Err(StreamError::Capture("Captured frame has no size".to_string()));
+        };
+        let mut format = frame.pixel_format.unwrap_or(VideoPixelFormat::Rgba32);
+        if frame.data.len() < format.frame_size(width, height) {
+            return Err(StreamError::Capture("Captured frame is truncated".to_string()));
+        }
+
+        let tone_mapped;
+        let data: &[u8] = if format.is_hdr() {
+            tone_mapped = self.tone_mapper.tone_map(&frame.data, width, height, format)?;
+            format = VideoPixelFormat::Rgba32;
+            &tone_mapped
+        } else {
+            &frame.data
+        };
+
+        let (bytes_per_pixel, order) = match format {
+            VideoPixelFormat::Rgba32 => (4, [0, 1, 2]),
+            VideoPixelFormat::Bgra32 => (4, [2, 1, 0]),
+            VideoPixelFormat::Rgb24 => (3, [0, 1, 2]),
+            VideoPixelFormat::Bgr24 => (3, [2, 1, 0]),
+            format => {
+                return Err(StreamError::Capture(format!("Cannot save {:?} frames as a snapshot", format)));
+            }
+        };
+        let pixels = (width * height) as usize;
+        let rgb = data.chunks_exact(bytes_per_pixel)
+            .take(pixels)
+            .flat_map(|pixel| order.map(|channel| pixel[channel]))
+            .collect();
+
+        RgbImage::from_raw(width, height, rgb)
+            .ok_or_else(|| StreamError::Capture("Captured frame is truncated".to_string()))
+    }
+}
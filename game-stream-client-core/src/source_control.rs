@@ -0,0 +1,79 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::info;
+
+use game_stream_common::{SceneConfig, StreamResult, StreamError};
+use crate::client::ClientControls;
+
+/// 主捕获源 ([capture.video_source]) 的名称
+pub const CAPTURE_SOURCE: &str = "capture";
+
+/// 视频源的开关句柄，推流过程中开关不需要重建捕获器
+///
+/// 源包括主捕获源和场景中有名称的图层，不同场景中同名的图层一起开关。
+/// 关闭的主捕获源不再截图，输出透明画面 (单独编码时为黑色，合成时不遮挡其他图层)；
+/// 关闭的图层不绘制，图层自己的捕获源暂停截图
+#[derive(Clone)]
+pub struct SourceControl {
+    sources: Arc<Vec<(String, Arc<AtomicBool>)>>,
+}
+
+impl SourceControl {
+    pub fn new(scenes: &[SceneConfig]) -> Self {
+        let mut sources = vec![(CAPTURE_SOURCE.to_string(), Arc::new(AtomicBool::new(true)))];
+        for layer in scenes.iter().flat_map(|scene| &scene.layers) {
+            if !layer.name.is_empty() && !sources.iter().any(|(name, _)| *name == layer.name) {
+                sources.push((layer.name.clone(), Arc::new(AtomicBool::new(layer.visible))));
+            }
+        }
+        Self { sources: Arc::new(sources) }
+    }
+
+    /// 源的开关状态，捕获器和合成器持有并在每帧读取
+    pub fn flag(&self, name: &str) -> Option<Arc<AtomicBool>> {
+        self.sources.iter().find(|(source, _)| source == name).map(|(_, enabled)| enabled.clone())
+    }
+
+    pub fn set_enabled(&self, name: &str, enabled: bool) -> StreamResult<()> {
+        let flag = self.flag(name).ok_or_else(|| StreamError::Config(format!(
+            "Source {:?} does not exist ({})", name, self.names().join(", ")
+        )))?;
+        if flag.swap(enabled, Ordering::Relaxed) != enabled {
+            info!("{} source {:?}", if enabled { "Enabled" } else { "Disabled" }, name);
+        }
+        Ok(())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.sources.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    /// 各视频源当前的 (名称, 是否开启)
+    pub fn status(&self) -> Vec<(String, bool)> {
+        self.sources.iter().map(|(name, enabled)| (name.clone(), enabled.load(Ordering::Relaxed))).collect()
+    }
+}
+
+/// 开启或关闭视频源或混音输入 (关闭即静音)，enabled 为 None 时切换；返回之后是否开启
+pub fn set_enabled(controls: &ClientControls, name: &str, enabled: Option<bool>) -> StreamResult<bool> {
+    if let Some(flag) = controls.sources.flag(name) {
+        let enabled = enabled.unwrap_or(!flag.load(Ordering::Relaxed));
+        controls.sources.set_enabled(name, enabled)?;
+        return Ok(enabled);
+    }
+
+    let mixer = controls.mixer.as_ref()
+        .filter(|mixer| mixer.names().contains(&name))
+        .ok_or_else(|| {
+            let mut names = controls.sources.names();
+            names.extend(controls.mixer.as_ref().map(|mixer| mixer.names()).unwrap_or_default());
+            StreamError::Config(format!("Source {:?} does not exist ({})", name, names.join(", ")))
+        })?;
+    match enabled {
+        Some(enabled) => {
+            mixer.set_muted(name, !enabled)?;
+            Ok(enabled)
+        }
+        None => Ok(!mixer.toggle_muted(name)?),
+    }
+}
@@ -0,0 +1,218 @@
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tracing::{info, warn, debug};
+
+use game_stream_common::{ClientStats, GamepadReport, StatsConfig};
+use crate::bitrate::BitrateControl;
+
+/// 本地 JSON 接口读取请求时的缓冲区大小，请求内容不使用
+const REQUEST_BUFFER_SIZE: usize = 4096;
+
+/// 推流统计计数器 - 编码和推流任务更新，汇总任务定期生成 ClientStats
+#[derive(Clone)]
+pub struct StatsRecorder {
+    inner: Arc<Counters>,
+}
+
+struct Counters {
+    encoded_frames: AtomicU64,
+    dropped_frames: AtomicU64,
+    sent_bytes: AtomicU64,
+    queue_depth: AtomicUsize,
+    encoder_queue: AtomicUsize, // 等待编码的视频帧数
+    rtt: AtomicU64, // 微秒，0 表示尚未测得
+    reconnects: AtomicU32,
+    link_overhead: Mutex<Option<(f64, f64)>>, // 最近一次链路统计的 FEC 和重传开销 (%)
+    silent_audio_inputs: Mutex<Vec<String>>,
+    remote_gamepads: Mutex<Vec<GamepadReport>>,
+    latest: watch::Sender<Option<ClientStats>>,
+}
+
+impl StatsRecorder {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Counters {
+                encoded_frames: AtomicU64::new(0),
+                dropped_frames: AtomicU64::new(0),
+                sent_bytes: AtomicU64::new(0),
+                queue_depth: AtomicUsize::new(0),
+                encoder_queue: AtomicUsize::new(0),
+                rtt: AtomicU64::new(0),
+                reconnects: AtomicU32::new(0),
+                link_overhead: Mutex::new(None),
+                silent_audio_inputs: Mutex::new(Vec::new()),
+                remote_gamepads: Mutex::new(Vec::new()),
+                latest: watch::channel(None).0,
+            }),
+        }
+    }
+
+    /// 编码器输出了视频帧
+    pub fn record_encoded(&self, frames: u64) {
+        self.inner.encoded_frames.fetch_add(frames, Ordering::Relaxed);
+    }
+
+    /// 编码跟不上而丢弃了视频帧
+    pub fn record_dropped(&self, frames: u64) {
+        self.inner.dropped_frames.fetch_add(frames, Ordering::Relaxed);
+    }
+
+    /// 推送了一个包，以及推送后的发送队列和 RTT
+    pub fn record_sent(&self, bytes: usize, queue_depth: usize, rtt: Option<Duration>) {
+        self.inner.sent_bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.inner.queue_depth.store(queue_depth, Ordering::Relaxed);
+        if let Some(rtt) = rtt {
+            self.inner.rtt.store(rtt.as_micros().max(1) as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// 编码队列中等待的视频帧数
+    pub fn record_encoder_queue(&self, frames: usize) {
+        self.inner.encoder_queue.store(frames, Ordering::Relaxed);
+    }
+
+    pub fn record_reconnect(&self) {
+        self.inner.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// UDP 推流的链路统计：FEC 校验包和重传包占数据包的百分比
+    pub fn record_link_overhead(&self, fec: f64, retransmit: f64) {
+        *self.inner.link_overhead.lock().unwrap_or_else(|e| e.into_inner()) = Some((fec, retransmit));
+    }
+
+    /// 当前持续无声的混音输入
+    pub fn record_silent_audio_inputs(&self, inputs: Vec<String>) {
+        *self.inner.silent_audio_inputs.lock().unwrap_or_else(|e| e.into_inner()) = inputs;
+    }
+
+    /// 观看者手柄的延迟报告
+    pub fn record_remote_gamepads(&self, reports: Vec<GamepadReport>) {
+        *self.inner.remote_gamepads.lock().unwrap_or_else(|e| e.into_inner()) = reports;
+    }
+
+    pub fn remote_gamepads(&self) -> Vec<GamepadReport> {
+        self.inner.remote_gamepads.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// 当前的累计值，供需要更短统计周期的使用者 (如画面上的统计叠加) 自行计算速率
+    pub fn totals(&self) -> StatsTotals {
+        let counters = &self.inner;
+        StatsTotals {
+            encoded_frames: counters.encoded_frames.load(Ordering::Relaxed),
+            dropped_frames: counters.dropped_frames.load(Ordering::Relaxed),
+            sent_bytes: counters.sent_bytes.load(Ordering::Relaxed),
+            encoder_queue: counters.encoder_queue.load(Ordering::Relaxed),
+        }
+    }
+
+    /// 订阅汇总后的统计，每个统计周期更新一次
+    pub fn subscribe(&self) -> watch::Receiver<Option<ClientStats>> {
+        self.inner.latest.subscribe()
+    }
+}
+
+/// 统计计数器的累计值
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StatsTotals {
+    pub encoded_frames: u64,
+    pub dropped_frames: u64,
+    pub sent_bytes: u64,
+    pub encoder_queue: usize,
+}
+
+impl Default for StatsRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 启动统计汇总任务，配置了 listen 时同时开启本地 JSON 接口
+pub async fn spawn_reporter(config: &StatsConfig, recorder: StatsRecorder, bitrate_control: BitrateControl) {
+    if let Some(address) = &config.listen {
+        match TcpListener::bind(address).await {
+            Ok(listener) => {
+                info!("Serving client stats on http://{}", address);
+                tokio::spawn(serve_stats(listener, recorder.subscribe()));
+            }
+            Err(e) => warn!("Cannot listen for stats on {}: {}", address, e),
+        }
+    }
+
+    let interval = Duration::from_secs(config.interval.max(1));
+    tokio::spawn(async move {
+        let counters = &recorder.inner;
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        let (mut last_encoded, mut last_dropped, mut last_sent) = (0, 0, 0);
+        let mut last_report = Instant::now();
+        loop {
+            ticker.tick().await;
+            let elapsed = last_report.elapsed().as_secs_f64();
+            last_report = Instant::now();
+
+            let encoded = counters.encoded_frames.load(Ordering::Relaxed);
+            let dropped = counters.dropped_frames.load(Ordering::Relaxed);
+            let sent = counters.sent_bytes.load(Ordering::Relaxed);
+            let link_overhead = *counters.link_overhead.lock().unwrap_or_else(|e| e.into_inner());
+            let stats = ClientStats {
+                timestamp: chrono::Utc::now().timestamp_millis(),
+                encoded_fps: (encoded - last_encoded) as f64 / elapsed,
+                dropped_frames: dropped - last_dropped,
+                total_dropped_frames: dropped,
+                target_bitrate: bitrate_control.target(),
+                send_bitrate: (sent - last_sent) as f64 * 8.0 / 1000.0 / elapsed,
+                queue_depth: counters.queue_depth.load(Ordering::Relaxed),
+                rtt_ms: match counters.rtt.load(Ordering::Relaxed) {
+                    0 => None,
+                    micros => Some(micros as f64 / 1000.0),
+                },
+                reconnects: counters.reconnects.load(Ordering::Relaxed),
+                fec_overhead: link_overhead.map(|(fec, _)| fec),
+                retransmit_overhead: link_overhead.map(|(_, retransmit)| retransmit),
+                silent_audio_inputs: counters.silent_audio_inputs.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+                remote_gamepads: counters.remote_gamepads.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+            };
+            (last_encoded, last_dropped, last_sent) = (encoded, dropped, sent);
+
+            info!("Stats: {:.1} fps encoded, {} frames dropped, bitrate {} kbps (sending {:.0} kbps), {} packets queued, rtt {}, {} reconnects{}",
+                  stats.encoded_fps, stats.dropped_frames, stats.target_bitrate, stats.send_bitrate, stats.queue_depth,
+                  stats.rtt_ms.map_or("-".to_string(), |rtt| format!("{:.1} ms", rtt)), stats.reconnects,
+                  link_overhead.map_or(String::new(), |(fec, retransmit)| {
+                      format!(", overhead {:.1}% FEC + {:.1}% retransmit", fec, retransmit)
+                  }));
+            counters.latest.send_replace(Some(stats));
+        }
+    });
+}
+
+/// 本地 JSON 接口：任何请求都返回最近一次汇总的统计，尚未汇总时返回 503
+async fn serve_stats(listener: TcpListener, latest: watch::Receiver<Option<ClientStats>>) {
+    loop {
+        let Ok((mut stream, peer)) = listener.accept().await else {
+            continue;
+        };
+        let latest = latest.clone();
+        tokio::spawn(async move {
+            let mut buffer = [0u8; REQUEST_BUFFER_SIZE];
+            let _ = stream.read(&mut buffer).await;
+
+            let stats = latest.borrow().clone();
+            let (status, body) = match stats {
+                Some(stats) => ("200 OK", serde_json::to_string(&stats).unwrap_or_default()),
+                None => ("503 Service Unavailable", "{\"error\":\"stats not available yet\"}".to_string()),
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status, body.len(), body,
+            );
+            if let Err(e) = stream.write_all(response.as_bytes()).await {
+                debug!("Failed to send stats to {}: {}", peer, e);
+            }
+            let _ = stream.shutdown().await;
+        });
+    }
+}
@@ -0,0 +1,201 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use bytes::Bytes;
+use tokio::sync::mpsc;
+use tracing::{info, warn, debug};
+
+use game_stream_common::{OverlayCorner, StatsOverlayConfig, VideoPixelFormat};
+use crate::bitrate::BitrateControl;
+use crate::capture::{CapturedFrame, FrameType};
+use crate::compositor::LayerFrame;
+use crate::overlay::TextOverlay;
+use crate::stats::{StatsRecorder, StatsTotals};
+
+/// 叠加内容的刷新间隔，速率按这个间隔计算
+const UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 叠加层与画面边缘的距离 (像素)
+const MARGIN: u32 = 16;
+
+const TEXT_COLOR: [u8; 4] = [255, 255, 255, 255];
+const BACKGROUND: [u8; 4] = [0, 0, 0, 160];
+
+/// 统计叠加的开关，推流中可通过终端命令切换
+#[derive(Clone)]
+pub struct StatsOverlay {
+    visible: Arc<AtomicBool>,
+}
+
+impl StatsOverlay {
+    pub fn new(visible: bool) -> Self {
+        Self { visible: Arc::new(AtomicBool::new(visible)) }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible.load(Ordering::Relaxed)
+    }
+
+    pub fn set_visible(&self, visible: bool) {
+        if self.visible.swap(visible, Ordering::Relaxed) != visible {
+            info!("Stats overlay {}", if visible { "shown" } else { "hidden" });
+        }
+    }
+
+    /// 切换显示/隐藏，返回切换后是否显示
+    pub fn toggle(&self) -> bool {
+        let visible = !self.is_visible();
+        self.set_visible(visible);
+        visible
+    }
+}
+
+/// 统计叠加的绘制 - 位于编码之前，显示时把最近一秒的帧率、码率、丢帧和编码队列绘制到视频帧上
+///
+/// 字体在第一次显示时加载；只支持内存中的 8 位 RGB 画面，其他格式原样转发
+pub struct StatsOverlayRenderer {
+    overlay: StatsOverlay,
+    config: StatsOverlayConfig,
+    stats: StatsRecorder,
+    bitrate_control: BitrateControl,
+    queue_capacity: usize,
+    text: Option<TextOverlay>,
+    label: Option<Arc<LayerFrame>>,
+    last_totals: StatsTotals,
+    last_update: Instant,
+    unsupported_logged: bool,
+}
+
+impl StatsOverlayRenderer {
+    pub fn new(
+        overlay: StatsOverlay,
+        config: &StatsOverlayConfig,
+        stats: StatsRecorder,
+        bitrate_control: BitrateControl,
+        queue_capacity: usize,
+    ) -> Self {
+        Self {
+            overlay,
+            config: config.clone(),
+            last_totals: stats.totals(),
+            stats,
+            bitrate_control,
+            queue_capacity,
+            text: None,
+            label: None,
+            last_update: Instant::now(),
+            unsupported_logged: false,
+        }
+    }
+
+    pub async fn run(
+        mut self,
+        mut frame_receiver: mpsc::UnboundedReceiver<CapturedFrame>,
+        frame_sender: mpsc::UnboundedSender<CapturedFrame>,
+    ) {
+        while let Some(mut frame) = frame_receiver.recv().await {
+            if self.overlay.is_visible() && matches!(frame.frame_type, FrameType::Video) {
+                if self.label.is_none() || self.last_update.elapsed() >= UPDATE_INTERVAL {
+                    self.update();
+                }
+                if let Some(label) = self.label.clone() {
+                    frame = self.draw(frame, &label);
+                }
+            }
+
+            if frame_sender.send(frame).is_err() {
+                break;
+            }
+        }
+    }
+
+    /// 按上次刷新以来的计数计算速率，重新绘制文字
+    fn update(&mut self) {
+        let totals = self.stats.totals();
+        let elapsed = self.last_update.elapsed().as_secs_f64();
+        let measured = self.label.is_some() && elapsed > 0.0;
+        let rate = |current: u64, previous: u64| current.saturating_sub(previous) as f64 / elapsed;
+        let text = if measured {
+            format!(
+                "{:.1} fps\n{} kbps (sending {:.0})\n{:.0} dropped/s ({} total)\nencoder queue {}/{}",
+                rate(totals.encoded_frames, self.last_totals.encoded_frames),
+                self.bitrate_control.target(),
+                rate(totals.sent_bytes, self.last_totals.sent_bytes) * 8.0 / 1000.0,
+                rate(totals.dropped_frames, self.last_totals.dropped_frames),
+                totals.dropped_frames, totals.encoder_queue, self.queue_capacity,
+            )
+        } else {
+            // 第一次显示时还没有可计算速率的间隔
+            format!(
+                "- fps\n{} kbps\n{} dropped\nencoder queue {}/{}",
+                self.bitrate_control.target(), totals.dropped_frames, totals.encoder_queue, self.queue_capacity,
+            )
+        };
+        self.last_totals = totals;
+        self.last_update = Instant::now();
+
+        if self.text.is_none() {
+            match TextOverlay::new("", self.config.font.as_deref(), self.config.font_size, TEXT_COLOR, Some(BACKGROUND)) {
+                Ok(text) => self.text = Some(text),
+                Err(e) => {
+                    warn!("Stats overlay unavailable: {}", e);
+                    self.overlay.set_visible(false);
+                    return;
+                }
+            }
+        }
+        self.label = self.text.as_mut().and_then(|overlay| overlay.render_text(text));
+    }
+
+    fn draw(&mut self, frame: CapturedFrame, label: &LayerFrame) -> CapturedFrame {
+        let (Some(width), Some(height)) = (frame.width, frame.height) else {
+            return frame;
+        };
+        let format = frame.pixel_format.unwrap_or(VideoPixelFormat::Rgba32);
+        let (bytes_per_pixel, order) = match format {
+            VideoPixelFormat::Rgba32 => (4, [0, 1, 2]),
+            VideoPixelFormat::Bgra32 => (4, [2, 1, 0]),
+            VideoPixelFormat::Rgb24 => (3, [0, 1, 2]),
+            VideoPixelFormat::Bgr24 => (3, [2, 1, 0]),
+            format => {
+                if !std::mem::replace(&mut self.unsupported_logged, true) {
+                    debug!("Stats overlay cannot draw on {:?} frames", format);
+                }
+                return frame;
+            }
+        };
+        if frame.data.len() < format.frame_size(width, height) {
+            return frame;
+        }
+
+        let columns = label.width.min(width.saturating_sub(MARGIN * 2));
+        let rows = label.height.min(height.saturating_sub(MARGIN * 2));
+        let left = match self.config.corner {
+            OverlayCorner::TopLeft | OverlayCorner::BottomLeft => MARGIN,
+            OverlayCorner::TopRight | OverlayCorner::BottomRight => width.saturating_sub(MARGIN + columns),
+        };
+        let top = match self.config.corner {
+            OverlayCorner::TopLeft | OverlayCorner::TopRight => MARGIN,
+            OverlayCorner::BottomLeft | OverlayCorner::BottomRight => height.saturating_sub(MARGIN + rows),
+        };
+
+        let mut data = Vec::from(frame.data);
+        for row in 0..rows {
+            for column in 0..columns {
+                let source = ((row * label.width + column) * 4) as usize;
+                let source = &label.data[source..source + 4];
+                let alpha = source[3] as u32;
+                if alpha == 0 {
+                    continue;
+                }
+                let target = (((top + row) * width + left + column) * bytes_per_pixel) as usize;
+                for (channel, offset) in order.into_iter().enumerate() {
+                    let value = &mut data[target + offset];
+                    *value = ((source[channel] as u32 * alpha + *value as u32 * (255 - alpha) + 127) / 255) as u8;
+                }
+            }
+        }
+
+        CapturedFrame { data: Bytes::from(data), ..frame }
+    }
+}
@@ -0,0 +1,161 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use bytes::Bytes;
+use tokio::sync::{mpsc, watch};
+use tracing::{info, warn};
+use image::imageops;
+
+use game_stream_common::{PauseConfig, VideoPixelFormat};
+use crate::capture::{CapturedFrame, FrameType};
+use crate::encoder::KeyframeRequest;
+
+/// 推流的开始/停止和暂停状态，由全局快捷键和终端命令切换
+///
+/// 停止时结束当前的推流管线并断开连接，进程保留，重新开始时重建管线；
+/// 暂停时连接保持，画面换成占位画面，声音静音
+#[derive(Clone)]
+pub struct StreamControl {
+    streaming: Arc<watch::Sender<bool>>,
+    paused: Arc<AtomicBool>,
+}
+
+impl StreamControl {
+    pub fn new(streaming: bool) -> Self {
+        Self {
+            streaming: Arc::new(watch::Sender::new(streaming)),
+            paused: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn is_streaming(&self) -> bool {
+        *self.streaming.borrow()
+    }
+
+    pub fn set_streaming(&self, streaming: bool) {
+        if self.streaming.send_replace(streaming) != streaming {
+            info!("{}", if streaming { "Starting stream" } else { "Stopping stream" });
+        }
+    }
+
+    /// 切换开始/停止，返回切换后是否在推流
+    pub fn toggle_streaming(&self) -> bool {
+        let streaming = !self.is_streaming();
+        self.set_streaming(streaming);
+        streaming
+    }
+
+    /// 等待推流状态变为 streaming
+    pub async fn wait_until(&self, streaming: bool) {
+        let mut receiver = self.streaming.subscribe();
+        // 发送端与控制句柄同时存在，不会关闭
+        let _ = receiver.wait_for(|state| *state == streaming).await;
+    }
+
+    /// 推流状态的变化，嵌入客户端的程序据此更新界面
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.streaming.subscribe()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        if self.paused.swap(paused, Ordering::Relaxed) != paused {
+            info!("{}", if paused { "Stream paused" } else { "Stream resumed" });
+        }
+    }
+
+    /// 切换暂停/继续，返回切换后是否暂停
+    pub fn toggle_paused(&self) -> bool {
+        let paused = !self.is_paused();
+        self.set_paused(paused);
+        paused
+    }
+}
+
+impl Default for StreamControl {
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+/// 编码分辨率的占位画面：纯色背景上绘制缩放后的图片
+pub fn slate_frame(config: &PauseConfig, width: u32, height: u32) -> CapturedFrame {
+    let [red, green, blue, alpha] = config.slate_color;
+    let mut data = [red, green, blue, alpha].repeat((width * height) as usize);
+
+    let image = config.slate_image.as_ref().and_then(|path| match image::open(path) {
+        Ok(image) => Some(imageops::resize(&image.to_rgba8(), width, height, imageops::FilterType::Triangle)),
+        Err(e) => {
+            warn!("Failed to load pause slate image {}, using the slate color: {}", path, e);
+            None
+        }
+    });
+    if let Some(image) = image {
+        for (target, source) in data.chunks_exact_mut(4).zip(image.as_raw().chunks_exact(4)) {
+            let opacity = source[3] as u32;
+            for channel in 0..3 {
+                target[channel] = ((source[channel] as u32 * opacity + target[channel] as u32 * (255 - opacity)) / 255) as u8;
+            }
+            target[3] = target[3].max(source[3]);
+        }
+    }
+
+    CapturedFrame {
+        frame_type: FrameType::Video,
+        data: Bytes::from(data),
+        timestamp: 0,
+        width: Some(width),
+        height: Some(height),
+        pixel_format: Some(VideoPixelFormat::Rgba32),
+    }
+}
+
+/// 编码之前应用暂停和停止：暂停时视频帧换成占位画面、音频帧换成静音，时间戳照常推进，
+/// 恢复后的第一帧请求编码为关键帧；停止时不再转发，通道关闭后编码和推流任务依次结束，连接正常关闭
+///
+/// live 为 false 时管线只为回放缓存运行，开始推流时结束
+pub async fn gate_frames(
+    control: StreamControl,
+    live: bool,
+    slate: CapturedFrame,
+    keyframe_request: KeyframeRequest,
+    mut frame_receiver: mpsc::UnboundedReceiver<CapturedFrame>,
+    frame_sender: mpsc::UnboundedSender<CapturedFrame>,
+) {
+    let mut slate_shown = false;
+
+    loop {
+        let mut frame = tokio::select! {
+            frame = frame_receiver.recv() => match frame {
+                Some(frame) => frame,
+                None => break,
+            },
+            _ = control.wait_until(!live) => break,
+        };
+
+        let paused = control.is_paused();
+        match frame.frame_type {
+            FrameType::Audio if paused => {
+                // 16 位有符号 PCM，全零即静音
+                frame.data = Bytes::from(vec![0u8; frame.data.len()]);
+            }
+            FrameType::Audio => {}
+            // 零拷贝捕获的画面也换成内存中的占位画面，编码器随输入切换重新开始
+            FrameType::Video | FrameType::GpuVideo(_) if paused => {
+                frame = CapturedFrame { timestamp: frame.timestamp, ..slate.clone() };
+                slate_shown = true;
+            }
+            FrameType::Video | FrameType::GpuVideo(_) => {
+                if std::mem::take(&mut slate_shown) {
+                    keyframe_request.request(frame.timestamp);
+                }
+            }
+        }
+
+        if frame_sender.send(frame).is_err() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use chrono::{Local, TimeZone, Timelike};
+
+/// 每秒开头闪烁和发出提示音的时长，用于检查音画同步
+const SYNC_MARK_MS: u64 = 200;
+
+/// 提示音频率和幅度 (-20 dBFS)
+const TONE_FREQUENCY: f64 = 1000.0;
+const TONE_AMPLITUDE: f64 = 0.1;
+
+/// 75% 彩条，从左到右
+const BARS: [[u8; 3]; 7] = [
+    [191, 191, 191], [191, 191, 0], [0, 191, 191], [0, 191, 0], [191, 0, 191], [191, 0, 0], [0, 0, 191],
+];
+
+/// 彩条下方的反向色块
+const CASTELLATIONS: [[u8; 3]; 7] = [
+    [0, 0, 191], [0, 0, 0], [191, 0, 191], [0, 0, 0], [0, 191, 191], [0, 0, 0], [191, 191, 191],
+];
+
+/// 5x7 点阵的数字和冒号，每行低 5 位从左到右
+const GLYPHS: [[u8; 7]; 11] = [
+    [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+    [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+    [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+    [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+    [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+    [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+    [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+    [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+    [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+    [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+    [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000],
+];
+
+/// 测试图案 - 彩条、灰阶、移动的方块和时间码，不需要捕获设备即可检查整个推流管线
+///
+/// 时间码为本地时间 (时:分:秒:帧)，与播放端的时钟对比可估算端到端延迟；
+/// 每秒开头时间码旁的方块点亮，与测试音的提示音同时出现
+pub struct TestPattern {
+    width: u32,
+    height: u32,
+    fps: f64,
+    background: Vec<u8>, // 不变的彩条和灰阶，每帧在副本上绘制
+}
+
+impl TestPattern {
+    pub fn new(width: u32, height: u32, fps: f64) -> Self {
+        let (width, height) = (width.max(16), height.max(16));
+        let mut background = vec![0u8; (width * height * 4) as usize];
+        let bars_bottom = height * 2 / 3;
+        let castellations_bottom = height * 3 / 4;
+
+        for (y, row) in background.chunks_exact_mut((width * 4) as usize).enumerate() {
+            let y = y as u32;
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let x = x as u32;
+                let bar = (x * 7 / width) as usize;
+                let [red, green, blue] = if y < bars_bottom {
+                    BARS[bar]
+                } else if y < castellations_bottom {
+                    CASTELLATIONS[bar]
+                } else {
+                    let level = (x * 255 / (width - 1)) as u8;
+                    [level, level, level]
+                };
+                pixel.copy_from_slice(&[red, green, blue, 255]);
+            }
+        }
+
+        Self { width, height, fps, background }
+    }
+
+    /// 绘制 timestamp (Unix 毫秒) 时刻的画面，Rgba32
+    pub fn render(&self, timestamp: u64) -> Vec<u8> {
+        let mut data = self.background.clone();
+        let (width, height) = (self.width, self.height);
+
+        // 方块每 4 秒在彩条区域内往返一次
+        let size = height / 8;
+        let travel = (width - size) as u64;
+        let phase = timestamp % 4000;
+        let offset = if phase < 2000 { phase * travel / 2000 } else { (4000 - phase) * travel / 2000 };
+        let top = (height * 2 / 3 - size) / 2;
+        self.fill_rect(&mut data, offset as u32, top, size, size, [255, 255, 255]);
+
+        // 时间码
+        let time = Local.timestamp_millis_opt(timestamp as i64).single().unwrap_or_else(Local::now);
+        let frame = ((timestamp % 1000) as f64 * self.fps / 1000.0) as u32;
+        let timecode = format!("{:02}:{:02}:{:02}:{:02}", time.hour(), time.minute(), time.second(), frame);
+        let scale = (height / 180).max(1);
+        let (glyph_width, glyph_height) = (6 * scale, 7 * scale);
+        let text_width = glyph_width * timecode.len() as u32;
+        let marker = glyph_height;
+        let box_width = text_width + marker + 4 * scale;
+        let box_height = glyph_height + 2 * scale;
+        let left = width.saturating_sub(box_width) / 2;
+        let top = height * 2 / 3 - box_height - scale;
+        self.fill_rect(&mut data, left, top, box_width, box_height, [0, 0, 0]);
+        for (index, c) in timecode.chars().enumerate() {
+            let glyph = match c {
+                ':' => &GLYPHS[10],
+                c => &GLYPHS[c.to_digit(10).unwrap_or(0) as usize],
+            };
+            let x = left + scale + index as u32 * glyph_width;
+            for (row, bits) in glyph.iter().enumerate() {
+                for column in 0..5 {
+                    if bits & (0b10000 >> column) != 0 {
+                        self.fill_rect(&mut data, x + column * scale, top + scale + row as u32 * scale, scale, scale, [255, 255, 255]);
+                    }
+                }
+            }
+        }
+
+        // 同步标记
+        if timestamp % 1000 < SYNC_MARK_MS {
+            self.fill_rect(&mut data, left + text_width + 3 * scale, top + scale, marker, marker, [255, 255, 255]);
+        }
+
+        data
+    }
+
+    fn fill_rect(&self, data: &mut [u8], x: u32, y: u32, width: u32, height: u32, [red, green, blue]: [u8; 3]) {
+        let right = (x + width).min(self.width);
+        let bottom = (y + height).min(self.height);
+        for row in y.min(bottom)..bottom {
+            let start = ((row * self.width + x.min(right)) * 4) as usize;
+            let end = ((row * self.width + right) * 4) as usize;
+            for pixel in data[start..end].chunks_exact_mut(4) {
+                pixel.copy_from_slice(&[red, green, blue, 255]);
+            }
+        }
+    }
+}
+
+/// 测试音 - 每秒开头 200ms 的 1 kHz 正弦波，按墙上时钟对齐，与测试图案的同步标记同时出现
+pub struct ToneGenerator {
+    sample_rate: u32,
+    channels: u32,
+    start: u64,     // 第一个采样的 Unix 毫秒
+    generated: u64, // 已生成的采样数 (每声道)
+}
+
+impl ToneGenerator {
+    pub fn new(sample_rate: u32, channels: u32, start: u64) -> Self {
+        Self { sample_rate: sample_rate.max(1), channels: channels.max(1), start, generated: 0 }
+    }
+
+    /// 生成到 now (Unix 毫秒) 为止的采样，交错的 f32
+    pub fn generate_until(&mut self, now: u64) -> Vec<f32> {
+        let target = now.saturating_sub(self.start) * self.sample_rate as u64 / 1000;
+        let count = target.saturating_sub(self.generated);
+        let mut samples = Vec::with_capacity((count * self.channels as u64) as usize);
+        for index in self.generated..target {
+            let millis = self.start + index * 1000 / self.sample_rate as u64;
+            let value = if millis % 1000 < SYNC_MARK_MS {
+                (std::f64::consts::TAU * TONE_FREQUENCY * index as f64 / self.sample_rate as f64).sin() * TONE_AMPLITUDE
+            } else {
+                0.0
+            };
+            samples.extend(std::iter::repeat_n(value as f32, self.channels as usize));
+        }
+        self.generated = target.max(self.generated);
+        samples
+    }
+}
@@ -0,0 +1,146 @@
+#[cfg(feature = "rtmps")]
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+#[cfg(feature = "rtmps")]
+use tokio_rustls::client::TlsStream;
+#[cfg(feature = "rtmps")]
+use tokio_rustls::rustls::{self, ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+#[cfg(feature = "rtmps")]
+use tokio_rustls::rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+#[cfg(feature = "rtmps")]
+use tokio_rustls::rustls::crypto::CryptoProvider;
+#[cfg(feature = "rtmps")]
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+#[cfg(feature = "rtmps")]
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+#[cfg(feature = "rtmps")]
+use tracing::{debug, warn};
+
+use game_stream_common::{ServerEndpoint, StreamResult, StreamError};
+
+/// RTMPS 的 TLS 连接器 - 创建推流器时加载信任的证书，之后每次 (重新) 连接共用
+#[cfg(feature = "rtmps")]
+pub struct TlsConnector {
+    connector: tokio_rustls::TlsConnector,
+    server_name: ServerName<'static>,
+}
+
+#[cfg(feature = "rtmps")]
+impl TlsConnector {
+    pub fn new(server_config: &ServerEndpoint) -> StreamResult<Self> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| StreamError::Internal(format!("TLS setup failed: {}", e)))?;
+        let config = if server_config.tls_verify {
+            builder.with_root_certificates(root_store(server_config.tls_ca_file.as_deref())?)
+        } else {
+            warn!("TLS certificate verification is disabled, the RTMPS connection is not protected against interception");
+            builder.dangerous().with_custom_certificate_verifier(Arc::new(NoVerification(provider)))
+        }.with_no_client_auth();
+
+        let name = server_config.tls_server_name.clone().unwrap_or_else(|| server_config.host.clone());
+        let server_name = ServerName::try_from(name.clone())
+            .map_err(|_| StreamError::Config(format!("Invalid TLS server name: {}", name)))?;
+
+        Ok(Self {
+            connector: tokio_rustls::TlsConnector::from(Arc::new(config)),
+            server_name,
+        })
+    }
+
+    /// 在已建立的 TCP 连接 (可能经过代理隧道) 上完成 TLS 握手
+    pub async fn connect(&self, stream: TcpStream, timeout: Duration) -> StreamResult<TlsStream<TcpStream>> {
+        let server_name = self.server_name.to_str();
+        match tokio::time::timeout(timeout, self.connector.connect(self.server_name.clone(), stream)).await {
+            Ok(Ok(stream)) => Ok(stream),
+            Ok(Err(e)) => Err(StreamError::Network(format!("TLS handshake with {} failed: {}", server_name, e))),
+            Err(_) => Err(StreamError::Network(format!("Timed out during TLS handshake with {}", server_name))),
+        }
+    }
+}
+
+/// 系统证书库 (没有可用证书时使用内置的 Mozilla 根证书)，加上配置的 CA 文件
+#[cfg(feature = "rtmps")]
+fn root_store(ca_file: Option<&str>) -> StreamResult<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    let native = rustls_native_certs::load_native_certs();
+    for error in &native.errors {
+        debug!("Cannot load system certificates: {}", error);
+    }
+    let (added, _) = roots.add_parsable_certificates(native.certs);
+    if added == 0 {
+        debug!("No system certificates found, using bundled root certificates");
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    }
+
+    if let Some(path) = ca_file {
+        let certs = CertificateDer::pem_file_iter(path)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| StreamError::Config(format!("Cannot read TLS CA file {}: {}", path, e)))?;
+        if certs.is_empty() {
+            return Err(StreamError::Config(format!("No certificates found in TLS CA file {}", path)));
+        }
+        for cert in certs {
+            roots.add(cert).map_err(|e| StreamError::Config(format!("Invalid certificate in {}: {}", path, e)))?;
+        }
+    }
+    Ok(roots)
+}
+
+/// tls_verify = false 时接受任何证书，握手签名仍然校验
+#[cfg(feature = "rtmps")]
+#[derive(Debug)]
+struct NoVerification(Arc<CryptoProvider>);
+
+#[cfg(feature = "rtmps")]
+impl ServerCertVerifier for NoVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// 未启用 rtmps 特性时没有 TLS 连接器，配置 tls = true 的服务器无法连接
+#[cfg(not(feature = "rtmps"))]
+pub enum TlsConnector {}
+
+#[cfg(not(feature = "rtmps"))]
+impl TlsConnector {
+    pub fn new(_server_config: &ServerEndpoint) -> StreamResult<Self> {
+        Err(StreamError::Config("RTMPS is not available, the client was built without the rtmps feature".to_string()))
+    }
+
+    pub async fn connect(&self, _stream: TcpStream, _timeout: Duration) -> StreamResult<TcpStream> {
+        match *self {}
+    }
+}
@@ -0,0 +1,175 @@
+use game_stream_common::{HdrConfig, ToneMappingOperator, VideoPixelFormat, StreamResult, StreamError};
+
+/// scRGB 中 1.0 对应的亮度
+const SCRGB_WHITE_NITS: f32 = 80.0;
+
+/// Hable 曲线的曝光补偿 (与原始 Uncharted 2 实现一致)
+const HABLE_EXPOSURE_BIAS: f32 = 2.0;
+
+/// sRGB 编码查找表的精度
+const SRGB_LUT_SIZE: usize = 4096;
+
+/// BT.2020 到 BT.709 的色域转换矩阵 (线性光)
+const BT2020_TO_BT709: [[f32; 3]; 3] = [
+    [1.6605, -0.5876, -0.0728],
+    [-0.1246, 1.1329, -0.0083],
+    [-0.0182, -0.1006, 1.1187],
+];
+
+/// 色调映射器 - 把 HDR 画面转换为 8 位 sRGB 的 RGBA
+pub struct ToneMapper {
+    operator: ToneMappingOperator,
+    white_point: f32, // 峰值亮度相对 SDR 白色的倍数
+    sdr_white: f32,
+    pq_lut: Vec<f32>, // 10 位 PQ 码值 -> 亮度 (nits)
+    srgb_lut: Vec<u8>, // 线性光 [0, 1] -> sRGB 8 位
+}
+
+impl ToneMapper {
+    pub fn new(config: &HdrConfig) -> Self {
+        let sdr_white = config.sdr_white.max(1.0);
+
+        Self {
+            operator: config.tone_mapping,
+            white_point: (config.peak_luminance / sdr_white).max(1.0),
+            sdr_white,
+            pq_lut: (0..1024).map(|code| pq_to_nits(code as f32 / 1023.0)).collect(),
+            srgb_lut: (0..SRGB_LUT_SIZE)
+                .map(|i| (srgb_encode(i as f32 / (SRGB_LUT_SIZE - 1) as f32) * 255.0).round() as u8)
+                .collect(),
+        }
+    }
+
+    /// 转换一帧 HDR 画面，返回 RGBA
+    pub fn tone_map(&self, data: &[u8], width: u32, height: u32, format: VideoPixelFormat) -> StreamResult<Vec<u8>> {
+        let pixels = width as usize * height as usize;
+        let bytes_per_pixel = match format {
+            VideoPixelFormat::Rgb10a2 => 4,
+            VideoPixelFormat::Rgba16f => 8,
+            _ => return Err(StreamError::Codec(format!("{:?} is not an HDR format", format))),
+        };
+        if data.len() < pixels * bytes_per_pixel {
+            return Err(StreamError::Codec(format!(
+                "HDR frame has {} bytes, expected {} for {}x{}", data.len(), pixels * bytes_per_pixel, width, height
+            )));
+        }
+
+        let mut output = Vec::with_capacity(pixels * 4);
+        for pixel in data.chunks_exact(bytes_per_pixel).take(pixels) {
+            let (rgb, alpha) = match format {
+                VideoPixelFormat::Rgb10a2 => self.decode_hdr10(pixel),
+                _ => self.decode_scrgb(pixel),
+            };
+            let [r, g, b] = self.map(rgb);
+            output.extend_from_slice(&[self.encode(r), self.encode(g), self.encode(b), alpha]);
+        }
+
+        Ok(output)
+    }
+
+    /// HDR10 (BT.2020 PQ) -> 相对 SDR 白色的 BT.709 线性光
+    fn decode_hdr10(&self, pixel: &[u8]) -> ([f32; 3], u8) {
+        let value = u32::from_le_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+        let channel = |shift: u32| self.pq_lut[((value >> shift) & 0x3ff) as usize] / self.sdr_white;
+        let bt2020 = [channel(0), channel(10), channel(20)];
+        let alpha = ((value >> 30) * 85) as u8;
+
+        let rgb = BT2020_TO_BT709.map(|row| {
+            (row[0] * bt2020[0] + row[1] * bt2020[1] + row[2] * bt2020[2]).max(0.0)
+        });
+        (rgb, alpha)
+    }
+
+    /// scRGB (BT.709 线性光，1.0 = 80 nits) -> 相对 SDR 白色的线性光
+    fn decode_scrgb(&self, pixel: &[u8]) -> ([f32; 3], u8) {
+        let channel = |index: usize| {
+            let value = f16_to_f32(u16::from_le_bytes([pixel[index * 2], pixel[index * 2 + 1]]));
+            (value * SCRGB_WHITE_NITS / self.sdr_white).max(0.0)
+        };
+        let alpha = (channel_alpha(f16_to_f32(u16::from_le_bytes([pixel[6], pixel[7]]))) * 255.0).round() as u8;
+        ([channel(0), channel(1), channel(2)], alpha)
+    }
+
+    /// 按亮度映射，保持色相
+    fn map(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let luminance = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+        if luminance <= 0.0 {
+            return [0.0; 3];
+        }
+
+        let mapped = match self.operator {
+            ToneMappingOperator::Reinhard => {
+                luminance * (1.0 + luminance / (self.white_point * self.white_point)) / (1.0 + luminance)
+            }
+            ToneMappingOperator::Hable => {
+                hable(luminance * HABLE_EXPOSURE_BIAS) / hable(self.white_point * HABLE_EXPOSURE_BIAS)
+            }
+            ToneMappingOperator::Aces => aces(luminance / self.white_point * 4.0) / aces(4.0),
+        };
+
+        let scale = mapped / luminance;
+        rgb.map(|channel| (channel * scale).min(1.0))
+    }
+
+    fn encode(&self, linear: f32) -> u8 {
+        self.srgb_lut[(linear.clamp(0.0, 1.0) * (SRGB_LUT_SIZE - 1) as f32) as usize]
+    }
+}
+
+/// SMPTE ST 2084 (PQ) 逆 EOTF，返回亮度 (nits)
+fn pq_to_nits(code: f32) -> f32 {
+    const M1: f32 = 2610.0 / 16384.0;
+    const M2: f32 = 2523.0 / 4096.0 * 128.0;
+    const C1: f32 = 3424.0 / 4096.0;
+    const C2: f32 = 2413.0 / 4096.0 * 32.0;
+    const C3: f32 = 2392.0 / 4096.0 * 32.0;
+
+    let power = code.powf(1.0 / M2);
+    let linear = ((power - C1).max(0.0) / (C2 - C3 * power)).powf(1.0 / M1);
+    linear * 10000.0
+}
+
+fn srgb_encode(linear: f32) -> f32 {
+    if linear <= 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Uncharted 2 filmic 曲线
+fn hable(x: f32) -> f32 {
+    const A: f32 = 0.15;
+    const B: f32 = 0.50;
+    const C: f32 = 0.10;
+    const D: f32 = 0.20;
+    const E: f32 = 0.02;
+    const F: f32 = 0.30;
+    ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F
+}
+
+/// ACES filmic 曲线的近似 (Narkowicz)
+fn aces(x: f32) -> f32 {
+    (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+}
+
+fn channel_alpha(value: f32) -> f32 {
+    if value.is_nan() {
+        1.0
+    } else {
+        value.clamp(0.0, 1.0)
+    }
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
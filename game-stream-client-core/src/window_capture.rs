@@ -0,0 +1,283 @@
+use std::time::{Duration, Instant};
+use regex::Regex;
+use tracing::{info, debug, warn};
+use image::{imageops, RgbaImage};
+
+use game_stream_common::{WindowCaptureConfig, VideoPixelFormat, StreamResult, StreamError};
+
+/// 重新枚举窗口以跟随移动和缩放的间隔
+const TRACK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 尚未捕获到窗口时输出的画面尺寸
+const DEFAULT_FRAME_SIZE: (u32, u32) = (1280, 720);
+
+/// 可捕获的窗口
+#[cfg(feature = "screen-capture")]
+pub(crate) type Window = xcap::Window;
+
+/// 未启用 screen-capture 特性时枚举不到窗口，窗口源只输出占位画面
+#[cfg(not(feature = "screen-capture"))]
+pub(crate) enum Window {}
+
+#[cfg(not(feature = "screen-capture"))]
+impl Window {
+    pub fn all() -> StreamResult<Vec<Window>> {
+        Err(StreamError::Capture("Window capture is not available, the client was built without the screen-capture feature".to_string()))
+    }
+
+    pub fn id(&self) -> u32 { match *self {} }
+    pub fn title(&self) -> &str { match *self {} }
+    pub fn app_name(&self) -> &str { match *self {} }
+    pub fn x(&self) -> i32 { match *self {} }
+    pub fn y(&self) -> i32 { match *self {} }
+    pub fn width(&self) -> u32 { match *self {} }
+    pub fn height(&self) -> u32 { match *self {} }
+    pub fn is_minimized(&self) -> bool { match *self {} }
+    pub fn capture_image(&self) -> StreamResult<RgbaImage> { match *self {} }
+    #[cfg(target_os = "windows")]
+    pub fn process_id(&self) -> u32 { match *self {} }
+}
+
+/// 窗口匹配条件
+pub struct WindowMatcher {
+    title: Option<String>,
+    title_regex: Option<Regex>,
+    process_name: Option<String>,
+    pid: Option<u32>,
+}
+
+impl WindowMatcher {
+    pub fn new(window_title: &str, title_regex: Option<&str>, process_name: Option<&str>, pid: Option<u32>) -> StreamResult<Self> {
+        let title_regex = title_regex
+            .map(|pattern| Regex::new(pattern)
+                .map_err(|e| StreamError::Config(format!("Invalid window title regex {:?}: {}", pattern, e))))
+            .transpose()?;
+
+        let mut matcher = Self {
+            title: Some(window_title.to_string()).filter(|title| !title.is_empty()),
+            title_regex,
+            process_name: process_name.map(normalize_process_name),
+            pid,
+        };
+
+        // 只有 Windows 能取得窗口所属进程的 PID，Linux 上改为匹配该 PID 的进程名
+        #[cfg(not(target_os = "windows"))]
+        if let Some(pid) = matcher.pid.take() {
+            let name = process_name_of(pid)
+                .ok_or_else(|| StreamError::Config(format!("Cannot resolve process {} for window matching", pid)))?;
+            info!("Matching windows of process {} by its name {:?}", pid, name);
+            matcher.process_name = Some(normalize_process_name(&name));
+        }
+
+        if matcher.title.is_none() && matcher.title_regex.is_none() && matcher.process_name.is_none() && matcher.pid.is_none() {
+            return Err(StreamError::Config("Window source needs a title, title regex, process name or PID".to_string()));
+        }
+
+        Ok(matcher)
+    }
+
+    fn matches(&self, window: &Window) -> bool {
+        self.title.as_ref().is_none_or(|title| window.title().contains(title.as_str()))
+            && self.title_regex.as_ref().is_none_or(|regex| regex.is_match(window.title()))
+            && self.process_name.as_ref().is_none_or(|name| normalize_process_name(window.app_name()) == *name)
+            && self.pid.is_none_or(|pid| window_pid(window) == Some(pid))
+    }
+}
+
+/// 捕获到的窗口画面
+pub struct WindowFrame {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+    pub pixel_format: VideoPixelFormat,
+    pub origin: Option<(i32, i32)>, // 窗口左上角的屏幕坐标，占位画面时为 None
+}
+
+/// 窗口跟踪器 - 持续跟随匹配的窗口，窗口不可用时输出黑帧或占位图
+pub struct WindowTracker {
+    matcher: WindowMatcher,
+    window: Option<Window>,
+    last_scan: Option<Instant>,
+    frame_size: (u32, u32),
+    slate: Option<RgbaImage>,
+    scaled_slate: Option<RgbaImage>,
+    available: bool,
+}
+
+impl WindowTracker {
+    pub fn new(matcher: WindowMatcher, config: &WindowCaptureConfig) -> Self {
+        let slate = config.slate_image.as_ref().and_then(|path| match image::open(path) {
+            Ok(slate) => Some(slate.to_rgba8()),
+            Err(e) => {
+                warn!("Failed to load slate image {}, using black frames: {}", path, e);
+                None
+            }
+        });
+
+        Self {
+            matcher,
+            window: None,
+            last_scan: None,
+            frame_size: DEFAULT_FRAME_SIZE,
+            slate,
+            scaled_slate: None,
+            available: true,
+        }
+    }
+
+    /// 捕获一帧画面，窗口不可用时返回占位画面而不是错误
+    pub fn capture(&mut self) -> WindowFrame {
+        if self.last_scan.is_none_or(|last| last.elapsed() >= TRACK_INTERVAL) {
+            self.rescan();
+        }
+
+        let image = self.window.as_ref()
+            .filter(|window| !window.is_minimized())
+            .and_then(|window| match window.capture_image() {
+                Ok(image) => Some((image, (window.x(), window.y()))),
+                Err(e) => {
+                    debug!("Failed to capture window {}: {}", window.title(), e);
+                    None
+                }
+            });
+
+        match image {
+            Some((image, origin)) if image.width() > 0 && image.height() > 0 => {
+                self.set_available(true);
+                self.frame_size = (image.width(), image.height());
+                WindowFrame {
+                    width: image.width(),
+                    height: image.height(),
+                    data: image.into_raw(),
+                    pixel_format: VideoPixelFormat::Rgba32,
+                    origin: Some(origin),
+                }
+            }
+            _ => {
+                self.set_available(false);
+                // 窗口可能已关闭，下一帧重新查找
+                self.last_scan = None;
+                self.placeholder_frame()
+            }
+        }
+    }
+
+    /// 跟踪窗口向外扩展 padding 后的屏幕区域 (x, y, 宽, 高)，窗口不可用时返回 None
+    pub fn bounds(&mut self, padding: u32) -> Option<(i32, i32, u32, u32)> {
+        if self.last_scan.is_none_or(|last| last.elapsed() >= TRACK_INTERVAL) {
+            self.rescan();
+        }
+
+        let padding_offset = padding.min(i32::MAX as u32) as i32;
+        let bounds = self.window.as_ref()
+            .filter(|window| !window.is_minimized() && window.width() > 0 && window.height() > 0)
+            .map(|window| (
+                window.x().saturating_sub(padding_offset),
+                window.y().saturating_sub(padding_offset),
+                window.width().saturating_add(padding.saturating_mul(2)),
+                window.height().saturating_add(padding.saturating_mul(2)),
+            ));
+
+        self.set_available(bounds.is_some());
+        match bounds {
+            Some((_, _, width, height)) => self.frame_size = (width, height),
+            None => self.last_scan = None,
+        }
+        bounds
+    }
+
+    /// 重新枚举窗口，优先保持跟踪同一个窗口
+    fn rescan(&mut self) {
+        self.last_scan = Some(Instant::now());
+
+        let windows = match Window::all() {
+            Ok(windows) => windows,
+            Err(e) => {
+                debug!("Failed to enumerate windows: {}", e);
+                self.window = None;
+                return;
+            }
+        };
+
+        let tracked_id = self.window.as_ref().map(|window| window.id());
+        let mut candidates: Vec<_> = windows.into_iter().filter(|window| self.matcher.matches(window)).collect();
+
+        let index = candidates.iter().position(|window| Some(window.id()) == tracked_id)
+            .or_else(|| {
+                // 新匹配时选择未最小化的最大窗口
+                candidates.iter().enumerate()
+                    .max_by_key(|(_, window)| (!window.is_minimized(), window.width() as u64 * window.height() as u64))
+                    .map(|(index, _)| index)
+            });
+
+        let window = index.map(|index| candidates.swap_remove(index));
+        if let Some(window) = &window {
+            if Some(window.id()) != tracked_id {
+                info!("Tracking window {:?} ({}, id {}) at {}x{}",
+                      window.title(), window.app_name(), window.id(), window.width(), window.height());
+            }
+        }
+        self.window = window;
+    }
+
+    fn set_available(&mut self, available: bool) {
+        if available == self.available {
+            return;
+        }
+        self.available = available;
+
+        if available {
+            info!("Captured window is available again");
+        } else {
+            warn!("Captured window is unavailable, sending {} frames", if self.slate.is_some() { "slate" } else { "black" });
+        }
+    }
+
+    /// 与上一帧相同尺寸的占位画面
+    pub fn placeholder_frame(&mut self) -> WindowFrame {
+        let (width, height) = self.frame_size;
+
+        let data = match &self.slate {
+            Some(slate) => {
+                let scaled = match self.scaled_slate.take() {
+                    Some(scaled) if scaled.dimensions() == (width, height) => scaled,
+                    _ => imageops::resize(slate, width, height, imageops::FilterType::Triangle),
+                };
+                let data = scaled.as_raw().clone();
+                self.scaled_slate = Some(scaled);
+                data
+            }
+            // 不透明黑色
+            None => [0u8, 0, 0, 255].repeat((width * height) as usize),
+        };
+
+        WindowFrame { width, height, data, pixel_format: VideoPixelFormat::Rgba32, origin: None }
+    }
+}
+
+fn normalize_process_name(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    name.strip_suffix(".exe").map(str::to_string).unwrap_or(name)
+}
+
+/// 窗口所属进程的 PID，只有 Windows 可以取得
+#[cfg(target_os = "windows")]
+pub(crate) fn window_pid(window: &Window) -> Option<u32> {
+    Some(window.process_id())
+}
+
+#[cfg(not(target_os = "windows"))]
+pub(crate) fn window_pid(_window: &Window) -> Option<u32> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn process_name_of(pid: u32) -> Option<String> {
+    std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok()
+        .map(|name| name.trim().to_string())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn process_name_of(_pid: u32) -> Option<String> {
+    None
+}
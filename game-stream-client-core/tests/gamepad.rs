@@ -0,0 +1,33 @@
+use game_stream_client_core::gamepad::Gamepads;
+use game_stream_common::{GamepadState, RemoteInputConfig};
+use uuid::Uuid;
+
+fn snapshot(seq: u32, transit_ms: i64) -> GamepadState {
+    GamepadState { seq, sent_at: chrono::Utc::now().timestamp_millis() - transit_ms, ..GamepadState::neutral(0) }
+}
+
+/// 序列号回绕时按差值判断新旧，延迟和抖动按 RFC 3550 平滑，缓冲不超过配置的上限
+#[test]
+fn sequence_wrap_and_jitter() {
+    let config = RemoteInputConfig { gamepad_jitter_buffer_ms: 2, max_gamepads: 1, ..RemoteInputConfig::default() };
+    let mut gamepads = Gamepads::new(&config);
+    let viewer = Uuid::new_v4();
+
+    gamepads.receive(viewer, snapshot(u32::MAX - 1, 50));
+    gamepads.receive(viewer, snapshot(u32::MAX - 2, 50)); // 乱序到达的旧快照
+    gamepads.receive(viewer, snapshot(1, 70));            // 回绕，中间缺失 u32::MAX 和 0
+    gamepads.receive(viewer, snapshot(1, 70));            // 重复
+    gamepads.receive(Uuid::new_v4(), snapshot(0, 50));    // 超过 max_gamepads
+
+    let reports = gamepads.reports();
+    assert_eq!(reports.len(), 1);
+    let report = &reports[0];
+    assert_eq!((report.viewer_id, report.pad), (viewer, 0));
+    assert_eq!(report.received, 2);
+    assert_eq!(report.lost, 2);
+    // 旧快照、重复快照，以及单程延迟超过 基准 + 缓冲 的第二个快照
+    assert_eq!(report.late, 3);
+    assert!((report.latency_ms - (50.0 + 20.0 / 16.0)).abs() < 1.0, "latency {}", report.latency_ms);
+    assert!((report.jitter_ms - 20.0 / 16.0).abs() < 0.5, "jitter {}", report.jitter_ms);
+    assert_eq!(report.buffer_ms, 2.0, "buffer capped at gamepad_jitter_buffer_ms");
+}
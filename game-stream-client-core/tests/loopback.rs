@@ -0,0 +1,90 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use game_stream_client_core::{LoopbackSink, StreamSink, StreamingClient};
+use game_stream_common::mock::MOCK_ENCODER;
+use game_stream_common::{
+    recv_media, AudioSource, ClientConfig, MediaPacket, Rendition, ServerConfig, StreamManager, VideoSource,
+    ViewProtocol, ViewerConnection,
+};
+use game_stream_server::hls::HlsManager;
+
+const STREAM_KEY: &str = "loopback";
+
+/// 从开始推流到观看者收到画面、HLS 切出第一个片段的最长时间
+const DEADLINE: Duration = Duration::from_secs(20);
+
+/// 测试图案和测试音经直通编码器和回环传输推到同一进程中的服务器，观看者和 HLS 都应在限定时间内收到数据，
+/// 不需要 ffmpeg
+#[tokio::test(flavor = "multi_thread")]
+async fn test_pattern_reaches_viewer_and_hls_playlist() {
+    let hls_dir = std::env::temp_dir().join(format!("game-stream-loopback-test-{}", std::process::id()));
+    let mut server_config = ServerConfig::default();
+    server_config.storage.hls_segment_dir = hls_dir.to_string_lossy().into_owned();
+    server_config.storage.hls_segment_duration = 1;
+    let stream_manager = Arc::new(StreamManager::new());
+    let hls_manager = Arc::new(HlsManager::new(&server_config.storage).await.unwrap());
+    tokio::spawn(hls_manager.clone().start(stream_manager.clone()));
+
+    let mut config = ClientConfig::default();
+    config.capture.video_source = VideoSource::TestPattern { width: 320, height: 240 };
+    config.capture.audio_source = AudioSource::TestTone;
+    config.encoding.video.width = 320;
+    config.encoding.video.height = 240;
+    config.encoding.video.keyframe_interval = 1;
+    config.encoding.video.encoder = Some(MOCK_ENCODER.to_string());
+    config.encoding.audio.encoder = Some(MOCK_ENCODER.to_string());
+    config.encoding.hardware_acceleration = false;
+
+    let encoding = config.encoding.clone();
+    let sink_manager = stream_manager.clone();
+    let handle = StreamingClient::builder(config)
+        .transport(move || Ok(Box::new(LoopbackSink::new(sink_manager.clone(), STREAM_KEY, &encoding)) as Box<dyn StreamSink>))
+        .build().await.unwrap()
+        .spawn();
+
+    let result = tokio::time::timeout(DEADLINE, async {
+        let stream = loop {
+            if let Some(stream) = stream_manager.get_stream(STREAM_KEY).await {
+                break stream;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        };
+
+        // 与 WebRTC 观看者相同的订阅方式，等到第一个关键帧 (序列头之后的画面)
+        let mut media = stream.add_viewer(ViewerConnection {
+            id: uuid::Uuid::new_v4(),
+            remote_addr: "127.0.0.1:0".parse().unwrap(),
+            connected_at: chrono::Utc::now(),
+            protocol: ViewProtocol::WebRtc,
+            stream_key: STREAM_KEY.to_string(),
+            user_agent: None,
+            rendition: Rendition::default(),
+        }).await;
+        while let Some(packet) = recv_media(&mut media).await {
+            if matches!(*packet, MediaPacket::Video { is_keyframe: true, .. }) && !packet.is_sequence_header() {
+                break;
+            }
+        }
+
+        loop {
+            if let Ok(playlist) = hls_manager.get_playlist(STREAM_KEY).await {
+                if playlist.contains(".ts") {
+                    break playlist;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    }).await;
+
+    handle.shutdown().await.unwrap();
+    let playlist = result.unwrap_or_else(|_| panic!("stream did not reach the viewer and HLS within {:?}", DEADLINE));
+    let segment = playlist.lines().find(|line| line.ends_with(".ts"))
+        .map(|name| std::fs::read(hls_dir.join(STREAM_KEY).join(name)));
+    let _ = std::fs::remove_dir_all(&hls_dir);
+
+    assert!(playlist.starts_with("#EXTM3U"), "unexpected playlist:\n{}", playlist);
+    let segment = segment.unwrap().expect("segment referenced by the playlist exists");
+    // 切片器还没有封装 MPEG-TS (见 HlsManager::run_segmenter)，只能检查片段有数据
+    assert!(!segment.is_empty(), "segment referenced by the playlist is empty");
+}
@@ -0,0 +1,43 @@
+use game_stream_client_core::remote_input::{InputGrant, RemoteInputControl};
+use game_stream_client_core::stats::StatsRecorder;
+use game_stream_common::{InputEvent, RemoteInput, RemoteInputConfig};
+use uuid::Uuid;
+
+fn key(viewer_id: Uuid, key: &str) -> RemoteInput {
+    RemoteInput { viewer_id, event: InputEvent::Key { key: key.to_string(), pressed: true } }
+}
+
+/// 只注入获得授权的观看者的输入，且输入类型和按键都在 [remote_input] 允许的范围内
+#[tokio::test]
+async fn grants_and_key_allowlist() {
+    let config = RemoteInputConfig {
+        enabled: true,
+        mouse: false,
+        allowed_keys: ["w", "a", "s", "d", "Escape"].map(str::to_string).to_vec(),
+        blocked_keys: vec!["Escape".to_string()],
+        ..RemoteInputConfig::default()
+    };
+    let control = RemoteInputControl::new(&config, StatsRecorder::new()).unwrap();
+    let viewer = Uuid::new_v4();
+    let mouse = RemoteInput { viewer_id: viewer, event: InputEvent::MouseMove { x: 0.5, y: 0.5 } };
+    let button = RemoteInput { viewer_id: viewer, event: InputEvent::GamepadButton { button: "A".to_string(), pressed: true } };
+
+    assert!(!control.admits(&key(viewer, "w")), "viewer without a grant");
+
+    control.grant(viewer, InputGrant::parse("keyboard").unwrap());
+    assert!(control.admits(&key(viewer, "w")));
+    assert!(!control.admits(&key(viewer, "q")), "key outside allowed_keys");
+    assert!(!control.admits(&key(viewer, "Escape")), "blocked key");
+    assert!(!control.admits(&button), "gamepad not granted");
+    assert!(!control.admits(&key(Uuid::new_v4(), "w")), "other viewer");
+
+    control.grant(viewer, InputGrant::parse("").unwrap());
+    assert!(control.admits(&button));
+    assert!(!control.admits(&mouse), "mouse disabled in config");
+
+    control.set_enabled(false);
+    assert!(!control.admits(&key(viewer, "w")), "remote input switched off");
+    control.set_enabled(true);
+    assert!(control.revoke(viewer));
+    assert!(!control.admits(&key(viewer, "w")), "grant revoked");
+}
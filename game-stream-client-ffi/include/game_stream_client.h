@@ -0,0 +1,66 @@
+/*
+ * game-stream-client 的 C 接口 (game-stream-client-ffi 编译出的动态库)
+ *
+ * 返回 int 的函数成功时返回 GSC_OK，失败时返回 GSC_ERROR，原因通过错误回调报告。
+ * 同一个客户端的函数不能在多个线程上同时调用，也不能在错误回调中调用。
+ */
+#ifndef GAME_STREAM_CLIENT_H
+#define GAME_STREAM_CLIENT_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define GSC_OK 0
+#define GSC_ERROR (-1)
+
+typedef struct GscClient GscClient;
+
+/* 错误回调，message 只在回调期间有效；可能在后台线程调用 */
+typedef void (*GscErrorCallback)(const char *message, void *user_data);
+
+/* 推流统计，没有测量值的字段为 -1 */
+typedef struct GscStats {
+    int streaming;                 /* 是否正在推流 */
+    int64_t timestamp;             /* 汇总时间 (Unix 毫秒)，尚未汇总时为 0 */
+    double encoded_fps;
+    uint64_t dropped_frames;       /* 统计周期内丢弃的视频帧 */
+    uint64_t total_dropped_frames;
+    uint32_t target_bitrate;       /* kbps */
+    double send_bitrate;           /* kbps */
+    uint64_t queue_depth;          /* 等待发送的包 */
+    double rtt_ms;
+    uint32_t reconnects;
+} GscStats;
+
+/* 创建客户端，使用默认配置；失败时返回 NULL */
+GscClient *gsc_client_create(void);
+
+/* 设置错误回调，callback 为 NULL 时取消 */
+void gsc_client_set_error_callback(GscClient *client, GscErrorCallback callback, void *user_data);
+
+/* 以 client.toml 格式的字符串设置配置，profile 可为 NULL；推流时不能修改配置 */
+int gsc_client_configure(GscClient *client, const char *config, const char *profile);
+
+/* 从 client.toml 格式的文件读取配置，其余同 gsc_client_configure */
+int gsc_client_configure_file(GscClient *client, const char *path, const char *profile);
+
+/* 开始捕获和推流，已在推流时不做任何事；之后的错误 (如重连次数用完) 通过错误回调报告 */
+int gsc_client_start(GscClient *client);
+
+/* 停止推流并等待连接关闭 */
+int gsc_client_stop(GscClient *client);
+
+/* 读取最近一次汇总的推流统计 */
+int gsc_client_stats(GscClient *client, GscStats *stats);
+
+/* 停止推流并释放客户端 */
+void gsc_client_destroy(GscClient *client);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* GAME_STREAM_CLIENT_H */
@@ -0,0 +1,320 @@
+//! game-stream-client-core 的 C 接口，供 C++/C# 编写的游戏启动器在进程内嵌入推流客户端
+//!
+//! 函数声明见 include/game_stream_client.h。返回 int 的函数成功时返回 GSC_OK，
+//! 失败时返回 GSC_ERROR，并通过 gsc_client_set_error_callback 设置的回调报告原因
+
+use anyhow::{anyhow, Context, Result};
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tokio::task::JoinHandle;
+use tracing::error;
+
+use game_stream_client_core::{profiles, ClientHandle, StreamingClient};
+use game_stream_common::{ClientConfig, ClientStats};
+
+pub const GSC_OK: c_int = 0;
+pub const GSC_ERROR: c_int = -1;
+
+/// 检查客户端是否意外退出的间隔
+const MONITOR_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 错误回调，message 只在回调期间有效
+pub type GscErrorCallback = extern "C" fn(message: *const c_char, user_data: *mut c_void);
+
+#[derive(Clone, Copy)]
+struct ErrorCallback {
+    callback: GscErrorCallback,
+    user_data: *mut c_void,
+}
+
+// user_data 由调用方提供，回调可能在后台线程调用，调用方需保证它可以跨线程使用
+unsafe impl Send for ErrorCallback {}
+
+/// 报告错误的回调，未设置时只记录日志
+#[derive(Clone, Default)]
+struct ErrorReporter(Arc<Mutex<Option<ErrorCallback>>>);
+
+impl ErrorReporter {
+    fn report(&self, error: &anyhow::Error) {
+        let message = format!("{:#}", error);
+        error!("{}", message);
+        let callback = *self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(ErrorCallback { callback, user_data }) = callback {
+            // 消息中的 NUL 字节换成空格，保证能转换为 C 字符串
+            let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+            callback(message.as_ptr(), user_data);
+        }
+    }
+}
+
+/// 推流统计，没有测量值的字段为 -1
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GscStats {
+    pub streaming: c_int, // 是否正在推流
+    pub timestamp: i64,   // 汇总时间 (Unix 毫秒)，尚未汇总时为 0
+    pub encoded_fps: f64,
+    pub dropped_frames: u64,
+    pub total_dropped_frames: u64,
+    pub target_bitrate: u32, // kbps
+    pub send_bitrate: f64,   // kbps
+    pub queue_depth: u64,
+    pub rtt_ms: f64,
+    pub reconnects: u32,
+}
+
+impl GscStats {
+    fn new(streaming: bool, stats: Option<&ClientStats>) -> Self {
+        let mut result = Self {
+            streaming: streaming as c_int,
+            timestamp: 0,
+            encoded_fps: 0.0,
+            dropped_frames: 0,
+            total_dropped_frames: 0,
+            target_bitrate: 0,
+            send_bitrate: 0.0,
+            queue_depth: 0,
+            rtt_ms: -1.0,
+            reconnects: 0,
+        };
+        if let Some(stats) = stats {
+            result.timestamp = stats.timestamp;
+            result.encoded_fps = stats.encoded_fps;
+            result.dropped_frames = stats.dropped_frames;
+            result.total_dropped_frames = stats.total_dropped_frames;
+            result.target_bitrate = stats.target_bitrate;
+            result.send_bitrate = stats.send_bitrate;
+            result.queue_depth = stats.queue_depth as u64;
+            result.rtt_ms = stats.rtt_ms.unwrap_or(-1.0);
+            result.reconnects = stats.reconnects;
+        }
+        result
+    }
+}
+
+/// 不透明的客户端句柄，由 gsc_client_create 创建，gsc_client_destroy 释放
+pub struct GscClient {
+    runtime: Runtime,
+    config: ClientConfig,
+    errors: ErrorReporter,
+    handle: Arc<Mutex<Option<ClientHandle>>>,
+    monitor: Option<JoinHandle<()>>,
+}
+
+impl GscClient {
+    fn new() -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .thread_name("game-stream-client")
+            .build()?;
+        Ok(Self {
+            runtime,
+            config: ClientConfig::default(),
+            errors: ErrorReporter::default(),
+            handle: Arc::new(Mutex::new(None)),
+            monitor: None,
+        })
+    }
+
+    fn is_running(&self) -> bool {
+        self.handle.lock().unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+    }
+
+    fn configure(&mut self, content: &str, profile: Option<&str>) -> Result<()> {
+        if self.is_running() {
+            return Err(anyhow!("Cannot change the configuration while streaming, call gsc_client_stop first"));
+        }
+        let mut table: toml::Table = toml::from_str(content).context("Invalid configuration")?;
+        profiles::apply(&mut table, profile)?;
+        self.config = table.try_into().context("Invalid configuration")?;
+        Ok(())
+    }
+
+    fn start(&mut self) -> Result<()> {
+        if self.is_running() {
+            return Ok(());
+        }
+        // 上一次运行已经退出，监视任务还未报告的错误在这里报告，然后重新创建客户端
+        if let Err(e) = self.stop() {
+            self.errors.report(&e);
+        }
+
+        let client = self.runtime.block_on(StreamingClient::builder(self.config.clone()).build())?;
+        let handle = {
+            let _guard = self.runtime.enter();
+            client.spawn()
+        };
+        *self.handle.lock().unwrap_or_else(|e| e.into_inner()) = Some(handle);
+
+        // 客户端出错退出 (如重连次数用完) 时报告错误
+        let handles = self.handle.clone();
+        let errors = self.errors.clone();
+        self.monitor = Some(self.runtime.spawn(async move {
+            loop {
+                tokio::time::sleep(MONITOR_INTERVAL).await;
+                let finished = {
+                    let mut handle = handles.lock().unwrap_or_else(|e| e.into_inner());
+                    match handle.as_ref() {
+                        Some(running) if running.is_finished() => handle.take(),
+                        Some(_) => continue,
+                        None => return,
+                    }
+                };
+                if let Some(handle) = finished {
+                    if let Err(e) = handle.shutdown().await {
+                        errors.report(&e);
+                    }
+                }
+                return;
+            }
+        }));
+        Ok(())
+    }
+
+    /// 停止推流并释放捕获和编码资源，等待连接关闭
+    fn stop(&mut self) -> Result<()> {
+        if let Some(monitor) = self.monitor.take() {
+            monitor.abort();
+        }
+        let handle = self.handle.lock().unwrap_or_else(|e| e.into_inner()).take();
+        match handle {
+            Some(handle) => self.runtime.block_on(handle.shutdown()),
+            None => Ok(()),
+        }
+    }
+
+    fn stats(&self) -> GscStats {
+        let handle = self.handle.lock().unwrap_or_else(|e| e.into_inner());
+        match handle.as_ref() {
+            Some(handle) => GscStats::new(handle.is_streaming(), handle.stats().as_ref()),
+            None => GscStats::new(false, None),
+        }
+    }
+}
+
+/// 执行一个接口调用，错误和 panic 都通过错误回调报告，不会跨越 C 边界展开
+fn call(client: *mut GscClient, f: impl FnOnce(&mut GscClient) -> Result<()>) -> c_int {
+    // 调用方保证指针来自 gsc_client_create 且未释放，同一客户端不会在多个线程上同时调用
+    let Some(client) = (unsafe { client.as_mut() }) else {
+        return GSC_ERROR;
+    };
+    let errors = client.errors.clone();
+    match catch_unwind(AssertUnwindSafe(|| f(client))) {
+        Ok(Ok(())) => GSC_OK,
+        Ok(Err(e)) => {
+            errors.report(&e);
+            GSC_ERROR
+        }
+        Err(_) => {
+            errors.report(&anyhow!("Internal error (panic) in game-stream-client"));
+            GSC_ERROR
+        }
+    }
+}
+
+/// 读取可为空的 C 字符串参数
+fn optional_str<'a>(value: *const c_char) -> Result<Option<&'a str>> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    // 调用方保证非空指针指向以 NUL 结尾的字符串
+    let value = unsafe { CStr::from_ptr(value) };
+    Ok(Some(value.to_str().context("String argument is not valid UTF-8")?))
+}
+
+/// 调用方传入的输出参数，为 NULL 时返回错误
+fn output<'a, T>(value: *mut T) -> Result<&'a mut T> {
+    // 调用方保证非空指针指向可写的对象
+    unsafe { value.as_mut() }.ok_or_else(|| anyhow!("Output pointer is NULL"))
+}
+
+/// 创建客户端，使用默认配置；失败时返回 NULL
+#[no_mangle]
+pub extern "C" fn gsc_client_create() -> *mut GscClient {
+    match catch_unwind(GscClient::new) {
+        Ok(Ok(client)) => Box::into_raw(Box::new(client)),
+        Ok(Err(e)) => {
+            error!("Failed to create client: {:#}", e);
+            std::ptr::null_mut()
+        }
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// 设置错误回调，callback 为 NULL 时取消；回调可能在后台线程调用
+#[no_mangle]
+pub extern "C" fn gsc_client_set_error_callback(
+    client: *mut GscClient,
+    callback: Option<GscErrorCallback>,
+    user_data: *mut c_void,
+) {
+    call(client, |client| {
+        *client.errors.0.lock().unwrap_or_else(|e| e.into_inner()) =
+            callback.map(|callback| ErrorCallback { callback, user_data });
+        Ok(())
+    });
+}
+
+/// 以 client.toml 格式的字符串设置配置，profile 可为 NULL (使用配置文件中的默认命名配置)；
+/// 推流时不能修改配置
+#[no_mangle]
+pub extern "C" fn gsc_client_configure(client: *mut GscClient, config: *const c_char, profile: *const c_char) -> c_int {
+    call(client, |client| {
+        let config = optional_str(config)?.ok_or_else(|| anyhow!("Configuration is NULL"))?;
+        client.configure(config, optional_str(profile)?)
+    })
+}
+
+/// 从 client.toml 格式的文件读取配置，其余同 gsc_client_configure
+#[no_mangle]
+pub extern "C" fn gsc_client_configure_file(client: *mut GscClient, path: *const c_char, profile: *const c_char) -> c_int {
+    call(client, |client| {
+        let path = optional_str(path)?.ok_or_else(|| anyhow!("Configuration path is NULL"))?;
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read configuration from {}", path))?;
+        client.configure(&content, optional_str(profile)?)
+    })
+}
+
+/// 开始捕获和推流，已在推流时不做任何事；连接建立前返回，之后的错误通过错误回调报告
+#[no_mangle]
+pub extern "C" fn gsc_client_start(client: *mut GscClient) -> c_int {
+    call(client, GscClient::start)
+}
+
+/// 停止推流并等待连接关闭，未在推流时不做任何事
+#[no_mangle]
+pub extern "C" fn gsc_client_stop(client: *mut GscClient) -> c_int {
+    call(client, GscClient::stop)
+}
+
+/// 读取最近一次汇总的推流统计 ([stats] interval)
+#[no_mangle]
+pub extern "C" fn gsc_client_stats(client: *mut GscClient, stats: *mut GscStats) -> c_int {
+    call(client, |client| {
+        let latest = client.stats();
+        *output(stats)? = latest;
+        Ok(())
+    })
+}
+
+/// 停止推流并释放客户端，之后不能再使用该指针
+#[no_mangle]
+pub extern "C" fn gsc_client_destroy(client: *mut GscClient) {
+    if client.is_null() {
+        return;
+    }
+    call(client, GscClient::stop);
+    release(client);
+}
+
+/// 释放 gsc_client_create 创建的客户端
+fn release(client: *mut GscClient) {
+    // 指针来自 gsc_client_create 的 Box::into_raw，调用方保证只释放一次
+    drop(unsafe { Box::from_raw(client) });
+}
@@ -1,279 +0,0 @@
-use anyhow::Result;
-use tokio::sync::mpsc;
-use tracing::{info, warn, error, debug};
-use std::time::{Duration, Instant};
-use bytes::Bytes;
-
-use game_stream_common::{CaptureConfig, VideoSource, AudioSource, StreamResult, StreamError};
-
-/// 捕获的帧数据
-#[derive(Debug, Clone)]
-pub struct CapturedFrame {
-    pub frame_type: FrameType,
-    pub data: Bytes,
-    pub timestamp: u64,
-    pub width: Option<u32>,
-    pub height: Option<u32>,
-}
-
-#[derive(Debug, Clone)]
-pub enum FrameType {
-    Video,
-    Audio,
-}
-
-/// 捕获管理器
-#[derive(Clone)]
-pub struct CaptureManager {
-    config: CaptureConfig,
-    video_capturer: Option<VideoCapturer>,
-    audio_capturer: Option<AudioCapturer>,
-}
-
-impl CaptureManager {
-    pub async fn new(config: &CaptureConfig) -> Result<Self> {
-        info!("Initializing capture manager...");
-        
-        // 初始化视频捕获器
-        let video_capturer = Some(VideoCapturer::new(&config.video_source, config.capture_cursor).await?);
-        
-        // 初始化音频捕获器
-        let audio_capturer = match &config.audio_source {
-            AudioSource::Disabled => None,
-            _ => Some(AudioCapturer::new(&config.audio_source).await?),
-        };
-        
-        Ok(Self {
-            config: config.clone(),
-            video_capturer,
-            audio_capturer,
-        })
-    }
-    
-    pub async fn start_capture(&mut self, frame_sender: mpsc::UnboundedSender<CapturedFrame>) -> StreamResult<()> {
-        info!("Starting capture...");
-        
-        let mut tasks = Vec::new();
-        
-        // 启动视频捕获
-        if let Some(video_capturer) = &mut self.video_capturer {
-            let mut capturer = video_capturer.clone();
-            let sender = frame_sender.clone();
-            
-            let task = tokio::spawn(async move {
-                capturer.start_capture(sender).await
-            });
-            tasks.push(task);
-        }
-        
-        // 启动音频捕获
-        if let Some(audio_capturer) = &mut self.audio_capturer {
-            let mut capturer = audio_capturer.clone();
-            let sender = frame_sender.clone();
-            
-            let task = tokio::spawn(async move {
-                capturer.start_capture(sender).await
-            });
-            tasks.push(task);
-        }
-        
-        // 等待所有捕获任务
-        for task in tasks {
-            if let Err(e) = task.await {
-                error!("Capture task failed: {}", e);
-            }
-        }
-        
-        Ok(())
-    }
-}
-
-/// 视频捕获器
-#[derive(Clone)]
-pub struct VideoCapturer {
-    source: VideoSource,
-    capture_cursor: bool,
-    target_fps: u32,
-}
-
-impl VideoCapturer {
-    pub async fn new(source: &VideoSource, capture_cursor: bool) -> Result<Self> {
-        info!("Initializing video capturer for source: {:?}", source);
-        
-        Ok(Self {
-            source: source.clone(),
-            capture_cursor,
-            target_fps: 30, // 默认30fps
-        })
-    }
-    
-    pub async fn start_capture(&mut self, frame_sender: mpsc::UnboundedSender<CapturedFrame>) -> StreamResult<()> {
-        info!("Starting video capture...");
-        
-        let frame_duration = Duration::from_millis(1000 / self.target_fps as u64);
-        let mut last_capture = Instant::now();
-        
-        loop {
-            let now = Instant::now();
-            if now.duration_since(last_capture) >= frame_duration {
-                match self.capture_frame().await {
-                    Ok(frame) => {
-                        if let Err(_) = frame_sender.send(frame) {
-                            warn!("Failed to send video frame, receiver dropped");
-                            break;
-                        }
-                        last_capture = now;
-                    }
-                    Err(e) => {
-                        error!("Failed to capture video frame: {}", e);
-                        tokio::time::sleep(Duration::from_millis(100)).await;
-                    }
-                }
-            } else {
-                // 等待到下一帧时间
-                let sleep_duration = frame_duration - now.duration_since(last_capture);
-                tokio::time::sleep(sleep_duration).await;
-            }
-        }
-        
-        Ok(())
-    }
-    
-    async fn capture_frame(&self) -> StreamResult<CapturedFrame> {
-        // 使用 xcap 进行屏幕捕获
-        match &self.source {
-            VideoSource::Screen { display_index } => {
-                self.capture_screen(*display_index).await
-            }
-            VideoSource::Window { window_title } => {
-                self.capture_window(window_title).await
-            }
-            VideoSource::Region { x, y, width, height } => {
-                self.capture_region(*x, *y, *width, *height).await
-            }
-        }
-    }
-    
-    async fn capture_screen(&self, display_index: u32) -> StreamResult<CapturedFrame> {
-        // 实际的屏幕捕获实现
-        // 这里需要使用 xcap 库进行实际的屏幕捕获
-        debug!("Capturing screen {}", display_index);
-        
-        // 模拟捕获的屏幕数据
-        let width = 1920;
-        let height = 1080;
-        let data_size = width * height * 4; // RGBA
-        let mock_data = vec![0u8; data_size as usize];
-        
-        Ok(CapturedFrame {
-            frame_type: FrameType::Video,
-            data: Bytes::from(mock_data),
-            timestamp: chrono::Utc::now().timestamp_millis() as u64,
-            width: Some(width),
-            height: Some(height),
-        })
-    }
-    
-    async fn capture_window(&self, window_title: &str) -> StreamResult<CapturedFrame> {
-        debug!("Capturing window: {}", window_title);
-        
-        // 实际的窗口捕获实现
-        // 这里需要使用平台特定的API进行窗口捕获
-        
-        // 模拟捕获的窗口数据
-        let width = 1280;
-        let height = 720;
-        let data_size = width * height * 4; // RGBA
-        let mock_data = vec![0u8; data_size as usize];
-        
-        Ok(CapturedFrame {
-            frame_type: FrameType::Video,
-            data: Bytes::from(mock_data),
-            timestamp: chrono::Utc::now().timestamp_millis() as u64,
-            width: Some(width),
-            height: Some(height),
-        })
-    }
-    
-    async fn capture_region(&self, x: u32, y: u32, width: u32, height: u32) -> StreamResult<CapturedFrame> {
-        debug!("Capturing region: {}x{} at ({}, {})", width, height, x, y);
-        
-        // 实际的区域捕获实现
-        let data_size = width * height * 4; // RGBA
-        let mock_data = vec![0u8; data_size as usize];
-        
-        Ok(CapturedFrame {
-            frame_type: FrameType::Video,
-            data: Bytes::from(mock_data),
-            timestamp: chrono::Utc::now().timestamp_millis() as u64,
-            width: Some(width),
-            height: Some(height),
-        })
-    }
-}
-
-/// 音频捕获器
-#[derive(Clone)]
-pub struct AudioCapturer {
-    source: AudioSource,
-    sample_rate: u32,
-    channels: u32,
-}
-
-impl AudioCapturer {
-    pub async fn new(source: &AudioSource) -> Result<Self> {
-        info!("Initializing audio capturer for source: {:?}", source);
-        
-        Ok(Self {
-            source: source.clone(),
-            sample_rate: 44100,
-            channels: 2,
-        })
-    }
-    
-    pub async fn start_capture(&mut self, frame_sender: mpsc::UnboundedSender<CapturedFrame>) -> StreamResult<()> {
-        info!("Starting audio capture...");
-        
-        // 音频帧大小 (1024 samples per frame)
-        let frame_size = 1024u32;
-        let frame_duration = Duration::from_millis((frame_size as u64 * 1000) / self.sample_rate as u64);
-        
-        loop {
-            match self.capture_audio_frame(frame_size).await {
-                Ok(frame) => {
-                    if let Err(_) = frame_sender.send(frame) {
-                        warn!("Failed to send audio frame, receiver dropped");
-                        break;
-                    }
-                }
-                Err(e) => {
-                    error!("Failed to capture audio frame: {}", e);
-                    tokio::time::sleep(Duration::from_millis(100)).await;
-                    continue;
-                }
-            }
-            
-            tokio::time::sleep(frame_duration).await;
-        }
-        
-        Ok(())
-    }
-    
-    async fn capture_audio_frame(&self, frame_size: u32) -> StreamResult<CapturedFrame> {
-        // 实际的音频捕获实现
-        // 这里需要使用 cpal 库进行实际的音频捕获
-        debug!("Capturing audio frame of size {}", frame_size);
-        
-        // 模拟捕获的音频数据 (16-bit stereo)
-        let data_size = frame_size * self.channels * 2; // 16-bit samples
-        let mock_data = vec![0u8; data_size as usize];
-        
-        Ok(CapturedFrame {
-            frame_type: FrameType::Audio,
-            data: Bytes::from(mock_data),
-            timestamp: chrono::Utc::now().timestamp_millis() as u64,
-            width: None,
-            height: None,
-        })
-    }
-}
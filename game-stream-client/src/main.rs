@@ -1,15 +1,17 @@
 use anyhow::Result;
-use clap::Parser;
+use std::io::IsTerminal;
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 use tracing::{info, error};
 use tracing_subscriber;
 
-mod capture;
-mod encoder;
-mod pusher;
-mod client;
-
-use client::StreamingClient;
-use game_stream_common::ClientConfig;
+use game_stream_client_core::{
+    capture, check, console, devices, hotkeys, profiles, region_picker, schedule, secrets, silence, snapshot,
+    StreamingClient,
+};
+#[cfg(feature = "control-api")]
+use game_stream_client_core::control_api;
+use game_stream_common::{AudioSource, ClientConfig, LatencyMode, VideoSource};
 
 #[derive(Parser)]
 #[command(name = "game-stream-client")]
@@ -19,7 +21,11 @@ struct Args {
     #[arg(short, long, default_value = "client.toml")]
     config: String,
     
-    /// Stream key
+    /// Named profile from [profiles.<name>] in the configuration file
+    #[arg(short, long)]
+    profile: Option<String>,
+    
+    /// Stream key, or env:<VARIABLE> / keychain:<name> to read it from the environment or the OS keychain
     #[arg(short, long)]
     stream_key: Option<String>,
     
@@ -34,25 +40,110 @@ struct Args {
     /// Enable verbose logging
     #[arg(short, long)]
     verbose: bool,
+    
+    /// Print the commented default configuration and exit
+    #[arg(long)]
+    print_default_config: bool,
+    
+    /// Print the JSON Schema of the configuration file and exit
+    #[arg(long)]
+    print_config_schema: bool,
+    
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List displays available for screen capture
+    ListDisplays,
+    /// List windows available for window capture
+    ListWindows,
+    /// List audio input devices
+    ListAudioDevices,
+    /// List the named profiles in the configuration file
+    ListProfiles,
+    /// Drag a rectangle on screen and save it as the Region source in the config file
+    PickRegion,
+    /// Show the captured picture in a preview window without streaming
+    Preview,
+    /// Keep the replay buffer running without streaming until started from a hotkey or the console
+    Replay,
+    /// Stream the built-in test pattern and test tone instead of captured video and audio
+    Test,
+    /// Capture one frame from the configured source and save it as a PNG
+    Snapshot {
+        /// Output file
+        #[arg(short, long, default_value = "snapshot.png")]
+        out: PathBuf,
+    },
+    /// Validate the configuration and probe the capture source and encoder without streaming
+    Check {
+        /// Also connect to the server (RTMP publishes briefly) and disconnect
+        #[arg(long)]
+        connect: bool,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
     
+    // 只输出到标准输出，便于重定向到文件，不初始化日志
+    if args.print_default_config {
+        print!("{}", ClientConfig::TEMPLATE);
+        return Ok(());
+    }
+    if args.print_config_schema {
+        println!("{:#}", ClientConfig::json_schema());
+        return Ok(());
+    }
+    
     // Initialize logging
     let log_level = if args.verbose { "debug" } else { "info" };
     tracing_subscriber::fmt()
-        .with_env_filter(format!("game_stream_client={},game_stream_common={}", log_level, log_level))
+        .with_env_filter(format!(
+            "game_stream_client={},game_stream_client_core={},game_stream_common={}", log_level, log_level, log_level,
+        ))
         .init();
     
+    let preview_only = matches!(args.command, Some(Command::Preview));
+    let replay_only = matches!(args.command, Some(Command::Replay));
+    let test_source = matches!(args.command, Some(Command::Test));
+    let check = match args.command {
+        Some(Command::Check { connect }) => Some(connect),
+        _ => None,
+    };
+    let snapshot = match &args.command {
+        Some(Command::Snapshot { out }) => Some(out.clone()),
+        _ => None,
+    };
+    
+    // 设备枚举命令只打印信息，不启动推流
+    match args.command {
+        Some(Command::ListDisplays) => return devices::list_displays(),
+        Some(Command::ListWindows) => return devices::list_windows(),
+        Some(Command::ListAudioDevices) => return devices::list_audio_devices(),
+        Some(Command::ListProfiles) => return list_profiles(&args.config),
+        Some(Command::PickRegion) => return pick_region(&args.config),
+        Some(Command::Preview) | Some(Command::Replay) | Some(Command::Test) | Some(Command::Check { .. })
+            | Some(Command::Snapshot { .. }) | None => {}
+    }
+    
     info!("Starting game streaming client...");
     
     // Load configuration
-    let mut config = load_config(&args.config).unwrap_or_else(|_| {
-        info!("Using default configuration");
-        ClientConfig::default()
-    });
+    // 指定了命名配置或检查配置时配置文件必须能正确加载
+    let mut config = match load_config(&args.config, args.profile.as_deref()) {
+        Ok(config) => config,
+        Err(e) if args.profile.is_some() || check.is_some() => {
+            return Err(e.context(format!("Cannot load configuration from {}", args.config)));
+        }
+        Err(_) => {
+            info!("Using default configuration");
+            ClientConfig::default()
+        }
+    };
     
     // Override config with command line arguments
     if let Some(stream_key) = args.stream_key {
@@ -64,15 +155,83 @@ async fn main() -> Result<()> {
     if let Some(port) = args.port {
         config.server.port = port;
     }
+    if preview_only {
+        config.preview.enabled = true;
+    }
+    if replay_only {
+        config.replay.enabled = true;
+    }
+    if test_source {
+        info!("Streaming the test pattern and test tone");
+        config.capture.video_source = VideoSource::TestPattern {
+            width: config.encoding.video.width,
+            height: config.encoding.video.height,
+        };
+        config.capture.audio_source = AudioSource::TestTone;
+        config.capture.audio_mixer.inputs.clear();
+    }
+    if config.latency_mode == LatencyMode::UltraLow {
+        info!("Ultra-low latency mode: overriding keyframe interval, B-frames and send buffers");
+        config.apply_latency_mode();
+    }
+    
+    // 流密钥和口令可以从环境变量或系统钥匙串读取，日志中的配置不包含它们
+    secrets::resolve_server_secrets(&mut config.server)?;
     
     info!("Configuration loaded: {:?}", config);
     
+    if let Some(connect) = check {
+        return check::run(&config, connect).await;
+    }
+    if let Some(out) = snapshot {
+        let capture = capture::CaptureManager::new(&config.capture, &config.encoding).await?;
+        snapshot::Snapshot::new(capture, &config.encoding.video.hdr).save(&out).await?;
+        println!("Saved snapshot to {}", out.display());
+        return Ok(());
+    }
+    
+    let hotkeys = hotkeys::Hotkeys::new(&config.hotkeys, &config.capture);
+    let control = config.control.clone();
+    let silence = config.capture.audio_mixer.silence.clone();
+    let schedule = if preview_only { None } else { schedule::Schedule::new(&config.stream.schedule)? };
+    
     // Create and start streaming client
-    let mut client = StreamingClient::new(config).await?;
+    let mut client = StreamingClient::builder(config)
+        .start_stopped(replay_only || schedule.as_ref().is_some_and(schedule::Schedule::waits_for_start))
+        .build().await?;
+    
+    // 定时开始和停止推流，等待开始时间时快捷键和终端命令仍可手动开始
+    if let Some(schedule) = schedule {
+        tokio::spawn(schedule.run(client.controls().stream));
+    }
+    
+    // 全局快捷键在游戏全屏时也能开始/停止、暂停推流、静音麦克风和保存回放
+    if let Some(hotkeys) = hotkeys {
+        hotkeys.spawn(client.controls());
+    }
+    
+    // 推流中麦克风或游戏声音持续无声时警告
+    if !preview_only {
+        silence::spawn_monitor(&silence, client.controls());
+    }
+    
+    // 本地控制接口供 Stream Deck 之类的工具和脚本控制推流
+    #[cfg(feature = "control-api")]
+    control_api::spawn(&control, client.controls()).await;
+    #[cfg(not(feature = "control-api"))]
+    if control.enabled {
+        tracing::warn!("The control API is not available, the client was built without the control-api feature");
+    }
+    
+    // 在终端中输入命令切换显示器、场景或调整混音，输入 help 查看命令
+    if std::io::stdin().is_terminal() {
+        tokio::spawn(console::read_commands(client.controls()));
+    }
     
     // Handle Ctrl+C gracefully
     let client_handle = tokio::spawn(async move {
-        if let Err(e) = client.start().await {
+        let result = if preview_only { client.preview().await } else { client.start().await };
+        if let Err(e) = result {
             error!("Streaming client error: {}", e);
         }
     });
@@ -90,8 +249,40 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-fn load_config(path: &str) -> Result<ClientConfig> {
+fn load_config(path: &str, profile: Option<&str>) -> Result<ClientConfig> {
     let content = std::fs::read_to_string(path)?;
-    let config: ClientConfig = toml::from_str(&content)?;
+    let mut table: toml::Table = toml::from_str(&content)?;
+    profiles::apply(&mut table, profile)?;
+    let config: ClientConfig = table.try_into()?;
     Ok(config)
 }
+
+fn list_profiles(config_path: &str) -> Result<()> {
+    let content = std::fs::read_to_string(config_path)?;
+    let (names, default) = profiles::names(&mut toml::from_str(&content)?)?;
+    if names.is_empty() {
+        println!("No profiles in {}", config_path);
+        return Ok(());
+    }
+
+    println!("Profiles in {}:", config_path);
+    for name in names {
+        let marker = if default.as_deref() == Some(name.as_str()) { "  (default)" } else { "" };
+        println!("  {}{}", name, marker);
+    }
+    Ok(())
+}
+
+fn pick_region(config_path: &str) -> Result<()> {
+    println!("Drag a rectangle with the left mouse button, right-click to cancel");
+
+    let Some(region) = region_picker::pick_region()? else {
+        println!("Selection cancelled");
+        return Ok(());
+    };
+
+    region_picker::save_region(config_path, region)?;
+    println!("Saved Region {{ x = {}, y = {}, width = {}, height = {} }} to {}",
+             region.x, region.y, region.width, region.height, config_path);
+    Ok(())
+}
@@ -0,0 +1,186 @@
+//! StreamManager 并发压测
+//!
+//! 模拟大量并发推流 (创建/移除流) 和 API 请求 (查询/列出流)，
+//! 对比单把 RwLock<HashMap> 的实现和分片后的 StreamManager。
+//!
+//! 运行: cargo run --release -p game-stream-common --example stream_manager_load -- [流数量] [并发任务数]
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use game_stream_common::{
+    AudioCodec, AudioConfig, LiveStream, StreamInfo, StreamManager, VideoCodec, VideoConfig,
+};
+
+/// 每个任务的操作轮数
+const ROUNDS: usize = 200;
+/// 每轮查询次数
+const LOOKUPS_PER_ROUND: usize = 16;
+/// 每隔多少轮列出一次所有流
+const LIST_EVERY: usize = 50;
+
+/// 旧实现: 所有流放在一把 RwLock 后面
+#[derive(Default)]
+struct LockedManager {
+    streams: RwLock<HashMap<String, Arc<LiveStream>>>,
+}
+
+impl LockedManager {
+    async fn create_stream(&self, stream_key: String, info: StreamInfo) {
+        let stream = Arc::new(LiveStream::new(stream_key.clone(), info));
+        self.streams.write().await.insert(stream_key, stream);
+    }
+
+    async fn get_stream(&self, stream_key: &str) -> Option<Arc<LiveStream>> {
+        self.streams.read().await.get(stream_key).cloned()
+    }
+
+    async fn remove_stream(&self, stream_key: &str) -> Option<Arc<LiveStream>> {
+        self.streams.write().await.remove(stream_key)
+    }
+
+    async fn list_streams(&self) -> Vec<(String, Arc<LiveStream>)> {
+        self.streams.read().await.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+}
+
+/// 压测对象
+enum Manager {
+    Locked(LockedManager),
+    Sharded(StreamManager),
+}
+
+impl Manager {
+    async fn create(&self, stream_key: String) {
+        let info = stream_info(stream_key.clone());
+        match self {
+            Manager::Locked(manager) => manager.create_stream(stream_key, info).await,
+            Manager::Sharded(manager) => {
+                let _ = manager.create_stream(stream_key, info).await;
+            }
+        }
+    }
+
+    async fn get(&self, stream_key: &str) -> bool {
+        match self {
+            Manager::Locked(manager) => manager.get_stream(stream_key).await.is_some(),
+            Manager::Sharded(manager) => manager.get_stream(stream_key).await.is_some(),
+        }
+    }
+
+    async fn remove(&self, stream_key: &str) {
+        match self {
+            Manager::Locked(manager) => manager.remove_stream(stream_key).await,
+            Manager::Sharded(manager) => manager.remove_stream(stream_key).await,
+        };
+    }
+
+    async fn list(&self) -> usize {
+        match self {
+            Manager::Locked(manager) => manager.list_streams().await.len(),
+            Manager::Sharded(manager) => manager.list_streams().await.len(),
+        }
+    }
+}
+
+fn stream_info(stream_key: String) -> StreamInfo {
+    StreamInfo {
+        stream_id: Uuid::new_v4(),
+        stream_key,
+        title: None,
+        description: None,
+        created_at: chrono::Utc::now(),
+        is_live: true,
+        viewer_count: 0,
+        video_config: VideoConfig {
+            width: 1920,
+            height: 1080,
+            fps: 30,
+            bitrate: 2500,
+            codec: VideoCodec::H264,
+        },
+        audio_config: AudioConfig {
+            sample_rate: 44100,
+            channels: 2,
+            bitrate: 128,
+            codec: AudioCodec::Aac,
+        },
+        thumbnail_url: None,
+        tags: Default::default(),
+    }
+}
+
+/// 运行一轮压测，返回 (总操作数, 耗时)
+async fn run(manager: Arc<Manager>, streams: usize, tasks: usize) -> (usize, Duration) {
+    // 预先创建常驻流
+    for i in 0..streams {
+        manager.create(format!("resident_{}", i)).await;
+    }
+
+    let start = Instant::now();
+    let mut handles = Vec::with_capacity(tasks);
+
+    for task in 0..tasks {
+        let manager = manager.clone();
+        handles.push(tokio::spawn(async move {
+            let mut ops = 0;
+            for round in 0..ROUNDS {
+                // 推流端: 频繁上下线
+                let key = format!("publisher_{}_{}", task, round);
+                manager.create(key.clone()).await;
+                ops += 1;
+
+                // API / 观看端: 查询常驻流
+                for lookup in 0..LOOKUPS_PER_ROUND {
+                    let index = (task * 7919 + round * 31 + lookup) % streams;
+                    assert!(manager.get(&format!("resident_{}", index)).await);
+                    ops += 1;
+                }
+
+                if round % LIST_EVERY == 0 {
+                    manager.list().await;
+                    ops += 1;
+                }
+
+                manager.remove(&key).await;
+                ops += 1;
+
+                tokio::task::yield_now().await;
+            }
+            ops
+        }));
+    }
+
+    let mut total = 0;
+    for handle in handles {
+        total += handle.await.expect("load task panicked");
+    }
+
+    (total, start.elapsed())
+}
+
+fn report(name: &str, ops: usize, elapsed: Duration) -> f64 {
+    let rate = ops as f64 / elapsed.as_secs_f64();
+    println!("{:<24} {:>10} ops in {:>8.1?}  ({:>12.0} ops/s)", name, ops, elapsed, rate);
+    rate
+}
+
+#[tokio::main]
+async fn main() {
+    let mut args = std::env::args().skip(1);
+    let streams: usize = args.next().and_then(|v| v.parse().ok()).unwrap_or(5000);
+    let tasks: usize = args.next().and_then(|v| v.parse().ok()).unwrap_or(256);
+
+    println!("streams: {}, tasks: {}, rounds per task: {}", streams, tasks, ROUNDS);
+
+    let (ops, elapsed) = run(Arc::new(Manager::Locked(LockedManager::default())), streams, tasks).await;
+    let locked = report("RwLock<HashMap>", ops, elapsed);
+
+    let (ops, elapsed) = run(Arc::new(Manager::Sharded(StreamManager::new())), streams, tasks).await;
+    let sharded = report("StreamManager (sharded)", ops, elapsed);
+
+    println!("speedup: {:.2}x", sharded / locked);
+}
@@ -0,0 +1,107 @@
+use bytes::Bytes;
+
+/// ADTS 头部长度 (不含 CRC)
+const ADTS_HEADER_SIZE: usize = 7;
+
+/// 采样率索引表 (ISO 14496-3 1.6.3.4)
+const SAMPLE_RATES: [u32; 13] = [96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350];
+
+/// AAC-LC 的 AudioSpecificConfig，与 ffmpeg 按相同采样率和声道数编码时 ADTS 头部对应的配置一致
+///
+/// 采样率不在索引表中或声道数超过 7 时返回 None
+pub fn audio_specific_config(sample_rate: u32, channels: u32) -> Option<Bytes> {
+    let frequency_index = SAMPLE_RATES.iter().position(|&rate| rate == sample_rate)? as u8;
+    if channels == 0 || channels > 7 {
+        return None;
+    }
+    let object_type = 2; // AAC-LC
+    let config = [
+        (object_type << 3) | (frequency_index >> 1),
+        ((frequency_index & 0x01) << 7) | ((channels as u8) << 3),
+    ];
+    Some(Bytes::copy_from_slice(&config))
+}
+
+/// 给原始 AAC 帧加上 ADTS 头部 (MPEG-TS 封装需要)，参数取自 AudioSpecificConfig
+pub fn to_adts(config: &[u8], frame: &[u8]) -> Option<Bytes> {
+    let (first, second) = (*config.first()?, *config.get(1)?);
+    let object_type = first >> 3;
+    let frequency_index = ((first & 0x07) << 1) | (second >> 7);
+    let channels = (second >> 3) & 0x0f;
+    let size = frame.len() + ADTS_HEADER_SIZE;
+    if object_type == 0 || object_type > 4 || size >= 1 << 13 {
+        return None;
+    }
+
+    let header = [
+        0xff,
+        0xf1, // MPEG-4，没有 CRC
+        ((object_type - 1) << 6) | (frequency_index << 2) | (channels >> 2),
+        ((channels & 0x03) << 6) | (size >> 11) as u8,
+        (size >> 3) as u8,
+        ((size as u8 & 0x07) << 5) | 0x1f,
+        0xfc, // 缓冲区满度 0x7ff，每个 ADTS 帧一个原始块
+    ];
+    Some([&header[..], frame].concat().into())
+}
+
+/// 从 ADTS 字节流中取出原始 AAC 帧，并根据头部生成 AudioSpecificConfig (ISO 14496-3)
+#[derive(Default)]
+pub struct AdtsParser {
+    buffer: Vec<u8>,
+    config: Option<Bytes>,
+}
+
+impl AdtsParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 最近一帧对应的 AudioSpecificConfig，FLV/MP4 封装需要它作为解码器配置
+    pub fn config(&self) -> Option<Bytes> {
+        self.config.clone()
+    }
+
+    /// 追加字节流，返回去掉 ADTS 头部的完整帧
+    pub fn push(&mut self, data: &[u8]) -> Vec<Bytes> {
+        self.buffer.extend_from_slice(data);
+
+        let mut frames = Vec::new();
+        let mut offset = 0;
+        while self.buffer.len() - offset >= ADTS_HEADER_SIZE {
+            let header = &self.buffer[offset..];
+            if header[0] != 0xff || header[1] & 0xf6 != 0xf0 {
+                // 丢失同步，逐字节查找下一个同步字
+                offset += 1;
+                continue;
+            }
+
+            let header_size = if header[1] & 0x01 == 0 { ADTS_HEADER_SIZE + 2 } else { ADTS_HEADER_SIZE };
+            let frame_size = ((header[3] as usize & 0x03) << 11) | ((header[4] as usize) << 3) | (header[5] as usize >> 5);
+            if frame_size < header_size {
+                offset += 1;
+                continue;
+            }
+            if header.len() < frame_size {
+                break;
+            }
+
+            let object_type = (header[2] >> 6) + 1;
+            let frequency_index = (header[2] >> 2) & 0x0f;
+            let channels = ((header[2] & 0x01) << 2) | (header[3] >> 6);
+            let config = [
+                (object_type << 3) | (frequency_index >> 1),
+                ((frequency_index & 0x01) << 7) | (channels << 3),
+            ];
+            if self.config.as_deref() != Some(&config[..]) {
+                self.config = Some(Bytes::copy_from_slice(&config));
+            }
+
+            frames.push(Bytes::copy_from_slice(&header[header_size..frame_size]));
+            offset += frame_size;
+        }
+
+        self.buffer.drain(..offset);
+        frames
+    }
+}
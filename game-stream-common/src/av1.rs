@@ -0,0 +1,236 @@
+use bytes::Bytes;
+
+use crate::bits::BitReader;
+
+/// OBU 类型 (AV1 规范 6.2.2)
+const OBU_SEQUENCE_HEADER: u8 = 1;
+const OBU_TEMPORAL_DELIMITER: u8 = 2;
+const OBU_FRAME_HEADER: u8 = 3;
+const OBU_FRAME: u8 = 6;
+
+/// 一个 OBU
+struct Obu<'a> {
+    obu_type: u8,
+    data: &'a [u8],    // 完整的 OBU，含头部
+    payload: &'a [u8],
+}
+
+/// 依次取出时间单元中的 OBU (低开销格式，每个 OBU 带长度字段)
+fn obus(mut data: &[u8]) -> impl Iterator<Item = Obu<'_>> {
+    std::iter::from_fn(move || {
+        let header = *data.first()?;
+        let obu_type = (header >> 3) & 0x0f;
+        let header_size = if header & 0x04 != 0 { 2 } else { 1 };
+        let (size, size_length) = if header & 0x02 != 0 {
+            leb128(data.get(header_size..)?)?
+        } else {
+            (data.len().checked_sub(header_size)?, 0)
+        };
+
+        let end = header_size + size_length + size;
+        if end > data.len() {
+            return None;
+        }
+        let obu = Obu {
+            obu_type,
+            data: &data[..end],
+            payload: &data[header_size + size_length..end],
+        };
+        data = &data[end..];
+        Some(obu)
+    })
+}
+
+/// 读取 leb128 编码的长度，返回 (值, 字节数)
+fn leb128(data: &[u8]) -> Option<(usize, usize)> {
+    let mut value = 0usize;
+    for (index, byte) in data.iter().take(8).enumerate() {
+        value |= ((byte & 0x7f) as usize) << (index * 7);
+        if byte & 0x80 == 0 {
+            return Some((value, index + 1));
+        }
+    }
+    None
+}
+
+/// 时间单元是否为关键帧：第一个帧头的 show_existing_frame 为 0 且 frame_type 为 KEY_FRAME
+pub fn is_keyframe(temporal_unit: &[u8]) -> bool {
+    obus(temporal_unit)
+        .find(|obu| matches!(obu.obu_type, OBU_FRAME | OBU_FRAME_HEADER))
+        .and_then(|obu| obu.payload.first().copied())
+        .is_some_and(|byte| byte & 0xe0 == 0)
+}
+
+/// 时间单元中的序列头 OBU
+pub fn sequence_header(temporal_unit: &[u8]) -> Option<&[u8]> {
+    obus(temporal_unit)
+        .find(|obu| obu.obu_type == OBU_SEQUENCE_HEADER)
+        .map(|obu| obu.data)
+}
+
+/// 在时间分隔符之后插入序列头，让关键帧可以独立解码
+pub fn insert_sequence_header(temporal_unit: &[u8], sequence_header: &[u8]) -> Bytes {
+    let delimiter = obus(temporal_unit)
+        .next()
+        .filter(|obu| obu.obu_type == OBU_TEMPORAL_DELIMITER)
+        .map_or(0, |obu| obu.data.len());
+    [&temporal_unit[..delimiter], sequence_header, &temporal_unit[delimiter..]].concat().into()
+}
+
+/// 去掉时间分隔符，Enhanced RTMP 和 MP4 的样本中不包含它
+pub fn remove_temporal_delimiters(temporal_unit: &[u8]) -> Bytes {
+    obus(temporal_unit)
+        .filter(|obu| obu.obu_type != OBU_TEMPORAL_DELIMITER)
+        .flat_map(|obu| obu.data.iter().copied())
+        .collect::<Vec<u8>>()
+        .into()
+}
+
+/// 由序列头 OBU 生成 AV1CodecConfigurationRecord (av1C)，用于 Enhanced RTMP 和 MP4 封装
+pub fn codec_config_record(sequence_header: &[u8]) -> Option<Bytes> {
+    let obu = obus(sequence_header).find(|obu| obu.obu_type == OBU_SEQUENCE_HEADER)?;
+    let info = parse_sequence_header(obu.payload)?;
+
+    let record = [
+        0x81, // marker 和 version 1
+        (info.profile << 5) | info.level,
+        (info.tier << 7)
+            | (info.high_bitdepth << 6)
+            | (info.twelve_bit << 5)
+            | (info.monochrome << 4)
+            | (info.subsampling_x << 3)
+            | (info.subsampling_y << 2)
+            | info.chroma_sample_position,
+        0, // 没有 initial_presentation_delay
+    ];
+    Some([&record[..], obu.data].concat().into())
+}
+
+/// av1C 需要的序列头字段
+struct SequenceInfo {
+    profile: u8,
+    level: u8,
+    tier: u8,
+    high_bitdepth: u8,
+    twelve_bit: u8,
+    monochrome: u8,
+    subsampling_x: u8,
+    subsampling_y: u8,
+    chroma_sample_position: u8,
+}
+
+/// 按 AV1 规范 5.5 解析序列头，直到 color_config 为止
+fn parse_sequence_header(payload: &[u8]) -> Option<SequenceInfo> {
+    let mut bits = BitReader::new(payload);
+
+    let profile = bits.read(3)? as u8;
+    bits.skip(1)?; // still_picture
+    let reduced_still_picture_header = bits.flag()?;
+
+    let (level, tier);
+    if reduced_still_picture_header {
+        level = bits.read(5)? as u8;
+        tier = 0;
+    } else {
+        let mut buffer_delay_length = None;
+        if bits.flag()? {
+            // timing_info
+            bits.skip(64)?;
+            if bits.flag()? {
+                bits.ue()?;
+            }
+            if bits.flag()? {
+                // decoder_model_info
+                buffer_delay_length = Some(bits.read(5)? as usize + 1);
+                bits.skip(32 + 5 + 5)?;
+            }
+        }
+        let initial_display_delay_present = bits.flag()?;
+        let operating_points = bits.read(5)? + 1;
+
+        let mut first = None;
+        for _ in 0..operating_points {
+            bits.skip(12)?; // operating_point_idc
+            let seq_level = bits.read(5)? as u8;
+            let seq_tier = if seq_level > 7 { bits.read(1)? as u8 } else { 0 };
+            if let Some(length) = buffer_delay_length {
+                if bits.flag()? {
+                    bits.skip(length * 2 + 1)?;
+                }
+            }
+            if initial_display_delay_present && bits.flag()? {
+                bits.skip(4)?;
+            }
+            first.get_or_insert((seq_level, seq_tier));
+        }
+        (level, tier) = first?;
+    }
+
+    let width_bits = bits.read(4)? as usize + 1;
+    let height_bits = bits.read(4)? as usize + 1;
+    bits.skip(width_bits + height_bits)?;
+    if !reduced_still_picture_header && bits.flag()? {
+        // frame_id_numbers_present_flag
+        bits.skip(4 + 3)?;
+    }
+    bits.skip(3)?; // use_128x128_superblock、enable_filter_intra、enable_intra_edge_filter
+    if !reduced_still_picture_header {
+        bits.skip(4)?; // enable_interintra_compound、enable_masked_compound、enable_warped_motion、enable_dual_filter
+        let enable_order_hint = bits.flag()?;
+        if enable_order_hint {
+            bits.skip(2)?; // enable_jnt_comp、enable_ref_frame_mvs
+        }
+        let force_screen_content_tools = if bits.flag()? { 2 } else { bits.read(1)? };
+        if force_screen_content_tools > 0 && !bits.flag()? {
+            bits.skip(1)?; // seq_force_integer_mv
+        }
+        if enable_order_hint {
+            bits.skip(3)?;
+        }
+    }
+    bits.skip(3)?; // enable_superres、enable_cdef、enable_restoration
+
+    // color_config
+    let high_bitdepth = bits.read(1)? as u8;
+    let twelve_bit = if profile == 2 && high_bitdepth == 1 { bits.read(1)? as u8 } else { 0 };
+    let monochrome = if profile == 1 { 0 } else { bits.read(1)? as u8 };
+    let (primaries, transfer, matrix) = if bits.flag()? {
+        (bits.read(8)?, bits.read(8)?, bits.read(8)?)
+    } else {
+        (2, 2, 2)
+    };
+
+    let (mut subsampling_x, mut subsampling_y, mut chroma_sample_position) = (1, 1, 0);
+    if monochrome == 1 {
+        bits.skip(1)?; // color_range
+    } else if primaries == 1 && transfer == 13 && matrix == 0 {
+        // sRGB，不做色度抽样
+        (subsampling_x, subsampling_y) = (0, 0);
+    } else {
+        bits.skip(1)?; // color_range
+        match profile {
+            0 => {}
+            1 => (subsampling_x, subsampling_y) = (0, 0),
+            _ if twelve_bit == 1 => {
+                subsampling_x = bits.read(1)? as u8;
+                subsampling_y = if subsampling_x == 1 { bits.read(1)? as u8 } else { 0 };
+            }
+            _ => (subsampling_x, subsampling_y) = (1, 0),
+        }
+        if subsampling_x == 1 && subsampling_y == 1 {
+            chroma_sample_position = bits.read(2)? as u8;
+        }
+    }
+
+    Some(SequenceInfo {
+        profile,
+        level,
+        tier,
+        high_bitdepth,
+        twelve_bit,
+        monochrome,
+        subsampling_x,
+        subsampling_y,
+        chroma_sample_position,
+    })
+}
@@ -0,0 +1,43 @@
+/// 按位读取，高位在前
+pub(crate) struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    pub(crate) fn read(&mut self, count: usize) -> Option<u32> {
+        let mut value = 0u32;
+        for _ in 0..count {
+            let byte = self.data.get(self.position / 8)?;
+            let bit = (byte >> (7 - self.position % 8)) & 1;
+            value = (value << 1) | bit as u32;
+            self.position += 1;
+        }
+        Some(value)
+    }
+
+    pub(crate) fn flag(&mut self) -> Option<bool> {
+        self.read(1).map(|bit| bit == 1)
+    }
+
+    pub(crate) fn skip(&mut self, count: usize) -> Option<()> {
+        self.position += count;
+        (self.position <= self.data.len() * 8).then_some(())
+    }
+
+    /// 指数哥伦布编码的无符号整数：H.264/H.265 的 ue(v)，即 AV1 的 uvlc()
+    pub(crate) fn ue(&mut self) -> Option<u32> {
+        let mut leading_zeros = 0;
+        while !self.flag()? {
+            leading_zeros += 1;
+            if leading_zeros >= 32 {
+                return None;
+            }
+        }
+        Some(self.read(leading_zeros)?.wrapping_add((1u32 << leading_zeros) - 1))
+    }
+}
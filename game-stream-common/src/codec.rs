@@ -1,5 +1,15 @@
-use crate::{StreamResult, StreamError};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, LazyLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use bytes::Bytes;
+use tracing::{info, warn, debug};
+
+use crate::{StreamResult, StreamError, RateControl};
+use crate::ffmpeg::{self, FfmpegProcess};
+use crate::h264::{AccessUnit, AccessUnitParser};
+use crate::ogg::OggPacketReader;
+use crate::aac::AdtsParser;
+use crate::av1;
+use crate::ivf::IvfReader;
 
 /// 视频编码器特征
 pub trait VideoEncoder: Send + Sync {
@@ -11,6 +21,38 @@ pub trait VideoEncoder: Send + Sync {
     
     /// 刷新编码器缓冲区
     fn flush(&mut self) -> StreamResult<Vec<EncodedPacket>>;
+    
+    /// 画面未变化时输出重复上一帧的包 (如全部宏块跳过的 P 帧)
+    ///
+    /// 不支持或该帧需要作为关键帧时返回 None，由调用方完整编码
+    fn encode_repeat(&mut self, _timestamp: u64) -> StreamResult<Option<Vec<EncodedPacket>>> {
+        Ok(None)
+    }
+    
+    /// 编码器希望的输入像素格式；捕获的画面是 RGB 时可以先转换为该格式，减少管道传输和 ffmpeg 的转换开销
+    fn preferred_input_format(&self) -> Option<VideoPixelFormat> {
+        None
+    }
+    
+    /// 能否直接编码显存中的画面 (零拷贝路径)，会实际抓取并编码一帧以确认权限和驱动可用
+    fn supports_gpu_surface(&self, _surface: &GpuSurface) -> bool {
+        false
+    }
+    
+    /// 编码显存中的一帧；画面由编码进程在显卡上抓取，这里只提供帧的时间戳
+    fn encode_gpu_frame(&mut self, surface: &GpuSurface, _timestamp: u64) -> StreamResult<Vec<EncodedPacket>> {
+        Err(StreamError::Codec(format!("Encoder cannot encode GPU frames from {:?}", surface)))
+    }
+    
+    /// 推流过程中调整目标码率 (kbps)，返回切换前已编码的包；新码率从下一帧 (关键帧) 开始生效
+    fn set_bitrate(&mut self, _bitrate: u32) -> StreamResult<Vec<EncodedPacket>> {
+        Err(StreamError::Codec("Encoder does not support changing the bitrate".to_string()))
+    }
+    
+    /// 让下一帧编码为关键帧，返回此前已编码的包；默认刷新编码器，下一帧重新开始编码
+    fn request_keyframe(&mut self) -> StreamResult<Vec<EncodedPacket>> {
+        self.flush()
+    }
 }
 
 /// 音频编码器特征
@@ -23,6 +65,11 @@ pub trait AudioEncoder: Send + Sync {
     
     /// 刷新编码器缓冲区
     fn flush(&mut self) -> StreamResult<Vec<EncodedPacket>>;
+    
+    /// 解码器配置 (如 AAC 的 AudioSpecificConfig)，编码器输出第一个包后可用
+    fn codec_config(&self) -> Option<Bytes> {
+        None
+    }
 }
 
 /// 视频帧数据
@@ -35,6 +82,54 @@ pub struct VideoFrame {
     pub timestamp: u64,
 }
 
+/// 显存中的画面 - 零拷贝路径中画面不下载到内存，帧只描述抓取源，由编码进程在显卡上直接抓取并编码
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GpuSurface {
+    /// Linux KMS 帧缓冲，以 DRM PRIME (dmabuf) 映射到 VA-API
+    DrmPrime {
+        device: String,       // DRM 设备，如 /dev/dri/card0
+        crtc_id: Option<u32>, // 抓取的显示输出，未设置时为第一个活动的输出
+    },
+}
+
+impl GpuSurface {
+    /// 抓取该画面的 ffmpeg 输入参数
+    fn input_args(&self, fps: u32) -> Vec<String> {
+        match self {
+            Self::DrmPrime { device, crtc_id } => {
+                let mut args = vec!["-device".to_string(), device.clone(), "-f".to_string(), "kmsgrab".to_string()];
+                if let Some(crtc_id) = crtc_id {
+                    args.extend(["-crtc_id".to_string(), crtc_id.to_string()]);
+                }
+                args.extend(["-framerate".to_string(), fps.max(1).to_string(), "-i".to_string(), "-".to_string()]);
+                args
+            }
+        }
+    }
+    
+    /// 在显卡上映射到编码器设备并缩放的滤镜
+    fn filter(&self, width: u32, height: u32) -> String {
+        match self {
+            Self::DrmPrime { .. } => format!("hwmap=derive_device=vaapi,scale_vaapi=w={}:h={}:format=nv12", width, height),
+        }
+    }
+    
+    /// 用该编码器实际抓取并编码一帧，检查权限 (kmsgrab 需要 CAP_SYS_ADMIN) 和驱动
+    fn probe(&self, encoder: &str) -> bool {
+        let mut args = self.input_args(1);
+        args.extend(["-frames:v", "1", "-vf", &self.filter(320, 240), "-c:v", encoder, "-f", "null", "-"]
+            .iter().map(|arg| arg.to_string()));
+        ffmpeg::run(&args.iter().map(String::as_str).collect::<Vec<_>>())
+    }
+}
+
+/// 编码进程的输入
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum EncoderInput {
+    Raw(u32, u32, VideoPixelFormat), // 通过管道写入的原始画面
+    Gpu(GpuSurface),                 // 编码进程在显卡上直接抓取
+}
+
 /// 音频帧数据
 #[derive(Debug, Clone)]
 pub struct AudioFrame {
@@ -63,7 +158,7 @@ pub enum PacketType {
 }
 
 /// 视频像素格式
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum VideoPixelFormat {
     Rgb24,
     Rgba32,
@@ -71,10 +166,45 @@ pub enum VideoPixelFormat {
     Bgra32,
     Yuv420p,
     Nv12,
+    /// HDR10：每像素 32 位，R/G/B 各 10 位 + 2 位 alpha，BT.2020 色域，PQ 传递函数
+    Rgb10a2,
+    /// scRGB：每通道 16 位浮点，BT.709 色域线性光，1.0 对应 80 nits
+    Rgba16f,
+}
+
+impl VideoPixelFormat {
+    /// 是否为 HDR 格式
+    pub fn is_hdr(self) -> bool {
+        matches!(self, Self::Rgb10a2 | Self::Rgba16f)
+    }
+    
+    /// 一帧画面的字节数
+    pub fn frame_size(self, width: u32, height: u32) -> usize {
+        let pixels = width as usize * height as usize;
+        match self {
+            Self::Rgb24 | Self::Bgr24 => pixels * 3,
+            Self::Rgba32 | Self::Bgra32 | Self::Rgb10a2 => pixels * 4,
+            Self::Rgba16f => pixels * 8,
+            Self::Yuv420p | Self::Nv12 => pixels * 3 / 2,
+        }
+    }
+    
+    /// 对应的 ffmpeg 原始视频格式，HDR 格式需要先色调映射，返回 None
+    pub fn raw_format(self) -> Option<&'static str> {
+        match self {
+            Self::Rgb24 => Some("rgb24"),
+            Self::Rgba32 => Some("rgba"),
+            Self::Bgr24 => Some("bgr24"),
+            Self::Bgra32 => Some("bgra"),
+            Self::Yuv420p => Some("yuv420p"),
+            Self::Nv12 => Some("nv12"),
+            Self::Rgb10a2 | Self::Rgba16f => None,
+        }
+    }
 }
 
 /// 音频采样格式
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AudioSampleFormat {
     S16,
     S32,
@@ -82,6 +212,18 @@ pub enum AudioSampleFormat {
     F64,
 }
 
+impl AudioSampleFormat {
+    /// 对应的 ffmpeg 原始音频格式 (小端交错)
+    pub fn raw_format(self) -> &'static str {
+        match self {
+            Self::S16 => "s16le",
+            Self::S32 => "s32le",
+            Self::F32 => "f32le",
+            Self::F64 => "f64le",
+        }
+    }
+}
+
 /// 视频编码器配置
 #[derive(Debug, Clone)]
 pub struct VideoEncoderConfig {
@@ -92,6 +234,80 @@ pub struct VideoEncoderConfig {
     pub bitrate: u32,
     pub keyframe_interval: u32,
     pub preset: String,
+    pub hardware_acceleration: bool,
+    pub vaapi_device: Option<String>,
+    pub rate_control: RateControl,
+    pub max_bitrate: Option<u32>,
+    pub buffer_size: Option<u32>,
+    pub qp: u32,
+    pub profile: Option<String>,
+    pub level: Option<String>,
+    pub b_frames: u32,
+    pub low_latency: bool,
+}
+
+impl VideoEncoderConfig {
+    /// 峰值码率 (kbps)：CBR 等于目标码率，VBR 未设置时为目标码率的 1.5 倍
+    pub fn peak_bitrate(&self) -> u32 {
+        let bitrate = self.bitrate.max(1);
+        match self.rate_control {
+            RateControl::Vbr => self.max_bitrate.unwrap_or(bitrate * 3 / 2).max(bitrate),
+            _ => bitrate,
+        }
+    }
+    
+    /// 修改目标码率，明确设置的峰值码率和 VBV 缓冲按相同比例缩放
+    pub fn set_bitrate(&mut self, bitrate: u32) {
+        let scale = |value: u32| (value as u64 * bitrate as u64 / self.bitrate.max(1) as u64) as u32;
+        self.max_bitrate = self.max_bitrate.map(scale);
+        self.buffer_size = self.buffer_size.map(scale);
+        self.bitrate = bitrate;
+    }
+    
+    /// VBV 缓冲大小 (kbit)，未设置时为峰值码率的 2 倍
+    pub fn vbv_size(&self) -> u32 {
+        self.buffer_size.unwrap_or(self.peak_bitrate() * 2).max(1)
+    }
+    
+    /// 通用的码率控制参数，各编码器再补充自己的设置
+    fn rate_control_args(&self) -> Vec<String> {
+        let bitrate = format!("{}k", self.bitrate.max(1));
+        match self.rate_control {
+            RateControl::Cbr => vec![
+                "-b:v".to_string(), bitrate.clone(),
+                "-minrate".to_string(), bitrate.clone(),
+                "-maxrate".to_string(), bitrate,
+                "-bufsize".to_string(), format!("{}k", self.vbv_size()),
+            ],
+            RateControl::Vbr => vec![
+                "-b:v".to_string(), bitrate,
+                "-maxrate".to_string(), format!("{}k", self.peak_bitrate()),
+                "-bufsize".to_string(), format!("{}k", self.vbv_size()),
+            ],
+            RateControl::Cqp => vec!["-qp".to_string(), self.qp.to_string()],
+        }
+    }
+    
+    /// VA-API 编码器的 -rc_mode
+    fn vaapi_rc_mode(&self) -> &'static str {
+        match self.rate_control {
+            RateControl::Cbr => "CBR",
+            RateControl::Vbr => "VBR",
+            RateControl::Cqp => "CQP",
+        }
+    }
+    
+    /// -profile:v 和 -level 参数
+    fn profile_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(profile) = &self.profile {
+            args.extend(["-profile:v".to_string(), profile.clone()]);
+        }
+        if let Some(level) = &self.level {
+            args.extend(["-level".to_string(), level.clone()]);
+        }
+        args
+    }
 }
 
 /// 音频编码器配置
@@ -101,83 +317,774 @@ pub struct AudioEncoderConfig {
     pub sample_rate: u32,
     pub channels: u32,
     pub bitrate: u32,
+    pub vbr: bool,
+}
+
+/// 通过管道输入原始画面的 ffmpeg 参数
+fn raw_input_args(width: u32, height: u32, pixel_format: &str, fps: u32) -> Vec<String> {
+    [
+        "-f", "rawvideo", "-pix_fmt", pixel_format,
+        "-s", &format!("{}x{}", width, height),
+        "-framerate", &fps.max(1).to_string(),
+        "-i", "pipe:0",
+    ].iter().map(|arg| arg.to_string()).collect()
+}
+
+/// SDR 输出的色彩空间标记，与客户端的 RGB 到 YUV 转换 (BT.709) 一致
+const BT709_TAGS: [&str; 6] = ["-colorspace", "bt709", "-color_primaries", "bt709", "-color_trc", "bt709"];
+
+/// 缩放到输出尺寸的滤镜；RGB 输入按 BT.709 (HDR10 按 BT.2020) 转换为 YUV
+fn scale_filter(config: &VideoEncoderConfig, format: VideoPixelFormat) -> String {
+    let matrix = match format {
+        VideoPixelFormat::Yuv420p | VideoPixelFormat::Nv12 => "",
+        VideoPixelFormat::Rgb10a2 => ":out_color_matrix=bt2020",
+        _ => ":out_color_matrix=bt709",
+    };
+    format!("scale={}:{}:flags=bicubic{}", config.width, config.height, matrix)
 }
 
-/// H.264 编码器实现
+/// 未指定设备时使用的 VA-API 渲染节点
+const DEFAULT_VAAPI_DEVICE: &str = "/dev/dri/renderD128";
+
+/// H.264 编码器 - 通过 ffmpeg 编码，输出 Annex-B 格式的访问单元
+///
+/// 开启硬件加速时在 Linux 上优先使用 VA-API (Intel/AMD 显卡)，否则使用 libx264 或 libopenh264。
+/// 每个关键帧前都带有 SPS/PPS，中途加入的观众也能解码；输入分辨率或像素格式变化时重启编码进程
 pub struct H264Encoder {
     config: VideoEncoderConfig,
-    frame_count: u64,
+    encoder: &'static str,
+    vaapi_device: Option<String>, // 使用 VA-API 编码时的设备
+    process: Option<FfmpegProcess>,
+    input: Option<EncoderInput>, // 当前编码进程的输入
+    parser: AccessUnitParser,
+    timestamps: VecDeque<u64>, // 已送入编码器、尚未输出的帧的时间戳
 }
 
 impl H264Encoder {
     pub fn new(config: VideoEncoderConfig) -> StreamResult<Self> {
-        // 这里应该初始化 FFmpeg 的 H.264 编码器
-        // 由于 FFmpeg 绑定比较复杂，这里提供一个简化的实现框架
+        let vaapi_device = if config.hardware_acceleration { find_vaapi_device(&config, "h264_vaapi") } else { None };
+        let encoder = match vaapi_device {
+            Some(_) => "h264_vaapi",
+            None => ["libx264", "libopenh264"].into_iter()
+                .find(|name| ffmpeg::has_encoder(name))
+                .ok_or_else(|| StreamError::Codec(
+                    "H.264 encoding requires ffmpeg built with libx264 or libopenh264 in PATH".to_string()
+                ))?,
+        };
+        info!("Encoding H.264 with {}{} ({}x{}, {} kbps, preset {})",
+              encoder, vaapi_device.as_deref().map(|device| format!(" on {}", device)).unwrap_or_default(),
+              config.width, config.height, config.bitrate, config.preset);
+
         Ok(Self {
             config,
-            frame_count: 0,
+            encoder,
+            vaapi_device,
+            process: None,
+            input: None,
+            parser: AccessUnitParser::new(),
+            timestamps: VecDeque::new(),
         })
     }
+
+    fn start(&mut self, input: EncoderInput) -> StreamResult<()> {
+        let gop = (self.config.keyframe_interval.max(1) * self.config.fps.max(1)).to_string();
+
+        let mut args: Vec<String> = Vec::new();
+        let filters = match &input {
+            EncoderInput::Raw(width, height, format) => {
+                let pixel_format = format.raw_format().ok_or_else(|| {
+                    StreamError::Codec(format!("H.264 cannot encode {:?} frames, tone map them to SDR first", format))
+                })?;
+                // VA-API 在 CPU 上缩放并转换为 NV12 后上传到显卡编码
+                let scale = scale_filter(&self.config, *format);
+                let filters = match &self.vaapi_device {
+                    Some(device) => {
+                        args.extend(["-vaapi_device".to_string(), device.clone()]);
+                        format!("{},format=nv12,hwupload", scale)
+                    }
+                    None => format!("{},format=yuv420p", scale),
+                };
+                args.extend(raw_input_args(*width, *height, pixel_format, self.config.fps));
+                filters
+            }
+            EncoderInput::Gpu(surface) => {
+                args.extend(surface.input_args(self.config.fps));
+                surface.filter(self.config.width, self.config.height)
+            }
+        };
+        args.extend([
+            "-vf", &filters,
+            "-c:v", self.encoder,
+            "-g", &gop,
+            "-keyint_min", &gop,
+            "-bf", &self.config.b_frames.to_string(),
+            "-vsync", "passthrough",
+        ].iter().map(|arg| arg.to_string()));
+        if self.vaapi_device.is_some() {
+            args.extend(["-rc_mode".to_string(), self.config.vaapi_rc_mode().to_string()]);
+        }
+        args.extend(self.config.rate_control_args());
+        args.extend(self.config.profile_args());
+        args.extend(BT709_TAGS.iter().map(|arg| arg.to_string()));
+        if self.encoder == "libx264" {
+            // 固定关键帧间隔、每个关键帧重复 SPS/PPS，适合直播
+            let mut params = "repeat-headers=1:aud=1".to_string();
+            if self.config.rate_control == RateControl::Cbr {
+                params.push_str(":nal-hrd=cbr");
+            }
+            args.extend(["-preset", &self.config.preset, "-sc_threshold", "0", "-x264-params", &params]
+                .iter().map(|arg| arg.to_string()));
+            if self.config.low_latency {
+                args.extend(["-tune".to_string(), "zerolatency".to_string()]);
+            }
+        }
+        args.extend(["-f", "h264", "pipe:1"].iter().map(|arg| arg.to_string()));
+
+        self.process = Some(FfmpegProcess::spawn(&args)?);
+        debug!("Started H.264 encoder for {:?}", input);
+        self.input = Some(input);
+        self.parser = AccessUnitParser::new();
+        self.timestamps.clear();
+        Ok(())
+    }
+
+    /// 把编码器输出切分为数据包，按送入顺序对应帧的时间戳
+    fn packets(&mut self, units: Vec<AccessUnit>) -> Vec<EncodedPacket> {
+        units.into_iter()
+            .filter_map(|unit| {
+                let timestamp = self.timestamps.pop_front()?;
+                Some(EncodedPacket {
+                    data: unit.data,
+                    timestamp,
+                    is_keyframe: unit.is_keyframe,
+                    packet_type: PacketType::Video,
+                })
+            })
+            .collect()
+    }
+}
+
+/// 查找可用的 VA-API 设备：ffmpeg 带有对应的编码器 (如 h264_vaapi)，并且能在设备上实际编码一帧 (驱动可用)
+fn find_vaapi_device(config: &VideoEncoderConfig, encoder: &str) -> Option<String> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    if !cfg!(feature = "hw-encoders") {
+        debug!("Hardware encoding is not enabled in this build (hw-encoders feature), not using {}", encoder);
+        return None;
+    }
+    let device = config.vaapi_device.clone().unwrap_or_else(|| DEFAULT_VAAPI_DEVICE.to_string());
+    if config.vaapi_device.is_none() && !std::path::Path::new(&device).exists() {
+        return None;
+    }
+
+    let usable = ffmpeg::has_encoder(encoder) && ffmpeg::run(&[
+        "-vaapi_device", &device,
+        "-f", "lavfi", "-i", "color=black:size=256x256",
+        "-frames:v", "1",
+        "-vf", "format=nv12,hwupload",
+        "-c:v", encoder,
+        "-f", "null", "-",
+    ]);
+    if !usable {
+        // 明确指定了设备时说明为什么没有使用
+        if config.vaapi_device.is_some() {
+            warn!("{} is not available on {}, falling back to software encoding", encoder, device);
+        } else {
+            debug!("{} is not available on {}", encoder, device);
+        }
+        return None;
+    }
+    Some(device)
 }
 
 impl VideoEncoder for H264Encoder {
     fn encode_frame(&mut self, frame: &VideoFrame) -> StreamResult<Vec<EncodedPacket>> {
-        // 实际的 H.264 编码逻辑
-        // 这里需要使用 FFmpeg 进行实际编码
-        self.frame_count += 1;
-        
-        // 模拟编码结果
-        let is_keyframe = self.frame_count % (self.config.keyframe_interval as u64 * self.config.fps as u64) == 1;
-        
-        let encoded_data = Bytes::from(format!("h264_frame_{}", self.frame_count));
-        
-        Ok(vec![EncodedPacket {
-            data: encoded_data,
-            timestamp: frame.timestamp,
-            is_keyframe,
-            packet_type: PacketType::Video,
-        }])
+        let size = frame.format.frame_size(frame.width, frame.height);
+        if frame.data.len() < size {
+            return Err(StreamError::Codec(format!(
+                "Video frame has {} bytes, expected {} for {}x{} {:?}",
+                frame.data.len(), size, frame.width, frame.height, frame.format
+            )));
+        }
+
+        let mut packets = Vec::new();
+        let input = EncoderInput::Raw(frame.width, frame.height, frame.format);
+        if self.input.as_ref() != Some(&input) {
+            packets = self.flush()?;
+            self.start(input)?;
+        }
+
+        let process = self.process.as_mut()
+            .ok_or_else(|| StreamError::Codec("H.264 encoder is not running".to_string()))?;
+        process.write(&frame.data[..size])?;
+        self.timestamps.push_back(frame.timestamp);
+
+        let output = process.read_available();
+        let units = self.parser.push(&output);
+        packets.extend(self.packets(units));
+        Ok(packets)
     }
     
     fn get_config(&self) -> VideoEncoderConfig {
         self.config.clone()
     }
     
+    fn preferred_input_format(&self) -> Option<VideoPixelFormat> {
+        // VA-API 上传需要 NV12，软件编码器使用 I420
+        Some(if self.vaapi_device.is_some() { VideoPixelFormat::Nv12 } else { VideoPixelFormat::Yuv420p })
+    }
+
+    fn supports_gpu_surface(&self, surface: &GpuSurface) -> bool {
+        // 画面映射到 VA-API 后直接编码，需要使用 VA-API 编码器
+        match surface {
+            GpuSurface::DrmPrime { .. } => self.vaapi_device.is_some() && surface.probe(self.encoder),
+        }
+    }
+
+    fn encode_gpu_frame(&mut self, surface: &GpuSurface, timestamp: u64) -> StreamResult<Vec<EncodedPacket>> {
+        let mut packets = Vec::new();
+        let input = EncoderInput::Gpu(surface.clone());
+        if self.input.as_ref() != Some(&input) {
+            packets = self.flush()?;
+            self.start(input)?;
+        }
+
+        // 编码进程按帧率自行抓取，每个时间戳对应一帧输出
+        let process = self.process.as_mut()
+            .ok_or_else(|| StreamError::Codec("H.264 encoder is not running".to_string()))?;
+        self.timestamps.push_back(timestamp);
+
+        let output = process.read_available();
+        let units = self.parser.push(&output);
+        packets.extend(self.packets(units));
+        Ok(packets)
+    }
+
+    fn set_bitrate(&mut self, bitrate: u32) -> StreamResult<Vec<EncodedPacket>> {
+        // ffmpeg 不能在编码过程中修改码率，结束当前进程，下一帧以新码率重新启动
+        self.config.set_bitrate(bitrate);
+        self.flush()
+    }
+
     fn flush(&mut self) -> StreamResult<Vec<EncodedPacket>> {
-        // 刷新编码器缓冲区
-        Ok(Vec::new())
+        let Some(mut process) = self.process.take() else {
+            return Ok(Vec::new());
+        };
+
+        // 显卡抓取不会因为输入关闭而结束，直接结束进程，丢弃尚未输出的帧
+        let output = match self.input.take() {
+            Some(EncoderInput::Gpu(_)) => process.read_available(),
+            _ => process.finish()?,
+        };
+        let mut units = self.parser.push(&output);
+        units.extend(self.parser.finish());
+        let packets = self.packets(units);
+        if !self.timestamps.is_empty() {
+            warn!("H.264 encoder dropped {} frames", self.timestamps.len());
+            self.timestamps.clear();
+        }
+        Ok(packets)
+    }
+}
+
+/// H.265 编码器 - 通过 ffmpeg 编码，输出 Annex-B 格式的访问单元
+///
+/// 开启硬件加速时在 Linux 上优先使用 VA-API，否则使用 libx265。每个关键帧前都带有 VPS/SPS/PPS，
+/// 推流时由此生成 hvcC；HDR10 画面以 Main 10 编码。输入分辨率或像素格式变化时重启编码进程
+pub struct H265Encoder {
+    config: VideoEncoderConfig,
+    encoder: &'static str,
+    vaapi_device: Option<String>, // 使用 VA-API 编码时的设备
+    process: Option<FfmpegProcess>,
+    input: Option<EncoderInput>, // 当前编码进程的输入
+    parser: AccessUnitParser,
+    timestamps: VecDeque<u64>, // 已送入编码器、尚未输出的帧的时间戳
+}
+
+impl H265Encoder {
+    pub fn new(config: VideoEncoderConfig) -> StreamResult<Self> {
+        let vaapi_device = if config.hardware_acceleration { find_vaapi_device(&config, "hevc_vaapi") } else { None };
+        let encoder = match vaapi_device {
+            Some(_) => "hevc_vaapi",
+            None if ffmpeg::has_encoder("libx265") => "libx265",
+            None => {
+                return Err(StreamError::Codec("H.265 encoding requires ffmpeg built with libx265 in PATH".to_string()));
+            }
+        };
+        info!("Encoding H.265 with {}{} ({}x{}, {} kbps, preset {})",
+              encoder, vaapi_device.as_deref().map(|device| format!(" on {}", device)).unwrap_or_default(),
+              config.width, config.height, config.bitrate, config.preset);
+
+        Ok(Self {
+            config,
+            encoder,
+            vaapi_device,
+            process: None,
+            input: None,
+            parser: AccessUnitParser::hevc(),
+            timestamps: VecDeque::new(),
+        })
+    }
+
+    fn start(&mut self, input: EncoderInput) -> StreamResult<()> {
+        // HDR10 画面 (R 在低位的 10 位打包格式) 以 Main 10 编码，保留 BT.2020/PQ
+        let hdr10 = matches!(input, EncoderInput::Raw(_, _, VideoPixelFormat::Rgb10a2));
+        let gop = (self.config.keyframe_interval.max(1) * self.config.fps.max(1)).to_string();
+
+        let mut args: Vec<String> = Vec::new();
+        let filters = match &input {
+            EncoderInput::Raw(width, height, format) => {
+                let pixel_format = if hdr10 { Some("x2bgr10le") } else { format.raw_format() }.ok_or_else(|| {
+                    StreamError::Codec(format!("H.265 encoder cannot encode {:?} frames yet, tone map them to SDR first", format))
+                })?;
+                let scale = scale_filter(&self.config, *format);
+                let filters = match &self.vaapi_device {
+                    Some(device) => {
+                        args.extend(["-vaapi_device".to_string(), device.clone()]);
+                        format!("{},format={},hwupload", scale, if hdr10 { "p010" } else { "nv12" })
+                    }
+                    None => format!("{},format={}", scale, if hdr10 { "yuv420p10le" } else { "yuv420p" }),
+                };
+                args.extend(raw_input_args(*width, *height, pixel_format, self.config.fps));
+                filters
+            }
+            EncoderInput::Gpu(surface) => {
+                args.extend(surface.input_args(self.config.fps));
+                surface.filter(self.config.width, self.config.height)
+            }
+        };
+        args.extend([
+            "-vf", &filters,
+            "-c:v", self.encoder,
+            "-g", &gop,
+            "-keyint_min", &gop,
+            "-bf", &self.config.b_frames.to_string(),
+            "-vsync", "passthrough",
+        ].iter().map(|arg| arg.to_string()));
+        if self.vaapi_device.is_some() {
+            args.extend(["-rc_mode".to_string(), self.config.vaapi_rc_mode().to_string()]);
+        }
+        args.extend(self.config.rate_control_args());
+        if hdr10 {
+            // HDR10 固定为 Main 10，忽略配置的档次
+            args.extend([
+                "-profile:v", "main10",
+                "-color_primaries", "bt2020",
+                "-color_trc", "smpte2084",
+                "-colorspace", "bt2020nc",
+            ].iter().map(|arg| arg.to_string()));
+            args.extend(self.config.level.iter().flat_map(|level| ["-level".to_string(), level.clone()]));
+        } else {
+            args.extend(self.config.profile_args());
+            args.extend(BT709_TAGS.iter().map(|arg| arg.to_string()));
+        }
+        if self.encoder == "libx265" {
+            // 与 libx264 相同：固定关键帧间隔、每个关键帧重复参数集
+            let mut params = "repeat-headers=1:aud=1:scenecut=0:log-level=error".to_string();
+            if self.config.rate_control == RateControl::Cbr {
+                params.push_str(":strict-cbr=1");
+            }
+            if hdr10 {
+                params.push_str(":hdr10=1:colorprim=bt2020:transfer=smpte2084:colormatrix=bt2020nc");
+            }
+            args.extend(["-preset", &self.config.preset, "-x265-params", &params].iter().map(|arg| arg.to_string()));
+            if self.config.low_latency {
+                args.extend(["-tune".to_string(), "zerolatency".to_string()]);
+            }
+        }
+        args.extend(["-f", "hevc", "pipe:1"].iter().map(|arg| arg.to_string()));
+
+        self.process = Some(FfmpegProcess::spawn(&args)?);
+        debug!("Started H.265 encoder for {:?}", input);
+        self.input = Some(input);
+        self.parser = AccessUnitParser::hevc();
+        self.timestamps.clear();
+        Ok(())
+    }
+
+    /// 把编码器输出切分为数据包，按送入顺序对应帧的时间戳
+    fn packets(&mut self, units: Vec<AccessUnit>) -> Vec<EncodedPacket> {
+        units.into_iter()
+            .filter_map(|unit| {
+                let timestamp = self.timestamps.pop_front()?;
+                Some(EncodedPacket {
+                    data: unit.data,
+                    timestamp,
+                    is_keyframe: unit.is_keyframe,
+                    packet_type: PacketType::Video,
+                })
+            })
+            .collect()
     }
 }
 
-/// AAC 编码器实现
+impl VideoEncoder for H265Encoder {
+    fn encode_frame(&mut self, frame: &VideoFrame) -> StreamResult<Vec<EncodedPacket>> {
+        let size = frame.format.frame_size(frame.width, frame.height);
+        if frame.data.len() < size {
+            return Err(StreamError::Codec(format!(
+                "Video frame has {} bytes, expected {} for {}x{} {:?}",
+                frame.data.len(), size, frame.width, frame.height, frame.format
+            )));
+        }
+
+        let mut packets = Vec::new();
+        let input = EncoderInput::Raw(frame.width, frame.height, frame.format);
+        if self.input.as_ref() != Some(&input) {
+            packets = self.flush()?;
+            self.start(input)?;
+        }
+
+        let process = self.process.as_mut()
+            .ok_or_else(|| StreamError::Codec("H.265 encoder is not running".to_string()))?;
+        process.write(&frame.data[..size])?;
+        self.timestamps.push_back(frame.timestamp);
+
+        let output = process.read_available();
+        let units = self.parser.push(&output);
+        packets.extend(self.packets(units));
+        Ok(packets)
+    }
+
+    fn get_config(&self) -> VideoEncoderConfig {
+        self.config.clone()
+    }
+
+    fn preferred_input_format(&self) -> Option<VideoPixelFormat> {
+        // VA-API 上传需要 NV12，软件编码器使用 I420
+        Some(if self.vaapi_device.is_some() { VideoPixelFormat::Nv12 } else { VideoPixelFormat::Yuv420p })
+    }
+
+    fn supports_gpu_surface(&self, surface: &GpuSurface) -> bool {
+        // 画面映射到 VA-API 后直接编码，需要使用 VA-API 编码器
+        match surface {
+            GpuSurface::DrmPrime { .. } => self.vaapi_device.is_some() && surface.probe(self.encoder),
+        }
+    }
+
+    fn encode_gpu_frame(&mut self, surface: &GpuSurface, timestamp: u64) -> StreamResult<Vec<EncodedPacket>> {
+        let mut packets = Vec::new();
+        let input = EncoderInput::Gpu(surface.clone());
+        if self.input.as_ref() != Some(&input) {
+            packets = self.flush()?;
+            self.start(input)?;
+        }
+
+        // 编码进程按帧率自行抓取，每个时间戳对应一帧输出
+        let process = self.process.as_mut()
+            .ok_or_else(|| StreamError::Codec("H.265 encoder is not running".to_string()))?;
+        self.timestamps.push_back(timestamp);
+
+        let output = process.read_available();
+        let units = self.parser.push(&output);
+        packets.extend(self.packets(units));
+        Ok(packets)
+    }
+
+    fn set_bitrate(&mut self, bitrate: u32) -> StreamResult<Vec<EncodedPacket>> {
+        // ffmpeg 不能在编码过程中修改码率，结束当前进程，下一帧以新码率重新启动
+        self.config.set_bitrate(bitrate);
+        self.flush()
+    }
+
+    fn flush(&mut self) -> StreamResult<Vec<EncodedPacket>> {
+        let Some(mut process) = self.process.take() else {
+            return Ok(Vec::new());
+        };
+
+        // 显卡抓取不会因为输入关闭而结束，直接结束进程，丢弃尚未输出的帧
+        let output = match self.input.take() {
+            Some(EncoderInput::Gpu(_)) => process.read_available(),
+            _ => process.finish()?,
+        };
+        let mut units = self.parser.push(&output);
+        units.extend(self.parser.finish());
+        let packets = self.packets(units);
+        if !self.timestamps.is_empty() {
+            warn!("H.265 encoder dropped {} frames", self.timestamps.len());
+            self.timestamps.clear();
+        }
+        Ok(packets)
+    }
+}
+
+/// x264 风格的预设名称，从快到慢；AV1 编码器按序号换算为各自的速度档位
+const PRESETS: [&str; 9] = ["ultrafast", "superfast", "veryfast", "faster", "fast", "medium", "slow", "slower", "veryslow"];
+
+/// AV1 编码器 - 通过 ffmpeg 的 SVT-AV1、rav1e 或 libaom 编码，每个数据包为一个时间单元 (低开销 OBU 格式)
+///
+/// 使用低延迟配置 (无帧重排)，关键帧前总是带有序列头。preset 可以是 x264 风格的名称，
+/// 也可以直接写编码器的速度档位数字 (SVT-AV1 的 preset、rav1e 的 speed、libaom 的 cpu-used)
+pub struct Av1Encoder {
+    config: VideoEncoderConfig,
+    encoder: &'static str,
+    process: Option<FfmpegProcess>,
+    input: Option<(u32, u32, VideoPixelFormat)>, // 当前编码进程的输入格式
+    reader: IvfReader,
+    timestamps: VecDeque<u64>, // 已送入编码器、尚未输出的帧的时间戳
+    sequence_header: Option<Bytes>, // 最近的序列头，补到没有序列头的关键帧前
+}
+
+impl Av1Encoder {
+    pub fn new(config: VideoEncoderConfig) -> StreamResult<Self> {
+        let encoder = ["libsvtav1", "librav1e", "libaom-av1"].into_iter()
+            .find(|name| ffmpeg::has_encoder(name))
+            .ok_or_else(|| StreamError::Codec(
+                "AV1 encoding requires ffmpeg built with libsvtav1, librav1e or libaom in PATH".to_string()
+            ))?;
+        info!("Encoding AV1 with {} ({}x{}, {} kbps, speed {})",
+              encoder, config.width, config.height, config.bitrate, av1_speed(encoder, &config.preset));
+        if config.profile.is_some() || config.level.is_some() {
+            warn!("AV1 encoders choose the profile and level themselves, ignoring the configured values");
+        }
+
+        Ok(Self {
+            config,
+            encoder,
+            process: None,
+            input: None,
+            reader: IvfReader::new(),
+            timestamps: VecDeque::new(),
+            sequence_header: None,
+        })
+    }
+
+    fn start(&mut self, width: u32, height: u32, format: VideoPixelFormat) -> StreamResult<()> {
+        let pixel_format = format.raw_format().ok_or_else(|| {
+            StreamError::Codec(format!("AV1 encoder cannot encode {:?} frames yet, tone map them to SDR first", format))
+        })?;
+        let gop = (self.config.keyframe_interval.max(1) * self.config.fps.max(1)).to_string();
+        let speed = av1_speed(self.encoder, &self.config.preset);
+
+        let mut args: Vec<String> = [
+            "-f", "rawvideo", "-pix_fmt", pixel_format,
+            "-s", &format!("{}x{}", width, height),
+            "-framerate", &self.config.fps.max(1).to_string(),
+            "-i", "pipe:0",
+            "-vf", &format!("{},format=yuv420p", scale_filter(&self.config, format)),
+            "-c:v", self.encoder,
+            "-g", &gop,
+            "-vsync", "passthrough",
+        ].iter().map(|arg| arg.to_string()).collect();
+        // AV1 编码器的恒定质量参数各不相同，rav1e 的量化参数范围为 0~255
+        match (self.config.rate_control, self.encoder) {
+            (RateControl::Cqp, "librav1e") => args.extend(["-qp".to_string(), (self.config.qp.min(63) * 4).to_string()]),
+            (RateControl::Cqp, "libaom-av1") => args.extend(["-crf", &self.config.qp.min(63).to_string(), "-b:v", "0"]
+                .iter().map(|arg| arg.to_string())),
+            (RateControl::Cqp, _) => args.extend(["-crf".to_string(), self.config.qp.min(63).to_string()]),
+            _ => args.extend(self.config.rate_control_args()),
+        }
+        args.extend(BT709_TAGS.iter().map(|arg| arg.to_string()));
+        // 各编码器的低延迟设置：不使用前向参考帧，每输入一帧就输出一帧
+        let tuning: &[&str] = match (self.encoder, self.config.low_latency) {
+            ("libsvtav1", true) => &["-preset", speed.as_str(), "-svtav1-params", "pred-struct=1:scd=0"],
+            ("libsvtav1", false) => &["-preset", speed.as_str(), "-svtav1-params", "scd=0"],
+            ("librav1e", true) => &["-speed", speed.as_str(), "-rav1e-params", "low_latency=true"],
+            ("librav1e", false) => &["-speed", speed.as_str()],
+            (_, true) => &["-cpu-used", speed.as_str(), "-usage", "realtime", "-lag-in-frames", "0", "-row-mt", "1"],
+            (_, false) => &["-cpu-used", speed.as_str(), "-row-mt", "1"],
+        };
+        args.extend(tuning.iter().map(|arg| arg.to_string()));
+        args.extend(["-f", "ivf", "pipe:1"].iter().map(|arg| arg.to_string()));
+
+        self.process = Some(FfmpegProcess::spawn(&args)?);
+        self.input = Some((width, height, format));
+        self.reader = IvfReader::new();
+        self.timestamps.clear();
+        debug!("Started AV1 encoder for {}x{} {:?} input", width, height, format);
+        Ok(())
+    }
+
+    /// 按送入顺序对应帧的时间戳，必要时在关键帧前补上序列头
+    fn packets(&mut self, frames: Vec<Bytes>) -> Vec<EncodedPacket> {
+        frames.into_iter()
+            .filter_map(|frame| {
+                let timestamp = self.timestamps.pop_front()?;
+                let is_keyframe = av1::is_keyframe(&frame);
+                let data = match (av1::sequence_header(&frame), &self.sequence_header) {
+                    (Some(header), _) => {
+                        self.sequence_header = Some(Bytes::copy_from_slice(header));
+                        frame
+                    }
+                    (None, Some(header)) if is_keyframe => av1::insert_sequence_header(&frame, header),
+                    _ => frame,
+                };
+                Some(EncodedPacket {
+                    data,
+                    timestamp,
+                    is_keyframe,
+                    packet_type: PacketType::Video,
+                })
+            })
+            .collect()
+    }
+}
+
+/// 把预设换算为编码器的速度档位；不认识的名称按 fast 处理
+fn av1_speed(encoder: &str, preset: &str) -> String {
+    if preset.parse::<u32>().is_ok() {
+        return preset.to_string();
+    }
+    let index = PRESETS.iter().position(|name| *name == preset).unwrap_or(4) as u32;
+    match encoder {
+        "libsvtav1" => 12 - index, // 12 ~ 4
+        _ => 10 - index,           // rav1e 的 speed 和 libaom 的 cpu-used：10 ~ 2
+    }.to_string()
+}
+
+impl VideoEncoder for Av1Encoder {
+    fn encode_frame(&mut self, frame: &VideoFrame) -> StreamResult<Vec<EncodedPacket>> {
+        let size = frame.format.frame_size(frame.width, frame.height);
+        if frame.data.len() < size {
+            return Err(StreamError::Codec(format!(
+                "Video frame has {} bytes, expected {} for {}x{} {:?}",
+                frame.data.len(), size, frame.width, frame.height, frame.format
+            )));
+        }
+
+        let mut packets = Vec::new();
+        if self.input != Some((frame.width, frame.height, frame.format)) {
+            packets = self.flush()?;
+            self.start(frame.width, frame.height, frame.format)?;
+        }
+
+        let process = self.process.as_mut()
+            .ok_or_else(|| StreamError::Codec("AV1 encoder is not running".to_string()))?;
+        process.write(&frame.data[..size])?;
+        self.timestamps.push_back(frame.timestamp);
+
+        let output = process.read_available();
+        let frames = self.reader.push(&output);
+        packets.extend(self.packets(frames));
+        Ok(packets)
+    }
+
+    fn get_config(&self) -> VideoEncoderConfig {
+        self.config.clone()
+    }
+
+    fn preferred_input_format(&self) -> Option<VideoPixelFormat> {
+        Some(VideoPixelFormat::Yuv420p)
+    }
+
+    fn set_bitrate(&mut self, bitrate: u32) -> StreamResult<Vec<EncodedPacket>> {
+        // ffmpeg 不能在编码过程中修改码率，结束当前进程，下一帧以新码率重新启动
+        self.config.set_bitrate(bitrate);
+        self.flush()
+    }
+
+    fn flush(&mut self) -> StreamResult<Vec<EncodedPacket>> {
+        let Some(mut process) = self.process.take() else {
+            return Ok(Vec::new());
+        };
+        self.input = None;
+
+        let output = process.finish()?;
+        let frames = self.reader.push(&output);
+        let packets = self.packets(frames);
+        if !self.timestamps.is_empty() {
+            warn!("AV1 encoder dropped {} frames", self.timestamps.len());
+            self.timestamps.clear();
+        }
+        Ok(packets)
+    }
+}
+
+/// 每个 AAC-LC 帧的采样数
+const AAC_FRAME_SAMPLES: u64 = 1024;
+
+/// AAC 编码器 - 通过 ffmpeg 编码为 AAC-LC，输出去掉 ADTS 头部的原始帧
+///
+/// 优先使用 libfdk_aac，没有时使用 ffmpeg 自带的 aac 编码器。解码器配置 (AudioSpecificConfig)
+/// 由 codec_config 提供，用于 FLV 的 AAC sequence header。输入格式变化时重启编码进程
 pub struct AacEncoder {
     config: AudioEncoderConfig,
-    frame_count: u64,
+    encoder: &'static str,
+    process: Option<FfmpegProcess>,
+    input: Option<(u32, u32, AudioSampleFormat)>, // 当前编码进程的输入格式
+    parser: AdtsParser,
+    start: Option<u64>, // 第一个输入帧的时间戳，输出包按帧时长顺延
+    frames: u64,
 }
 
 impl AacEncoder {
     pub fn new(config: AudioEncoderConfig) -> StreamResult<Self> {
-        // 这里应该初始化 FFmpeg 的 AAC 编码器
+        let encoder = ["libfdk_aac", "aac"].into_iter()
+            .find(|name| ffmpeg::has_encoder(name))
+            .ok_or_else(|| StreamError::Codec("AAC encoding requires ffmpeg in PATH".to_string()))?;
+        info!("Encoding AAC-LC with {} ({} Hz, {} channels, {} kbps)",
+              encoder, config.sample_rate, config.channels, config.bitrate);
+
         Ok(Self {
             config,
-            frame_count: 0,
+            encoder,
+            process: None,
+            input: None,
+            parser: AdtsParser::new(),
+            start: None,
+            frames: 0,
         })
     }
+
+    fn start(&mut self, sample_rate: u32, channels: u32, format: AudioSampleFormat) -> StreamResult<()> {
+        // 直播平台通常要求恒定码率的 AAC，vbr 只对 Opus 生效
+        let args: Vec<String> = [
+            "-f", format.raw_format(),
+            "-ar", &sample_rate.to_string(),
+            "-ac", &channels.to_string(),
+            "-i", "pipe:0",
+            "-c:a", self.encoder,
+            "-profile:a", "aac_low",
+            "-ar", &self.config.sample_rate.to_string(),
+            "-ac", &self.config.channels.to_string(),
+            "-b:a", &format!("{}k", self.config.bitrate.max(8)),
+            "-flush_packets", "1",
+            "-f", "adts", "pipe:1",
+        ].iter().map(|arg| arg.to_string()).collect();
+
+        self.process = Some(FfmpegProcess::spawn(&args)?);
+        self.input = Some((sample_rate, channels, format));
+        self.parser = AdtsParser::new();
+        self.start = None;
+        self.frames = 0;
+        debug!("Started AAC encoder for {} Hz {} channel {:?} input", sample_rate, channels, format);
+        Ok(())
+    }
+
+    fn packets(&mut self, data: &[u8]) -> Vec<EncodedPacket> {
+        let start = self.start.unwrap_or_default();
+        let sample_rate = self.config.sample_rate.max(1) as u64;
+        let mut packets = Vec::new();
+        for data in self.parser.push(data) {
+            packets.push(EncodedPacket {
+                data,
+                timestamp: start + self.frames * AAC_FRAME_SAMPLES * 1000 / sample_rate,
+                is_keyframe: true,
+                packet_type: PacketType::Audio,
+            });
+            self.frames += 1;
+        }
+        packets
+    }
 }
 
 impl AudioEncoder for AacEncoder {
     fn encode_frame(&mut self, frame: &AudioFrame) -> StreamResult<Vec<EncodedPacket>> {
-        // 实际的 AAC 编码逻辑
-        self.frame_count += 1;
-        
-        let encoded_data = Bytes::from(format!("aac_frame_{}", self.frame_count));
-        
-        Ok(vec![EncodedPacket {
-            data: encoded_data,
-            timestamp: frame.timestamp,
-            is_keyframe: false,
-            packet_type: PacketType::Audio,
-        }])
+        let mut packets = Vec::new();
+        if self.input != Some((frame.sample_rate, frame.channels, frame.format)) {
+            packets = self.flush()?;
+            self.start(frame.sample_rate, frame.channels, frame.format)?;
+        }
+        self.start.get_or_insert(frame.timestamp);
+
+        let process = self.process.as_mut()
+            .ok_or_else(|| StreamError::Codec("AAC encoder is not running".to_string()))?;
+        process.write(&frame.data)?;
+
+        let output = process.read_available();
+        packets.extend(self.packets(&output));
+        Ok(packets)
     }
     
     fn get_config(&self) -> AudioEncoderConfig {
@@ -185,33 +1092,280 @@ impl AudioEncoder for AacEncoder {
     }
     
     fn flush(&mut self) -> StreamResult<Vec<EncodedPacket>> {
-        Ok(Vec::new())
+        let Some(mut process) = self.process.take() else {
+            return Ok(Vec::new());
+        };
+        self.input = None;
+
+        let output = process.finish()?;
+        Ok(self.packets(&output))
+    }
+
+    fn codec_config(&self) -> Option<Bytes> {
+        self.parser.config()
+    }
+}
+
+/// Opus 的输出采样率，WebRTC 协商为 OPUS/48000/2
+const OPUS_SAMPLE_RATE: u32 = 48000;
+
+/// 每个 Opus 包的时长 (毫秒)
+const OPUS_FRAME_MS: u64 = 20;
+
+/// Opus 编码器 - 通过 ffmpeg 的 libopus 编码，每个数据包为 20ms 的 Opus 帧
+///
+/// 输入不是 48kHz 时由 ffmpeg 重采样；输出的 Ogg 流在这里拆出 Opus 包，
+/// 可以直接发给 WebRTC 观众，服务器无需转码。输入格式变化时重启编码进程
+pub struct OpusEncoder {
+    config: AudioEncoderConfig,
+    process: Option<FfmpegProcess>,
+    input: Option<(u32, u32, AudioSampleFormat)>, // 当前编码进程的输入格式
+    reader: OggPacketReader,
+    headers: usize,     // 还需跳过的 OpusHead/OpusTags 头部包
+    start: Option<u64>, // 第一个输入帧的时间戳，输出包按帧时长顺延
+    packets: u64,
+}
+
+impl OpusEncoder {
+    pub fn new(config: AudioEncoderConfig) -> StreamResult<Self> {
+        if !ffmpeg::has_encoder("libopus") {
+            return Err(StreamError::Codec("Opus encoding requires ffmpeg built with libopus in PATH".to_string()));
+        }
+        info!("Encoding Opus with libopus ({} channels, {} kbps, {})",
+              config.channels, config.bitrate, if config.vbr { "VBR" } else { "CBR" });
+
+        Ok(Self {
+            config,
+            process: None,
+            input: None,
+            reader: OggPacketReader::new(),
+            headers: 2,
+            start: None,
+            packets: 0,
+        })
+    }
+
+    fn start(&mut self, sample_rate: u32, channels: u32, format: AudioSampleFormat) -> StreamResult<()> {
+        let args: Vec<String> = [
+            "-f", format.raw_format(),
+            "-ar", &sample_rate.to_string(),
+            "-ac", &channels.to_string(),
+            "-i", "pipe:0",
+            "-c:a", "libopus",
+            "-ar", &OPUS_SAMPLE_RATE.to_string(),
+            "-ac", &self.config.channels.clamp(1, 2).to_string(),
+            "-b:a", &format!("{}k", self.config.bitrate.max(6)),
+            "-vbr", if self.config.vbr { "on" } else { "off" },
+            "-application", "audio",
+            "-frame_duration", &OPUS_FRAME_MS.to_string(),
+            // 每页只放一个包，编码结果尽快输出
+            "-page_duration", &(OPUS_FRAME_MS * 1000).to_string(),
+            "-flush_packets", "1",
+            "-f", "ogg", "pipe:1",
+        ].iter().map(|arg| arg.to_string()).collect();
+
+        self.process = Some(FfmpegProcess::spawn(&args)?);
+        self.input = Some((sample_rate, channels, format));
+        self.reader = OggPacketReader::new();
+        self.headers = 2;
+        self.start = None;
+        self.packets = 0;
+        debug!("Started Opus encoder for {} Hz {} channel {:?} input", sample_rate, channels, format);
+        Ok(())
+    }
+
+    /// 跳过 Ogg 头部包，其余每个包为一帧
+    fn packets(&mut self, data: &[u8]) -> Vec<EncodedPacket> {
+        let start = self.start.unwrap_or_default();
+        let mut packets = Vec::new();
+        for data in self.reader.push(data) {
+            if self.headers > 0 {
+                self.headers -= 1;
+                continue;
+            }
+            packets.push(EncodedPacket {
+                data,
+                timestamp: start + self.packets * OPUS_FRAME_MS,
+                is_keyframe: true,
+                packet_type: PacketType::Audio,
+            });
+            self.packets += 1;
+        }
+        packets
+    }
+}
+
+impl AudioEncoder for OpusEncoder {
+    fn encode_frame(&mut self, frame: &AudioFrame) -> StreamResult<Vec<EncodedPacket>> {
+        let mut packets = Vec::new();
+        if self.input != Some((frame.sample_rate, frame.channels, frame.format)) {
+            packets = self.flush()?;
+            self.start(frame.sample_rate, frame.channels, frame.format)?;
+        }
+        self.start.get_or_insert(frame.timestamp);
+
+        let process = self.process.as_mut()
+            .ok_or_else(|| StreamError::Codec("Opus encoder is not running".to_string()))?;
+        process.write(&frame.data)?;
+
+        let output = process.read_available();
+        packets.extend(self.packets(&output));
+        Ok(packets)
+    }
+
+    fn get_config(&self) -> AudioEncoderConfig {
+        self.config.clone()
+    }
+
+    fn flush(&mut self) -> StreamResult<Vec<EncodedPacket>> {
+        let Some(mut process) = self.process.take() else {
+            return Ok(Vec::new());
+        };
+        self.input = None;
+
+        let output = process.finish()?;
+        Ok(self.packets(&output))
+    }
+}
+
+/// 视频编码器构造函数，按编码格式 + 后端名称注册到 EncoderFactory
+pub type VideoEncoderConstructor = Arc<dyn Fn(VideoEncoderConfig) -> StreamResult<Box<dyn VideoEncoder>> + Send + Sync>;
+
+/// 音频编码器构造函数，按编码格式 + 后端名称注册到 EncoderFactory
+pub type AudioEncoderConstructor = Arc<dyn Fn(AudioEncoderConfig) -> StreamResult<Box<dyn AudioEncoder>> + Send + Sync>;
+
+/// 内置编码器的后端名称，未指定后端时使用
+pub const BUILTIN_ENCODER: &str = "ffmpeg";
+
+/// 已注册的编码器构造函数
+struct EncoderRegistry {
+    video: HashMap<(crate::VideoCodec, String), VideoEncoderConstructor>,
+    audio: HashMap<(crate::AudioCodec, String), AudioEncoderConstructor>,
+}
+
+impl EncoderRegistry {
+    fn with_builtin() -> Self {
+        let mut registry = Self { video: HashMap::new(), audio: HashMap::new() };
+        registry.add_video(crate::VideoCodec::H264, |config| Ok(Box::new(H264Encoder::new(config)?)));
+        registry.add_video(crate::VideoCodec::H265, |config| Ok(Box::new(H265Encoder::new(config)?)));
+        registry.add_video(crate::VideoCodec::Av1, |config| Ok(Box::new(Av1Encoder::new(config)?)));
+        registry.add_audio(crate::AudioCodec::Aac, |config| Ok(Box::new(AacEncoder::new(config)?)));
+        registry.add_audio(crate::AudioCodec::Opus, |config| Ok(Box::new(OpusEncoder::new(config)?)));
+        #[cfg(feature = "mock")]
+        registry.add_mock();
+        registry
     }
+
+    fn add_video<F>(&mut self, codec: crate::VideoCodec, constructor: F)
+    where
+        F: Fn(VideoEncoderConfig) -> StreamResult<Box<dyn VideoEncoder>> + Send + Sync + 'static,
+    {
+        self.video.insert((codec, BUILTIN_ENCODER.to_string()), Arc::new(constructor));
+    }
+
+    fn add_audio<F>(&mut self, codec: crate::AudioCodec, constructor: F)
+    where
+        F: Fn(AudioEncoderConfig) -> StreamResult<Box<dyn AudioEncoder>> + Send + Sync + 'static,
+    {
+        self.audio.insert((codec, BUILTIN_ENCODER.to_string()), Arc::new(constructor));
+    }
+
+    /// 所有编码格式都注册 "mock" 后端的直通编码器
+    #[cfg(feature = "mock")]
+    fn add_mock(&mut self) {
+        use crate::mock::{PassthroughAudioEncoder, PassthroughVideoEncoder, MOCK_ENCODER};
+        use crate::{AudioCodec, VideoCodec};
+
+        for codec in [VideoCodec::H264, VideoCodec::H265, VideoCodec::Vp8, VideoCodec::Vp9, VideoCodec::Av1] {
+            self.video.insert((codec, MOCK_ENCODER.to_string()), Arc::new(|config| Ok(Box::new(PassthroughVideoEncoder::new(config)))));
+        }
+        for codec in [AudioCodec::Aac, AudioCodec::Opus, AudioCodec::Mp3, AudioCodec::Pcm] {
+            self.audio.insert((codec, MOCK_ENCODER.to_string()), Arc::new(|config| Ok(Box::new(PassthroughAudioEncoder::new(config)))));
+        }
+    }
+}
+
+static ENCODERS: LazyLock<RwLock<EncoderRegistry>> = LazyLock::new(|| RwLock::new(EncoderRegistry::with_builtin()));
+
+fn registry() -> RwLockReadGuard<'static, EncoderRegistry> {
+    // 构造函数在锁外调用，锁不会因编码器创建失败而中毒；万一中毒，表中的数据仍然完整
+    ENCODERS.read().unwrap_or_else(|e| e.into_inner())
+}
+
+fn registry_mut() -> RwLockWriteGuard<'static, EncoderRegistry> {
+    ENCODERS.write().unwrap_or_else(|e| e.into_inner())
 }
 
 /// 编码器工厂
+///
+/// 内置编码器以 "ffmpeg" 后端注册；外部 crate 可以为任意编码格式注册自己的后端 (如硬件厂商 SDK)，
+/// 再通过配置中的 encoder 选择，无需修改本 crate
 pub struct EncoderFactory;
 
 impl EncoderFactory {
-    /// 创建视频编码器
+    /// 注册视频编码器，同一编码格式和后端名称重复注册时覆盖之前的构造函数 (包括内置的 "ffmpeg")
+    pub fn register_video_encoder<F>(codec: crate::VideoCodec, backend: &str, constructor: F)
+    where
+        F: Fn(VideoEncoderConfig) -> StreamResult<Box<dyn VideoEncoder>> + Send + Sync + 'static,
+    {
+        info!("Registered {:?} video encoder backend: {}", codec, backend);
+        registry_mut().video.insert((codec, backend.to_string()), Arc::new(constructor));
+    }
+
+    /// 注册音频编码器，同一编码格式和后端名称重复注册时覆盖之前的构造函数 (包括内置的 "ffmpeg")
+    pub fn register_audio_encoder<F>(codec: crate::AudioCodec, backend: &str, constructor: F)
+    where
+        F: Fn(AudioEncoderConfig) -> StreamResult<Box<dyn AudioEncoder>> + Send + Sync + 'static,
+    {
+        info!("Registered {:?} audio encoder backend: {}", codec, backend);
+        registry_mut().audio.insert((codec, backend.to_string()), Arc::new(constructor));
+    }
+
+    /// 某个视频编码格式已注册的后端名称，按名称排序
+    pub fn video_backends(codec: &crate::VideoCodec) -> Vec<String> {
+        let mut backends: Vec<String> = registry().video.keys()
+            .filter(|(registered, _)| registered == codec)
+            .map(|(_, backend)| backend.clone())
+            .collect();
+        backends.sort();
+        backends
+    }
+
+    /// 某个音频编码格式已注册的后端名称，按名称排序
+    pub fn audio_backends(codec: &crate::AudioCodec) -> Vec<String> {
+        let mut backends: Vec<String> = registry().audio.keys()
+            .filter(|(registered, _)| registered == codec)
+            .map(|(_, backend)| backend.clone())
+            .collect();
+        backends.sort();
+        backends
+    }
+
+    /// 使用内置后端创建视频编码器
     pub fn create_video_encoder(config: VideoEncoderConfig) -> StreamResult<Box<dyn VideoEncoder>> {
-        match config.codec {
-            crate::VideoCodec::H264 => {
-                let encoder = H264Encoder::new(config)?;
-                Ok(Box::new(encoder))
-            }
-            _ => Err(StreamError::Codec(format!("Unsupported video codec: {:?}", config.codec))),
+        Self::create_video_encoder_with(BUILTIN_ENCODER, config)
+    }
+
+    /// 使用指定后端创建视频编码器
+    pub fn create_video_encoder_with(backend: &str, config: VideoEncoderConfig) -> StreamResult<Box<dyn VideoEncoder>> {
+        let constructor = registry().video.get(&(config.codec.clone(), backend.to_string())).cloned();
+        match constructor {
+            Some(constructor) => constructor(config),
+            None => Err(StreamError::Codec(format!("Unsupported video codec: {:?} (encoder backend: {})", config.codec, backend))),
         }
     }
-    
-    /// 创建音频编码器
+
+    /// 使用内置后端创建音频编码器
     pub fn create_audio_encoder(config: AudioEncoderConfig) -> StreamResult<Box<dyn AudioEncoder>> {
-        match config.codec {
-            crate::AudioCodec::Aac => {
-                let encoder = AacEncoder::new(config)?;
-                Ok(Box::new(encoder))
-            }
-            _ => Err(StreamError::Codec(format!("Unsupported audio codec: {:?}", config.codec))),
+        Self::create_audio_encoder_with(BUILTIN_ENCODER, config)
+    }
+
+    /// 使用指定后端创建音频编码器
+    pub fn create_audio_encoder_with(backend: &str, config: AudioEncoderConfig) -> StreamResult<Box<dyn AudioEncoder>> {
+        let constructor = registry().audio.get(&(config.codec.clone(), backend.to_string())).cloned();
+        match constructor {
+            Some(constructor) => constructor(config),
+            None => Err(StreamError::Codec(format!("Unsupported audio codec: {:?} (encoder backend: {})", config.codec, backend))),
         }
     }
 }
@@ -1,121 +1,1311 @@
+use std::collections::HashMap;
+use std::fmt;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use crate::protocol::{StreamProtocol, VideoCodec, AudioCodec};
 
 /// 客户端配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ClientConfig {
+    #[serde(default)]
+    pub latency_mode: LatencyMode,
     pub server: ServerEndpoint,
     pub stream: StreamConfig,
     pub capture: CaptureConfig,
     pub encoding: EncodingConfig,
     pub network: NetworkConfig,
+    #[serde(default)]
+    pub stats: StatsConfig,
+    #[serde(default)]
+    pub scenes: Vec<SceneConfig>, // 为空时直接推送捕获画面，否则从第一个场景开始
+    #[serde(default)]
+    pub hotkeys: HotkeyConfig,
+    #[serde(default)]
+    pub preview: PreviewConfig,
+    #[serde(default)]
+    pub replay: ReplayConfig,
+    #[serde(default)]
+    pub control: ControlConfig,
+    #[serde(default)]
+    pub remote_input: RemoteInputConfig,
+}
+
+/// 超低延迟模式的推流端设置
+const ULTRA_LOW_KEYFRAME_INTERVAL: u32 = 1; // 秒
+const ULTRA_LOW_HLS_SEGMENT_DURATION: u32 = 1; // 秒
+const ULTRA_LOW_QUEUED_FRAMES: usize = 2;
+const ULTRA_LOW_WRITE_SIZE: usize = 16 * 1024; // 字节
+const ULTRA_LOW_SRT_LATENCY: u32 = 60; // 毫秒
+const ULTRA_LOW_RIST_REORDER_BUFFER: u32 = 20; // 毫秒
+
+/// 延迟模式 - 推流端和服务器分别设置，都设为 ultra_low 时整条链路按最低延迟配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LatencyMode {
+    #[default]
+    Normal,
+    UltraLow, // 互动直播和远程游玩：画质、抗丢包能力和 HLS 的流畅度让位于延迟
+}
+
+impl ClientConfig {
+    /// 带注释的默认配置 (仓库中的 client.toml)，--print-default-config 输出的内容
+    pub const TEMPLATE: &'static str = include_str!("../../client.toml");
+
+    /// 配置文件的 JSON Schema，供部署工具和编辑器在启动前校验配置；[profiles.<name>] 按名称覆盖的部分不在其中
+    pub fn json_schema() -> serde_json::Value {
+        schemars::schema_for!(ClientConfig).to_value()
+    }
+
+    /// ultra_low 时收紧编码和发送配置：1 秒 GOP、无 B 帧、低延迟调优、半秒的 VBV 缓冲、
+    /// 更短的编码队列和单次写入，以及更小的 SRT/RIST 接收缓冲；已经更小的设置保持不变
+    pub fn apply_latency_mode(&mut self) {
+        if self.latency_mode != LatencyMode::UltraLow {
+            return;
+        }
+
+        let video = &mut self.encoding.video;
+        video.keyframe_interval = ULTRA_LOW_KEYFRAME_INTERVAL;
+        video.b_frames = 0;
+        video.low_latency = true;
+        let half_second = video.max_bitrate.unwrap_or(video.bitrate) / 2;
+        video.buffer_size = Some(video.buffer_size.map_or(half_second, |size| size.min(half_second)));
+        self.encoding.max_queued_frames = self.encoding.max_queued_frames.clamp(1, ULTRA_LOW_QUEUED_FRAMES);
+
+        self.network.buffer_size = self.network.buffer_size.min(ULTRA_LOW_WRITE_SIZE);
+        self.server.latency = self.server.latency.min(ULTRA_LOW_SRT_LATENCY);
+        let reorder_buffer = &mut self.network.loss_recovery.rist_reorder_buffer;
+        *reorder_buffer = Some(reorder_buffer.map_or(ULTRA_LOW_RIST_REORDER_BUFFER, |buffer| buffer.min(ULTRA_LOW_RIST_REORDER_BUFFER)));
+    }
+}
+
+/// 场景 - 由多个图层合成画面，画布大小为编码分辨率
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SceneConfig {
+    pub name: String,
+    #[serde(default)]
+    pub layers: Vec<LayerConfig>,
+}
+
+/// 场景图层
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LayerConfig {
+    #[serde(default)]
+    pub name: String,
+    pub source: LayerSource,
+    #[serde(default)]
+    pub x: i32,
+    #[serde(default)]
+    pub y: i32,
+    #[serde(default)]
+    pub width: Option<u32>, // 缩放到的宽度，未设置时按 scale 缩放
+    #[serde(default)]
+    pub height: Option<u32>,
+    #[serde(default = "default_layer_scale")]
+    pub scale: f32,
+    #[serde(default)]
+    pub crop: LayerCrop,
+    #[serde(default)]
+    pub z_index: i32, // 大的在上层，相同时按配置顺序
+    #[serde(default = "default_layer_opacity")]
+    pub opacity: f32, // 0.0 - 1.0
+    #[serde(default = "default_layer_visible")]
+    pub visible: bool,
+}
+
+fn default_layer_scale() -> f32 {
+    1.0
+}
+
+fn default_layer_opacity() -> f32 {
+    1.0
+}
+
+fn default_layer_visible() -> bool {
+    true
+}
+
+/// 图层裁剪 (从各边裁掉的像素)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct LayerCrop {
+    #[serde(default)]
+    pub left: u32,
+    #[serde(default)]
+    pub top: u32,
+    #[serde(default)]
+    pub right: u32,
+    #[serde(default)]
+    pub bottom: u32,
+}
+
+/// 图层来源
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub enum LayerSource {
+    Capture, // [capture.video_source] 的画面
+    Video {
+        source: VideoSource, // 另一个捕获源
+    },
+    Webcam {
+        device: String, // Linux 为 /dev/videoN，Windows 为 DirectShow 设备名，macOS 为 AVFoundation 设备序号
+        #[serde(default = "default_webcam_size")]
+        size: (u32, u32),
+        #[serde(default = "default_webcam_fps")]
+        fps: u32,
+    },
+    Image {
+        path: String,
+    },
+    Color {
+        color: [u8; 4], // RGBA
+    },
+    Text {
+        text: String, // 可包含 {time}、{date}、{fps} 占位符，多行用 \n 分隔
+        #[serde(default)]
+        font: Option<String>, // TTF/OTF 字体文件，未设置时使用系统字体
+        #[serde(default = "default_font_size")]
+        font_size: f32,
+        #[serde(default = "default_text_color")]
+        color: [u8; 4],
+        #[serde(default)]
+        background: Option<[u8; 4]>, // 文字背景色，未设置时透明
+    },
+    Browser {
+        url: String, // 网页地址，可用于关注提醒等动态内容
+        #[serde(default = "default_browser_size")]
+        size: (u32, u32),
+        #[serde(default = "default_browser_refresh")]
+        refresh_interval: u64, // 重新截取网页的间隔(秒)
+        #[serde(default)]
+        executable: Option<String>, // Chrome/Chromium/Edge 可执行文件，未设置时自动查找
+    },
+}
+
+fn default_font_size() -> f32 {
+    32.0
+}
+
+fn default_text_color() -> [u8; 4] {
+    [255, 255, 255, 255]
+}
+
+fn default_browser_size() -> (u32, u32) {
+    (1920, 1080)
+}
+
+fn default_browser_refresh() -> u64 {
+    5
+}
+
+fn default_webcam_size() -> (u32, u32) {
+    (1280, 720)
+}
+
+fn default_webcam_fps() -> u32 {
+    30
 }
 
 /// 服务器端点配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ServerEndpoint {
     pub protocol: StreamProtocol,
     pub host: String,
     pub port: u16,
-    pub stream_key: String,
+    pub stream_key: String, // 也可以是 "env:<变量名>" 或 "keychain:<名称>"，启动时替换为实际的值
     pub app_name: Option<String>, // For RTMP
+    #[serde(default = "default_srt_latency")]
+    pub latency: u32, // SRT 接收端缓冲 (毫秒)，丢包重传需要在这段时间内完成
+    #[serde(default)]
+    pub passphrase: Option<String>, // SRT 加密口令 (10~79 个字符)，为空时不加密
+    #[serde(default)]
+    pub tls: bool, // RTMPS：经 TLS 推流到 rtmps://host:port (端口通常为 443)
+    #[serde(default = "default_true")]
+    pub tls_verify: bool, // 校验服务器证书，只应在测试自签名证书时关闭
+    #[serde(default)]
+    pub tls_ca_file: Option<String>, // 额外信任的 CA 证书 (PEM)，用于私有 CA 或企业网络的 TLS 检查
+    #[serde(default)]
+    pub tls_server_name: Option<String>, // SNI 和证书校验使用的域名，默认为 host
+}
+
+fn default_srt_latency() -> u32 {
+    120
+}
+
+// 流密钥和口令不出现在日志中
+impl fmt::Debug for ServerEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerEndpoint")
+            .field("protocol", &self.protocol)
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("stream_key", &Redacted(&self.stream_key))
+            .field("app_name", &self.app_name)
+            .field("latency", &self.latency)
+            .field("passphrase", &self.passphrase.as_deref().map(Redacted))
+            .field("tls", &self.tls)
+            .field("tls_verify", &self.tls_verify)
+            .field("tls_ca_file", &self.tls_ca_file)
+            .field("tls_server_name", &self.tls_server_name)
+            .finish()
+    }
+}
+
+/// 调试输出中代替密钥的内容，"env:" 和 "keychain:" 引用本身不是密钥，照常输出
+struct Redacted<'a>(&'a str);
+
+impl fmt::Debug for Redacted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() || self.0.starts_with("env:") || self.0.starts_with("keychain:") {
+            fmt::Debug::fmt(self.0, f)
+        } else {
+            f.write_str("\"<redacted>\"")
+        }
+    }
 }
 
 /// 流配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct StreamConfig {
     pub title: Option<String>,
     pub description: Option<String>,
     pub auto_reconnect: bool,
     pub reconnect_interval: u64, // seconds
     pub max_reconnect_attempts: u32,
+    #[serde(default)]
+    pub pause: PauseConfig,
+    #[serde(default)]
+    pub schedule: ScheduleConfig,
+}
+
+/// 定时推流 - 无人值守的机器 (如比赛用电脑) 到点自动开始推流，推流一段时间后自动停止
+///
+/// 设置了 start_at 或 cron 时客户端以停止推流的状态启动，等到开始时间；都未设置时立即开始
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ScheduleConfig {
+    #[serde(default)]
+    pub start_at: Option<String>, // 本地时间 "YYYY-MM-DD HH:MM"，只开始一次
+    #[serde(default)]
+    pub cron: Option<String>, // 重复开始的时间，与 crontab 的前五项相同："分 时 日 月 星期"
+    #[serde(default)]
+    pub stop_after: Option<u64>, // 每次开始后推流的秒数，未设置时不自动停止
+}
+
+/// 暂停推流时代替捕获画面的占位画面 ("稍后回来")，声音同时静音
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PauseConfig {
+    #[serde(default)]
+    pub slate_image: Option<String>, // 缩放到编码分辨率，透明部分显示 slate_color
+    #[serde(default = "default_slate_color")]
+    pub slate_color: [u8; 4], // RGBA，未设置图片或图片无法加载时显示纯色
+}
+
+fn default_slate_color() -> [u8; 4] {
+    [0, 0, 0, 255]
+}
+
+impl Default for PauseConfig {
+    fn default() -> Self {
+        Self {
+            slate_image: None,
+            slate_color: default_slate_color(),
+        }
+    }
+}
+
+/// 捕获配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CaptureConfig {
+    pub video_source: VideoSource,
+    pub audio_source: AudioSource,
+    pub capture_cursor: bool,
+    #[serde(default)]
+    pub portal: PortalConfig,
+    #[serde(default)]
+    pub window: WindowCaptureConfig,
+    #[serde(default)]
+    pub cursor: CursorConfig,
+    #[serde(default)]
+    pub game: GameCaptureConfig,
+    #[serde(default)]
+    pub audio_mixer: AudioMixerConfig,
+    #[serde(default)]
+    pub audio_filters: Vec<AudioFilter>, // 作用于 audio_source 的滤镜，混音输入在各自的 filters 中配置
+    #[serde(default)]
+    pub zero_copy: ZeroCopyConfig,
+}
+
+/// 零拷贝捕获配置 - 屏幕捕获和编码都能在显卡上进行时，画面不下载到内存，直接在显卡上编码
+///
+/// 目前支持 Linux 上 KMS 抓取 + VA-API 编码，需要开启 hardware_acceleration，且 ffmpeg 有 CAP_SYS_ADMIN 权限
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ZeroCopyConfig {
+    pub enabled: bool, // 条件满足时自动使用，否则回退到内存中的画面
+    pub drm_device: String,
+    pub crtc_id: Option<u32>, // 抓取的显示输出，未设置时为第一个活动的输出
+}
+
+impl Default for ZeroCopyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            drm_device: "/dev/dri/card0".to_string(),
+            crtc_id: None,
+        }
+    }
+}
+
+/// 音频混音配置 - 配置了 inputs 时代替 audio_source，把多个音频源混合为一路
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AudioMixerConfig {
+    #[serde(default)]
+    pub inputs: Vec<AudioMixInput>,
+    #[serde(default)]
+    pub limiter: LimiterConfig,
+    #[serde(default)]
+    pub silence: SilenceDetectionConfig,
+}
+
+/// 混音输入
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AudioMixInput {
+    pub name: String, // 运行时调整音量和静音时使用的名称
+    pub source: AudioSource,
+    #[serde(default)]
+    pub gain_db: f32,
+    #[serde(default)]
+    pub muted: bool,
+    #[serde(default)]
+    pub filters: Vec<AudioFilter>,
+}
+
+/// 混音输出的限幅器，防止多路叠加后削波
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LimiterConfig {
+    pub enabled: bool,
+    pub threshold_db: f32, // 输出峰值上限 (dBFS)
+    pub release_ms: u32,   // 增益恢复时间
+}
+
+impl Default for LimiterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold_db: -1.0,
+            release_ms: 250,
+        }
+    }
+}
+
+/// 静音检测 - 推流中的输入持续低于阈值时警告，防止麦克风或游戏声音没有声音而不自知
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SilenceDetectionConfig {
+    #[serde(default = "default_silence_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_silence_threshold")]
+    pub threshold_db: f32, // 峰值电平低于该值 (dBFS) 视为无声，在音量和静音之后测量
+    #[serde(default = "default_silence_duration")]
+    pub duration: u64,     // 持续无声多少秒后警告
+    #[serde(default)]
+    pub inputs: Vec<String>, // 检测的混音输入，为空时检测全部
+}
+
+fn default_silence_enabled() -> bool {
+    true
+}
+
+fn default_silence_threshold() -> f32 {
+    -60.0
+}
+
+fn default_silence_duration() -> u64 {
+    30
+}
+
+impl Default for SilenceDetectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_silence_enabled(),
+            threshold_db: default_silence_threshold(),
+            duration: default_silence_duration(),
+            inputs: Vec::new(),
+        }
+    }
+}
+
+/// 音频滤镜，在混音之前按顺序作用于输入
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub enum AudioFilter {
+    NoiseSuppression {
+        #[serde(default = "default_suppression_strength")]
+        strength: f32, // 0~1，越大去除的噪声越多，过大会损伤人声
+    },
+    Gate {
+        #[serde(default = "default_gate_threshold")]
+        threshold_db: f32, // 低于该电平时关闭
+        #[serde(default = "default_gate_attack")]
+        attack_ms: u32,    // 打开所需时间
+        #[serde(default = "default_gate_release")]
+        release_ms: u32,   // 关闭所需时间
+    },
+}
+
+fn default_suppression_strength() -> f32 {
+    0.8
+}
+
+fn default_gate_threshold() -> f32 {
+    -45.0
+}
+
+fn default_gate_attack() -> u32 {
+    5
+}
+
+fn default_gate_release() -> u32 {
+    150
+}
+
+/// 游戏捕获配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GameCaptureConfig {
+    pub frame_dir: Option<String>, // 钩子写入共享画面的目录，默认 Linux 为 /dev/shm，其他系统为临时目录
+    pub hook_timeout: u64, // seconds，等待钩子出帧的时间
+    pub fallback_to_window: bool, // 没有钩子时改为捕获该进程的窗口
+}
+
+impl Default for GameCaptureConfig {
+    fn default() -> Self {
+        Self {
+            frame_dir: None,
+            hook_timeout: 10,
+            fallback_to_window: true,
+        }
+    }
+}
+
+/// 光标绘制配置 (capture_cursor = true 且捕获画面本身不含光标时生效)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CursorConfig {
+    pub highlight: bool, // 在光标周围绘制高亮圆环，适合教程演示
+    pub highlight_radius: u32,
+    pub highlight_color: [u8; 4], // RGBA
+}
+
+impl Default for CursorConfig {
+    fn default() -> Self {
+        Self {
+            highlight: false,
+            highlight_radius: 24,
+            highlight_color: [255, 215, 0, 160],
+        }
+    }
+}
+
+/// 窗口捕获配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct WindowCaptureConfig {
+    pub slate_image: Option<String>, // 窗口不可用 (关闭、最小化) 时显示的图片，未设置时输出黑帧
+}
+
+/// Wayland 桌面门户 (xdg-desktop-portal) 捕获配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PortalConfig {
+    pub persist: bool, // 保存授权，之后启动时不再弹出选择对话框
+    pub restore_token_file: String,
+}
+
+impl Default for PortalConfig {
+    fn default() -> Self {
+        Self {
+            persist: true,
+            restore_token_file: ".portal_restore_token".to_string(),
+        }
+    }
+}
+
+/// 视频源配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub enum VideoSource {
+    Screen {
+        display_index: u32,
+    },
+    /// 所有已设置的条件都满足的窗口，窗口移动、缩放后继续跟随
+    Window {
+        #[serde(default)]
+        window_title: String, // 标题包含该文本
+        #[serde(default)]
+        title_regex: Option<String>,
+        #[serde(default)]
+        process_name: Option<String>,
+        #[serde(default)]
+        pid: Option<u32>,
+    },
+    /// 设置 follow_window 时区域跟随窗口的位置和大小，忽略 x/y/width/height
+    Region {
+        #[serde(default)]
+        x: u32,
+        #[serde(default)]
+        y: u32,
+        #[serde(default)]
+        width: u32,
+        #[serde(default)]
+        height: u32,
+        #[serde(default)]
+        follow_window: Option<WindowSelector>,
+        #[serde(default)]
+        padding: u32, // 跟随窗口时向外扩展的像素
+    },
+    /// 通过桌面门户和 PipeWire 捕获 (Wayland)，由系统对话框选择屏幕或窗口
+    Portal {
+        #[serde(default)]
+        source_type: PortalSourceType,
+    },
+    /// 把多个显示器拼接成一个画面
+    Mosaic {
+        #[serde(default)]
+        displays: Vec<u32>, // 为空时使用所有显示器
+        #[serde(default)]
+        layout: MosaicLayout,
+        #[serde(default)]
+        columns: Option<u32>, // 网格列数，默认取接近正方形的列数
+    },
+    /// 游戏捕获：由注入目标进程的图形 API 钩子在合成前提供画面，按进程名或 PID 选择进程
+    Game {
+        #[serde(default)]
+        process_name: Option<String>,
+        #[serde(default)]
+        pid: Option<u32>,
+        #[serde(default)]
+        api: GraphicsApi,
+    },
+    /// 内置测试图案 (彩条、移动的方块和时间码)，不需要捕获设备
+    TestPattern {
+        #[serde(default = "default_test_pattern_width")]
+        width: u32,
+        #[serde(default = "default_test_pattern_height")]
+        height: u32,
+    },
+}
+
+fn default_test_pattern_width() -> u32 {
+    1920
+}
+
+fn default_test_pattern_height() -> u32 {
+    1080
+}
+
+/// 门户对话框中可选择的源类型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum PortalSourceType {
+    #[default]
+    Monitor,
+    Window,
+    Any,
+}
+
+/// 多显示器拼接方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum MosaicLayout {
+    #[default]
+    SideBySide, // 排成一行
+    Grid,
+}
+
+/// 游戏捕获钩子的图形 API
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum GraphicsApi {
+    #[default]
+    Auto, // 接受任意钩子提供的画面
+    Vulkan,
+    D3D11,
+    D3D12,
+    OpenGL,
+}
+
+/// 区域跟随的窗口，所有已设置的条件都需满足
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WindowSelector {
+    #[serde(default)]
+    pub window_title: String,
+    #[serde(default)]
+    pub title_regex: Option<String>,
+    #[serde(default)]
+    pub process_name: Option<String>,
+    #[serde(default)]
+    pub pid: Option<u32>,
+}
+
+/// 音频源配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub enum AudioSource {
+    Default,
+    Device {
+        device_name: String,
+    },
+    System, // 系统播放的声音 (Windows WASAPI 环回，Linux PulseAudio/PipeWire 监视器)
+    TestTone, // 内置测试音，每秒开头 200ms 的 1 kHz 提示音，与测试图案同步
+    Disabled,
+}
+
+/// 编码配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EncodingConfig {
+    pub video: VideoEncodingConfig,
+    pub audio: AudioEncodingConfig,
+    pub hardware_acceleration: bool,
+    #[serde(default)]
+    pub vaapi_device: Option<String>, // Linux VA-API 渲染节点，未设置时使用 /dev/dri/renderD128
+    #[serde(default = "default_max_queued_frames")]
+    pub max_queued_frames: usize, // 等待编码的视频帧上限，编码跟不上时丢弃最旧的帧
+}
+
+fn default_max_queued_frames() -> usize {
+    8
+}
+
+/// 视频编码配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct VideoEncodingConfig {
+    pub codec: VideoCodec,
+    pub width: u32,
+    pub height: u32,
+    pub fps: f64, // 可为小数，如 59.94 (按 60000/1001 处理)
+    pub bitrate: u32, // kbps
+    pub keyframe_interval: u32, // seconds
+    pub preset: String, // e.g., "ultrafast", "fast", "medium", "slow"
+    #[serde(default)]
+    pub hdr: HdrConfig,
+    #[serde(default)]
+    pub static_frames: StaticFrameConfig,
+    #[serde(default)]
+    pub rate_control: RateControl,
+    pub max_bitrate: Option<u32>, // kbps，VBR 的峰值码率，未设置时为 bitrate 的 1.5 倍
+    pub buffer_size: Option<u32>, // kbit，VBV 缓冲大小，未设置时为峰值码率的 2 倍
+    #[serde(default = "default_video_qp")]
+    pub qp: u32, // CQP 的量化参数，H.264/H.265 为 0~51，AV1 为 0~63
+    pub profile: Option<String>, // 如 "baseline"、"main"、"high"，未设置时由编码器决定
+    pub level: Option<String>, // 如 "4.1"
+    #[serde(default)]
+    pub b_frames: u32,
+    #[serde(default = "default_low_latency")]
+    pub low_latency: bool, // 低延迟调优：不使用前瞻和帧重排，每输入一帧立即输出
+    #[serde(default)]
+    pub encoder: Option<String>, // 编码器后端，未设置时使用内置的 ffmpeg；其他后端需由嵌入程序注册到 EncoderFactory
+}
+
+fn default_video_qp() -> u32 {
+    23
+}
+
+fn default_low_latency() -> bool {
+    true
+}
+
+/// 码率控制方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+pub enum RateControl {
+    #[default]
+    Cbr, // 恒定码率，直播平台推荐
+    Vbr, // 可变码率，不超过 max_bitrate
+    Cqp, // 恒定量化参数，码率随画面变化，适合本地录制
+}
+
+/// HDR 画面处理配置 (捕获到 HDR 画面时生效)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HdrConfig {
+    pub mode: HdrMode,
+    pub tone_mapping: ToneMappingOperator,
+    pub peak_luminance: f32, // nits，映射到 SDR 白色以上的最高亮度
+    pub sdr_white: f32, // nits，SDR 白色对应的亮度 (BT.2408 建议 203)
+}
+
+impl Default for HdrConfig {
+    fn default() -> Self {
+        Self {
+            mode: HdrMode::ToneMap,
+            tone_mapping: ToneMappingOperator::Hable,
+            peak_luminance: 1000.0,
+            sdr_white: 203.0,
+        }
+    }
+}
+
+/// HDR 处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum HdrMode {
+    ToneMap, // 色调映射到 SDR
+    Passthrough, // 以 10 位 HDR 编码，编码格式不支持时改为色调映射
+}
+
+/// 画面静止 (菜单、暂停) 时的处理，降低空闲时的 CPU 占用和码率
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StaticFrameConfig {
+    pub mode: StaticFrameMode,
+    pub refresh_interval: u64, // ms，画面静止时至少按该间隔完整编码一帧
+}
+
+impl Default for StaticFrameConfig {
+    fn default() -> Self {
+        Self {
+            mode: StaticFrameMode::Repeat,
+            refresh_interval: 1000,
+        }
+    }
+}
+
+/// 未变化的帧的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum StaticFrameMode {
+    Off,    // 每帧都编码
+    Repeat, // 输出重复帧 (编码器不支持时照常编码)，保持恒定帧率
+    Skip,   // 不输出，帧率随画面变化
+}
+
+/// 色调映射算子
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum ToneMappingOperator {
+    Reinhard,
+    Hable,
+    Aces,
+}
+
+/// 音频编码配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AudioEncodingConfig {
+    pub codec: AudioCodec,
+    pub sample_rate: u32,
+    pub channels: u32,
+    pub bitrate: u32, // kbps
+    #[serde(default = "default_audio_vbr")]
+    pub vbr: bool, // 可变码率，静音时码率更低；关闭后为恒定码率
+    #[serde(default)]
+    pub encoder: Option<String>, // 编码器后端，未设置时使用内置的 ffmpeg
+}
+
+fn default_audio_vbr() -> bool {
+    true
+}
+
+impl AudioEncodingConfig {
+    /// 实际采集和编码使用的采样率，Opus 固定为 48kHz，避免编码前再重采样
+    pub fn effective_sample_rate(&self) -> u32 {
+        match self.codec {
+            AudioCodec::Opus => 48000,
+            _ => self.sample_rate,
+        }
+    }
+}
+
+/// 网络配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct NetworkConfig {
+    pub connection_timeout: u64, // seconds
+    pub read_timeout: u64, // seconds
+    pub write_timeout: u64, // seconds
+    pub buffer_size: usize, // 字节，单次写入网络的上限
+    #[serde(default = "default_true")]
+    pub pacing: bool, // 把关键帧等大包拆成小块，在帧间隔内均匀发送，避免突发写入在受限的上行链路上丢包
+    #[serde(default)]
+    pub adaptive_bitrate: AdaptiveBitrateConfig,
+    #[serde(default)]
+    pub reconnect: ReconnectConfig,
+    #[serde(default)]
+    pub loss_recovery: LossRecoveryConfig,
+    #[serde(default)]
+    pub failover: FailoverConfig,
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>, // 推流连接经过的代理，未设置时直接连接
+}
+
+/// 推流代理：无法直接访问服务器端口 (如 1935) 时经由 SOCKS5 或 HTTP CONNECT 代理建立 TCP 连接
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProxyConfig {
+    pub protocol: ProxyProtocol,
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+impl fmt::Debug for ProxyConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProxyConfig")
+            .field("protocol", &self.protocol)
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("password", &self.password.as_deref().map(Redacted))
+            .finish()
+    }
+}
+
+/// 代理协议
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum ProxyProtocol {
+    Socks5, // 目标主机名交给代理解析
+    Http,   // HTTP CONNECT 隧道
+}
+
+/// 自适应码率：上行带宽不足 (发送队列堆积、RTT 升高、写入阻塞) 时降低视频码率，恢复后逐步提高
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AdaptiveBitrateConfig {
+    #[serde(default = "default_adaptive_bitrate_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_adaptive_min_bitrate")]
+    pub min_bitrate: u32, // kbps，码率不会降到这以下；上限为 [encoding.video] 的 bitrate
+    #[serde(default = "default_adaptive_increase_interval")]
+    pub increase_interval: u64, // 秒，网络持续正常这么久后才提高一档
+}
+
+fn default_adaptive_bitrate_enabled() -> bool {
+    true
+}
+
+fn default_adaptive_min_bitrate() -> u32 {
+    500
+}
+
+fn default_adaptive_increase_interval() -> u64 {
+    15
+}
+
+impl Default for AdaptiveBitrateConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_adaptive_bitrate_enabled(),
+            min_bitrate: default_adaptive_min_bitrate(),
+            increase_interval: default_adaptive_increase_interval(),
+        }
+    }
+}
+
+/// 推流连接断开时缓存编码后的数据，按指数退避 (带随机抖动) 重连，恢复后从缓存继续发送
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReconnectConfig {
+    #[serde(default = "default_reconnect_buffer_size")]
+    pub buffer_size: usize, // KB，缓存上限，超出时丢弃最旧的 GOP
+    #[serde(default = "default_reconnect_interval")]
+    pub reconnect_interval: u64, // milliseconds, 每次失败翻倍
+    #[serde(default = "default_max_reconnect_interval")]
+    pub max_reconnect_interval: u64, // milliseconds
+    #[serde(default = "default_reconnect_timeout")]
+    pub timeout: u64, // seconds，超过这么久仍未连上时放弃，由 [stream] 的 auto_reconnect 重新开始推流
+}
+
+fn default_reconnect_buffer_size() -> usize {
+    8192
+}
+
+fn default_reconnect_interval() -> u64 {
+    500
+}
+
+fn default_max_reconnect_interval() -> u64 {
+    8000
+}
+
+fn default_reconnect_timeout() -> u64 {
+    30
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            buffer_size: default_reconnect_buffer_size(),
+            reconnect_interval: default_reconnect_interval(),
+            max_reconnect_interval: default_max_reconnect_interval(),
+            timeout: default_reconnect_timeout(),
+        }
+    }
+}
+
+/// 断线录制：推流中断期间把编码数据写入本地 MPEG-TS 文件；开启后重连超时也不放弃，
+/// 一边录制一边按 max_reconnect_interval 继续重连，网络恢复后接着推流
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FailoverConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_failover_directory")]
+    pub directory: String, // 录制文件目录，文件名为 <stream_key>-<日期>-<时间>.ts
+}
+
+fn default_failover_directory() -> String {
+    "recordings".to_string()
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: default_failover_directory(),
+        }
+    }
+}
+
+/// UDP 推流的丢包恢复：SRT 可开启前向纠错 (libsrt 的 FEC 包过滤器)，RIST 可调整重传 (ARQ) 参数
+///
+/// 少量丢包靠 FEC 直接恢复，不需要等待重传，也就不会因超过 latency 而丢包花屏
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LossRecoveryConfig {
+    #[serde(default)]
+    pub fec: bool, // SRT FEC，接收端也需要支持 (libsrt 1.4+)
+    #[serde(default = "default_fec_columns")]
+    pub fec_columns: u32, // 每行的数据包数，每行发送一个行校验包
+    #[serde(default = "default_fec_rows")]
+    pub fec_rows: u32, // 行数，每列发送一个列校验包；1 时只有行校验。开销约为 1/columns + 1/rows
+    #[serde(default)]
+    pub fec_layout: FecLayout,
+    #[serde(default)]
+    pub fec_arq: FecArq,
+    #[serde(default)]
+    pub rist_rtt_min: Option<u32>, // 毫秒，RIST 重传请求间隔的下限，未设置时使用 librist 的默认值
+    #[serde(default)]
+    pub rist_rtt_max: Option<u32>, // 毫秒，上限
+    #[serde(default)]
+    pub rist_reorder_buffer: Option<u32>, // 毫秒，RIST 乱序包的重排缓冲
+}
+
+fn default_fec_columns() -> u32 {
+    10
+}
+
+fn default_fec_rows() -> u32 {
+    5
+}
+
+impl Default for LossRecoveryConfig {
+    fn default() -> Self {
+        Self {
+            fec: false,
+            fec_columns: default_fec_columns(),
+            fec_rows: default_fec_rows(),
+            fec_layout: FecLayout::default(),
+            fec_arq: FecArq::default(),
+            rist_rtt_min: None,
+            rist_rtt_max: None,
+            rist_reorder_buffer: None,
+        }
+    }
+}
+
+/// SRT FEC 的列分组方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum FecLayout {
+    Even,      // 列与行对齐，列校验包集中在矩阵末尾发出
+    #[default]
+    Staircase, // 列错开排列，校验包分散发出，突发更小
+}
+
+/// 开启 SRT FEC 时的重传方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum FecArq {
+    Always, // 与 FEC 同时重传
+    #[default]
+    OnReq,  // FEC 无法恢复时才重传
+    Never,  // 只靠 FEC
+}
+
+/// 推流统计：定期汇总编码帧率、丢帧、码率、发送队列、RTT 和重连次数
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StatsConfig {
+    #[serde(default = "default_stats_interval")]
+    pub interval: u64, // 秒，汇总并写入日志的间隔
+    #[serde(default)]
+    pub listen: Option<String>, // 本地 JSON 接口的监听地址，如 "127.0.0.1:9100"，未设置时不开启
+    #[serde(default)]
+    pub report_to_server: bool, // 通过 RTMP 数据消息 (onClientStats) 上报给服务器
+    #[serde(default)]
+    pub overlay: StatsOverlayConfig,
+}
+
+fn default_stats_interval() -> u64 {
+    10
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            interval: default_stats_interval(),
+            listen: None,
+            report_to_server: false,
+            overlay: StatsOverlayConfig::default(),
+        }
+    }
+}
+
+/// 画面上的统计叠加 - 把帧率、码率、丢帧和编码队列绘制到推流画面中，录像里也能看到当时的状态
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StatsOverlayConfig {
+    #[serde(default)]
+    pub enabled: bool, // 推流中可用终端命令 overlay 切换
+    #[serde(default)]
+    pub corner: OverlayCorner,
+    #[serde(default)]
+    pub font: Option<String>, // TTF/OTF 字体文件，未设置时使用系统字体
+    #[serde(default = "default_stats_overlay_font_size")]
+    pub font_size: f32,
+}
+
+fn default_stats_overlay_font_size() -> f32 {
+    20.0
+}
+
+impl Default for StatsOverlayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            corner: OverlayCorner::default(),
+            font: None,
+            font_size: default_stats_overlay_font_size(),
+        }
+    }
+}
+
+/// 叠加层所在的画面角落
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum OverlayCorner {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// 全局快捷键 - 游戏全屏时不用切出窗口即可控制推流
+///
+/// 格式如 "Ctrl+Shift+F9"，修饰键为 Ctrl、Shift、Alt、Super；设为空字符串的动作不注册快捷键
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HotkeyConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_start_stop_hotkey")]
+    pub start_stop: String, // 开始/停止推流，停止后进程保留，再次按下重新连接
+    #[serde(default = "default_pause_hotkey")]
+    pub pause: String, // 暂停/继续，暂停时显示占位画面、声音静音，连接保持
+    #[serde(default = "default_mute_hotkey")]
+    pub mute: String, // 麦克风静音/取消静音
+    #[serde(default = "default_save_replay_hotkey")]
+    pub save_replay: String, // 把回放缓存保存为 MP4，需要开启 [replay]
+    #[serde(default)]
+    pub mute_input: Option<String>, // 静音快捷键控制的混音输入，未设置时为第一个麦克风输入
+    #[serde(default)]
+    pub toggle_sources: HashMap<String, String>, // 视频源或混音输入名称 -> 开关该源的快捷键
+}
+
+fn default_start_stop_hotkey() -> String {
+    "Ctrl+Shift+F9".to_string()
+}
+
+fn default_pause_hotkey() -> String {
+    "Ctrl+Shift+F10".to_string()
+}
+
+fn default_mute_hotkey() -> String {
+    "Ctrl+Shift+F11".to_string()
+}
+
+fn default_save_replay_hotkey() -> String {
+    "Ctrl+Shift+F12".to_string()
+}
+
+impl Default for HotkeyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            start_stop: default_start_stop_hotkey(),
+            pause: default_pause_hotkey(),
+            mute: default_mute_hotkey(),
+            save_replay: default_save_replay_hotkey(),
+            mute_input: None,
+            toggle_sources: HashMap::new(),
+        }
+    }
+}
+
+/// 本地预览窗口 - 显示即将编码的画面，标题栏显示帧率和捕获到显示的延迟
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PreviewConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_preview_width")]
+    pub width: u32, // 窗口初始宽度，高度按编码分辨率的比例
+}
+
+fn default_preview_width() -> u32 {
+    960
+}
+
+impl Default for PreviewConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            width: default_preview_width(),
+        }
+    }
+}
+
+/// 回放缓存 - 在内存中保留最近的编码数据，按快捷键保存为 MP4，与是否在推流无关
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReplayConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_replay_duration")]
+    pub duration: u64, // 保留的秒数
+    #[serde(default = "default_replay_directory")]
+    pub directory: String, // 保存目录，文件名为 replay-<日期>-<时间>.mp4
+}
+
+fn default_replay_duration() -> u64 {
+    30
+}
+
+fn default_replay_directory() -> String {
+    "replays".to_string()
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            duration: default_replay_duration(),
+            directory: default_replay_directory(),
+        }
+    }
 }
 
-/// 捕获配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CaptureConfig {
-    pub video_source: VideoSource,
-    pub audio_source: AudioSource,
-    pub capture_cursor: bool,
+/// 本地控制接口 - 通过 HTTP 和 WebSocket 开始/停止推流、切换场景和显示器、调整码率和混音、获取统计，
+/// 供 Stream Deck 之类的工具和脚本使用
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ControlConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_control_listen")]
+    pub listen: String, // 接口没有认证，只应监听本机地址
 }
 
-/// 视频源配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum VideoSource {
-    Screen {
-        display_index: u32,
-    },
-    Window {
-        window_title: String,
-    },
-    Region {
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    },
+fn default_control_listen() -> String {
+    "127.0.0.1:9200".to_string()
 }
 
-/// 音频源配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum AudioSource {
-    Default,
-    Device {
-        device_name: String,
-    },
-    Disabled,
+impl Default for ControlConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: default_control_listen(),
+        }
+    }
 }
 
-/// 编码配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EncodingConfig {
-    pub video: VideoEncodingConfig,
-    pub audio: AudioEncodingConfig,
-    pub hardware_acceleration: bool,
+/// 远程输入 - 把服务器转发来的观看者键鼠事件注入系统，让观看者操作游戏
+///
+/// 只注入获得授权的观看者的事件，授权在推流端通过终端命令或控制接口授予，断线重连后保留
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RemoteInputConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_true")]
+    pub keyboard: bool,
+    #[serde(default = "default_true")]
+    pub mouse: bool,
+    #[serde(default = "default_true")]
+    pub gamepad: bool,
+    #[serde(default)]
+    pub allowed_keys: Vec<String>, // 允许的按键，为空时允许除 blocked_keys 外的所有按键
+    #[serde(default = "default_blocked_keys")]
+    pub blocked_keys: Vec<String>,
+    #[serde(default = "default_gamepad_jitter_buffer")]
+    pub gamepad_jitter_buffer_ms: u64, // 手柄快照抖动缓冲的上限，实际缓冲按测得的抖动调整，0 表示收到即输出
+    #[serde(default = "default_max_gamepads")]
+    pub max_gamepads: usize, // 同时创建的虚拟手柄上限，超出的观看者手柄被忽略
 }
 
-/// 视频编码配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct VideoEncodingConfig {
-    pub codec: VideoCodec,
-    pub width: u32,
-    pub height: u32,
-    pub fps: u32,
-    pub bitrate: u32, // kbps
-    pub keyframe_interval: u32, // seconds
-    pub preset: String, // e.g., "ultrafast", "fast", "medium", "slow"
+fn default_gamepad_jitter_buffer() -> u64 {
+    50
 }
 
-/// 音频编码配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AudioEncodingConfig {
-    pub codec: AudioCodec,
-    pub sample_rate: u32,
-    pub channels: u32,
-    pub bitrate: u32, // kbps
+fn default_max_gamepads() -> usize {
+    4
 }
 
-/// 网络配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct NetworkConfig {
-    pub connection_timeout: u64, // seconds
-    pub read_timeout: u64, // seconds
-    pub write_timeout: u64, // seconds
-    pub buffer_size: usize,
+fn default_blocked_keys() -> Vec<String> {
+    // 防止观看者切出游戏或操作系统
+    vec!["Super".to_string(), "Alt".to_string(), "Delete".to_string(), "Print".to_string()]
+}
+
+impl Default for RemoteInputConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            keyboard: true,
+            mouse: true,
+            gamepad: true,
+            allowed_keys: Vec::new(),
+            blocked_keys: default_blocked_keys(),
+            gamepad_jitter_buffer_ms: default_gamepad_jitter_buffer(),
+            max_gamepads: default_max_gamepads(),
+        }
+    }
 }
 
 /// 服务器配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ServerConfig {
+    #[serde(default)]
+    pub latency_mode: LatencyMode,
     pub rtmp: RtmpServerConfig,
     pub webrtc: WebRtcServerConfig,
     pub http: HttpServerConfig,
     pub auth: AuthConfig,
     pub storage: StorageConfig,
+    #[serde(default)]
+    pub analytics: AnalyticsConfig,
+    #[serde(default)]
+    pub webhooks: WebhookConfig,
+    #[serde(default)]
+    pub overload: OverloadConfig,
+    #[serde(default)]
+    pub relay: RelayConfig,
+    #[serde(default)]
+    pub cluster: ClusterConfig,
+    #[serde(default)]
+    pub restream: RestreamConfig,
+    #[serde(default)]
+    pub push_pool: PushPoolConfig,
+    #[serde(default)]
+    pub recording: RecordingConfig,
+    #[serde(default)]
+    pub clips: ClipConfig,
+    #[serde(default)]
+    pub thumbnails: ThumbnailConfig,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    #[serde(default)]
+    pub composite: CompositeConfig,
+}
+
+impl ServerConfig {
+    /// 带注释的默认配置 (仓库中的 server.toml)，--print-default-config 输出的内容
+    pub const TEMPLATE: &'static str = include_str!("../../server.toml");
+
+    /// 配置文件的 JSON Schema，供部署工具和编辑器在启动前校验配置
+    pub fn json_schema() -> serde_json::Value {
+        schemars::schema_for!(ServerConfig).to_value()
+    }
+
+    /// ultra_low 时 HLS 切出 1 秒的片段，播放器从最新的片段开始播放；
+    /// WebRTC 观看者落后时直接跳到最新的关键帧 (见 recv_latest_media)
+    pub fn apply_latency_mode(&mut self) {
+        if self.latency_mode != LatencyMode::UltraLow {
+            return;
+        }
+        self.storage.hls_segment_duration = ULTRA_LOW_HLS_SEGMENT_DURATION;
+        self.storage.hls_live_edge = true;
+    }
 }
 
 /// RTMP 服务器配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RtmpServerConfig {
     pub bind_addr: String,
     pub port: u16,
@@ -124,15 +1314,46 @@ pub struct RtmpServerConfig {
 }
 
 /// WebRTC 服务器配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct WebRtcServerConfig {
     pub ice_servers: Vec<IceServerConfig>,
     pub dtls_cert_path: Option<String>,
     pub dtls_key_path: Option<String>,
+    #[serde(default)]
+    pub remote_input: RemoteInputRelayConfig,
+}
+
+/// 远程输入转发 - 观看者通过 "input" 数据通道发送键鼠和手柄事件，服务器转发给 RTMP 推流端
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RemoteInputRelayConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_remote_input_rate")]
+    pub max_events_per_second: u32, // 每个观看者每秒最多转发的事件，超出的丢弃
+    #[serde(default = "default_gamepad_tick_rate")]
+    pub gamepad_tick_rate: u32, // 每个手柄每秒最多转发的状态快照 ("gamepad" 数据通道)，超出的丢弃
+}
+
+fn default_remote_input_rate() -> u32 {
+    250
+}
+
+fn default_gamepad_tick_rate() -> u32 {
+    120
+}
+
+impl Default for RemoteInputRelayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_events_per_second: default_remote_input_rate(),
+            gamepad_tick_rate: default_gamepad_tick_rate(),
+        }
+    }
 }
 
 /// ICE 服务器配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct IceServerConfig {
     pub urls: Vec<String>,
     pub username: Option<String>,
@@ -140,41 +1361,600 @@ pub struct IceServerConfig {
 }
 
 /// HTTP 服务器配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct HttpServerConfig {
     pub bind_addr: String,
     pub port: u16,
     pub static_dir: String,
     pub cors_enabled: bool,
+    #[serde(default = "default_true")]
+    pub access_log: bool,
 }
 
 /// 认证配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct AuthConfig {
     pub enabled: bool,
     pub valid_stream_keys: Vec<String>,
     pub jwt_secret: Option<String>,
+    #[serde(default)]
+    pub admin_token: Option<String>, // 管理接口 (观看权限、邀请和 /api/admin/*) 的 Bearer 令牌，未设置时管理接口不可用
+    #[serde(default)]
+    pub streams: HashMap<String, StreamAccessConfig>, // 按流密钥的观看权限，也可通过 /api/streams/:key/access 修改
+}
+
+/// 流的可见性
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamVisibility {
+    /// 出现在流列表中，任何人都可以观看
+    #[default]
+    Public,
+    /// 不出现在流列表中，知道地址即可观看
+    Unlisted,
+    /// 不出现在流列表中，观看需要密码或邀请
+    Private,
+}
+
+/// 单个流的观看权限
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct StreamAccessConfig {
+    #[serde(default)]
+    pub visibility: StreamVisibility,
+    pub password: Option<String>, // private 时的观看密码，不设置时只能通过邀请观看
 }
 
 /// 存储配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct StorageConfig {
     pub hls_segment_dir: String,
     pub hls_segment_duration: u32, // seconds
     pub hls_playlist_length: u32, // number of segments
+    #[serde(default)]
+    pub hls_dvr_window: u32, // seconds, 0 表示不启用时移
     pub dash_segment_dir: String,
     pub dash_segment_duration: u32, // seconds
+    #[serde(default)]
+    pub hls_recovery: HlsRecoveryPolicy,
+    #[serde(default)]
+    pub hls_live_edge: bool, // 播放器从最新的片段开始播放，不先缓冲三个片段
+    #[serde(default)]
+    pub s3: S3StorageConfig,
+}
+
+/// S3 兼容对象存储配置 - 启用后 HLS 片段和播放列表写入共享存储
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct S3StorageConfig {
+    pub enabled: bool,
+    pub bucket: String,
+    pub region: String,
+    pub endpoint: Option<String>, // 自定义端点 (MinIO 等)，为空时使用 AWS
+    pub access_key_id: Option<String>, // 为空时从环境变量读取
+    pub secret_access_key: Option<String>,
+    pub prefix: String, // 对象键前缀
+    pub public_base_url: Option<String>, // CDN 地址，播放列表中的片段指向该地址
+    pub multipart_part_size: usize, // bytes
+}
+
+impl Default for S3StorageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bucket: "game-stream".to_string(),
+            region: "us-east-1".to_string(),
+            endpoint: None,
+            access_key_id: None,
+            secret_access_key: None,
+            prefix: "hls".to_string(),
+            public_base_url: None,
+            multipart_part_size: 5 * 1024 * 1024,
+        }
+    }
+}
+
+/// 重启后 HLS 状态恢复策略
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum HlsRecoveryPolicy {
+    /// 恢复播放列表并在推流恢复后延续片段序号
+    #[default]
+    Resume,
+    /// 恢复播放列表并标记为已结束的 EVENT 播放列表，推流恢复后继续追加
+    Event,
+    /// 删除上次运行遗留的播放列表和片段
+    Cleanup,
+}
+
+/// 观看分析配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AnalyticsConfig {
+    pub enabled: bool,
+    pub max_sessions_per_stream: usize, // 每个流保留的历史会话数量
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_sessions_per_stream: 10000,
+        }
+    }
+}
+
+/// Webhook 配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookConfig {
+    pub enabled: bool,
+    #[serde(default)]
+    pub endpoints: Vec<WebhookEndpointConfig>,
+    pub viewer_milestones: Vec<u32>, // 观看人数里程碑
+    pub max_retries: u32,
+    pub retry_interval: u64, // milliseconds, 每次重试翻倍
+    pub timeout: u64, // seconds
+}
+
+/// Webhook 端点配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookEndpointConfig {
+    pub url: String,
+    pub secret: Option<String>, // 用于 HMAC-SHA256 签名
+    #[serde(default)]
+    pub events: Vec<String>, // 为空表示订阅所有事件
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoints: Vec::new(),
+            viewer_milestones: vec![10, 100, 1000],
+            max_retries: 3,
+            retry_interval: 1000,
+            timeout: 10,
+        }
+    }
+}
+
+/// 过载保护配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct OverloadConfig {
+    pub enabled: bool,
+    pub max_cpu_percent: f32,
+    pub max_memory_percent: f32,
+    pub max_streams: u32,
+    pub max_viewers: u32,
+    pub sample_interval: u64, // seconds
+    pub retry_after: u64, // seconds, 返回给被拒绝观看者的 Retry-After
+}
+
+impl Default for OverloadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_cpu_percent: 90.0,
+            max_memory_percent: 90.0,
+            max_streams: 100,
+            max_viewers: 1000,
+            sample_interval: 5,
+            retry_after: 30,
+        }
+    }
+}
+
+/// 源站-边缘中继配置 (边缘节点按需从源站拉流)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RelayConfig {
+    pub enabled: bool,
+    pub origin_url: String, // 例如 rtmp://origin.example.com:1935/live 或 relay://origin.example.com:1940
+    pub connect_timeout: u64, // seconds
+    pub idle_timeout: u64, // seconds, 无观看者超过该时间后停止拉流
+    pub listen: bool, // 是否接受其他实例的内部中继连接
+    pub bind_addr: String,
+    pub port: u16,
+    pub secret: Option<String>, // 内部中继协议的共享密钥
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            origin_url: "rtmp://127.0.0.1:1935/live".to_string(),
+            connect_timeout: 5,
+            idle_timeout: 30,
+            listen: false,
+            bind_addr: "0.0.0.0".to_string(),
+            port: 1940,
+            secret: None,
+        }
+    }
+}
+
+/// 集群流目录配置 (基于 Redis 跨实例共享流信息)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ClusterConfig {
+    pub enabled: bool,
+    pub redis_url: String,
+    pub key_prefix: String,
+    pub instance_id: Option<String>, // 为空时自动生成
+    pub advertise_url: String, // 其他实例拉取本实例流的地址，例如 rtmp://10.0.0.1:1935/live
+    pub public_url: String, // 观看者访问本实例的 HTTP 地址，用于边缘选择
+    pub accept_viewers: bool, // 是否参与观看者边缘选择 (纯源站可关闭)
+    pub heartbeat_interval: u64, // seconds
+    pub entry_ttl: u64, // seconds, 实例失联后目录条目过期时间
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            redis_url: "redis://127.0.0.1:6379".to_string(),
+            key_prefix: "game-stream".to_string(),
+            instance_id: None,
+            advertise_url: "rtmp://127.0.0.1:1935/live".to_string(),
+            public_url: "http://127.0.0.1:8080".to_string(),
+            accept_viewers: true,
+            heartbeat_interval: 5,
+            entry_ttl: 15,
+        }
+    }
+}
+
+/// 转推配置 - 将直播流转推到一个或多个下游 RTMP/SRT 目标
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RestreamConfig {
+    pub enabled: bool,
+    pub queue_size: usize, // 每个目标的发送队列长度 (数据包)，满时丢弃新数据包
+    pub connect_timeout: u64, // seconds
+    pub reconnect_interval: u64, // milliseconds, 每次失败翻倍
+    pub max_reconnect_interval: u64, // milliseconds
+    #[serde(default)]
+    pub targets: Vec<RestreamTargetConfig>,
+}
+
+/// 转推目标配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RestreamTargetConfig {
+    pub name: String,
+    pub stream_key: String, // 要转推的本地流
+    pub url: String, // rtmp://host/app/key 或 srt://host:port
+}
+
+impl Default for RestreamConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            queue_size: 1024,
+            connect_timeout: 5,
+            reconnect_interval: 1000,
+            max_reconnect_interval: 30000,
+            targets: Vec::new(),
+        }
+    }
+}
+
+/// 向下游推流的连接池配置 (中继、转推共用)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PushPoolConfig {
+    pub max_connections_per_host: usize, // 同一下游主机的最大连接数 (含正在建立和空闲的连接)
+    pub max_idle_per_host: usize, // 每个主机保留的空闲连接数
+    pub idle_timeout: u64, // seconds, 空闲连接超过该时间后关闭
+    pub batch_max_packets: usize, // 单次批量写入的最大数据包数
+    pub batch_max_bytes: usize, // 单次批量写入的最大字节数
+}
+
+impl Default for PushPoolConfig {
+    fn default() -> Self {
+        Self {
+            max_connections_per_host: 64,
+            max_idle_per_host: 8,
+            idle_timeout: 60,
+            batch_max_packets: 64,
+            batch_max_bytes: 256 * 1024,
+        }
+    }
+}
+
+/// 服务端录制配置 - 将每次直播录制为文件
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RecordingConfig {
+    pub enabled: bool, // 全局默认是否录制
+    pub dir: String,
+    pub filename_template: String, // 支持 {key} {date} {time} {seq} {ext}
+    pub format: RecordingFormat,
+    pub ffmpeg_path: String, // 封装 MP4 时使用
+    #[serde(default)]
+    pub overrides: HashMap<String, bool>, // 按流密钥覆盖全局默认
+    #[serde(default)]
+    pub rules: Vec<RecordingRule>, // 按顺序匹配，优先级低于按流密钥的覆盖
+    #[serde(default)]
+    pub tags: HashMap<String, Vec<String>>, // 流密钥 -> 标签，供规则匹配
+    #[serde(default)]
+    pub upload: RecordingUploadConfig,
+}
+
+/// 录制规则集 - 可通过管理 API 整体替换
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct RecordingRuleset {
+    #[serde(default)]
+    pub rules: Vec<RecordingRule>,
+    #[serde(default)]
+    pub tags: HashMap<String, Vec<String>>,
+}
+
+/// 录制规则 - 所有已设置的条件都满足时规则匹配
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RecordingRule {
+    #[serde(default)]
+    pub name: String,
+    pub action: RecordingRuleAction,
+    #[serde(default)]
+    pub key_prefix: Option<String>,
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub schedule: Option<RecordingSchedule>,
+}
+
+/// 规则匹配后的动作
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum RecordingRuleAction {
+    Record,
+    Skip,
+}
+
+/// 每日生效时段 ("HH:MM"，服务器本地时间)，结束早于开始时跨越午夜
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RecordingSchedule {
+    pub start: String,
+    pub end: String,
+}
+
+/// 录制文件格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum RecordingFormat {
+    /// 直接写入 RTMP 消息，崩溃时已写入的部分仍可播放
+    #[default]
+    Flv,
+    /// 先录制为 FLV，结束后用 FFmpeg 无损转封装为 MP4
+    Mp4,
+    /// 通过 FFmpeg 实时封装为分片 MP4，崩溃时已写入的分片仍可播放
+    Fmp4,
+    /// 通过 FFmpeg 实时封装为 MKV，崩溃时已写入的部分仍可播放
+    Mkv,
+}
+
+impl RecordingFormat {
+    /// 文件扩展名
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RecordingFormat::Flv => "flv",
+            RecordingFormat::Mp4 | RecordingFormat::Fmp4 => "mp4",
+            RecordingFormat::Mkv => "mkv",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            RecordingFormat::Flv => "video/x-flv",
+            RecordingFormat::Mp4 | RecordingFormat::Fmp4 => "video/mp4",
+            RecordingFormat::Mkv => "video/x-matroska",
+        }
+    }
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: "./recordings".to_string(),
+            filename_template: "{key}/{date}_{seq}.{ext}".to_string(),
+            format: RecordingFormat::default(),
+            ffmpeg_path: "ffmpeg".to_string(),
+            overrides: HashMap::new(),
+            rules: Vec::new(),
+            tags: HashMap::new(),
+            upload: RecordingUploadConfig::default(),
+        }
+    }
+}
+
+/// 录制上传配置 - 录制完成后上传到对象存储并通过 Webhook 通知
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RecordingUploadConfig {
+    pub enabled: bool,
+    pub provider: ObjectStoreProvider,
+    pub bucket: String,
+    pub region: String, // 仅 S3
+    pub endpoint: Option<String>, // 自定义 S3 端点 (MinIO 等)
+    pub access_key_id: Option<String>, // 为空时从环境变量读取
+    pub secret_access_key: Option<String>,
+    pub service_account_path: Option<String>, // GCS 服务账号密钥文件，为空时从环境变量读取
+    pub prefix: String, // 对象键前缀
+    pub public_base_url: Option<String>, // Webhook 中的对象地址使用该前缀
+    pub multipart_part_size: usize, // bytes
+    pub max_retries: u32,
+    pub retry_interval: u64, // milliseconds, 每次重试翻倍
+    pub delete_local: bool, // 上传成功后删除本地文件
+}
+
+/// 对象存储服务
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum ObjectStoreProvider {
+    #[default]
+    S3,
+    Gcs,
+}
+
+impl Default for RecordingUploadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            provider: ObjectStoreProvider::default(),
+            bucket: "game-stream".to_string(),
+            region: "us-east-1".to_string(),
+            endpoint: None,
+            access_key_id: None,
+            secret_access_key: None,
+            service_account_path: None,
+            prefix: "recordings".to_string(),
+            public_base_url: None,
+            multipart_part_size: 8 * 1024 * 1024,
+            max_retries: 5,
+            retry_interval: 2000,
+            delete_local: false,
+        }
+    }
+}
+
+/// 即时剪辑配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ClipConfig {
+    pub enabled: bool,
+    pub dir: String,
+    pub buffer_duration: u64,  // 每路流保留的最近内容 (秒)
+    pub default_duration: u64, // 未指定时的剪辑时长 (秒)
+    pub ffmpeg_path: String,
+}
+
+impl Default for ClipConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: "./clips".to_string(),
+            buffer_duration: 60,
+            default_duration: 30,
+            ffmpeg_path: "ffmpeg".to_string(),
+        }
+    }
+}
+
+/// 多路合成配置 - 将两路直播合成为一路新的流
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CompositeConfig {
+    pub enabled: bool,
+    pub width: u32,            // 输出分辨率
+    pub height: u32,
+    pub fps: u32,
+    pub video_bitrate: u32,    // kbps
+    pub audio_bitrate: u32,    // kbps
+    pub max_composites: usize, // 同时进行的合成数，每路合成占用一个 FFmpeg 编码进程
+    pub ffmpeg_path: String,
+}
+
+impl Default for CompositeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            width: 1280,
+            height: 720,
+            fps: 30,
+            video_bitrate: 3000,
+            audio_bitrate: 128,
+            max_composites: 2,
+            ffmpeg_path: "ffmpeg".to_string(),
+        }
+    }
+}
+
+/// 缩略图配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ThumbnailConfig {
+    pub enabled: bool,
+    pub interval: u64, // 生成间隔 (秒)
+    pub width: u32,    // 输出宽度，高度按比例缩放
+    pub format: ThumbnailFormat,
+    pub dir: String,   // 保存每路流最新的缩略图，流结束后仍可访问
+    pub ffmpeg_path: String,
+}
+
+/// 缩略图格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum ThumbnailFormat {
+    #[default]
+    Jpeg,
+    WebP,
+}
+
+impl ThumbnailFormat {
+    /// 文件扩展名
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ThumbnailFormat::Jpeg => "jpg",
+            ThumbnailFormat::WebP => "webp",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ThumbnailFormat::Jpeg => "image/jpeg",
+            ThumbnailFormat::WebP => "image/webp",
+        }
+    }
+}
+
+impl Default for ThumbnailConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: 10,
+            width: 320,
+            format: ThumbnailFormat::default(),
+            dir: "./thumbnails".to_string(),
+            ffmpeg_path: "ffmpeg".to_string(),
+        }
+    }
+}
+
+/// 磁盘数据保留策略 - 后台定期清理 HLS 片段、缩略图、录制和剪辑
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RetentionConfig {
+    pub enabled: bool,
+    pub interval: u64, // 清理间隔 (秒)
+    #[serde(default)]
+    pub hls: RetentionPolicy,
+    #[serde(default)]
+    pub thumbnails: RetentionPolicy,
+    #[serde(default)]
+    pub recordings: RetentionPolicy,
+    #[serde(default)]
+    pub clips: RetentionPolicy,
+}
+
+/// 单个目录的保留策略，未设置的限制不生效
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct RetentionPolicy {
+    pub max_age: Option<u64>, // seconds
+    pub max_total_bytes: Option<u64>, // 超出时从最旧的文件开始删除
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 300,
+            hls: RetentionPolicy::default(),
+            thumbnails: RetentionPolicy::default(),
+            recordings: RetentionPolicy::default(),
+            clips: RetentionPolicy::default(),
+        }
+    }
 }
 
 impl Default for ClientConfig {
     fn default() -> Self {
         Self {
+            latency_mode: LatencyMode::default(),
             server: ServerEndpoint {
                 protocol: StreamProtocol::Rtmp,
                 host: "localhost".to_string(),
                 port: 1935,
                 stream_key: "test_stream".to_string(),
                 app_name: Some("live".to_string()),
+                latency: default_srt_latency(),
+                passphrase: None,
+                tls: false,
+                tls_verify: true,
+                tls_ca_file: None,
+                tls_server_name: None,
             },
             stream: StreamConfig {
                 title: None,
@@ -182,36 +1962,73 @@ impl Default for ClientConfig {
                 auto_reconnect: true,
                 reconnect_interval: 5,
                 max_reconnect_attempts: 10,
+                pause: PauseConfig::default(),
+                schedule: ScheduleConfig::default(),
             },
             capture: CaptureConfig {
                 video_source: VideoSource::Screen { display_index: 0 },
                 audio_source: AudioSource::Default,
                 capture_cursor: true,
+                portal: PortalConfig::default(),
+                window: WindowCaptureConfig::default(),
+                cursor: CursorConfig::default(),
+                game: GameCaptureConfig::default(),
+                audio_mixer: AudioMixerConfig::default(),
+                audio_filters: Vec::new(),
+                zero_copy: ZeroCopyConfig::default(),
             },
             encoding: EncodingConfig {
                 video: VideoEncodingConfig {
                     codec: VideoCodec::H264,
                     width: 1920,
                     height: 1080,
-                    fps: 30,
+                    fps: 30.0,
                     bitrate: 2500,
                     keyframe_interval: 2,
                     preset: "fast".to_string(),
+                    hdr: HdrConfig::default(),
+                    static_frames: StaticFrameConfig::default(),
+                    rate_control: RateControl::Cbr,
+                    max_bitrate: None,
+                    buffer_size: None,
+                    qp: default_video_qp(),
+                    profile: None,
+                    level: None,
+                    b_frames: 0,
+                    low_latency: true,
+                    encoder: None,
                 },
                 audio: AudioEncodingConfig {
                     codec: AudioCodec::Aac,
                     sample_rate: 44100,
                     channels: 2,
                     bitrate: 128,
+                    vbr: true,
+                    encoder: None,
                 },
                 hardware_acceleration: true,
+                vaapi_device: None,
+                max_queued_frames: default_max_queued_frames(),
             },
             network: NetworkConfig {
                 connection_timeout: 10,
                 read_timeout: 30,
                 write_timeout: 30,
                 buffer_size: 65536,
+                pacing: true,
+                adaptive_bitrate: AdaptiveBitrateConfig::default(),
+                reconnect: ReconnectConfig::default(),
+                loss_recovery: LossRecoveryConfig::default(),
+                failover: FailoverConfig::default(),
+                proxy: None,
             },
+            stats: StatsConfig::default(),
+            scenes: Vec::new(),
+            hotkeys: HotkeyConfig::default(),
+            preview: PreviewConfig::default(),
+            replay: ReplayConfig::default(),
+            control: ControlConfig::default(),
+            remote_input: RemoteInputConfig::default(),
         }
     }
 }
@@ -219,6 +2036,7 @@ impl Default for ClientConfig {
 impl Default for ServerConfig {
     fn default() -> Self {
         Self {
+            latency_mode: LatencyMode::default(),
             rtmp: RtmpServerConfig {
                 bind_addr: "0.0.0.0".to_string(),
                 port: 1935,
@@ -235,25 +2053,49 @@ impl Default for ServerConfig {
                 ],
                 dtls_cert_path: None,
                 dtls_key_path: None,
+                remote_input: RemoteInputRelayConfig::default(),
             },
             http: HttpServerConfig {
                 bind_addr: "0.0.0.0".to_string(),
                 port: 8080,
                 static_dir: "./web".to_string(),
                 cors_enabled: true,
+                access_log: true,
             },
             auth: AuthConfig {
                 enabled: false,
                 valid_stream_keys: vec!["test_stream".to_string()],
                 jwt_secret: None,
+                admin_token: None,
+                streams: HashMap::new(),
             },
             storage: StorageConfig {
                 hls_segment_dir: "./hls".to_string(),
                 hls_segment_duration: 6,
                 hls_playlist_length: 10,
+                hls_dvr_window: 0,
                 dash_segment_dir: "./dash".to_string(),
                 dash_segment_duration: 6,
+                hls_recovery: HlsRecoveryPolicy::default(),
+                hls_live_edge: false,
+                s3: S3StorageConfig::default(),
             },
+            analytics: AnalyticsConfig::default(),
+            webhooks: WebhookConfig::default(),
+            overload: OverloadConfig::default(),
+            relay: RelayConfig::default(),
+            cluster: ClusterConfig::default(),
+            restream: RestreamConfig::default(),
+            push_pool: PushPoolConfig::default(),
+            recording: RecordingConfig::default(),
+            clips: ClipConfig::default(),
+            thumbnails: ThumbnailConfig::default(),
+            retention: RetentionConfig::default(),
+            composite: CompositeConfig::default(),
         }
     }
 }
+
+fn default_true() -> bool {
+    true
+}
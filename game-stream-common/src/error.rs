@@ -43,6 +43,9 @@ pub enum StreamError {
     #[error("Timeout")]
     Timeout,
     
+    #[error("Server overloaded: {0}")]
+    Overloaded(String),
+    
     #[error("Internal error: {0}")]
     Internal(String),
 }
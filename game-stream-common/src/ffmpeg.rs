@@ -0,0 +1,139 @@
+use std::io::{Read, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::{mpsc, Mutex};
+use tracing::{debug, warn};
+
+use crate::{StreamResult, StreamError};
+
+/// 在 PATH 中查找的 ffmpeg 可执行文件
+const FFMPEG: &str = "ffmpeg";
+
+/// 查询 ffmpeg 是否带有某个编码器 (如 libx264)
+pub fn has_encoder(name: &str) -> bool {
+    let output = Command::new(FFMPEG)
+        .args(["-hide_banner", "-encoders"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output();
+
+    output.is_ok_and(|output| {
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .any(|line| line.split_whitespace().nth(1) == Some(name))
+    })
+}
+
+/// 运行一次 ffmpeg，返回是否成功 (用于检测编码器和硬件是否可用)
+pub fn run(args: &[&str]) -> bool {
+    Command::new(FFMPEG)
+        .args(["-hide_banner", "-nostdin", "-loglevel", "error"])
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// 通过标准输入输出驱动的 ffmpeg 编码进程
+///
+/// 原始数据写入 stdin，编码结果由后台线程从 stdout 读取，避免双方互相阻塞
+pub struct FfmpegProcess {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    output: Mutex<mpsc::Receiver<Vec<u8>>>, // 编码器需要 Sync
+}
+
+impl FfmpegProcess {
+    pub fn spawn(args: &[String]) -> StreamResult<Self> {
+        debug!("Starting {} {}", FFMPEG, args.join(" "));
+
+        let mut child = Command::new(FFMPEG)
+            .args(["-hide_banner", "-nostdin", "-loglevel", "error"])
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| StreamError::Codec(format!("Failed to start ffmpeg (is it installed?): {}", e)))?;
+
+        let mut stdout = child.stdout.take()
+            .ok_or_else(|| StreamError::Codec("ffmpeg has no output".to_string()))?;
+        let (sender, output) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("ffmpeg-output".to_string())
+            .spawn(move || {
+                let mut buffer = vec![0u8; 64 * 1024];
+                loop {
+                    match stdout.read(&mut buffer) {
+                        Ok(0) | Err(_) => break,
+                        Ok(read) => {
+                            if sender.send(buffer[..read].to_vec()).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }
+            })
+            .map_err(|e| StreamError::Codec(format!("Failed to start ffmpeg reader: {}", e)))?;
+
+        if let Some(stderr) = child.stderr.take() {
+            let _ = std::thread::Builder::new()
+                .name("ffmpeg-log".to_string())
+                .spawn(move || {
+                    let mut stderr = std::io::BufReader::new(stderr);
+                    let mut line = String::new();
+                    while matches!(std::io::BufRead::read_line(&mut stderr, &mut line), Ok(read) if read > 0) {
+                        warn!("ffmpeg: {}", line.trim_end());
+                        line.clear();
+                    }
+                });
+        }
+
+        let stdin = child.stdin.take();
+        Ok(Self { child, stdin, output: Mutex::new(output) })
+    }
+
+    /// 写入一帧原始数据
+    pub fn write(&mut self, data: &[u8]) -> StreamResult<()> {
+        let stdin = self.stdin.as_mut()
+            .ok_or_else(|| StreamError::Codec("ffmpeg input is already closed".to_string()))?;
+        stdin.write_all(data)
+            .map_err(|e| StreamError::Codec(format!("ffmpeg stopped accepting input: {}", e)))
+    }
+
+    /// 取出目前已经输出的数据，不等待
+    pub fn read_available(&mut self) -> Vec<u8> {
+        let output = self.output.get_mut().unwrap_or_else(|e| e.into_inner());
+        let mut data = Vec::new();
+        while let Ok(chunk) = output.try_recv() {
+            data.extend_from_slice(&chunk);
+        }
+        data
+    }
+
+    /// 关闭输入，等待 ffmpeg 输出剩余的数据后退出
+    pub fn finish(&mut self) -> StreamResult<Vec<u8>> {
+        drop(self.stdin.take());
+
+        let output = self.output.get_mut().unwrap_or_else(|e| e.into_inner());
+        let mut data = Vec::new();
+        while let Ok(chunk) = output.recv() {
+            data.extend_from_slice(&chunk);
+        }
+        let status = self.child.wait()
+            .map_err(|e| StreamError::Codec(format!("Failed to wait for ffmpeg: {}", e)))?;
+        if !status.success() {
+            warn!("ffmpeg exited with {}", status);
+        }
+        Ok(data)
+    }
+}
+
+impl Drop for FfmpegProcess {
+    fn drop(&mut self) {
+        drop(self.stdin.take());
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
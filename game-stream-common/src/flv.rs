@@ -0,0 +1,68 @@
+use bytes::Bytes;
+
+/// FLV 视频标签的帧类型
+const FRAME_TYPE_KEY: u8 = 1;
+const FRAME_TYPE_INTER: u8 = 2;
+
+/// FLV 视频标签的编码 ID：AVC
+const CODEC_AVC: u8 = 7;
+
+/// AVC 和 AAC 的包类型：序列头或编码数据
+const AVC_SEQUENCE_HEADER: u8 = 0;
+const AVC_NALU: u8 = 1;
+const AAC_SEQUENCE_HEADER: u8 = 0;
+const AAC_RAW: u8 = 1;
+
+/// FLV 音频标签头：AAC (10)、44kHz、16 位、立体声，AAC 的实际参数以 AudioSpecificConfig 为准
+const AAC_SOUND_HEADER: u8 = 0xaf;
+
+/// Enhanced RTMP 扩展头标志，置位时低 4 位为包类型，后跟 FourCC
+const EX_HEADER: u8 = 0x80;
+
+/// Enhanced RTMP 视频包类型
+const PACKET_TYPE_SEQUENCE_START: u8 = 0;
+const PACKET_TYPE_CODED_FRAMES: u8 = 1;
+const PACKET_TYPE_CODED_FRAMES_X: u8 = 3;
+
+/// Enhanced RTMP 中 AV1 的 FourCC
+pub const FOURCC_AV1: [u8; 4] = *b"av01";
+
+/// Enhanced RTMP 中 HEVC 的 FourCC
+pub const FOURCC_HEVC: [u8; 4] = *b"hvc1";
+
+/// AVC sequence header：解码器配置记录 (avcC)，在第一帧和 SPS/PPS 变化时发送
+pub fn avc_sequence_header(config: &[u8]) -> Bytes {
+    // 帧类型和编码 ID 之后是包类型和 3 字节 composition time
+    [&[(FRAME_TYPE_KEY << 4) | CODEC_AVC, AVC_SEQUENCE_HEADER, 0, 0, 0][..], config].concat().into()
+}
+
+/// AVC 编码帧 (长度前缀格式的 NAL 单元)，没有 B 帧，composition time 为 0
+pub fn avc_video_frame(is_keyframe: bool, data: &[u8]) -> Bytes {
+    let frame_type = if is_keyframe { FRAME_TYPE_KEY } else { FRAME_TYPE_INTER };
+    [&[(frame_type << 4) | CODEC_AVC, AVC_NALU, 0, 0, 0][..], data].concat().into()
+}
+
+/// AAC sequence header：AudioSpecificConfig，在第一帧和配置变化时发送
+pub fn aac_sequence_header(config: &[u8]) -> Bytes {
+    [&[AAC_SOUND_HEADER, AAC_SEQUENCE_HEADER][..], config].concat().into()
+}
+
+/// AAC 原始帧，作为 RTMP 音频消息的负载 (即 FLV 音频标签体)
+pub fn aac_audio_frame(data: &[u8]) -> Bytes {
+    [&[AAC_SOUND_HEADER, AAC_RAW][..], data].concat().into()
+}
+
+/// Enhanced RTMP 的序列头：解码器配置记录 (AV1 为 av1C，HEVC 为 hvcC)，在第一帧和配置变化时发送
+pub fn enhanced_video_sequence_start(fourcc: [u8; 4], config: &[u8]) -> Bytes {
+    let header = EX_HEADER | (FRAME_TYPE_KEY << 4) | PACKET_TYPE_SEQUENCE_START;
+    [&[header][..], &fourcc, config].concat().into()
+}
+
+/// Enhanced RTMP 的编码帧，作为 RTMP 视频消息的负载 (即 FLV 视频标签体)
+pub fn enhanced_video_frame(fourcc: [u8; 4], is_keyframe: bool, data: &[u8]) -> Bytes {
+    let frame_type = if is_keyframe { FRAME_TYPE_KEY } else { FRAME_TYPE_INTER };
+    // HEVC 的 CodedFrames 带 3 字节 composition time，没有 B 帧时用 CodedFramesX 省掉
+    let packet_type = if fourcc == FOURCC_HEVC { PACKET_TYPE_CODED_FRAMES_X } else { PACKET_TYPE_CODED_FRAMES };
+    let header = EX_HEADER | (frame_type << 4) | packet_type;
+    [&[header][..], &fourcc, data].concat().into()
+}
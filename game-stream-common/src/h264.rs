@@ -0,0 +1,174 @@
+use bytes::Bytes;
+
+/// NAL 单元类型
+const NAL_SLICE: u8 = 1;
+const NAL_IDR_SLICE: u8 = 5;
+const NAL_SEI: u8 = 6;
+const NAL_SPS: u8 = 7;
+const NAL_PPS: u8 = 8;
+const NAL_AUD: u8 = 9;
+
+/// H.265 NAL 单元类型
+const HEVC_NAL_IRAP: std::ops::RangeInclusive<u8> = 16..=21; // BLA、IDR、CRA
+const HEVC_NAL_VPS: u8 = 32;
+const HEVC_NAL_SPS: u8 = 33;
+const HEVC_NAL_PPS: u8 = 34;
+const HEVC_NAL_AUD: u8 = 35;
+const HEVC_NAL_PREFIX_SEI: u8 = 39;
+
+/// 一个访问单元 (一帧)，Annex-B 格式，关键帧前带有 SPS/PPS
+pub struct AccessUnit {
+    pub data: Bytes,
+    pub is_keyframe: bool,
+}
+
+/// 把 Annex-B 字节流切分为访问单元
+///
+/// 按 H.264 7.4.1.2.3 的规则判断新访问单元的开始：AUD/SPS/PPS/SEI，
+/// 或 first_mb_in_slice 为 0 的片；H.265 (7.4.2.4.4) 规则相同，只是 NAL 头部和类型不同。
+/// 需要看到下一帧的开头才能确定当前帧结束
+#[derive(Default)]
+pub struct AccessUnitParser {
+    hevc: bool,
+    buffer: Vec<u8>,  // 尚未切分出完整 NAL 的数据
+    current: Vec<u8>, // 正在组装的访问单元
+    has_slice: bool,
+    is_keyframe: bool,
+}
+
+impl AccessUnitParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// H.265 字节流的解析器
+    pub fn hevc() -> Self {
+        Self { hevc: true, ..Self::default() }
+    }
+
+    /// 追加字节流，返回已经完整的访问单元
+    pub fn push(&mut self, data: &[u8]) -> Vec<AccessUnit> {
+        self.buffer.extend_from_slice(data);
+
+        let starts = start_codes(&self.buffer);
+        let mut units = Vec::new();
+        for pair in starts.windows(2) {
+            let nal = self.buffer[pair[0]..pair[1]].to_vec();
+            if let Some(unit) = self.push_nal(&nal) {
+                units.push(unit);
+            }
+        }
+        // 保留最后一个 (可能不完整的) NAL
+        if let Some(&last) = starts.last() {
+            self.buffer.drain(..last);
+        }
+        units
+    }
+
+    /// 流结束，返回剩余的访问单元
+    pub fn finish(&mut self) -> Vec<AccessUnit> {
+        let rest = std::mem::take(&mut self.buffer);
+        let mut units = Vec::new();
+        if start_codes(&rest).first() == Some(&0) {
+            units.extend(self.push_nal(&rest));
+        }
+        units.extend(self.take_unit());
+        units
+    }
+
+    fn push_nal(&mut self, nal: &[u8]) -> Option<AccessUnit> {
+        let header = nal.iter().position(|&byte| byte == 1).map(|index| index + 1)?;
+        let (is_slice, first_slice, starts_unit, is_keyframe) = if self.hevc {
+            let nal_type = (nal.get(header)? >> 1) & 0x3f;
+            let is_slice = nal_type <= 21;
+            // first_slice_segment_in_pic_flag 是片头的第一个比特
+            let first_slice = is_slice && nal.get(header + 2).is_some_and(|byte| byte & 0x80 != 0);
+            let starts_unit = matches!(nal_type,
+                HEVC_NAL_VPS | HEVC_NAL_SPS | HEVC_NAL_PPS | HEVC_NAL_AUD | HEVC_NAL_PREFIX_SEI | 41..=44 | 48..=55);
+            (is_slice, first_slice, starts_unit, HEVC_NAL_IRAP.contains(&nal_type))
+        } else {
+            let nal_type = nal.get(header)? & 0x1f;
+            let is_slice = matches!(nal_type, NAL_SLICE | NAL_IDR_SLICE);
+            // first_mb_in_slice 为 ue(v)，值为 0 时编码为单个 1 比特
+            let first_slice = is_slice && nal.get(header + 1).is_some_and(|byte| byte & 0x80 != 0);
+            let starts_unit = matches!(nal_type, NAL_AUD | NAL_SPS | NAL_PPS | NAL_SEI | 14..=18);
+            (is_slice, first_slice, starts_unit, nal_type == NAL_IDR_SLICE)
+        };
+
+        let unit = if (starts_unit || first_slice) && self.has_slice { self.take_unit() } else { None };
+        self.current.extend_from_slice(nal);
+        self.has_slice |= is_slice;
+        self.is_keyframe |= is_keyframe;
+        unit
+    }
+
+    fn take_unit(&mut self) -> Option<AccessUnit> {
+        if !self.has_slice {
+            return None;
+        }
+        let unit = AccessUnit {
+            data: Bytes::from(std::mem::take(&mut self.current)),
+            is_keyframe: self.is_keyframe,
+        };
+        self.has_slice = false;
+        self.is_keyframe = false;
+        Some(unit)
+    }
+}
+
+/// 把 Annex-B 访问单元转换为 4 字节长度前缀的格式 (avcC 中 lengthSizeMinusOne 为 3)，去掉 AUD
+pub fn to_length_prefixed(access_unit: &[u8]) -> Bytes {
+    let mut data = Vec::with_capacity(access_unit.len() + 16);
+    for nal in nal_units(access_unit).filter(|nal| nal.first().map_or(0, |byte| byte & 0x1f) != NAL_AUD) {
+        data.extend_from_slice(&(nal.len() as u32).to_be_bytes());
+        data.extend_from_slice(nal);
+    }
+    data.into()
+}
+
+/// 由关键帧中的 SPS/PPS 生成 AVCDecoderConfigurationRecord (avcC)，用于 RTMP 的 AVC sequence header 和 MP4 封装
+pub fn codec_config_record(access_unit: &[u8]) -> Option<Bytes> {
+    let find = |nal_type: u8| nal_units(access_unit).find(|nal| nal.first().is_some_and(|byte| byte & 0x1f == nal_type));
+    let (sps, pps) = (find(NAL_SPS)?, find(NAL_PPS)?);
+    // SPS 头部之后依次是 profile_idc、constraint_set 标志和 level_idc
+    let profile = sps.get(1..4)?;
+
+    let mut record = vec![1]; // configurationVersion
+    record.extend_from_slice(profile);
+    record.extend_from_slice(&[
+        0xff, // lengthSizeMinusOne = 3
+        0xe1, // 1 个 SPS
+    ]);
+    record.extend_from_slice(&(sps.len() as u16).to_be_bytes());
+    record.extend_from_slice(sps);
+    record.push(1); // 1 个 PPS
+    record.extend_from_slice(&(pps.len() as u16).to_be_bytes());
+    record.extend_from_slice(pps);
+    Some(record.into())
+}
+
+/// 依次取出 Annex-B 数据中的 NAL 单元 (不含起始码)
+pub fn nal_units(data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let starts = start_codes(data);
+    let ends: Vec<usize> = starts.iter().skip(1).copied().chain(std::iter::once(data.len())).collect();
+    starts.into_iter().zip(ends).filter_map(move |(start, end)| {
+        let nal = &data[start..end];
+        let header = nal.iter().position(|&byte| byte == 1)? + 1;
+        Some(&nal[header..])
+    })
+}
+
+/// 所有起始码 (00 00 01，前面的 00 也算入起始码) 的位置
+fn start_codes(data: &[u8]) -> Vec<usize> {
+    let mut starts = Vec::new();
+    let mut index = 0;
+    while index + 3 <= data.len() {
+        if data[index] == 0 && data[index + 1] == 0 && data[index + 2] == 1 {
+            starts.push(if index > 0 && data[index - 1] == 0 { index - 1 } else { index });
+            index += 3;
+        } else {
+            index += 1;
+        }
+    }
+    starts
+}
@@ -0,0 +1,126 @@
+use bytes::Bytes;
+
+use crate::bits::BitReader;
+use crate::h264::nal_units;
+
+/// H.265 NAL 单元类型
+const NAL_VPS: u8 = 32;
+const NAL_SPS: u8 = 33;
+const NAL_PPS: u8 = 34;
+const NAL_AUD: u8 = 35;
+
+fn nal_type(nal: &[u8]) -> u8 {
+    nal.first().map_or(0, |byte| (byte >> 1) & 0x3f)
+}
+
+/// 把 Annex-B 访问单元转换为 4 字节长度前缀的格式 (hvcC 中 lengthSizeMinusOne 为 3)，去掉 AUD
+pub fn to_length_prefixed(access_unit: &[u8]) -> Bytes {
+    let mut data = Vec::with_capacity(access_unit.len() + 16);
+    for nal in nal_units(access_unit).filter(|nal| nal_type(nal) != NAL_AUD) {
+        data.extend_from_slice(&(nal.len() as u32).to_be_bytes());
+        data.extend_from_slice(nal);
+    }
+    data.into()
+}
+
+/// 由关键帧中的 VPS/SPS/PPS 生成 HEVCDecoderConfigurationRecord (hvcC)，用于 Enhanced RTMP 和 MP4 封装
+pub fn codec_config_record(access_unit: &[u8]) -> Option<Bytes> {
+    let find = |nal_type_wanted: u8| nal_units(access_unit).find(|nal| nal_type(nal) == nal_type_wanted);
+    let (vps, sps, pps) = (find(NAL_VPS)?, find(NAL_SPS)?, find(NAL_PPS)?);
+    let info = parse_sps(&remove_emulation_prevention(sps))?;
+
+    let mut record = vec![1]; // configurationVersion
+    record.extend_from_slice(&info.profile_tier_level); // 档次、兼容性、约束标志和级别
+    record.extend_from_slice(&[
+        0xf0, 0x00, // min_spatial_segmentation_idc
+        0xfc,       // parallelismType
+        0xfc | info.chroma_format,
+        0xf8 | info.bit_depth_luma_minus8,
+        0xf8 | info.bit_depth_chroma_minus8,
+        0x00, 0x00, // avgFrameRate
+        (info.temporal_layers << 3) | (info.temporal_id_nested << 2) | 0x03, // lengthSizeMinusOne = 3
+        3,          // numOfArrays
+    ]);
+    for (nal_type, nal) in [(NAL_VPS, vps), (NAL_SPS, sps), (NAL_PPS, pps)] {
+        record.push(0x80 | nal_type); // array_completeness
+        record.extend_from_slice(&1u16.to_be_bytes());
+        record.extend_from_slice(&(nal.len() as u16).to_be_bytes());
+        record.extend_from_slice(nal);
+    }
+    Some(record.into())
+}
+
+/// hvcC 需要的 SPS 字段
+struct SpsInfo {
+    profile_tier_level: [u8; 12], // general_profile_space 到 general_level_idc
+    temporal_layers: u8,
+    temporal_id_nested: u8,
+    chroma_format: u8,
+    bit_depth_luma_minus8: u8,
+    bit_depth_chroma_minus8: u8,
+}
+
+/// 按 H.265 7.3.2.2 解析 SPS，直到位深为止
+fn parse_sps(rbsp: &[u8]) -> Option<SpsInfo> {
+    // 2 字节 NAL 头部之后：sps_video_parameter_set_id (4)、sps_max_sub_layers_minus1 (3)、sps_temporal_id_nesting_flag (1)
+    let first = *rbsp.get(2)?;
+    let max_sub_layers_minus1 = ((first >> 1) & 0x07) as usize;
+    let profile_tier_level: [u8; 12] = rbsp.get(3..15)?.try_into().ok()?;
+
+    let mut bits = BitReader::new(rbsp.get(15..)?);
+    let mut sub_layers = Vec::with_capacity(max_sub_layers_minus1);
+    for _ in 0..max_sub_layers_minus1 {
+        sub_layers.push((bits.flag()?, bits.flag()?));
+    }
+    if max_sub_layers_minus1 > 0 {
+        bits.skip((8 - max_sub_layers_minus1) * 2)?;
+    }
+    for (profile_present, level_present) in sub_layers {
+        if profile_present {
+            bits.skip(88)?;
+        }
+        if level_present {
+            bits.skip(8)?;
+        }
+    }
+
+    bits.ue()?; // sps_seq_parameter_set_id
+    let chroma_format = bits.ue()? as u8;
+    if chroma_format == 3 {
+        bits.skip(1)?; // separate_colour_plane_flag
+    }
+    bits.ue()?; // pic_width_in_luma_samples
+    bits.ue()?; // pic_height_in_luma_samples
+    if bits.flag()? {
+        // conformance_window
+        for _ in 0..4 {
+            bits.ue()?;
+        }
+    }
+    let bit_depth_luma_minus8 = bits.ue()? as u8;
+    let bit_depth_chroma_minus8 = bits.ue()? as u8;
+
+    Some(SpsInfo {
+        profile_tier_level,
+        temporal_layers: max_sub_layers_minus1 as u8 + 1,
+        temporal_id_nested: first & 0x01,
+        chroma_format: chroma_format & 0x03,
+        bit_depth_luma_minus8: bit_depth_luma_minus8 & 0x07,
+        bit_depth_chroma_minus8: bit_depth_chroma_minus8 & 0x07,
+    })
+}
+
+/// 去掉防竞争字节 (00 00 03 中的 03)，得到 RBSP
+fn remove_emulation_prevention(nal: &[u8]) -> Vec<u8> {
+    let mut rbsp = Vec::with_capacity(nal.len());
+    let mut zeros = 0;
+    for &byte in nal {
+        if zeros >= 2 && byte == 3 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        rbsp.push(byte);
+    }
+    rbsp
+}
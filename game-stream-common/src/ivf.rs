@@ -0,0 +1,55 @@
+use bytes::Bytes;
+
+/// IVF 文件头长度
+const FILE_HEADER_SIZE: usize = 32;
+
+/// 每帧前的帧头长度：4 字节帧长度 + 8 字节时间戳
+const FRAME_HEADER_SIZE: usize = 12;
+
+/// 从 IVF 字节流中取出帧 (AV1/VP8/VP9 编码器的原始输出，每帧一个数据包)
+#[derive(Default)]
+pub struct IvfReader {
+    buffer: Vec<u8>,
+    header_read: bool,
+}
+
+impl IvfReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 追加字节流，返回已经完整的帧
+    pub fn push(&mut self, data: &[u8]) -> Vec<Bytes> {
+        self.buffer.extend_from_slice(data);
+
+        let mut offset = 0;
+        if !self.header_read {
+            if self.buffer.len() < FILE_HEADER_SIZE {
+                return Vec::new();
+            }
+            // 文件头中记录了头部长度，通常为 32
+            let header_size = (u16::from_le_bytes([self.buffer[6], self.buffer[7]]) as usize).max(FILE_HEADER_SIZE);
+            if self.buffer.len() < header_size {
+                return Vec::new();
+            }
+            offset = header_size;
+            self.header_read = true;
+        }
+
+        let mut frames = Vec::new();
+        while self.buffer.len() >= offset + FRAME_HEADER_SIZE {
+            let size = u32::from_le_bytes([
+                self.buffer[offset], self.buffer[offset + 1], self.buffer[offset + 2], self.buffer[offset + 3],
+            ]) as usize;
+            let start = offset + FRAME_HEADER_SIZE;
+            if self.buffer.len() < start + size {
+                break;
+            }
+            frames.push(Bytes::copy_from_slice(&self.buffer[start..start + size]));
+            offset = start + size;
+        }
+
+        self.buffer.drain(..offset);
+        frames
+    }
+}
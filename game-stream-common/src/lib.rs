@@ -3,9 +3,23 @@ pub mod config;
 pub mod error;
 pub mod stream;
 pub mod codec;
+pub mod relay;
+pub mod ffmpeg;
+pub mod h264;
+pub mod hevc;
+pub mod ogg;
+pub mod aac;
+pub mod av1;
+pub mod ivf;
+pub mod flv;
+pub mod ts;
+#[cfg(feature = "mock")]
+pub mod mock;
+mod bits;
 
 pub use error::{StreamError, StreamResult};
 pub use protocol::*;
 pub use config::*;
 pub use stream::*;
 pub use codec::*;
+pub use relay::*;
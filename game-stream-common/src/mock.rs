@@ -0,0 +1,230 @@
+//! 测试用的确定性后端 (mock 特性)：帧计数画面、正弦波音频和直通 "编码器"
+//!
+//! 不依赖捕获设备和 ffmpeg，输出只由参数和调用次数决定，用于测试管线中的包顺序、时间戳和重连逻辑。
+//! 直通编码器以 "mock" 后端注册到 EncoderFactory，配置中设置 encoder = "mock" 即可使用
+
+use bytes::Bytes;
+
+use crate::{
+    AudioEncoder, AudioEncoderConfig, AudioFrame, AudioSampleFormat, EncodedPacket, PacketType, StreamResult,
+    VideoCodec, VideoEncoder, VideoEncoderConfig, VideoFrame, VideoPixelFormat,
+};
+
+/// 直通编码器的后端名称，所有编码格式都已注册
+pub const MOCK_ENCODER: &str = "mock";
+
+/// 正弦波幅度 (-20 dBFS)
+const SINE_AMPLITUDE: f64 = 0.1;
+
+/// 每个音频帧的采样数，与 AAC 帧相同
+const SINE_FRAME_SAMPLES: u64 = 1024;
+
+/// 直通 H.264 关键帧前的占位 SPS/PPS (Baseline 3.0)，只用于生成 avcC，真正的解码器无法解码
+const MOCK_H264_SPS: [u8; 4] = [0x67, 0x42, 0xc0, 0x1e];
+const MOCK_H264_PPS: [u8; 4] = [0x68, 0xce, 0x3c, 0x80];
+
+/// Annex-B 的 4 字节起始码
+const START_CODE: [u8; 4] = [0, 0, 0, 1];
+
+/// 帧计数画面：每帧开头 8 字节为帧序号 (小端)，其余字节为序号的低 8 位；时间戳按帧率从 0 开始递增
+pub struct FrameCounterSource {
+    width: u32,
+    height: u32,
+    fps: u32,
+    index: u64,
+}
+
+impl FrameCounterSource {
+    pub fn new(width: u32, height: u32, fps: u32) -> Self {
+        Self { width: width.max(2), height: height.max(2), fps: fps.max(1), index: 0 }
+    }
+
+    /// 从画面或直通编码的包中读出帧序号，H.264 的包从片的负载中读出
+    pub fn frame_index(data: &[u8]) -> Option<u64> {
+        if data.starts_with(&START_CODE) {
+            let slice = crate::h264::nal_units(data).last()?;
+            return Self::frame_index(&remove_emulation_prevention(slice.get(1..)?));
+        }
+        Some(u64::from_le_bytes(data.get(..8)?.try_into().ok()?))
+    }
+}
+
+impl Iterator for FrameCounterSource {
+    type Item = VideoFrame;
+
+    fn next(&mut self) -> Option<VideoFrame> {
+        let mut data = vec![self.index as u8; (self.width * self.height * 4) as usize];
+        data[..8].copy_from_slice(&self.index.to_le_bytes());
+        let frame = VideoFrame {
+            data: Bytes::from(data),
+            width: self.width,
+            height: self.height,
+            format: VideoPixelFormat::Rgba32,
+            timestamp: self.index * 1000 / self.fps as u64,
+        };
+        self.index += 1;
+        Some(frame)
+    }
+}
+
+/// 正弦波音频：S16 交错，每帧 1024 个采样，时间戳按已输出的采样数计算
+pub struct SineWaveSource {
+    sample_rate: u32,
+    channels: u32,
+    frequency: f64,
+    position: u64, // 已输出的采样数 (每声道)
+}
+
+impl SineWaveSource {
+    pub fn new(sample_rate: u32, channels: u32, frequency: f64) -> Self {
+        Self { sample_rate: sample_rate.max(1), channels: channels.max(1), frequency, position: 0 }
+    }
+}
+
+impl Iterator for SineWaveSource {
+    type Item = AudioFrame;
+
+    fn next(&mut self) -> Option<AudioFrame> {
+        let mut data = Vec::with_capacity((SINE_FRAME_SAMPLES * self.channels as u64 * 2) as usize);
+        for sample in self.position..self.position + SINE_FRAME_SAMPLES {
+            let phase = std::f64::consts::TAU * self.frequency * sample as f64 / self.sample_rate as f64;
+            let value = ((phase.sin() * SINE_AMPLITUDE) * i16::MAX as f64) as i16;
+            for _ in 0..self.channels {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        let frame = AudioFrame {
+            data: Bytes::from(data),
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+            format: AudioSampleFormat::S16,
+            timestamp: self.position * 1000 / self.sample_rate as u64,
+        };
+        self.position += SINE_FRAME_SAMPLES;
+        Some(frame)
+    }
+}
+
+/// 直通视频编码器：包的负载就是输入画面，时间戳不变
+///
+/// 第一帧和之后每隔 keyframe_interval 秒的帧为关键帧，request_keyframe 让下一帧成为关键帧。
+/// H.264 的画面放在 Annex-B 访问单元的片中 (关键帧前带占位 SPS/PPS)，可以经过 RTMP/FLV 封装和 HLS 切片
+pub struct PassthroughVideoEncoder {
+    config: VideoEncoderConfig,
+    gop_position: u64, // 下一帧在 GOP 中的位置，为 0 时是关键帧
+}
+
+impl PassthroughVideoEncoder {
+    pub fn new(config: VideoEncoderConfig) -> Self {
+        Self { config, gop_position: 0 }
+    }
+}
+
+impl VideoEncoder for PassthroughVideoEncoder {
+    fn encode_frame(&mut self, frame: &VideoFrame) -> StreamResult<Vec<EncodedPacket>> {
+        let gop = (self.config.keyframe_interval.max(1) * self.config.fps.max(1)) as u64;
+        let is_keyframe = self.gop_position == 0;
+        self.gop_position = (self.gop_position + 1) % gop;
+        let data = match self.config.codec {
+            VideoCodec::H264 => h264_access_unit(&frame.data, is_keyframe),
+            _ => frame.data.clone(),
+        };
+        Ok(vec![EncodedPacket {
+            data,
+            timestamp: frame.timestamp,
+            is_keyframe,
+            packet_type: PacketType::Video,
+        }])
+    }
+
+    fn get_config(&self) -> VideoEncoderConfig {
+        self.config.clone()
+    }
+
+    fn flush(&mut self) -> StreamResult<Vec<EncodedPacket>> {
+        Ok(Vec::new())
+    }
+
+    fn set_bitrate(&mut self, bitrate: u32) -> StreamResult<Vec<EncodedPacket>> {
+        self.config.set_bitrate(bitrate);
+        Ok(Vec::new())
+    }
+
+    fn request_keyframe(&mut self) -> StreamResult<Vec<EncodedPacket>> {
+        self.gop_position = 0;
+        Ok(Vec::new())
+    }
+}
+
+/// 直通音频编码器：每个输入帧输出一个包，负载就是输入的采样
+pub struct PassthroughAudioEncoder {
+    config: AudioEncoderConfig,
+}
+
+impl PassthroughAudioEncoder {
+    pub fn new(config: AudioEncoderConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl AudioEncoder for PassthroughAudioEncoder {
+    fn encode_frame(&mut self, frame: &AudioFrame) -> StreamResult<Vec<EncodedPacket>> {
+        Ok(vec![EncodedPacket {
+            data: frame.data.clone(),
+            timestamp: frame.timestamp,
+            is_keyframe: true,
+            packet_type: PacketType::Audio,
+        }])
+    }
+
+    fn get_config(&self) -> AudioEncoderConfig {
+        self.config.clone()
+    }
+
+    fn flush(&mut self) -> StreamResult<Vec<EncodedPacket>> {
+        Ok(Vec::new())
+    }
+}
+
+/// 把画面封装为 H.264 访问单元，画面加上防竞争字节后作为片 (IDR 或非 IDR) 的负载
+fn h264_access_unit(frame: &[u8], is_keyframe: bool) -> Bytes {
+    let mut data = Vec::with_capacity(frame.len() + frame.len() / 64 + 24);
+    if is_keyframe {
+        for nal in [&MOCK_H264_SPS[..], &MOCK_H264_PPS[..]] {
+            data.extend_from_slice(&START_CODE);
+            data.extend_from_slice(nal);
+        }
+    }
+    data.extend_from_slice(&START_CODE);
+    data.push(if is_keyframe { 0x65 } else { 0x41 });
+
+    let mut zeros = 0;
+    for &byte in frame {
+        if zeros >= 2 && byte <= 3 {
+            data.push(3);
+            zeros = 0;
+        }
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        data.push(byte);
+    }
+    // NAL 不能以 00 结尾
+    if zeros > 0 {
+        data.push(3);
+    }
+    data.into()
+}
+
+/// 去掉防竞争字节 (00 00 03 中的 03)
+fn remove_emulation_prevention(nal: &[u8]) -> Vec<u8> {
+    let mut rbsp = Vec::with_capacity(nal.len());
+    let mut zeros = 0;
+    for &byte in nal {
+        if zeros >= 2 && byte == 3 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        rbsp.push(byte);
+    }
+    rbsp
+}
@@ -0,0 +1,61 @@
+use bytes::Bytes;
+
+/// Ogg 页头的固定部分长度 (不含分段表)
+const PAGE_HEADER_SIZE: usize = 27;
+
+/// 从 Ogg 字节流中取出数据包 (RFC 3533)，数据包可以跨页
+#[derive(Default)]
+pub struct OggPacketReader {
+    buffer: Vec<u8>,
+    packet: Vec<u8>, // 跨页的未完成数据包
+}
+
+impl OggPacketReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 追加字节流，返回已经完整的数据包
+    pub fn push(&mut self, data: &[u8]) -> Vec<Bytes> {
+        self.buffer.extend_from_slice(data);
+
+        let mut packets = Vec::new();
+        let mut offset = 0;
+        loop {
+            let page = &self.buffer[offset..];
+            if page.len() < PAGE_HEADER_SIZE {
+                break;
+            }
+            if &page[..4] != b"OggS" {
+                // 不是页的开头，跳到下一个捕获模式
+                offset += page[1..].windows(4).position(|bytes| bytes == b"OggS").map_or(page.len(), |index| index + 1);
+                continue;
+            }
+
+            let segments = page[26] as usize;
+            let header_size = PAGE_HEADER_SIZE + segments;
+            if page.len() < header_size {
+                break;
+            }
+            let lacing = &page[PAGE_HEADER_SIZE..header_size];
+            let body_size: usize = lacing.iter().map(|&size| size as usize).sum();
+            if page.len() < header_size + body_size {
+                break;
+            }
+
+            // 分段长度小于 255 表示数据包在该段结束
+            let mut position = header_size;
+            for &size in lacing {
+                self.packet.extend_from_slice(&page[position..position + size as usize]);
+                position += size as usize;
+                if size < 255 {
+                    packets.push(Bytes::from(std::mem::take(&mut self.packet)));
+                }
+            }
+            offset += header_size + body_size;
+        }
+
+        self.buffer.drain(..offset);
+        packets
+    }
+}
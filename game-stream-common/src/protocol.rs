@@ -1,21 +1,50 @@
+use std::collections::BTreeMap;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 /// 支持的推流协议类型
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub enum StreamProtocol {
     Rtmp,
     Srt,
+    Rist,
     Custom,
 }
 
 /// 支持的观看协议类型
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum ViewProtocol {
     Rtmp,
     Hls,
     Dash,
     WebRtc,
+    Relay, // 集群内部中继
+}
+
+/// 观看者选择的画质档位
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Rendition {
+    #[default]
+    Source,
+    #[serde(rename = "720p")]
+    P720,
+    #[serde(rename = "480p")]
+    P480,
+    AudioOnly, // 只转发音频，不需要转码
+}
+
+impl Rendition {
+    /// 配置和接口中使用的名称
+    pub fn name(self) -> &'static str {
+        match self {
+            Rendition::Source => "source",
+            Rendition::P720 => "720p",
+            Rendition::P480 => "480p",
+            Rendition::AudioOnly => "audio_only",
+        }
+    }
 }
 
 /// 流媒体信息
@@ -30,6 +59,40 @@ pub struct StreamInfo {
     pub viewer_count: u32,
     pub video_config: VideoConfig,
     pub audio_config: AudioConfig,
+    #[serde(default)]
+    pub thumbnail_url: Option<String>,
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>, // 自定义标签，如 game、category
+}
+
+/// 直播中修改流信息 (PATCH /api/streams/:key)，未给出的字段不变
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StreamInfoUpdate {
+    pub title: Option<String>,       // 空字符串清除
+    pub description: Option<String>, // 空字符串清除
+    #[serde(default)]
+    pub tags: BTreeMap<String, Option<String>>, // 值为 null 时删除该标签
+}
+
+impl StreamInfoUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.description.is_none() && self.tags.is_empty()
+    }
+
+    pub fn apply(self, info: &mut StreamInfo) {
+        if let Some(title) = self.title {
+            info.title = Some(title).filter(|title| !title.is_empty());
+        }
+        if let Some(description) = self.description {
+            info.description = Some(description).filter(|description| !description.is_empty());
+        }
+        for (name, value) in self.tags {
+            match value {
+                Some(value) => info.tags.insert(name, value),
+                None => info.tags.remove(&name),
+            };
+        }
+    }
 }
 
 /// 视频配置
@@ -52,7 +115,7 @@ pub struct AudioConfig {
 }
 
 /// 视频编码格式
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 pub enum VideoCodec {
     H264,
     H265,
@@ -62,7 +125,7 @@ pub enum VideoCodec {
 }
 
 /// 音频编码格式
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 pub enum AudioCodec {
     Aac,
     Opus,
@@ -70,15 +133,117 @@ pub enum AudioCodec {
     Pcm,
 }
 
+/// 观看者的远程输入事件 - 经 WebRTC 数据通道发到服务器，由服务器转发给推流端注入系统
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum InputEvent {
+    Key {
+        key: String, // 按键名称，写法与快捷键配置相同，如 "w"、"Space"、"F5"
+        pressed: bool,
+    },
+    MouseMove {
+        x: f32, // 在画面中的相对位置，0.0 - 1.0
+        y: f32,
+    },
+    MouseButton {
+        button: MouseButton,
+        pressed: bool,
+    },
+    Scroll {
+        dx: i32, // 滚动的格数，向右、向下为正
+        dy: i32,
+    },
+    GamepadButton {
+        button: String, // 如 "A"、"Start"、"DPadUp"
+        pressed: bool,
+    },
+    GamepadAxis {
+        axis: String, // 如 "LeftX"、"RightTrigger"
+        value: f32,   // 摇杆 -1.0 - 1.0，扳机 0.0 - 1.0
+    },
+    /// 手柄的完整状态，经 "gamepad" 数据通道按固定频率发送
+    Gamepad(GamepadState),
+}
+
+/// 手柄状态快照 - 观看者按固定频率发送所有按键和摇杆的当前状态，丢失的快照由下一个补上
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GamepadState {
+    #[serde(default)]
+    pub pad: u8,            // 观看者的第几个手柄
+    pub seq: u32,           // 序列号，每个快照加 1，推流端丢弃乱序到达的旧快照
+    pub sent_at: i64,       // 发送时间 (Unix 毫秒)，观看者按时钟同步换算为服务器时钟，服务器转发时再换算为推流端时钟
+    pub buttons: u32,       // 按下的按键，位序同浏览器 Gamepad API 的标准布局 (见 GAMEPAD_BUTTONS)
+    pub axes: [f32; 4],     // LeftX、LeftY、RightX、RightY，-1.0 - 1.0，向右、向下为正
+    pub triggers: [f32; 2], // LeftTrigger、RightTrigger，0.0 - 1.0
+}
+
+/// GamepadState.buttons 的位序 (浏览器 Gamepad API 标准布局，扳机 6、7 使用 triggers)，
+/// 也是 GamepadButton 事件的按键名称
+pub const GAMEPAD_BUTTONS: [&str; 17] = [
+    "A", "B", "X", "Y", "LeftBumper", "RightBumper", "LeftTrigger", "RightTrigger",
+    "Back", "Start", "LeftStick", "RightStick", "DPadUp", "DPadDown", "DPadLeft", "DPadRight", "Home",
+];
+
+/// GamepadState.axes 和 triggers 的顺序，也是 GamepadAxis 事件的摇杆名称
+pub const GAMEPAD_AXES: [&str; 6] = ["LeftX", "LeftY", "RightX", "RightY", "LeftTrigger", "RightTrigger"];
+
+impl GamepadState {
+    /// 所有按键松开、摇杆回中的状态
+    pub fn neutral(pad: u8) -> Self {
+        Self { pad, seq: 0, sent_at: 0, buttons: 0, axes: [0.0; 4], triggers: [0.0; 2] }
+    }
+
+    pub fn is_pressed(&self, button: usize) -> bool {
+        self.buttons & (1 << button) != 0
+    }
+}
+
+/// "gamepad" 数据通道的消息 (无序、不重传)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GamepadMessage {
+    State(GamepadState),
+    /// 观看者的时钟同步：观看者发送 client_time，服务器原样带回并附上 server_time，
+    /// 观看者取往返时间最短的一次估算时钟偏移，把 sent_at 换算为服务器时钟
+    ClockSync {
+        client_time: i64,
+        #[serde(default)]
+        server_time: Option<i64>,
+    },
+}
+
+/// 鼠标按键
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+/// 服务器转发给推流端的远程输入，带发送者的观看者 ID
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteInput {
+    pub viewer_id: Uuid,
+    pub event: InputEvent,
+}
+
 /// WebRTC 信令消息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum WebRtcSignal {
     Offer {
         stream_key: String,
         sdp: String,
+        #[serde(default)]
+        rendition: Rendition,
+        #[serde(default)]
+        access: Option<String>, // 私有流的观看密码或邀请 token
     },
     Answer {
         sdp: String,
+        viewer_id: Uuid, // 之后切换画质时使用
+    },
+    /// 播放过程中切换画质，成功时没有响应
+    SelectRendition {
+        viewer_id: Uuid,
+        rendition: Rendition,
     },
     IceCandidate {
         candidate: String,
@@ -110,11 +275,14 @@ pub struct ClientConnection {
 }
 
 /// 观看者连接信息
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ViewerConnection {
     pub id: Uuid,
     pub remote_addr: std::net::SocketAddr,
     pub connected_at: chrono::DateTime<chrono::Utc>,
     pub protocol: ViewProtocol,
     pub stream_key: String,
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub rendition: Rendition,
 }
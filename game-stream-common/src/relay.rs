@@ -0,0 +1,221 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use crate::{MediaPacket, StreamResult, StreamError};
+
+/// 内部中继协议版本
+pub const RELAY_PROTOCOL_VERSION: u8 = 1;
+
+/// 单帧最大长度，防止异常数据耗尽内存
+pub const MAX_RELAY_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+const FRAME_HELLO: u8 = 0x01;
+const FRAME_ACCEPT: u8 = 0x02;
+const FRAME_REJECT: u8 = 0x03;
+const FRAME_VIDEO: u8 = 0x10;
+const FRAME_AUDIO: u8 = 0x11;
+const FRAME_METADATA: u8 = 0x12;
+
+const FLAG_KEYFRAME: u8 = 0x01;
+const FLAG_CAPTURE_TIME: u8 = 0x02;
+
+/// 实例间中继协议帧
+///
+/// 线路格式: `[u32 长度 (大端)][u8 帧类型][负载]`，长度包含帧类型字节
+#[derive(Debug, Clone)]
+pub enum RelayFrame {
+    /// 拉流端发起请求，signature 为共享密钥对 `stream_key:timestamp` 的签名
+    Hello {
+        version: u8,
+        stream_key: String,
+        timestamp: i64,
+        signature: String,
+    },
+    Accept,
+    Reject {
+        reason: String,
+    },
+    Media(MediaPacket),
+}
+
+impl RelayFrame {
+    /// 编码为完整的线路帧 (含长度前缀)
+    pub fn encode(&self) -> Bytes {
+        build_frame(0, |body| match self {
+            RelayFrame::Hello { version, stream_key, timestamp, signature } => {
+                body.put_u8(*version);
+                body.put_i64(*timestamp);
+                put_string(body, stream_key);
+                put_string(body, signature);
+                FRAME_HELLO
+            }
+            RelayFrame::Accept => FRAME_ACCEPT,
+            RelayFrame::Reject { reason } => {
+                body.put_slice(reason.as_bytes());
+                FRAME_REJECT
+            }
+            RelayFrame::Media(packet) => put_media(body, packet),
+        })
+    }
+
+    /// 直接编码媒体包，分发共享的数据包时无需先复制成 RelayFrame
+    pub fn encode_media(packet: &MediaPacket) -> Bytes {
+        build_frame(17 + packet.size(), |body| put_media(body, packet))
+    }
+
+    /// 将媒体包编码追加到缓冲区，用于把多个帧合并为一次写入
+    pub fn encode_media_into(packet: &MediaPacket, buf: &mut BytesMut) {
+        buf.reserve(22 + packet.size());
+        append_frame(buf, |body| put_media(body, packet));
+    }
+
+    /// 从帧类型和负载解码
+    pub fn decode(frame_type: u8, mut body: Bytes) -> StreamResult<Self> {
+        match frame_type {
+            FRAME_HELLO => {
+                ensure_remaining(&body, 9)?;
+                let version = body.get_u8();
+                let timestamp = body.get_i64();
+                let stream_key = get_string(&mut body)?;
+                let signature = get_string(&mut body)?;
+                Ok(RelayFrame::Hello { version, stream_key, timestamp, signature })
+            }
+            FRAME_ACCEPT => Ok(RelayFrame::Accept),
+            FRAME_REJECT => Ok(RelayFrame::Reject {
+                reason: String::from_utf8_lossy(&body).into_owned(),
+            }),
+            FRAME_VIDEO => {
+                let (flags, timestamp, capture_time) = get_media_header(&mut body)?;
+                Ok(RelayFrame::Media(MediaPacket::Video {
+                    data: body,
+                    timestamp,
+                    is_keyframe: flags & FLAG_KEYFRAME != 0,
+                    capture_time,
+                }))
+            }
+            FRAME_AUDIO => {
+                let (_, timestamp, capture_time) = get_media_header(&mut body)?;
+                Ok(RelayFrame::Media(MediaPacket::Audio {
+                    data: body,
+                    timestamp,
+                    capture_time,
+                }))
+            }
+            FRAME_METADATA => Ok(RelayFrame::Media(MediaPacket::Metadata { data: body })),
+            other => Err(protocol_error(format!("unknown frame type 0x{:02x}", other))),
+        }
+    }
+
+    /// 写入一帧
+    pub async fn write_to<W: AsyncWrite + Unpin>(&self, writer: &mut W) -> StreamResult<()> {
+        writer.write_all(&self.encode()).await?;
+        Ok(())
+    }
+
+    /// 写入一个媒体包
+    pub async fn write_media<W: AsyncWrite + Unpin>(packet: &MediaPacket, writer: &mut W) -> StreamResult<()> {
+        writer.write_all(&Self::encode_media(packet)).await?;
+        Ok(())
+    }
+
+    /// 读取一帧，对端正常关闭时返回 None
+    pub async fn read_from<R: AsyncRead + Unpin>(reader: &mut R) -> StreamResult<Option<Self>> {
+        let len = match reader.read_u32().await {
+            Ok(len) => len as usize,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        if len == 0 || len > MAX_RELAY_FRAME_SIZE {
+            return Err(protocol_error(format!("invalid frame length {}", len)));
+        }
+
+        let frame_type = reader.read_u8().await?;
+        let mut body = vec![0u8; len - 1];
+        reader.read_exact(&mut body).await?;
+
+        Self::decode(frame_type, Bytes::from(body)).map(Some)
+    }
+}
+
+fn build_frame(capacity: usize, write_body: impl FnOnce(&mut BytesMut) -> u8) -> Bytes {
+    let mut frame = BytesMut::with_capacity(5 + capacity);
+    append_frame(&mut frame, write_body);
+    frame.freeze()
+}
+
+/// 在缓冲区末尾写入负载，再回填长度和帧类型
+fn append_frame(buf: &mut BytesMut, write_body: impl FnOnce(&mut BytesMut) -> u8) {
+    let start = buf.len();
+    buf.put_u32(0);
+    buf.put_u8(0);
+
+    let frame_type = write_body(buf);
+    let len = (buf.len() - start - 4) as u32;
+    buf[start..start + 4].copy_from_slice(&len.to_be_bytes());
+    buf[start + 4] = frame_type;
+}
+
+fn put_media(body: &mut BytesMut, packet: &MediaPacket) -> u8 {
+    match packet {
+        MediaPacket::Video { data, timestamp, is_keyframe, capture_time } => {
+            let mut flags = 0;
+            if *is_keyframe {
+                flags |= FLAG_KEYFRAME;
+            }
+            put_media_header(body, flags, *timestamp, *capture_time);
+            body.put_slice(data);
+            FRAME_VIDEO
+        }
+        MediaPacket::Audio { data, timestamp, capture_time } => {
+            put_media_header(body, 0, *timestamp, *capture_time);
+            body.put_slice(data);
+            FRAME_AUDIO
+        }
+        MediaPacket::Metadata { data } => {
+            body.put_slice(data);
+            FRAME_METADATA
+        }
+    }
+}
+
+fn put_string(buf: &mut BytesMut, value: &str) {
+    buf.put_u16(value.len() as u16);
+    buf.put_slice(value.as_bytes());
+}
+
+fn get_string(buf: &mut Bytes) -> StreamResult<String> {
+    ensure_remaining(buf, 2)?;
+    let len = buf.get_u16() as usize;
+    ensure_remaining(buf, len)?;
+    String::from_utf8(buf.split_to(len).to_vec())
+        .map_err(|_| protocol_error("invalid utf-8 string".to_string()))
+}
+
+fn put_media_header(buf: &mut BytesMut, mut flags: u8, timestamp: u64, capture_time: Option<i64>) {
+    if capture_time.is_some() {
+        flags |= FLAG_CAPTURE_TIME;
+    }
+    buf.put_u8(flags);
+    buf.put_u64(timestamp);
+    buf.put_i64(capture_time.unwrap_or(0));
+}
+
+fn get_media_header(buf: &mut Bytes) -> StreamResult<(u8, u64, Option<i64>)> {
+    ensure_remaining(buf, 17)?;
+    let flags = buf.get_u8();
+    let timestamp = buf.get_u64();
+    let capture_time = buf.get_i64();
+    let capture_time = (flags & FLAG_CAPTURE_TIME != 0).then_some(capture_time);
+    Ok((flags, timestamp, capture_time))
+}
+
+fn ensure_remaining(buf: &Bytes, len: usize) -> StreamResult<()> {
+    if buf.remaining() < len {
+        return Err(protocol_error("truncated frame".to_string()));
+    }
+    Ok(())
+}
+
+fn protocol_error(message: String) -> StreamError {
+    StreamError::Network(format!("Relay protocol error: {}", message))
+}
@@ -1,9 +1,15 @@
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{RwLock, mpsc};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{RwLock, broadcast, watch};
+use tracing::{debug, warn};
 use uuid::Uuid;
 use bytes::Bytes;
-use crate::{StreamInfo, StreamStatus, StreamResult, ViewerConnection};
+use dashmap::DashMap;
+use crate::{
+    RemoteInput, Rendition, StreamError, StreamInfo, StreamStatus, StreamResult, ViewerConnection, ViewProtocol
+};
 
 /// 媒体数据包类型
 #[derive(Debug, Clone)]
@@ -12,55 +18,284 @@ pub enum MediaPacket {
         data: Bytes,
         timestamp: u64,
         is_keyframe: bool,
+        /// 采集时间 (Unix 毫秒)，用于端到端延迟统计
+        capture_time: Option<i64>,
     },
     Audio {
         data: Bytes,
         timestamp: u64,
+        /// 采集时间 (Unix 毫秒)，用于端到端延迟统计
+        capture_time: Option<i64>,
     },
     Metadata {
         data: Bytes,
     },
 }
 
+impl MediaPacket {
+    /// 获取负载字节数
+    pub fn size(&self) -> usize {
+        self.data().len()
+    }
+
+    /// 获取负载数据
+    pub fn data(&self) -> &Bytes {
+        match self {
+            MediaPacket::Video { data, .. } => data,
+            MediaPacket::Audio { data, .. } => data,
+            MediaPacket::Metadata { data } => data,
+        }
+    }
+
+    /// 获取时间戳 (毫秒)，元数据没有时间戳
+    pub fn timestamp(&self) -> Option<u64> {
+        match self {
+            MediaPacket::Video { timestamp, .. } => Some(*timestamp),
+            MediaPacket::Audio { timestamp, .. } => Some(*timestamp),
+            MediaPacket::Metadata { .. } => None,
+        }
+    }
+
+    /// 是否为解码器配置 (AVC 序列头、Enhanced RTMP 的 HEVC/AV1 SequenceStart、AAC 序列头)，
+    /// 播放任意片段都需要先发送
+    pub fn is_sequence_header(&self) -> bool {
+        match self {
+            // Enhanced RTMP：首字节最高位为扩展头标志，低 4 位为包类型 (0 为 PacketTypeSequenceStart)
+            MediaPacket::Video { data, .. } if data.first().is_some_and(|header| header & 0x80 != 0) => data[0] & 0x0f == 0,
+            MediaPacket::Video { data, .. } => data.len() >= 2 && data[0] & 0x0f == 7 && data[1] == 0,
+            MediaPacket::Audio { data, .. } => data.len() >= 2 && data[0] >> 4 == 10 && data[1] == 0,
+            MediaPacket::Metadata { .. } => false,
+        }
+    }
+
+    /// 获取采集时间 (Unix 毫秒)
+    pub fn capture_time(&self) -> Option<i64> {
+        match self {
+            MediaPacket::Video { capture_time, .. } => *capture_time,
+            MediaPacket::Audio { capture_time, .. } => *capture_time,
+            MediaPacket::Metadata { .. } => None,
+        }
+    }
+
+    /// 计算从采集到现在经过的毫秒数
+    pub fn latency_ms(&self) -> Option<u64> {
+        self.capture_time()
+            .map(|captured| (chrono::Utc::now().timestamp_millis() - captured).max(0) as u64)
+    }
+}
+
+/// 流事件 - 通过事件总线广播给各个子系统
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum StreamEvent {
+    StreamCreated {
+        stream_key: String,
+    },
+    StreamRemoved {
+        stream_key: String,
+    },
+    StatusChanged {
+        stream_key: String,
+        status: StreamStatus,
+    },
+    ViewerJoined {
+        stream_key: String,
+        viewer: ViewerConnection,
+        viewer_count: u32,
+    },
+    ViewerLeft {
+        stream_key: String,
+        viewer_id: Uuid,
+        viewer_count: u32,
+    },
+    InfoChanged {
+        stream_key: String,
+        info: StreamInfo,
+    },
+}
+
+/// 事件总线容量
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// 媒体分发通道容量，消费过慢的订阅者会跳过最旧的数据包
+const MEDIA_CHANNEL_CAPACITY: usize = 512;
+
+/// 远程输入转发通道容量，推流端连接处理不及时时丢弃最旧的事件
+const REMOTE_INPUT_CHANNEL_CAPACITY: usize = 256;
+
+/// 媒体数据订阅端 - 所有订阅者共享同一份数据包，不按观看者复制负载
+pub type MediaReceiver = broadcast::Receiver<Arc<MediaPacket>>;
+
+/// 接收下一个媒体数据包，跳过因消费过慢而丢失的数据包，流结束时返回 None
+pub async fn recv_media(receiver: &mut MediaReceiver) -> Option<Arc<MediaPacket>> {
+    loop {
+        match receiver.recv().await {
+            Ok(packet) => return Some(packet),
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                debug!("Media subscriber lagged, skipped {} packets", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => return None,
+        }
+    }
+}
+
+/// 接收下一个媒体数据包，消费过慢而丢失数据包后丢弃视频直到下一个关键帧，
+/// 不再发送积压的画面 (超低延迟模式的实时观看)；流结束时返回 None
+pub async fn recv_latest_media(receiver: &mut MediaReceiver) -> Option<Arc<MediaPacket>> {
+    let mut waiting_for_keyframe = false;
+    loop {
+        match receiver.recv().await {
+            Ok(packet) => {
+                if waiting_for_keyframe && matches!(*packet, MediaPacket::Video { is_keyframe: false, .. }) {
+                    continue;
+                }
+                return Some(packet);
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                debug!("Media subscriber lagged, skipped {} packets, waiting for the next keyframe", skipped);
+                waiting_for_keyframe = true;
+            }
+            Err(broadcast::error::RecvError::Closed) => return None,
+        }
+    }
+}
+
+/// 直播流的回调 - 通过 StreamManager::add_observer 注册，录制、HLS、统计或第三方扩展可以直接挂接到
+/// 流的创建、状态变化和媒体数据上，不需要订阅事件总线再用 list_streams 补上已有的流
+///
+/// 回调在推流连接的任务中同步调用，不会像事件总线那样因处理过慢而跳过；回调不能阻塞，
+/// 耗时的处理 (写文件、上传) 应交给自己的任务
+pub trait StreamObserver: Send + Sync {
+    /// 流创建后调用，此时还未开始直播
+    fn on_create(&self, _stream: &Arc<LiveStream>) {}
+
+    /// 流状态变化后调用
+    fn on_status(&self, _stream: &LiveStream, _status: &StreamStatus) {}
+
+    /// 流收到媒体包时调用，在分发给观看者之前
+    fn on_packet(&self, _stream: &LiveStream, _packet: &Arc<MediaPacket>) {}
+
+    /// 流移除后调用
+    fn on_remove(&self, _stream: &Arc<LiveStream>) {}
+}
+
+/// 已注册的回调，流管理器和它创建的所有直播流共用，之后注册的回调也能收到已有流的数据
+#[derive(Clone, Default)]
+struct Observers(Arc<std::sync::RwLock<Vec<Arc<dyn StreamObserver>>>>);
+
+impl Observers {
+    fn add(&self, observer: Arc<dyn StreamObserver>) {
+        self.0.write().unwrap_or_else(|e| e.into_inner()).push(observer);
+    }
+
+    /// 依次调用所有回调；复制列表后在锁外调用，回调中可以再注册回调
+    fn notify(&self, callback: impl Fn(&dyn StreamObserver)) {
+        let observers = self.0.read().unwrap_or_else(|e| e.into_inner()).clone();
+        observers.iter().for_each(|observer| callback(observer.as_ref()));
+    }
+}
+
+impl std::fmt::Debug for Observers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let count = self.0.read().map(|observers| observers.len()).unwrap_or_default();
+        f.debug_struct("Observers").field("count", &count).finish()
+    }
+}
+
 /// 流管理器 - 管理所有活跃的直播流
+///
+/// 使用分片的并发哈希表，大量并发推流和 API 请求时不会争用同一把锁
 #[derive(Debug)]
 pub struct StreamManager {
-    streams: Arc<RwLock<HashMap<String, Arc<LiveStream>>>>,
+    streams: Arc<DashMap<String, Arc<LiveStream>>>,
+    events: broadcast::Sender<StreamEvent>,
+    observers: Observers,
 }
 
 impl StreamManager {
     pub fn new() -> Self {
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        
         Self {
-            streams: Arc::new(RwLock::new(HashMap::new())),
+            streams: Arc::new(DashMap::new()),
+            events,
+            observers: Observers::default(),
         }
     }
 
+    /// 注册回调，之后创建的流和已有的流都会调用
+    pub fn add_observer(&self, observer: Arc<dyn StreamObserver>) {
+        self.observers.add(observer);
+    }
+
+    /// 订阅流事件
+    pub fn subscribe_events(&self) -> broadcast::Receiver<StreamEvent> {
+        self.events.subscribe()
+    }
+
+    /// 以异步流的形式订阅流事件，与服务器内部各子系统使用同一个事件总线，供嵌入程序驱动界面、计费或通知
+    ///
+    /// 订阅者处理过慢时跳过落后的事件 (记录警告) 继续接收；StreamManager 及其所有直播流释放后流结束
+    pub fn subscribe(&self) -> impl futures::Stream<Item = StreamEvent> + Send + 'static {
+        futures::stream::unfold(self.events.subscribe(), |mut events| async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => return Some((event, events)),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Stream event subscriber lagged behind event bus, skipped {} events", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
     /// 创建新的直播流
     pub async fn create_stream(&self, stream_key: String, info: StreamInfo) -> StreamResult<Arc<LiveStream>> {
-        let stream = Arc::new(LiveStream::new(stream_key.clone(), info));
+        let stream = Arc::new(LiveStream::with_observers(stream_key.clone(), info, self.events.clone(), self.observers.clone()));
         
-        let mut streams = self.streams.write().await;
-        streams.insert(stream_key, stream.clone());
+        self.streams.insert(stream_key.clone(), stream.clone());
+        self.observers.notify(|observer| observer.on_create(&stream));
+        
+        let _ = self.events.send(StreamEvent::StreamCreated { stream_key });
         
         Ok(stream)
     }
 
     /// 获取直播流
     pub async fn get_stream(&self, stream_key: &str) -> Option<Arc<LiveStream>> {
-        let streams = self.streams.read().await;
-        streams.get(stream_key).cloned()
+        self.streams.get(stream_key).map(|entry| entry.value().clone())
     }
 
     /// 移除直播流
     pub async fn remove_stream(&self, stream_key: &str) -> Option<Arc<LiveStream>> {
-        let mut streams = self.streams.write().await;
-        streams.remove(stream_key)
+        let removed = self.streams.remove(stream_key).map(|(_, stream)| stream);
+        
+        if let Some(stream) = &removed {
+            self.observers.notify(|observer| observer.on_remove(stream));
+            let _ = self.events.send(StreamEvent::StreamRemoved {
+                stream_key: stream_key.to_string(),
+            });
+        }
+        
+        removed
     }
 
     /// 获取所有活跃的流
     pub async fn list_streams(&self) -> Vec<(String, Arc<LiveStream>)> {
-        let streams = self.streams.read().await;
-        streams.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+        self.streams.iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect()
+    }
+
+    /// 获取活跃流数量
+    pub fn stream_count(&self) -> usize {
+        self.streams.len()
+    }
+}
+
+impl Default for StreamManager {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -71,75 +306,200 @@ pub struct LiveStream {
     pub info: Arc<RwLock<StreamInfo>>,
     pub status: Arc<RwLock<StreamStatus>>,
     pub viewers: Arc<RwLock<HashMap<Uuid, ViewerConnection>>>,
+    pub bandwidth: Arc<BandwidthStats>,
+    pub latency: Arc<LatencyStats>,
+    pub client_stats: Arc<RwLock<Option<ClientStats>>>, // 推流端最近一次上报的统计
+    
+    // HLS 观看者没有连接，数量由观看分析按会话维护
+    hls_viewers: AtomicU32,
     
     // 媒体数据分发通道
-    media_sender: mpsc::UnboundedSender<MediaPacket>,
-    media_receivers: Arc<RwLock<Vec<mpsc::UnboundedReceiver<MediaPacket>>>>,
+    media: broadcast::Sender<Arc<MediaPacket>>,
+    
+    // 流事件发送端
+    events: broadcast::Sender<StreamEvent>,
+    
+    // 观看者发给推流端的远程输入
+    remote_input: broadcast::Sender<RemoteInput>,
+    
+    // 观看者当前选择的画质，转发任务通过 RenditionFilter 读取
+    renditions: DashMap<Uuid, watch::Sender<Rendition>>,
+    
+    // 最近的音视频序列头，从直播中途开始消费时需要先发送
+    sequence_headers: RwLock<Vec<Arc<MediaPacket>>>,
+    
+    // 流管理器上注册的回调
+    observers: Observers,
 }
 
 impl LiveStream {
     pub fn new(stream_key: String, info: StreamInfo) -> Self {
-        let (media_sender, _) = mpsc::unbounded_channel();
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self::with_events(stream_key, info, events)
+    }
+
+    /// 创建直播流，并将事件发送到指定的事件总线
+    pub fn with_events(stream_key: String, info: StreamInfo, events: broadcast::Sender<StreamEvent>) -> Self {
+        Self::with_observers(stream_key, info, events, Observers::default())
+    }
+
+    fn with_observers(stream_key: String, info: StreamInfo, events: broadcast::Sender<StreamEvent>, observers: Observers) -> Self {
+        let (media, _) = broadcast::channel(MEDIA_CHANNEL_CAPACITY);
+        let (remote_input, _) = broadcast::channel(REMOTE_INPUT_CHANNEL_CAPACITY);
         
         Self {
             stream_key,
             info: Arc::new(RwLock::new(info)),
             status: Arc::new(RwLock::new(StreamStatus::Starting)),
             viewers: Arc::new(RwLock::new(HashMap::new())),
-            media_sender,
-            media_receivers: Arc::new(RwLock::new(Vec::new())),
+            bandwidth: Arc::new(BandwidthStats::new()),
+            latency: Arc::new(LatencyStats::new()),
+            client_stats: Arc::new(RwLock::new(None)),
+            hls_viewers: AtomicU32::new(0),
+            media,
+            events,
+            remote_input,
+            renditions: DashMap::new(),
+            sequence_headers: RwLock::new(Vec::new()),
+            observers,
         }
     }
 
     /// 发送媒体数据包
     pub async fn send_media_packet(&self, packet: MediaPacket) -> StreamResult<()> {
-        self.media_sender.send(packet)
-            .map_err(|_| crate::StreamError::Internal("Failed to send media packet".to_string()))?;
+        self.bandwidth.record_ingress(packet.size() as u64);
+        
+        if let Some(latency) = packet.latency_ms() {
+            self.latency.record_ingest(latency).await;
+        }
+        
+        let packet = Arc::new(packet);
+        if packet.is_sequence_header() {
+            let mut headers = self.sequence_headers.write().await;
+            headers.retain(|header| std::mem::discriminant(header.as_ref()) != std::mem::discriminant(packet.as_ref()));
+            headers.push(packet.clone());
+        }
+        self.observers.notify(|observer| observer.on_packet(self, &packet));
+        
+        // 没有订阅者时直接丢弃
+        let _ = self.media.send(packet);
         Ok(())
     }
 
+    /// 最近的音视频序列头 (解码器配置)
+    pub async fn sequence_headers(&self) -> Vec<Arc<MediaPacket>> {
+        self.sequence_headers.read().await.clone()
+    }
+
     /// 添加观看者
-    pub async fn add_viewer(&self, viewer: ViewerConnection) -> mpsc::UnboundedReceiver<MediaPacket> {
-        let (_sender, receiver) = mpsc::unbounded_channel();
+    pub async fn add_viewer(&self, viewer: ViewerConnection) -> MediaReceiver {
+        let receiver = self.media.subscribe();
+        self.renditions.insert(viewer.id, watch::channel(viewer.rendition).0);
 
         // 添加观看者信息
-        {
+        let viewer_count = {
             let mut viewers = self.viewers.write().await;
-            viewers.insert(viewer.id, viewer);
-        }
+            viewers.insert(viewer.id, viewer.clone());
+            viewers.len() as u32 + self.hls_viewers.load(Ordering::Relaxed)
+        };
 
         // 更新观看者数量
-        {
-            let mut info = self.info.write().await;
-            info.viewer_count = self.viewers.read().await.len() as u32;
-        }
+        self.info.write().await.viewer_count = viewer_count;
+
+        let _ = self.events.send(StreamEvent::ViewerJoined {
+            stream_key: self.stream_key.clone(),
+            viewer,
+            viewer_count,
+        });
 
         receiver
     }
 
+    /// 观看者的画质过滤器，观看者切换画质后立即生效
+    pub fn rendition_filter(&self, viewer_id: Uuid) -> RenditionFilter {
+        let rendition = match self.renditions.get(&viewer_id) {
+            Some(sender) => sender.subscribe(),
+            None => watch::channel(Rendition::Source).1,
+        };
+        RenditionFilter { rendition, waiting_for_keyframe: false }
+    }
+
+    /// 这个流可以选择的画质档位；服务器没有转码档位，只有原画和纯音频
+    pub fn available_renditions(&self) -> Vec<Rendition> {
+        vec![Rendition::Source, Rendition::AudioOnly]
+    }
+
+    /// 画质档位不可选时返回错误
+    pub fn check_rendition(&self, rendition: Rendition) -> StreamResult<()> {
+        let available = self.available_renditions();
+        if available.contains(&rendition) {
+            return Ok(());
+        }
+        let names: Vec<&str> = available.iter().map(|rendition| rendition.name()).collect();
+        Err(StreamError::Config(format!(
+            "Rendition {} is not available for stream {} ({})", rendition.name(), self.stream_key, names.join(", ")
+        )))
+    }
+
+    /// 切换观看者的画质
+    pub async fn set_viewer_rendition(&self, viewer_id: Uuid, rendition: Rendition) -> StreamResult<()> {
+        self.check_rendition(rendition)?;
+        
+        let mut viewers = self.viewers.write().await;
+        let (Some(viewer), Some(sender)) = (viewers.get_mut(&viewer_id), self.renditions.get(&viewer_id)) else {
+            return Err(StreamError::Config(format!("Viewer {} is not watching stream {}", viewer_id, self.stream_key)));
+        };
+        if viewer.rendition != rendition {
+            debug!("Viewer {} of stream {} switched to {}", viewer_id, self.stream_key, rendition.name());
+            viewer.rendition = rendition;
+            sender.send_replace(rendition);
+        }
+        Ok(())
+    }
+
+    /// 订阅媒体数据 (转推等内部消费者，不计入观看者)
+    pub fn subscribe_media(&self) -> MediaReceiver {
+        self.media.subscribe()
+    }
+
     /// 移除观看者
     pub async fn remove_viewer(&self, viewer_id: Uuid) {
-        let mut viewers = self.viewers.write().await;
-        viewers.remove(&viewer_id);
+        let viewer_count = {
+            let mut viewers = self.viewers.write().await;
+            if viewers.remove(&viewer_id).is_none() {
+                return;
+            }
+            viewers.len() as u32 + self.hls_viewers.load(Ordering::Relaxed)
+        };
+        self.latency.remove_viewer(viewer_id).await;
+        self.renditions.remove(&viewer_id);
         
         // 更新观看者数量
-        let mut info = self.info.write().await;
-        info.viewer_count = viewers.len() as u32;
+        self.info.write().await.viewer_count = viewer_count;
+
+        let _ = self.events.send(StreamEvent::ViewerLeft {
+            stream_key: self.stream_key.clone(),
+            viewer_id,
+            viewer_count,
+        });
     }
 
     /// 设置流状态
     pub async fn set_status(&self, status: StreamStatus) {
-        let mut current_status = self.status.write().await;
-        *current_status = status;
+        *self.status.write().await = status.clone();
         
         // 如果流状态变为 Live，更新信息中的 is_live 字段
-        if matches!(*current_status, StreamStatus::Live) {
-            let mut info = self.info.write().await;
-            info.is_live = true;
-        } else if matches!(*current_status, StreamStatus::Stopped | StreamStatus::Error(_)) {
-            let mut info = self.info.write().await;
-            info.is_live = false;
+        if matches!(status, StreamStatus::Live) {
+            self.info.write().await.is_live = true;
+        } else if matches!(status, StreamStatus::Stopped | StreamStatus::Error(_)) {
+            self.info.write().await.is_live = false;
         }
+        self.observers.notify(|observer| observer.on_status(self, &status));
+        
+        let _ = self.events.send(StreamEvent::StatusChanged {
+            stream_key: self.stream_key.clone(),
+            status,
+        });
     }
 
     /// 获取流状态
@@ -152,9 +512,307 @@ impl LiveStream {
         self.info.read().await.clone()
     }
 
-    /// 获取观看者数量
+    /// 修改流信息 (标题、简介等) 并广播 InfoChanged 事件
+    pub async fn update_info(&self, update: impl FnOnce(&mut StreamInfo)) -> StreamInfo {
+        let info = {
+            let mut info = self.info.write().await;
+            update(&mut info);
+            info.clone()
+        };
+        
+        let _ = self.events.send(StreamEvent::InfoChanged {
+            stream_key: self.stream_key.clone(),
+            info: info.clone(),
+        });
+        info
+    }
+
+    /// 获取观看者数量 (包括 HLS 观看者)
     pub async fn get_viewer_count(&self) -> u32 {
-        self.viewers.read().await.len() as u32
+        self.viewers.read().await.len() as u32 + self.hls_viewers.load(Ordering::Relaxed)
+    }
+
+    /// 更新 HLS 观看者数量
+    pub async fn set_hls_viewer_count(&self, count: u32) {
+        self.hls_viewers.store(count, Ordering::Relaxed);
+        let viewer_count = self.get_viewer_count().await;
+        self.info.write().await.viewer_count = viewer_count;
+    }
+
+    /// 把观看者的输入转发给推流端，推流端未订阅时丢弃
+    pub fn send_remote_input(&self, input: RemoteInput) {
+        let _ = self.remote_input.send(input);
+    }
+
+    /// 推流端连接订阅远程输入
+    pub fn subscribe_remote_input(&self) -> broadcast::Receiver<RemoteInput> {
+        self.remote_input.subscribe()
+    }
+
+    /// 保存推流端上报的统计
+    pub async fn set_client_stats(&self, stats: ClientStats) {
+        *self.client_stats.write().await = Some(stats);
+    }
+}
+
+/// 推流端的统计 - 客户端定期汇总，写入日志、本地 JSON 接口，并可上报给服务器
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClientStats {
+    pub timestamp: i64,              // 汇总时间 (Unix 毫秒)
+    pub encoded_fps: f64,            // 统计周期内编码输出的视频帧率
+    pub dropped_frames: u64,         // 统计周期内编码跟不上而丢弃的视频帧
+    pub total_dropped_frames: u64,
+    pub target_bitrate: u32,         // 视频编码器当前的目标码率 (kbps)，自适应码率会调整
+    pub send_bitrate: f64,           // 统计周期内实际发送的码率 (kbps)
+    pub queue_depth: usize,          // 等待发送的包
+    pub rtt_ms: Option<f64>,         // 推流协议能测量时的往返时间
+    pub reconnects: u32,
+    pub fec_overhead: Option<f64>,   // FEC 校验包占数据包的百分比 (SRT)
+    pub retransmit_overhead: Option<f64>, // 重传包占数据包的百分比 (SRT)
+    #[serde(default)]
+    pub silent_audio_inputs: Vec<String>, // 持续无声超过 [capture.audio_mixer.silence] 时长的混音输入
+    #[serde(default)]
+    pub remote_gamepads: Vec<GamepadReport>, // 正在操作的观看者手柄
+}
+
+/// 观看者手柄的延迟报告 - 推流端按收到的手柄快照统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GamepadReport {
+    pub viewer_id: Uuid,
+    pub pad: u8,
+    pub latency_ms: f64, // 观看者发送到推流端收到的平滑单程延迟，依赖两段时钟同步的精度
+    pub jitter_ms: f64,  // 到达间隔的抖动 (RFC 3550 的算法)
+    pub buffer_ms: f64,  // 当前抖动缓冲的延迟
+    pub received: u64,
+    pub lost: u64,       // 序列号缺失的快照，包括服务器限速丢弃的
+    pub late: u64,       // 乱序到达或超过缓冲时间到达的快照
+}
+
+/// 带宽统计 - 按流和观看协议累计流入/流出字节数 (流出只统计实际发出的数据，WebRTC 的媒体发送尚未实现，不计入)
+#[derive(Debug)]
+pub struct BandwidthStats {
+    ingress_bytes: AtomicU64,
+    ingress_packets: AtomicU64,
+    egress: RwLock<HashMap<ViewProtocol, u64>>,
+}
+
+/// 带宽统计快照
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BandwidthSnapshot {
+    pub ingress_bytes: u64,
+    pub ingress_packets: u64,
+    pub egress_bytes: u64,
+    pub egress_by_protocol: HashMap<ViewProtocol, u64>,
+}
+
+impl BandwidthStats {
+    pub fn new() -> Self {
+        Self {
+            ingress_bytes: AtomicU64::new(0),
+            ingress_packets: AtomicU64::new(0),
+            egress: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 记录推流端流入的字节数
+    pub fn record_ingress(&self, bytes: u64) {
+        self.ingress_bytes.fetch_add(bytes, Ordering::Relaxed);
+        self.ingress_packets.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录发送给观看者的字节数
+    pub async fn record_egress(&self, protocol: ViewProtocol, bytes: u64) {
+        let mut egress = self.egress.write().await;
+        *egress.entry(protocol).or_insert(0) += bytes;
+    }
+
+    /// 获取当前统计快照
+    pub async fn snapshot(&self) -> BandwidthSnapshot {
+        let egress_by_protocol = self.egress.read().await.clone();
+        
+        BandwidthSnapshot {
+            ingress_bytes: self.ingress_bytes.load(Ordering::Relaxed),
+            ingress_packets: self.ingress_packets.load(Ordering::Relaxed),
+            egress_bytes: egress_by_protocol.values().sum(),
+            egress_by_protocol,
+        }
+    }
+}
+
+impl Default for BandwidthStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 按观看者选择的画质过滤媒体包
+///
+/// 纯音频时丢弃视频；切回有画面的档位后从下一个关键帧开始发送视频
+#[derive(Debug)]
+pub struct RenditionFilter {
+    rendition: watch::Receiver<Rendition>,
+    waiting_for_keyframe: bool,
+}
+
+impl RenditionFilter {
+    /// 是否把数据包发给观看者
+    pub fn accept(&mut self, packet: &MediaPacket) -> bool {
+        let MediaPacket::Video { is_keyframe, .. } = packet else {
+            return true;
+        };
+        if *self.rendition.borrow() == Rendition::AudioOnly {
+            self.waiting_for_keyframe = true;
+            return false;
+        }
+        if self.waiting_for_keyframe && !is_keyframe {
+            return false;
+        }
+        self.waiting_for_keyframe = false;
+        true
+    }
+}
+
+/// 延迟统计 - 推流端采集到服务器接收 (ingest)，以及采集到观看端发送 (glass-to-glass)
+///
+/// 推流端完成时钟同步后，采集时间已换算为服务器时钟
+#[derive(Debug)]
+pub struct LatencyStats {
+    ingest: RwLock<LatencyTracker>,
+    delivery: RwLock<HashMap<ViewProtocol, LatencyTracker>>,
+    viewers: RwLock<HashMap<Uuid, (ViewProtocol, LatencyTracker)>>,
+    clock_offset_ms: RwLock<Option<i64>>,
+}
+
+/// 单项延迟的统计值 (毫秒)
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct LatencySummary {
+    pub last_ms: u64,
+    pub avg_ms: u64,
+    pub max_ms: u64,
+    pub samples: u64,
+}
+
+/// 延迟统计快照
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LatencySnapshot {
+    pub ingest: Option<LatencySummary>,
+    pub glass_to_glass: HashMap<ViewProtocol, LatencySummary>,
+    pub clock_offset_ms: Option<i64>, // 服务器时钟减推流端时钟，推流端不支持时钟同步时为 null
+    #[serde(default)]
+    pub viewers: Vec<ViewerLatency>,
+}
+
+/// 单个观看者的延迟估算 (毫秒)：推流端到服务器 + 服务器分发 + 播放端缓冲
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewerLatency {
+    pub viewer_id: Uuid,
+    pub protocol: ViewProtocol,
+    pub ingest_ms: u64,
+    pub fanout_ms: u64,
+    pub buffer_ms: u64, // 由服务器按观看协议估算
+    pub estimated_ms: u64,
+}
+
+/// 延迟平滑系数，平均值为指数移动平均
+const LATENCY_SMOOTHING: f64 = 0.1;
+
+#[derive(Debug, Default)]
+struct LatencyTracker {
+    last_ms: u64,
+    avg_ms: f64,
+    max_ms: u64,
+    samples: u64,
+}
+
+impl LatencyTracker {
+    fn record(&mut self, latency_ms: u64) {
+        self.avg_ms = if self.samples == 0 {
+            latency_ms as f64
+        } else {
+            self.avg_ms + (latency_ms as f64 - self.avg_ms) * LATENCY_SMOOTHING
+        };
+        self.last_ms = latency_ms;
+        self.max_ms = self.max_ms.max(latency_ms);
+        self.samples += 1;
+    }
+
+    fn summary(&self) -> LatencySummary {
+        LatencySummary {
+            last_ms: self.last_ms,
+            avg_ms: self.avg_ms.round() as u64,
+            max_ms: self.max_ms,
+            samples: self.samples,
+        }
+    }
+}
+
+impl LatencyStats {
+    pub fn new() -> Self {
+        Self {
+            ingest: RwLock::new(LatencyTracker::default()),
+            delivery: RwLock::new(HashMap::new()),
+            viewers: RwLock::new(HashMap::new()),
+            clock_offset_ms: RwLock::new(None),
+        }
+    }
+
+    /// 记录推流端时钟同步的结果
+    pub async fn set_clock_offset(&self, offset_ms: i64) {
+        *self.clock_offset_ms.write().await = Some(offset_ms);
+    }
+
+    /// 记录采集到服务器接收的延迟
+    pub async fn record_ingest(&self, latency_ms: u64) {
+        self.ingest.write().await.record(latency_ms);
+    }
+
+    /// 记录采集到发送给观看者的延迟
+    pub async fn record_delivery(&self, viewer_id: Uuid, protocol: ViewProtocol, latency_ms: u64) {
+        self.viewers.write().await
+            .entry(viewer_id)
+            .or_insert_with(|| (protocol.clone(), LatencyTracker::default()))
+            .1.record(latency_ms);
+        let mut delivery = self.delivery.write().await;
+        delivery.entry(protocol).or_default().record(latency_ms);
+    }
+
+    pub async fn remove_viewer(&self, viewer_id: Uuid) {
+        self.viewers.write().await.remove(&viewer_id);
+    }
+
+    /// 获取当前统计快照
+    pub async fn snapshot(&self) -> LatencySnapshot {
+        let ingest = self.ingest.read().await;
+        
+        let ingest_ms = ingest.summary().avg_ms;
+        
+        LatencySnapshot {
+            ingest: (ingest.samples > 0).then(|| ingest.summary()),
+            glass_to_glass: self.delivery.read().await.iter()
+                .map(|(protocol, tracker)| (protocol.clone(), tracker.summary()))
+                .collect(),
+            clock_offset_ms: *self.clock_offset_ms.read().await,
+            // 分发延迟是送达观看者与到达服务器的平均延迟之差，播放端缓冲留给服务器按协议补充
+            viewers: self.viewers.read().await.iter()
+                .map(|(viewer_id, (protocol, tracker))| {
+                    let delivery_ms = tracker.summary().avg_ms;
+                    ViewerLatency {
+                        viewer_id: *viewer_id,
+                        protocol: protocol.clone(),
+                        ingest_ms,
+                        fanout_ms: delivery_ms.saturating_sub(ingest_ms),
+                        buffer_ms: 0,
+                        estimated_ms: delivery_ms.max(ingest_ms),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Default for LatencyStats {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -0,0 +1,192 @@
+use bytes::Bytes;
+
+/// TS 包长度
+const PACKET_SIZE: usize = 188;
+
+/// TS 包头之后的负载长度
+const PAYLOAD_SIZE: usize = PACKET_SIZE - 4;
+
+/// 固定的 PID 分配：PAT、PMT、视频和音频
+const PID_PAT: u16 = 0x0000;
+const PID_PMT: u16 = 0x1000;
+const PID_VIDEO: u16 = 0x0100;
+const PID_AUDIO: u16 = 0x0101;
+
+/// PMT 中的流类型 (ISO 13818-1 表 2-34)
+pub const STREAM_TYPE_AAC: u8 = 0x0f; // ADTS
+pub const STREAM_TYPE_H264: u8 = 0x1b;
+pub const STREAM_TYPE_HEVC: u8 = 0x24;
+
+/// PES 的 stream_id
+const STREAM_ID_VIDEO: u8 = 0xe0;
+const STREAM_ID_AUDIO: u8 = 0xc0;
+
+/// PTS 比 PCR 晚的时间 (毫秒)，给解码端留出缓冲，与 ffmpeg 默认的 muxdelay 相同
+const PTS_DELAY_MS: u64 = 700;
+
+/// PTS/PCR 基准时钟为 90kHz，33 位回绕
+const CLOCK_RATE: u64 = 90;
+const CLOCK_MASK: u64 = (1 << 33) - 1;
+
+/// MPEG-TS 封装 - 一个节目，最多一路视频 (Annex-B) 和一路音频 (ADTS)，用于 SRT/RIST 推流
+///
+/// 每个关键帧前重复 PAT/PMT，PCR 随每个视频 PES 发送 (没有视频时随音频)，
+/// 方便接收端从任意关键帧开始解码
+pub struct TsMuxer {
+    video_type: Option<u8>,
+    audio_type: Option<u8>,
+    continuity: [u8; 4], // PAT、PMT、视频、音频的连续计数器
+    tables_sent: bool,
+}
+
+impl TsMuxer {
+    /// 视频和音频的流类型，为 None 时节目中不含该流
+    pub fn new(video_type: Option<u8>, audio_type: Option<u8>) -> Self {
+        Self { video_type, audio_type, continuity: [0; 4], tables_sent: false }
+    }
+
+    /// 封装一帧视频，时间戳为毫秒
+    pub fn write_video(&mut self, data: &[u8], timestamp: u64, is_keyframe: bool) -> Bytes {
+        let mut output = Vec::with_capacity(data.len() + data.len() / PAYLOAD_SIZE * 4 + 3 * PACKET_SIZE);
+        if is_keyframe || !self.tables_sent {
+            self.write_tables(&mut output);
+        }
+        let pes = pes(STREAM_ID_VIDEO, data, timestamp, false);
+        self.write_pes(PID_VIDEO, 2, &pes, Some(timestamp), is_keyframe, &mut output);
+        output.into()
+    }
+
+    /// 封装一帧音频 (带 ADTS 头部的 AAC)，时间戳为毫秒
+    pub fn write_audio(&mut self, data: &[u8], timestamp: u64) -> Bytes {
+        let mut output = Vec::with_capacity(data.len() + 3 * PACKET_SIZE);
+        if !self.tables_sent {
+            self.write_tables(&mut output);
+        }
+        let pes = pes(STREAM_ID_AUDIO, data, timestamp, true);
+        let pcr = if self.video_type.is_none() { Some(timestamp) } else { None };
+        self.write_pes(PID_AUDIO, 3, &pes, pcr, self.video_type.is_none(), &mut output);
+        output.into()
+    }
+
+    fn write_tables(&mut self, output: &mut Vec<u8>) {
+        // PAT：节目 1 对应 PMT
+        let mut pat = vec![0x00, 0xb0, 0x00, 0x00, 0x01, 0xc1, 0x00, 0x00];
+        pat.extend_from_slice(&[0x00, 0x01, 0xe0 | (PID_PMT >> 8) as u8, PID_PMT as u8]);
+        self.write_section(PID_PAT, 0, pat, output);
+
+        let pcr_pid = if self.video_type.is_some() { PID_VIDEO } else { PID_AUDIO };
+        let mut pmt = vec![0x02, 0xb0, 0x00, 0x00, 0x01, 0xc1, 0x00, 0x00];
+        pmt.extend_from_slice(&[0xe0 | (pcr_pid >> 8) as u8, pcr_pid as u8, 0xf0, 0x00]);
+        for (stream_type, pid) in [(self.video_type, PID_VIDEO), (self.audio_type, PID_AUDIO)] {
+            if let Some(stream_type) = stream_type {
+                pmt.extend_from_slice(&[stream_type, 0xe0 | (pid >> 8) as u8, pid as u8, 0xf0, 0x00]);
+            }
+        }
+        self.write_section(PID_PMT, 1, pmt, output);
+        self.tables_sent = true;
+    }
+
+    /// 补上 section_length 和 CRC，写入一个 TS 包
+    fn write_section(&mut self, pid: u16, counter: usize, mut section: Vec<u8>, output: &mut Vec<u8>) {
+        let length = section.len() - 3 + 4;
+        section[1] |= (length >> 8) as u8 & 0x0f;
+        section[2] = length as u8;
+        let crc = crc32(&section);
+        section.extend_from_slice(&crc.to_be_bytes());
+
+        let mut payload = vec![0x00]; // pointer_field
+        payload.extend_from_slice(&section);
+        payload.resize(PAYLOAD_SIZE, 0xff);
+        output.extend_from_slice(&self.header(pid, counter, true, false));
+        output.extend_from_slice(&payload);
+    }
+
+    /// 把 PES 切分为 TS 包，第一个包带 PCR 和随机访问标志，最后一个包用适配字段填充
+    fn write_pes(&mut self, pid: u16, counter: usize, pes: &[u8], pcr: Option<u64>, random_access: bool, output: &mut Vec<u8>) {
+        let mut remaining = pes;
+        let mut first = true;
+        while !remaining.is_empty() {
+            // 适配字段中长度字节之后的内容
+            let mut adaptation = Vec::new();
+            if first && (pcr.is_some() || random_access) {
+                adaptation.push(if random_access { 0x40 } else { 0x00 } | if pcr.is_some() { 0x10 } else { 0x00 });
+                if let Some(pcr) = pcr {
+                    adaptation.extend_from_slice(&encode_pcr(pcr * CLOCK_RATE));
+                }
+            }
+            let space = PAYLOAD_SIZE - if adaptation.is_empty() { 0 } else { 1 + adaptation.len() };
+            let stuffing = space.saturating_sub(remaining.len());
+            let has_adaptation = !adaptation.is_empty() || stuffing > 0;
+            if !adaptation.is_empty() {
+                adaptation.resize(adaptation.len() + stuffing, 0xff);
+            } else if stuffing > 1 {
+                // 长度字节和标志字节之后全部填充 0xff；只差一个字节时只有长度字节 (0)
+                adaptation.push(0x00);
+                adaptation.resize(stuffing - 1, 0xff);
+            }
+
+            output.extend_from_slice(&self.header(pid, counter, first, has_adaptation));
+            if has_adaptation {
+                output.push(adaptation.len() as u8);
+                output.extend_from_slice(&adaptation);
+            }
+            let size = PACKET_SIZE - 4 - if has_adaptation { 1 + adaptation.len() } else { 0 };
+            output.extend_from_slice(&remaining[..size]);
+            remaining = &remaining[size..];
+            first = false;
+        }
+    }
+
+    fn header(&mut self, pid: u16, counter: usize, payload_start: bool, has_adaptation: bool) -> [u8; 4] {
+        let continuity = self.continuity[counter];
+        self.continuity[counter] = (continuity + 1) & 0x0f;
+        [
+            0x47,
+            if payload_start { 0x40 } else { 0x00 } | (pid >> 8) as u8 & 0x1f,
+            pid as u8,
+            if has_adaptation { 0x30 } else { 0x10 } | continuity,
+        ]
+    }
+}
+
+/// PES 包：只带 PTS。视频的 PES_packet_length 为 0 (不限长度)
+fn pes(stream_id: u8, data: &[u8], timestamp: u64, bounded: bool) -> Vec<u8> {
+    let header_size = 3 + 5; // 标志、头部长度和 PTS
+    let length = if bounded && data.len() + header_size <= u16::MAX as usize { (data.len() + header_size) as u16 } else { 0 };
+    let mut pes = Vec::with_capacity(data.len() + 6 + header_size);
+    pes.extend_from_slice(&[0x00, 0x00, 0x01, stream_id]);
+    pes.extend_from_slice(&length.to_be_bytes());
+    pes.extend_from_slice(&[0x80, 0x80, 0x05]); // PTS_DTS_flags = '10'
+    pes.extend_from_slice(&encode_pts((timestamp + PTS_DELAY_MS) * CLOCK_RATE));
+    pes.extend_from_slice(data);
+    pes
+}
+
+fn encode_pts(pts: u64) -> [u8; 5] {
+    let pts = pts & CLOCK_MASK;
+    [
+        0x21 | ((pts >> 29) as u8 & 0x0e),
+        (pts >> 22) as u8,
+        ((pts >> 14) as u8 & 0xfe) | 0x01,
+        (pts >> 7) as u8,
+        ((pts << 1) as u8 & 0xfe) | 0x01,
+    ]
+}
+
+/// PCR：33 位基准 (90kHz) 和 9 位扩展 (27MHz 余数，这里为 0)
+fn encode_pcr(base: u64) -> [u8; 6] {
+    let base = base & CLOCK_MASK;
+    [(base >> 25) as u8, (base >> 17) as u8, (base >> 9) as u8, (base >> 1) as u8, ((base << 7) as u8 & 0x80) | 0x7e, 0x00]
+}
+
+/// CRC-32/MPEG-2
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= (byte as u32) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 { (crc << 1) ^ 0x04c1_1db7 } else { crc << 1 };
+        }
+    }
+    crc
+}
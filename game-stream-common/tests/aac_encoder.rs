@@ -0,0 +1,89 @@
+use std::process::Command;
+
+use game_stream_common::ffmpeg;
+use game_stream_common::{AudioCodec, AudioEncoderConfig, AudioFrame, AudioSampleFormat, EncoderFactory, EncodedPacket};
+
+const SAMPLE_RATE: u32 = 44100;
+const CHANNELS: u32 = 2;
+const FRAME_SAMPLES: u32 = 1024;
+const FRAMES: u32 = 50;
+
+/// 编码一段正弦波，原始帧加 AudioSpecificConfig 封装为 FLV 后应能被 ffprobe 解码
+#[test]
+#[ignore = "needs ffmpeg with an AAC encoder and ffprobe, run with --ignored"]
+fn aac_output_muxes_into_flv() {
+    assert!(ffmpeg::has_encoder("aac") || ffmpeg::has_encoder("libfdk_aac"), "ffmpeg with an AAC encoder not found");
+
+    let mut encoder = EncoderFactory::create_audio_encoder(AudioEncoderConfig {
+        codec: AudioCodec::Aac,
+        sample_rate: SAMPLE_RATE,
+        channels: CHANNELS,
+        bitrate: 128,
+        vbr: false,
+    }).unwrap();
+
+    let mut packets = Vec::new();
+    for index in 0..FRAMES {
+        let data: Vec<u8> = (0..FRAME_SAMPLES)
+            .flat_map(|sample| {
+                let t = (index * FRAME_SAMPLES + sample) as f32 / SAMPLE_RATE as f32;
+                let value = ((t * 440.0 * std::f32::consts::TAU).sin() * 8000.0) as i16;
+                [value, value]
+            })
+            .flat_map(i16::to_le_bytes)
+            .collect();
+        let frame = AudioFrame {
+            data: data.into(),
+            sample_rate: SAMPLE_RATE,
+            channels: CHANNELS,
+            format: AudioSampleFormat::S16,
+            timestamp: (index * FRAME_SAMPLES * 1000 / SAMPLE_RATE) as u64,
+        };
+        packets.extend(encoder.encode_frame(&frame).unwrap());
+    }
+    packets.extend(encoder.flush().unwrap());
+
+    // AAC-LC (2)、44.1kHz (索引 4)、双声道
+    assert_eq!(encoder.codec_config().as_deref(), Some(&[0x12, 0x10][..]));
+    assert!(packets.len() as u32 >= FRAMES, "at least one packet per input frame");
+    assert!(packets.windows(2).all(|pair| pair[0].timestamp < pair[1].timestamp), "timestamps in order");
+
+    assert!(Command::new("ffprobe").arg("-version").output().is_ok(), "ffprobe not found");
+
+    let path = std::env::temp_dir().join(format!("game-stream-aac-test-{}.flv", std::process::id()));
+    std::fs::write(&path, flv(&encoder.codec_config().unwrap(), &packets)).unwrap();
+
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-count_frames", "-select_streams", "a:0"])
+        .args(["-show_entries", "stream=codec_name,sample_rate,channels,nb_read_frames", "-of", "csv=p=0"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    assert!(output.status.success(), "ffprobe failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        format!("aac,{},{},{}", SAMPLE_RATE, CHANNELS, packets.len())
+    );
+}
+
+/// 只含音频的 FLV：AAC sequence header (AudioSpecificConfig) 后跟原始帧
+fn flv(config: &[u8], packets: &[EncodedPacket]) -> Vec<u8> {
+    let mut flv = vec![b'F', b'L', b'V', 1, 0x04, 0, 0, 0, 9, 0, 0, 0, 0];
+    let mut tag = |timestamp: u64, body: Vec<u8>| {
+        let (size, ts) = (body.len() as u32, timestamp as u32);
+        flv.extend_from_slice(&[8, (size >> 16) as u8, (size >> 8) as u8, size as u8]);
+        flv.extend_from_slice(&[(ts >> 16) as u8, (ts >> 8) as u8, ts as u8, (ts >> 24) as u8, 0, 0, 0]);
+        flv.extend_from_slice(&body);
+        flv.extend_from_slice(&(11 + size).to_be_bytes());
+    };
+
+    // 0xAF：AAC、44kHz、16 位、立体声
+    tag(0, [&[0xaf, 0x00][..], config].concat());
+    let start = packets[0].timestamp;
+    for packet in packets {
+        tag(packet.timestamp - start, [&[0xaf, 0x01][..], &packet.data].concat());
+    }
+    flv
+}
@@ -0,0 +1,26 @@
+use game_stream_common::{ClientConfig, ServerConfig};
+
+/// --print-default-config 输出的配置必须能直接加载
+#[test]
+fn templates_parse() {
+    let client: ClientConfig = toml::from_str(ClientConfig::TEMPLATE).unwrap();
+    assert_eq!(client.server.port, 1935);
+    let server: ServerConfig = toml::from_str(ServerConfig::TEMPLATE).unwrap();
+    assert_eq!(server.rtmp.port, 1935);
+}
+
+/// Schema 描述顶层的每个配置段，必填的段与反序列化一致
+#[test]
+fn schemas_describe_config_sections() {
+    let client = ClientConfig::json_schema();
+    for section in ["server", "stream", "capture", "encoding", "network", "scenes", "remote_input"] {
+        assert!(client["properties"].get(section).is_some(), "client schema lacks {}", section);
+    }
+    let required: Vec<_> = client["required"].as_array().unwrap().iter().filter_map(|v| v.as_str()).collect();
+    assert!(required.contains(&"server") && !required.contains(&"scenes"), "required: {:?}", required);
+
+    let server = ServerConfig::json_schema();
+    for section in ["rtmp", "http", "storage"] {
+        assert!(server["properties"].get(section).is_some(), "server schema lacks {}", section);
+    }
+}
@@ -0,0 +1,79 @@
+use std::process::Command;
+
+use game_stream_common::ffmpeg;
+use game_stream_common::{EncoderFactory, RateControl, VideoCodec, VideoEncoderConfig, VideoFrame, VideoPixelFormat};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+const FRAMES: u64 = 60;
+
+/// 编码一段移动的渐变画面，输出应能被 ffprobe 解码
+#[test]
+#[ignore = "needs ffmpeg with libx264 or libopenh264 and ffprobe, run with --ignored"]
+fn h264_output_decodes_with_ffprobe() {
+    assert!(
+        ffmpeg::has_encoder("libx264") || ffmpeg::has_encoder("libopenh264"),
+        "ffmpeg with libx264 or libopenh264 not found"
+    );
+
+    let mut encoder = EncoderFactory::create_video_encoder(VideoEncoderConfig {
+        codec: VideoCodec::H264,
+        width: WIDTH,
+        height: HEIGHT,
+        fps: 30,
+        bitrate: 500,
+        keyframe_interval: 1,
+        preset: "ultrafast".to_string(),
+        hardware_acceleration: false,
+        vaapi_device: None,
+        rate_control: RateControl::Cbr,
+        max_bitrate: None,
+        buffer_size: None,
+        qp: 23,
+        profile: None,
+        level: None,
+        b_frames: 0,
+        low_latency: true,
+    }).unwrap();
+
+    let mut packets = Vec::new();
+    for index in 0..FRAMES {
+        let data: Vec<u8> = (0..WIDTH * HEIGHT)
+            .flat_map(|pixel| {
+                let (x, y) = ((pixel % WIDTH) as u64, (pixel / WIDTH) as u64);
+                [(x + index * 4) as u8, (y + index * 2) as u8, (x ^ y) as u8, 255]
+            })
+            .collect();
+        let frame = VideoFrame {
+            data: data.into(),
+            width: WIDTH,
+            height: HEIGHT,
+            format: VideoPixelFormat::Rgba32,
+            timestamp: index * 1000 / 30,
+        };
+        packets.extend(encoder.encode_frame(&frame).unwrap());
+    }
+    packets.extend(encoder.flush().unwrap());
+
+    assert_eq!(packets.len() as u64, FRAMES, "one access unit per frame");
+    assert!(packets[0].is_keyframe, "stream starts with a keyframe");
+    assert!(packets.iter().filter(|packet| packet.is_keyframe).count() >= 2, "keyframe every second");
+    assert!(packets.windows(2).all(|pair| pair[0].timestamp < pair[1].timestamp), "timestamps in order");
+
+    assert!(Command::new("ffprobe").arg("-version").output().is_ok(), "ffprobe not found");
+
+    let path = std::env::temp_dir().join(format!("game-stream-h264-test-{}.h264", std::process::id()));
+    let stream: Vec<u8> = packets.iter().flat_map(|packet| packet.data.iter().copied()).collect();
+    std::fs::write(&path, stream).unwrap();
+
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-count_frames", "-select_streams", "v:0"])
+        .args(["-show_entries", "stream=codec_name,width,height,nb_read_frames", "-of", "csv=p=0"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    assert!(output.status.success(), "ffprobe failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), format!("h264,{},{},{}", WIDTH, HEIGHT, FRAMES));
+}
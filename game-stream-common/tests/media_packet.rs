@@ -0,0 +1,27 @@
+use bytes::Bytes;
+use game_stream_common::flv::{self, FOURCC_AV1, FOURCC_HEVC};
+use game_stream_common::MediaPacket;
+
+fn video(data: Bytes) -> MediaPacket {
+    MediaPacket::Video { data, timestamp: 0, is_keyframe: true, capture_time: None }
+}
+
+/// 经典 FLV 的 AVC/AAC 序列头和 Enhanced RTMP 的 SequenceStart 都是序列头，编码帧不是
+#[test]
+fn sequence_headers() {
+    assert!(video(flv::avc_sequence_header(&[1, 2, 3])).is_sequence_header());
+    assert!(!video(flv::avc_video_frame(true, &[0, 0, 0, 1, 0x65])).is_sequence_header());
+
+    for fourcc in [FOURCC_HEVC, FOURCC_AV1] {
+        assert!(video(flv::enhanced_video_sequence_start(fourcc, &[1, 2, 3])).is_sequence_header());
+        assert!(!video(flv::enhanced_video_frame(fourcc, true, &[1, 2, 3])).is_sequence_header());
+        assert!(!video(flv::enhanced_video_frame(fourcc, false, &[1, 2, 3])).is_sequence_header());
+    }
+    // 扩展头的 SequenceEnd (包类型 2) 不是
+    assert!(!video(Bytes::from_static(&[0x92, b'h', b'v', b'c', b'1'])).is_sequence_header());
+
+    let audio = |data| MediaPacket::Audio { data, timestamp: 0, capture_time: None };
+    assert!(audio(flv::aac_sequence_header(&[0x12, 0x10])).is_sequence_header());
+    assert!(!audio(flv::aac_audio_frame(&[0x21])).is_sequence_header());
+    assert!(!video(Bytes::new()).is_sequence_header());
+}
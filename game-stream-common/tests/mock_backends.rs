@@ -0,0 +1,91 @@
+use game_stream_common::mock::{FrameCounterSource, SineWaveSource, MOCK_ENCODER};
+use game_stream_common::{
+    AudioCodec, AudioEncoderConfig, EncoderFactory, RateControl, VideoCodec, VideoEncoderConfig,
+};
+
+const FPS: u32 = 30;
+
+fn video_config() -> VideoEncoderConfig {
+    VideoEncoderConfig {
+        codec: VideoCodec::H264,
+        width: 64,
+        height: 36,
+        fps: FPS,
+        bitrate: 500,
+        keyframe_interval: 1,
+        preset: "ultrafast".to_string(),
+        hardware_acceleration: false,
+        vaapi_device: None,
+        rate_control: RateControl::Cbr,
+        max_bitrate: None,
+        buffer_size: None,
+        qp: 23,
+        profile: None,
+        level: None,
+        b_frames: 0,
+        low_latency: true,
+    }
+}
+
+/// 直通编码后帧序号和时间戳保持输入的顺序，关键帧间隔与配置一致
+#[test]
+fn passthrough_video_keeps_order_and_timestamps() {
+    let mut encoder = EncoderFactory::create_video_encoder_with(MOCK_ENCODER, video_config()).unwrap();
+    let packets: Vec<_> = FrameCounterSource::new(64, 36, FPS)
+        .take(90)
+        .flat_map(|frame| encoder.encode_frame(&frame).unwrap())
+        .collect();
+
+    assert_eq!(packets.len(), 90, "one packet per frame");
+    for (index, packet) in packets.iter().enumerate() {
+        let index = index as u64;
+        assert_eq!(FrameCounterSource::frame_index(&packet.data), Some(index), "frames in order");
+        assert_eq!(packet.timestamp, index * 1000 / FPS as u64);
+        assert_eq!(packet.is_keyframe, index.is_multiple_of(FPS as u64), "keyframe every second, frame {}", index);
+    }
+}
+
+/// 请求关键帧后下一帧是关键帧，之后的 GOP 从这一帧重新计算
+#[test]
+fn passthrough_video_honours_keyframe_requests() {
+    let mut encoder = EncoderFactory::create_video_encoder_with(MOCK_ENCODER, video_config()).unwrap();
+    let mut frames = FrameCounterSource::new(64, 36, FPS);
+    for frame in frames.by_ref().take(10) {
+        encoder.encode_frame(&frame).unwrap();
+    }
+
+    assert!(encoder.request_keyframe().unwrap().is_empty());
+    let keyframes: Vec<_> = frames.take(FPS as usize + 1)
+        .map(|frame| encoder.encode_frame(&frame).unwrap()[0].is_keyframe)
+        .collect();
+    assert!(keyframes[0], "requested keyframe");
+    assert!(!keyframes[1..FPS as usize].iter().any(|&keyframe| keyframe));
+    assert!(keyframes[FPS as usize], "next GOP starts one second after the requested keyframe");
+}
+
+/// 正弦波每帧 1024 个采样，时间戳按采样数连续递增
+#[test]
+fn sine_wave_timestamps_follow_sample_count() {
+    let mut encoder = EncoderFactory::create_audio_encoder_with(MOCK_ENCODER, AudioEncoderConfig {
+        codec: AudioCodec::Aac,
+        sample_rate: 48000,
+        channels: 2,
+        bitrate: 128,
+        vbr: false,
+    }).unwrap();
+    let packets: Vec<_> = SineWaveSource::new(48000, 2, 1000.0)
+        .take(50)
+        .flat_map(|frame| encoder.encode_frame(&frame).unwrap())
+        .collect();
+
+    assert_eq!(packets.len(), 50);
+    for (index, packet) in packets.iter().enumerate() {
+        assert_eq!(packet.data.len(), 1024 * 2 * 2, "S16 stereo");
+        assert_eq!(packet.timestamp, index as u64 * 1024 * 1000 / 48000);
+    }
+    let peak = packets[0].data.chunks_exact(2)
+        .map(|sample| i16::from_le_bytes([sample[0], sample[1]]).unsigned_abs())
+        .max()
+        .unwrap();
+    assert!((3000..=3300).contains(&peak), "-20 dBFS peak, got {}", peak);
+}
@@ -0,0 +1,71 @@
+use std::net::SocketAddr;
+use std::time::Instant;
+use axum::{
+    body::{Body, HttpBody},
+    extract::{ConnectInfo, Request},
+    http::header,
+    middleware::Next,
+    response::Response,
+};
+use tracing::info;
+
+/// HTTP 访问日志中间件 - 记录播放列表、片段和 API 请求
+pub async fn access_log(request: Request, next: Next) -> Response {
+    let started = Instant::now();
+
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let stream_key = stream_key_from_path(&path).map(|key| key.to_string());
+    let remote_addr = request.extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.to_string())
+        .unwrap_or_else(|| "-".to_string());
+    let user_agent = request.headers()
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("-")
+        .to_string();
+
+    let response = next.run(request).await;
+
+    let bytes = response_bytes(&response);
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    info!(
+        %method,
+        path = %path,
+        stream_key = stream_key.as_deref().unwrap_or("-"),
+        status = response.status().as_u16(),
+        bytes,
+        latency_ms = format!("{:.2}", latency_ms),
+        remote_addr = %remote_addr,
+        user_agent = %user_agent,
+        "{} {} {}",
+        method,
+        path,
+        response.status().as_u16(),
+    );
+
+    response
+}
+
+/// 从请求路径中解析流密钥
+fn stream_key_from_path(path: &str) -> Option<&str> {
+    let mut parts = path.trim_start_matches('/').split('/');
+
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some("hls"), Some(key), _) => Some(key),
+        (Some("api"), Some("streams"), Some(key)) => Some(key),
+        _ => None,
+    }
+}
+
+/// 获取响应体字节数
+fn response_bytes(response: &Response<Body>) -> u64 {
+    response.headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .or_else(|| response.body().size_hint().exact())
+        .unwrap_or(0)
+}
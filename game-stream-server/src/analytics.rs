@@ -0,0 +1,327 @@
+use std::sync::Arc;
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::time::Duration;
+use tokio::sync::{RwLock, broadcast};
+use tracing::{info, debug, warn};
+use serde::Serialize;
+use uuid::Uuid;
+
+use game_stream_common::{AnalyticsConfig, StreamEvent, StreamManager, ViewProtocol, ViewerConnection};
+
+/// HLS 观看会话超过这么久没有请求播放列表时视为离开
+pub const HLS_SESSION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 检查闲置 HLS 会话的间隔
+const HLS_SWEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 同一客户端地址在一个流上最多同时计入的 HLS 会话 (同一地址后的多个播放器)，
+/// 更换 User-Agent 不能无限增加观看人数
+pub const MAX_HLS_SESSIONS_PER_ADDR: usize = 8;
+
+/// 观看分析管理器 - 记录观看会话历史并计算汇总指标
+///
+/// RTMP、WebRTC 观看者由事件总线的加入/离开事件记录；HLS 没有连接，
+/// 按客户端地址和 User-Agent 在请求播放列表时开始或延续会话，闲置超时后结束。
+/// 关闭观看分析时仍然维护 HLS 会话，用于流的观看人数和过载保护
+pub struct AnalyticsManager {
+    config: AnalyticsConfig,
+    stream_manager: Arc<StreamManager>,
+    active_sessions: RwLock<HashMap<Uuid, ViewerConnection>>,
+    hls_sessions: RwLock<HashMap<(String, IpAddr, String), HlsSession>>, // (流, 客户端地址, User-Agent)
+    streams: RwLock<HashMap<String, StreamAnalytics>>,
+}
+
+/// 进行中的 HLS 观看会话
+struct HlsSession {
+    viewer_id: Uuid,
+    last_seen: chrono::DateTime<chrono::Utc>,
+}
+
+/// 单个流的观看统计
+#[derive(Default)]
+struct StreamAnalytics {
+    sessions: VecDeque<ViewerSession>,
+    total_sessions: u64,
+    total_watch_seconds: i64,
+    peak_concurrent_viewers: u32,
+}
+
+/// 已结束的观看会话
+#[derive(Debug, Clone, Serialize)]
+pub struct ViewerSession {
+    pub viewer_id: Uuid,
+    pub stream_key: String,
+    pub protocol: ViewProtocol,
+    pub remote_addr: std::net::SocketAddr,
+    pub user_agent: Option<String>,
+    pub joined_at: chrono::DateTime<chrono::Utc>,
+    pub left_at: chrono::DateTime<chrono::Utc>,
+    pub watched_seconds: i64,
+}
+
+/// 流的观看汇总指标
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalyticsSummary {
+    pub stream_key: String,
+    pub active_viewers: u32,
+    pub peak_concurrent_viewers: u32,
+    pub total_sessions: u64,
+    pub average_watch_seconds: f64,
+    pub total_watch_seconds: i64,
+}
+
+impl AnalyticsManager {
+    pub fn new(config: &AnalyticsConfig, stream_manager: Arc<StreamManager>) -> Self {
+        info!("Initializing analytics manager...");
+
+        Self {
+            config: config.clone(),
+            stream_manager,
+            active_sessions: RwLock::new(HashMap::new()),
+            hls_sessions: RwLock::new(HashMap::new()),
+            streams: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 订阅事件总线并持续记录观看会话
+    pub async fn start(self: Arc<Self>) {
+        let mut sweep = tokio::time::interval(HLS_SWEEP_INTERVAL);
+
+        if !self.config.enabled {
+            info!("Viewer analytics disabled, only counting HLS viewers");
+            loop {
+                sweep.tick().await;
+                self.expire_hls_sessions(chrono::Utc::now()).await;
+            }
+        }
+
+        let mut events = self.stream_manager.subscribe_events();
+
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(event) => self.handle_event(event).await,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Analytics lagged behind event bus, skipped {} events", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = sweep.tick() => self.expire_hls_sessions(chrono::Utc::now()).await,
+            }
+        }
+    }
+
+    /// HLS 播放列表请求：同一客户端地址和 User-Agent 的首次请求开始会话，之后的请求延续会话；
+    /// 该地址已有 MAX_HLS_SESSIONS_PER_ADDR 个会话时不再开始新的会话
+    pub async fn touch_hls_session(
+        &self,
+        stream_key: &str,
+        remote_addr: std::net::SocketAddr,
+        user_agent: Option<String>,
+    ) {
+        let now = chrono::Utc::now();
+        let key = (stream_key.to_string(), remote_addr.ip(), user_agent.clone().unwrap_or_default());
+        let mut hls_sessions = self.hls_sessions.write().await;
+        if let Some(hls_session) = hls_sessions.get_mut(&key) {
+            hls_session.last_seen = now;
+            return;
+        }
+        let sessions_from_addr = hls_sessions.keys()
+            .filter(|(key_stream, addr, _)| key_stream == stream_key && *addr == remote_addr.ip())
+            .count();
+        if sessions_from_addr >= MAX_HLS_SESSIONS_PER_ADDR {
+            debug!("Too many HLS sessions from {} on stream {}, not counting a new one", remote_addr.ip(), stream_key);
+            return;
+        }
+
+        let viewer = ViewerConnection {
+            id: Uuid::new_v4(),
+            remote_addr,
+            connected_at: now,
+            protocol: ViewProtocol::Hls,
+            stream_key: stream_key.to_string(),
+            user_agent,
+            rendition: Default::default(),
+        };
+        debug!("HLS viewer {} joined stream {}", viewer.id, stream_key);
+        hls_sessions.insert(key, HlsSession { viewer_id: viewer.id, last_seen: now });
+        let hls_viewer_count = count_hls_sessions(&hls_sessions, stream_key);
+        drop(hls_sessions);
+        self.update_hls_viewer_count(stream_key, hls_viewer_count).await;
+
+        if !self.config.enabled {
+            return;
+        }
+
+        let mut active_sessions = self.active_sessions.write().await;
+        active_sessions.insert(viewer.id, viewer);
+        let viewer_count = active_sessions.values()
+            .filter(|viewer| viewer.stream_key == stream_key)
+            .count() as u32;
+        drop(active_sessions);
+
+        let mut streams = self.streams.write().await;
+        let analytics = streams.entry(stream_key.to_string()).or_default();
+        analytics.peak_concurrent_viewers = analytics.peak_concurrent_viewers.max(viewer_count);
+    }
+
+    /// 结束闲置超过 HLS_SESSION_TIMEOUT 的 HLS 会话，离开时间取最后一次请求的时间
+    pub async fn expire_hls_sessions(&self, now: chrono::DateTime<chrono::Utc>) {
+        let timeout = chrono::Duration::from_std(HLS_SESSION_TIMEOUT).unwrap_or_default();
+        let mut expired = Vec::new();
+        let mut hls_sessions = self.hls_sessions.write().await;
+        hls_sessions.retain(|(stream_key, _, _), hls_session| {
+            if now.signed_duration_since(hls_session.last_seen) < timeout {
+                return true;
+            }
+            expired.push((stream_key.clone(), hls_session.viewer_id, hls_session.last_seen));
+            false
+        });
+        let mut hls_viewer_counts: HashMap<String, u32> = HashMap::new();
+        for (stream_key, _, _) in &expired {
+            hls_viewer_counts.entry(stream_key.clone())
+                .or_insert_with(|| count_hls_sessions(&hls_sessions, stream_key));
+        }
+        drop(hls_sessions);
+
+        for (stream_key, hls_viewer_count) in hls_viewer_counts {
+            self.update_hls_viewer_count(&stream_key, hls_viewer_count).await;
+        }
+
+        for (stream_key, viewer_id, last_seen) in expired {
+            self.end_session(stream_key, viewer_id, last_seen).await;
+        }
+    }
+
+    /// 该客户端地址和 User-Agent 是否已有进行中的 HLS 会话 (再次请求不会开始新的会话)
+    pub async fn has_hls_session(&self, stream_key: &str, remote_addr: std::net::SocketAddr, user_agent: Option<&str>) -> bool {
+        let key = (stream_key.to_string(), remote_addr.ip(), user_agent.unwrap_or_default().to_string());
+        self.hls_sessions.read().await.contains_key(&key)
+    }
+
+    /// HLS 会话数同步到流的观看者数量
+    async fn update_hls_viewer_count(&self, stream_key: &str, hls_viewer_count: u32) {
+        if let Some(stream) = self.stream_manager.get_stream(stream_key).await {
+            stream.set_hls_viewer_count(hls_viewer_count).await;
+        }
+    }
+
+    async fn handle_event(&self, event: StreamEvent) {
+        match event {
+            StreamEvent::ViewerJoined { stream_key, viewer, viewer_count } => {
+                debug!("Viewer {} joined stream {}", viewer.id, stream_key);
+
+                self.active_sessions.write().await.insert(viewer.id, viewer);
+
+                let mut streams = self.streams.write().await;
+                let analytics = streams.entry(stream_key).or_default();
+                analytics.peak_concurrent_viewers = analytics.peak_concurrent_viewers.max(viewer_count);
+            }
+            StreamEvent::ViewerLeft { stream_key, viewer_id, .. } => {
+                self.end_session(stream_key, viewer_id, chrono::Utc::now()).await;
+            }
+            _ => {}
+        }
+    }
+
+    /// 结束观看会话，记入流的历史会话
+    async fn end_session(&self, stream_key: String, viewer_id: Uuid, left_at: chrono::DateTime<chrono::Utc>) {
+        let viewer = match self.active_sessions.write().await.remove(&viewer_id) {
+            Some(viewer) => viewer,
+            None => return,
+        };
+
+        let session = ViewerSession {
+            viewer_id,
+            stream_key: stream_key.clone(),
+            protocol: viewer.protocol,
+            remote_addr: viewer.remote_addr,
+            user_agent: viewer.user_agent,
+            joined_at: viewer.connected_at,
+            left_at,
+            watched_seconds: left_at.signed_duration_since(viewer.connected_at).num_seconds(),
+        };
+
+        debug!("Viewer {} left stream {} after {}s", viewer_id, stream_key, session.watched_seconds);
+
+        let mut streams = self.streams.write().await;
+        let analytics = streams.entry(stream_key).or_default();
+        analytics.total_sessions += 1;
+        analytics.total_watch_seconds += session.watched_seconds;
+        analytics.sessions.push_back(session);
+
+        // 保持历史会话数量
+        while analytics.sessions.len() > self.config.max_sessions_per_stream {
+            analytics.sessions.pop_front();
+        }
+    }
+
+    /// 获取流的观看汇总指标
+    pub async fn get_summary(&self, stream_key: &str) -> Option<AnalyticsSummary> {
+        let active_viewers = self.active_sessions.read().await.values()
+            .filter(|viewer| viewer.stream_key == stream_key)
+            .count() as u32;
+
+        let streams = self.streams.read().await;
+        let analytics = streams.get(stream_key)?;
+
+        let average_watch_seconds = if analytics.total_sessions > 0 {
+            analytics.total_watch_seconds as f64 / analytics.total_sessions as f64
+        } else {
+            0.0
+        };
+
+        Some(AnalyticsSummary {
+            stream_key: stream_key.to_string(),
+            active_viewers,
+            peak_concurrent_viewers: analytics.peak_concurrent_viewers,
+            total_sessions: analytics.total_sessions,
+            average_watch_seconds,
+            total_watch_seconds: analytics.total_watch_seconds,
+        })
+    }
+
+    /// 获取流的历史观看会话
+    pub async fn get_sessions(&self, stream_key: &str) -> Vec<ViewerSession> {
+        let streams = self.streams.read().await;
+        streams.get(stream_key)
+            .map(|analytics| analytics.sessions.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 将观看会话导出为 CSV
+    pub fn sessions_to_csv(sessions: &[ViewerSession]) -> String {
+        let mut csv = String::from("viewer_id,stream_key,protocol,remote_addr,user_agent,joined_at,left_at,watched_seconds\n");
+
+        for session in sessions {
+            csv.push_str(&format!(
+                "{},{},{:?},{},{},{},{},{}\n",
+                session.viewer_id,
+                escape_csv(&session.stream_key),
+                session.protocol,
+                session.remote_addr,
+                escape_csv(session.user_agent.as_deref().unwrap_or("")),
+                session.joined_at.to_rfc3339(),
+                session.left_at.to_rfc3339(),
+                session.watched_seconds,
+            ));
+        }
+
+        csv
+    }
+}
+
+/// 转义 CSV 字段
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// 流正在进行的 HLS 会话数
+fn count_hls_sessions(hls_sessions: &HashMap<(String, IpAddr, String), HlsSession>, stream_key: &str) -> u32 {
+    hls_sessions.keys().filter(|(key_stream, _, _)| key_stream == stream_key).count() as u32
+}
@@ -1,12 +1,146 @@
-use std::collections::HashSet;
-use tracing::{info, debug};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use tokio::sync::RwLock;
+use tracing::{info, debug, warn};
 
-use game_stream_common::AuthConfig;
+use game_stream_common::{AuthConfig, StreamVisibility};
+
+/// 邀请的最长有效期 (秒)
+const MAX_INVITE_TTL: u64 = 365 * 24 * 3600;
+
+/// 每个私有流在 PASSWORD_FAILURE_WINDOW 内最多做这么多次失败的密码验证，超过后不再计算慢哈希直接拒绝
+const MAX_PASSWORD_FAILURES: usize = 10;
+
+/// 统计密码验证失败次数的时间窗口
+const PASSWORD_FAILURE_WINDOW: Duration = Duration::from_secs(60);
+
+/// 每个私有流记住的验证失败的密码摘要数量上限，超过后清空
+const MAX_REJECTED_DIGESTS: usize = 1024;
 
 /// 认证管理器
 pub struct AuthManager {
     config: AuthConfig,
     valid_stream_keys: HashSet<String>,
+    validator: Option<Arc<dyn StreamKeyValidator>>,
+    access: RwLock<HashMap<String, StreamAccess>>, // 按流密钥保存，重新推流后仍然有效
+}
+
+/// 嵌入服务器的程序自定义的推流认证 (如查询自己的用户数据库)，设置后代替 valid_stream_keys；
+/// [auth] 未开启时不调用
+#[async_trait]
+pub trait StreamKeyValidator: Send + Sync {
+    async fn validate(&self, stream_key: &str) -> bool;
+}
+
+/// 单个流的观看权限
+#[derive(Default)]
+struct StreamAccess {
+    visibility: StreamVisibility,
+    password_hash: Option<String>, // Argon2id 的 PHC 字符串 (含随机盐)
+    verified: HashSet<[u8; 32]>, // 验证通过的密码摘要，HLS 每个片段请求都带密码，避免每次都做慢哈希
+    rejected: HashSet<[u8; 32]>, // 验证失败的密码摘要，同一个错误密码反复请求时不再计算慢哈希
+    failures: VecDeque<Instant>, // 最近的验证失败时间
+    pending: usize, // 正在计算的验证，计入失败次数上限，避免并发请求同时绕过
+    invites: HashMap<String, Invite>,
+}
+
+/// 私有流的邀请，凭 token 观看
+#[derive(Debug, Clone, Serialize)]
+pub struct Invite {
+    pub token: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// 流的观看权限 (不含密码)
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamAccessInfo {
+    pub stream_key: String,
+    pub visibility: StreamVisibility,
+    pub password_protected: bool,
+    pub invites: Vec<Invite>,
+}
+
+/// 修改观看权限 (PUT /api/streams/:key/access)，未给出的字段不变
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StreamAccessUpdate {
+    pub visibility: Option<StreamVisibility>,
+    pub password: Option<String>, // 空字符串清除
+}
+
+impl Invite {
+    fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= chrono::Utc::now())
+    }
+}
+
+impl StreamAccess {
+    fn info(&self, stream_key: &str) -> StreamAccessInfo {
+        let mut invites: Vec<Invite> = self.invites.values()
+            .filter(|invite| !invite.is_expired())
+            .cloned()
+            .collect();
+        invites.sort_by_key(|invite| invite.created_at);
+
+        StreamAccessInfo {
+            stream_key: stream_key.to_string(),
+            visibility: self.visibility,
+            password_protected: self.password_hash.is_some(),
+            invites,
+        }
+    }
+
+    fn set_password(&mut self, password: Option<&str>) {
+        self.password_hash = password.filter(|password| !password.is_empty()).map(hash_password);
+        self.verified.clear();
+        self.rejected.clear();
+        self.failures.clear();
+    }
+
+    /// 预留一次密码验证，最近失败和正在进行的验证达到上限时返回 false
+    fn reserve_password_attempt(&mut self) -> bool {
+        while self.failures.front().is_some_and(|failed_at| failed_at.elapsed() >= PASSWORD_FAILURE_WINDOW) {
+            self.failures.pop_front();
+        }
+        if self.failures.len() + self.pending >= MAX_PASSWORD_FAILURES {
+            return false;
+        }
+        self.pending += 1;
+        true
+    }
+
+    /// 记住验证结果的摘要，失败时计入失败次数
+    fn record_password_attempt(&mut self, digest: [u8; 32], allowed: bool) {
+        if allowed {
+            self.verified.insert(digest);
+            return;
+        }
+        self.failures.push_back(Instant::now());
+        if self.rejected.len() >= MAX_REJECTED_DIGESTS {
+            self.rejected.clear();
+        }
+        self.rejected.insert(digest);
+    }
+}
+
+/// 加盐的 Argon2id 哈希
+fn hash_password(password: &str) -> String {
+    let salt = SaltString::encode_b64(&rand::random::<[u8; 16]>()).expect("16-byte salt is valid");
+    Argon2::default().hash_password(password.as_bytes(), &salt)
+        .expect("Argon2 with default parameters accepts any password")
+        .to_string()
+}
+
+fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .is_ok_and(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
 }
 
 impl AuthManager {
@@ -14,13 +148,31 @@ impl AuthManager {
         info!("Initializing auth manager...");
         
         let valid_stream_keys = config.valid_stream_keys.iter().cloned().collect();
+        let access = config.streams.iter()
+            .map(|(stream_key, stream)| {
+                let mut access = StreamAccess { visibility: stream.visibility, ..Default::default() };
+                access.set_password(stream.password.as_deref());
+                (stream_key.clone(), access)
+            })
+            .collect();
+        if config.admin_token.as_deref().is_none_or(str::is_empty) {
+            warn!("No [auth] admin_token configured, stream access and admin APIs are disabled");
+        }
         
         Self {
             config: config.clone(),
             valid_stream_keys,
+            validator: None,
+            access: RwLock::new(access),
         }
     }
     
+    /// 使用自定义的推流认证
+    pub fn with_validator(mut self, validator: Arc<dyn StreamKeyValidator>) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+    
     /// 验证流密钥
     pub async fn validate_stream_key(&self, stream_key: &str) -> bool {
         if !self.config.enabled {
@@ -28,7 +180,10 @@ impl AuthManager {
             return true;
         }
         
-        let is_valid = self.valid_stream_keys.contains(stream_key);
+        let is_valid = match &self.validator {
+            Some(validator) => validator.validate(stream_key).await,
+            None => self.valid_stream_keys.contains(stream_key),
+        };
         
         if is_valid {
             debug!("Stream key validated: {}", stream_key);
@@ -39,10 +194,123 @@ impl AuthManager {
         is_valid
     }
     
-    /// 验证观看者权限
-    pub async fn validate_viewer(&self, stream_key: &str, _viewer_token: Option<&str>) -> bool {
-        // 简单实现：如果流存在且有效，则允许观看
-        self.validate_stream_key(stream_key).await
+    /// 验证管理接口的令牌 (常量时间比较)，未配置 admin_token 时总是拒绝
+    pub fn validate_admin(&self, token: Option<&str>) -> bool {
+        match (self.config.admin_token.as_deref().filter(|admin_token| !admin_token.is_empty()), token) {
+            (Some(admin_token), Some(token)) => {
+                let expected = Sha256::digest(admin_token.as_bytes());
+                bool::from(expected.ct_eq(&Sha256::digest(token.as_bytes())))
+            }
+            _ => false,
+        }
+    }
+    
+    /// 验证观看者权限：私有流需要观看密码或未过期的邀请 token
+    pub async fn validate_viewer(&self, stream_key: &str, access: Option<&str>) -> bool {
+        let access = access.filter(|access| !access.is_empty());
+        let digest: Option<[u8; 32]> = access.map(|access| Sha256::digest(access.as_bytes()).into());
+        let password_hash = {
+            let access_map = self.access.read().await;
+            let Some(stream) = access_map.get(stream_key).filter(|stream| stream.visibility == StreamVisibility::Private) else {
+                return true;
+            };
+            let (Some(access), Some(digest)) = (access, digest) else {
+                return false;
+            };
+            if stream.invites.get(access).is_some_and(|invite| !invite.is_expired()) || stream.verified.contains(&digest) {
+                return true;
+            }
+            if stream.rejected.contains(&digest) {
+                debug!("Rejected viewer access to private stream: {}", stream_key);
+                return false;
+            }
+            stream.password_hash.clone()
+        };
+        let (Some(hash), Some(access), Some(digest)) = (password_hash.clone(), access, digest) else {
+            debug!("Rejected viewer access to private stream: {}", stream_key);
+            return false;
+        };
+        
+        // 限制失败的慢哈希次数，避免用随机密码耗尽 CPU
+        let reserved = self.access.write().await.get_mut(stream_key)
+            .filter(|stream| stream.password_hash == password_hash)
+            .is_some_and(StreamAccess::reserve_password_attempt);
+        if !reserved {
+            debug!("Too many failed password attempts for private stream {}, rejecting without verifying", stream_key);
+            return false;
+        }
+        
+        // 慢哈希在锁外的阻塞线程中计算，记住验证结果的摘要 (期间密码被修改则不记)
+        let access = access.to_string();
+        let allowed = tokio::task::spawn_blocking(move || verify_password(&access, &hash)).await.unwrap_or(false);
+        if let Some(stream) = self.access.write().await.get_mut(stream_key) {
+            stream.pending = stream.pending.saturating_sub(1);
+            if stream.password_hash == password_hash {
+                stream.record_password_attempt(digest, allowed);
+            }
+        }
+        if !allowed {
+            debug!("Rejected viewer access to private stream: {}", stream_key);
+        }
+        allowed
+    }
+    
+    /// 是否出现在流列表中
+    pub async fn is_listed(&self, stream_key: &str) -> bool {
+        self.access.read().await.get(stream_key)
+            .is_none_or(|stream| stream.visibility == StreamVisibility::Public)
+    }
+    
+    /// 获取流的观看权限
+    pub async fn get_access(&self, stream_key: &str) -> StreamAccessInfo {
+        match self.access.read().await.get(stream_key) {
+            Some(stream) => stream.info(stream_key),
+            None => StreamAccess::default().info(stream_key),
+        }
+    }
+    
+    /// 修改流的可见性和观看密码
+    pub async fn update_access(&self, stream_key: &str, update: StreamAccessUpdate) -> StreamAccessInfo {
+        let mut access_map = self.access.write().await;
+        let stream = access_map.entry(stream_key.to_string()).or_default();
+        
+        if let Some(visibility) = update.visibility {
+            stream.visibility = visibility;
+        }
+        if let Some(password) = update.password {
+            stream.set_password(Some(&password));
+        }
+        
+        info!("Updated access for stream {}: {:?}, password {}", stream_key, stream.visibility,
+              if stream.password_hash.is_some() { "set" } else { "not set" });
+        stream.info(stream_key)
+    }
+    
+    /// 为流创建邀请，ttl 为有效期 (秒)，不指定时一直有效直到撤销
+    pub async fn create_invite(&self, stream_key: &str, ttl: Option<u64>) -> Invite {
+        let now = chrono::Utc::now();
+        let invite = Invite {
+            token: hex::encode(rand::random::<[u8; 16]>()),
+            created_at: now,
+            expires_at: ttl.map(|ttl| now + chrono::Duration::seconds(ttl.min(MAX_INVITE_TTL) as i64)),
+        };
+        
+        let mut access_map = self.access.write().await;
+        let stream = access_map.entry(stream_key.to_string()).or_default();
+        stream.invites.retain(|_, invite| !invite.is_expired());
+        stream.invites.insert(invite.token.clone(), invite.clone());
+        
+        info!("Created invite for stream {}", stream_key);
+        invite
+    }
+    
+    /// 撤销邀请，已经在观看的观看者不受影响
+    pub async fn revoke_invite(&self, stream_key: &str, token: &str) -> Option<Invite> {
+        let revoked = self.access.write().await.get_mut(stream_key)?.invites.remove(token);
+        if revoked.is_some() {
+            info!("Revoked invite for stream {}", stream_key);
+        }
+        revoked
     }
     
     /// 添加有效的流密钥
@@ -0,0 +1,263 @@
+use std::sync::Arc;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Component, Path, PathBuf};
+use serde::Serialize;
+use tokio::fs::{self, File};
+use tokio::io::BufWriter;
+use tokio::sync::{RwLock, broadcast};
+use tokio::task::JoinHandle;
+use tracing::{info, debug, warn};
+use uuid::Uuid;
+
+use game_stream_common::{
+    ClipConfig, RecordingFormat, StreamManager, StreamEvent, StreamStatus, MediaPacket, MediaReceiver,
+    StreamResult, StreamError, recv_media
+};
+use crate::recording::{FlvWriter, remux_to_mp4};
+use crate::segment_store::is_safe_path_component;
+
+/// 即时剪辑管理器 - 为每路直播流保留最近一段内容，按需截取为独立的 MP4 文件
+pub struct ClipManager {
+    config: ClipConfig,
+    stream_manager: Arc<StreamManager>,
+    buffers: RwLock<HashMap<String, StreamBuffer>>,
+}
+
+struct StreamBuffer {
+    buffer: Arc<RwLock<ClipBuffer>>,
+    task: JoinHandle<()>,
+}
+
+/// 最近内容的环形缓冲区
+#[derive(Default)]
+struct ClipBuffer {
+    metadata: Option<Arc<MediaPacket>>,
+    video_header: Option<Arc<MediaPacket>>,
+    audio_header: Option<Arc<MediaPacket>>,
+    packets: VecDeque<Arc<MediaPacket>>,
+}
+
+/// 剪辑结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ClipInfo {
+    pub stream_key: String,
+    pub file: String, // 相对剪辑目录的路径
+    pub url: String,
+    pub format: RecordingFormat,
+    pub duration_ms: u64,
+    pub size_bytes: u64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ClipManager {
+    pub fn new(config: &ClipConfig, stream_manager: Arc<StreamManager>) -> Self {
+        info!("Initializing clip manager...");
+
+        Self {
+            config: config.clone(),
+            stream_manager,
+            buffers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 订阅事件总线，为直播中的流维护缓冲区
+    pub async fn start(self: Arc<Self>) {
+        if !self.config.enabled {
+            return;
+        }
+
+        let mut events = self.stream_manager.subscribe_events();
+
+        // 启动前已经在直播的流
+        for (stream_key, stream) in self.stream_manager.list_streams().await {
+            if matches!(stream.get_status().await, StreamStatus::Live) {
+                self.start_buffering(stream_key, stream.subscribe_media()).await;
+            }
+        }
+
+        loop {
+            match events.recv().await {
+                Ok(StreamEvent::StatusChanged { stream_key, status }) => match status {
+                    StreamStatus::Live => {
+                        if let Some(stream) = self.stream_manager.get_stream(&stream_key).await {
+                            self.start_buffering(stream_key, stream.subscribe_media()).await;
+                        }
+                    }
+                    StreamStatus::Stopped | StreamStatus::Error(_) => self.stop_buffering(&stream_key).await,
+                    _ => {}
+                },
+                Ok(StreamEvent::StreamRemoved { stream_key }) => self.stop_buffering(&stream_key).await,
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Clip manager lagged behind event bus, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+
+    /// 截取最近 `duration` 秒 (未指定时使用默认时长，最多为缓冲时长)
+    pub async fn create_clip(&self, stream_key: &str, duration: Option<u64>) -> StreamResult<ClipInfo> {
+        if !self.config.enabled {
+            return Err(StreamError::Config("Clips are disabled".to_string()));
+        }
+        if !is_safe_path_component(stream_key) {
+            return Err(StreamError::StreamNotFound(stream_key.to_string()));
+        }
+
+        let duration = duration
+            .unwrap_or(self.config.default_duration)
+            .clamp(1, self.config.buffer_duration.max(1));
+
+        let buffer = self.buffers.read().await.get(stream_key)
+            .map(|buffer| buffer.buffer.clone())
+            .ok_or_else(|| StreamError::StreamNotFound(stream_key.to_string()))?;
+        let packets = buffer.read().await.snapshot(duration * 1000);
+
+        if !packets.iter().any(|packet| packet.timestamp().is_some()) {
+            return Err(StreamError::Internal(format!("No buffered media for stream {}", stream_key)));
+        }
+
+        let created_at = chrono::Utc::now();
+        let name = format!(
+            "{}_{}_{}",
+            stream_key,
+            created_at.format("%Y%m%d_%H%M%S"),
+            &Uuid::new_v4().simple().to_string()[..8],
+        );
+        let flv_path = PathBuf::from(&self.config.dir).join(stream_key).join(format!("{}.flv", name));
+        if let Some(parent) = flv_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let mut writer = FlvWriter::new(BufWriter::new(File::create(&flv_path).await?));
+        // 序列头的时间戳可能远早于剪辑起点，以第一个普通数据包为零点
+        if let Some(base) = packets.iter().filter(|packet| !packet.is_sequence_header()).find_map(|packet| packet.timestamp()) {
+            writer.set_base_timestamp(base);
+        }
+        writer.write_header().await?;
+        for packet in &packets {
+            writer.write_packet(packet).await?;
+        }
+        let duration_ms = writer.duration_ms();
+        writer.finish().await?;
+
+        let mp4_path = flv_path.with_extension("mp4");
+        let (path, format) = match remux_to_mp4(&self.config.ffmpeg_path, &flv_path, &mp4_path).await {
+            Ok(()) => {
+                let _ = fs::remove_file(&flv_path).await;
+                (mp4_path, RecordingFormat::Mp4)
+            }
+            Err(e) => {
+                warn!("Failed to remux clip {} to MP4, keeping FLV: {}", flv_path.display(), e);
+                (flv_path, RecordingFormat::Flv)
+            }
+        };
+
+        let size_bytes = fs::metadata(&path).await?.len();
+        let file = format!("{}/{}.{}", stream_key, name, format.extension());
+
+        info!("Created {} ms clip of stream {}: {}", duration_ms, stream_key, path.display());
+
+        Ok(ClipInfo {
+            stream_key: stream_key.to_string(),
+            url: format!("/clips/{}", file),
+            file,
+            format,
+            duration_ms,
+            size_bytes,
+            created_at,
+        })
+    }
+
+    /// 获取剪辑文件路径，拒绝跳出剪辑目录的路径
+    pub async fn clip_path(&self, file: &str) -> StreamResult<PathBuf> {
+        let relative = Path::new(file);
+        let is_safe = !file.is_empty()
+            && relative.components().all(|component| matches!(component, Component::Normal(_)));
+
+        let path = PathBuf::from(&self.config.dir).join(relative);
+        if !is_safe || !fs::try_exists(&path).await? {
+            return Err(StreamError::StreamNotFound(file.to_string()));
+        }
+
+        Ok(path)
+    }
+
+    /// 启动缓冲任务 (同名流重新推流时替换旧缓冲区)
+    async fn start_buffering(&self, stream_key: String, media_receiver: MediaReceiver) {
+        let buffer = Arc::new(RwLock::new(ClipBuffer::default()));
+        let window_ms = self.config.buffer_duration.max(1) * 1000;
+        let task = tokio::spawn(run_buffer(buffer.clone(), media_receiver, window_ms));
+
+        if let Some(previous) = self.buffers.write().await.insert(stream_key, StreamBuffer { buffer, task }) {
+            previous.task.abort();
+        }
+    }
+
+    async fn stop_buffering(&self, stream_key: &str) {
+        if let Some(previous) = self.buffers.write().await.remove(stream_key) {
+            debug!("Stopping clip buffer for stream: {}", stream_key);
+            previous.task.abort();
+        }
+    }
+}
+
+/// 缓冲任务：持续写入最近的数据包，丢弃超出窗口的内容
+async fn run_buffer(buffer: Arc<RwLock<ClipBuffer>>, mut media_receiver: MediaReceiver, window_ms: u64) {
+    while let Some(packet) = recv_media(&mut media_receiver).await {
+        buffer.write().await.push(packet, window_ms);
+    }
+}
+
+impl ClipBuffer {
+    fn push(&mut self, packet: Arc<MediaPacket>, window_ms: u64) {
+        // 解码器配置和元数据单独保存，每个剪辑都需要
+        if packet.is_sequence_header() {
+            match packet.as_ref() {
+                MediaPacket::Video { .. } => self.video_header = Some(packet),
+                _ => self.audio_header = Some(packet),
+            }
+            return;
+        }
+
+        let Some(timestamp) = packet.timestamp() else {
+            self.metadata = Some(packet);
+            return;
+        };
+
+        self.packets.push_back(packet);
+        while self.packets.front()
+            .and_then(|front| front.timestamp())
+            .is_some_and(|front| timestamp.saturating_sub(front) > window_ms)
+        {
+            self.packets.pop_front();
+        }
+    }
+
+    /// 取出最近 `duration_ms` 的内容，从不晚于起点的最后一个关键帧开始，保证可以独立解码
+    fn snapshot(&self, duration_ms: u64) -> Vec<Arc<MediaPacket>> {
+        let Some(newest) = self.packets.back().and_then(|packet| packet.timestamp()) else {
+            return Vec::new();
+        };
+        let cutoff = newest.saturating_sub(duration_ms);
+
+        let is_keyframe = |packet: &Arc<MediaPacket>| matches!(packet.as_ref(), MediaPacket::Video { is_keyframe: true, .. });
+        let start = self.packets.iter()
+            .rposition(|packet| is_keyframe(packet) && packet.timestamp().is_some_and(|ts| ts <= cutoff))
+            .or_else(|| self.packets.iter().position(is_keyframe))
+            .unwrap_or_else(|| {
+                // 纯音频流没有关键帧
+                self.packets.iter()
+                    .position(|packet| packet.timestamp().is_some_and(|ts| ts >= cutoff))
+                    .unwrap_or(0)
+            });
+
+        self.metadata.iter()
+            .chain(self.video_header.iter())
+            .chain(self.audio_header.iter())
+            .chain(self.packets.range(start..))
+            .cloned()
+            .collect()
+    }
+}
@@ -0,0 +1,420 @@
+use anyhow::Result;
+use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+#[cfg(feature = "cluster")]
+use redis::AsyncCommands;
+#[cfg(feature = "cluster")]
+use redis::aio::ConnectionManager;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::sync::{RwLock, broadcast::error::RecvError};
+use tracing::{info, debug, warn};
+use uuid::Uuid;
+
+use game_stream_common::{
+    ClusterConfig, StreamManager, StreamEvent, StreamInfo, StreamStatus, StreamResult, StreamError
+};
+use crate::overload::OverloadGuard;
+
+/// 集群流目录 - 各实例通过 Redis 登记本地的直播流和观看人数
+pub struct ClusterDirectory {
+    config: ClusterConfig,
+    instance_id: String,
+    stream_manager: Arc<StreamManager>,
+    overload_guard: Arc<OverloadGuard>,
+    store: Option<RedisStore>,
+    // 从其他实例中继过来的流，不作为源站登记
+    relayed: RwLock<HashSet<String>>,
+}
+
+/// 目录条目 - 某个实例上的一路流
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryEntry {
+    pub instance_id: String,
+    pub origin_url: String,
+    pub relayed: bool,
+    pub status: StreamStatus,
+    pub info: StreamInfo,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// 实例负载 - 用于为观看者选择负载最低的节点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceLoad {
+    pub instance_id: String,
+    pub public_url: String,
+    pub accept_viewers: bool,
+    pub stream_count: u32,
+    pub viewer_count: u32,
+    pub max_viewers: u32,
+    pub cpu_percent: f32,
+    pub memory_percent: f32,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl InstanceLoad {
+    /// 负载评分 (0-1)，取观看人数、CPU、内存中最紧张的一项
+    pub fn score(&self) -> f32 {
+        let viewers = if self.max_viewers > 0 {
+            self.viewer_count as f32 / self.max_viewers as f32
+        } else {
+            0.0
+        };
+        viewers.max(self.cpu_percent / 100.0).max(self.memory_percent / 100.0)
+    }
+}
+
+impl ClusterDirectory {
+    pub fn new(
+        config: &ClusterConfig,
+        stream_manager: Arc<StreamManager>,
+        overload_guard: Arc<OverloadGuard>,
+    ) -> Result<Self> {
+        info!("Initializing cluster directory...");
+
+        let store = if config.enabled {
+            Some(RedisStore::open(&config.redis_url)?)
+        } else {
+            None
+        };
+
+        let instance_id = config.instance_id.clone()
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+        Ok(Self {
+            config: config.clone(),
+            instance_id,
+            stream_manager,
+            overload_guard,
+            store,
+            relayed: RwLock::new(HashSet::new()),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// 标记从其他实例中继过来的流
+    pub async fn mark_relayed(&self, stream_key: &str) {
+        self.relayed.write().await.insert(stream_key.to_string());
+    }
+
+    /// 定期刷新本实例的目录条目，并在流结束时立即注销
+    pub async fn start(self: Arc<Self>) {
+        if !self.config.enabled {
+            return;
+        }
+
+        info!("Cluster directory started, instance id: {}", self.instance_id);
+
+        let mut events = self.stream_manager.subscribe_events();
+        let mut heartbeat = tokio::time::interval(Duration::from_secs(self.config.heartbeat_interval.max(1)));
+
+        loop {
+            tokio::select! {
+                _ = heartbeat.tick() => {
+                    if let Err(e) = self.register_all().await {
+                        warn!("Failed to refresh cluster directory: {}", e);
+                    }
+                    if let Err(e) = self.report_load().await {
+                        warn!("Failed to report instance load: {}", e);
+                    }
+                }
+                event = events.recv() => {
+                    match event {
+                        Ok(StreamEvent::StreamCreated { stream_key }) => {
+                            if let Err(e) = self.register(&stream_key).await {
+                                warn!("Failed to register stream {} in cluster directory: {}", stream_key, e);
+                            }
+                        }
+                        Ok(StreamEvent::StreamRemoved { stream_key }) => {
+                            self.relayed.write().await.remove(&stream_key);
+                            if let Err(e) = self.unregister(&stream_key).await {
+                                warn!("Failed to unregister stream {} from cluster directory: {}", stream_key, e);
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(RecvError::Lagged(skipped)) => {
+                            warn!("Cluster directory lagged behind event bus, skipped {} events", skipped);
+                        }
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            }
+        }
+    }
+
+    /// 列出集群中的所有目录条目
+    pub async fn list_entries(&self) -> StreamResult<Vec<DirectoryEntry>> {
+        self.scan_values(&format!("{}:streams:*", self.config.key_prefix)).await
+    }
+
+    /// 检查集群中是否有实例持有该流
+    pub async fn has_stream(&self, stream_key: &str) -> StreamResult<bool> {
+        if self.stream_manager.get_stream(stream_key).await.is_some() {
+            return Ok(true);
+        }
+        if !self.config.enabled {
+            return Ok(false);
+        }
+
+        let pattern = format!("{}:streams:{}:*", self.config.key_prefix, stream_key);
+        let entries: Vec<DirectoryEntry> = self.scan_values(&pattern).await?;
+        Ok(entries.iter().any(|entry| entry.info.stream_key == stream_key))
+    }
+
+    /// 选择负载最低的可接受观看者的实例，单机模式下返回本实例
+    pub async fn select_instance(&self) -> StreamResult<InstanceLoad> {
+        let local = self.local_load().await;
+        if !self.config.enabled {
+            return Ok(local);
+        }
+
+        let mut instances: Vec<InstanceLoad> = self.scan_values(&format!("{}:instances:*", self.config.key_prefix)).await?;
+        if !instances.iter().any(|instance| instance.instance_id == self.instance_id) {
+            instances.push(local.clone());
+        }
+
+        Ok(instances.into_iter()
+            .filter(|instance| instance.accept_viewers && instance.score() < 1.0)
+            .min_by(|a, b| a.score().total_cmp(&b.score()))
+            .unwrap_or(local))
+    }
+
+    /// 合并集群中所有实例的流信息，观看人数按实例累加
+    pub async fn cluster_streams(&self, local: Vec<StreamInfo>) -> StreamResult<Vec<StreamInfo>> {
+        let mut streams: HashMap<String, StreamInfo> = local.into_iter()
+            .map(|info| (info.stream_key.clone(), info))
+            .collect();
+
+        for entry in self.list_entries().await? {
+            if entry.instance_id == self.instance_id {
+                continue;
+            }
+
+            match streams.get_mut(&entry.info.stream_key) {
+                Some(info) => info.viewer_count += entry.info.viewer_count,
+                None => {
+                    streams.insert(entry.info.stream_key.clone(), entry.info);
+                }
+            }
+        }
+
+        Ok(streams.into_values().collect())
+    }
+
+    /// 查找持有该流的源站地址 (排除本实例和中继副本)
+    pub async fn find_origin(&self, stream_key: &str) -> Option<String> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        let pattern = format!("{}:streams:{}:*", self.config.key_prefix, stream_key);
+        let entries: Vec<DirectoryEntry> = match self.scan_values(&pattern).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Failed to look up origin for stream {}: {}", stream_key, e);
+                return None;
+            }
+        };
+
+        entries.into_iter()
+            .find(|entry| {
+                entry.info.stream_key == stream_key
+                    && !entry.relayed
+                    && entry.instance_id != self.instance_id
+            })
+            .map(|entry| entry.origin_url)
+    }
+
+    async fn register_all(&self) -> StreamResult<()> {
+        for (stream_key, _) in self.stream_manager.list_streams().await {
+            self.register(&stream_key).await?;
+        }
+        Ok(())
+    }
+
+    async fn register(&self, stream_key: &str) -> StreamResult<()> {
+        let Some(stream) = self.stream_manager.get_stream(stream_key).await else {
+            return Ok(());
+        };
+
+        let mut info = stream.get_info().await;
+        info.viewer_count = stream.get_viewer_count().await;
+
+        let entry = DirectoryEntry {
+            instance_id: self.instance_id.clone(),
+            origin_url: self.config.advertise_url.clone(),
+            relayed: self.relayed.read().await.contains(stream_key),
+            status: stream.get_status().await,
+            info,
+            updated_at: chrono::Utc::now(),
+        };
+
+        let value = serde_json::to_string(&entry)?;
+        self.store()?.set_ex(self.entry_key(stream_key), value, self.config.entry_ttl.max(1)).await?;
+
+        debug!("Registered stream {} in cluster directory", stream_key);
+        Ok(())
+    }
+
+    /// 上报本实例负载
+    async fn report_load(&self) -> StreamResult<()> {
+        let load = serde_json::to_string(&self.local_load().await)?;
+        let key = format!("{}:instances:{}", self.config.key_prefix, self.instance_id);
+
+        self.store()?.set_ex(key, load, self.config.entry_ttl.max(1)).await?;
+
+        Ok(())
+    }
+
+    async fn local_load(&self) -> InstanceLoad {
+        let streams = self.stream_manager.list_streams().await;
+        let mut viewer_count = 0;
+        for (_, stream) in &streams {
+            viewer_count += stream.get_viewer_count().await;
+        }
+
+        let (cpu_percent, memory_percent) = self.overload_guard.resource_usage();
+
+        InstanceLoad {
+            instance_id: self.instance_id.clone(),
+            public_url: self.config.public_url.trim_end_matches('/').to_string(),
+            accept_viewers: self.config.accept_viewers,
+            stream_count: streams.len() as u32,
+            viewer_count,
+            max_viewers: self.overload_guard.max_viewers(),
+            cpu_percent,
+            memory_percent,
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    async fn unregister(&self, stream_key: &str) -> StreamResult<()> {
+        self.store()?.del(self.entry_key(stream_key)).await?;
+
+        debug!("Unregistered stream {} from cluster directory", stream_key);
+        Ok(())
+    }
+
+    async fn scan_values<T: DeserializeOwned>(&self, pattern: &str) -> StreamResult<Vec<T>> {
+        self.store()?.scan_values(pattern).await
+    }
+
+    fn store(&self) -> StreamResult<&RedisStore> {
+        self.store.as_ref()
+            .ok_or_else(|| StreamError::Config("Cluster directory is disabled".to_string()))
+    }
+
+    fn entry_key(&self, stream_key: &str) -> String {
+        format!("{}:streams:{}:{}", self.config.key_prefix, stream_key, self.instance_id)
+    }
+}
+
+/// 目录所在的 Redis，首次使用时建立连接 (断线由 ConnectionManager 自动重连)
+#[cfg(feature = "cluster")]
+struct RedisStore {
+    url: String,
+    client: redis::Client,
+    connection: RwLock<Option<ConnectionManager>>,
+}
+
+#[cfg(feature = "cluster")]
+impl RedisStore {
+    fn open(url: &str) -> Result<Self> {
+        Ok(Self {
+            url: url.to_string(),
+            client: redis::Client::open(url)?,
+            connection: RwLock::new(None),
+        })
+    }
+
+    async fn set_ex(&self, key: String, value: String, ttl: u64) -> StreamResult<()> {
+        self.connection().await?.set_ex::<_, _, ()>(key, value, ttl).await
+            .map_err(redis_error)
+    }
+
+    async fn del(&self, key: String) -> StreamResult<()> {
+        self.connection().await?.del::<_, ()>(key).await
+            .map_err(redis_error)
+    }
+
+    async fn scan_values<T: DeserializeOwned>(&self, pattern: &str) -> StreamResult<Vec<T>> {
+        let mut connection = self.connection().await?;
+
+        let keys: Vec<String> = {
+            let mut iter = connection.scan_match::<_, String>(pattern).await
+                .map_err(redis_error)?;
+            let mut keys = Vec::new();
+            while let Some(key) = iter.next_item().await {
+                keys.push(key);
+            }
+            keys
+        };
+
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let values: Vec<Option<String>> = connection.mget(&keys).await
+            .map_err(redis_error)?;
+
+        Ok(values.into_iter()
+            .flatten()
+            .filter_map(|value| match serde_json::from_str(&value) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    warn!("Ignoring malformed cluster directory entry: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    async fn connection(&self) -> StreamResult<ConnectionManager> {
+        if let Some(connection) = self.connection.read().await.as_ref() {
+            return Ok(connection.clone());
+        }
+
+        let mut slot = self.connection.write().await;
+        if let Some(connection) = slot.as_ref() {
+            return Ok(connection.clone());
+        }
+
+        let connection = ConnectionManager::new(self.client.clone()).await
+            .map_err(redis_error)?;
+        *slot = Some(connection.clone());
+
+        info!("Connected to cluster directory at {}", self.url);
+        Ok(connection)
+    }
+}
+
+#[cfg(feature = "cluster")]
+fn redis_error(e: redis::RedisError) -> StreamError {
+    StreamError::Network(format!("Redis error: {}", e))
+}
+
+/// 未启用 cluster 特性时没有 Redis，开启集群的配置无法启动
+#[cfg(not(feature = "cluster"))]
+enum RedisStore {}
+
+#[cfg(not(feature = "cluster"))]
+impl RedisStore {
+    fn open(_url: &str) -> Result<Self> {
+        anyhow::bail!("cluster.enabled is set but the server was built without the cluster feature")
+    }
+
+    async fn set_ex(&self, _key: String, _value: String, _ttl: u64) -> StreamResult<()> {
+        match *self {}
+    }
+
+    async fn del(&self, _key: String) -> StreamResult<()> {
+        match *self {}
+    }
+
+    async fn scan_values<T: DeserializeOwned>(&self, _pattern: &str) -> StreamResult<Vec<T>> {
+        match *self {}
+    }
+}
@@ -0,0 +1,430 @@
+use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::process::Stdio;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::net::TcpListener;
+use tokio::process::{Child, Command};
+use tokio::sync::{RwLock, broadcast};
+use tokio::task::JoinHandle;
+use tracing::{info, debug, warn};
+use uuid::Uuid;
+
+use game_stream_common::{
+    CompositeConfig, StreamManager, StreamEvent, StreamStatus, StreamInfo, LiveStream, MediaPacket, MediaReceiver,
+    VideoConfig, AudioConfig, VideoCodec, AudioCodec, StreamResult, StreamError, recv_media
+};
+use crate::auth::AuthManager;
+use crate::recording::{FlvWriter, FLV_TAG_AUDIO, FLV_TAG_VIDEO, FLV_TAG_SCRIPT, FLV_MAX_TAG_SIZE};
+use crate::segment_store::is_safe_path_component;
+
+/// 画中画小窗占输出宽度的比例和到边缘的距离 (像素)
+const PIP_SCALE: u32 = 4;
+const PIP_MARGIN: u32 = 16;
+
+/// 合成管理器 - 用 FFmpeg 将两路直播合成为一路新的流
+///
+/// 每路输入通过本地 TCP 端口以 FLV 发给 FFmpeg，FFmpeg 输出的 FLV 解析后作为新的流发布，
+/// 观看、HLS 和录制与普通推流相同。任意一路输入结束时合成随之结束
+pub struct CompositeManager {
+    config: CompositeConfig,
+    stream_manager: Arc<StreamManager>,
+    auth_manager: Arc<AuthManager>,
+    composites: Arc<RwLock<HashMap<String, CompositeSession>>>,
+}
+
+struct CompositeSession {
+    info: CompositeInfo,
+    task: JoinHandle<()>,
+}
+
+/// 合成布局
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompositeLayout {
+    /// 左右并排，各占一半宽度
+    #[default]
+    SideBySide,
+    /// 第一路铺满画面，第二路缩小到右下角
+    Pip,
+}
+
+/// 创建合成的请求 (POST /api/composites)
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompositeRequest {
+    pub output_key: String,
+    pub inputs: Vec<String>, // 两路输入流，画中画时第一路为主画面
+    #[serde(default)]
+    pub layout: CompositeLayout,
+    pub title: Option<String>,
+    #[serde(default)]
+    pub access: HashMap<String, String>, // 私有输入流的观看密码或邀请 token (按流密钥)
+}
+
+/// 进行中的合成
+#[derive(Debug, Clone, Serialize)]
+pub struct CompositeInfo {
+    pub output_key: String,
+    pub inputs: Vec<String>,
+    pub layout: CompositeLayout,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl CompositeManager {
+    pub fn new(config: &CompositeConfig, stream_manager: Arc<StreamManager>, auth_manager: Arc<AuthManager>) -> Self {
+        info!("Initializing composite manager...");
+
+        Self {
+            config: config.clone(),
+            stream_manager,
+            auth_manager,
+            composites: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// 订阅事件总线，输入流结束时停止相关的合成
+    pub async fn start(self: Arc<Self>) {
+        if !self.config.enabled {
+            return;
+        }
+
+        let mut events = self.stream_manager.subscribe_events();
+
+        loop {
+            match events.recv().await {
+                Ok(StreamEvent::StatusChanged { stream_key, status: StreamStatus::Stopped | StreamStatus::Error(_) })
+                | Ok(StreamEvent::StreamRemoved { stream_key }) => {
+                    let affected: Vec<String> = self.composites.read().await.values()
+                        .filter(|session| session.info.inputs.contains(&stream_key))
+                        .map(|session| session.info.output_key.clone())
+                        .collect();
+                    for output_key in affected {
+                        info!("Input stream {} ended, stopping composite {}", stream_key, output_key);
+                        let _ = self.stop(&output_key).await;
+                    }
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Composite manager lagged behind event bus, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+
+    /// 开始合成，输出作为新的流发布到 output_key；私有输入需要在 access 中提供观看密码或邀请 token
+    pub async fn create(&self, request: CompositeRequest) -> StreamResult<CompositeInfo> {
+        if !self.config.enabled {
+            return Err(StreamError::Config("Compositing is disabled".to_string()));
+        }
+        let [first, second] = request.inputs.as_slice() else {
+            return Err(StreamError::Config(format!("A composite needs exactly 2 inputs, got {}", request.inputs.len())));
+        };
+        if first == second {
+            return Err(StreamError::Config("The two inputs must be different streams".to_string()));
+        }
+        if !is_safe_path_component(&request.output_key) {
+            return Err(StreamError::InvalidStreamKey(request.output_key));
+        }
+        // 合成的输出是新的公开流，私有输入需要与观看相同的密码或邀请
+        for stream_key in &request.inputs {
+            let access = request.access.get(stream_key).map(String::as_str);
+            if !self.auth_manager.validate_viewer(stream_key, access).await {
+                return Err(StreamError::Auth(stream_key.clone()));
+            }
+        }
+
+        // 持有写锁，避免并发请求重复创建
+        let mut composites = self.composites.write().await;
+        if composites.len() >= self.config.max_composites {
+            return Err(StreamError::Config(format!(
+                "Too many composites running (max_composites = {})", self.config.max_composites
+            )));
+        }
+        if self.stream_manager.get_stream(&request.output_key).await.is_some() {
+            return Err(StreamError::Config(format!("Stream {} already exists", request.output_key)));
+        }
+
+        let mut inputs = Vec::new();
+        for stream_key in &request.inputs {
+            match self.stream_manager.get_stream(stream_key).await {
+                Some(stream) if matches!(stream.get_status().await, StreamStatus::Live) => inputs.push(stream),
+                _ => return Err(StreamError::StreamNotFound(stream_key.clone())),
+            }
+        }
+
+        // FFmpeg 主动连接每路输入的本地端口
+        let mut listeners = Vec::new();
+        for _ in &inputs {
+            listeners.push(TcpListener::bind("127.0.0.1:0").await?);
+        }
+        let addrs = listeners.iter()
+            .map(|listener| listener.local_addr())
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut child = spawn_compositor(&self.config, request.layout, &addrs)?;
+        let stdout = child.stdout.take()
+            .ok_or_else(|| StreamError::Codec("ffmpeg stdout is not available".to_string()))?;
+
+        let title = request.title.clone().unwrap_or_else(|| format!("{} + {}", first, second));
+        let output = self.stream_manager.create_stream(
+            request.output_key.clone(),
+            composite_stream_info(&self.config, &request.output_key, title),
+        ).await?;
+        output.set_status(StreamStatus::Live).await;
+
+        let feeders = inputs.iter().zip(listeners)
+            .map(|(input, listener)| feed_input(input.clone(), input.subscribe_media(), listener))
+            .collect();
+        let task = tokio::spawn(run_composite(
+            child,
+            stdout,
+            feeders,
+            output,
+            self.stream_manager.clone(),
+            self.composites.clone(),
+        ));
+
+        let info = CompositeInfo {
+            output_key: request.output_key.clone(),
+            inputs: request.inputs.clone(),
+            layout: request.layout,
+            started_at: chrono::Utc::now(),
+        };
+        info!("Compositing {} and {} into stream {} ({:?})", first, second, info.output_key, info.layout);
+        composites.insert(request.output_key, CompositeSession { info: info.clone(), task });
+
+        Ok(info)
+    }
+
+    /// 停止合成并结束输出流
+    pub async fn stop(&self, output_key: &str) -> StreamResult<CompositeInfo> {
+        let session = self.composites.write().await.remove(output_key)
+            .ok_or_else(|| StreamError::StreamNotFound(output_key.to_string()))?;
+        session.task.abort();
+        stop_output_stream(&self.stream_manager, output_key).await;
+
+        Ok(session.info)
+    }
+
+    pub async fn list(&self) -> Vec<CompositeInfo> {
+        let mut composites: Vec<CompositeInfo> = self.composites.read().await.values()
+            .map(|session| session.info.clone())
+            .collect();
+        composites.sort_by(|a, b| a.output_key.cmp(&b.output_key));
+        composites
+    }
+}
+
+/// 合成任务：向 FFmpeg 输送两路输入，并将输出发布到新的流，直到任意一方结束
+async fn run_composite(
+    mut child: Child,
+    stdout: impl AsyncRead + Unpin,
+    feeders: Vec<impl std::future::Future<Output = ()>>,
+    output: Arc<LiveStream>,
+    stream_manager: Arc<StreamManager>,
+    composites: Arc<RwLock<HashMap<String, CompositeSession>>>,
+) {
+    tokio::select! {
+        result = publish_output(stdout, &output) => {
+            if let Err(e) = result {
+                warn!("Failed to read composite output for stream {}: {}", output.stream_key, e);
+            }
+        }
+        _ = futures::future::join_all(feeders) => {}
+    }
+
+    match child.try_wait() {
+        Ok(Some(status)) if !status.success() => warn!("ffmpeg exited with {} while compositing {}", status, output.stream_key),
+        _ => {
+            let _ = child.kill().await;
+        }
+    }
+
+    info!("Composite {} ended", output.stream_key);
+    composites.write().await.remove(&output.stream_key);
+    stop_output_stream(&stream_manager, &output.stream_key).await;
+}
+
+/// 将一路输入以 FLV 写入 FFmpeg 连接的端口，从下一个关键帧开始
+async fn feed_input(input: Arc<LiveStream>, mut media_receiver: MediaReceiver, listener: TcpListener) {
+    let socket = match listener.accept().await {
+        Ok((socket, _)) => socket,
+        Err(e) => {
+            warn!("Failed to accept compositor connection for stream {}: {}", input.stream_key, e);
+            return;
+        }
+    };
+    // 不经缓冲直接写入套接字，每个数据包立即送出
+    let mut writer = FlvWriter::new(socket);
+    let mut started = false;
+
+    while let Some(packet) = recv_media(&mut media_receiver).await {
+        if !started {
+            let MediaPacket::Video { is_keyframe: true, timestamp, .. } = packet.as_ref() else {
+                continue;
+            };
+            if packet.is_sequence_header() {
+                continue;
+            }
+
+            // 序列头早于关键帧发送，时间戳从关键帧开始归零
+            writer.set_base_timestamp(*timestamp);
+            let mut result = writer.write_header().await;
+            for header in input.sequence_headers().await {
+                if result.is_ok() {
+                    result = writer.write_packet(&header).await;
+                }
+            }
+            if let Err(e) = result {
+                debug!("Compositor closed input for stream {}: {}", input.stream_key, e);
+                return;
+            }
+            started = true;
+        }
+
+        if let Err(e) = writer.write_packet(&packet).await {
+            debug!("Compositor closed input for stream {}: {}", input.stream_key, e);
+            return;
+        }
+    }
+
+    debug!("Input stream {} closed", input.stream_key);
+}
+
+/// 解析 FFmpeg 输出的 FLV 并发布到输出流
+async fn publish_output(mut stdout: impl AsyncRead + Unpin, output: &LiveStream) -> StreamResult<()> {
+    // FLV 文件头 (9 字节) 和第一个 PreviousTagSize
+    let mut header = [0u8; 13];
+    stdout.read_exact(&mut header).await?;
+    if &header[..3] != b"FLV" {
+        return Err(StreamError::Codec("ffmpeg output is not FLV".to_string()));
+    }
+
+    loop {
+        let mut tag_header = [0u8; 11];
+        match stdout.read_exact(&mut tag_header).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+
+        let tag_type = tag_header[0] & 0x1f;
+        let size = u32::from_be_bytes([0, tag_header[1], tag_header[2], tag_header[3]]) as usize;
+        let timestamp = u32::from_be_bytes([tag_header[7], tag_header[4], tag_header[5], tag_header[6]]) as u64;
+        if size > FLV_MAX_TAG_SIZE {
+            return Err(StreamError::Codec(format!("Invalid FLV tag size {}", size)));
+        }
+
+        // 标签体之后是 4 字节的 PreviousTagSize
+        let mut body = vec![0u8; size + 4];
+        stdout.read_exact(&mut body).await?;
+        body.truncate(size);
+        if body.is_empty() {
+            continue;
+        }
+
+        let data = Bytes::from(body);
+        let packet = match tag_type {
+            FLV_TAG_VIDEO => MediaPacket::Video {
+                is_keyframe: data[0] >> 4 == 1,
+                data,
+                timestamp,
+                capture_time: None,
+            },
+            FLV_TAG_AUDIO => MediaPacket::Audio { data, timestamp, capture_time: None },
+            FLV_TAG_SCRIPT => MediaPacket::Metadata { data },
+            _ => continue,
+        };
+        output.send_media_packet(packet).await?;
+    }
+}
+
+/// 启动 FFmpeg，从两个本地端口读取 FLV，按布局合成画面、混合音频，输出 FLV 到标准输出
+fn spawn_compositor(config: &CompositeConfig, layout: CompositeLayout, inputs: &[SocketAddr]) -> StreamResult<Child> {
+    let mut command = Command::new(&config.ffmpeg_path);
+    command.args(["-loglevel", "error", "-fflags", "nobuffer"]);
+    for addr in inputs {
+        command.args(["-f", "flv", "-i"]).arg(format!("tcp://{}", addr));
+    }
+
+    let fps = config.fps.max(1);
+    let child = command
+        .args(["-filter_complex", &filter_graph(config, layout)])
+        .args(["-map", "[v]", "-map", "[a]"])
+        .args(["-c:v", "libx264", "-preset", "veryfast", "-tune", "zerolatency", "-pix_fmt", "yuv420p"])
+        .args(["-b:v", &format!("{}k", config.video_bitrate), "-g", &(fps * 2).to_string()])
+        .args(["-c:a", "aac", "-b:a", &format!("{}k", config.audio_bitrate), "-ar", "44100"])
+        .args(["-f", "flv", "pipe:1"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    Ok(child)
+}
+
+/// 合成的滤镜图：两路画面缩放到布局中的位置 (保持比例，黑边填充)，两路音频混合
+fn filter_graph(config: &CompositeConfig, layout: CompositeLayout) -> String {
+    // H.264 要求宽高为偶数
+    let (width, height) = (config.width & !1, config.height & !1);
+    let fit = |input: usize, w: u32, h: u32, label: &str| format!(
+        "[{input}:v]scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1[{label}]"
+    );
+
+    let video = match layout {
+        CompositeLayout::SideBySide => {
+            let half = (width / 2) & !1;
+            format!(
+                "{};{};[left][right]hstack=inputs=2,pad={}:{}:(ow-iw)/2:0",
+                fit(0, half, height, "left"), fit(1, half, height, "right"), width, height,
+            )
+        }
+        CompositeLayout::Pip => {
+            let (inset_width, inset_height) = ((width / PIP_SCALE) & !1, (height / PIP_SCALE) & !1);
+            format!(
+                "{};{};[main][inset]overlay=W-w-{margin}:H-h-{margin}:eof_action=pass",
+                fit(0, width, height, "main"), fit(1, inset_width, inset_height, "inset"), margin = PIP_MARGIN,
+            )
+        }
+    };
+
+    format!("{},fps={}[v];[0:a][1:a]amix=inputs=2:duration=longest[a]", video, config.fps.max(1))
+}
+
+async fn stop_output_stream(stream_manager: &StreamManager, stream_key: &str) {
+    if let Some(stream) = stream_manager.get_stream(stream_key).await {
+        stream.set_status(StreamStatus::Stopped).await;
+    }
+    stream_manager.remove_stream(stream_key).await;
+}
+
+/// 合成流的初始信息
+fn composite_stream_info(config: &CompositeConfig, stream_key: &str, title: String) -> StreamInfo {
+    StreamInfo {
+        stream_id: Uuid::new_v4(),
+        stream_key: stream_key.to_string(),
+        title: Some(title),
+        description: None,
+        created_at: chrono::Utc::now(),
+        is_live: false,
+        viewer_count: 0,
+        video_config: VideoConfig {
+            width: config.width & !1,
+            height: config.height & !1,
+            fps: config.fps,
+            bitrate: config.video_bitrate,
+            codec: VideoCodec::H264,
+        },
+        audio_config: AudioConfig {
+            sample_rate: 44100,
+            channels: 2,
+            bitrate: config.audio_bitrate,
+            codec: AudioCodec::Aac,
+        },
+        thumbnail_url: None,
+        tags: BTreeMap::new(),
+    }
+}
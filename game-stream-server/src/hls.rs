@@ -2,17 +2,30 @@ use anyhow::Result;
 use std::sync::Arc;
 use std::collections::HashMap;
 use std::path::PathBuf;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant};
+use bytes::{Bytes, BytesMut};
+use tokio::sync::{RwLock, broadcast};
+use tokio::task::JoinHandle;
 use tokio::fs;
-use tracing::{info, error, debug, warn};
+use tracing::{info, debug, warn, error};
 
-use game_stream_common::{StorageConfig, LiveStream, MediaPacket, StreamResult, StreamError};
+use game_stream_common::{
+    StorageConfig, HlsRecoveryPolicy, StreamManager, StreamEvent, StreamStatus, MediaReceiver,
+    StreamResult, StreamError, recv_media
+};
+use crate::segment_store::{self, SegmentStore};
+
+/// 播放器通常缓冲的片段数，用于估算 HLS 延迟
+const HLS_PLAYER_BUFFER_SEGMENTS: u64 = 3;
 
 /// HLS 管理器
 pub struct HlsManager {
     config: StorageConfig,
     playlists: Arc<RwLock<HashMap<String, HlsPlaylist>>>,
-    segments: Arc<RwLock<HashMap<String, Vec<u8>>>>,
+    segments: Arc<RwLock<HashMap<String, Bytes>>>,
+    store: Arc<dyn SegmentStore>,
+    // 每个直播流一个切片任务
+    segmenters: RwLock<HashMap<String, JoinHandle<()>>>,
 }
 
 impl HlsManager {
@@ -22,45 +35,138 @@ impl HlsManager {
         // 创建 HLS 目录
         fs::create_dir_all(&config.hls_segment_dir).await?;
         
-        Ok(Self {
+        let manager = Self {
             config: config.clone(),
             playlists: Arc::new(RwLock::new(HashMap::new())),
             segments: Arc::new(RwLock::new(HashMap::new())),
-        })
+            store: segment_store::create_segment_store(config)?,
+            segmenters: RwLock::new(HashMap::new()),
+        };
+        
+        // 恢复上次运行遗留的播放列表
+        manager.recover_state().await?;
+        
+        Ok(manager)
     }
     
-    /// 处理流的 HLS 生成
-    pub async fn process_stream(&self, stream_key: &str, stream: &LiveStream) -> StreamResult<()> {
-        debug!("Processing HLS for stream: {}", stream_key);
+    /// 订阅事件总线，流开始直播时启动切片任务，结束时停止
+    pub async fn start(self: Arc<Self>, stream_manager: Arc<StreamManager>) {
+        info!("Starting HLS processing...");
+        
+        let mut events = stream_manager.subscribe_events();
         
-        // 检查流是否为直播状态
-        let status = stream.get_status().await;
-        if !matches!(status, game_stream_common::StreamStatus::Live) {
-            return Ok(());
+        // 启动前已经在直播的流
+        for (stream_key, stream) in stream_manager.list_streams().await {
+            if matches!(stream.get_status().await, StreamStatus::Live) {
+                self.clone().start_segmenter(stream_key, stream.subscribe_media()).await;
+            }
         }
         
-        // 获取或创建播放列表
-        let mut playlists = self.playlists.write().await;
-        let playlist = playlists.entry(stream_key.to_string())
-            .or_insert_with(|| HlsPlaylist::new(stream_key.to_string(), &self.config));
+        loop {
+            match events.recv().await {
+                Ok(StreamEvent::StatusChanged { stream_key, status }) => match status {
+                    StreamStatus::Live => {
+                        if let Some(stream) = stream_manager.get_stream(&stream_key).await {
+                            self.clone().start_segmenter(stream_key, stream.subscribe_media()).await;
+                        }
+                    }
+                    StreamStatus::Stopped | StreamStatus::Error(_) => self.stop_segmenter(&stream_key).await,
+                    _ => {}
+                },
+                Ok(StreamEvent::StreamRemoved { stream_key }) => self.stop_segmenter(&stream_key).await,
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("HLS processing lagged behind event bus, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+    
+    /// 启动切片任务 (同名流重新推流时替换旧任务)
+    async fn start_segmenter(self: Arc<Self>, stream_key: String, media_receiver: MediaReceiver) {
+        let task = tokio::spawn(self.clone().run_segmenter(stream_key.clone(), media_receiver));
         
-        // 模拟生成新的片段
-        if playlist.should_generate_segment().await {
-            let segment_name = format!("segment_{}.ts", playlist.next_segment_number);
-            let segment_data = self.generate_segment(stream_key, &segment_name).await?;
-            
-            // 存储片段
-            {
-                let mut segments = self.segments.write().await;
-                let segment_key = format!("{}_{}", stream_key, segment_name);
-                segments.insert(segment_key, segment_data);
+        if let Some(previous) = self.segmenters.write().await.insert(stream_key, task) {
+            previous.abort();
+        }
+    }
+    
+    async fn stop_segmenter(&self, stream_key: &str) {
+        if let Some(task) = self.segmenters.write().await.remove(stream_key) {
+            debug!("Stopping HLS segmenter for stream: {}", stream_key);
+            task.abort();
+        }
+    }
+    
+    /// 切片任务：只在收到媒体数据时唤醒，累计到目标时长后切出一个片段
+    async fn run_segmenter(self: Arc<Self>, stream_key: String, mut media_receiver: MediaReceiver) {
+        debug!("HLS segmenter started for stream: {}", stream_key);
+        
+        let target_duration = Duration::from_secs(self.config.hls_segment_duration.max(1) as u64);
+        let mut buffer = BytesMut::new();
+        let mut segment_started: Option<Instant> = None;
+        
+        while let Some(packet) = recv_media(&mut media_receiver).await {
+            // 到达目标时长后，以当前数据包作为下一个片段的开头
+            // (实际实现中应等到视频关键帧再切片)
+            if segment_started.is_some_and(|started| started.elapsed() >= target_duration) {
+                if let Err(e) = self.append_segment(&stream_key, buffer.split().freeze()).await {
+                    error!("Failed to process HLS for stream {}: {}", stream_key, e);
+                }
+                segment_started = None;
             }
             
-            // 更新播放列表
-            playlist.add_segment(segment_name, self.config.hls_segment_duration).await;
-            
-            // 写入播放列表文件
-            self.write_playlist_file(stream_key, playlist).await?;
+            // 实际实现中需要将音视频数据封装为 MPEG-TS
+            buffer.extend_from_slice(packet.data());
+            segment_started.get_or_insert_with(Instant::now);
+        }
+        
+        debug!("HLS segmenter finished for stream: {}", stream_key);
+    }
+    
+    /// 写入一个新片段并更新播放列表
+    async fn append_segment(&self, stream_key: &str, segment_data: Bytes) -> StreamResult<()> {
+        let segment_name = {
+            let mut playlists = self.playlists.write().await;
+            let playlist = playlists.entry(stream_key.to_string())
+                .or_insert_with(|| HlsPlaylist::new(stream_key.to_string(), &self.config));
+            format!("segment_{}.ts", playlist.next_segment_number)
+        };
+        debug!("Writing HLS segment: {} for stream: {}", segment_name, stream_key);
+        
+        // 持久化片段，重启后或其他分发节点仍可提供给已连接的播放器
+        // (上传期间不持有播放列表锁，避免阻塞其他流)
+        self.store.put_segment(stream_key, &segment_name, segment_data.clone()).await?;
+        
+        // 存储片段
+        {
+            let mut segments = self.segments.write().await;
+            let segment_key = format!("{}_{}", stream_key, segment_name);
+            segments.insert(segment_key, segment_data);
+        }
+        
+        // 更新播放列表
+        let (evicted, cold_segment, playlist_content) = {
+            let mut playlists = self.playlists.write().await;
+            let playlist = playlists.entry(stream_key.to_string())
+                .or_insert_with(|| HlsPlaylist::new(stream_key.to_string(), &self.config));
+            let evicted = playlist.add_segment(segment_name, self.config.hls_segment_duration).await;
+            let cold_segment = playlist.segments.len()
+                .checked_sub(self.config.hls_playlist_length as usize + 1)
+                .map(|index| playlist.segments[index].name.clone());
+            (evicted, cold_segment, playlist.generate_m3u8(self.store.as_ref()).await)
+        };
+        
+        // 写入播放列表文件
+        self.write_playlist_file(stream_key, &playlist_content).await?;
+        
+        // 清理移出播放列表的片段
+        self.remove_segments(stream_key, &evicted).await;
+        
+        // 时移窗口中较早的片段只保留在片段存储中，内存只缓存直播边缘附近的片段
+        if let Some(segment_name) = cold_segment {
+            self.segments.write().await.remove(&format!("{}_{}", stream_key, segment_name));
         }
         
         Ok(())
@@ -72,41 +178,117 @@ impl HlsManager {
         let playlist = playlists.get(stream_key)
             .ok_or_else(|| StreamError::StreamNotFound(stream_key.to_string()))?;
         
-        Ok(playlist.generate_m3u8().await)
+        Ok(playlist.generate_m3u8(self.store.as_ref()).await)
     }
     
     /// 获取 HLS 片段
     pub async fn get_segment(&self, stream_key: &str, segment_name: &str) -> StreamResult<Vec<u8>> {
-        let segments = self.segments.read().await;
-        let segment_key = format!("{}_{}", stream_key, segment_name);
-        let segment_data = segments.get(&segment_key)
-            .ok_or_else(|| StreamError::StreamNotFound(format!("Segment not found: {}", segment_name)))?;
+        {
+            let segments = self.segments.read().await;
+            let segment_key = format!("{}_{}", stream_key, segment_name);
+            if let Some(segment_data) = segments.get(&segment_key) {
+                return Ok(segment_data.to_vec());
+            }
+        }
         
-        Ok(segment_data.clone())
+        // 内存中没有时从片段存储读取 (例如重启前或其他节点生成的片段)
+        self.store.get_segment(stream_key, segment_name).await
+    }
+    
+    /// 估算 HLS 在推流延迟之上增加的延迟 (毫秒)：
+    /// 切出一个完整片段，加上播放器缓冲的片段数 (从最新片段开始播放时只缓冲一个)
+    pub fn estimated_delivery_latency_ms(&self) -> u64 {
+        let buffered = if self.config.hls_live_edge { 1 } else { HLS_PLAYER_BUFFER_SEGMENTS };
+        (buffered + 1) * self.config.hls_segment_duration as u64 * 1000
+    }
+    
+    fn playlist_path(&self, stream_key: &str) -> PathBuf {
+        PathBuf::from(&self.config.hls_segment_dir).join(format!("{}.m3u8", stream_key))
     }
     
-    async fn generate_segment(&self, stream_key: &str, segment_name: &str) -> StreamResult<Vec<u8>> {
-        debug!("Generating HLS segment: {} for stream: {}", segment_name, stream_key);
+    async fn remove_segments(&self, stream_key: &str, evicted: &[HlsSegment]) {
+        if evicted.is_empty() {
+            return;
+        }
         
-        // 实际实现中，这里需要：
-        // 1. 从流中收集音视频数据
-        // 2. 使用 FFmpeg 转码为 TS 格式
-        // 3. 返回 TS 数据
+        let mut segments = self.segments.write().await;
+        for segment in evicted {
+            segments.remove(&format!("{}_{}", stream_key, segment.name));
+            
+            if let Err(e) = self.store.delete_segment(stream_key, &segment.name).await {
+                debug!("Failed to remove HLS segment {}: {}", segment.name, e);
+            }
+        }
+    }
+    
+    async fn write_playlist_file(&self, stream_key: &str, playlist_content: &str) -> StreamResult<()> {
+        let playlist_path = self.playlist_path(stream_key);
+        let temp_path = playlist_path.with_extension("m3u8.tmp");
         
-        // 模拟生成 TS 片段数据
-        let mock_ts_data = vec![0u8; 1024 * 1024]; // 1MB 模拟数据
+        // 先写临时文件再重命名，避免崩溃时留下半个播放列表
+        fs::write(&temp_path, playlist_content).await?;
+        fs::rename(&temp_path, &playlist_path).await?;
         
-        Ok(mock_ts_data)
+        self.store.put_playlist(stream_key, playlist_content).await
     }
     
-    async fn write_playlist_file(&self, stream_key: &str, playlist: &HlsPlaylist) -> StreamResult<()> {
-        let playlist_path = PathBuf::from(&self.config.hls_segment_dir)
-            .join(format!("{}.m3u8", stream_key));
+    /// 扫描 HLS 目录并按策略恢复播放列表
+    async fn recover_state(&self) -> Result<()> {
+        let mut entries = fs::read_dir(&self.config.hls_segment_dir).await?;
+        let mut recovered = 0;
         
-        let playlist_content = playlist.generate_m3u8().await;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("m3u8") {
+                continue;
+            }
+            
+            let stream_key = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(stem) => stem.to_string(),
+                None => continue,
+            };
+            
+            if self.config.hls_recovery == HlsRecoveryPolicy::Cleanup {
+                info!("Cleaning up HLS state for stream: {}", stream_key);
+                let _ = fs::remove_file(&path).await;
+                let _ = fs::remove_dir_all(PathBuf::from(&self.config.hls_segment_dir).join(&stream_key)).await;
+                continue;
+            }
+            
+            let content = match fs::read(&path).await {
+                Ok(content) => content,
+                Err(e) => {
+                    warn!("Failed to read HLS playlist {:?}: {}", path, e);
+                    continue;
+                }
+            };
+            
+            let media_playlist = match m3u8_rs::parse_media_playlist_res(&content) {
+                Ok(media_playlist) => media_playlist,
+                Err(e) => {
+                    warn!("Failed to parse HLS playlist {:?}: {:?}", path, e);
+                    continue;
+                }
+            };
+            
+            let ended = self.config.hls_recovery == HlsRecoveryPolicy::Event;
+            let playlist = HlsPlaylist::recover(stream_key.clone(), &self.config, &media_playlist, ended);
+            
+            info!("Recovered HLS playlist for stream {} (next sequence {})",
+                  stream_key, playlist.next_segment_number);
+            
+            if ended {
+                let playlist_content = playlist.generate_m3u8(self.store.as_ref()).await;
+                self.write_playlist_file(&stream_key, &playlist_content).await?;
+            }
+            
+            self.playlists.write().await.insert(stream_key, playlist);
+            recovered += 1;
+        }
         
-        fs::write(playlist_path, playlist_content).await
-            .map_err(|e| StreamError::Io(e))?;
+        if recovered > 0 {
+            info!("Recovered {} HLS playlists", recovered);
+        }
         
         Ok(())
     }
@@ -119,7 +301,12 @@ struct HlsPlaylist {
     next_segment_number: u32,
     target_duration: u32,
     max_segments: u32,
-    last_segment_time: Option<chrono::DateTime<chrono::Utc>>,
+    // 启用时移且尚未移出过片段时，播放列表为 EVENT 类型
+    event: bool,
+    discontinuity_sequence: u32,
+    discontinuity_pending: bool,
+    ended: bool,
+    live_edge: bool,
 }
 
 impl HlsPlaylist {
@@ -129,40 +316,74 @@ impl HlsPlaylist {
             segments: Vec::new(),
             next_segment_number: 0,
             target_duration: config.hls_segment_duration,
-            max_segments: config.hls_playlist_length,
-            last_segment_time: None,
+            max_segments: max_segments(config),
+            event: config.hls_dvr_window > 0,
+            discontinuity_sequence: 0,
+            discontinuity_pending: false,
+            ended: false,
+            live_edge: config.hls_live_edge,
         }
     }
     
-    async fn should_generate_segment(&self) -> bool {
-        match self.last_segment_time {
-            None => true, // 第一个片段
-            Some(last_time) => {
-                let now = chrono::Utc::now();
-                let duration = now.signed_duration_since(last_time);
-                duration.num_seconds() >= self.target_duration as i64
-            }
-        }
+    /// 从磁盘上的播放列表恢复
+    fn recover(
+        stream_key: String,
+        config: &StorageConfig,
+        media_playlist: &m3u8_rs::MediaPlaylist,
+        ended: bool,
+    ) -> Self {
+        let mut playlist = Self::new(stream_key, config);
+        let first_sequence = media_playlist.media_sequence as u32;
+        
+        playlist.segments = media_playlist.segments.iter().enumerate()
+            .map(|(index, segment)| HlsSegment {
+                // 片段 URI 可能是 CDN 地址，只保留片段名
+                name: segment.uri.rsplit('/').next().unwrap_or(&segment.uri).to_string(),
+                duration: segment.duration.round() as u32,
+                sequence: first_sequence + index as u32,
+                discontinuity: segment.discontinuity,
+            })
+            .collect();
+        playlist.next_segment_number = first_sequence + playlist.segments.len() as u32;
+        playlist.discontinuity_sequence = media_playlist.discontinuity_sequence as u32;
+        playlist.event &= matches!(media_playlist.playlist_type, Some(m3u8_rs::MediaPlaylistType::Event));
+        // 推流恢复后的第一个片段与之前的内容不连续
+        playlist.discontinuity_pending = true;
+        playlist.ended = ended;
+        
+        playlist
     }
     
-    async fn add_segment(&mut self, segment_name: String, duration: u32) {
+    /// 添加片段，返回被移出播放列表的片段
+    async fn add_segment(&mut self, segment_name: String, duration: u32) -> Vec<HlsSegment> {
         let segment = HlsSegment {
             name: segment_name,
             duration,
             sequence: self.next_segment_number,
+            discontinuity: self.discontinuity_pending,
         };
         
         self.segments.push(segment);
         self.next_segment_number += 1;
-        self.last_segment_time = Some(chrono::Utc::now());
+        self.discontinuity_pending = false;
+        self.ended = false;
         
         // 保持播放列表长度
+        let mut evicted = Vec::new();
         while self.segments.len() > self.max_segments as usize {
-            self.segments.remove(0);
+            let segment = self.segments.remove(0);
+            if segment.discontinuity {
+                self.discontinuity_sequence += 1;
+            }
+            evicted.push(segment);
+            // EVENT 播放列表只能追加片段，时移窗口满后转为普通的滑动窗口
+            self.event = false;
         }
+        
+        evicted
     }
     
-    async fn generate_m3u8(&self) -> String {
+    async fn generate_m3u8(&self, store: &dyn SegmentStore) -> String {
         let mut m3u8 = String::new();
         
         // M3U8 头部
@@ -170,24 +391,56 @@ impl HlsPlaylist {
         m3u8.push_str("#EXT-X-VERSION:3\n");
         m3u8.push_str(&format!("#EXT-X-TARGETDURATION:{}\n", self.target_duration));
         
-        if let Some(first_segment) = self.segments.first() {
-            m3u8.push_str(&format!("#EXT-X-MEDIA-SEQUENCE:{}\n", first_segment.sequence));
+        // 让播放器从最后一个片段开始播放
+        if self.live_edge && !self.ended {
+            m3u8.push_str(&format!("#EXT-X-START:TIME-OFFSET=-{}.0\n", self.target_duration));
+        }
+        
+        if self.ended || self.event {
+            m3u8.push_str("#EXT-X-PLAYLIST-TYPE:EVENT\n");
+        }
+        
+        let media_sequence = self.segments.first()
+            .map(|segment| segment.sequence)
+            .unwrap_or(self.next_segment_number);
+        m3u8.push_str(&format!("#EXT-X-MEDIA-SEQUENCE:{}\n", media_sequence));
+        
+        if self.discontinuity_sequence > 0 {
+            m3u8.push_str(&format!("#EXT-X-DISCONTINUITY-SEQUENCE:{}\n", self.discontinuity_sequence));
         }
         
         // 片段列表
         for segment in &self.segments {
+            if segment.discontinuity {
+                m3u8.push_str("#EXT-X-DISCONTINUITY\n");
+            }
             m3u8.push_str(&format!("#EXTINF:{}.0,\n", segment.duration));
-            m3u8.push_str(&format!("{}\n", segment.name));
+            m3u8.push_str(&format!("{}\n", store.segment_uri(&self.stream_key, &segment.name)));
+        }
+        
+        if self.ended {
+            m3u8.push_str("#EXT-X-ENDLIST\n");
         }
         
         m3u8
     }
 }
 
+/// 播放列表保留的片段数 (启用时移时覆盖整个时移窗口)
+fn max_segments(config: &StorageConfig) -> u32 {
+    if config.hls_dvr_window == 0 {
+        return config.hls_playlist_length;
+    }
+    
+    let segment_duration = config.hls_segment_duration.max(1);
+    config.hls_dvr_window.div_ceil(segment_duration).max(config.hls_playlist_length)
+}
+
 /// HLS 片段信息
 #[derive(Debug, Clone)]
 struct HlsSegment {
     name: String,
     duration: u32,
     sequence: u32,
+    discontinuity: bool,
 }
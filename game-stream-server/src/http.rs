@@ -1,103 +1,242 @@
 use anyhow::Result;
 use std::sync::Arc;
 use axum::{
-    extract::{Path, Query, State, WebSocketUpgrade},
-    http::StatusCode,
-    response::{Html, IntoResponse, Response},
-    routing::{get, post},
-    Json, Router,
+    body::Body,
+    extract::{Path, Query, Request, State, WebSocketUpgrade},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{delete, get, patch, post, put},
+    middleware, Json, Router,
 };
 use axum::extract::ws::{WebSocket, Message};
-use tower::ServiceBuilder;
-use tower_http::{cors::CorsLayer, services::ServeDir};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures::StreamExt;
+use tower::{ServiceBuilder, ServiceExt};
+use tower_http::{cors::CorsLayer, services::{ServeDir, ServeFile}};
 use tracing::{info, error, debug, warn};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use rml_rtmp::rml_amf0::{self, Amf0Value};
+use uuid::Uuid;
 
 use game_stream_common::{
-    HttpServerConfig, StreamManager, WebRtcSignal, StreamInfo,
-    StreamResult, StreamError
+    HttpServerConfig, StreamManager, StreamInfo, ViewProtocol,
+    BandwidthSnapshot, ClientStats, LatencySnapshot, LiveStream, StreamEvent, StreamResult, StreamError,
+    RecordingRuleset, LatencyMode, Rendition, ViewerConnection, StreamStatus, StreamInfoUpdate, MediaPacket
 };
-use crate::webrtc::WebRtcSignalingHandler;
+#[cfg(any(feature = "webrtc", feature = "hls"))]
+use axum::{extract::ConnectInfo, http::HeaderMap};
+#[cfg(feature = "webrtc")]
+use game_stream_common::WebRtcSignal;
+#[cfg(feature = "webrtc")]
+use crate::webrtc::{WebRtcSignalingHandler, SignalPeer};
+#[cfg(feature = "hls")]
+use game_stream_common::LatencySummary;
+#[cfg(feature = "hls")]
 use crate::hls::HlsManager;
+use crate::analytics::{AnalyticsManager, AnalyticsSummary};
+use crate::access_log::access_log;
+use crate::overload::OverloadGuard;
+use crate::relay::RelayManager;
+use crate::cluster::ClusterDirectory;
+use crate::restream::{RestreamManager, TargetHealth};
+use crate::recording::{RecordingManager, RecordingStatus};
+use crate::vod::{VodManager, VodEntry};
+use crate::clip::{ClipManager, ClipInfo};
+use crate::thumbnail::ThumbnailManager;
+use crate::retention::{RetentionManager, RetentionStats};
+use crate::composite::{CompositeManager, CompositeRequest, CompositeInfo};
+use crate::auth::{AuthManager, StreamAccessInfo, StreamAccessUpdate, Invite};
 
 /// HTTP 服务器
 #[derive(Clone)]
 pub struct HttpServer {
     config: HttpServerConfig,
     app_state: AppState,
+    extra_routes: Vec<Router>, // 嵌入服务器的程序合并进来的路由
 }
 
 #[derive(Clone)]
 struct AppState {
     stream_manager: Arc<StreamManager>,
+    auth_manager: Arc<AuthManager>,
+    latency_mode: LatencyMode,
+    #[cfg(feature = "webrtc")]
     webrtc_handler: Arc<WebRtcSignalingHandler>,
+    #[cfg(feature = "hls")]
     hls_manager: Arc<HlsManager>,
+    analytics_manager: Arc<AnalyticsManager>,
+    #[cfg_attr(not(any(feature = "webrtc", feature = "hls")), allow(dead_code))]
+    overload_guard: Arc<OverloadGuard>,
+    #[cfg_attr(not(feature = "hls"), allow(dead_code))]
+    relay_manager: Arc<RelayManager>,
+    cluster_directory: Arc<ClusterDirectory>,
+    restream_manager: Arc<RestreamManager>,
+    recording_manager: Arc<RecordingManager>,
+    vod_manager: Arc<VodManager>,
+    clip_manager: Arc<ClipManager>,
+    thumbnail_manager: Arc<ThumbnailManager>,
+    retention_manager: Arc<RetentionManager>,
+    composite_manager: Arc<CompositeManager>,
 }
 
 impl HttpServer {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         config: &HttpServerConfig,
         stream_manager: Arc<StreamManager>,
-        webrtc_handler: Arc<WebRtcSignalingHandler>,
-        hls_manager: Arc<HlsManager>,
+        auth_manager: Arc<AuthManager>,
+        latency_mode: LatencyMode,
+        #[cfg(feature = "webrtc")] webrtc_handler: Arc<WebRtcSignalingHandler>,
+        #[cfg(feature = "hls")] hls_manager: Arc<HlsManager>,
+        analytics_manager: Arc<AnalyticsManager>,
+        overload_guard: Arc<OverloadGuard>,
+        relay_manager: Arc<RelayManager>,
+        cluster_directory: Arc<ClusterDirectory>,
+        restream_manager: Arc<RestreamManager>,
+        recording_manager: Arc<RecordingManager>,
+        vod_manager: Arc<VodManager>,
+        clip_manager: Arc<ClipManager>,
+        thumbnail_manager: Arc<ThumbnailManager>,
+        retention_manager: Arc<RetentionManager>,
+        composite_manager: Arc<CompositeManager>,
     ) -> Result<Self> {
         info!("Initializing HTTP server...");
         
         let app_state = AppState {
             stream_manager,
+            auth_manager,
+            latency_mode,
+            #[cfg(feature = "webrtc")]
             webrtc_handler,
+            #[cfg(feature = "hls")]
             hls_manager,
+            analytics_manager,
+            overload_guard,
+            relay_manager,
+            cluster_directory,
+            restream_manager,
+            recording_manager,
+            vod_manager,
+            clip_manager,
+            thumbnail_manager,
+            retention_manager,
+            composite_manager,
         };
         
         Ok(Self {
             config: config.clone(),
             app_state,
+            extra_routes: Vec::new(),
         })
     }
     
-    pub async fn start(&mut self) -> Result<()> {
+    /// 合并额外的路由，与内置路由共用 CORS 和访问日志；路径不能与内置路由重复
+    pub fn merge_routes(&mut self, router: Router) {
+        self.extra_routes.push(router);
+    }
+    
+    /// 绑定 HTTP 监听端口
+    pub async fn bind(&self) -> Result<tokio::net::TcpListener> {
         let bind_addr = format!("{}:{}", self.config.bind_addr, self.config.port);
+        let listener = tokio::net::TcpListener::bind(&bind_addr).await?;
+        
+        info!("HTTP server listening on {}", bind_addr);
         
+        Ok(listener)
+    }
+    
+    /// 在已绑定的端口上提供 HTTP 服务
+    pub async fn serve(&mut self, listener: tokio::net::TcpListener) -> Result<()> {
         // 构建路由
         let app = self.build_router().await;
         
-        info!("HTTP server listening on {}", bind_addr);
-        
         // 启动服务器
-        let listener = tokio::net::TcpListener::bind(&bind_addr).await?;
-        axum::serve(listener, app).await?;
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
         
         Ok(())
     }
     
-    async fn build_router(&self) -> Router {
+    /// 完整的路由，嵌入的程序可以用自己的 HTTP 服务提供
+    pub async fn build_router(&self) -> Router {
         let cors = if self.config.cors_enabled {
             CorsLayer::permissive()
         } else {
             CorsLayer::new()
         };
         
-        Router::new()
+        let router = Router::new()
             // API 路由
             .route("/api/streams", get(list_streams))
             .route("/api/streams/:stream_key", get(get_stream_info))
             .route("/api/streams/:stream_key/stats", get(get_stream_stats))
+            .route("/api/streams/:stream_key/analytics", get(get_stream_analytics))
+            .route("/api/streams/:stream_key/playback", get(get_playback_info))
+            .route("/api/streams/:stream_key/events", get(stream_events))
+            .route("/api/streams/:stream_key/viewers/:viewer_id/rendition", put(set_viewer_rendition))
+            .route("/api/streams/:stream_key/restream", get(get_restream_health))
+            .route("/api/streams/:stream_key/recording", get(get_recording_status))
+            .route("/api/streams/:stream_key/clip", post(create_clip))
+            .route("/api/streams/:stream_key/thumbnail.jpg", get(get_thumbnail))
+            .route("/api/streams/:stream_key/thumbnail.webp", get(get_thumbnail))
             
-            // WebRTC 信令
-            .route("/api/webrtc/signal", post(webrtc_signal))
-            .route("/api/webrtc/ws", get(webrtc_websocket))
+            // 多路合成
+            .route("/api/composites", get(list_composites))
             
-            // HLS 播放列表
+            // 点播 (已完成的录制)
+            .route("/api/vod", get(list_vod))
+            .route("/api/vod/*file", get(get_vod))
+            .route("/vod/*file", get(vod_file))
+            .route("/clips/*file", get(clip_file));
+        
+        // 管理接口：修改流信息、观看者和会话历史 (含客户端地址)、观看权限、邀请、手动录制和删除录制、
+        // 多路合成和管理后台，需要 admin_token
+        let admin = Router::new()
+            .route("/api/streams/:stream_key", patch(update_stream_info))
+            .route("/api/streams/:stream_key/sessions", get(get_stream_sessions))
+            .route("/api/streams/:stream_key/viewers", get(list_viewers))
+            .route("/api/streams/:stream_key/access", get(get_stream_access).put(update_stream_access))
+            .route("/api/streams/:stream_key/invites", post(create_invite))
+            .route("/api/streams/:stream_key/invites/:token", delete(revoke_invite))
+            .route("/api/streams/:stream_key/recording/start", post(start_recording))
+            .route("/api/streams/:stream_key/recording/stop", post(stop_recording))
+            .route("/api/vod/*file", delete(delete_vod))
+            .route("/api/composites", post(create_composite))
+            .route("/api/composites/:output_key", delete(stop_composite))
+            .route("/api/admin/ws", get(admin_websocket))
+            .route("/api/admin/recording/rules", get(get_recording_rules).put(set_recording_rules))
+            .route("/api/admin/retention", get(get_retention_stats))
+            .route_layer(middleware::from_fn_with_state(self.app_state.clone(), require_admin));
+        let router = router.merge(admin);
+        
+        // WebRTC 信令
+        #[cfg(feature = "webrtc")]
+        let router = router
+            .route("/api/webrtc/signal", post(webrtc_signal))
+            .route("/api/webrtc/ws", get(webrtc_websocket));
+        
+        // HLS 播放列表
+        #[cfg(feature = "hls")]
+        let router = router
             .route("/hls/:stream_key/playlist.m3u8", get(hls_playlist))
-            .route("/hls/:stream_key/:segment", get(hls_segment))
-            
+            .route("/hls/:stream_key/:segment", get(hls_segment));
+        
+        let router = router
             // 静态文件服务
             .nest_service("/", ServeDir::new(&self.config.static_dir))
             
             // 状态和中间件
-            .with_state(self.app_state.clone())
-            .layer(ServiceBuilder::new().layer(cors))
+            .with_state(self.app_state.clone());
+        let router = self.extra_routes.iter().cloned()
+            .fold(router, Router::merge)
+            .layer(ServiceBuilder::new().layer(cors));
+        
+        if self.config.access_log {
+            router.layer(middleware::from_fn(access_log))
+        } else {
+            router
+        }
     }
 }
 
@@ -112,14 +251,34 @@ async fn list_streams(State(state): State<AppState>) -> Result<Json<Vec<StreamIn
         })
     ).await;
     
+    // 不公开和私有的流不出现在列表中
+    let mut listed = Vec::with_capacity(stream_infos.len());
+    for info in stream_infos {
+        if state.auth_manager.is_listed(&info.stream_key).await {
+            listed.push(info);
+        }
+    }
+    let stream_infos = listed;
+    
+    // 集群模式下合并其他实例上的流
+    if state.cluster_directory.is_enabled() {
+        match state.cluster_directory.cluster_streams(stream_infos.clone()).await {
+            Ok(cluster_infos) => return Ok(Json(cluster_infos)),
+            Err(e) => warn!("Failed to query cluster directory, returning local streams: {}", e),
+        }
+    }
+    
     Ok(Json(stream_infos))
 }
 
 /// 获取特定流信息
 async fn get_stream_info(
     Path(stream_key): Path<String>,
+    Query(params): Query<AccessParams>,
     State(state): State<AppState>,
 ) -> Result<Json<StreamInfo>, AppError> {
+    check_viewer_access(&state, &stream_key, params.access.as_deref()).await?;
+    
     let stream = state.stream_manager.get_stream(&stream_key).await
         .ok_or(AppError::StreamNotFound(stream_key))?;
     
@@ -127,34 +286,575 @@ async fn get_stream_info(
     Ok(Json(info))
 }
 
+/// 直播中修改标题、简介和自定义标签，广播 InfoChanged 事件，
+/// 并作为 onCuePoint 元数据随媒体流发给观看者 (录制和边缘节点也会收到)；需要 admin_token
+async fn update_stream_info(
+    Path(stream_key): Path<String>,
+    State(state): State<AppState>,
+    Json(update): Json<StreamInfoUpdate>,
+) -> Result<Json<StreamInfo>, AppError> {
+    if update.is_empty() {
+        return Err(AppError::BadRequest("Nothing to update".to_string()));
+    }
+    if update.tags.keys().any(|name| name.is_empty()) {
+        return Err(AppError::BadRequest("Tag names must not be empty".to_string()));
+    }
+    let stream = state.stream_manager.get_stream(&stream_key).await
+        .ok_or(AppError::StreamNotFound(stream_key))?;
+    
+    let info = stream.update_info(|info| update.apply(info)).await;
+    let cue_point = stream_info_cue_point(&info)
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    stream.send_media_packet(MediaPacket::Metadata { data: cue_point }).await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    
+    Ok(Json(info))
+}
+
+/// 流信息的 FLV 脚本数据：onCuePoint {name: "streamInfo", type: "event", parameters: {title, description, 标签...}}
+fn stream_info_cue_point(info: &StreamInfo) -> StreamResult<bytes::Bytes> {
+    let mut parameters: HashMap<String, Amf0Value> = info.tags.iter()
+        .map(|(name, value)| (name.clone(), Amf0Value::Utf8String(value.clone())))
+        .collect();
+    for (name, value) in [("title", &info.title), ("description", &info.description)] {
+        let value = value.clone().map_or(Amf0Value::Null, Amf0Value::Utf8String);
+        parameters.insert(name.to_string(), value);
+    }
+    
+    let cue_point = HashMap::from([
+        ("name".to_string(), Amf0Value::Utf8String("streamInfo".to_string())),
+        ("type".to_string(), Amf0Value::Utf8String("event".to_string())),
+        ("time".to_string(), Amf0Value::Number(0.0)),
+        ("parameters".to_string(), Amf0Value::Object(parameters)),
+    ]);
+    let data = rml_amf0::serialize(&vec![
+        Amf0Value::Utf8String("onCuePoint".to_string()),
+        Amf0Value::Object(cue_point),
+    ]).map_err(|e| StreamError::Internal(format!("AMF0 serialization failed: {}", e)))?;
+    Ok(data.into())
+}
+
 /// 获取流统计信息
 async fn get_stream_stats(
     Path(stream_key): Path<String>,
+    Query(params): Query<AccessParams>,
     State(state): State<AppState>,
 ) -> Result<Json<StreamStats>, AppError> {
+    check_viewer_access(&state, &stream_key, params.access.as_deref()).await?;
+    
     let stream = state.stream_manager.get_stream(&stream_key).await
         .ok_or(AppError::StreamNotFound(stream_key))?;
     
-    let stats = StreamStats {
+    Ok(Json(collect_stream_stats(&state, &stream).await))
+}
+
+/// 收集单个流的统计信息
+async fn collect_stream_stats(state: &AppState, stream: &LiveStream) -> StreamStats {
+    let mut latency = stream.latency.snapshot().await;
+    
+    // HLS 无法逐包测量，根据推流延迟和片段时长估算
+    #[cfg(feature = "hls")]
+    if let Some(ingest) = latency.ingest {
+        let extra = state.hls_manager.estimated_delivery_latency_ms();
+        latency.glass_to_glass.entry(ViewProtocol::Hls).or_insert(LatencySummary {
+            last_ms: ingest.last_ms + extra,
+            avg_ms: ingest.avg_ms + extra,
+            max_ms: ingest.max_ms + extra,
+            samples: ingest.samples,
+        });
+    }
+    
+    // 每个观看者的估算加上播放端缓冲
+    for viewer in &mut latency.viewers {
+        viewer.buffer_ms = player_buffer_ms(state, &viewer.protocol);
+        viewer.estimated_ms += viewer.buffer_ms;
+    }
+    latency.viewers.sort_by_key(|viewer| std::cmp::Reverse(viewer.estimated_ms));
+    
+    StreamStats {
         viewer_count: stream.get_viewer_count().await,
         status: stream.get_status().await,
         uptime: chrono::Utc::now().signed_duration_since(
             stream.get_info().await.created_at
         ).num_seconds(),
+        bandwidth: stream.bandwidth.snapshot().await,
+        latency,
+        client: stream.client_stats.read().await.clone(),
+    }
+}
+
+/// 播放端缓冲的估计值 (毫秒)
+#[cfg_attr(not(feature = "hls"), allow(unused_variables))]
+fn player_buffer_ms(state: &AppState, protocol: &ViewProtocol) -> u64 {
+    match protocol {
+        ViewProtocol::WebRtc => WEBRTC_JITTER_BUFFER_MS,
+        #[cfg(feature = "hls")]
+        ViewProtocol::Hls => state.hls_manager.estimated_delivery_latency_ms(),
+        _ => 0,
+    }
+}
+
+/// 播放页的流状态推送 (SSE)：连接时先发送当前状态，之后推送观看人数、开播/下播和标题的变化
+///
+/// 流尚未开播时也可以订阅，开播后收到 status 事件
+async fn stream_events(
+    Path(stream_key): Path<String>,
+    Query(params): Query<AccessParams>,
+    State(state): State<AppState>,
+) -> Result<Sse<impl futures::Stream<Item = Result<Event, axum::Error>>>, AppError> {
+    check_viewer_access(&state, &stream_key, params.access.as_deref()).await?;
+    
+    // 先订阅再读取当前状态，不会漏掉两者之间的变化
+    let events = state.stream_manager.subscribe_events();
+    let initial = player_snapshot(&state.stream_manager, &stream_key).await;
+    
+    let stream_manager = state.stream_manager.clone();
+    let updates = futures::stream::unfold((events, stream_key), move |(mut events, stream_key)| {
+        let stream_manager = stream_manager.clone();
+        async move {
+            loop {
+                let messages = match events.recv().await {
+                    Ok(event) => PlayerEvent::from_stream_event(&stream_key, event).into_iter().collect(),
+                    // 落后时丢失的变化用当前状态补上
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        debug!("Player event feed for {} lagged, skipped {} events", stream_key, skipped);
+                        player_snapshot(&stream_manager, &stream_key).await
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+                };
+                if !messages.is_empty() {
+                    return Some((futures::stream::iter(messages), (events, stream_key)));
+                }
+            }
+        }
+    }).flatten();
+    
+    let events = futures::stream::iter(initial).chain(updates).map(PlayerEvent::into_sse);
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// 流的当前状态，流不存在时为未开播
+async fn player_snapshot(stream_manager: &StreamManager, stream_key: &str) -> Vec<PlayerEvent> {
+    let Some(stream) = stream_manager.get_stream(stream_key).await else {
+        return vec![PlayerEvent::Status { live: false, status: StreamStatus::Stopped }];
+    };
+    let info = stream.get_info().await;
+    vec![
+        PlayerEvent::Status { live: info.is_live, status: stream.get_status().await },
+        PlayerEvent::Viewers { viewer_count: stream.get_viewer_count().await },
+        PlayerEvent::Info { title: info.title, description: info.description, tags: info.tags },
+    ]
+}
+
+/// 获取流的可见性、是否设置了观看密码和有效的邀请
+async fn get_stream_access(
+    Path(stream_key): Path<String>,
+    State(state): State<AppState>,
+) -> Json<StreamAccessInfo> {
+    Json(state.auth_manager.get_access(&stream_key).await)
+}
+
+/// 修改流的可见性和观看密码 (按流密钥保存，流未开播时也可以设置)
+async fn update_stream_access(
+    Path(stream_key): Path<String>,
+    State(state): State<AppState>,
+    Json(update): Json<StreamAccessUpdate>,
+) -> Json<StreamAccessInfo> {
+    Json(state.auth_manager.update_access(&stream_key, update).await)
+}
+
+/// 创建私有流的邀请 (可选有效期 ttl 秒)，观看者凭 token 作为 access 观看
+async fn create_invite(
+    Path(stream_key): Path<String>,
+    State(state): State<AppState>,
+    Json(params): Json<InviteParams>,
+) -> (StatusCode, Json<Invite>) {
+    (StatusCode::CREATED, Json(state.auth_manager.create_invite(&stream_key, params.ttl).await))
+}
+
+/// 撤销邀请
+async fn revoke_invite(
+    Path((stream_key, token)): Path<(String, String)>,
+    State(state): State<AppState>,
+) -> Result<StatusCode, AppError> {
+    state.auth_manager.revoke_invite(&stream_key, &token).await
+        .ok_or_else(|| AppError::BadRequest(format!("Invite not found: {}", token)))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 列出流的观看者和各自选择的画质 (需要 admin_token)
+async fn list_viewers(
+    Path(stream_key): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<ViewerConnection>>, AppError> {
+    let stream = state.stream_manager.get_stream(&stream_key).await
+        .ok_or(AppError::StreamNotFound(stream_key))?;
+    
+    let viewers = stream.viewers.read().await.values().cloned().collect();
+    Ok(Json(viewers))
+}
+
+/// 播放过程中切换观看者的画质
+async fn set_viewer_rendition(
+    Path((stream_key, viewer_id)): Path<(String, Uuid)>,
+    State(state): State<AppState>,
+    Json(params): Json<RenditionParams>,
+) -> Result<StatusCode, AppError> {
+    let stream = state.stream_manager.get_stream(&stream_key).await
+        .ok_or(AppError::StreamNotFound(stream_key))?;
+    
+    stream.set_viewer_rendition(viewer_id, params.rendition).await
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 获取流观看分析汇总
+async fn get_stream_analytics(
+    Path(stream_key): Path<String>,
+    Query(params): Query<AccessParams>,
+    State(state): State<AppState>,
+) -> Result<Json<AnalyticsSummary>, AppError> {
+    check_viewer_access(&state, &stream_key, params.access.as_deref()).await?;
+    
+    let summary = state.analytics_manager.get_summary(&stream_key).await
+        .ok_or(AppError::StreamNotFound(stream_key))?;
+    
+    Ok(Json(summary))
+}
+
+/// 获取流观看会话历史 (支持 ?format=csv 导出，需要 admin_token)
+async fn get_stream_sessions(
+    Path(stream_key): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> Response {
+    let sessions = state.analytics_manager.get_sessions(&stream_key).await;
+    
+    if params.get("format").map(String::as_str) == Some("csv") {
+        let csv = AnalyticsManager::sessions_to_csv(&sessions);
+        return ([(header::CONTENT_TYPE, "text/csv; charset=utf-8")], csv).into_response();
+    }
+    
+    Json(sessions).into_response()
+}
+
+/// 为观看者选择负载最低的节点并返回播放地址 (支持 ?redirect=true 直接跳转到 HLS 地址)
+async fn get_playback_info(
+    Path(stream_key): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let access = params.get("access").map(String::as_str);
+    check_viewer_access(&state, &stream_key, access).await?;
+    
+    let exists = state.cluster_directory.has_stream(&stream_key).await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    if !exists {
+        return Err(AppError::StreamNotFound(stream_key));
+    }
+    
+    let instance = state.cluster_directory.select_instance().await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    
+    // 编译时未启用的观看协议 (hls、webrtc 特性) 没有播放地址
+    let hls = cfg!(feature = "hls");
+    let webrtc = cfg!(feature = "webrtc");
+    let playback = PlaybackInfo {
+        hls_url: hls.then(|| format!("{}/hls/{}/playlist.m3u8{}", instance.public_url, stream_key, access_query(access))),
+        webrtc_signal_url: webrtc.then(|| format!("{}/api/webrtc/signal", instance.public_url)),
+        webrtc_ws_url: webrtc.then(|| format!("{}/api/webrtc/ws", instance.public_url.replacen("http", "ws", 1))),
+        instance_id: instance.instance_id,
+        stream_key,
+        latency_mode: state.latency_mode,
+        preferred_protocol: match state.latency_mode {
+            LatencyMode::UltraLow if webrtc => ViewProtocol::WebRtc,
+            _ if hls => ViewProtocol::Hls,
+            _ if webrtc => ViewProtocol::WebRtc,
+            _ => ViewProtocol::Rtmp,
+        },
     };
     
-    Ok(Json(stats))
+    if params.get("redirect").map(String::as_str) == Some("true") {
+        let hls_url = playback.hls_url
+            .ok_or_else(|| AppError::BadRequest("HLS playback is not enabled on this server".to_string()))?;
+        return Ok(Redirect::temporary(&hls_url).into_response());
+    }
+    
+    Ok(Json(playback).into_response())
+}
+
+/// 获取流的转推目标健康状态
+async fn get_restream_health(
+    Path(stream_key): Path<String>,
+    Query(params): Query<AccessParams>,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<TargetHealth>>, AppError> {
+    check_viewer_access(&state, &stream_key, params.access.as_deref()).await?;
+    
+    let health = state.restream_manager.get_health(&stream_key).await
+        .ok_or(AppError::StreamNotFound(stream_key))?;
+    
+    Ok(Json(health))
+}
+
+/// 获取流的录制状态
+async fn get_recording_status(
+    Path(stream_key): Path<String>,
+    Query(params): Query<AccessParams>,
+    State(state): State<AppState>,
+) -> Result<Json<RecordingStatus>, AppError> {
+    check_viewer_access(&state, &stream_key, params.access.as_deref()).await?;
+    
+    let status = state.recording_manager.get_status(&stream_key).await
+        .ok_or(AppError::StreamNotFound(stream_key))?;
+    
+    Ok(Json(status))
+}
+
+/// 手动开始录制 (需要 admin_token)
+async fn start_recording(
+    Path(stream_key): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<RecordingStatus>, AppError> {
+    let status = state.recording_manager.start_recording(&stream_key).await
+        .map_err(|e| match e {
+            StreamError::StreamNotFound(stream_key) => AppError::StreamNotFound(stream_key),
+            e => AppError::Internal(e.to_string()),
+        })?;
+    
+    Ok(Json(status))
+}
+
+/// 手动停止录制 (需要 admin_token)
+async fn stop_recording(
+    Path(stream_key): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<RecordingStatus>, AppError> {
+    let status = state.recording_manager.stop_recording(&stream_key).await
+        .ok_or(AppError::StreamNotFound(stream_key))?;
+    
+    Ok(Json(status))
+}
+
+/// 获取录制规则集
+async fn get_recording_rules(State(state): State<AppState>) -> Json<RecordingRuleset> {
+    Json(state.recording_manager.get_rules().await)
+}
+
+/// 替换录制规则集
+async fn set_recording_rules(
+    State(state): State<AppState>,
+    Json(ruleset): Json<RecordingRuleset>,
+) -> Result<Json<RecordingRuleset>, AppError> {
+    state.recording_manager.set_rules(ruleset).await
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+    
+    Ok(Json(state.recording_manager.get_rules().await))
+}
+
+/// 获取磁盘数据清理统计
+async fn get_retention_stats(State(state): State<AppState>) -> Json<RetentionStats> {
+    Json(state.retention_manager.get_stats().await)
+}
+
+/// 获取流的最新缩略图
+async fn get_thumbnail(
+    Path(stream_key): Path<String>,
+    Query(params): Query<AccessParams>,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    check_viewer_access(&state, &stream_key, params.access.as_deref()).await?;
+    
+    let thumbnail = state.thumbnail_manager.get_thumbnail(&stream_key).await
+        .map_err(|_| AppError::StreamNotFound(stream_key))?;
+    
+    let cache_control = format!("public, max-age={}", state.thumbnail_manager.interval().as_secs());
+    Ok((
+        [
+            (header::CONTENT_TYPE, thumbnail.format.content_type().to_string()),
+            (header::CACHE_CONTROL, cache_control),
+            (header::LAST_MODIFIED, thumbnail.updated_at.to_rfc2822()),
+        ],
+        thumbnail.data,
+    ).into_response())
+}
+
+/// 截取最近一段直播为剪辑 (?duration=秒)
+async fn create_clip(
+    Path(stream_key): Path<String>,
+    Query(params): Query<ClipParams>,
+    State(state): State<AppState>,
+) -> Result<(StatusCode, Json<ClipInfo>), AppError> {
+    check_viewer_access(&state, &stream_key, params.access.as_deref()).await?;
+    let clip = state.clip_manager.create_clip(&stream_key, params.duration).await
+        .map_err(|e| match e {
+            StreamError::StreamNotFound(stream_key) => AppError::StreamNotFound(stream_key),
+            StreamError::Config(msg) => AppError::BadRequest(msg),
+            e => AppError::Internal(e.to_string()),
+        })?;
+    
+    Ok((StatusCode::CREATED, Json(clip)))
+}
+
+/// 下载剪辑文件 (支持 Range 请求)，剪辑路径的第一级目录是流密钥
+async fn clip_file(
+    Path(file): Path<String>,
+    Query(params): Query<AccessParams>,
+    State(state): State<AppState>,
+    request: Request,
+) -> Result<Response, AppError> {
+    let stream_key = file.split('/').next().unwrap_or_default();
+    check_viewer_access(&state, stream_key, params.access.as_deref()).await?;
+    let path = state.clip_manager.clip_path(&file).await
+        .map_err(|_| AppError::RecordingNotFound(file))?;
+    
+    serve_file(path, request).await
+}
+
+/// 列出进行中的合成
+async fn list_composites(State(state): State<AppState>) -> Json<Vec<CompositeInfo>> {
+    Json(state.composite_manager.list().await)
+}
+
+/// 将两路直播合成为新的流 (需要 admin_token)
+async fn create_composite(
+    State(state): State<AppState>,
+    Json(request): Json<CompositeRequest>,
+) -> Result<(StatusCode, Json<CompositeInfo>), AppError> {
+    let composite = state.composite_manager.create(request).await
+        .map_err(|e| match e {
+            StreamError::StreamNotFound(stream_key) => AppError::StreamNotFound(stream_key),
+            StreamError::Auth(stream_key) => AppError::Unauthorized(stream_key),
+            StreamError::Config(msg) => AppError::BadRequest(msg),
+            StreamError::InvalidStreamKey(stream_key) => AppError::BadRequest(format!("Invalid stream key: {}", stream_key)),
+            e => AppError::Internal(e.to_string()),
+        })?;
+    
+    Ok((StatusCode::CREATED, Json(composite)))
+}
+
+/// 停止合成，输出流随之结束 (需要 admin_token)
+async fn stop_composite(
+    Path(output_key): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<CompositeInfo>, AppError> {
+    let composite = state.composite_manager.stop(&output_key).await
+        .map_err(|_| AppError::StreamNotFound(output_key))?;
+    
+    Ok(Json(composite))
+}
+
+/// 列出点播录制 (支持 ?stream_key= 过滤)；与流列表一样不列出不公开和私有流的录制，
+/// 按流密钥过滤时列出可以观看的录制 (私有流需要 ?access=)
+async fn list_vod(
+    Query(params): Query<HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<VodEntry>>, AppError> {
+    let stream_key = params.get("stream_key").map(String::as_str);
+    let entries = state.vod_manager.list(stream_key).await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    
+    let access = params.get("access").map(String::as_str);
+    let mut visible = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let key = entry.metadata.stream_key.as_str();
+        let allowed = if stream_key.is_some() {
+            state.auth_manager.validate_viewer(key, access).await
+        } else {
+            state.auth_manager.is_listed(key).await
+        };
+        if allowed {
+            visible.push(entry);
+        }
+    }
+    
+    Ok(Json(visible))
+}
+
+/// 获取点播录制的元数据
+async fn get_vod(
+    Path(file): Path<String>,
+    Query(params): Query<AccessParams>,
+    State(state): State<AppState>,
+) -> Result<Json<VodEntry>, AppError> {
+    let entry = state.vod_manager.get(&file).await
+        .map_err(vod_error)?;
+    check_viewer_access(&state, &entry.metadata.stream_key, params.access.as_deref()).await?;
+    
+    Ok(Json(entry))
+}
+
+/// 删除点播录制 (需要 admin_token)
+async fn delete_vod(
+    Path(file): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<VodEntry>, AppError> {
+    let entry = state.vod_manager.delete(&file).await
+        .map_err(vod_error)?;
+    
+    Ok(Json(entry))
+}
+
+/// 点播播放 (支持 Range 请求，可直接拖动进度)
+async fn vod_file(
+    Path(file): Path<String>,
+    Query(params): Query<AccessParams>,
+    State(state): State<AppState>,
+    request: Request,
+) -> Result<Response, AppError> {
+    let entry = state.vod_manager.get(&file).await
+        .map_err(vod_error)?;
+    check_viewer_access(&state, &entry.metadata.stream_key, params.access.as_deref()).await?;
+    let path = state.vod_manager.playback_path(&file).await
+        .map_err(vod_error)?;
+    
+    serve_file(path, request).await
+}
+
+/// 返回本地文件，由 ServeFile 处理 Range 和条件请求
+async fn serve_file(path: std::path::PathBuf, request: Request) -> Result<Response, AppError> {
+    let response = ServeFile::new(path).oneshot(request).await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    
+    Ok(response.map(Body::new))
+}
+
+fn vod_error(e: StreamError) -> AppError {
+    match e {
+        StreamError::StreamNotFound(file) => AppError::RecordingNotFound(file),
+        e => AppError::Internal(e.to_string()),
+    }
+}
+
+/// 从请求中提取信令来源信息
+#[cfg(feature = "webrtc")]
+fn signal_peer(remote_addr: SocketAddr, headers: &HeaderMap) -> SignalPeer {
+    SignalPeer {
+        remote_addr,
+        user_agent: headers.get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string()),
+        viewer_id: None,
+    }
 }
 
 /// WebRTC 信令处理 (HTTP POST)
+#[cfg(feature = "webrtc")]
 async fn webrtc_signal(
     State(state): State<AppState>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(signal): Json<WebRtcSignal>,
 ) -> Result<Json<Option<WebRtcSignal>>, AppError> {
     debug!("Received WebRTC signal: {:?}", signal);
+    check_signal_access(&state, &signal).await?;
     
-    match state.webrtc_handler.handle_signal(signal).await {
+    let peer = signal_peer(remote_addr, &headers);
+    match state.webrtc_handler.handle_signal(signal, &peer).await {
         Ok(response) => Ok(Json(response)),
+        Err(StreamError::Overloaded(reason)) => {
+            Err(AppError::Overloaded(reason, state.overload_guard.retry_after()))
+        }
         Err(e) => {
             error!("WebRTC signal error: {}", e);
             Err(AppError::WebRtcError(e.to_string()))
@@ -163,14 +863,19 @@ async fn webrtc_signal(
 }
 
 /// WebRTC 信令处理 (WebSocket)
+#[cfg(feature = "webrtc")]
 async fn webrtc_websocket(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
 ) -> Response {
-    ws.on_upgrade(|socket| handle_webrtc_websocket(socket, state))
+    let peer = signal_peer(remote_addr, &headers);
+    ws.on_upgrade(move |socket| handle_webrtc_websocket(socket, state, peer))
 }
 
-async fn handle_webrtc_websocket(mut socket: WebSocket, state: AppState) {
+#[cfg(feature = "webrtc")]
+async fn handle_webrtc_websocket(mut socket: WebSocket, state: AppState, mut peer: SignalPeer) {
     info!("New WebRTC WebSocket connection");
     
     while let Some(msg) = socket.recv().await {
@@ -180,8 +885,15 @@ async fn handle_webrtc_websocket(mut socket: WebSocket, state: AppState) {
                     Ok(signal) => {
                         debug!("Received WebRTC signal via WebSocket: {:?}", signal);
                         
-                        match state.webrtc_handler.handle_signal(signal).await {
+                        let result = match check_signal_access(&state, &signal).await {
+                            Ok(()) => state.webrtc_handler.handle_signal(signal, &peer).await,
+                            Err(_) => Err(StreamError::Auth("Password or invite required".to_string())),
+                        };
+                        match result {
                             Ok(Some(response)) => {
+                                if let WebRtcSignal::Answer { viewer_id, .. } = &response {
+                                    peer.viewer_id = Some(*viewer_id);
+                                }
                                 if let Ok(response_text) = serde_json::to_string(&response) {
                                     if let Err(e) = socket.send(Message::Text(response_text)).await {
                                         error!("Failed to send WebSocket response: {}", e);
@@ -223,35 +935,323 @@ async fn handle_webrtc_websocket(mut socket: WebSocket, state: AppState) {
     }
 }
 
-/// HLS 播放列表
+/// 管理后台实时推送 (WebSocket)
+async fn admin_websocket(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+) -> Response {
+    ws.on_upgrade(|socket| handle_admin_websocket(socket, state))
+}
+
+async fn handle_admin_websocket(mut socket: WebSocket, state: AppState) {
+    info!("New admin WebSocket connection");
+    
+    let mut events = state.stream_manager.subscribe_events();
+    let mut stats_interval = tokio::time::interval(ADMIN_STATS_INTERVAL);
+    
+    loop {
+        let message = tokio::select! {
+            event = events.recv() => {
+                match event {
+                    Ok(event) => AdminFeedMessage::Event { event },
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Admin feed lagged behind event bus, skipped {} events", skipped);
+                        continue;
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+            _ = stats_interval.tick() => {
+                let streams = state.stream_manager.list_streams().await;
+                let mut stats = Vec::with_capacity(streams.len());
+                for (stream_key, stream) in streams {
+                    stats.push(AdminStreamStats {
+                        stream_key,
+                        stats: collect_stream_stats(&state, &stream).await,
+                    });
+                }
+                AdminFeedMessage::Stats { streams: stats }
+            }
+            msg = socket.recv() => {
+                match msg {
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Err(e)) => {
+                        debug!("Admin WebSocket error: {}", e);
+                        break;
+                    }
+                    // 忽略客户端发送的其他消息
+                    Some(Ok(_)) => continue,
+                }
+            }
+        };
+        
+        let text = match serde_json::to_string(&message) {
+            Ok(text) => text,
+            Err(e) => {
+                error!("Failed to serialize admin feed message: {}", e);
+                continue;
+            }
+        };
+        
+        if socket.send(Message::Text(text)).await.is_err() {
+            break;
+        }
+    }
+    
+    info!("Admin WebSocket connection closed");
+}
+
+/// HLS 播放列表，同时开始或延续观看分析的 HLS 会话
+#[cfg(feature = "hls")]
 async fn hls_playlist(
     Path(stream_key): Path<String>,
+    Query(params): Query<AccessParams>,
     State(state): State<AppState>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
 ) -> Result<String, AppError> {
-    let playlist = state.hls_manager.get_playlist(&stream_key).await
+    check_viewer_access(&state, &stream_key, params.access.as_deref()).await?;
+    
+    // 边缘模式下首次请求触发从源站拉流
+    // (拉流失败时仍尝试返回已有的播放列表，例如重启前遗留的录制)
+    if let Err(e) = state.relay_manager.ensure_stream(&stream_key).await {
+        debug!("No live stream for HLS request {}: {}", stream_key, e);
+    }
+    
+    let mut playlist = state.hls_manager.get_playlist(&stream_key).await
         .map_err(|e| AppError::HlsError(e.to_string()))?;
     
+    // 播放器请求片段时不会带上播放列表的查询参数，私有流的片段地址需要附加
+    if let Some(access) = params.access.as_deref().filter(|access| !access.is_empty()) {
+        playlist = with_segment_access(&playlist, access);
+    }
+    
+    // 按客户端地址和 User-Agent 区分观看者，开始新的会话前检查过载保护
+    let user_agent = headers.get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+    if !state.analytics_manager.has_hls_session(&stream_key, remote_addr, user_agent.as_deref()).await {
+        if let Err(StreamError::Overloaded(reason)) = state.overload_guard.check_viewer().await {
+            return Err(AppError::Overloaded(reason, state.overload_guard.retry_after()));
+        }
+    }
+    state.analytics_manager.touch_hls_session(&stream_key, remote_addr, user_agent).await;
+    
+    record_egress(&state, &stream_key, ViewProtocol::Hls, playlist.len()).await;
+    
     Ok(playlist)
 }
 
 /// HLS 片段
+#[cfg(feature = "hls")]
 async fn hls_segment(
     Path((stream_key, segment)): Path<(String, String)>,
+    Query(params): Query<AccessParams>,
     State(state): State<AppState>,
 ) -> Result<Vec<u8>, AppError> {
+    check_viewer_access(&state, &stream_key, params.access.as_deref()).await?;
+    
     let segment_data = state.hls_manager.get_segment(&stream_key, &segment).await
         .map_err(|e| AppError::HlsError(e.to_string()))?;
     
+    record_egress(&state, &stream_key, ViewProtocol::Hls, segment_data.len()).await;
+    
     Ok(segment_data)
 }
 
+/// 管理接口需要 Authorization: Bearer <admin_token>；浏览器的 WebSocket 不能设置请求头，也可以用 ?token=
+async fn require_admin(
+    State(state): State<AppState>,
+    Query(params): Query<AdminParams>,
+    request: Request,
+    next: middleware::Next,
+) -> Result<Response, AppError> {
+    let bearer = request.headers().get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if state.auth_manager.validate_admin(bearer.or(params.token.as_deref())) {
+        Ok(next.run(request).await)
+    } else {
+        Err(AppError::AdminRequired)
+    }
+}
+
+/// 私有流的播放路由需要观看密码或邀请 token (?access=)
+async fn check_viewer_access(state: &AppState, stream_key: &str, access: Option<&str>) -> Result<(), AppError> {
+    if state.auth_manager.validate_viewer(stream_key, access).await {
+        Ok(())
+    } else {
+        Err(AppError::Unauthorized(stream_key.to_string()))
+    }
+}
+
+/// WebRTC 观看请求在 Offer 中携带 access
+#[cfg(feature = "webrtc")]
+async fn check_signal_access(state: &AppState, signal: &WebRtcSignal) -> Result<(), AppError> {
+    match signal {
+        WebRtcSignal::Offer { stream_key, access, .. } => check_viewer_access(state, stream_key, access.as_deref()).await,
+        _ => Ok(()),
+    }
+}
+
+/// 为播放列表中的片段和初始化段地址附加 access 参数
+#[cfg(feature = "hls")]
+fn with_segment_access(playlist: &str, access: &str) -> String {
+    let query = access_query(Some(access));
+    playlist.lines()
+        .map(|line| {
+            if let Some((prefix, rest)) = line.split_once("URI=\"") {
+                match rest.split_once('"') {
+                    Some((uri, suffix)) => format!("{}URI=\"{}{}\"{}", prefix, uri, query, suffix),
+                    None => line.to_string(),
+                }
+            } else if line.is_empty() || line.starts_with('#') {
+                line.to_string()
+            } else {
+                format!("{}{}", line, query)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n") + "\n"
+}
+
+/// access 查询参数 (没有时为空字符串)
+fn access_query(access: Option<&str>) -> String {
+    match access.filter(|access| !access.is_empty()) {
+        Some(access) => format!("?access={}", percent_encode(access)),
+        None => String::new(),
+    }
+}
+
+/// URL 查询参数编码，保留 RFC 3986 的非保留字符
+fn percent_encode(value: &str) -> String {
+    value.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// 记录流出字节数到对应流的带宽统计
+#[cfg(feature = "hls")]
+async fn record_egress(state: &AppState, stream_key: &str, protocol: ViewProtocol, bytes: usize) {
+    if let Some(stream) = state.stream_manager.get_stream(stream_key).await {
+        stream.bandwidth.record_egress(protocol, bytes as u64).await;
+    }
+}
+
 // 数据结构
 
+/// 管理后台统计推送间隔
+const ADMIN_STATS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// 浏览器 WebRTC 抖动缓冲的典型延迟 (毫秒)
+const WEBRTC_JITTER_BUFFER_MS: u64 = 50;
+
+/// 管理后台推送消息
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AdminFeedMessage {
+    Event { event: StreamEvent },
+    Stats { streams: Vec<AdminStreamStats> },
+}
+
+#[derive(Serialize)]
+struct AdminStreamStats {
+    stream_key: String,
+    #[serde(flatten)]
+    stats: StreamStats,
+}
+
+#[derive(Deserialize)]
+struct AccessParams {
+    access: Option<String>, // 私有流的观看密码或邀请 token
+}
+
+
+#[derive(Deserialize)]
+struct AdminParams {
+    token: Option<String>, // 管理令牌 (用于不能设置请求头的 WebSocket)
+}
+
+#[derive(Deserialize)]
+struct InviteParams {
+    ttl: Option<u64>, // 有效期 (秒)
+}
+
+#[derive(Deserialize)]
+struct ClipParams {
+    duration: Option<u64>,
+    access: Option<String>, // 私有流的观看密码或邀请 token
+}
+
+/// 播放页推送消息，变体名为 SSE 的事件名
+#[derive(Serialize)]
+#[serde(untagged)]
+enum PlayerEvent {
+    Status { live: bool, status: StreamStatus },
+    Viewers { viewer_count: u32 },
+    Info { title: Option<String>, description: Option<String>, tags: BTreeMap<String, String> },
+}
+
+impl PlayerEvent {
+    /// 只保留指定流的、播放页关心的事件
+    fn from_stream_event(stream_key: &str, event: StreamEvent) -> Option<Self> {
+        match event {
+            StreamEvent::StatusChanged { stream_key: key, status } if key == stream_key => {
+                let live = matches!(status, StreamStatus::Live);
+                Some(PlayerEvent::Status { live, status })
+            }
+            StreamEvent::StreamRemoved { stream_key: key } if key == stream_key => {
+                Some(PlayerEvent::Status { live: false, status: StreamStatus::Stopped })
+            }
+            StreamEvent::ViewerJoined { stream_key: key, viewer_count, .. }
+            | StreamEvent::ViewerLeft { stream_key: key, viewer_count, .. } if key == stream_key => {
+                Some(PlayerEvent::Viewers { viewer_count })
+            }
+            StreamEvent::InfoChanged { stream_key: key, info } if key == stream_key => {
+                Some(PlayerEvent::Info { title: info.title, description: info.description, tags: info.tags })
+            }
+            _ => None,
+        }
+    }
+    
+    fn into_sse(self) -> Result<Event, axum::Error> {
+        let name = match self {
+            PlayerEvent::Status { .. } => "status",
+            PlayerEvent::Viewers { .. } => "viewers",
+            PlayerEvent::Info { .. } => "info",
+        };
+        Event::default().event(name).json_data(&self)
+    }
+}
+
+#[derive(Deserialize)]
+struct RenditionParams {
+    rendition: Rendition,
+}
+
+#[derive(Serialize)]
+struct PlaybackInfo {
+    stream_key: String,
+    instance_id: String,
+    hls_url: Option<String>, // 服务器未启用对应的观看协议时为 null
+    webrtc_signal_url: Option<String>,
+    webrtc_ws_url: Option<String>,
+    latency_mode: LatencyMode,
+    /// 超低延迟模式下优先使用 WebRTC 观看
+    preferred_protocol: ViewProtocol,
+}
+
 #[derive(Serialize)]
 struct StreamStats {
     viewer_count: u32,
     status: game_stream_common::StreamStatus,
     uptime: i64, // seconds
+    bandwidth: BandwidthSnapshot,
+    latency: LatencySnapshot,
+    client: Option<ClientStats>, // 推流端上报的统计，未开启上报时为 null
 }
 
 // 错误处理
@@ -259,8 +1259,16 @@ struct StreamStats {
 #[derive(Debug)]
 enum AppError {
     StreamNotFound(String),
+    RecordingNotFound(String),
+    BadRequest(String),
+    #[cfg(feature = "webrtc")]
     WebRtcError(String),
+    #[cfg(feature = "hls")]
     HlsError(String),
+    #[cfg_attr(not(any(feature = "webrtc", feature = "hls")), allow(dead_code))]
+    Overloaded(String, u64),
+    Unauthorized(String),
+    AdminRequired,
     Internal(String),
 }
 
@@ -270,12 +1278,37 @@ impl IntoResponse for AppError {
             AppError::StreamNotFound(stream_key) => {
                 (StatusCode::NOT_FOUND, format!("Stream not found: {}", stream_key))
             }
+            AppError::RecordingNotFound(file) => {
+                (StatusCode::NOT_FOUND, format!("Recording not found: {}", file))
+            }
+            AppError::BadRequest(msg) => {
+                (StatusCode::BAD_REQUEST, msg)
+            }
+            #[cfg(feature = "webrtc")]
             AppError::WebRtcError(msg) => {
                 (StatusCode::BAD_REQUEST, format!("WebRTC error: {}", msg))
             }
+            #[cfg(feature = "hls")]
             AppError::HlsError(msg) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, format!("HLS error: {}", msg))
             }
+            AppError::Overloaded(msg, retry_after) => {
+                return (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    [(header::RETRY_AFTER, retry_after.to_string())],
+                    Json(serde_json::json!({ "error": format!("Server overloaded: {}", msg) })),
+                ).into_response();
+            }
+            AppError::Unauthorized(stream_key) => {
+                (StatusCode::UNAUTHORIZED, format!("Password or invite required for stream: {}", stream_key))
+            }
+            AppError::AdminRequired => {
+                return (
+                    StatusCode::UNAUTHORIZED,
+                    [(header::WWW_AUTHENTICATE, "Bearer")],
+                    Json(serde_json::json!({ "error": "Admin token required" })),
+                ).into_response();
+            }
             AppError::Internal(msg) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, format!("Internal error: {}", msg))
             }
@@ -0,0 +1,100 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use game_stream_common::{
+    AudioConfig, LiveStream, MediaPacket, StreamError, StreamInfo, StreamManager, StreamResult, StreamStatus, VideoConfig,
+};
+use crate::auth::AuthManager;
+use crate::overload::OverloadGuard;
+
+/// 第三方推流协议 (如专有采集卡、私有传输协议) 的扩展点，通过 StreamingServer::register_ingest 注册
+///
+/// 服务器启动时先调用所有协议的 bind，全部成功后才发出就绪通知，之后在后台运行 serve；
+/// serve 返回或出错时服务器停止，与内置的 RTMP 服务相同
+#[async_trait]
+pub trait IngestProtocol: Send + Sync + 'static {
+    /// 协议名称，用于日志
+    fn name(&self) -> &str;
+
+    /// 绑定监听端口或打开设备
+    async fn bind(&mut self) -> Result<()>;
+
+    /// 接受推流，通过 context.publish 发布流并送入媒体包
+    async fn serve(self: Box<Self>, context: IngestContext) -> Result<()>;
+}
+
+/// 推流协议发布流时使用的服务器组件，流密钥认证和过载保护与 RTMP 推流相同
+#[derive(Clone)]
+pub struct IngestContext {
+    stream_manager: Arc<StreamManager>,
+    auth_manager: Arc<AuthManager>,
+    overload_guard: Arc<OverloadGuard>,
+}
+
+impl IngestContext {
+    pub(crate) fn new(
+        stream_manager: Arc<StreamManager>,
+        auth_manager: Arc<AuthManager>,
+        overload_guard: Arc<OverloadGuard>,
+    ) -> Self {
+        Self { stream_manager, auth_manager, overload_guard }
+    }
+
+    pub fn stream_manager(&self) -> &Arc<StreamManager> {
+        &self.stream_manager
+    }
+
+    /// 验证流密钥并检查过载后创建直播流，推流结束时调用 Publication::unpublish
+    pub async fn publish(&self, stream_key: &str, video_config: VideoConfig, audio_config: AudioConfig) -> StreamResult<Publication> {
+        if !self.auth_manager.validate_stream_key(stream_key).await {
+            warn!("Invalid stream key: {}", stream_key);
+            return Err(StreamError::Auth(format!("Invalid stream key: {}", stream_key)));
+        }
+        self.overload_guard.check_publish().await?;
+
+        let stream_info = StreamInfo {
+            stream_id: Uuid::new_v4(),
+            stream_key: stream_key.to_string(),
+            title: None,
+            description: None,
+            created_at: chrono::Utc::now(),
+            is_live: false,
+            viewer_count: 0,
+            video_config,
+            audio_config,
+            thumbnail_url: None,
+            tags: Default::default(),
+        };
+        let stream = self.stream_manager.create_stream(stream_key.to_string(), stream_info).await?;
+        stream.set_status(StreamStatus::Live).await;
+
+        Ok(Publication { stream, stream_manager: self.stream_manager.clone() })
+    }
+}
+
+/// 正在发布的流
+pub struct Publication {
+    stream: Arc<LiveStream>,
+    stream_manager: Arc<StreamManager>,
+}
+
+impl Publication {
+    /// 直播流本身，可用于上报推流端统计、订阅远程输入等
+    pub fn stream(&self) -> &Arc<LiveStream> {
+        &self.stream
+    }
+
+    pub async fn send(&self, packet: MediaPacket) -> StreamResult<()> {
+        self.stream.send_media_packet(packet).await
+    }
+
+    /// 停止并移除直播流
+    pub async fn unpublish(self) {
+        self.stream.set_status(StreamStatus::Stopped).await;
+        self.stream_manager.remove_stream(&self.stream.stream_key).await;
+        info!("Stream {} stopped", self.stream.stream_key);
+    }
+}
@@ -0,0 +1,32 @@
+pub mod server;
+pub mod rtmp;
+#[cfg(feature = "webrtc")]
+pub mod webrtc;
+pub mod http;
+pub mod auth;
+#[cfg(feature = "hls")]
+pub mod hls;
+pub mod analytics;
+pub mod webhook;
+pub mod systemd;
+pub mod overload;
+pub mod relay;
+pub mod cluster;
+pub mod restream;
+pub mod push_pool;
+pub mod recording;
+pub mod recording_upload;
+pub mod vod;
+pub mod clip;
+pub mod thumbnail;
+pub mod retention;
+pub mod composite;
+pub mod ingest;
+mod access_log;
+#[cfg_attr(not(feature = "hls"), allow(dead_code))] // 片段存储只有 HLS 使用，路径检查等工具函数其他模块也会用到
+mod segment_store;
+mod recording_rules;
+
+pub use server::{StreamingServer, StreamingServerBuilder};
+pub use auth::{AuthManager, StreamKeyValidator};
+pub use ingest::{IngestContext, IngestProtocol, Publication};
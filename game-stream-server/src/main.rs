@@ -3,15 +3,8 @@ use clap::Parser;
 use tracing::{info, error};
 use tracing_subscriber;
 
-mod server;
-mod rtmp;
-mod webrtc;
-mod http;
-mod auth;
-mod hls;
-
-use server::StreamingServer;
-use game_stream_common::ServerConfig;
+use game_stream_server::{systemd, StreamingServer};
+use game_stream_common::{LatencyMode, ServerConfig};
 
 #[derive(Parser)]
 #[command(name = "game-stream-server")]
@@ -32,12 +25,30 @@ struct Args {
     /// Enable verbose logging
     #[arg(short, long)]
     verbose: bool,
+    
+    /// Print the commented default configuration and exit
+    #[arg(long)]
+    print_default_config: bool,
+    
+    /// Print the JSON Schema of the configuration file and exit
+    #[arg(long)]
+    print_config_schema: bool,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
     
+    // 只输出到标准输出，便于重定向到文件，不初始化日志
+    if args.print_default_config {
+        print!("{}", ServerConfig::TEMPLATE);
+        return Ok(());
+    }
+    if args.print_config_schema {
+        println!("{:#}", ServerConfig::json_schema());
+        return Ok(());
+    }
+    
     // Initialize logging
     let log_level = if args.verbose { "debug" } else { "info" };
     tracing_subscriber::fmt()
@@ -59,13 +70,17 @@ async fn main() -> Result<()> {
     if let Some(http_port) = args.http_port {
         config.http.port = http_port;
     }
+    if config.latency_mode == LatencyMode::UltraLow {
+        info!("Ultra-low latency mode: 1 s HLS segments starting at the live edge, WebRTC preferred");
+        config.apply_latency_mode();
+    }
     
     info!("Configuration loaded: {:?}", config);
     
     // Create and start streaming server
     let mut server = StreamingServer::new(config).await?;
     
-    // Handle Ctrl+C gracefully
+    // Handle Ctrl+C / SIGTERM gracefully
     let server_handle = tokio::spawn(async move {
         if let Err(e) = server.start().await {
             error!("Streaming server error: {}", e);
@@ -73,8 +88,9 @@ async fn main() -> Result<()> {
     });
     
     tokio::select! {
-        _ = tokio::signal::ctrl_c() => {
-            info!("Received Ctrl+C, shutting down...");
+        _ = shutdown_signal() => {
+            info!("Received shutdown signal, shutting down...");
+            systemd::notify_stopping();
         }
         _ = server_handle => {
             info!("Server finished");
@@ -85,6 +101,27 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// 等待 Ctrl+C 或 SIGTERM (systemd 停止服务时发送)
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = sigterm.recv() => {}
+                }
+                return;
+            }
+            Err(e) => error!("Failed to install SIGTERM handler: {}", e),
+        }
+    }
+    
+    let _ = tokio::signal::ctrl_c().await;
+}
+
 fn load_config(path: &str) -> Result<ServerConfig> {
     let content = std::fs::read_to_string(path)?;
     let config: ServerConfig = toml::from_str(&content)?;
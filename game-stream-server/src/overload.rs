@@ -0,0 +1,146 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+#[cfg(feature = "system-metrics")]
+use std::time::Duration;
+#[cfg(feature = "system-metrics")]
+use sysinfo::System;
+use tracing::{info, warn};
+#[cfg(feature = "system-metrics")]
+use tracing::debug;
+
+use game_stream_common::{OverloadConfig, StreamManager, StreamError, StreamResult};
+
+/// 过载保护 - 资源紧张时拒绝新的推流和观看者，保持已有会话
+pub struct OverloadGuard {
+    config: OverloadConfig,
+    stream_manager: Arc<StreamManager>,
+    // 以百分比 * 100 存储，便于原子更新
+    cpu_usage: AtomicU32,
+    memory_usage: AtomicU32,
+}
+
+impl OverloadGuard {
+    pub fn new(config: &OverloadConfig, stream_manager: Arc<StreamManager>) -> Self {
+        info!("Initializing overload guard...");
+
+        Self {
+            config: config.clone(),
+            stream_manager,
+            cpu_usage: AtomicU32::new(0),
+            memory_usage: AtomicU32::new(0),
+        }
+    }
+
+    /// 周期性采样 CPU 和内存使用率
+    pub async fn start(self: Arc<Self>) {
+        if !self.config.enabled {
+            info!("Overload protection disabled");
+            return;
+        }
+        self.sample_resources().await;
+    }
+
+    #[cfg(feature = "system-metrics")]
+    async fn sample_resources(&self) {
+        let mut system = System::new();
+        let mut interval = tokio::time::interval(Duration::from_secs(self.config.sample_interval.max(1)));
+
+        loop {
+            interval.tick().await;
+
+            system.refresh_cpu_usage();
+            system.refresh_memory();
+
+            let cpu = system.global_cpu_usage();
+            let memory = if system.total_memory() > 0 {
+                system.used_memory() as f32 / system.total_memory() as f32 * 100.0
+            } else {
+                0.0
+            };
+
+            self.cpu_usage.store((cpu * 100.0) as u32, Ordering::Relaxed);
+            self.memory_usage.store((memory * 100.0) as u32, Ordering::Relaxed);
+
+            debug!("Resource usage: cpu {:.1}%, memory {:.1}%", cpu, memory);
+        }
+    }
+
+    /// 未启用 system-metrics 特性时不采样，CPU 和内存使用率保持为 0
+    #[cfg(not(feature = "system-metrics"))]
+    async fn sample_resources(&self) {
+        info!("CPU and memory sampling is not available, the server was built without the system-metrics feature");
+    }
+
+    /// 检查是否允许新的推流
+    pub async fn check_publish(&self) -> StreamResult<()> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        self.check_resources()?;
+
+        let stream_count = self.stream_manager.list_streams().await.len() as u32;
+        if stream_count >= self.config.max_streams {
+            return Err(self.reject(format!("stream limit reached ({}/{})", stream_count, self.config.max_streams)));
+        }
+
+        Ok(())
+    }
+
+    /// 检查是否允许新的观看者
+    pub async fn check_viewer(&self) -> StreamResult<()> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        self.check_resources()?;
+
+        let mut viewer_count = 0;
+        for (_, stream) in self.stream_manager.list_streams().await {
+            viewer_count += stream.get_viewer_count().await;
+        }
+
+        if viewer_count >= self.config.max_viewers {
+            return Err(self.reject(format!("viewer limit reached ({}/{})", viewer_count, self.config.max_viewers)));
+        }
+
+        Ok(())
+    }
+
+    /// 当前 CPU 和内存使用率 (%)
+    pub fn resource_usage(&self) -> (f32, f32) {
+        (
+            self.cpu_usage.load(Ordering::Relaxed) as f32 / 100.0,
+            self.memory_usage.load(Ordering::Relaxed) as f32 / 100.0,
+        )
+    }
+
+    /// 最大同时观看数
+    pub fn max_viewers(&self) -> u32 {
+        self.config.max_viewers
+    }
+
+    /// 被拒绝的观看者应等待的秒数
+    pub fn retry_after(&self) -> u64 {
+        self.config.retry_after
+    }
+
+    fn check_resources(&self) -> StreamResult<()> {
+        let cpu = self.cpu_usage.load(Ordering::Relaxed) as f32 / 100.0;
+        if cpu >= self.config.max_cpu_percent {
+            return Err(self.reject(format!("cpu usage {:.1}% above {:.1}%", cpu, self.config.max_cpu_percent)));
+        }
+
+        let memory = self.memory_usage.load(Ordering::Relaxed) as f32 / 100.0;
+        if memory >= self.config.max_memory_percent {
+            return Err(self.reject(format!("memory usage {:.1}% above {:.1}%", memory, self.config.max_memory_percent)));
+        }
+
+        Ok(())
+    }
+
+    fn reject(&self, reason: String) -> StreamError {
+        warn!("Rejecting new session: {}", reason);
+        StreamError::Overloaded(reason)
+    }
+}
@@ -0,0 +1,182 @@
+use std::sync::Arc;
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, RwLock, Semaphore};
+use tracing::{info, debug};
+
+use game_stream_common::{PushPoolConfig, StreamResult, StreamError};
+
+/// 空闲连接清理间隔
+const IDLE_SWEEP_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 下游推流连接池 - 按主机复用 TCP 连接并限制并发连接数
+///
+/// 大量流同时重连同一主机时，超出上限的连接排队等待，而不是一起涌向下游
+pub struct PushPool {
+    config: PushPoolConfig,
+    hosts: RwLock<HashMap<String, Arc<HostPool>>>,
+}
+
+/// 单个主机的连接池
+struct HostPool {
+    permits: Arc<Semaphore>,
+    idle: Mutex<Vec<IdleConnection>>,
+}
+
+struct IdleConnection {
+    socket: TcpStream,
+    permit: OwnedSemaphorePermit,
+    since: Instant,
+}
+
+/// 从连接池取出的连接，丢弃时关闭，调用 `PushPool::release` 时放回池中
+pub struct PooledConnection {
+    socket: TcpStream,
+    addr: String,
+    permit: OwnedSemaphorePermit,
+    reused: bool,
+}
+
+impl PushPool {
+    pub fn new(config: &PushPoolConfig) -> Self {
+        info!("Initializing push connection pool...");
+
+        Self {
+            config: config.clone(),
+            hosts: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &PushPoolConfig {
+        &self.config
+    }
+
+    /// 获取到指定主机的连接，优先复用空闲连接
+    pub async fn connect(&self, addr: &str, timeout: Duration) -> StreamResult<PooledConnection> {
+        let host = self.host(addr).await;
+
+        if let Some(connection) = host.take_idle(self.idle_timeout()).await {
+            debug!("Reusing pooled connection to {}", addr);
+            return Ok(PooledConnection {
+                socket: connection.socket,
+                addr: addr.to_string(),
+                permit: connection.permit,
+                reused: true,
+            });
+        }
+
+        // 等待连接名额和建立连接共用同一个超时
+        let connect = async {
+            let permit = host.permits.clone().acquire_owned().await
+                .map_err(|_| StreamError::Internal("Push pool closed".to_string()))?;
+            let socket = TcpStream::connect(addr).await
+                .map_err(|e| StreamError::Network(format!("Failed to connect to {}: {}", addr, e)))?;
+            socket.set_nodelay(true)?;
+            Ok::<_, StreamError>((socket, permit))
+        };
+
+        let (socket, permit) = tokio::time::timeout(timeout, connect).await
+            .map_err(|_| StreamError::Network(format!("Timed out connecting to {}", addr)))??;
+
+        Ok(PooledConnection {
+            socket,
+            addr: addr.to_string(),
+            permit,
+            reused: false,
+        })
+    }
+
+    /// 将仍然可用的连接放回池中
+    pub async fn release(&self, connection: PooledConnection) {
+        let host = self.host(&connection.addr).await;
+        let mut idle = host.idle.lock().await;
+
+        if idle.len() >= self.config.max_idle_per_host || !is_alive(&connection.socket) {
+            return;
+        }
+
+        debug!("Returning connection to {} to pool", connection.addr);
+        idle.push(IdleConnection {
+            socket: connection.socket,
+            permit: connection.permit,
+            since: Instant::now(),
+        });
+    }
+
+    /// 定期关闭超时的空闲连接
+    pub async fn start(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(IDLE_SWEEP_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let hosts: Vec<Arc<HostPool>> = self.hosts.read().await.values().cloned().collect();
+            for host in hosts {
+                host.idle.lock().await
+                    .retain(|connection| connection.since.elapsed() < self.idle_timeout());
+            }
+        }
+    }
+
+    async fn host(&self, addr: &str) -> Arc<HostPool> {
+        if let Some(host) = self.hosts.read().await.get(addr) {
+            return host.clone();
+        }
+
+        self.hosts.write().await
+            .entry(addr.to_string())
+            .or_insert_with(|| Arc::new(HostPool {
+                permits: Arc::new(Semaphore::new(self.config.max_connections_per_host.max(1))),
+                idle: Mutex::new(Vec::new()),
+            }))
+            .clone()
+    }
+
+    fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.config.idle_timeout)
+    }
+}
+
+impl HostPool {
+    /// 取出一个未超时且对端未关闭的空闲连接
+    async fn take_idle(&self, idle_timeout: Duration) -> Option<IdleConnection> {
+        let mut idle = self.idle.lock().await;
+
+        while let Some(connection) = idle.pop() {
+            if connection.since.elapsed() < idle_timeout && is_alive(&connection.socket) {
+                return Some(connection);
+            }
+        }
+
+        None
+    }
+}
+
+impl PooledConnection {
+    /// 是否复用了池中的连接
+    pub fn is_reused(&self) -> bool {
+        self.reused
+    }
+}
+
+impl Deref for PooledConnection {
+    type Target = TcpStream;
+
+    fn deref(&self) -> &TcpStream {
+        &self.socket
+    }
+}
+
+impl DerefMut for PooledConnection {
+    fn deref_mut(&mut self) -> &mut TcpStream {
+        &mut self.socket
+    }
+}
+
+/// 检查连接是否仍然可用 (对端未关闭且没有未读数据)
+fn is_alive(socket: &TcpStream) -> bool {
+    let mut buf = [0u8; 1];
+    matches!(socket.try_read(&mut buf), Err(e) if e.kind() == std::io::ErrorKind::WouldBlock)
+}
@@ -0,0 +1,604 @@
+use std::sync::Arc;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tokio::fs::{self, File};
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+use tokio::process::{Child, Command};
+use tokio::sync::{RwLock, broadcast, oneshot};
+use tracing::{info, debug, warn, error};
+
+use game_stream_common::{
+    RecordingConfig, RecordingFormat, RecordingRuleset, RecordingRuleAction, StreamManager, StreamEvent, StreamStatus, MediaPacket, MediaReceiver,
+    StreamResult, StreamError, recv_media
+};
+use crate::recording_rules;
+use crate::recording_upload::{RecordingUploader, RecordingUpload};
+use crate::webhook::{WebhookManager, WebhookEventKind};
+use crate::segment_store::is_safe_path_component;
+
+/// 同一模板下尝试的最大序号
+const MAX_RECORDING_SEQ: u32 = 9999;
+
+/// 输入结束后等待 FFmpeg 写完文件尾的最长时间
+const MUXER_EXIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 录制管理器 - 流开始直播时按配置自动录制，结束时完成文件
+pub struct RecordingManager {
+    config: RecordingConfig,
+    stream_manager: Arc<StreamManager>,
+    uploader: Option<Arc<RecordingUploader>>,
+    webhook_manager: Arc<WebhookManager>,
+    rules: RwLock<RecordingRuleset>,
+    recordings: RwLock<HashMap<String, ActiveRecording>>,
+}
+
+/// 进行中的录制
+struct ActiveRecording {
+    status: Arc<RwLock<RecordingStatus>>,
+    stop: oneshot::Sender<()>,
+}
+
+/// 录制输出
+enum RecordingOutput {
+    /// 直接写入 FLV 文件
+    File(File),
+    /// 写入 FFmpeg 的标准输入，由 FFmpeg 实时封装
+    Muxer(Child),
+}
+
+/// 录制状态
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingStatus {
+    pub stream_key: String,
+    pub file: String, // 相对录制目录的路径
+    pub format: RecordingFormat,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub duration_ms: u64,
+    pub bytes_written: u64,
+    pub packets_written: u64,
+}
+
+/// 录制完成后与录制文件一起写入的元数据 (`<文件名>.json`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingMetadata {
+    pub stream_key: String,
+    pub file: String,
+    pub format: RecordingFormat,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub ended_at: chrono::DateTime<chrono::Utc>,
+    pub duration_ms: u64,
+    pub size_bytes: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upload: Option<RecordingUpload>, // 上传到对象存储后填写
+}
+
+impl RecordingManager {
+    pub fn new(
+        config: &RecordingConfig,
+        stream_manager: Arc<StreamManager>,
+        uploader: Option<Arc<RecordingUploader>>,
+        webhook_manager: Arc<WebhookManager>,
+    ) -> Self {
+        info!("Initializing recording manager...");
+
+        let rules = RecordingRuleset {
+            rules: config.rules.clone(),
+            tags: config.tags.clone(),
+        };
+        if let Err(e) = recording_rules::validate(&rules) {
+            warn!("{}; the rule will never match", e);
+        }
+
+        Self {
+            config: config.clone(),
+            stream_manager,
+            uploader,
+            webhook_manager,
+            rules: RwLock::new(rules),
+            recordings: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 订阅事件总线，流开始直播时按配置开始录制，结束时完成录制
+    pub async fn start(self: Arc<Self>) {
+        let mut events = self.stream_manager.subscribe_events();
+
+        loop {
+            match events.recv().await {
+                Ok(StreamEvent::StatusChanged { stream_key, status }) => match status {
+                    StreamStatus::Live => {
+                        // 同名流重新推流时结束上一段录制
+                        self.stop_recording(&stream_key).await;
+
+                        if self.should_record(&stream_key).await {
+                            if let Err(e) = self.start_recording(&stream_key).await {
+                                error!("Failed to start recording for stream {}: {}", stream_key, e);
+                            }
+                        }
+                    }
+                    StreamStatus::Stopped | StreamStatus::Error(_) => {
+                        self.stop_recording(&stream_key).await;
+                    }
+                    _ => {}
+                },
+                Ok(StreamEvent::StreamRemoved { stream_key }) => {
+                    self.stop_recording(&stream_key).await;
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Recording manager lagged behind event bus, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+
+    /// 开始录制 (已在录制时返回当前状态)
+    pub async fn start_recording(&self, stream_key: &str) -> StreamResult<RecordingStatus> {
+        let stream = self.stream_manager.get_stream(stream_key).await
+            .ok_or_else(|| StreamError::StreamNotFound(stream_key.to_string()))?;
+
+        if !matches!(stream.get_status().await, StreamStatus::Live) {
+            return Err(StreamError::Internal(format!("Stream {} is not live", stream_key)));
+        }
+
+        let mut recordings = self.recordings.write().await;
+        if let Some(recording) = recordings.get(stream_key) {
+            if !recording.stop.is_closed() {
+                return Ok(recording.status.read().await.clone());
+            }
+        }
+
+        let (file, path) = self.next_recording_path(stream_key).await?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let (output, write_path, format) = open_output(&self.config, &path).await?;
+        // 退回 FLV 录制时文件名随之改变
+        let file = if format == self.config.format {
+            file
+        } else {
+            Path::new(&file).with_extension(format.extension()).to_string_lossy().into_owned()
+        };
+
+        info!("Recording stream {} to {}", stream_key, write_path.display());
+
+        let status = Arc::new(RwLock::new(RecordingStatus {
+            stream_key: stream_key.to_string(),
+            file,
+            format,
+            started_at: chrono::Utc::now(),
+            duration_ms: 0,
+            bytes_written: 0,
+            packets_written: 0,
+        }));
+        let (stop, stop_receiver) = oneshot::channel();
+
+        tokio::spawn(run_recording(
+            self.config.clone(),
+            self.uploader.clone(),
+            self.webhook_manager.clone(),
+            path,
+            write_path,
+            output,
+            stream.subscribe_media(),
+            stop_receiver,
+            status.clone(),
+        ));
+
+        let snapshot = status.read().await.clone();
+        recordings.insert(stream_key.to_string(), ActiveRecording { status, stop });
+
+        Ok(snapshot)
+    }
+
+    /// 停止录制，返回停止前的状态
+    pub async fn stop_recording(&self, stream_key: &str) -> Option<RecordingStatus> {
+        let recording = self.recordings.write().await.remove(stream_key)?;
+        let status = recording.status.read().await.clone();
+
+        // 录制任务收到信号后写完缓冲区并完成文件
+        let _ = recording.stop.send(());
+
+        Some(status)
+    }
+
+    /// 获取进行中的录制状态
+    pub async fn get_status(&self, stream_key: &str) -> Option<RecordingStatus> {
+        let recordings = self.recordings.read().await;
+        let recording = recordings.get(stream_key)?;
+        if recording.stop.is_closed() {
+            return None;
+        }
+
+        let status = recording.status.read().await.clone();
+        Some(status)
+    }
+
+    /// 获取当前的录制规则集
+    pub async fn get_rules(&self) -> RecordingRuleset {
+        self.rules.read().await.clone()
+    }
+
+    /// 替换录制规则集，对之后开始直播的流生效
+    pub async fn set_rules(&self, ruleset: RecordingRuleset) -> StreamResult<()> {
+        recording_rules::validate(&ruleset)?;
+
+        info!("Updated recording rules ({} rules, {} tagged streams)", ruleset.rules.len(), ruleset.tags.len());
+        *self.rules.write().await = ruleset;
+        Ok(())
+    }
+
+    /// 流是否需要自动录制 (按流密钥的覆盖 > 录制规则 > 全局默认)
+    async fn should_record(&self, stream_key: &str) -> bool {
+        if let Some(record) = self.config.overrides.get(stream_key) {
+            return *record;
+        }
+
+        let rules = self.rules.read().await;
+        match recording_rules::evaluate(&rules, stream_key, chrono::Local::now().time()) {
+            Some(rule) => {
+                debug!("Recording rule {:?} matched stream {}: {:?}", rule.name, stream_key, rule.action);
+                rule.action == RecordingRuleAction::Record
+            }
+            None => self.config.enabled,
+        }
+    }
+
+    /// 根据文件名模板选择一个尚未使用的录制路径，返回 (相对路径, 完整路径)
+    async fn next_recording_path(&self, stream_key: &str) -> StreamResult<(String, PathBuf)> {
+        if !is_safe_path_component(stream_key) {
+            return Err(StreamError::Config(format!("Invalid stream key for recording: {}", stream_key)));
+        }
+
+        let now = chrono::Utc::now();
+        let date = now.format("%Y-%m-%d").to_string();
+        let time = now.format("%H%M%S").to_string();
+        let template = &self.config.filename_template;
+
+        for seq in 1..=MAX_RECORDING_SEQ {
+            let file = template
+                .replace("{key}", stream_key)
+                .replace("{date}", &date)
+                .replace("{time}", &time)
+                .replace("{seq}", &seq.to_string())
+                .replace("{ext}", self.config.format.extension());
+
+            // 模板展开后不能跳出录制目录
+            if !Path::new(&file).components().all(|component| matches!(component, Component::Normal(_))) {
+                return Err(StreamError::Config(format!("Invalid recording filename template: {}", template)));
+            }
+
+            let path = PathBuf::from(&self.config.dir).join(&file);
+            if !fs::try_exists(&path).await? && !fs::try_exists(path.with_extension("flv")).await? {
+                return Ok((file, path));
+            }
+
+            if !template.contains("{seq}") {
+                break;
+            }
+        }
+
+        Err(StreamError::Internal(format!("No free recording filename for stream {}", stream_key)))
+    }
+}
+
+/// 录制任务：写入媒体数据直到收到停止信号或流结束，然后完成文件
+#[allow(clippy::too_many_arguments)]
+async fn run_recording(
+    config: RecordingConfig,
+    uploader: Option<Arc<RecordingUploader>>,
+    webhook_manager: Arc<WebhookManager>,
+    path: PathBuf,
+    write_path: PathBuf,
+    output: RecordingOutput,
+    media_receiver: MediaReceiver,
+    stop: oneshot::Receiver<()>,
+    status: Arc<RwLock<RecordingStatus>>,
+) {
+    let stream_key = status.read().await.stream_key.clone();
+
+    let result = match output {
+        RecordingOutput::File(file) => record(BufWriter::new(file), media_receiver, stop, &status).await,
+        RecordingOutput::Muxer(mut child) => {
+            let result = match child.stdin.take() {
+                Some(stdin) => record(BufWriter::new(stdin), media_receiver, stop, &status).await,
+                None => Err(StreamError::Internal("ffmpeg stdin unavailable".to_string())),
+            };
+            finish_muxer(&mut child, &write_path).await;
+            result
+        }
+    };
+    if let Err(e) = result {
+        error!("Recording of stream {} failed: {}", stream_key, e);
+    }
+
+    let mut final_path = write_path.clone();
+    let mut format = status.read().await.format;
+    if format == RecordingFormat::Mp4 {
+        match remux_to_mp4(&config.ffmpeg_path, &write_path, &path).await {
+            Ok(()) => {
+                let _ = fs::remove_file(&write_path).await;
+                final_path = path;
+            }
+            Err(e) => {
+                warn!("Failed to remux recording {} to MP4, keeping FLV: {}", write_path.display(), e);
+                format = RecordingFormat::Flv;
+            }
+        }
+    }
+
+    let status = status.read().await.clone();
+    let size_bytes = fs::metadata(&final_path).await.map(|metadata| metadata.len()).unwrap_or(status.bytes_written);
+    let file = final_path.strip_prefix(&config.dir)
+        .map(|file| file.to_string_lossy().into_owned())
+        .unwrap_or(status.file);
+
+    let metadata = RecordingMetadata {
+        stream_key: stream_key.clone(),
+        format,
+        file,
+        started_at: status.started_at,
+        ended_at: chrono::Utc::now(),
+        duration_ms: status.duration_ms,
+        size_bytes,
+        upload: None,
+    };
+
+    if let Err(e) = write_metadata(&final_path, &metadata).await {
+        warn!("Failed to write recording metadata for {}: {}", final_path.display(), e);
+    }
+
+    info!("Recording of stream {} finished: {} ({} bytes, {} ms)",
+          stream_key, final_path.display(), metadata.size_bytes, metadata.duration_ms);
+
+    // 无论是否配置上传，元数据写入后都通知录制完成
+    webhook_manager.dispatch(WebhookManager::payload(WebhookEventKind::RecordingFinished, stream_key, serde_json::json!({
+        "file": metadata.file,
+        "format": metadata.format,
+        "started_at": metadata.started_at,
+        "ended_at": metadata.ended_at,
+        "duration_ms": metadata.duration_ms,
+        "size_bytes": metadata.size_bytes,
+    })));
+
+    if let Some(uploader) = uploader {
+        upload_recording(&uploader, &final_path, metadata).await;
+    }
+}
+
+/// 上传完成的录制，成功后在元数据中记录对象地址
+async fn upload_recording(uploader: &RecordingUploader, path: &Path, mut metadata: RecordingMetadata) {
+    let Ok(upload) = uploader.upload(path, &metadata).await else {
+        return;
+    };
+
+    metadata.upload = Some(upload);
+    if let Err(e) = write_metadata(path, &metadata).await {
+        warn!("Failed to write recording metadata for {}: {}", path.display(), e);
+    }
+
+    // 元数据保留在本地，记录文件上传到了哪里
+    if uploader.delete_local() {
+        if let Err(e) = fs::remove_file(path).await {
+            warn!("Failed to delete uploaded recording {}: {}", path.display(), e);
+        }
+    }
+}
+
+async fn record<W: AsyncWrite + Unpin>(
+    output: W,
+    mut media_receiver: MediaReceiver,
+    mut stop: oneshot::Receiver<()>,
+    status: &RwLock<RecordingStatus>,
+) -> StreamResult<()> {
+    let mut writer = FlvWriter::new(output);
+    writer.write_header().await?;
+
+    loop {
+        tokio::select! {
+            _ = &mut stop => break,
+            packet = recv_media(&mut media_receiver) => {
+                let Some(packet) = packet else { break };
+                writer.write_packet(&packet).await?;
+
+                let mut status = status.write().await;
+                status.duration_ms = writer.duration_ms();
+                status.bytes_written = writer.bytes_written();
+                status.packets_written += 1;
+            }
+        }
+    }
+
+    writer.finish().await
+}
+
+/// 打开录制输出，返回 (输出, 写入路径, 实际录制格式)
+///
+/// FFmpeg 无法启动时退回到直接录制 FLV
+async fn open_output(config: &RecordingConfig, path: &Path) -> StreamResult<(RecordingOutput, PathBuf, RecordingFormat)> {
+    let format = match config.format {
+        RecordingFormat::Flv => {
+            return Ok((RecordingOutput::File(File::create(path).await?), path.to_path_buf(), RecordingFormat::Flv));
+        }
+        RecordingFormat::Fmp4 | RecordingFormat::Mkv => match spawn_muxer(&config.ffmpeg_path, config.format, path) {
+            Ok(child) => return Ok((RecordingOutput::Muxer(child), path.to_path_buf(), config.format)),
+            Err(e) => {
+                warn!("Failed to start ffmpeg for {:?} recording, recording FLV instead: {}", config.format, e);
+                RecordingFormat::Flv
+            }
+        },
+        // 先录制为 FLV，结束后转封装
+        RecordingFormat::Mp4 => RecordingFormat::Mp4,
+    };
+
+    let write_path = path.with_extension("flv");
+    Ok((RecordingOutput::File(File::create(&write_path).await?), write_path, format))
+}
+
+/// 启动 FFmpeg，从标准输入读取 FLV 并实时无损封装到录制文件
+fn spawn_muxer(ffmpeg_path: &str, format: RecordingFormat, output: &Path) -> StreamResult<Child> {
+    let mut command = Command::new(ffmpeg_path);
+    command.args(["-y", "-loglevel", "error", "-f", "flv", "-i", "pipe:0", "-c", "copy"]);
+
+    match format {
+        // 每个关键帧开始一个分片，moov 写在文件开头
+        RecordingFormat::Fmp4 => command.args(["-f", "mp4", "-movflags", "+frag_keyframe+empty_moov+default_base_moof"]),
+        _ => command.args(["-f", "matroska"]),
+    };
+
+    let child = command
+        .arg(output)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    Ok(child)
+}
+
+/// 输入结束后等待 FFmpeg 写完文件
+async fn finish_muxer(child: &mut Child, output: &Path) {
+    match tokio::time::timeout(MUXER_EXIT_TIMEOUT, child.wait()).await {
+        Ok(Ok(status)) if status.success() => {}
+        Ok(Ok(status)) => warn!("ffmpeg exited with {} while recording {}", status, output.display()),
+        Ok(Err(e)) => warn!("Failed to wait for ffmpeg recording {}: {}", output.display(), e),
+        Err(_) => {
+            warn!("ffmpeg did not finish recording {} in time, killing it", output.display());
+            let _ = child.kill().await;
+        }
+    }
+}
+
+/// 使用 FFmpeg 无损转封装为 MP4
+pub async fn remux_to_mp4(ffmpeg_path: &str, input: &Path, output: &Path) -> StreamResult<()> {
+    debug!("Remuxing {} to {}", input.display(), output.display());
+
+    let result = Command::new(ffmpeg_path)
+        .args(["-y", "-loglevel", "error", "-i"])
+        .arg(input)
+        .args(["-c", "copy", "-movflags", "+faststart"])
+        .arg(output)
+        .output()
+        .await?;
+
+    if !result.status.success() {
+        let _ = fs::remove_file(output).await;
+        return Err(StreamError::Internal(format!(
+            "ffmpeg exited with {}: {}",
+            result.status,
+            String::from_utf8_lossy(&result.stderr).trim(),
+        )));
+    }
+
+    Ok(())
+}
+
+async fn write_metadata(recording_path: &Path, metadata: &RecordingMetadata) -> StreamResult<()> {
+    fs::write(metadata_path(recording_path), serde_json::to_vec_pretty(metadata)?).await?;
+    Ok(())
+}
+
+/// 录制文件对应的元数据文件路径
+pub fn metadata_path(recording_path: &Path) -> PathBuf {
+    let mut metadata_path = recording_path.as_os_str().to_owned();
+    metadata_path.push(".json");
+    PathBuf::from(metadata_path)
+}
+
+pub const FLV_TAG_AUDIO: u8 = 8;
+pub const FLV_TAG_VIDEO: u8 = 9;
+pub const FLV_TAG_SCRIPT: u8 = 18;
+const FLV_TAG_HEADER_SIZE: u32 = 11;
+pub const FLV_MAX_TAG_SIZE: usize = 0xFF_FFFF;
+
+/// FLV 写入器
+///
+/// RTMP 音视频消息的负载就是 FLV 标签体，可以原样写入
+pub struct FlvWriter<W> {
+    inner: W,
+    base_timestamp: Option<u64>,
+    last_timestamp: u32,
+    bytes_written: u64,
+}
+
+impl<W: AsyncWrite + Unpin> FlvWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            base_timestamp: None,
+            last_timestamp: 0,
+            bytes_written: 0,
+        }
+    }
+
+    /// 指定时间戳零点 (默认为第一个带时间戳的数据包)
+    pub fn set_base_timestamp(&mut self, timestamp: u64) {
+        self.base_timestamp = Some(timestamp);
+    }
+
+    pub async fn write_header(&mut self) -> StreamResult<()> {
+        // 签名、版本 1、含音频和视频、头部长度 9，然后是第一个 PreviousTagSize (0)
+        const HEADER: [u8; 13] = [b'F', b'L', b'V', 1, 0x05, 0, 0, 0, 9, 0, 0, 0, 0];
+        self.inner.write_all(&HEADER).await?;
+        self.bytes_written += HEADER.len() as u64;
+        Ok(())
+    }
+
+    pub async fn write_packet(&mut self, packet: &MediaPacket) -> StreamResult<()> {
+        let (tag_type, timestamp) = match packet {
+            MediaPacket::Video { timestamp, .. } => (FLV_TAG_VIDEO, Some(*timestamp)),
+            MediaPacket::Audio { timestamp, .. } => (FLV_TAG_AUDIO, Some(*timestamp)),
+            MediaPacket::Metadata { .. } => (FLV_TAG_SCRIPT, None),
+        };
+
+        let data = packet.data();
+        if data.is_empty() || data.len() > FLV_MAX_TAG_SIZE {
+            debug!("Skipping FLV tag with invalid size {}", data.len());
+            return Ok(());
+        }
+
+        // 时间戳从录制开始时归零
+        if let Some(timestamp) = timestamp {
+            let base = *self.base_timestamp.get_or_insert(timestamp);
+            self.last_timestamp = timestamp.saturating_sub(base) as u32;
+        }
+
+        let size = data.len() as u32;
+        let ts = self.last_timestamp;
+        let header = [
+            tag_type,
+            (size >> 16) as u8, (size >> 8) as u8, size as u8,
+            (ts >> 16) as u8, (ts >> 8) as u8, ts as u8, (ts >> 24) as u8,
+            0, 0, 0,
+        ];
+
+        self.inner.write_all(&header).await?;
+        self.inner.write_all(data).await?;
+        self.inner.write_u32(FLV_TAG_HEADER_SIZE + size).await?;
+        self.bytes_written += (FLV_TAG_HEADER_SIZE + size + 4) as u64;
+
+        Ok(())
+    }
+
+    pub fn duration_ms(&self) -> u64 {
+        self.last_timestamp as u64
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    pub async fn finish(mut self) -> StreamResult<()> {
+        self.inner.flush().await?;
+        self.inner.shutdown().await?;
+        Ok(())
+    }
+}
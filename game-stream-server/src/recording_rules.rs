@@ -0,0 +1,54 @@
+use chrono::NaiveTime;
+
+use game_stream_common::{RecordingRuleset, RecordingRule, RecordingSchedule, StreamResult, StreamError};
+
+/// 规则中的时间格式
+const SCHEDULE_TIME_FORMAT: &str = "%H:%M";
+
+/// 按顺序匹配规则，返回第一条匹配的规则
+pub fn evaluate<'a>(ruleset: &'a RecordingRuleset, stream_key: &str, now: NaiveTime) -> Option<&'a RecordingRule> {
+    let tags = ruleset.tags.get(stream_key);
+
+    ruleset.rules.iter().find(|rule| {
+        rule.key_prefix.as_ref().is_none_or(|prefix| stream_key.starts_with(prefix.as_str()))
+            && rule.tag.as_ref().is_none_or(|tag| tags.is_some_and(|tags| tags.contains(tag)))
+            && rule.schedule.as_ref().is_none_or(|schedule| in_schedule(schedule, now))
+    })
+}
+
+/// 检查规则集，时段格式错误时返回配置错误
+pub fn validate(ruleset: &RecordingRuleset) -> StreamResult<()> {
+    for (index, rule) in ruleset.rules.iter().enumerate() {
+        let Some(schedule) = &rule.schedule else {
+            continue;
+        };
+
+        for time in [&schedule.start, &schedule.end] {
+            if parse_time(time).is_none() {
+                return Err(StreamError::Config(format!(
+                    "Invalid time {:?} in recording rule {} ({}), expected HH:MM",
+                    time, index, rule.name,
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 时段包含开始时间、不包含结束时间；时段无效时不匹配
+fn in_schedule(schedule: &RecordingSchedule, now: NaiveTime) -> bool {
+    let (Some(start), Some(end)) = (parse_time(&schedule.start), parse_time(&schedule.end)) else {
+        return false;
+    };
+
+    if start <= end {
+        start <= now && now < end
+    } else {
+        now >= start || now < end
+    }
+}
+
+fn parse_time(time: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time.trim(), SCHEDULE_TIME_FORMAT).ok()
+}
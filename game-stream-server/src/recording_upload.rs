@@ -0,0 +1,273 @@
+use anyhow::Result;
+use std::sync::Arc;
+use std::path::Path;
+#[cfg(feature = "object-storage")]
+use std::time::Duration;
+#[cfg(feature = "object-storage")]
+use object_store::{
+    aws::AmazonS3Builder,
+    gcp::GoogleCloudStorageBuilder,
+    path::Path as ObjectPath,
+    Attribute, AttributeValue, Attributes, ObjectStore, PutMultipartOpts, WriteMultipart,
+};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "object-storage")]
+use sha2::{Digest, Sha256};
+#[cfg(feature = "object-storage")]
+use tokio::fs::File;
+#[cfg(feature = "object-storage")]
+use tokio::io::AsyncReadExt;
+#[cfg(feature = "object-storage")]
+use tracing::{info, warn, error};
+
+use game_stream_common::{RecordingUploadConfig, StreamResult};
+#[cfg(feature = "object-storage")]
+use game_stream_common::{ObjectStoreProvider, RecordingFormat, StreamError};
+use crate::recording::RecordingMetadata;
+#[cfg(feature = "object-storage")]
+use crate::segment_store::storage_error;
+use crate::webhook::WebhookManager;
+#[cfg(feature = "object-storage")]
+use crate::webhook::WebhookEventKind;
+
+/// 读取本地文件和计算校验和时的缓冲区大小
+#[cfg(feature = "object-storage")]
+const READ_BUFFER_SIZE: usize = 256 * 1024;
+
+/// 同时进行中的分片上传请求数
+#[cfg(feature = "object-storage")]
+const MAX_CONCURRENT_PARTS: usize = 4;
+
+/// 录制上传器 - 录制完成后上传到对象存储，并通过 Webhook 通知点播处理流程
+#[cfg(feature = "object-storage")]
+pub struct RecordingUploader {
+    config: RecordingUploadConfig,
+    store: Arc<dyn ObjectStore>,
+    webhook_manager: Arc<WebhookManager>,
+}
+
+/// 上传结果，写入录制元数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingUpload {
+    pub url: String,
+    pub object_key: String,
+    pub sha256: String,
+    pub uploaded_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[cfg(feature = "object-storage")]
+impl RecordingUploader {
+    pub fn new(config: &RecordingUploadConfig, webhook_manager: Arc<WebhookManager>) -> Result<Self> {
+        info!("Initializing recording uploader ({:?} bucket {})...", config.provider, config.bucket);
+
+        let store: Arc<dyn ObjectStore> = match config.provider {
+            ObjectStoreProvider::S3 => {
+                let mut builder = AmazonS3Builder::from_env()
+                    .with_bucket_name(&config.bucket)
+                    .with_region(&config.region);
+
+                if let Some(endpoint) = &config.endpoint {
+                    builder = builder
+                        .with_endpoint(endpoint)
+                        .with_allow_http(endpoint.starts_with("http://"));
+                }
+                if let Some(access_key_id) = &config.access_key_id {
+                    builder = builder.with_access_key_id(access_key_id);
+                }
+                if let Some(secret_access_key) = &config.secret_access_key {
+                    builder = builder.with_secret_access_key(secret_access_key);
+                }
+
+                Arc::new(builder.build()?)
+            }
+            ObjectStoreProvider::Gcs => {
+                let mut builder = GoogleCloudStorageBuilder::from_env()
+                    .with_bucket_name(&config.bucket);
+
+                if let Some(service_account_path) = &config.service_account_path {
+                    builder = builder.with_service_account_path(service_account_path);
+                }
+
+                Arc::new(builder.build()?)
+            }
+        };
+
+        Ok(Self {
+            config: config.clone(),
+            store,
+            webhook_manager,
+        })
+    }
+
+    /// 上传录制文件，失败时按指数退避重试，结果通过 Webhook 通知
+    pub async fn upload(&self, path: &Path, metadata: &RecordingMetadata) -> StreamResult<RecordingUpload> {
+        let object_key = self.object_key(&metadata.file);
+        let result = self.upload_with_retries(path, &object_key, metadata).await;
+
+        match &result {
+            Ok(upload) => {
+                info!("Uploaded recording {} to {}", metadata.file, upload.url);
+                self.notify(WebhookEventKind::RecordingUploaded, metadata, serde_json::json!({
+                    "file": metadata.file,
+                    "format": metadata.format,
+                    "duration_ms": metadata.duration_ms,
+                    "size_bytes": metadata.size_bytes,
+                    "url": upload.url,
+                    "object_key": upload.object_key,
+                    "sha256": upload.sha256,
+                }));
+            }
+            Err(e) => {
+                error!("Giving up on uploading recording {}: {}", metadata.file, e);
+                self.notify(WebhookEventKind::RecordingUploadFailed, metadata, serde_json::json!({
+                    "file": metadata.file,
+                    "object_key": object_key,
+                    "error": e.to_string(),
+                }));
+            }
+        }
+
+        result
+    }
+
+    /// 上传成功后是否删除本地文件
+    pub fn delete_local(&self) -> bool {
+        self.config.delete_local
+    }
+
+    async fn upload_with_retries(&self, path: &Path, object_key: &str, metadata: &RecordingMetadata) -> StreamResult<RecordingUpload> {
+        // 校验和只计算一次，作为对象元数据一起上传，供下游校验
+        let sha256 = file_sha256(path).await?;
+        let mut delay = Duration::from_millis(self.config.retry_interval);
+        let max_retries = self.config.max_retries;
+
+        for attempt in 0..=max_retries {
+            match self.put_file(path, object_key, &sha256, metadata.format).await {
+                Ok(()) => {
+                    return Ok(RecordingUpload {
+                        url: self.object_url(object_key),
+                        object_key: object_key.to_string(),
+                        sha256,
+                        uploaded_at: chrono::Utc::now(),
+                    });
+                }
+                Err(e) if attempt < max_retries => {
+                    warn!("Upload of recording {} failed: {} (attempt {}/{})",
+                          metadata.file, e, attempt + 1, max_retries + 1);
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        unreachable!("the last attempt always returns")
+    }
+
+    /// 分片上传文件，完成后核对对象大小
+    async fn put_file(&self, path: &Path, object_key: &str, sha256: &str, format: RecordingFormat) -> StreamResult<()> {
+        let location = ObjectPath::from(object_key);
+        let opts = PutMultipartOpts {
+            attributes: Attributes::from_iter([
+                (Attribute::ContentType, AttributeValue::from(format.content_type())),
+                (Attribute::Metadata("sha256".into()), AttributeValue::from(sha256.to_string())),
+            ]),
+            ..Default::default()
+        };
+
+        let mut file = File::open(path).await?;
+        let size = file.metadata().await?.len();
+
+        let upload = self.store.put_multipart_opts(&location, opts).await.map_err(storage_error)?;
+        let mut writer = WriteMultipart::new_with_chunk_size(upload, self.config.multipart_part_size.max(5 * 1024 * 1024));
+        let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+
+        loop {
+            let n = match file.read(&mut buffer).await {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) => {
+                    let _ = writer.abort().await;
+                    return Err(e.into());
+                }
+            };
+
+            if let Err(e) = writer.wait_for_capacity(MAX_CONCURRENT_PARTS).await {
+                let _ = writer.abort().await;
+                return Err(storage_error(e));
+            }
+            writer.write(&buffer[..n]);
+        }
+        writer.finish().await.map_err(storage_error)?;
+
+        let uploaded = self.store.head(&location).await.map_err(storage_error)?;
+        if uploaded.size as u64 != size {
+            return Err(StreamError::Internal(format!(
+                "Uploaded object {} has {} bytes, expected {}", object_key, uploaded.size, size
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn notify(&self, event: WebhookEventKind, metadata: &RecordingMetadata, data: serde_json::Value) {
+        self.webhook_manager.dispatch(WebhookManager::payload(event, metadata.stream_key.clone(), data));
+    }
+
+    fn object_key(&self, file: &str) -> String {
+        let prefix = self.config.prefix.trim_matches('/');
+        if prefix.is_empty() {
+            file.to_string()
+        } else {
+            format!("{}/{}", prefix, file)
+        }
+    }
+
+    /// 对象的访问地址 (未配置公开地址时使用存储自身的 URL)
+    fn object_url(&self, object_key: &str) -> String {
+        match &self.config.public_base_url {
+            Some(base_url) => format!("{}/{}", base_url.trim_end_matches('/'), object_key),
+            None => match self.config.provider {
+                ObjectStoreProvider::S3 => format!("s3://{}/{}", self.config.bucket, object_key),
+                ObjectStoreProvider::Gcs => format!("gs://{}/{}", self.config.bucket, object_key),
+            },
+        }
+    }
+}
+
+/// 计算文件的 SHA-256 (十六进制)
+#[cfg(feature = "object-storage")]
+async fn file_sha256(path: &Path) -> StreamResult<String> {
+    let mut file = File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+
+    loop {
+        let n = file.read(&mut buffer).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// 未启用 object-storage 特性时没有录制上传器，开启上传的配置无法启动
+#[cfg(not(feature = "object-storage"))]
+pub enum RecordingUploader {}
+
+#[cfg(not(feature = "object-storage"))]
+impl RecordingUploader {
+    pub fn new(_config: &RecordingUploadConfig, _webhook_manager: Arc<WebhookManager>) -> Result<Self> {
+        anyhow::bail!("recording.upload is enabled but the server was built without the object-storage feature")
+    }
+
+    pub async fn upload(&self, _path: &Path, _metadata: &RecordingMetadata) -> StreamResult<RecordingUpload> {
+        match *self {}
+    }
+
+    pub fn delete_local(&self) -> bool {
+        match *self {}
+    }
+}
@@ -0,0 +1,502 @@
+use std::sync::Arc;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use bytes::BytesMut;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{RwLock, broadcast};
+use tokio::task::JoinHandle;
+use tracing::{info, debug, warn, error};
+use uuid::Uuid;
+
+use game_stream_common::{
+    RelayConfig, StreamManager, LiveStream, StreamInfo, StreamStatus, MediaPacket, RelayFrame,
+    ViewerConnection, ViewProtocol, VideoConfig, AudioConfig, VideoCodec, AudioCodec,
+    MediaReceiver, StreamResult, StreamError, RELAY_PROTOCOL_VERSION, Rendition, recv_media
+};
+use crate::cluster::ClusterDirectory;
+use crate::push_pool::{PooledConnection, PushPool};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 空闲中继检查间隔
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 内部中继握手允许的最大时钟偏差 (秒)
+const RELAY_AUTH_MAX_SKEW: u64 = 60;
+
+/// 中继管理器 - 边缘节点在首个观看请求时从源站拉流，空闲时释放
+pub struct RelayManager {
+    config: RelayConfig,
+    stream_manager: Arc<StreamManager>,
+    cluster_directory: Arc<ClusterDirectory>,
+    push_pool: Arc<PushPool>,
+    relays: Arc<RwLock<HashMap<String, RelaySession>>>,
+}
+
+/// 单个中继拉流会话
+struct RelaySession {
+    task: JoinHandle<()>,
+    last_access: Instant,
+}
+
+impl RelayManager {
+    pub fn new(
+        config: &RelayConfig,
+        stream_manager: Arc<StreamManager>,
+        cluster_directory: Arc<ClusterDirectory>,
+        push_pool: Arc<PushPool>,
+    ) -> Self {
+        info!("Initializing relay manager...");
+
+        Self {
+            config: config.clone(),
+            stream_manager,
+            cluster_directory,
+            push_pool,
+            relays: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// 获取本地流，不存在时从源站拉取
+    pub async fn ensure_stream(&self, stream_key: &str) -> StreamResult<Arc<LiveStream>> {
+        if !self.config.enabled {
+            return self.stream_manager.get_stream(stream_key).await
+                .ok_or_else(|| StreamError::StreamNotFound(stream_key.to_string()));
+        }
+
+        // 持有写锁，避免并发请求重复拉流
+        let mut relays = self.relays.write().await;
+
+        if let Some(stream) = self.stream_manager.get_stream(stream_key).await {
+            if let Some(session) = relays.get_mut(stream_key) {
+                session.last_access = Instant::now();
+            }
+            return Ok(stream);
+        }
+
+        // 优先从集群目录查找持有该流的源站
+        let origin_url = self.cluster_directory.find_origin(stream_key).await
+            .unwrap_or_else(|| self.config.origin_url.clone());
+        let origin = parse_origin_url(&origin_url)?;
+        info!("Pulling stream {} from origin {}", stream_key, origin_url);
+
+        // 经连接池限制到同一源站的并发连接数，避免大量流同时重连时压垮源站
+        let timeout = Duration::from_secs(self.config.connect_timeout);
+        let mut socket = self.push_pool.connect(&origin.addr, timeout).await?;
+
+        if origin.transport == OriginTransport::Internal {
+            tokio::time::timeout(timeout, self.internal_handshake(&mut socket, stream_key)).await
+                .map_err(|_| StreamError::Timeout)??;
+        }
+
+        let stream = self.stream_manager.create_stream(stream_key.to_string(), relay_stream_info(stream_key)).await?;
+        stream.set_status(StreamStatus::Live).await;
+        self.cluster_directory.mark_relayed(stream_key).await;
+
+        let task = tokio::spawn(pull_stream(
+            socket,
+            origin.transport,
+            stream.clone(),
+            self.stream_manager.clone(),
+            self.relays.clone(),
+        ));
+
+        relays.insert(stream_key.to_string(), RelaySession {
+            task,
+            last_access: Instant::now(),
+        });
+
+        Ok(stream)
+    }
+
+    /// 内部中继协议握手：发送签名的 Hello，等待源站确认
+    async fn internal_handshake(&self, socket: &mut TcpStream, stream_key: &str) -> StreamResult<()> {
+        let secret = self.config.secret.as_deref()
+            .ok_or_else(|| StreamError::Config("relay.secret is required for relay:// origins".to_string()))?;
+
+        let timestamp = chrono::Utc::now().timestamp();
+        RelayFrame::Hello {
+            version: RELAY_PROTOCOL_VERSION,
+            stream_key: stream_key.to_string(),
+            timestamp,
+            signature: sign_relay_request(secret, stream_key, timestamp),
+        }.write_to(socket).await?;
+
+        match RelayFrame::read_from(socket).await? {
+            Some(RelayFrame::Accept) => Ok(()),
+            Some(RelayFrame::Reject { reason }) => Err(StreamError::Auth(format!("Origin rejected relay: {}", reason))),
+            Some(_) => Err(StreamError::Network("Unexpected relay handshake response".to_string())),
+            None => Err(StreamError::ConnectionClosed),
+        }
+    }
+
+    /// 接受其他实例的内部中继连接 (源站侧)
+    pub async fn serve_internal(self: Arc<Self>) {
+        if !self.config.listen {
+            return;
+        }
+
+        if self.config.secret.is_none() {
+            warn!("Relay listener enabled without relay.secret, refusing to accept relay connections");
+            return;
+        }
+
+        let addr = format!("{}:{}", self.config.bind_addr, self.config.port);
+        let listener = match TcpListener::bind(&addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Failed to bind relay listener on {}: {}", addr, e);
+                return;
+            }
+        };
+
+        info!("Relay listener started on {}", addr);
+
+        loop {
+            match listener.accept().await {
+                Ok((socket, remote_addr)) => {
+                    let manager = self.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = manager.handle_replica(socket, remote_addr).await {
+                            warn!("Relay connection from {} failed: {}", remote_addr, e);
+                        }
+                    });
+                }
+                Err(e) => error!("Failed to accept relay connection: {}", e),
+            }
+        }
+    }
+
+    /// 处理单个拉流实例：校验握手后持续推送媒体帧
+    async fn handle_replica(&self, mut socket: TcpStream, remote_addr: SocketAddr) -> StreamResult<()> {
+        let hello = tokio::time::timeout(
+            Duration::from_secs(self.config.connect_timeout),
+            RelayFrame::read_from(&mut socket),
+        ).await.map_err(|_| StreamError::Timeout)??;
+
+        let stream_key = match self.verify_hello(hello) {
+            Ok(stream_key) => stream_key,
+            Err(reason) => {
+                RelayFrame::Reject { reason: reason.clone() }.write_to(&mut socket).await?;
+                return Err(StreamError::Auth(reason));
+            }
+        };
+
+        let Some(stream) = self.stream_manager.get_stream(&stream_key).await else {
+            RelayFrame::Reject { reason: "stream not found".to_string() }.write_to(&mut socket).await?;
+            return Err(StreamError::StreamNotFound(stream_key));
+        };
+
+        RelayFrame::Accept.write_to(&mut socket).await?;
+        info!("Relaying stream {} to {}", stream_key, remote_addr);
+
+        let viewer_id = Uuid::new_v4();
+        let viewer = ViewerConnection {
+            id: viewer_id,
+            remote_addr,
+            connected_at: chrono::Utc::now(),
+            protocol: ViewProtocol::Relay,
+            stream_key: stream_key.clone(),
+            user_agent: None,
+            rendition: Rendition::Source,
+        };
+        let mut media_receiver = stream.add_viewer(viewer).await;
+
+        let (mut reader, mut writer) = socket.into_split();
+        let mut buf = [0u8; 64];
+
+        let result = loop {
+            tokio::select! {
+                packet = recv_media(&mut media_receiver) => {
+                    let Some(packet) = packet else { break Ok(()) };
+                    let (frames, size) = self.encode_batch(packet, &mut media_receiver);
+                    if let Err(e) = writer.write_all(&frames).await {
+                        break Err(e.into());
+                    }
+                    stream.bandwidth.record_egress(ViewProtocol::Relay, size).await;
+                }
+                // 拉流端不会发送数据，读到 EOF 表示连接关闭
+                read = reader.read(&mut buf) => {
+                    match read {
+                        Ok(0) => break Ok(()),
+                        Ok(_) => continue,
+                        Err(e) => break Err(e.into()),
+                    }
+                }
+            }
+        };
+
+        stream.remove_viewer(viewer_id).await;
+        info!("Relay of stream {} to {} finished", stream_key, remote_addr);
+        result
+    }
+
+    /// 将已到达的媒体包与第一个包一起编码，合并为一次写入，返回编码结果和负载字节数
+    fn encode_batch(&self, first: Arc<MediaPacket>, media_receiver: &mut MediaReceiver) -> (BytesMut, u64) {
+        let max_packets = self.push_pool.config().batch_max_packets.max(1);
+        let max_bytes = self.push_pool.config().batch_max_bytes;
+
+        let mut frames = BytesMut::new();
+        let mut size = 0;
+        let mut packets = 0;
+        let mut next = Some(first);
+
+        while let Some(packet) = next.take() {
+            RelayFrame::encode_media_into(&packet, &mut frames);
+            size += packet.size() as u64;
+            packets += 1;
+
+            if packets >= max_packets || frames.len() >= max_bytes {
+                break;
+            }
+
+            next = loop {
+                match media_receiver.try_recv() {
+                    Ok(packet) => break Some(packet),
+                    Err(broadcast::error::TryRecvError::Lagged(skipped)) => {
+                        debug!("Relay subscriber lagged, skipped {} packets", skipped);
+                    }
+                    Err(_) => break None,
+                }
+            };
+        }
+
+        (frames, size)
+    }
+
+    /// 校验 Hello 帧，返回请求的流密钥
+    fn verify_hello(&self, hello: Option<RelayFrame>) -> Result<String, String> {
+        let Some(RelayFrame::Hello { version, stream_key, timestamp, signature }) = hello else {
+            return Err("expected hello".to_string());
+        };
+
+        if version != RELAY_PROTOCOL_VERSION {
+            return Err(format!("unsupported protocol version {}", version));
+        }
+
+        if chrono::Utc::now().timestamp().abs_diff(timestamp) > RELAY_AUTH_MAX_SKEW {
+            return Err("request expired".to_string());
+        }
+
+        let secret = self.config.secret.as_deref().unwrap_or_default();
+        if !verify_relay_request(secret, &stream_key, timestamp, &signature) {
+            return Err("authentication failed".to_string());
+        }
+
+        Ok(stream_key)
+    }
+
+    /// 周期性释放没有观看者的中继流
+    pub async fn start(self: Arc<Self>) {
+        if !self.config.enabled {
+            return;
+        }
+
+        let idle_timeout = Duration::from_secs(self.config.idle_timeout);
+        let mut interval = tokio::time::interval(IDLE_CHECK_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let mut idle = Vec::new();
+            {
+                let mut relays = self.relays.write().await;
+                for (stream_key, session) in relays.iter_mut() {
+                    let viewer_count = match self.stream_manager.get_stream(stream_key).await {
+                        Some(stream) => stream.get_viewer_count().await,
+                        None => 0,
+                    };
+
+                    if viewer_count > 0 {
+                        session.last_access = Instant::now();
+                    } else if session.last_access.elapsed() >= idle_timeout {
+                        idle.push(stream_key.clone());
+                    }
+                }
+
+                for stream_key in &idle {
+                    if let Some(session) = relays.remove(stream_key) {
+                        session.task.abort();
+                    }
+                }
+            }
+
+            for stream_key in idle {
+                info!("Relay for stream {} idle, stopping pull from origin", stream_key);
+                stop_relay_stream(&self.stream_manager, &stream_key).await;
+            }
+        }
+    }
+}
+
+/// 从源站拉取媒体数据并写入本地流
+async fn pull_stream(
+    socket: PooledConnection,
+    transport: OriginTransport,
+    stream: Arc<LiveStream>,
+    stream_manager: Arc<StreamManager>,
+    relays: Arc<RwLock<HashMap<String, RelaySession>>>,
+) {
+    match transport {
+        OriginTransport::Rtmp => pull_rtmp(socket, &stream).await,
+        OriginTransport::Internal => pull_internal(socket, &stream).await,
+    }
+
+    relays.write().await.remove(&stream.stream_key);
+    stop_relay_stream(&stream_manager, &stream.stream_key).await;
+}
+
+/// 通过内部中继协议拉流，媒体包无需重新封装
+async fn pull_internal(mut socket: PooledConnection, stream: &LiveStream) {
+    loop {
+        match RelayFrame::read_from(&mut *socket).await {
+            Ok(Some(RelayFrame::Media(packet))) => {
+                if let Err(e) = stream.send_media_packet(packet).await {
+                    debug!("Failed to forward relayed packet: {}", e);
+                }
+            }
+            Ok(Some(frame)) => debug!("Ignoring unexpected relay frame: {:?}", frame),
+            Ok(None) => {
+                info!("Origin closed relay connection for stream {}", stream.stream_key);
+                break;
+            }
+            Err(e) => {
+                warn!("Relay connection error for stream {}: {}", stream.stream_key, e);
+                break;
+            }
+        }
+    }
+}
+
+/// 通过 RTMP 拉流
+async fn pull_rtmp(mut socket: PooledConnection, stream: &LiveStream) {
+    // 实际实现中需要完成 RTMP 握手，发送 connect/play 命令，然后解析 FLV 音视频消息
+    let mut buf = [0u8; 4096];
+    let mut frame_interval = tokio::time::interval(Duration::from_millis(33));
+
+    loop {
+        tokio::select! {
+            read = socket.read(&mut buf) => {
+                match read {
+                    Ok(0) => {
+                        info!("Origin closed relay connection for stream {}", stream.stream_key);
+                        break;
+                    }
+                    Ok(n) => debug!("Received {} bytes from origin for stream {}", n, stream.stream_key),
+                    Err(e) => {
+                        warn!("Relay connection error for stream {}: {}", stream.stream_key, e);
+                        break;
+                    }
+                }
+            }
+            _ = frame_interval.tick() => {
+                // 模拟从源站收到的视频数据
+                let packet = MediaPacket::Video {
+                    data: bytes::Bytes::from(vec![0u8; 1024]),
+                    timestamp: chrono::Utc::now().timestamp_millis() as u64,
+                    is_keyframe: false,
+                    capture_time: None,
+                };
+                if let Err(e) = stream.send_media_packet(packet).await {
+                    debug!("Failed to forward relayed packet: {}", e);
+                }
+            }
+        }
+    }
+}
+
+async fn stop_relay_stream(stream_manager: &StreamManager, stream_key: &str) {
+    if let Some(stream) = stream_manager.get_stream(stream_key).await {
+        stream.set_status(StreamStatus::Stopped).await;
+    }
+    stream_manager.remove_stream(stream_key).await;
+}
+
+/// 中继流的初始信息 (实际参数由源站的 onMetaData 更新)
+fn relay_stream_info(stream_key: &str) -> StreamInfo {
+    StreamInfo {
+        stream_id: Uuid::new_v4(),
+        stream_key: stream_key.to_string(),
+        title: None,
+        description: None,
+        created_at: chrono::Utc::now(),
+        is_live: false,
+        viewer_count: 0,
+        video_config: VideoConfig {
+            width: 1920,
+            height: 1080,
+            fps: 30,
+            bitrate: 2500,
+            codec: VideoCodec::H264,
+        },
+        audio_config: AudioConfig {
+            sample_rate: 44100,
+            channels: 2,
+            bitrate: 128,
+            codec: AudioCodec::Aac,
+        },
+        thumbnail_url: None,
+        tags: Default::default(),
+    }
+}
+
+/// 源站拉流方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OriginTransport {
+    Rtmp,
+    Internal,
+}
+
+/// 解析后的源站地址
+struct OriginAddr {
+    transport: OriginTransport,
+    addr: String,
+}
+
+/// 解析源站地址 rtmp://host[:port]/app 或 relay://host[:port]
+fn parse_origin_url(url: &str) -> StreamResult<OriginAddr> {
+    let (transport, rest, default_port) = if let Some(rest) = url.strip_prefix("rtmp://") {
+        (OriginTransport::Rtmp, rest, 1935)
+    } else if let Some(rest) = url.strip_prefix("relay://") {
+        (OriginTransport::Internal, rest, 1940)
+    } else {
+        return Err(StreamError::Config(format!("Unsupported origin url: {}", url)));
+    };
+
+    let host = rest.split('/').next().unwrap_or_default();
+    if host.is_empty() {
+        return Err(StreamError::Config(format!("Missing origin host: {}", url)));
+    }
+
+    let addr = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:{}", host, default_port)
+    };
+
+    Ok(OriginAddr { transport, addr })
+}
+
+/// 计算内部中继请求签名
+fn sign_relay_request(secret: &str, stream_key: &str, timestamp: i64) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(format!("{}:{}", stream_key, timestamp).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// 校验内部中继请求签名 (常量时间比较)
+fn verify_relay_request(secret: &str, stream_key: &str, timestamp: i64, signature: &str) -> bool {
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(format!("{}:{}", stream_key, timestamp).as_bytes());
+    mac.verify_slice(&signature).is_ok()
+}
@@ -0,0 +1,383 @@
+use std::sync::Arc;
+use std::collections::HashMap;
+use std::time::Duration;
+use bytes::BytesMut;
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use tokio::net::UdpSocket;
+use tokio::sync::{RwLock, broadcast, mpsc};
+use tokio::task::JoinHandle;
+use tracing::{info, debug, warn};
+
+use game_stream_common::{
+    RestreamConfig, RestreamTargetConfig, StreamEvent, StreamManager, StreamStatus, LiveStream,
+    MediaPacket, StreamResult, StreamError, recv_media
+};
+use crate::push_pool::{PooledConnection, PushPool};
+
+/// 转推管理器 - 每个流可转推到多个下游目标，各目标独立排队和重连
+pub struct RestreamManager {
+    config: RestreamConfig,
+    stream_manager: Arc<StreamManager>,
+    push_pool: Arc<PushPool>,
+    sessions: RwLock<HashMap<String, RestreamSession>>,
+}
+
+/// 单个流的转推会话
+struct RestreamSession {
+    fanout: JoinHandle<()>,
+    targets: Vec<RestreamTarget>,
+}
+
+/// 单个转推目标
+struct RestreamTarget {
+    sender: mpsc::Sender<Arc<MediaPacket>>,
+    health: Arc<RwLock<TargetHealth>>,
+    task: JoinHandle<()>,
+}
+
+/// 转推目标状态
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetState {
+    Connecting,
+    Connected,
+    Backoff,
+    Stopped,
+}
+
+/// 转推目标健康状态
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetHealth {
+    pub name: String,
+    pub url: String, // 已隐藏推流密钥
+    pub state: TargetState,
+    pub connected_since: Option<chrono::DateTime<chrono::Utc>>,
+    pub reconnect_attempts: u32,
+    pub packets_sent: u64,
+    pub bytes_sent: u64,
+    pub packets_dropped: u64,
+    pub queue_len: usize,
+    pub last_error: Option<String>,
+}
+
+impl RestreamManager {
+    pub fn new(config: &RestreamConfig, stream_manager: Arc<StreamManager>, push_pool: Arc<PushPool>) -> Self {
+        info!("Initializing restream manager...");
+
+        Self {
+            config: config.clone(),
+            stream_manager,
+            push_pool,
+            sessions: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 订阅事件总线，在流开始/结束时启停转推
+    pub async fn start(self: Arc<Self>) {
+        if !self.config.enabled || self.config.targets.is_empty() {
+            info!("Restream disabled");
+            return;
+        }
+
+        let mut events = self.stream_manager.subscribe_events();
+
+        loop {
+            match events.recv().await {
+                Ok(StreamEvent::StatusChanged { stream_key, status }) => match status {
+                    StreamStatus::Live => self.start_session(&stream_key).await,
+                    StreamStatus::Stopped | StreamStatus::Error(_) => self.stop_session(&stream_key).await,
+                    _ => {}
+                },
+                Ok(StreamEvent::StreamRemoved { stream_key }) => self.stop_session(&stream_key).await,
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Restream manager lagged behind event bus, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+
+    /// 获取流的各转推目标健康状态
+    pub async fn get_health(&self, stream_key: &str) -> Option<Vec<TargetHealth>> {
+        let sessions = self.sessions.read().await;
+        let session = sessions.get(stream_key)?;
+
+        let mut health = Vec::with_capacity(session.targets.len());
+        for target in &session.targets {
+            let mut snapshot = target.health.read().await.clone();
+            snapshot.queue_len = target.sender.max_capacity() - target.sender.capacity();
+            health.push(snapshot);
+        }
+
+        Some(health)
+    }
+
+    async fn start_session(&self, stream_key: &str) {
+        let targets: Vec<&RestreamTargetConfig> = self.config.targets.iter()
+            .filter(|target| target.stream_key == stream_key)
+            .collect();
+        if targets.is_empty() {
+            return;
+        }
+
+        let Some(stream) = self.stream_manager.get_stream(stream_key).await else {
+            return;
+        };
+
+        let mut sessions = self.sessions.write().await;
+        if sessions.contains_key(stream_key) {
+            return;
+        }
+
+        info!("Starting restream of {} to {} targets", stream_key, targets.len());
+
+        let targets: Vec<RestreamTarget> = targets.into_iter()
+            .map(|target| self.spawn_target(target.clone()))
+            .collect();
+
+        let outputs = targets.iter()
+            .map(|target| (target.sender.clone(), target.health.clone()))
+            .collect();
+        let fanout = tokio::spawn(fanout(stream, outputs));
+
+        sessions.insert(stream_key.to_string(), RestreamSession { fanout, targets });
+    }
+
+    async fn stop_session(&self, stream_key: &str) {
+        let Some(session) = self.sessions.write().await.remove(stream_key) else {
+            return;
+        };
+
+        info!("Stopping restream of {}", stream_key);
+
+        session.fanout.abort();
+        for target in session.targets {
+            target.task.abort();
+        }
+    }
+
+    fn spawn_target(&self, config: RestreamTargetConfig) -> RestreamTarget {
+        let (sender, receiver) = mpsc::channel(self.config.queue_size.max(1));
+        let health = Arc::new(RwLock::new(TargetHealth {
+            name: config.name.clone(),
+            url: redact_url(&config.url),
+            state: TargetState::Connecting,
+            connected_since: None,
+            reconnect_attempts: 0,
+            packets_sent: 0,
+            bytes_sent: 0,
+            packets_dropped: 0,
+            queue_len: 0,
+            last_error: None,
+        }));
+
+        let task = tokio::spawn(run_target(
+            config,
+            self.config.clone(),
+            self.push_pool.clone(),
+            receiver,
+            health.clone(),
+        ));
+
+        RestreamTarget { sender, health, task }
+    }
+}
+
+/// 目标队列发送端及其健康状态
+type TargetOutput = (mpsc::Sender<Arc<MediaPacket>>, Arc<RwLock<TargetHealth>>);
+
+/// 将流的媒体数据分发到各目标队列，某个目标阻塞时只丢弃该目标的数据
+async fn fanout(stream: Arc<LiveStream>, outputs: Vec<TargetOutput>) {
+    let mut media_receiver = stream.subscribe_media();
+
+    while let Some(packet) = recv_media(&mut media_receiver).await {
+        for (sender, health) in &outputs {
+            if let Err(mpsc::error::TrySendError::Full(_)) = sender.try_send(packet.clone()) {
+                health.write().await.packets_dropped += 1;
+            }
+        }
+    }
+
+    debug!("Restream fanout finished for stream {}", stream.stream_key);
+}
+
+/// 单个目标的发送循环，断线后按指数退避重连
+async fn run_target(
+    target: RestreamTargetConfig,
+    config: RestreamConfig,
+    push_pool: Arc<PushPool>,
+    mut receiver: mpsc::Receiver<Arc<MediaPacket>>,
+    health: Arc<RwLock<TargetHealth>>,
+) {
+    let mut attempts: u32 = 0;
+    let batch_max_packets = push_pool.config().batch_max_packets.max(1);
+    let batch_max_bytes = push_pool.config().batch_max_bytes;
+    let mut batch = Vec::with_capacity(batch_max_packets);
+
+    loop {
+        health.write().await.state = TargetState::Connecting;
+
+        match RestreamConnection::connect(&push_pool, &target.url, Duration::from_secs(config.connect_timeout)).await {
+            Ok(mut connection) => {
+                info!("Restream target {} connected{}", target.name,
+                      if connection.is_reused() { " (pooled connection)" } else { "" });
+                attempts = 0;
+                {
+                    let mut health = health.write().await;
+                    health.state = TargetState::Connected;
+                    health.connected_since = Some(chrono::Utc::now());
+                }
+
+                loop {
+                    let Some(packet) = receiver.recv().await else {
+                        // 流正常结束，连接放回池中供后续转推复用
+                        health.write().await.state = TargetState::Stopped;
+                        connection.release(&push_pool).await;
+                        return;
+                    };
+
+                    // 队列中已积压的数据包合并为一次写入
+                    let mut size = packet.size();
+                    batch.push(packet);
+                    while batch.len() < batch_max_packets && size < batch_max_bytes {
+                        match receiver.try_recv() {
+                            Ok(packet) => {
+                                size += packet.size();
+                                batch.push(packet);
+                            }
+                            Err(_) => break,
+                        }
+                    }
+
+                    let sent = batch.len() as u64;
+                    let result = connection.send_batch(&batch).await;
+                    batch.clear();
+
+                    if let Err(e) = result {
+                        warn!("Restream target {} send failed: {}", target.name, e);
+                        health.write().await.last_error = Some(e.to_string());
+                        break;
+                    }
+
+                    let mut health = health.write().await;
+                    health.packets_sent += sent;
+                    health.bytes_sent += size as u64;
+                }
+            }
+            Err(e) => {
+                warn!("Restream target {} connect failed: {}", target.name, e);
+                health.write().await.last_error = Some(e.to_string());
+            }
+        }
+
+        // 丢弃断线期间积压的数据，重连后从最新数据开始
+        let mut dropped = 0;
+        while receiver.try_recv().is_ok() {
+            dropped += 1;
+        }
+
+        attempts += 1;
+        let backoff = config.reconnect_interval
+            .saturating_mul(1u64 << (attempts - 1).min(16))
+            .min(config.max_reconnect_interval);
+
+        {
+            let mut health = health.write().await;
+            health.state = TargetState::Backoff;
+            health.connected_since = None;
+            health.reconnect_attempts += 1;
+            health.packets_dropped += dropped;
+        }
+
+        debug!("Restream target {} reconnecting in {}ms", target.name, backoff);
+        tokio::time::sleep(Duration::from_millis(backoff)).await;
+    }
+}
+
+/// 下游连接
+enum RestreamConnection {
+    Rtmp(PooledConnection),
+    Srt(UdpSocket),
+}
+
+impl RestreamConnection {
+    async fn connect(push_pool: &PushPool, url: &str, timeout: Duration) -> StreamResult<Self> {
+        if let Some(rest) = url.strip_prefix("rtmp://") {
+            let host = host_with_port(rest, 1935);
+            // 实际实现中新连接需要完成 RTMP 握手和 connect 命令，复用的连接只需重新发送 publish
+            Ok(RestreamConnection::Rtmp(push_pool.connect(&host, timeout).await?))
+        } else if let Some(rest) = url.strip_prefix("srt://") {
+            let host = host_with_port(rest, 9000);
+            // 实际实现中需要完成 SRT 握手
+            let connect = async {
+                let socket = UdpSocket::bind("0.0.0.0:0").await?;
+                socket.connect(host).await?;
+                Ok(RestreamConnection::Srt(socket))
+            };
+            tokio::time::timeout(timeout, connect).await
+                .map_err(|_| StreamError::Timeout)?
+        } else {
+            Err(StreamError::Config(format!("Unsupported restream url: {}", redact_url(url))))
+        }
+    }
+
+    fn is_reused(&self) -> bool {
+        matches!(self, RestreamConnection::Rtmp(connection) if connection.is_reused())
+    }
+
+    /// 批量发送数据包 (RTMP 合并为一次写入，SRT 每个数据包一个报文)
+    async fn send_batch(&mut self, packets: &[Arc<MediaPacket>]) -> StreamResult<()> {
+        // 实际实现中需要将媒体包封装为 FLV 标签 (RTMP) 或 MPEG-TS (SRT)
+        match self {
+            RestreamConnection::Rtmp(connection) => {
+                if let [packet] = packets {
+                    connection.write_all(packet.data()).await?;
+                } else {
+                    let mut buf = BytesMut::with_capacity(packets.iter().map(|packet| packet.size()).sum());
+                    for packet in packets {
+                        buf.extend_from_slice(packet.data());
+                    }
+                    connection.write_all(&buf).await?;
+                }
+            }
+            RestreamConnection::Srt(socket) => {
+                for packet in packets {
+                    socket.send(packet.data()).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 结束转推，可复用的连接放回连接池
+    async fn release(self, push_pool: &PushPool) {
+        if let RestreamConnection::Rtmp(connection) = self {
+            // 实际实现中需要先发送 deleteStream 结束当前推流
+            push_pool.release(connection).await;
+        }
+    }
+}
+
+/// 取出 URL 中的 host:port，没有端口时使用默认端口
+fn host_with_port(rest: &str, default_port: u16) -> String {
+    let host = rest.split(['/', '?']).next().unwrap_or_default();
+    if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:{}", host, default_port)
+    }
+}
+
+/// 隐藏 URL 中的推流密钥 (最后一段路径和查询参数)
+fn redact_url(url: &str) -> String {
+    let url = url.split('?').next().unwrap_or(url);
+    match url.rsplit_once('/') {
+        Some((base, key)) if !key.is_empty() && base.contains("://") && !base.ends_with('/') => {
+            format!("{}/****", base)
+        }
+        _ => url.to_string(),
+    }
+}
@@ -0,0 +1,255 @@
+use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use serde::Serialize;
+use tokio::fs;
+use tokio::sync::RwLock;
+use tracing::{info, debug, warn};
+
+use game_stream_common::{
+    ServerConfig, RetentionConfig, RetentionPolicy, StreamManager, StreamStatus, StreamResult
+};
+use crate::recording::metadata_path;
+
+/// 最近写入的文件可能仍在使用 (进行中的录制、正在生成的缩略图)，不会被删除
+const MIN_FILE_AGE: Duration = Duration::from_secs(60);
+
+/// 数据保留管理器 - 定期按保留策略删除磁盘上的旧数据
+pub struct RetentionManager {
+    config: RetentionConfig,
+    stream_manager: Arc<StreamManager>,
+    targets: Vec<RetentionTarget>,
+    stats: RwLock<RetentionStats>,
+}
+
+/// 被清理的数据类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionTargetKind {
+    Hls,
+    Thumbnails,
+    Recordings,
+    Clips,
+}
+
+struct RetentionTarget {
+    kind: RetentionTargetKind,
+    dir: PathBuf,
+    policy: RetentionPolicy,
+}
+
+/// 清理统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RetentionStats {
+    pub runs: u64,
+    pub last_run_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub targets: HashMap<RetentionTargetKind, TargetStats>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TargetStats {
+    pub files_deleted: u64,
+    pub bytes_reclaimed: u64,
+    pub last_run_files_deleted: u64,
+    pub last_run_bytes_reclaimed: u64,
+    pub current_bytes: u64, // 上次清理后的占用
+}
+
+/// 一起删除的一组文件 (录制文件和它的元数据)
+struct Entry {
+    paths: Vec<PathBuf>,
+    size: u64,
+    modified: SystemTime,
+}
+
+impl RetentionManager {
+    pub fn new(config: &ServerConfig, stream_manager: Arc<StreamManager>) -> Self {
+        info!("Initializing retention manager...");
+
+        let retention = &config.retention;
+        let targets = [
+            (RetentionTargetKind::Hls, &config.storage.hls_segment_dir, &retention.hls),
+            (RetentionTargetKind::Thumbnails, &config.thumbnails.dir, &retention.thumbnails),
+            (RetentionTargetKind::Recordings, &config.recording.dir, &retention.recordings),
+            (RetentionTargetKind::Clips, &config.clips.dir, &retention.clips),
+        ]
+        .into_iter()
+        .filter(|(_, _, policy)| policy.max_age.is_some() || policy.max_total_bytes.is_some())
+        .map(|(kind, dir, policy)| RetentionTarget {
+            kind,
+            dir: PathBuf::from(dir),
+            policy: policy.clone(),
+        })
+        .collect();
+
+        Self {
+            config: retention.clone(),
+            stream_manager,
+            targets,
+            stats: RwLock::new(RetentionStats::default()),
+        }
+    }
+
+    /// 定期执行清理
+    pub async fn start(self: Arc<Self>) {
+        if !self.config.enabled || self.targets.is_empty() {
+            return;
+        }
+
+        let mut interval = tokio::time::interval(Duration::from_secs(self.config.interval.max(1)));
+
+        loop {
+            interval.tick().await;
+            self.run_once().await;
+        }
+    }
+
+    /// 获取清理统计
+    pub async fn get_stats(&self) -> RetentionStats {
+        self.stats.read().await.clone()
+    }
+
+    async fn run_once(&self) {
+        // 直播中的流的 HLS 片段由 HLS 管理器按播放列表长度清理
+        let mut live_streams = HashSet::new();
+        for (stream_key, stream) in self.stream_manager.list_streams().await {
+            if matches!(stream.get_status().await, StreamStatus::Live) {
+                live_streams.insert(stream_key);
+            }
+        }
+
+        let mut results = Vec::with_capacity(self.targets.len());
+        for target in &self.targets {
+            match prune(target, &live_streams).await {
+                Ok(result) => results.push((target.kind, result)),
+                Err(e) => warn!("Failed to apply {:?} retention in {}: {}", target.kind, target.dir.display(), e),
+            }
+        }
+
+        let mut stats = self.stats.write().await;
+        stats.runs += 1;
+        stats.last_run_at = Some(chrono::Utc::now());
+
+        for (kind, (files_deleted, bytes_reclaimed, current_bytes)) in results {
+            if files_deleted > 0 {
+                info!("Retention removed {} {:?} files, reclaimed {} bytes", files_deleted, kind, bytes_reclaimed);
+            }
+
+            let target = stats.targets.entry(kind).or_default();
+            target.files_deleted += files_deleted;
+            target.bytes_reclaimed += bytes_reclaimed;
+            target.last_run_files_deleted = files_deleted;
+            target.last_run_bytes_reclaimed = bytes_reclaimed;
+            target.current_bytes = current_bytes;
+        }
+    }
+}
+
+/// 按保留策略清理一个目录，返回 (删除的文件数, 回收的字节数, 剩余占用)
+async fn prune(target: &RetentionTarget, live_streams: &HashSet<String>) -> StreamResult<(u64, u64, u64)> {
+    let mut entries = collect_entries(&target.dir).await?;
+    entries.sort_by_key(|entry| entry.modified);
+
+    let now = SystemTime::now();
+    let mut total_bytes: u64 = entries.iter().map(|entry| entry.size).sum();
+    let mut files_deleted = 0;
+    let mut bytes_reclaimed = 0;
+
+    for entry in entries {
+        let age = now.duration_since(entry.modified).unwrap_or_default();
+        let expired = target.policy.max_age.is_some_and(|max_age| age > Duration::from_secs(max_age));
+        let over_quota = target.policy.max_total_bytes.is_some_and(|max_bytes| total_bytes > max_bytes);
+
+        if !expired && !over_quota {
+            continue;
+        }
+        if age < MIN_FILE_AGE || (target.kind == RetentionTargetKind::Hls && belongs_to_live_stream(&target.dir, &entry, live_streams)) {
+            continue;
+        }
+
+        for path in &entry.paths {
+            match fs::remove_file(path).await {
+                Ok(()) => {
+                    debug!("Retention removed {}", path.display());
+                    files_deleted += 1;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => warn!("Failed to remove {}: {}", path.display(), e),
+            }
+        }
+        total_bytes -= entry.size;
+        bytes_reclaimed += entry.size;
+
+        // 删除清空的子目录 (非空时失败，忽略)
+        if let Some(parent) = entry.paths[0].parent() {
+            if parent != target.dir {
+                let _ = fs::remove_dir(parent).await;
+            }
+        }
+    }
+
+    Ok((files_deleted, bytes_reclaimed, total_bytes))
+}
+
+/// 递归列出目录中的文件，录制文件与其元数据合并为一项
+async fn collect_entries(dir: &Path) -> StreamResult<Vec<Entry>> {
+    let mut files = HashMap::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let mut read_dir = match fs::read_dir(&dir).await {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        while let Some(entry) = read_dir.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else {
+                files.insert(entry.path(), (metadata.len(), metadata.modified()?));
+            }
+        }
+    }
+
+    let sidecars: HashSet<PathBuf> = files.keys()
+        .map(|path| metadata_path(path))
+        .filter(|sidecar| files.contains_key(sidecar))
+        .collect();
+
+    let entries = files.iter()
+        .filter(|(path, _)| !sidecars.contains(*path))
+        .map(|(path, (size, modified))| {
+            let mut entry = Entry {
+                paths: vec![path.clone()],
+                size: *size,
+                modified: *modified,
+            };
+            let sidecar = metadata_path(path);
+            if let Some((sidecar_size, _)) = files.get(&sidecar) {
+                entry.paths.push(sidecar);
+                entry.size += sidecar_size;
+            }
+            entry
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+/// HLS 目录结构为 `{key}.m3u8` 和 `{key}/segment_N.ts`
+fn belongs_to_live_stream(dir: &Path, entry: &Entry, live_streams: &HashSet<String>) -> bool {
+    let Ok(relative) = entry.paths[0].strip_prefix(dir) else {
+        return false;
+    };
+
+    let stream_key = match relative.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.components().next()
+            .and_then(|component| component.as_os_str().to_str()),
+        _ => relative.file_stem().and_then(|stem| stem.to_str()),
+    };
+
+    stream_key.is_some_and(|stream_key| live_streams.contains(stream_key))
+}
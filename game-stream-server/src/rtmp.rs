@@ -1,17 +1,42 @@
 use anyhow::Result;
 use std::sync::Arc;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use rml_rtmp::chunk_io::{ChunkDeserializer, ChunkSerializer};
+use rml_rtmp::handshake::{Handshake, HandshakeProcessResult, PeerType};
+use rml_rtmp::rml_amf0::Amf0Value;
+use rml_rtmp::sessions::{ServerSession, ServerSessionConfig, ServerSessionEvent, ServerSessionResult};
+use rml_rtmp::time::RtmpTimestamp;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
 use tracing::{info, error, debug, warn};
 use uuid::Uuid;
 
 use game_stream_common::{
     RtmpServerConfig, StreamManager, StreamInfo, StreamStatus, MediaPacket,
     VideoConfig, AudioConfig, VideoCodec, AudioCodec, ClientConnection, StreamProtocol,
-    StreamResult, StreamError
+    ClientStats, RemoteInput, InputEvent, StreamResult, StreamError
 };
 use crate::auth::AuthManager;
+use crate::overload::OverloadGuard;
+
+/// 向推流端发送时钟同步请求的间隔
+const CLOCK_SYNC_INTERVAL: Duration = Duration::from_secs(10);
+/// 选取往返时间最短的同步结果时参考的最近样本数
+const CLOCK_SYNC_SAMPLES: usize = 8;
+/// 握手必须在这个时间内完成
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// 每次从连接读取的缓冲区大小
+const READ_BUFFER_SIZE: usize = 4096;
+/// RTMP 消息类型 ID：设置块大小、AMF0 数据消息
+const SET_CHUNK_SIZE_TYPE_ID: u8 = 1;
+const AMF0_DATA_TYPE_ID: u8 = 18;
+const AMF0_COMMAND_TYPE_ID: u8 = 20;
+/// 服务器主动发送的命令 (onStatus、onRemoteInput、onClockSync) 使用的块流，会话自己只使用 2 到 6
+const SERVER_COMMAND_CSID: u8 = 8;
+/// 推流端发送的自定义数据消息，服务器会话会丢弃它们，需要在交给会话之前取出
+const PUBLISHER_DATA_MESSAGES: [&str; 3] = ["onClientStats", "onCaptureTime", "onClockSync"];
 
 /// RTMP 服务器
 #[derive(Clone)]
@@ -19,6 +44,7 @@ pub struct RtmpServer {
     config: RtmpServerConfig,
     stream_manager: Arc<StreamManager>,
     auth_manager: Arc<AuthManager>,
+    overload_guard: Arc<OverloadGuard>,
     connections: Arc<RwLock<HashMap<Uuid, RtmpConnection>>>,
 }
 
@@ -27,6 +53,7 @@ impl RtmpServer {
         config: &RtmpServerConfig,
         stream_manager: Arc<StreamManager>,
         auth_manager: Arc<AuthManager>,
+        overload_guard: Arc<OverloadGuard>,
     ) -> Result<Self> {
         info!("Initializing RTMP server...");
         
@@ -34,16 +61,23 @@ impl RtmpServer {
             config: config.clone(),
             stream_manager,
             auth_manager,
+            overload_guard,
             connections: Arc::new(RwLock::new(HashMap::new())),
         })
     }
     
-    pub async fn start(&mut self) -> Result<()> {
+    /// 绑定 RTMP 监听端口
+    pub async fn bind(&self) -> Result<TcpListener> {
         let bind_addr = format!("{}:{}", self.config.bind_addr, self.config.port);
         let listener = TcpListener::bind(&bind_addr).await?;
         
         info!("RTMP server listening on {}", bind_addr);
         
+        Ok(listener)
+    }
+    
+    /// 在已绑定的端口上接受 RTMP 连接
+    pub async fn serve(&mut self, listener: TcpListener) -> Result<()> {
         loop {
             match listener.accept().await {
                 Ok((stream, addr)) => {
@@ -56,6 +90,7 @@ impl RtmpServer {
                         addr,
                         self.stream_manager.clone(),
                         self.auth_manager.clone(),
+                        self.overload_guard.clone(),
                         self.config.clone(),
                     );
                     
@@ -94,6 +129,7 @@ struct RtmpConnection {
     remote_addr: std::net::SocketAddr,
     stream_manager: Arc<StreamManager>,
     auth_manager: Arc<AuthManager>,
+    overload_guard: Arc<OverloadGuard>,
     config: RtmpServerConfig,
 }
 
@@ -104,6 +140,7 @@ impl RtmpConnection {
         remote_addr: std::net::SocketAddr,
         stream_manager: Arc<StreamManager>,
         auth_manager: Arc<AuthManager>,
+        overload_guard: Arc<OverloadGuard>,
         config: RtmpServerConfig,
     ) -> Self {
         Self {
@@ -112,122 +149,191 @@ impl RtmpConnection {
             remote_addr,
             stream_manager,
             auth_manager,
+            overload_guard,
             config,
         }
     }
     
     async fn handle(&self) -> StreamResult<()> {
         info!("Handling RTMP connection {}", self.id);
+        let mut socket = self.stream.lock().await;
         
         // RTMP 握手
-        self.perform_handshake().await?;
+        let remaining = self.perform_handshake(&mut socket).await?;
         
         // 处理 RTMP 消息
-        self.process_messages().await?;
+        self.process_messages(&mut socket, &remaining).await?;
         
         Ok(())
     }
     
-    async fn perform_handshake(&self) -> StreamResult<()> {
+    /// C0/S0、C1/S1、C2/S2 握手，返回握手之后多读到的字节
+    async fn perform_handshake(&self, socket: &mut TcpStream) -> StreamResult<Vec<u8>> {
         debug!("Performing RTMP handshake for connection {}", self.id);
         
-        // 实际的 RTMP 握手逻辑
-        // 这里需要实现完整的 RTMP 握手协议
-        // 包括 C0/S0, C1/S1, C2/S2 消息交换
-        
-        // 模拟握手过程
-        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+        let mut handshake = Handshake::new(PeerType::Server);
+        let mut buffer = [0u8; READ_BUFFER_SIZE];
+        let remaining = tokio::time::timeout(HANDSHAKE_TIMEOUT, async {
+            loop {
+                let size = socket.read(&mut buffer).await?;
+                if size == 0 {
+                    return Err(StreamError::Network("RTMP client closed the connection during handshake".to_string()));
+                }
+                match handshake.process_bytes(&buffer[..size]).map_err(rtmp_error)? {
+                    HandshakeProcessResult::InProgress { response_bytes } => socket.write_all(&response_bytes).await?,
+                    HandshakeProcessResult::Completed { response_bytes, remaining_bytes } => {
+                        socket.write_all(&response_bytes).await?;
+                        return Ok(remaining_bytes);
+                    }
+                }
+            }
+        }).await.map_err(|_| StreamError::Network("Timed out during RTMP handshake".to_string()))??;
         
         info!("RTMP handshake completed for connection {}", self.id);
-        Ok(())
+        Ok(remaining)
     }
     
-    async fn process_messages(&self) -> StreamResult<()> {
+    async fn process_messages(&self, socket: &mut TcpStream, remaining: &[u8]) -> StreamResult<()> {
         debug!("Processing RTMP messages for connection {}", self.id);
         
+        let mut session = PublisherSession::new(self.config.chunk_size)?;
+        let mut pending = session.decode(remaining)?;
+        let mut buffer = [0u8; READ_BUFFER_SIZE];
         let mut stream_key: Option<String> = None;
         let mut live_stream: Option<Arc<game_stream_common::LiveStream>> = None;
+        let mut remote_input: Option<broadcast::Receiver<RemoteInput>> = None;
+        let mut clock = PublisherClock::default();
+        let mut clock_sync = tokio::time::interval(CLOCK_SYNC_INTERVAL);
         
-        // 模拟 RTMP 消息处理循环
-        loop {
-            // 读取 RTMP 消息
-            match self.read_rtmp_message().await {
-                Ok(message) => {
-                    match message {
-                        RtmpMessage::Connect { app_name } => {
-                            info!("RTMP connect to app: {}", app_name);
-                            self.send_connect_response().await?;
+        'messages: loop {
+            socket.write_all(&session.take_outbound()).await?;
+            
+            // 读取 RTMP 消息，发布后同时把观看者的远程输入转发给推流端，并定期同步推流端时钟
+            let messages = if pending.is_empty() {
+                tokio::select! {
+                    size = socket.read(&mut buffer) => match size {
+                        Ok(0) => Ok(vec![RtmpMessage::Disconnect]),
+                        Ok(size) => session.decode(&buffer[..size]),
+                        Err(e) => Err(e.into()),
+                    },
+                    input = next_remote_input(&mut remote_input) => {
+                        self.send_remote_input(&mut session, &clock.localize_input(input))?;
+                        continue;
+                    }
+                    _ = clock_sync.tick(), if live_stream.is_some() => {
+                        self.send_clock_sync(&mut session, chrono::Utc::now().timestamp_millis())?;
+                        continue;
+                    }
+                }
+            } else {
+                Ok(std::mem::take(&mut pending))
+            };
+            let messages = match messages {
+                Ok(messages) => messages,
+                Err(e) => {
+                    error!("Failed to read RTMP message: {}", e);
+                    break;
+                }
+            };
+            for message in messages {
+                match message {
+                    RtmpMessage::Connect { request_id, app_name } => {
+                        info!("RTMP connect to app: {}", app_name);
+                        self.send_connect_response(&mut session, request_id)?;
+                    }
+                    RtmpMessage::Publish { request_id, stream_key: key } => {
+                        info!("RTMP publish stream: {}", key);
+                        
+                        // 验证流密钥
+                        if !self.auth_manager.validate_stream_key(&key).await {
+                            warn!("Invalid stream key: {}", key);
+                            return Err(StreamError::Auth(format!("Invalid stream key: {}", key)));
                         }
-                        RtmpMessage::Publish { stream_key: key } => {
-                            info!("RTMP publish stream: {}", key);
-                            
-                            // 验证流密钥
-                            if !self.auth_manager.validate_stream_key(&key).await {
-                                warn!("Invalid stream key: {}", key);
-                                return Err(StreamError::Auth(format!("Invalid stream key: {}", key)));
-                            }
-                            
-                            // 创建直播流
-                            let stream_info = StreamInfo {
-                                stream_id: Uuid::new_v4(),
-                                stream_key: key.clone(),
-                                title: None,
-                                description: None,
-                                created_at: chrono::Utc::now(),
-                                is_live: false,
-                                viewer_count: 0,
-                                video_config: VideoConfig {
-                                    width: 1920,
-                                    height: 1080,
-                                    fps: 30,
-                                    bitrate: 2500,
-                                    codec: VideoCodec::H264,
-                                },
-                                audio_config: AudioConfig {
-                                    sample_rate: 44100,
-                                    channels: 2,
-                                    bitrate: 128,
-                                    codec: AudioCodec::Aac,
-                                },
+                        
+                        // 过载保护：拒绝新的推流，保留已有会话
+                        if let Err(e) = self.overload_guard.check_publish().await {
+                            self.send_publish_rejected(&mut session, &e.to_string())?;
+                            socket.write_all(&session.take_outbound()).await?;
+                            return Err(e);
+                        }
+                        
+                        // 创建直播流
+                        let stream_info = StreamInfo {
+                            stream_id: Uuid::new_v4(),
+                            stream_key: key.clone(),
+                            title: None,
+                            description: None,
+                            created_at: chrono::Utc::now(),
+                            is_live: false,
+                            viewer_count: 0,
+                            video_config: VideoConfig {
+                                width: 1920,
+                                height: 1080,
+                                fps: 30,
+                                bitrate: 2500,
+                                codec: VideoCodec::H264,
+                            },
+                            audio_config: AudioConfig {
+                                sample_rate: 44100,
+                                channels: 2,
+                                bitrate: 128,
+                                codec: AudioCodec::Aac,
+                            },
+                            thumbnail_url: None,
+                            tags: Default::default(),
+                        };
+                        
+                        let stream = self.stream_manager.create_stream(key.clone(), stream_info).await?;
+                        stream.set_status(StreamStatus::Live).await;
+                        
+                        stream_key = Some(key);
+                        remote_input = Some(stream.subscribe_remote_input());
+                        live_stream = Some(stream);
+                        
+                        self.send_publish_response(&mut session, request_id)?;
+                    }
+                    RtmpMessage::VideoData { data, timestamp } => {
+                        if let Some(stream) = &live_stream {
+                            let is_keyframe = is_keyframe(&data);
+                            let packet = MediaPacket::Video {
+                                data,
+                                timestamp,
+                                is_keyframe,
+                                capture_time: clock.capture_time(timestamp),
                             };
-                            
-                            let stream = self.stream_manager.create_stream(key.clone(), stream_info).await?;
-                            stream.set_status(StreamStatus::Live).await;
-                            
-                            stream_key = Some(key);
-                            live_stream = Some(stream);
-                            
-                            self.send_publish_response().await?;
+                            stream.send_media_packet(packet).await?;
                         }
-                        RtmpMessage::VideoData { data, timestamp } => {
-                            if let Some(stream) = &live_stream {
-                                let is_keyframe = self.is_keyframe(&data);
-                                let packet = MediaPacket::Video {
-                                    data,
-                                    timestamp,
-                                    is_keyframe,
-                                };
-                                stream.send_media_packet(packet).await?;
-                            }
+                    }
+                    RtmpMessage::AudioData { data, timestamp } => {
+                        if let Some(stream) = &live_stream {
+                            let packet = MediaPacket::Audio {
+                                data,
+                                timestamp,
+                                capture_time: clock.capture_time(timestamp),
+                            };
+                            stream.send_media_packet(packet).await?;
                         }
-                        RtmpMessage::AudioData { data, timestamp } => {
-                            if let Some(stream) = &live_stream {
-                                let packet = MediaPacket::Audio {
-                                    data,
-                                    timestamp,
-                                };
-                                stream.send_media_packet(packet).await?;
-                            }
+                    }
+                    RtmpMessage::CaptureTime { timestamp, capture_time } => {
+                        clock.record_capture_time(timestamp, capture_time);
+                    }
+                    RtmpMessage::ClockSync { server_time, client_time } => {
+                        let offset = clock.record_sync(server_time, client_time, chrono::Utc::now().timestamp_millis());
+                        if let Some(stream) = &live_stream {
+                            debug!("Publisher clock offset for {}: {} ms", stream.stream_key, offset);
+                            stream.latency.set_clock_offset(offset).await;
                         }
-                        RtmpMessage::Disconnect => {
-                            info!("RTMP client disconnected");
-                            break;
+                    }
+                    RtmpMessage::ClientStats { stats } => {
+                        if let Some(stream) = &live_stream {
+                            debug!("Client stats for {}: {:?}", stream.stream_key, stats);
+                            stream.set_client_stats(stats).await;
                         }
                     }
-                }
-                Err(e) => {
-                    error!("Failed to read RTMP message: {}", e);
-                    break;
+                    RtmpMessage::Disconnect => {
+                        info!("RTMP client disconnected");
+                        break 'messages;
+                    }
                 }
             }
         }
@@ -244,58 +350,306 @@ impl RtmpConnection {
         Ok(())
     }
     
-    async fn read_rtmp_message(&self) -> StreamResult<RtmpMessage> {
-        // 实际的 RTMP 消息读取逻辑
-        // 这里需要解析 RTMP 协议的各种消息类型
-        
-        // 模拟消息读取
-        tokio::time::sleep(tokio::time::Duration::from_millis(33)).await; // ~30fps
+    fn send_connect_response(&self, session: &mut PublisherSession, request_id: u32) -> StreamResult<()> {
+        debug!("Sending RTMP connect response");
+        session.accept(request_id)
+    }
+    
+    fn send_publish_response(&self, session: &mut PublisherSession, request_id: u32) -> StreamResult<()> {
+        debug!("Sending RTMP publish response");
+        session.accept(request_id)
+    }
+    
+    /// 在 publish 所在的消息流上发送 onStatus(level=error, code=NetStream.Publish.Rejected)，之后关闭连接
+    fn send_publish_rejected(&self, session: &mut PublisherSession, description: &str) -> StreamResult<()> {
+        debug!("Sending RTMP publish rejection: NetStream.Publish.Rejected ({})", description);
+        let status = Amf0Value::Object(HashMap::from([
+            ("level".to_string(), Amf0Value::Utf8String("error".to_string())),
+            ("code".to_string(), Amf0Value::Utf8String("NetStream.Publish.Rejected".to_string())),
+            ("description".to_string(), Amf0Value::Utf8String(description.to_string())),
+        ]));
+        session.command(session.publish_stream_id, "onStatus", vec![status])
+    }
+    
+    /// 通过 onClockSync 命令请求推流端时钟，命令参数为服务器当前时间 (Unix 毫秒)，
+    /// 推流端以 onClockSync 数据消息回复 [服务器时间, 推流端时间]
+    fn send_clock_sync(&self, session: &mut PublisherSession, server_time: i64) -> StreamResult<()> {
+        debug!("Sending RTMP onClockSync to connection {}: {}", self.id, server_time);
+        session.command(0, "onClockSync", vec![Amf0Value::Number(server_time as f64)])
+    }
+    
+    /// 通过 onRemoteInput 命令把观看者的输入发给推流端，命令参数为 RemoteInput 的 JSON
+    fn send_remote_input(&self, session: &mut PublisherSession, input: &RemoteInput) -> StreamResult<()> {
+        let payload = serde_json::to_string(input)?;
+        debug!("Sending RTMP onRemoteInput to connection {}: {}", self.id, payload);
+        session.command(0, "onRemoteInput", vec![Amf0Value::Utf8String(payload)])
+    }
+}
+
+/// FLV 视频标签的帧类型是否为关键帧：高 4 位 (Enhanced RTMP 去掉扩展头标志后的 3 位) 为 1
+fn is_keyframe(data: &bytes::Bytes) -> bool {
+    data.first().is_some_and(|header| (header >> 4) & 0x07 == 1)
+}
+
+/// 等待下一个远程输入，尚未发布时一直等待
+async fn next_remote_input(receiver: &mut Option<broadcast::Receiver<RemoteInput>>) -> RemoteInput {
+    loop {
+        let Some(current) = receiver.as_mut() else {
+            return std::future::pending().await;
+        };
+        match current.recv().await {
+            Ok(input) => return input,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                debug!("Publisher connection lagged, skipped {} remote input events", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => *receiver = None,
+        }
+    }
+}
+
+/// 推流端时钟 - 把 RTMP 时间戳换算为服务器时钟的采集时间
+#[derive(Debug, Default)]
+struct PublisherClock {
+    sync_samples: VecDeque<(i64, i64)>, // 最近的 (往返时间, 服务器时钟减推流端时钟)
+    offset_ms: Option<i64>,
+    capture_base: Option<i64>, // 推流端时钟的采集时间减 RTMP 时间戳
+}
+
+impl PublisherClock {
+    /// 记录一次时钟同步，取最近样本中往返时间最短的一次，返回服务器时钟减推流端时钟
+    fn record_sync(&mut self, sent: i64, client_time: i64, received: i64) -> i64 {
+        // 假设往返路径对称，推流端回复时服务器时钟为请求和响应的中点
+        let offset = sent + (received - sent) / 2 - client_time;
+        if self.sync_samples.len() == CLOCK_SYNC_SAMPLES {
+            self.sync_samples.pop_front();
+        }
+        self.sync_samples.push_back((received - sent, offset));
         
-        // 模拟不同类型的消息
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
-        let message_type = rng.gen_range(0..4);
+        let best = self.sync_samples.iter()
+            .min_by_key(|(rtt, _)| *rtt)
+            .map_or(offset, |(_, offset)| *offset);
+        self.offset_ms = Some(best);
+        best
+    }
+    
+    fn record_capture_time(&mut self, timestamp: u64, capture_time: i64) {
+        self.capture_base = Some(capture_time - timestamp as i64);
+    }
+    
+    /// 手柄快照的发送时间换算为推流端时钟，推流端据此统计观看者到推流端的延迟；
+    /// 尚未同步时钟时假设两端时钟一致
+    fn localize_input(&self, mut input: RemoteInput) -> RemoteInput {
+        if let InputEvent::Gamepad(state) = &mut input.event {
+            state.sent_at -= self.offset_ms.unwrap_or(0);
+        }
+        input
+    }
+    
+    /// 采集时间 (服务器时钟的 Unix 毫秒)，推流端未发送 onCaptureTime 时为 None；
+    /// 尚未同步时钟时假设两端时钟一致
+    fn capture_time(&self, timestamp: u64) -> Option<i64> {
+        self.capture_base.map(|base| base + timestamp as i64 + self.offset_ms.unwrap_or(0))
+    }
+}
+
+/// 推流连接的 RTMP 会话
+///
+/// rml_rtmp 的服务器会话会丢弃 @setDataFrame 以外的数据消息，所以推流端发来的块先经过前置的解析，
+/// 取出 onClientStats、onCaptureTime 和 onClockSync 数据消息，其余消息重新分块后交给会话
+struct PublisherSession {
+    session: ServerSession,
+    deserializer: ChunkDeserializer, // 推流端发来的块
+    serializer: ChunkSerializer,     // 重新分块交给会话
+    outbound: Vec<u8>,               // 等待发送给推流端的字节
+    chunk_size: usize,               // 会话发送的块大小，服务器命令也按这个大小分块
+    publish_stream_id: u32,          // publish 命令所在的消息流
+}
+
+impl PublisherSession {
+    fn new(chunk_size: u32) -> StreamResult<Self> {
+        let mut config = ServerSessionConfig::new();
+        config.chunk_size = chunk_size;
+        let (session, results) = ServerSession::new(config).map_err(rtmp_error)?;
         
-        match message_type {
-            0 => Ok(RtmpMessage::Connect { app_name: "live".to_string() }),
-            1 => Ok(RtmpMessage::Publish { stream_key: "test_stream".to_string() }),
-            2 => Ok(RtmpMessage::VideoData { 
-                data: bytes::Bytes::from(vec![0u8; 1024]), 
-                timestamp: chrono::Utc::now().timestamp_millis() as u64 
-            }),
-            3 => Ok(RtmpMessage::AudioData { 
-                data: bytes::Bytes::from(vec![0u8; 256]), 
-                timestamp: chrono::Utc::now().timestamp_millis() as u64 
-            }),
-            _ => Ok(RtmpMessage::Disconnect),
+        let mut publisher = Self {
+            session,
+            deserializer: ChunkDeserializer::new(),
+            serializer: ChunkSerializer::new(),
+            outbound: Vec::new(),
+            chunk_size: chunk_size as usize,
+            publish_stream_id: 0,
+        };
+        publisher.apply(results);
+        Ok(publisher)
+    }
+    
+    /// 解析推流端发来的字节，返回其中完整的消息，会话的响应留在 outbound 中
+    fn decode(&mut self, bytes: &[u8]) -> StreamResult<Vec<RtmpMessage>> {
+        let mut messages = Vec::new();
+        let mut input = bytes;
+        while let Some(payload) = self.deserializer.get_next_message(input).map_err(rtmp_error)? {
+            input = &[];
+            
+            let packet = match payload.type_id {
+                SET_CHUNK_SIZE_TYPE_ID => {
+                    let rml_rtmp::messages::RtmpMessage::SetChunkSize { size } = payload.to_rtmp_message().map_err(rtmp_error)? else {
+                        continue;
+                    };
+                    // 前置解析和交给会话的块都改用新的块大小
+                    self.deserializer.set_max_chunk_size(size as usize).map_err(rtmp_error)?;
+                    self.serializer.set_max_chunk_size(size, payload.timestamp).map_err(rtmp_error)?
+                }
+                AMF0_DATA_TYPE_ID => {
+                    if let rml_rtmp::messages::RtmpMessage::Amf0Data { values } = payload.to_rtmp_message().map_err(rtmp_error)? {
+                        if let Some(Amf0Value::Utf8String(name)) = values.first() {
+                            if PUBLISHER_DATA_MESSAGES.contains(&name.as_str()) {
+                                match publisher_data(name, &values[1..]) {
+                                    Some(message) => messages.push(message),
+                                    None => debug!("Ignoring malformed RTMP {} data message", name),
+                                }
+                                continue;
+                            }
+                        }
+                    }
+                    self.serializer.serialize(&payload, true, false).map_err(rtmp_error)?
+                }
+                AMF0_COMMAND_TYPE_ID => {
+                    if let Ok(rml_rtmp::messages::RtmpMessage::Amf0Command { command_name, .. }) = payload.to_rtmp_message() {
+                        if command_name == "publish" {
+                            self.publish_stream_id = payload.message_stream_id;
+                        }
+                    }
+                    self.serializer.serialize(&payload, true, false).map_err(rtmp_error)?
+                }
+                _ => self.serializer.serialize(&payload, true, false).map_err(rtmp_error)?,
+            };
+            
+            let results = self.session.handle_input(&packet.bytes).map_err(rtmp_error)?;
+            messages.extend(self.apply(results));
         }
+        Ok(messages)
     }
     
-    async fn send_connect_response(&self) -> StreamResult<()> {
-        debug!("Sending RTMP connect response");
-        // 实际的响应发送逻辑
+    /// 接受会话的 connect 或 publish 请求
+    fn accept(&mut self, request_id: u32) -> StreamResult<()> {
+        let results = self.session.accept_request(request_id).map_err(rtmp_error)?;
+        for message in self.apply(results) {
+            debug!("Ignoring RTMP message raised by accepted request: {:?}", message);
+        }
         Ok(())
     }
     
-    async fn send_publish_response(&self) -> StreamResult<()> {
-        debug!("Sending RTMP publish response");
-        // 实际的响应发送逻辑
+    /// 在独立的块流上发送 AMF0 命令
+    ///
+    /// 会话没有发送自定义命令的接口；每条命令都以完整的块头开始，不会干扰会话自己的块头压缩
+    fn command(&mut self, stream_id: u32, name: &str, arguments: Vec<Amf0Value>) -> StreamResult<()> {
+        let payload = rml_rtmp::messages::RtmpMessage::Amf0Command {
+            command_name: name.to_string(),
+            transaction_id: 0.0,
+            command_object: Amf0Value::Null,
+            additional_arguments: arguments,
+        }.into_message_payload(RtmpTimestamp::new(0), stream_id).map_err(rtmp_error)?;
+        
+        // 类型 0 的块头：时间戳、消息长度、类型 ID 和消息流 ID (小端)
+        self.outbound.push(SERVER_COMMAND_CSID);
+        self.outbound.extend_from_slice(&[0, 0, 0]);
+        self.outbound.extend_from_slice(&(payload.data.len() as u32).to_be_bytes()[1..]);
+        self.outbound.push(payload.type_id);
+        self.outbound.extend_from_slice(&stream_id.to_le_bytes());
+        for (index, chunk) in payload.data.chunks(self.chunk_size).enumerate() {
+            // 后续的块使用类型 3 的块头，沿用同一条消息的头
+            if index > 0 {
+                self.outbound.push(0xc0 | SERVER_COMMAND_CSID);
+            }
+            self.outbound.extend_from_slice(chunk);
+        }
         Ok(())
     }
     
-    fn is_keyframe(&self, data: &bytes::Bytes) -> bool {
-        // 简单的关键帧检测逻辑
-        // 实际实现需要解析视频数据格式
-        data.len() > 1000 // 简单假设大包是关键帧
+    /// 取出等待发送的字节
+    fn take_outbound(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.outbound)
     }
+    
+    /// 保存会话的响应，返回会话事件对应的消息
+    fn apply(&mut self, results: Vec<ServerSessionResult>) -> Vec<RtmpMessage> {
+        let mut messages = Vec::new();
+        for result in results {
+            match result {
+                ServerSessionResult::OutboundResponse(packet) => self.outbound.extend_from_slice(&packet.bytes),
+                ServerSessionResult::RaisedEvent(event) => messages.extend(session_message(event)),
+                ServerSessionResult::UnhandleableMessageReceived(payload) => {
+                    debug!("Ignoring RTMP message type {}", payload.type_id);
+                }
+            }
+        }
+        messages
+    }
+}
+
+/// 会话事件转换为连接处理的消息
+fn session_message(event: ServerSessionEvent) -> Option<RtmpMessage> {
+    match event {
+        ServerSessionEvent::ConnectionRequested { request_id, app_name } => Some(RtmpMessage::Connect { request_id, app_name }),
+        ServerSessionEvent::PublishStreamRequested { request_id, stream_key, .. } => Some(RtmpMessage::Publish { request_id, stream_key }),
+        ServerSessionEvent::PublishStreamFinished { .. } => Some(RtmpMessage::Disconnect),
+        ServerSessionEvent::VideoDataReceived { data, timestamp, .. } => Some(RtmpMessage::VideoData { data, timestamp: timestamp.value as u64 }),
+        ServerSessionEvent::AudioDataReceived { data, timestamp, .. } => Some(RtmpMessage::AudioData { data, timestamp: timestamp.value as u64 }),
+        event => {
+            debug!("RTMP session event: {:?}", event);
+            None
+        }
+    }
+}
+
+/// 解析推流端的自定义数据消息 (不含消息名)，格式不对时返回 None
+fn publisher_data(name: &str, values: &[Amf0Value]) -> Option<RtmpMessage> {
+    match (name, values) {
+        ("onClientStats", [stats, ..]) => match serde_json::from_value(json_value(stats)) {
+            Ok(stats) => Some(RtmpMessage::ClientStats { stats }),
+            Err(e) => {
+                debug!("Invalid client stats: {}", e);
+                None
+            }
+        },
+        ("onCaptureTime", [Amf0Value::Number(timestamp), Amf0Value::Number(capture_time), ..]) => {
+            Some(RtmpMessage::CaptureTime { timestamp: *timestamp as u64, capture_time: *capture_time as i64 })
+        }
+        ("onClockSync", [Amf0Value::Number(server_time), Amf0Value::Number(client_time), ..]) => {
+            Some(RtmpMessage::ClockSync { server_time: *server_time as i64, client_time: *client_time as i64 })
+        }
+        _ => None,
+    }
+}
+
+/// AMF0 转换为 JSON，整数值的 Number 转为 JSON 整数，以便反序列化为整数字段
+fn json_value(value: &Amf0Value) -> serde_json::Value {
+    match value {
+        Amf0Value::Number(value) if value.fract() == 0.0 && value.abs() < i64::MAX as f64 => (*value as i64).into(),
+        Amf0Value::Number(value) => (*value).into(),
+        Amf0Value::Boolean(value) => (*value).into(),
+        Amf0Value::Utf8String(value) => value.clone().into(),
+        Amf0Value::Object(fields) => serde_json::Value::Object(
+            fields.iter().map(|(key, value)| (key.clone(), json_value(value))).collect()
+        ),
+        Amf0Value::StrictArray(values) => values.iter().map(json_value).collect(),
+        Amf0Value::Null | Amf0Value::Undefined => serde_json::Value::Null,
+    }
+}
+
+fn rtmp_error(error: impl std::fmt::Display) -> StreamError {
+    StreamError::Rtmp(error.to_string())
 }
 
 /// RTMP 消息类型
 #[derive(Debug)]
 enum RtmpMessage {
-    Connect { app_name: String },
-    Publish { stream_key: String },
+    Connect { request_id: u32, app_name: String },
+    Publish { request_id: u32, stream_key: String },
     VideoData { data: bytes::Bytes, timestamp: u64 },
     AudioData { data: bytes::Bytes, timestamp: u64 },
+    ClientStats { stats: ClientStats }, // 推流端的 onClientStats 数据消息
+    CaptureTime { timestamp: u64, capture_time: i64 }, // 推流端的 onCaptureTime 数据消息，采集时间为推流端时钟
+    ClockSync { server_time: i64, client_time: i64 }, // 推流端对 onClockSync 的回复
     Disconnect,
 }
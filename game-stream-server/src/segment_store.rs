@@ -0,0 +1,224 @@
+use anyhow::Result;
+use std::sync::Arc;
+use std::path::PathBuf;
+use async_trait::async_trait;
+use bytes::Bytes;
+#[cfg(feature = "object-storage")]
+use object_store::{
+    aws::{AmazonS3, AmazonS3Builder},
+    path::Path as ObjectPath,
+    Attribute, Attributes, ObjectStore, PutMultipartOpts, PutOptions, PutPayload, WriteMultipart,
+};
+use tokio::fs;
+#[cfg(feature = "object-storage")]
+use tracing::info;
+
+use game_stream_common::{StorageConfig, StreamResult, StreamError};
+#[cfg(feature = "object-storage")]
+use game_stream_common::S3StorageConfig;
+
+/// HLS 片段存储 - 本地磁盘或共享的对象存储
+#[async_trait]
+pub trait SegmentStore: Send + Sync {
+    /// 写入片段
+    async fn put_segment(&self, stream_key: &str, segment_name: &str, data: Bytes) -> StreamResult<()>;
+
+    /// 读取片段
+    async fn get_segment(&self, stream_key: &str, segment_name: &str) -> StreamResult<Vec<u8>>;
+
+    /// 删除片段
+    async fn delete_segment(&self, stream_key: &str, segment_name: &str) -> StreamResult<()>;
+
+    /// 写入播放列表 (共享存储上供无状态分发节点读取)
+    async fn put_playlist(&self, stream_key: &str, content: &str) -> StreamResult<()>;
+
+    /// 播放列表中引用片段的 URI
+    fn segment_uri(&self, _stream_key: &str, segment_name: &str) -> String {
+        segment_name.to_string()
+    }
+}
+
+/// 根据配置创建片段存储
+pub fn create_segment_store(config: &StorageConfig) -> Result<Arc<dyn SegmentStore>> {
+    if config.s3.enabled {
+        #[cfg(feature = "object-storage")]
+        {
+            info!("Using S3 segment store: bucket {}", config.s3.bucket);
+            return Ok(Arc::new(S3SegmentStore::new(&config.s3)?));
+        }
+        #[cfg(not(feature = "object-storage"))]
+        anyhow::bail!("storage.s3 is enabled but the server was built without the object-storage feature");
+    }
+    Ok(Arc::new(LocalSegmentStore::new(&config.hls_segment_dir)))
+}
+
+/// 本地磁盘片段存储
+pub struct LocalSegmentStore {
+    dir: PathBuf,
+}
+
+impl LocalSegmentStore {
+    pub fn new(dir: &str) -> Self {
+        Self {
+            dir: PathBuf::from(dir),
+        }
+    }
+
+    fn segment_path(&self, stream_key: &str, segment_name: &str) -> StreamResult<PathBuf> {
+        if !is_safe_path_component(stream_key) || !is_safe_path_component(segment_name) {
+            return Err(StreamError::StreamNotFound(format!("Segment not found: {}", segment_name)));
+        }
+
+        Ok(self.dir.join(stream_key).join(segment_name))
+    }
+}
+
+#[async_trait]
+impl SegmentStore for LocalSegmentStore {
+    async fn put_segment(&self, stream_key: &str, segment_name: &str, data: Bytes) -> StreamResult<()> {
+        let segment_path = self.segment_path(stream_key, segment_name)?;
+        if let Some(parent) = segment_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        fs::write(segment_path, data).await?;
+        Ok(())
+    }
+
+    async fn get_segment(&self, stream_key: &str, segment_name: &str) -> StreamResult<Vec<u8>> {
+        fs::read(self.segment_path(stream_key, segment_name)?).await
+            .map_err(|_| StreamError::StreamNotFound(format!("Segment not found: {}", segment_name)))
+    }
+
+    async fn delete_segment(&self, stream_key: &str, segment_name: &str) -> StreamResult<()> {
+        fs::remove_file(self.segment_path(stream_key, segment_name)?).await?;
+        Ok(())
+    }
+
+    async fn put_playlist(&self, _stream_key: &str, _content: &str) -> StreamResult<()> {
+        // 本地播放列表由 HlsManager 写入 HLS 目录
+        Ok(())
+    }
+}
+
+/// S3 兼容对象存储片段存储
+#[cfg(feature = "object-storage")]
+pub struct S3SegmentStore {
+    store: AmazonS3,
+    prefix: String,
+    public_base_url: Option<String>,
+    part_size: usize,
+}
+
+#[cfg(feature = "object-storage")]
+impl S3SegmentStore {
+    pub fn new(config: &S3StorageConfig) -> Result<Self> {
+        let mut builder = AmazonS3Builder::from_env()
+            .with_bucket_name(&config.bucket)
+            .with_region(&config.region);
+
+        if let Some(endpoint) = &config.endpoint {
+            builder = builder
+                .with_endpoint(endpoint)
+                .with_allow_http(endpoint.starts_with("http://"));
+        }
+        if let Some(access_key_id) = &config.access_key_id {
+            builder = builder.with_access_key_id(access_key_id);
+        }
+        if let Some(secret_access_key) = &config.secret_access_key {
+            builder = builder.with_secret_access_key(secret_access_key);
+        }
+
+        Ok(Self {
+            store: builder.build()?,
+            prefix: config.prefix.trim_matches('/').to_string(),
+            public_base_url: config.public_base_url.as_ref()
+                .map(|url| url.trim_end_matches('/').to_string()),
+            part_size: config.multipart_part_size.max(5 * 1024 * 1024),
+        })
+    }
+
+    fn object_key(&self, name: &str) -> String {
+        if self.prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", self.prefix, name)
+        }
+    }
+
+    fn segment_path(&self, stream_key: &str, segment_name: &str) -> ObjectPath {
+        ObjectPath::from(self.object_key(&format!("{}/{}", stream_key, segment_name)))
+    }
+}
+
+#[cfg(feature = "object-storage")]
+#[async_trait]
+impl SegmentStore for S3SegmentStore {
+    async fn put_segment(&self, stream_key: &str, segment_name: &str, data: Bytes) -> StreamResult<()> {
+        let opts = PutMultipartOpts {
+            attributes: Attributes::from_iter([(Attribute::ContentType, "video/mp2t")]),
+            ..Default::default()
+        };
+
+        // 分片上传，避免大片段一次性占用一个请求
+        let upload = self.store.put_multipart_opts(&self.segment_path(stream_key, segment_name), opts).await
+            .map_err(storage_error)?;
+        let mut writer = WriteMultipart::new_with_chunk_size(upload, self.part_size);
+        writer.write(&data);
+        writer.finish().await.map_err(storage_error)?;
+
+        Ok(())
+    }
+
+    async fn get_segment(&self, stream_key: &str, segment_name: &str) -> StreamResult<Vec<u8>> {
+        let result = self.store.get(&self.segment_path(stream_key, segment_name)).await
+            .map_err(|e| match e {
+                object_store::Error::NotFound { .. } => {
+                    StreamError::StreamNotFound(format!("Segment not found: {}", segment_name))
+                }
+                e => storage_error(e),
+            })?;
+
+        Ok(result.bytes().await.map_err(storage_error)?.to_vec())
+    }
+
+    async fn delete_segment(&self, stream_key: &str, segment_name: &str) -> StreamResult<()> {
+        self.store.delete(&self.segment_path(stream_key, segment_name)).await
+            .map_err(storage_error)
+    }
+
+    async fn put_playlist(&self, stream_key: &str, content: &str) -> StreamResult<()> {
+        let opts = PutOptions {
+            attributes: Attributes::from_iter([
+                (Attribute::ContentType, "application/vnd.apple.mpegurl"),
+                (Attribute::CacheControl, "no-cache"),
+            ]),
+            ..Default::default()
+        };
+
+        let path = ObjectPath::from(self.object_key(&format!("{}.m3u8", stream_key)));
+        self.store.put_opts(&path, PutPayload::from(content.to_string()), opts).await
+            .map_err(storage_error)?;
+
+        Ok(())
+    }
+
+    fn segment_uri(&self, stream_key: &str, segment_name: &str) -> String {
+        match &self.public_base_url {
+            Some(base_url) => format!("{}/{}", base_url, self.object_key(&format!("{}/{}", stream_key, segment_name))),
+            None => segment_name.to_string(),
+        }
+    }
+}
+
+/// 检查路径片段是否安全 (不能跳出 HLS 目录)
+pub fn is_safe_path_component(component: &str) -> bool {
+    !component.is_empty()
+        && !component.starts_with('.')
+        && !component.contains(['/', '\\'])
+}
+
+#[cfg(feature = "object-storage")]
+pub(crate) fn storage_error(e: object_store::Error) -> StreamError {
+    StreamError::Io(std::io::Error::other(e))
+}
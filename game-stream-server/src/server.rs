@@ -1,160 +1,429 @@
 use anyhow::Result;
+use axum::Router;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::{info, error};
 
-use game_stream_common::{ServerConfig, StreamManager, StreamResult};
+use game_stream_common::{ServerConfig, StreamManager, StreamObserver};
 use crate::rtmp::RtmpServer;
+#[cfg(feature = "webrtc")]
 use crate::webrtc::WebRtcServer;
 use crate::http::HttpServer;
 use crate::auth::AuthManager;
+#[cfg(feature = "hls")]
 use crate::hls::HlsManager;
+use crate::analytics::AnalyticsManager;
+use crate::webhook::WebhookManager;
+use crate::systemd;
+use crate::overload::OverloadGuard;
+use crate::relay::RelayManager;
+use crate::cluster::ClusterDirectory;
+use crate::restream::RestreamManager;
+use crate::push_pool::PushPool;
+use crate::recording::RecordingManager;
+use crate::recording_upload::RecordingUploader;
+use crate::vod::VodManager;
+use crate::clip::ClipManager;
+use crate::thumbnail::ThumbnailManager;
+use crate::retention::RetentionManager;
+use crate::composite::CompositeManager;
+use crate::ingest::{IngestContext, IngestProtocol};
 
 /// 主要的流媒体服务器
 pub struct StreamingServer {
     config: ServerConfig,
     stream_manager: Arc<StreamManager>,
     auth_manager: Arc<AuthManager>,
+    #[cfg(feature = "hls")]
     hls_manager: Arc<HlsManager>,
+    analytics_manager: Arc<AnalyticsManager>,
+    webhook_manager: Arc<WebhookManager>,
+    overload_guard: Arc<OverloadGuard>,
+    relay_manager: Arc<RelayManager>,
+    cluster_directory: Arc<ClusterDirectory>,
+    restream_manager: Arc<RestreamManager>,
+    push_pool: Arc<PushPool>,
+    recording_manager: Arc<RecordingManager>,
+    clip_manager: Arc<ClipManager>,
+    thumbnail_manager: Arc<ThumbnailManager>,
+    retention_manager: Arc<RetentionManager>,
+    composite_manager: Arc<CompositeManager>,
     rtmp_server: RtmpServer,
+    #[cfg(feature = "webrtc")]
     webrtc_server: WebRtcServer,
     http_server: HttpServer,
+    ingests: Vec<Box<dyn IngestProtocol>>, // 注册的第三方推流协议
 }
 
-impl StreamingServer {
-    pub async fn new(config: ServerConfig) -> Result<Self> {
+/// 嵌入服务器的程序用来创建 StreamingServer：可以注入自己的认证和流管理器，合并自己的 axum 路由
+///
+/// ```ignore
+/// let auth = AuthManager::new(&config.auth).with_validator(Arc::new(MyValidator));
+/// let server = StreamingServer::builder(config)
+///     .auth_manager(Arc::new(auth))
+///     .merge_routes(Router::new().route("/my/health", get(|| async { "ok" })))
+///     .build().await?;
+/// ```
+pub struct StreamingServerBuilder {
+    config: ServerConfig,
+    stream_manager: Option<Arc<StreamManager>>,
+    auth_manager: Option<Arc<AuthManager>>,
+    routes: Vec<Router>,
+    ingests: Vec<Box<dyn IngestProtocol>>,
+    observers: Vec<Arc<dyn StreamObserver>>,
+}
+
+impl StreamingServerBuilder {
+    /// 与嵌入的程序共用的流管理器，程序可以订阅流事件或直接读写流
+    pub fn stream_manager(mut self, stream_manager: Arc<StreamManager>) -> Self {
+        self.stream_manager = Some(stream_manager);
+        self
+    }
+    
+    /// 代替按 [auth] 创建的认证管理器
+    pub fn auth_manager(mut self, auth_manager: Arc<AuthManager>) -> Self {
+        self.auth_manager = Some(auth_manager);
+        self
+    }
+    
+    /// 在 HTTP 服务中合并额外的路由，路径不能与内置路由重复
+    pub fn merge_routes(mut self, router: Router) -> Self {
+        self.routes.push(router);
+        self
+    }
+    
+    /// 注册第三方推流协议，见 StreamingServer::register_ingest
+    pub fn ingest(mut self, protocol: impl IngestProtocol) -> Self {
+        self.ingests.push(Box::new(protocol));
+        self
+    }
+    
+    /// 挂接到流的创建、状态变化和媒体数据上的回调，见 StreamObserver
+    pub fn observer(mut self, observer: impl StreamObserver + 'static) -> Self {
+        self.observers.push(Arc::new(observer));
+        self
+    }
+    
+    pub async fn build(self) -> Result<StreamingServer> {
+        let Self { config, stream_manager, auth_manager, routes, ingests, observers } = self;
         info!("Initializing streaming server...");
         
         // 创建共享组件
-        let stream_manager = Arc::new(StreamManager::new());
-        let auth_manager = Arc::new(AuthManager::new(&config.auth));
+        let stream_manager = stream_manager.unwrap_or_else(|| Arc::new(StreamManager::new()));
+        observers.into_iter().for_each(|observer| stream_manager.add_observer(observer));
+        let auth_manager = auth_manager.unwrap_or_else(|| Arc::new(AuthManager::new(&config.auth)));
+        #[cfg(feature = "hls")]
         let hls_manager = Arc::new(HlsManager::new(&config.storage).await?);
+        let analytics_manager = Arc::new(AnalyticsManager::new(&config.analytics, stream_manager.clone()));
+        let webhook_manager = Arc::new(WebhookManager::new(&config.webhooks));
+        let overload_guard = Arc::new(OverloadGuard::new(&config.overload, stream_manager.clone()));
+        let cluster_directory = Arc::new(ClusterDirectory::new(
+            &config.cluster,
+            stream_manager.clone(),
+            overload_guard.clone(),
+        )?);
+        let push_pool = Arc::new(PushPool::new(&config.push_pool));
+        let restream_manager = Arc::new(RestreamManager::new(
+            &config.restream,
+            stream_manager.clone(),
+            push_pool.clone(),
+        ));
+        let recording_uploader = if config.recording.upload.enabled {
+            Some(Arc::new(RecordingUploader::new(&config.recording.upload, webhook_manager.clone())?))
+        } else {
+            None
+        };
+        let recording_manager = Arc::new(RecordingManager::new(
+            &config.recording,
+            stream_manager.clone(),
+            recording_uploader,
+            webhook_manager.clone(),
+        ));
+        let vod_manager = Arc::new(VodManager::new(&config.recording));
+        let clip_manager = Arc::new(ClipManager::new(&config.clips, stream_manager.clone()));
+        let thumbnail_manager = Arc::new(ThumbnailManager::new(&config.thumbnails, stream_manager.clone()));
+        let retention_manager = Arc::new(RetentionManager::new(&config, stream_manager.clone()));
+        let composite_manager = Arc::new(CompositeManager::new(
+            &config.composite,
+            stream_manager.clone(),
+            auth_manager.clone(),
+        ));
+        let relay_manager = Arc::new(RelayManager::new(
+            &config.relay,
+            stream_manager.clone(),
+            cluster_directory.clone(),
+            push_pool.clone(),
+        ));
         
         // 创建各个服务器组件
         let rtmp_server = RtmpServer::new(
             &config.rtmp,
             stream_manager.clone(),
             auth_manager.clone(),
+            overload_guard.clone(),
         ).await?;
         
+        #[cfg(feature = "webrtc")]
         let webrtc_server = WebRtcServer::new(
             &config.webrtc,
+            config.latency_mode,
             stream_manager.clone(),
+            overload_guard.clone(),
+            relay_manager.clone(),
         ).await?;
         
-        let http_server = HttpServer::new(
+        let mut http_server = HttpServer::new(
             &config.http,
             stream_manager.clone(),
-            webrtc_server.get_signaling_handler(),
-            hls_manager.clone(),
+            auth_manager.clone(),
+            config.latency_mode,
+            #[cfg(feature = "webrtc")] webrtc_server.get_signaling_handler(),
+            #[cfg(feature = "hls")] hls_manager.clone(),
+            analytics_manager.clone(),
+            overload_guard.clone(),
+            relay_manager.clone(),
+            cluster_directory.clone(),
+            restream_manager.clone(),
+            recording_manager.clone(),
+            vod_manager,
+            clip_manager.clone(),
+            thumbnail_manager.clone(),
+            retention_manager.clone(),
+            composite_manager.clone(),
         ).await?;
+        for router in routes {
+            http_server.merge_routes(router);
+        }
         
-        Ok(Self {
+        Ok(StreamingServer {
             config,
             stream_manager,
             auth_manager,
+            #[cfg(feature = "hls")]
             hls_manager,
+            analytics_manager,
+            webhook_manager,
+            overload_guard,
+            relay_manager,
+            cluster_directory,
+            restream_manager,
+            push_pool,
+            recording_manager,
+            clip_manager,
+            thumbnail_manager,
+            retention_manager,
+            composite_manager,
             rtmp_server,
+            #[cfg(feature = "webrtc")]
             webrtc_server,
             http_server,
+            ingests,
         })
     }
+}
+
+impl StreamingServer {
+    pub fn builder(config: ServerConfig) -> StreamingServerBuilder {
+        StreamingServerBuilder {
+            config,
+            stream_manager: None,
+            auth_manager: None,
+            routes: Vec::new(),
+            ingests: Vec::new(),
+            observers: Vec::new(),
+        }
+    }
+    
+    pub async fn new(config: ServerConfig) -> Result<Self> {
+        Self::builder(config).build().await
+    }
+    
+    /// 注册第三方推流协议，需要在 start 之前调用；与 RTMP 推流使用相同的认证和过载保护
+    pub fn register_ingest(&mut self, protocol: Box<dyn IngestProtocol>) {
+        info!("Registered ingest protocol: {}", protocol.name());
+        self.ingests.push(protocol);
+    }
+    
+    pub fn config(&self) -> &ServerConfig {
+        &self.config
+    }
+    
+    pub fn stream_manager(&self) -> Arc<StreamManager> {
+        self.stream_manager.clone()
+    }
+    
+    pub fn auth_manager(&self) -> Arc<AuthManager> {
+        self.auth_manager.clone()
+    }
+    
+    /// 完整的 HTTP 路由 (API、WebRTC 信令、HLS 和静态文件)，嵌入的程序可以挂在自己的 HTTP 服务下，
+    /// 此时 [http] 的端口仍由 start 监听
+    pub async fn router(&self) -> Router {
+        self.http_server.build_router().await
+    }
     
     pub async fn start(&mut self) -> Result<()> {
         info!("Starting streaming server...");
         
+        // 先绑定所有监听端口，确保就绪通知发出时端口已可用
+        let rtmp_listener = self.rtmp_server.bind().await?;
+        let http_listener = self.http_server.bind().await?;
+        for protocol in &mut self.ingests {
+            protocol.bind().await
+                .map_err(|e| anyhow::anyhow!("Failed to bind ingest protocol {}: {}", protocol.name(), e))?;
+        }
+        
         // 启动各个服务器组件
-        let rtmp_handle = {
+        let mut rtmp_handle = {
             let mut rtmp_server = self.rtmp_server.clone();
             tokio::spawn(async move {
-                if let Err(e) = rtmp_server.start().await {
+                if let Err(e) = rtmp_server.serve(rtmp_listener).await {
                     error!("RTMP server error: {}", e);
                 }
             })
         };
         
-        let webrtc_handle = {
+        // 编译时可选的观看协议 (webrtc、hls 特性)，任务返回组件名称
+        #[allow(unused_mut)]
+        let mut viewer_handles: tokio::task::JoinSet<&'static str> = tokio::task::JoinSet::new();
+        #[cfg(feature = "webrtc")]
+        {
             let mut webrtc_server = self.webrtc_server.clone();
-            tokio::spawn(async move {
+            viewer_handles.spawn(async move {
                 if let Err(e) = webrtc_server.start().await {
                     error!("WebRTC server error: {}", e);
                 }
-            })
-        };
+                "WebRTC server"
+            });
+        }
         
-        let http_handle = {
+        let mut http_handle = {
             let mut http_server = self.http_server.clone();
             tokio::spawn(async move {
-                if let Err(e) = http_server.start().await {
+                if let Err(e) = http_server.serve(http_listener).await {
                     error!("HTTP server error: {}", e);
                 }
             })
         };
         
-        let hls_handle = {
+        // 启动第三方推流协议
+        let mut ingest_handles = tokio::task::JoinSet::new();
+        let context = IngestContext::new(
+            self.stream_manager.clone(),
+            self.auth_manager.clone(),
+            self.overload_guard.clone(),
+        );
+        for protocol in self.ingests.drain(..) {
+            let context = context.clone();
+            ingest_handles.spawn(async move {
+                let name = protocol.name().to_string();
+                if let Err(e) = protocol.serve(context).await {
+                    error!("{} ingest error: {}", name, e);
+                }
+                name
+            });
+        }
+        
+        // 启动 HLS 切片
+        #[cfg(feature = "hls")]
+        {
             let hls_manager = self.hls_manager.clone();
             let stream_manager = self.stream_manager.clone();
-            tokio::spawn(async move {
-                if let Err(e) = Self::start_hls_processing(hls_manager, stream_manager).await {
-                    error!("HLS processing error: {}", e);
-                }
-            })
-        };
+            viewer_handles.spawn(async move {
+                hls_manager.start(stream_manager).await;
+                "HLS processing"
+            });
+        }
+        
+        // 启动观看分析
+        tokio::spawn(self.analytics_manager.clone().start());
+        
+        // 启动 Webhook 分发
+        tokio::spawn(self.webhook_manager.clone().start(self.stream_manager.clone()));
+        
+        // 启动资源采样
+        tokio::spawn(self.overload_guard.clone().start());
+        
+        // 启动空闲中继清理
+        tokio::spawn(self.relay_manager.clone().start());
+        tokio::spawn(self.relay_manager.clone().serve_internal());
+        
+        // 启动集群流目录登记
+        tokio::spawn(self.cluster_directory.clone().start());
+        
+        // 启动转推
+        tokio::spawn(self.restream_manager.clone().start());
+        
+        // 启动自动录制
+        tokio::spawn(self.recording_manager.clone().start());
+        
+        // 启动即时剪辑缓冲
+        tokio::spawn(self.clip_manager.clone().start());
+        
+        // 启动缩略图生成
+        tokio::spawn(self.thumbnail_manager.clone().start());
+        
+        // 启动磁盘数据清理
+        tokio::spawn(self.retention_manager.clone().start());
+        
+        // 启动多路合成 (跟随输入流结束)
+        tokio::spawn(self.composite_manager.clone().start());
+        
+        // 启动推流连接池空闲清理
+        tokio::spawn(self.push_pool.clone().start());
         
         info!("All server components started");
         info!("RTMP server listening on: {}:{}", self.config.rtmp.bind_addr, self.config.rtmp.port);
         info!("HTTP server listening on: {}:{}", self.config.http.bind_addr, self.config.http.port);
         
+        // 通知 systemd 服务已就绪
+        systemd::notify_ready();
+        systemd::notify_status("Accepting RTMP and HTTP connections");
+        
+        // systemd 看门狗心跳 (未启用时使用一个不会触发的间隔)
+        let watchdog_interval = systemd::watchdog_interval();
+        let mut watchdog = tokio::time::interval(watchdog_interval.unwrap_or(Duration::from_secs(3600)));
+        
         // 等待任何一个服务器组件完成或出错
-        tokio::select! {
-            result = rtmp_handle => {
-                match result {
-                    Ok(_) => info!("RTMP server completed"),
-                    Err(e) => error!("RTMP server task failed: {}", e),
+        loop {
+            tokio::select! {
+                result = &mut rtmp_handle => {
+                    match result {
+                        Ok(_) => info!("RTMP server completed"),
+                        Err(e) => error!("RTMP server task failed: {}", e),
+                    }
+                    break;
                 }
-            }
-            result = webrtc_handle => {
-                match result {
-                    Ok(_) => info!("WebRTC server completed"),
-                    Err(e) => error!("WebRTC server task failed: {}", e),
+                result = &mut http_handle => {
+                    match result {
+                        Ok(_) => info!("HTTP server completed"),
+                        Err(e) => error!("HTTP server task failed: {}", e),
+                    }
+                    break;
                 }
-            }
-            result = http_handle => {
-                match result {
-                    Ok(_) => info!("HTTP server completed"),
-                    Err(e) => error!("HTTP server task failed: {}", e),
+                Some(result) = ingest_handles.join_next() => {
+                    match result {
+                        Ok(name) => info!("{} ingest completed", name),
+                        Err(e) => error!("Ingest task failed: {}", e),
+                    }
+                    break;
                 }
-            }
-            result = hls_handle => {
-                match result {
-                    Ok(_) => info!("HLS processing completed"),
-                    Err(e) => error!("HLS processing task failed: {}", e),
+                Some(result) = viewer_handles.join_next() => {
+                    match result {
+                        Ok(name) => info!("{} completed", name),
+                        Err(e) => error!("Viewer protocol task failed: {}", e),
+                    }
+                    break;
+                }
+                _ = watchdog.tick(), if watchdog_interval.is_some() => {
+                    systemd::notify_watchdog();
                 }
             }
         }
         
-        Ok(())
-    }
-    
-    async fn start_hls_processing(
-        hls_manager: Arc<HlsManager>,
-        stream_manager: Arc<StreamManager>,
-    ) -> StreamResult<()> {
-        info!("Starting HLS processing...");
+        systemd::notify_stopping();
         
-        loop {
-            // 获取所有活跃的流
-            let streams = stream_manager.list_streams().await;
-            
-            for (stream_key, stream) in streams {
-                // 为每个流生成HLS片段
-                if let Err(e) = hls_manager.process_stream(&stream_key, &stream).await {
-                    error!("Failed to process HLS for stream {}: {}", stream_key, e);
-                }
-            }
-            
-            // 等待一段时间再处理下一轮
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-        }
+        Ok(())
     }
 }
 
@@ -0,0 +1,90 @@
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// 通知 systemd 服务已就绪
+pub fn notify_ready() {
+    notify("READY=1");
+}
+
+/// 通知 systemd 服务正在停止
+pub fn notify_stopping() {
+    notify("STOPPING=1");
+}
+
+/// 更新 systemd 中显示的服务状态
+pub fn notify_status(status: &str) {
+    notify(&format!("STATUS={}", status));
+}
+
+/// 发送看门狗心跳
+pub fn notify_watchdog() {
+    notify("WATCHDOG=1");
+}
+
+/// 获取看门狗心跳间隔 (systemd 超时时间的一半)
+pub fn watchdog_interval() -> Option<Duration> {
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+
+    // WATCHDOG_PID 存在时必须与当前进程匹配
+    if let Ok(pid) = std::env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok()? != std::process::id() {
+            return None;
+        }
+    }
+
+    if usec == 0 {
+        return None;
+    }
+
+    Some(Duration::from_micros(usec / 2))
+}
+
+/// 向 $NOTIFY_SOCKET 发送 sd_notify 消息，未在 systemd 下运行时忽略
+#[cfg(unix)]
+fn notify(state: &str) {
+    use std::os::unix::net::UnixDatagram;
+
+    let socket_path = match std::env::var("NOTIFY_SOCKET") {
+        Ok(path) if !path.is_empty() => path,
+        _ => return,
+    };
+
+    let socket = match UnixDatagram::unbound() {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!("Failed to create sd_notify socket: {}", e);
+            return;
+        }
+    };
+
+    let result = match socket_path.strip_prefix('@') {
+        Some(name) => send_abstract(&socket, name, state),
+        None => socket.send_to(state.as_bytes(), &socket_path).map(|_| ()),
+    };
+
+    match result {
+        Ok(()) => debug!("sd_notify: {}", state),
+        Err(e) => warn!("Failed to send sd_notify message {}: {}", state, e),
+    }
+}
+
+#[cfg(not(unix))]
+fn notify(_state: &str) {}
+
+/// 发送到抽象命名空间的 socket (仅 Linux)
+#[cfg(target_os = "linux")]
+fn send_abstract(socket: &std::os::unix::net::UnixDatagram, name: &str, state: &str) -> std::io::Result<()> {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::SocketAddr;
+
+    let addr = SocketAddr::from_abstract_name(name.as_bytes())?;
+    socket.send_to_addr(state.as_bytes(), &addr).map(|_| ())
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn send_abstract(_socket: &std::os::unix::net::UnixDatagram, _name: &str, _state: &str) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "abstract notify sockets are only supported on Linux",
+    ))
+}
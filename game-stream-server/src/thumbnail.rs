@@ -0,0 +1,249 @@
+use std::sync::Arc;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use bytes::Bytes;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::{RwLock, broadcast};
+use tokio::task::JoinHandle;
+use tracing::{info, debug, warn};
+
+use game_stream_common::{
+    ThumbnailConfig, ThumbnailFormat, StreamManager, StreamEvent, StreamStatus, MediaPacket, MediaReceiver,
+    StreamResult, StreamError, recv_media
+};
+use crate::recording::FlvWriter;
+use crate::segment_store::is_safe_path_component;
+
+/// 单次解码的超时时间
+const DECODE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 缩略图管理器 - 定期解码每路直播流的关键帧生成缩略图
+pub struct ThumbnailManager {
+    config: ThumbnailConfig,
+    stream_manager: Arc<StreamManager>,
+    tasks: RwLock<HashMap<String, JoinHandle<()>>>,
+    thumbnails: RwLock<HashMap<String, Thumbnail>>,
+}
+
+/// 最新的缩略图
+#[derive(Clone)]
+pub struct Thumbnail {
+    pub data: Bytes,
+    pub format: ThumbnailFormat,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ThumbnailManager {
+    pub fn new(config: &ThumbnailConfig, stream_manager: Arc<StreamManager>) -> Self {
+        info!("Initializing thumbnail manager...");
+
+        Self {
+            config: config.clone(),
+            stream_manager,
+            tasks: RwLock::new(HashMap::new()),
+            thumbnails: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 订阅事件总线，为直播中的流定期生成缩略图
+    pub async fn start(self: Arc<Self>) {
+        if !self.config.enabled {
+            return;
+        }
+
+        let mut events = self.stream_manager.subscribe_events();
+
+        // 启动前已经在直播的流
+        for (stream_key, stream) in self.stream_manager.list_streams().await {
+            if matches!(stream.get_status().await, StreamStatus::Live) {
+                self.clone().start_task(stream_key, stream.subscribe_media()).await;
+            }
+        }
+
+        loop {
+            match events.recv().await {
+                Ok(StreamEvent::StatusChanged { stream_key, status }) => match status {
+                    StreamStatus::Live => {
+                        if let Some(stream) = self.stream_manager.get_stream(&stream_key).await {
+                            self.clone().start_task(stream_key, stream.subscribe_media()).await;
+                        }
+                    }
+                    StreamStatus::Stopped | StreamStatus::Error(_) => self.stop_task(&stream_key).await,
+                    _ => {}
+                },
+                Ok(StreamEvent::StreamRemoved { stream_key }) => {
+                    self.stop_task(&stream_key).await;
+                    // 流结束后从磁盘读取最后一张缩略图
+                    self.thumbnails.write().await.remove(&stream_key);
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Thumbnail manager lagged behind event bus, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+
+    /// 获取流的最新缩略图 (内存中没有时读取磁盘上最后保存的一张)
+    pub async fn get_thumbnail(&self, stream_key: &str) -> StreamResult<Thumbnail> {
+        if let Some(thumbnail) = self.thumbnails.read().await.get(stream_key) {
+            return Ok(thumbnail.clone());
+        }
+
+        let path = self.thumbnail_path(stream_key)?;
+        let data = fs::read(&path).await
+            .map_err(|_| StreamError::StreamNotFound(stream_key.to_string()))?;
+        let updated_at = fs::metadata(&path).await?.modified()
+            .map(chrono::DateTime::<chrono::Utc>::from)
+            .unwrap_or_else(|_| chrono::Utc::now());
+
+        Ok(Thumbnail {
+            data: Bytes::from(data),
+            format: self.config.format,
+            updated_at,
+        })
+    }
+
+    /// 缩略图的访问地址
+    pub fn thumbnail_url(&self, stream_key: &str) -> String {
+        format!("/api/streams/{}/thumbnail.{}", stream_key, self.config.format.extension())
+    }
+
+    /// 缩略图的刷新间隔，用于设置缓存时间
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.config.interval.max(1))
+    }
+
+    /// 启动生成任务 (同名流重新推流时替换旧任务)
+    async fn start_task(self: Arc<Self>, stream_key: String, media_receiver: MediaReceiver) {
+        let task = tokio::spawn(self.clone().run(stream_key.clone(), media_receiver));
+
+        if let Some(previous) = self.tasks.write().await.insert(stream_key, task) {
+            previous.abort();
+        }
+    }
+
+    async fn stop_task(&self, stream_key: &str) {
+        if let Some(task) = self.tasks.write().await.remove(stream_key) {
+            debug!("Stopping thumbnail generation for stream: {}", stream_key);
+            task.abort();
+        }
+    }
+
+    /// 生成任务：每隔一个间隔取下一个关键帧解码
+    async fn run(self: Arc<Self>, stream_key: String, mut media_receiver: MediaReceiver) {
+        let mut video_header: Option<Arc<MediaPacket>> = None;
+        let mut last_generated: Option<Instant> = None;
+        let mut failures = 0u32;
+
+        while let Some(packet) = recv_media(&mut media_receiver).await {
+            let MediaPacket::Video { is_keyframe, .. } = packet.as_ref() else {
+                continue;
+            };
+            if packet.is_sequence_header() {
+                video_header = Some(packet);
+                continue;
+            }
+            if !is_keyframe || last_generated.is_some_and(|last| last.elapsed() < self.interval()) {
+                continue;
+            }
+            last_generated = Some(Instant::now());
+
+            // 解码在当前任务中进行，期间积压的数据包在广播通道中被跳过，不影响推流
+            match self.generate(&stream_key, video_header.as_deref(), &packet).await {
+                Ok(()) => failures = 0,
+                Err(e) => {
+                    failures += 1;
+                    if failures == 1 {
+                        warn!("Failed to generate thumbnail for stream {}: {}", stream_key, e);
+                    } else {
+                        debug!("Failed to generate thumbnail for stream {}: {}", stream_key, e);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn generate(&self, stream_key: &str, video_header: Option<&MediaPacket>, keyframe: &MediaPacket) -> StreamResult<()> {
+        let data = decode_keyframe(&self.config, video_header, keyframe).await?;
+
+        let path = self.thumbnail_path(stream_key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(&path, &data).await?;
+
+        self.thumbnails.write().await.insert(stream_key.to_string(), Thumbnail {
+            data,
+            format: self.config.format,
+            updated_at: chrono::Utc::now(),
+        });
+
+        if let Some(stream) = self.stream_manager.get_stream(stream_key).await {
+            stream.info.write().await.thumbnail_url = Some(self.thumbnail_url(stream_key));
+        }
+
+        debug!("Generated thumbnail for stream {}", stream_key);
+        Ok(())
+    }
+
+    fn thumbnail_path(&self, stream_key: &str) -> StreamResult<PathBuf> {
+        if !is_safe_path_component(stream_key) {
+            return Err(StreamError::StreamNotFound(stream_key.to_string()));
+        }
+
+        Ok(PathBuf::from(&self.config.dir).join(format!("{}.{}", stream_key, self.config.format.extension())))
+    }
+}
+
+/// 将序列头和关键帧封装为 FLV 交给 FFmpeg 解码并缩放为图片
+async fn decode_keyframe(config: &ThumbnailConfig, video_header: Option<&MediaPacket>, keyframe: &MediaPacket) -> StreamResult<Bytes> {
+    let mut writer = FlvWriter::new(Vec::new());
+    writer.write_header().await?;
+    if let Some(video_header) = video_header {
+        writer.write_packet(video_header).await?;
+    }
+    writer.write_packet(keyframe).await?;
+    let input = writer.into_inner();
+
+    let codec = match config.format {
+        ThumbnailFormat::Jpeg => "mjpeg",
+        ThumbnailFormat::WebP => "libwebp",
+    };
+
+    let mut child = Command::new(&config.ffmpeg_path)
+        .args(["-loglevel", "error", "-f", "flv", "-i", "pipe:0", "-frames:v", "1"])
+        .args(["-vf", &format!("scale={}:-2", config.width.max(16))])
+        .args(["-c:v", codec, "-f", "image2pipe", "pipe:1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    // 输入很小，但仍需与读取输出并行写入，避免管道缓冲区写满时互相等待
+    let mut stdin = child.stdin.take()
+        .ok_or_else(|| StreamError::Internal("ffmpeg stdin unavailable".to_string()))?;
+    let writer = tokio::spawn(async move {
+        let _ = stdin.write_all(&input).await;
+    });
+
+    let output = tokio::time::timeout(DECODE_TIMEOUT, child.wait_with_output()).await
+        .map_err(|_| StreamError::Internal("ffmpeg timed out".to_string()))??;
+    let _ = writer.await;
+
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(StreamError::Internal(format!(
+            "ffmpeg exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim(),
+        )));
+    }
+
+    Ok(Bytes::from(output.stdout))
+}
@@ -0,0 +1,135 @@
+use std::path::{Component, Path, PathBuf};
+use serde::Serialize;
+use tokio::fs;
+use tracing::{info, debug, warn};
+
+use game_stream_common::{RecordingConfig, StreamResult, StreamError};
+use crate::recording::{RecordingMetadata, metadata_path};
+
+/// 点播管理器 - 列出、查询和删除录制目录中已完成的录制
+///
+/// 只有写入了元数据文件的录制才会作为点播内容对外提供
+pub struct VodManager {
+    dir: PathBuf,
+}
+
+/// 点播条目
+#[derive(Debug, Clone, Serialize)]
+pub struct VodEntry {
+    #[serde(flatten)]
+    pub metadata: RecordingMetadata,
+    pub url: String, // 播放地址，支持 Range 请求
+}
+
+impl VodManager {
+    pub fn new(config: &RecordingConfig) -> Self {
+        info!("Initializing VOD manager...");
+
+        Self {
+            dir: PathBuf::from(&config.dir),
+        }
+    }
+
+    /// 列出已完成的录制 (按开始时间倒序)，可按流密钥过滤
+    pub async fn list(&self, stream_key: Option<&str>) -> StreamResult<Vec<VodEntry>> {
+        let mut entries = Vec::new();
+        let mut pending = vec![self.dir.clone()];
+
+        while let Some(dir) = pending.pop() {
+            let mut read_dir = match fs::read_dir(&dir).await {
+                Ok(read_dir) => read_dir,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            while let Some(entry) = read_dir.next_entry().await? {
+                let path = entry.path();
+                if entry.file_type().await?.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+                if path.extension().is_none_or(|ext| ext != "json") {
+                    continue;
+                }
+
+                match read_metadata(&path).await {
+                    Ok(metadata) => {
+                        if stream_key.is_some_and(|stream_key| stream_key != metadata.stream_key) {
+                            continue;
+                        }
+                        // 录制文件已被外部删除的元数据不再列出
+                        if !fs::try_exists(self.dir.join(&metadata.file)).await? {
+                            debug!("Skipping VOD metadata without recording: {}", path.display());
+                            continue;
+                        }
+                        entries.push(vod_entry(metadata));
+                    }
+                    Err(e) => warn!("Ignoring malformed recording metadata {}: {}", path.display(), e),
+                }
+            }
+        }
+
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.metadata.started_at));
+        Ok(entries)
+    }
+
+    /// 获取单个录制的元数据
+    pub async fn get(&self, file: &str) -> StreamResult<VodEntry> {
+        let path = self.recording_path(file)?;
+        let metadata = read_metadata(&metadata_path(&path)).await
+            .map_err(|_| StreamError::StreamNotFound(file.to_string()))?;
+
+        Ok(vod_entry(metadata))
+    }
+
+    /// 删除录制文件及其元数据
+    pub async fn delete(&self, file: &str) -> StreamResult<VodEntry> {
+        let entry = self.get(file).await?;
+        let path = self.recording_path(file)?;
+
+        match fs::remove_file(&path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        fs::remove_file(metadata_path(&path)).await?;
+
+        info!("Deleted recording {}", file);
+        Ok(entry)
+    }
+
+    /// 获取可供播放的录制文件路径 (必须是已完成的录制)
+    pub async fn playback_path(&self, file: &str) -> StreamResult<PathBuf> {
+        let path = self.recording_path(file)?;
+        if !fs::try_exists(metadata_path(&path)).await? || !fs::try_exists(&path).await? {
+            return Err(StreamError::StreamNotFound(file.to_string()));
+        }
+
+        Ok(path)
+    }
+
+    /// 将相对路径映射到录制目录中，拒绝跳出录制目录的路径
+    fn recording_path(&self, file: &str) -> StreamResult<PathBuf> {
+        let relative = Path::new(file);
+        let is_safe = !file.is_empty()
+            && relative.components().all(|component| matches!(component, Component::Normal(_)));
+
+        if !is_safe {
+            return Err(StreamError::StreamNotFound(file.to_string()));
+        }
+
+        Ok(self.dir.join(relative))
+    }
+}
+
+fn vod_entry(metadata: RecordingMetadata) -> VodEntry {
+    VodEntry {
+        url: format!("/vod/{}", metadata.file),
+        metadata,
+    }
+}
+
+async fn read_metadata(path: &Path) -> StreamResult<RecordingMetadata> {
+    let content = fs::read(path).await?;
+    Ok(serde_json::from_slice(&content)?)
+}
@@ -0,0 +1,268 @@
+use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "webhooks")]
+use std::time::Duration;
+use tokio::sync::{RwLock, broadcast};
+use tracing::{info, debug, warn};
+#[cfg(feature = "webhooks")]
+use tracing::error;
+use serde::Serialize;
+#[cfg(feature = "webhooks")]
+use hmac::{Hmac, Mac};
+#[cfg(feature = "webhooks")]
+use sha2::Sha256;
+
+use game_stream_common::{
+    WebhookConfig, StreamEvent, StreamManager, StreamStatus,
+};
+#[cfg(feature = "webhooks")]
+use game_stream_common::WebhookEndpointConfig;
+
+#[cfg(feature = "webhooks")]
+type HmacSha256 = Hmac<Sha256>;
+
+/// Webhook 事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventKind {
+    StreamStarted,
+    StreamStopped,
+    FirstViewer,
+    ViewerMilestone,
+    RecordingFinished,
+    RecordingUploaded,
+    RecordingUploadFailed,
+}
+
+impl WebhookEventKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEventKind::StreamStarted => "stream_started",
+            WebhookEventKind::StreamStopped => "stream_stopped",
+            WebhookEventKind::FirstViewer => "first_viewer",
+            WebhookEventKind::ViewerMilestone => "viewer_milestone",
+            WebhookEventKind::RecordingFinished => "recording_finished",
+            WebhookEventKind::RecordingUploaded => "recording_uploaded",
+            WebhookEventKind::RecordingUploadFailed => "recording_upload_failed",
+        }
+    }
+}
+
+/// Webhook 请求负载
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookPayload {
+    pub event: WebhookEventKind,
+    pub stream_key: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub data: serde_json::Value,
+}
+
+/// Webhook 管理器 - 将流生命周期事件推送到外部 HTTP 端点
+pub struct WebhookManager {
+    config: WebhookConfig,
+    #[cfg(feature = "webhooks")]
+    client: reqwest::Client,
+    stream_states: RwLock<HashMap<String, StreamWebhookState>>,
+}
+
+/// 每个流的通知状态，避免重复触发
+#[derive(Default)]
+struct StreamWebhookState {
+    first_viewer_sent: bool,
+    milestones_sent: HashSet<u32>,
+}
+
+impl WebhookManager {
+    pub fn new(config: &WebhookConfig) -> Self {
+        info!("Initializing webhook manager...");
+
+        Self {
+            config: config.clone(),
+            #[cfg(feature = "webhooks")]
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(config.timeout))
+                .build()
+                .unwrap_or_default(),
+            stream_states: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 订阅事件总线并分发 Webhook
+    pub async fn start(self: Arc<Self>, stream_manager: Arc<StreamManager>) {
+        if !self.config.enabled || self.config.endpoints.is_empty() {
+            info!("Webhooks disabled");
+            return;
+        }
+        if !cfg!(feature = "webhooks") {
+            warn!("Webhooks are configured but the server was built without the webhooks feature, no webhooks will be sent");
+            return;
+        }
+
+        let mut events = stream_manager.subscribe_events();
+
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    for payload in self.payloads_for_event(event).await {
+                        self.dispatch(payload);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Webhook manager lagged behind event bus, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+
+    /// 将流事件转换为 Webhook 负载
+    async fn payloads_for_event(&self, event: StreamEvent) -> Vec<WebhookPayload> {
+        let mut payloads = Vec::new();
+
+        match event {
+            StreamEvent::StatusChanged { stream_key, status } => {
+                let kind = match status {
+                    StreamStatus::Live => WebhookEventKind::StreamStarted,
+                    StreamStatus::Stopped | StreamStatus::Error(_) => WebhookEventKind::StreamStopped,
+                    _ => return payloads,
+                };
+
+                // 新一轮直播重新计算首位观众和里程碑
+                if kind == WebhookEventKind::StreamStarted {
+                    self.stream_states.write().await.remove(&stream_key);
+                }
+
+                payloads.push(Self::payload(kind, stream_key, serde_json::json!({ "status": status })));
+            }
+            StreamEvent::StreamRemoved { stream_key } => {
+                self.stream_states.write().await.remove(&stream_key);
+            }
+            StreamEvent::ViewerJoined { stream_key, viewer_count, .. } => {
+                let mut states = self.stream_states.write().await;
+                let state = states.entry(stream_key.clone()).or_default();
+
+                if !state.first_viewer_sent {
+                    state.first_viewer_sent = true;
+                    payloads.push(Self::payload(
+                        WebhookEventKind::FirstViewer,
+                        stream_key.clone(),
+                        serde_json::json!({ "viewer_count": viewer_count }),
+                    ));
+                }
+
+                for milestone in &self.config.viewer_milestones {
+                    if viewer_count >= *milestone && state.milestones_sent.insert(*milestone) {
+                        payloads.push(Self::payload(
+                            WebhookEventKind::ViewerMilestone,
+                            stream_key.clone(),
+                            serde_json::json!({ "milestone": milestone, "viewer_count": viewer_count }),
+                        ));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        payloads
+    }
+
+    pub fn payload(event: WebhookEventKind, stream_key: String, data: serde_json::Value) -> WebhookPayload {
+        WebhookPayload {
+            event,
+            stream_key,
+            timestamp: chrono::Utc::now(),
+            data,
+        }
+    }
+
+    /// 异步发送 Webhook 到所有订阅该事件的端点
+    #[cfg(feature = "webhooks")]
+    pub fn dispatch(&self, payload: WebhookPayload) {
+        let body = match serde_json::to_vec(&payload) {
+            Ok(body) => body,
+            Err(e) => {
+                error!("Failed to serialize webhook payload: {}", e);
+                return;
+            }
+        };
+
+        for endpoint in &self.config.endpoints {
+            if !endpoint.events.is_empty() && !endpoint.events.iter().any(|e| e == payload.event.as_str()) {
+                continue;
+            }
+
+            let client = self.client.clone();
+            let endpoint = endpoint.clone();
+            let body = body.clone();
+            let event = payload.event;
+            let max_retries = self.config.max_retries;
+            let retry_interval = self.config.retry_interval;
+
+            tokio::spawn(async move {
+                Self::deliver(client, endpoint, event, body, max_retries, retry_interval).await;
+            });
+        }
+    }
+
+    /// 未启用 webhooks 特性时不发送 Webhook
+    #[cfg(not(feature = "webhooks"))]
+    pub fn dispatch(&self, payload: WebhookPayload) {
+        debug!("Webhook {} not sent, the server was built without the webhooks feature", payload.event.as_str());
+    }
+
+    /// 发送单个 Webhook，失败时按指数退避重试
+    #[cfg(feature = "webhooks")]
+    async fn deliver(
+        client: reqwest::Client,
+        endpoint: WebhookEndpointConfig,
+        event: WebhookEventKind,
+        body: Vec<u8>,
+        max_retries: u32,
+        retry_interval: u64,
+    ) {
+        let signature = endpoint.secret.as_ref().map(|secret| sign_payload(secret, &body));
+        let mut delay = Duration::from_millis(retry_interval);
+
+        for attempt in 0..=max_retries {
+            let mut request = client.post(&endpoint.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header("X-Webhook-Event", event.as_str())
+                .body(body.clone());
+
+            if let Some(signature) = &signature {
+                request = request.header("X-Signature-256", format!("sha256={}", signature));
+            }
+
+            match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    debug!("Webhook {} delivered to {}", event.as_str(), endpoint.url);
+                    return;
+                }
+                Ok(response) => {
+                    warn!("Webhook {} to {} returned {} (attempt {}/{})",
+                          event.as_str(), endpoint.url, response.status(), attempt + 1, max_retries + 1);
+                }
+                Err(e) => {
+                    warn!("Webhook {} to {} failed: {} (attempt {}/{})",
+                          event.as_str(), endpoint.url, e, attempt + 1, max_retries + 1);
+                }
+            }
+
+            if attempt < max_retries {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+        }
+
+        error!("Giving up on webhook {} to {}", event.as_str(), endpoint.url);
+    }
+}
+
+/// 使用 HMAC-SHA256 对负载签名
+#[cfg(feature = "webhooks")]
+fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
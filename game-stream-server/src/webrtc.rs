@@ -5,32 +5,74 @@ use tokio::sync::{RwLock, mpsc};
 use tracing::{info, error, debug, warn};
 use uuid::Uuid;
 use serde_json;
+use webrtc::api::{APIBuilder, API};
+use webrtc::api::media_engine::MediaEngine;
+use webrtc::data_channel::RTCDataChannel;
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
+use webrtc::ice_transport::ice_candidate::RTCIceCandidateInit;
+use webrtc::ice_transport::ice_server::RTCIceServer;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
 
 use game_stream_common::{
     WebRtcServerConfig, StreamManager, WebRtcSignal, ViewerConnection, ViewProtocol,
-    StreamResult, StreamError
+    StreamResult, recv_media, recv_latest_media, LiveStream, InputEvent, RemoteInput, RemoteInputRelayConfig,
+    LatencyMode, Rendition, StreamError, GamepadMessage
 };
+use crate::overload::OverloadGuard;
+use crate::relay::RelayManager;
+
+/// 观看者发送远程输入的数据通道名称
+const REMOTE_INPUT_CHANNEL: &str = "input";
+
+/// 观看者发送手柄状态快照的数据通道名称 (无序、不重传)
+const GAMEPAD_CHANNEL: &str = "gamepad";
 
 /// WebRTC 服务器
 #[derive(Clone)]
 pub struct WebRtcServer {
     config: WebRtcServerConfig,
     stream_manager: Arc<StreamManager>,
-    peer_connections: Arc<RwLock<HashMap<Uuid, WebRtcPeerConnection>>>,
+    peer_connections: Arc<RwLock<HashMap<Uuid, Arc<WebRtcPeerConnection>>>>,
     signaling_handler: Arc<WebRtcSignalingHandler>,
 }
 
 impl WebRtcServer {
     pub async fn new(
         config: &WebRtcServerConfig,
+        latency_mode: LatencyMode,
         stream_manager: Arc<StreamManager>,
+        overload_guard: Arc<OverloadGuard>,
+        relay_manager: Arc<RelayManager>,
     ) -> Result<Self> {
         info!("Initializing WebRTC server...");
         
+        let mut media_engine = MediaEngine::default();
+        media_engine.register_default_codecs()?;
+        let api = APIBuilder::new().with_media_engine(media_engine).build();
+        let rtc_config = RTCConfiguration {
+            ice_servers: config.ice_servers.iter()
+                .map(|server| RTCIceServer {
+                    urls: server.urls.clone(),
+                    username: server.username.clone().unwrap_or_default(),
+                    credential: server.credential.clone().unwrap_or_default(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        
         let peer_connections = Arc::new(RwLock::new(HashMap::new()));
         let signaling_handler = Arc::new(WebRtcSignalingHandler::new(
             stream_manager.clone(),
             peer_connections.clone(),
+            overload_guard,
+            relay_manager,
+            config.remote_input.clone(),
+            latency_mode,
+            api,
+            rtc_config,
         ));
         
         Ok(Self {
@@ -64,7 +106,7 @@ impl WebRtcServer {
         self.signaling_handler.clone()
     }
     
-    async fn cleanup_connections(peer_connections: Arc<RwLock<HashMap<Uuid, WebRtcPeerConnection>>>) {
+    async fn cleanup_connections(peer_connections: Arc<RwLock<HashMap<Uuid, Arc<WebRtcPeerConnection>>>>) {
         loop {
             tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
             
@@ -78,38 +120,75 @@ impl WebRtcServer {
             }
             
             for id in to_remove {
-                connections.remove(&id);
+                if let Some(connection) = connections.remove(&id) {
+                    connection.close().await;
+                }
                 debug!("Cleaned up expired WebRTC connection: {}", id);
             }
         }
     }
 }
 
+/// 信令请求来源信息
+#[derive(Debug, Clone)]
+pub struct SignalPeer {
+    pub remote_addr: std::net::SocketAddr,
+    pub user_agent: Option<String>,
+    pub viewer_id: Option<Uuid>, // 同一个 WebSocket 上最近建立的连接，之后的 ICE 候选属于它
+}
+
 /// WebRTC 信令处理器
 pub struct WebRtcSignalingHandler {
     stream_manager: Arc<StreamManager>,
-    peer_connections: Arc<RwLock<HashMap<Uuid, WebRtcPeerConnection>>>,
+    peer_connections: Arc<RwLock<HashMap<Uuid, Arc<WebRtcPeerConnection>>>>,
+    overload_guard: Arc<OverloadGuard>,
+    relay_manager: Arc<RelayManager>,
+    remote_input: RemoteInputRelayConfig,
+    latency_mode: LatencyMode,
+    api: API,
+    rtc_config: RTCConfiguration, // 新建对等连接使用的 ICE 服务器
 }
 
 impl WebRtcSignalingHandler {
-    pub fn new(
+    #[allow(clippy::too_many_arguments)]
+    fn new(
         stream_manager: Arc<StreamManager>,
-        peer_connections: Arc<RwLock<HashMap<Uuid, WebRtcPeerConnection>>>,
+        peer_connections: Arc<RwLock<HashMap<Uuid, Arc<WebRtcPeerConnection>>>>,
+        overload_guard: Arc<OverloadGuard>,
+        relay_manager: Arc<RelayManager>,
+        remote_input: RemoteInputRelayConfig,
+        latency_mode: LatencyMode,
+        api: API,
+        rtc_config: RTCConfiguration,
     ) -> Self {
         Self {
             stream_manager,
             peer_connections,
+            overload_guard,
+            relay_manager,
+            remote_input,
+            latency_mode,
+            api,
+            rtc_config,
         }
     }
     
+    pub fn latency_mode(&self) -> LatencyMode {
+        self.latency_mode
+    }
+    
     /// 处理 WebRTC 信令消息
-    pub async fn handle_signal(&self, signal: WebRtcSignal) -> StreamResult<Option<WebRtcSignal>> {
+    pub async fn handle_signal(&self, signal: WebRtcSignal, peer: &SignalPeer) -> StreamResult<Option<WebRtcSignal>> {
         match signal {
-            WebRtcSignal::Offer { stream_key, sdp } => {
-                self.handle_offer(stream_key, sdp).await
+            // 私有流的观看权限 (access) 已在 HTTP 信令入口检查
+            WebRtcSignal::Offer { stream_key, sdp, rendition, .. } => {
+                self.handle_offer(stream_key, sdp, rendition, peer).await
+            }
+            WebRtcSignal::SelectRendition { viewer_id, rendition } => {
+                self.handle_select_rendition(viewer_id, rendition).await
             }
             WebRtcSignal::IceCandidate { candidate, sdp_mid, sdp_mline_index } => {
-                self.handle_ice_candidate(candidate, sdp_mid, sdp_mline_index).await
+                self.handle_ice_candidate(peer.viewer_id, candidate, sdp_mid, sdp_mline_index).await
             }
             _ => {
                 warn!("Unhandled WebRTC signal: {:?}", signal);
@@ -118,34 +197,89 @@ impl WebRtcSignalingHandler {
         }
     }
     
-    async fn handle_offer(&self, stream_key: String, sdp: String) -> StreamResult<Option<WebRtcSignal>> {
+    async fn handle_offer(
+        &self,
+        stream_key: String,
+        sdp: String,
+        rendition: Rendition,
+        peer: &SignalPeer,
+    ) -> StreamResult<Option<WebRtcSignal>> {
         info!("Handling WebRTC offer for stream: {}", stream_key);
         
         // 检查流是否存在
-        let stream = self.stream_manager.get_stream(&stream_key).await
-            .ok_or_else(|| StreamError::StreamNotFound(stream_key.clone()))?;
+        // 本地不存在时从源站拉流 (边缘模式)
+        let stream = self.relay_manager.ensure_stream(&stream_key).await?;
+        stream.check_rendition(rendition)?;
+        
+        // 过载保护：拒绝新的观看者
+        self.overload_guard.check_viewer().await?;
         
         // 创建 WebRTC 连接
         let connection_id = Uuid::new_v4();
+        let rtc_peer = self.api.new_peer_connection(self.rtc_config.clone()).await.map_err(webrtc_error)?;
         let peer_connection = WebRtcPeerConnection::new(
             connection_id,
             stream_key.clone(),
             self.stream_manager.clone(),
+            stream.clone(),
+            rtc_peer,
+            &self.remote_input,
         ).await?;
         
         // 处理 SDP Offer
-        let answer_sdp = peer_connection.handle_offer(sdp).await?;
+        let answer_sdp = match peer_connection.handle_offer(sdp).await {
+            Ok(answer_sdp) => answer_sdp,
+            Err(e) => {
+                peer_connection.close().await;
+                return Err(e);
+            }
+        };
         
         // 添加观看者
         let viewer = ViewerConnection {
             id: connection_id,
-            remote_addr: "0.0.0.0:0".parse().unwrap(), // 实际应该从请求中获取
+            remote_addr: peer.remote_addr,
             connected_at: chrono::Utc::now(),
             protocol: ViewProtocol::WebRtc,
             stream_key: stream_key.clone(),
+            user_agent: peer.user_agent.clone(),
+            rendition,
         };
         
-        let _media_receiver = stream.add_viewer(viewer).await;
+        let mut media_receiver = stream.add_viewer(viewer).await;
+        let mut rendition_filter = stream.rendition_filter(connection_id);
+        
+        // 转发媒体数据到对等连接，超低延迟模式下落后时跳到最新的关键帧
+        {
+            let stream = stream.clone();
+            let latest_only = self.latency_mode == LatencyMode::UltraLow;
+            tokio::spawn(async move {
+                loop {
+                    let packet = if latest_only {
+                        recv_latest_media(&mut media_receiver).await
+                    } else {
+                        recv_media(&mut media_receiver).await
+                    };
+                    let Some(packet) = packet else {
+                        break;
+                    };
+                    if !rendition_filter.accept(&packet) {
+                        continue;
+                    }
+                    // 实际实现中，这里需要将媒体包封装为 RTP 并通过 PeerConnection 发送
+                    // 推流端使用 Opus 编码时，每个音频包就是一个 20ms 的 Opus 帧，直接作为 RTP 负载 (PT 97)，无需转码
+                    // 元数据包 (如修改流信息时的 onCuePoint) 通过数据通道发送
+                    // 出口流量在实际写出 RTP 包的地方统计，目前没有发送，不计入
+                    
+                    if let Some(latency) = packet.latency_ms() {
+                        stream.latency.record_delivery(connection_id, ViewProtocol::WebRtc, latency).await;
+                    }
+                }
+                
+                stream.remove_viewer(connection_id).await;
+                debug!("WebRTC media forwarding finished for connection {}", connection_id);
+            });
+        }
         
         // 存储连接
         {
@@ -154,19 +288,40 @@ impl WebRtcSignalingHandler {
         }
         
         // 返回 Answer
-        Ok(Some(WebRtcSignal::Answer { sdp: answer_sdp }))
+        Ok(Some(WebRtcSignal::Answer { sdp: answer_sdp, viewer_id: connection_id }))
     }
     
+    /// 观看者在播放过程中切换画质
+    async fn handle_select_rendition(&self, viewer_id: Uuid, rendition: Rendition) -> StreamResult<Option<WebRtcSignal>> {
+        let connections = self.peer_connections.read().await;
+        let connection = connections.get(&viewer_id)
+            .ok_or_else(|| StreamError::WebRtc(format!("Unknown WebRTC connection {}", viewer_id)))?;
+        connection.stream.set_viewer_rendition(viewer_id, rendition).await?;
+        Ok(None)
+    }
+    
+    /// 观看者陆续发来的 ICE 候选，加到同一个 WebSocket 上最近建立的连接
     async fn handle_ice_candidate(
         &self,
+        viewer_id: Option<Uuid>,
         candidate: String,
-        _sdp_mid: Option<String>,
-        _sdp_mline_index: Option<u16>,
+        sdp_mid: Option<String>,
+        sdp_mline_index: Option<u16>,
     ) -> StreamResult<Option<WebRtcSignal>> {
         debug!("Handling ICE candidate: {}", candidate);
         
-        // 实际的 ICE 候选处理逻辑
-        // 这里需要将候选添加到对应的 PeerConnection
+        let Some(viewer_id) = viewer_id else {
+            debug!("Ignoring ICE candidate without a connection");
+            return Ok(None);
+        };
+        let connection = self.peer_connections.read().await.get(&viewer_id).cloned()
+            .ok_or_else(|| StreamError::WebRtc(format!("Unknown WebRTC connection {}", viewer_id)))?;
+        connection.peer.add_ice_candidate(RTCIceCandidateInit {
+            candidate,
+            sdp_mid,
+            sdp_mline_index,
+            username_fragment: None,
+        }).await.map_err(webrtc_error)?;
         
         Ok(None)
     }
@@ -179,6 +334,16 @@ struct WebRtcPeerConnection {
     stream_manager: Arc<StreamManager>,
     created_at: chrono::DateTime<chrono::Utc>,
     last_activity: Arc<RwLock<chrono::DateTime<chrono::Utc>>>,
+    stream: Arc<LiveStream>,
+    peer: RTCPeerConnection,
+    // 每秒最多转发的远程输入事件，未开启远程输入时为 None
+    input_rate_limit: Option<u32>,
+    // 当前一秒的开始时间和已转发的事件数
+    input_window: std::sync::Mutex<(std::time::Instant, u32)>,
+    // 手柄快照的最短转发间隔
+    gamepad_interval: std::time::Duration,
+    // 每个手柄最近转发的快照序列号和时间
+    gamepad_forwarded: std::sync::Mutex<HashMap<u8, (u32, std::time::Instant)>>,
 }
 
 impl WebRtcPeerConnection {
@@ -186,49 +351,165 @@ impl WebRtcPeerConnection {
         id: Uuid,
         stream_key: String,
         stream_manager: Arc<StreamManager>,
-    ) -> StreamResult<Self> {
+        stream: Arc<LiveStream>,
+        peer: RTCPeerConnection,
+        remote_input: &RemoteInputRelayConfig,
+    ) -> StreamResult<Arc<Self>> {
         let now = chrono::Utc::now();
         
-        Ok(Self {
+        Ok(Arc::new(Self {
             id,
             stream_key,
             stream_manager,
             created_at: now,
             last_activity: Arc::new(RwLock::new(now)),
-        })
+            stream,
+            peer,
+            input_rate_limit: remote_input.enabled.then_some(remote_input.max_events_per_second),
+            input_window: std::sync::Mutex::new((std::time::Instant::now(), 0)),
+            gamepad_interval: std::time::Duration::from_secs(1) / remote_input.gamepad_tick_rate.max(1),
+            gamepad_forwarded: std::sync::Mutex::new(HashMap::new()),
+        }))
     }
     
-    async fn handle_offer(&self, _offer_sdp: String) -> StreamResult<String> {
+    /// 设置观看者的 Offer 并生成 Answer，等 ICE 收集完成后返回，Answer 中带有全部服务器候选
+    async fn handle_offer(self: &Arc<Self>, offer_sdp: String) -> StreamResult<String> {
         info!("Processing SDP offer for connection {}", self.id);
         
-        // 实际的 SDP 处理逻辑
-        // 这里需要：
-        // 1. 解析 offer SDP
-        // 2. 创建 answer SDP
-        // 3. 设置媒体流
-        
         // 更新活动时间
         {
             let mut last_activity = self.last_activity.write().await;
             *last_activity = chrono::Utc::now();
         }
         
-        // 模拟生成 Answer SDP
-        let answer_sdp = format!(
-            "v=0\r\n\
-             o=- {} 2 IN IP4 127.0.0.1\r\n\
-             s=-\r\n\
-             t=0 0\r\n\
-             m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
-             a=rtpmap:96 H264/90000\r\n\
-             a=sendonly\r\n\
-             m=audio 9 UDP/TLS/RTP/SAVPF 97\r\n\
-             a=rtpmap:97 OPUS/48000/2\r\n\
-             a=sendonly\r\n",
-            chrono::Utc::now().timestamp()
-        );
-        
-        Ok(answer_sdp)
+        // 观看者创建的数据通道："input" 通道的输入事件和 "gamepad" 通道的手柄快照
+        let connection = Arc::downgrade(self);
+        self.peer.on_data_channel(Box::new(move |channel: Arc<RTCDataChannel>| {
+            if let Some(connection) = connection.upgrade() {
+                connection.attach_data_channel(channel);
+            }
+            Box::pin(async {})
+        }));
+        
+        let offer = RTCSessionDescription::offer(offer_sdp).map_err(webrtc_error)?;
+        self.peer.set_remote_description(offer).await.map_err(webrtc_error)?;
+        let answer = self.peer.create_answer(None).await.map_err(webrtc_error)?;
+        let mut gathering_complete = self.peer.gathering_complete_promise().await;
+        self.peer.set_local_description(answer).await.map_err(webrtc_error)?;
+        let _ = gathering_complete.recv().await;
+        
+        let answer = self.peer.local_description().await
+            .ok_or_else(|| StreamError::WebRtc(format!("No local description for connection {}", self.id)))?;
+        Ok(answer.sdp)
+    }
+    
+    /// 数据通道的消息交给 handle_data_channel_message，需要回复时在同一通道上发送
+    fn attach_data_channel(self: &Arc<Self>, channel: Arc<RTCDataChannel>) {
+        debug!("Data channel {:?} opened on connection {}", channel.label(), self.id);
+        let connection = Arc::downgrade(self);
+        let reply_channel = Arc::downgrade(&channel);
+        let label = channel.label().to_string();
+        channel.on_message(Box::new(move |message: DataChannelMessage| {
+            let connection = connection.clone();
+            let reply_channel = reply_channel.clone();
+            let label = label.clone();
+            Box::pin(async move {
+                let Some(connection) = connection.upgrade() else {
+                    return;
+                };
+                let Some(reply) = connection.handle_data_channel_message(&label, &message.data).await else {
+                    return;
+                };
+                if let Some(channel) = reply_channel.upgrade() {
+                    if let Err(e) = channel.send_text(reply).await {
+                        debug!("Failed to reply on data channel {:?} of connection {}: {}", label, connection.id, e);
+                    }
+                }
+            })
+        }));
+    }
+    
+    /// 处理观看者数据通道的消息，"input" 通道的输入事件和 "gamepad" 通道的手柄快照限速后转发给推流端，
+    /// 返回需要在同一通道上回复的消息
+    async fn handle_data_channel_message(&self, label: &str, data: &[u8]) -> Option<String> {
+        if label == GAMEPAD_CHANNEL {
+            return self.handle_gamepad_message(data).await;
+        }
+        if label != REMOTE_INPUT_CHANNEL {
+            debug!("Ignoring message on data channel {:?} of connection {}", label, self.id);
+            return None;
+        }
+        let Some(limit) = self.input_rate_limit else {
+            debug!("Remote input is disabled, dropping event from connection {}", self.id);
+            return None;
+        };
+        let event: InputEvent = match serde_json::from_slice(data) {
+            Ok(event) => event,
+            Err(e) => {
+                debug!("Invalid remote input from connection {}: {}", self.id, e);
+                return None;
+            }
+        };
+        
+        {
+            let mut window = self.input_window.lock().unwrap();
+            if window.0.elapsed() >= std::time::Duration::from_secs(1) {
+                *window = (std::time::Instant::now(), 0);
+            }
+            if window.1 >= limit {
+                return None;
+            }
+            window.1 += 1;
+        }
+        
+        *self.last_activity.write().await = chrono::Utc::now();
+        self.stream.send_remote_input(RemoteInput { viewer_id: self.id, event });
+        None
+    }
+    
+    /// 手柄快照：回复时钟同步请求；状态按 gamepad_tick_rate 限速，乱序到达的旧快照丢弃
+    async fn handle_gamepad_message(&self, data: &[u8]) -> Option<String> {
+        self.input_rate_limit?;
+        let message: GamepadMessage = match serde_json::from_slice(data) {
+            Ok(message) => message,
+            Err(e) => {
+                debug!("Invalid gamepad message from connection {}: {}", self.id, e);
+                return None;
+            }
+        };
+        
+        let state = match message {
+            GamepadMessage::ClockSync { client_time, .. } => {
+                let reply = GamepadMessage::ClockSync {
+                    client_time,
+                    server_time: Some(chrono::Utc::now().timestamp_millis()),
+                };
+                return serde_json::to_string(&reply).ok();
+            }
+            GamepadMessage::State(state) => state,
+        };
+        
+        {
+            let mut forwarded = self.gamepad_forwarded.lock().unwrap();
+            let now = std::time::Instant::now();
+            if let Some((seq, at)) = forwarded.get(&state.pad) {
+                // 序列号回绕时按差值判断新旧
+                if state.seq.wrapping_sub(*seq) as i32 <= 0 || now.duration_since(*at) < self.gamepad_interval {
+                    return None;
+                }
+            }
+            forwarded.insert(state.pad, (state.seq, now));
+        }
+        
+        *self.last_activity.write().await = chrono::Utc::now();
+        self.stream.send_remote_input(RemoteInput { viewer_id: self.id, event: InputEvent::Gamepad(state) });
+        None
+    }
+    
+    async fn close(&self) {
+        if let Err(e) = self.peer.close().await {
+            debug!("Failed to close WebRTC connection {}: {}", self.id, e);
+        }
     }
     
     async fn is_expired(&self) -> bool {
@@ -240,3 +521,7 @@ impl WebRtcPeerConnection {
         duration.num_minutes() > 5
     }
 }
+
+fn webrtc_error(error: webrtc::Error) -> StreamError {
+    StreamError::WebRtc(error.to_string())
+}
@@ -0,0 +1,144 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use game_stream_common::{AnalyticsConfig, AudioCodec, AudioConfig, StreamInfo, StreamManager, VideoCodec, VideoConfig, ViewProtocol};
+use game_stream_server::analytics::{AnalyticsManager, HLS_SESSION_TIMEOUT, MAX_HLS_SESSIONS_PER_ADDR};
+#[cfg(feature = "hls")]
+use {
+    axum::body::Body,
+    axum::extract::connect_info::MockConnectInfo,
+    axum::http::{header, Request, StatusCode},
+    axum::Router,
+    game_stream_common::{OverloadConfig, ServerConfig},
+    game_stream_server::StreamingServer,
+    tower::ServiceExt,
+};
+
+fn stream_info(stream_key: &str) -> StreamInfo {
+    StreamInfo {
+        stream_id: uuid::Uuid::new_v4(),
+        stream_key: stream_key.to_string(),
+        title: None,
+        description: None,
+        created_at: chrono::Utc::now(),
+        is_live: true,
+        viewer_count: 0,
+        video_config: VideoConfig { width: 1280, height: 720, fps: 30, bitrate: 2500, codec: VideoCodec::H264 },
+        audio_config: AudioConfig { sample_rate: 44100, channels: 2, bitrate: 128, codec: AudioCodec::Aac },
+        thumbnail_url: None,
+        tags: Default::default(),
+    }
+}
+
+/// HLS 会话按客户端地址和 User-Agent 开始和延续，计入流的观看人数，闲置超时后以最后一次请求的时间结束
+#[tokio::test]
+async fn hls_sessions_expire_after_idle_timeout() {
+    let stream_manager = Arc::new(StreamManager::new());
+    let stream = stream_manager.create_stream("game".to_string(), stream_info("game")).await.unwrap();
+    let analytics = AnalyticsManager::new(&AnalyticsConfig::default(), stream_manager);
+    let addr = SocketAddr::from(([192, 0, 2, 1], 50000));
+
+    analytics.touch_hls_session("game", addr, Some("hls.js".to_string())).await;
+    analytics.touch_hls_session("game", addr, None).await;
+    analytics.touch_hls_session("game", SocketAddr::from(([192, 0, 2, 1], 50001)), Some("hls.js".to_string())).await;
+    analytics.touch_hls_session("other", addr, None).await;
+
+    let summary = analytics.get_summary("game").await.unwrap();
+    assert_eq!(summary.active_viewers, 2, "repeated requests continue the same session");
+    assert_eq!(summary.peak_concurrent_viewers, 2);
+    assert_eq!(summary.total_sessions, 0);
+    assert_eq!(stream.get_viewer_count().await, 2);
+    assert_eq!(stream.get_info().await.viewer_count, 2);
+
+    // 超时之前不结束
+    analytics.expire_hls_sessions(chrono::Utc::now()).await;
+    assert_eq!(analytics.get_summary("game").await.unwrap().active_viewers, 2);
+
+    let timeout = chrono::Duration::from_std(HLS_SESSION_TIMEOUT).unwrap();
+    analytics.expire_hls_sessions(chrono::Utc::now() + timeout).await;
+    let summary = analytics.get_summary("game").await.unwrap();
+    assert_eq!(summary.active_viewers, 0);
+    assert_eq!(summary.total_sessions, 2);
+    assert_eq!(stream.get_viewer_count().await, 0);
+
+    let sessions = analytics.get_sessions("game").await;
+    assert!(sessions.iter().all(|session| session.protocol == ViewProtocol::Hls && session.remote_addr.ip() == addr.ip()));
+    assert!(sessions.iter().any(|session| session.user_agent.as_deref() == Some("hls.js")));
+    assert!(sessions.iter().all(|session| session.left_at < chrono::Utc::now() && session.watched_seconds < 5));
+    assert_eq!(analytics.get_sessions("other").await.len(), 1);
+}
+
+/// 同一地址更换 User-Agent 最多计入 MAX_HLS_SESSIONS_PER_ADDR 个观看者，不影响其他地址
+#[tokio::test]
+async fn hls_sessions_are_capped_per_address() {
+    let analytics = AnalyticsManager::new(&AnalyticsConfig::default(), Arc::new(StreamManager::new()));
+    let addr = SocketAddr::from(([192, 0, 2, 1], 50000));
+
+    for index in 0..100 {
+        analytics.touch_hls_session("game", addr, Some(format!("player/{}", index))).await;
+    }
+    analytics.touch_hls_session("game", SocketAddr::from(([192, 0, 2, 2], 50000)), None).await;
+
+    let summary = analytics.get_summary("game").await.unwrap();
+    assert_eq!(summary.active_viewers as usize, MAX_HLS_SESSIONS_PER_ADDR + 1);
+    assert_eq!(summary.peak_concurrent_viewers as usize, MAX_HLS_SESSIONS_PER_ADDR + 1);
+}
+
+/// 关闭观看分析时 HLS 会话仍计入观看人数，但不记录历史
+#[tokio::test]
+async fn hls_viewers_counted_without_analytics() {
+    let stream_manager = Arc::new(StreamManager::new());
+    let stream = stream_manager.create_stream("game".to_string(), stream_info("game")).await.unwrap();
+    let analytics = AnalyticsManager::new(&AnalyticsConfig { enabled: false, ..AnalyticsConfig::default() }, stream_manager);
+    let addr = SocketAddr::from(([192, 0, 2, 1], 50000));
+
+    analytics.touch_hls_session("game", addr, None).await;
+    assert_eq!(stream.get_viewer_count().await, 1);
+    assert!(analytics.get_summary("game").await.is_none());
+
+    let timeout = chrono::Duration::from_std(HLS_SESSION_TIMEOUT).unwrap();
+    analytics.expire_hls_sessions(chrono::Utc::now() + timeout).await;
+    assert_eq!(stream.get_viewer_count().await, 0);
+    assert!(analytics.get_sessions("game").await.is_empty());
+}
+
+#[cfg(feature = "hls")]
+async fn hls_request(router: &Router, stream_key: &str, user_agent: &str) -> axum::response::Response {
+    let request = Request::get(format!("/hls/{}/playlist.m3u8", stream_key))
+        .header(header::USER_AGENT, user_agent)
+        .body(Body::empty())
+        .unwrap();
+    router.clone().oneshot(request).await.unwrap()
+}
+
+/// 观看者数量达到上限后新的 HLS 会话返回 503 和 Retry-After，已有会话继续请求播放列表
+#[cfg(feature = "hls")]
+#[tokio::test]
+async fn overload_rejects_new_hls_sessions() {
+    let hls_dir = std::env::temp_dir().join(format!("game-stream-analytics-test-{}", std::process::id()));
+    std::fs::create_dir_all(&hls_dir).unwrap();
+    std::fs::write(
+        hls_dir.join("game.m3u8"),
+        "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:2\n#EXT-X-MEDIA-SEQUENCE:0\n#EXTINF:2.0,\nsegment_0.ts\n",
+    ).unwrap();
+    let mut config = ServerConfig {
+        overload: OverloadConfig { max_viewers: 1, retry_after: 7, ..OverloadConfig::default() },
+        ..ServerConfig::default()
+    };
+    config.storage.hls_segment_dir = hls_dir.to_string_lossy().into_owned();
+    let stream_manager = Arc::new(StreamManager::new());
+    let stream = stream_manager.create_stream("game".to_string(), stream_info("game")).await.unwrap();
+    let server = StreamingServer::builder(config).stream_manager(stream_manager).build().await.unwrap();
+    let router = server.router().await.layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+
+    assert_eq!(hls_request(&router, "game", "first").await.status(), StatusCode::OK);
+    assert_eq!(stream.get_viewer_count().await, 1);
+
+    let response = hls_request(&router, "game", "second").await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers()[header::RETRY_AFTER], "7");
+    assert_eq!(stream.get_viewer_count().await, 1);
+
+    assert_eq!(hls_request(&router, "game", "first").await.status(), StatusCode::OK);
+    std::fs::remove_dir_all(&hls_dir).ok();
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::extract::connect_info::MockConnectInfo;
+use axum::http::{header, Method, Request, StatusCode};
+use axum::Router;
+use game_stream_common::{AuthConfig, CompositeConfig, ServerConfig, StreamAccessConfig, StreamError, StreamManager, StreamVisibility};
+use game_stream_server::auth::StreamAccessUpdate;
+use game_stream_server::composite::{CompositeManager, CompositeRequest};
+use game_stream_server::{AuthManager, StreamingServer};
+use tower::ServiceExt;
+
+const ADMIN_TOKEN: &str = "admin-secret";
+
+fn auth_config(admin_token: Option<&str>) -> AuthConfig {
+    AuthConfig {
+        enabled: false,
+        valid_stream_keys: Vec::new(),
+        jwt_secret: None,
+        admin_token: admin_token.map(str::to_string),
+        streams: HashMap::from([("secret".to_string(), StreamAccessConfig {
+            visibility: StreamVisibility::Private,
+            password: Some("hunter2".to_string()),
+        })]),
+    }
+}
+
+/// 未配置 admin_token 时管理接口总是拒绝，配置后只接受相同的令牌
+#[test]
+fn admin_token_required() {
+    let disabled = AuthManager::new(&auth_config(None));
+    assert!(!disabled.validate_admin(None));
+    assert!(!disabled.validate_admin(Some("")));
+
+    let auth = AuthManager::new(&auth_config(Some(ADMIN_TOKEN)));
+    assert!(auth.validate_admin(Some(ADMIN_TOKEN)));
+    assert!(!auth.validate_admin(Some("admin-secre")));
+    assert!(!auth.validate_admin(None));
+}
+
+/// 私有流需要正确的密码或邀请，修改密码后旧密码 (包括已验证过的) 失效
+#[tokio::test]
+async fn private_stream_password_and_invites() {
+    let auth = AuthManager::new(&auth_config(None));
+    assert!(auth.validate_viewer("public", None).await);
+    assert!(!auth.validate_viewer("secret", None).await);
+    assert!(!auth.validate_viewer("secret", Some("wrong")).await);
+    assert!(auth.validate_viewer("secret", Some("hunter2")).await);
+    assert!(auth.validate_viewer("secret", Some("hunter2")).await, "verified password stays valid");
+
+    auth.update_access("secret", StreamAccessUpdate { visibility: None, password: Some("correct horse".to_string()) }).await;
+    assert!(!auth.validate_viewer("secret", Some("hunter2")).await);
+    assert!(auth.validate_viewer("secret", Some("correct horse")).await);
+
+    let invite = auth.create_invite("secret", Some(60)).await;
+    assert!(auth.validate_viewer("secret", Some(&invite.token)).await);
+    auth.revoke_invite("secret", &invite.token).await.unwrap();
+    assert!(!auth.validate_viewer("secret", Some(&invite.token)).await);
+}
+
+/// 重复的错误密码不再计算慢哈希；失败次数达到上限后新的密码直接拒绝，已验证的密码和邀请不受影响
+#[tokio::test]
+async fn password_failures_are_limited() {
+    let auth = AuthManager::new(&auth_config(None));
+    for _ in 0..20 {
+        assert!(!auth.validate_viewer("secret", Some("wrong")).await);
+    }
+    assert!(auth.validate_viewer("secret", Some("hunter2")).await, "a repeated wrong password counts once");
+
+    for index in 0..10 {
+        assert!(!auth.validate_viewer("secret", Some(&format!("guess-{}", index))).await);
+    }
+    assert!(auth.validate_viewer("secret", Some("hunter2")).await, "verified passwords skip the limit");
+    let invite = auth.create_invite("secret", Some(60)).await;
+    assert!(auth.validate_viewer("secret", Some(&invite.token)).await);
+
+    auth.update_access("locked", StreamAccessUpdate { visibility: Some(StreamVisibility::Private), password: Some("letmein".to_string()) }).await;
+    for index in 0..10 {
+        assert!(!auth.validate_viewer("locked", Some(&format!("guess-{}", index))).await);
+    }
+    assert!(!auth.validate_viewer("locked", Some("letmein")).await, "new passwords are rejected after too many failures");
+
+    // 修改密码后重新计数
+    auth.update_access("locked", StreamAccessUpdate { visibility: None, password: Some("letmein".to_string()) }).await;
+    assert!(auth.validate_viewer("locked", Some("letmein")).await);
+}
+
+/// 配置了 admin_token 的完整 HTTP 路由
+async fn admin_router() -> Router {
+    let mut config = ServerConfig { auth: auth_config(Some(ADMIN_TOKEN)), ..Default::default() };
+    config.storage.hls_segment_dir = std::env::temp_dir()
+        .join(format!("game-stream-auth-test-{}", std::process::id()))
+        .to_string_lossy()
+        .into_owned();
+    let server = StreamingServer::builder(config).build().await.unwrap();
+    server.router().await.layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))))
+}
+
+async fn request_status(router: &Router, method: Method, uri: &str, token: Option<&str>) -> StatusCode {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(token) = token {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    router.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap().status()
+}
+
+/// 管理接口没有令牌或令牌错误时返回 401，令牌正确时才进入处理函数
+#[tokio::test]
+async fn admin_routes_require_token() {
+    let router = admin_router().await;
+    let routes = [
+        (Method::PATCH, "/api/streams/demo"),
+        (Method::GET, "/api/streams/demo/sessions?format=csv"),
+        (Method::GET, "/api/streams/demo/viewers"),
+        (Method::POST, "/api/streams/demo/recording/start"),
+        (Method::POST, "/api/streams/demo/recording/stop"),
+        (Method::DELETE, "/api/vod/demo/recording.flv"),
+        (Method::POST, "/api/composites"),
+        (Method::DELETE, "/api/composites/collab"),
+    ];
+    for (method, uri) in routes {
+        assert_eq!(request_status(&router, method.clone(), uri, None).await, StatusCode::UNAUTHORIZED, "{} {}", method, uri);
+        assert_eq!(request_status(&router, method.clone(), uri, Some("wrong")).await, StatusCode::UNAUTHORIZED, "{} {}", method, uri);
+        assert_ne!(request_status(&router, method.clone(), uri, Some(ADMIN_TOKEN)).await, StatusCode::UNAUTHORIZED, "{} {}", method, uri);
+    }
+
+    // 同一路径上的查询接口不需要令牌
+    assert_eq!(request_status(&router, Method::GET, "/api/vod/demo/recording.flv", None).await, StatusCode::NOT_FOUND);
+}
+
+/// 私有流的统计、观看分析、转推和录制状态也需要观看密码或邀请
+#[tokio::test]
+async fn private_stream_status_requires_access() {
+    let router = admin_router().await;
+    for path in ["stats", "analytics", "restream", "recording"] {
+        let uri = format!("/api/streams/secret/{}", path);
+        assert_eq!(request_status(&router, Method::GET, &uri, None).await, StatusCode::UNAUTHORIZED, "{}", uri);
+        assert_eq!(request_status(&router, Method::GET, &format!("{}?access=wrong", uri), None).await, StatusCode::UNAUTHORIZED, "{}", uri);
+        assert_eq!(request_status(&router, Method::GET, &format!("{}?access=hunter2", uri), None).await, StatusCode::NOT_FOUND, "{}", uri);
+        assert_eq!(request_status(&router, Method::GET, &format!("/api/streams/public/{}", path), None).await, StatusCode::NOT_FOUND, "{}", uri);
+    }
+}
+
+/// 私有流只有提供观看密码时才能作为合成的输入
+#[tokio::test]
+async fn composite_rejects_private_inputs_without_access() {
+    let auth = Arc::new(AuthManager::new(&auth_config(None)));
+    let composites = CompositeManager::new(&CompositeConfig::default(), Arc::new(StreamManager::new()), auth);
+    let request = |access: &[(&str, &str)]| CompositeRequest {
+        output_key: "collab".to_string(),
+        inputs: vec!["secret".to_string(), "public".to_string()],
+        layout: Default::default(),
+        title: None,
+        access: access.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect(),
+    };
+
+    assert!(matches!(composites.create(request(&[])).await, Err(StreamError::Auth(key)) if key == "secret"));
+    assert!(matches!(composites.create(request(&[("secret", "wrong")])).await, Err(StreamError::Auth(_))));
+    // 密码正确时通过权限检查，因为输入流不在直播而失败
+    assert!(matches!(composites.create(request(&[("secret", "hunter2")])).await, Err(StreamError::StreamNotFound(_))));
+}
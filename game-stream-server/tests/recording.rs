@@ -0,0 +1,77 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::routing::post;
+use axum::Router;
+use bytes::Bytes;
+use game_stream_common::{
+    AudioCodec, AudioConfig, MediaPacket, RecordingConfig, RecordingFormat, StreamInfo, StreamManager, StreamStatus, VideoCodec,
+    VideoConfig, WebhookConfig, WebhookEndpointConfig,
+};
+use game_stream_server::recording::RecordingManager;
+use game_stream_server::webhook::WebhookManager;
+use tokio::sync::mpsc;
+
+/// 在本机端口接收 Webhook，返回端点地址和收到的请求体
+async fn webhook_receiver() -> (String, mpsc::UnboundedReceiver<serde_json::Value>) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let router = Router::new().route("/hook", post(move |body: axum::Json<serde_json::Value>| async move {
+        let _ = sender.send(body.0);
+    }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    (format!("http://{}/hook", addr), receiver)
+}
+
+fn stream_info(stream_key: &str) -> StreamInfo {
+    StreamInfo {
+        stream_id: uuid::Uuid::new_v4(),
+        stream_key: stream_key.to_string(),
+        title: None,
+        description: None,
+        created_at: chrono::Utc::now(),
+        is_live: false,
+        viewer_count: 0,
+        video_config: VideoConfig { width: 1280, height: 720, fps: 30, bitrate: 2500, codec: VideoCodec::H264 },
+        audio_config: AudioConfig { sample_rate: 44100, channels: 2, bitrate: 128, codec: AudioCodec::Aac },
+        thumbnail_url: None,
+        tags: Default::default(),
+    }
+}
+
+/// 未配置上传时，录制完成并写入元数据后也会发送 recording_finished Webhook
+#[tokio::test]
+async fn recording_finished_webhook_without_upload() {
+    let (url, mut webhooks) = webhook_receiver().await;
+    let webhook_config = WebhookConfig {
+        enabled: true,
+        endpoints: vec![WebhookEndpointConfig { url, secret: None, events: vec!["recording_finished".to_string()] }],
+        ..WebhookConfig::default()
+    };
+    let dir = std::env::temp_dir().join(format!("game-stream-recording-test-{}", std::process::id()));
+    let config = RecordingConfig { dir: dir.to_string_lossy().into_owned(), format: RecordingFormat::Flv, ..RecordingConfig::default() };
+    assert!(!config.upload.enabled);
+
+    let stream_manager = Arc::new(StreamManager::new());
+    let stream = stream_manager.create_stream("game".to_string(), stream_info("game")).await.unwrap();
+    stream.set_status(StreamStatus::Live).await;
+    let recordings = RecordingManager::new(&config, stream_manager.clone(), None, Arc::new(WebhookManager::new(&webhook_config)));
+
+    recordings.start_recording("game").await.unwrap();
+    for timestamp in [0, 33, 66] {
+        let data = Bytes::from_static(&[0x17, 0x01, 0, 0, 0, 0, 0, 0, 1, 0x65]);
+        stream.send_media_packet(MediaPacket::Video { data, timestamp, is_keyframe: timestamp == 0, capture_time: None }).await.unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    recordings.stop_recording("game").await.unwrap();
+
+    let payload = tokio::time::timeout(Duration::from_secs(10), webhooks.recv()).await.unwrap().unwrap();
+    assert_eq!(payload["event"], "recording_finished");
+    assert_eq!(payload["stream_key"], "game");
+    let file = payload["data"]["file"].as_str().unwrap();
+    let path = dir.join(file);
+    assert_eq!(payload["data"]["size_bytes"].as_u64().unwrap(), std::fs::metadata(&path).unwrap().len());
+    assert!(game_stream_server::recording::metadata_path(&path).exists(), "metadata is written before the webhook");
+    let _ = std::fs::remove_dir_all(&dir);
+}
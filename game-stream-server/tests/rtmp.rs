@@ -0,0 +1,438 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use game_stream_common::{AuthConfig, ClientStats, OverloadConfig, RtmpServerConfig, StreamManager};
+use game_stream_server::overload::OverloadGuard;
+use game_stream_server::rtmp::RtmpServer;
+use game_stream_server::AuthManager;
+#[cfg(feature = "webrtc")]
+use {
+    axum::body::Body,
+    axum::extract::connect_info::MockConnectInfo,
+    axum::http::{header, Request, StatusCode},
+    axum::response::Response,
+    axum::Router,
+    game_stream_common::{
+        recv_media, GamepadMessage, GamepadState, InputEvent, MediaPacket, RemoteInput, RemoteInputRelayConfig, Rendition, ServerConfig, ViewProtocol,
+        ViewerConnection, WebRtcSignal,
+    },
+    game_stream_server::StreamingServer,
+    tower::ServiceExt,
+    webrtc::api::APIBuilder,
+    webrtc::api::media_engine::MediaEngine,
+    webrtc::data_channel::RTCDataChannel,
+    webrtc::data_channel::data_channel_init::RTCDataChannelInit,
+    webrtc::peer_connection::RTCPeerConnection,
+    webrtc::peer_connection::configuration::RTCConfiguration,
+    webrtc::peer_connection::sdp::session_description::RTCSessionDescription,
+};
+use rml_rtmp::chunk_io::ChunkSerializer;
+use rml_rtmp::handshake::{Handshake, HandshakeProcessResult, PeerType};
+use rml_rtmp::messages::RtmpMessage;
+use rml_rtmp::rml_amf0::Amf0Value;
+use rml_rtmp::sessions::{ClientSession, ClientSessionConfig, ClientSessionEvent, ClientSessionResult, PublishRequestType};
+use rml_rtmp::time::RtmpTimestamp;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// 在随机端口启动 RTMP 服务器
+async fn start_server(overload: OverloadConfig) -> (SocketAddr, Arc<StreamManager>, Arc<OverloadGuard>) {
+    let stream_manager = Arc::new(StreamManager::new());
+    let auth_manager = Arc::new(AuthManager::new(&AuthConfig {
+        enabled: false,
+        valid_stream_keys: Vec::new(),
+        jwt_secret: None,
+        admin_token: None,
+        streams: HashMap::new(),
+    }));
+    let overload_guard = Arc::new(OverloadGuard::new(&overload, stream_manager.clone()));
+    let config = RtmpServerConfig {
+        bind_addr: "127.0.0.1".to_string(),
+        port: 0,
+        chunk_size: 4096,
+        max_connections: 16,
+    };
+
+    let mut server = RtmpServer::new(&config, stream_manager.clone(), auth_manager, overload_guard.clone()).await.unwrap();
+    let listener = server.bind().await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { server.serve(listener).await });
+    (addr, stream_manager, overload_guard)
+}
+
+/// 用 rml_rtmp 客户端会话模拟推流端
+struct Publisher {
+    socket: TcpStream,
+    session: ClientSession,
+    data_serializer: ChunkSerializer,
+    events: Vec<ClientSessionEvent>,
+}
+
+impl Publisher {
+    async fn connect(addr: SocketAddr) -> Self {
+        let mut socket = TcpStream::connect(addr).await.unwrap();
+        let mut handshake = Handshake::new(PeerType::Client);
+        socket.write_all(&handshake.generate_outbound_p0_and_p1().unwrap()).await.unwrap();
+
+        let config = ClientSessionConfig::new();
+        let mut data_serializer = ChunkSerializer::new();
+        data_serializer.set_max_chunk_size(config.chunk_size, RtmpTimestamp::new(0)).unwrap();
+        let (session, results) = ClientSession::new(config).unwrap();
+        let mut publisher = Self { socket, session, data_serializer, events: Vec::new() };
+        publisher.send(results).await;
+
+        let mut buffer = [0u8; 4096];
+        loop {
+            let size = publisher.socket.read(&mut buffer).await.unwrap();
+            assert!(size > 0, "server closed the connection during handshake");
+            match handshake.process_bytes(&buffer[..size]).unwrap() {
+                HandshakeProcessResult::InProgress { response_bytes } => publisher.socket.write_all(&response_bytes).await.unwrap(),
+                HandshakeProcessResult::Completed { response_bytes, remaining_bytes } => {
+                    publisher.socket.write_all(&response_bytes).await.unwrap();
+                    let results = publisher.session.handle_input(&remaining_bytes).unwrap();
+                    publisher.send(results).await;
+                    return publisher;
+                }
+            }
+        }
+    }
+
+    /// connect 后 publish，返回服务器拒绝时的 onStatus code
+    async fn publish(&mut self, stream_key: &str) -> Result<(), String> {
+        let result = self.session.request_connection("live".to_string()).unwrap();
+        self.send(vec![result]).await;
+        self.wait_for(|event| matches!(event, ClientSessionEvent::ConnectionRequestAccepted)).await?;
+
+        let result = self.session.request_publishing(stream_key.to_string(), PublishRequestType::Live).unwrap();
+        self.send(vec![result]).await;
+        self.wait_for(|event| matches!(event, ClientSessionEvent::PublishRequestAccepted)).await?;
+        Ok(())
+    }
+
+    /// 读取直到出现期望的事件，返回该事件；onStatus 错误或连接关闭时返回 Err
+    async fn wait_for(&mut self, expected: impl Fn(&ClientSessionEvent) -> bool) -> Result<ClientSessionEvent, String> {
+        loop {
+            if let Some(index) = self.events.iter().position(&expected) {
+                return Ok(self.events.remove(index));
+            }
+            if let Some(index) = self.events.iter().position(|event| matches!(event, ClientSessionEvent::UnhandleableOnStatusCode { .. })) {
+                let ClientSessionEvent::UnhandleableOnStatusCode { code } = self.events.remove(index) else { unreachable!() };
+                return Err(code);
+            }
+
+            let mut buffer = [0u8; 4096];
+            let size = tokio::time::timeout(Duration::from_secs(5), self.socket.read(&mut buffer)).await
+                .expect("timed out waiting for server")
+                .map_err(|e| e.to_string())?;
+            if size == 0 {
+                return Err("connection closed".to_string());
+            }
+            let results = self.session.handle_input(&buffer[..size]).unwrap();
+            self.send(results).await;
+        }
+    }
+
+    /// 在给定时间内收集服务器发来的指定命令的参数
+    #[cfg(feature = "webrtc")]
+    async fn collect_commands(&mut self, name: &str, duration: Duration) -> Vec<Vec<Amf0Value>> {
+        let deadline = tokio::time::Instant::now() + duration;
+        let mut buffer = [0u8; 4096];
+        while let Ok(size) = tokio::time::timeout_at(deadline, self.socket.read(&mut buffer)).await {
+            let size = size.unwrap();
+            assert!(size > 0, "server closed the connection");
+            let results = self.session.handle_input(&buffer[..size]).unwrap();
+            self.send(results).await;
+        }
+
+        let mut commands = Vec::new();
+        self.events.retain(|event| match event {
+            ClientSessionEvent::UnhandleableAmf0Command { command_name, additional_values, .. } if command_name == name => {
+                commands.push(additional_values.clone());
+                false
+            }
+            _ => true,
+        });
+        commands
+    }
+
+    async fn send_video(&mut self, data: &'static [u8], timestamp: u32) {
+        let result = self.session.publish_video_data(Bytes::from_static(data), RtmpTimestamp::new(timestamp), false).unwrap();
+        self.send(vec![result]).await;
+    }
+
+    /// 与推流客户端相同，在消息流 0 上以完整的块头发送数据消息
+    async fn send_data(&mut self, values: Vec<Amf0Value>) {
+        let payload = RtmpMessage::Amf0Data { values }.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
+        let packet = self.data_serializer.serialize(&payload, true, false).unwrap();
+        self.socket.write_all(&packet.bytes).await.unwrap();
+    }
+
+    async fn send(&mut self, results: Vec<ClientSessionResult>) {
+        for result in results {
+            match result {
+                ClientSessionResult::OutboundResponse(packet) => self.socket.write_all(&packet.bytes).await.unwrap(),
+                ClientSessionResult::RaisedEvent(event) => self.events.push(event),
+                ClientSessionResult::UnhandleableMessageReceived(_) => {}
+            }
+        }
+    }
+}
+
+fn amf0_value(value: &serde_json::Value) -> Amf0Value {
+    match value {
+        serde_json::Value::Null => Amf0Value::Null,
+        serde_json::Value::Bool(value) => Amf0Value::Boolean(*value),
+        serde_json::Value::Number(value) => Amf0Value::Number(value.as_f64().unwrap()),
+        serde_json::Value::String(value) => Amf0Value::Utf8String(value.clone()),
+        serde_json::Value::Array(values) => Amf0Value::StrictArray(values.iter().map(amf0_value).collect()),
+        serde_json::Value::Object(fields) => Amf0Value::Object(
+            fields.iter().map(|(key, value)| (key.clone(), amf0_value(value))).collect()
+        ),
+    }
+}
+
+/// 与 RTMP 服务器共用流管理器的 HTTP 路由 (API 和 WebRTC 信令)
+#[cfg(feature = "webrtc")]
+async fn http_router(stream_manager: Arc<StreamManager>, mut config: ServerConfig) -> Router {
+    config.webrtc.ice_servers.clear();
+    config.storage.hls_segment_dir = std::env::temp_dir()
+        .join(format!("game-stream-rtmp-test-{}", std::process::id()))
+        .to_string_lossy()
+        .into_owned();
+    let server = StreamingServer::builder(config).stream_manager(stream_manager).build().await.unwrap();
+    server.router().await.layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))))
+}
+
+#[cfg(feature = "webrtc")]
+async fn post_signal(router: &Router, signal: &WebRtcSignal) -> Response {
+    let request = Request::post("/api/webrtc/signal")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(serde_json::to_vec(signal).unwrap()))
+        .unwrap();
+    router.clone().oneshot(request).await.unwrap()
+}
+
+/// 用 webrtc-rs 模拟浏览器观看者：创建数据通道，经 HTTP 信令连接，等数据通道打开
+#[cfg(feature = "webrtc")]
+async fn open_data_channel(
+    router: &Router,
+    stream_key: &str,
+    label: &str,
+    init: Option<RTCDataChannelInit>,
+) -> (RTCPeerConnection, Arc<RTCDataChannel>, uuid::Uuid) {
+    let mut media_engine = MediaEngine::default();
+    media_engine.register_default_codecs().unwrap();
+    let api = APIBuilder::new().with_media_engine(media_engine).build();
+    let peer = api.new_peer_connection(RTCConfiguration::default()).await.unwrap();
+    let channel = peer.create_data_channel(label, init).await.unwrap();
+    let opened = Arc::new(tokio::sync::Notify::new());
+    {
+        let opened = opened.clone();
+        channel.on_open(Box::new(move || {
+            opened.notify_one();
+            Box::pin(async {})
+        }));
+    }
+
+    let offer = peer.create_offer(None).await.unwrap();
+    let mut gathering_complete = peer.gathering_complete_promise().await;
+    peer.set_local_description(offer).await.unwrap();
+    let _ = gathering_complete.recv().await;
+    let offer = WebRtcSignal::Offer {
+        stream_key: stream_key.to_string(),
+        sdp: peer.local_description().await.unwrap().sdp,
+        rendition: Rendition::default(),
+        access: None,
+    };
+
+    let response = post_signal(router, &offer).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let Some(WebRtcSignal::Answer { sdp, viewer_id }) = serde_json::from_slice(&body).unwrap() else {
+        panic!("expected an answer");
+    };
+    peer.set_remote_description(RTCSessionDescription::answer(sdp).unwrap()).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(10), opened.notified()).await.expect("data channel not opened");
+    (peer, channel, viewer_id)
+}
+
+/// onRemoteInput 命令的参数解析为 RemoteInput
+#[cfg(feature = "webrtc")]
+fn remote_inputs(commands: Vec<Vec<Amf0Value>>) -> Vec<RemoteInput> {
+    commands.into_iter()
+        .map(|values| match values.first() {
+            Some(Amf0Value::Utf8String(payload)) => serde_json::from_str(payload).unwrap(),
+            other => panic!("unexpected onRemoteInput arguments: {:?}", other),
+        })
+        .collect()
+}
+
+/// 轮询直到条件成立，最多等待 5 秒
+async fn eventually<F: std::future::Future<Output = bool>>(mut condition: impl FnMut() -> F) {
+    for _ in 0..100 {
+        if condition().await {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("condition not met within 5 seconds");
+}
+
+/// 真实的 RTMP 推流：握手、connect/publish 后收到音视频，推流端统计只来自 onClientStats 数据消息
+#[tokio::test]
+async fn publish_media_and_client_stats() {
+    let (addr, stream_manager, _) = start_server(OverloadConfig::default()).await;
+    let mut publisher = Publisher::connect(addr).await;
+    publisher.publish("game").await.unwrap();
+
+    let stream = stream_manager.get_stream("game").await.expect("stream created on publish");
+    publisher.send_video(&[0x17, 0, 0, 0, 0, 1, 0x64, 0, 0x1f], 0).await;
+    eventually(|| async { !stream.sequence_headers().await.is_empty() }).await;
+    assert!(stream.client_stats.read().await.is_none(), "no stats before the publisher reports them");
+
+    let stats = ClientStats { encoded_fps: 59.5, target_bitrate: 6000, dropped_frames: 3, ..ClientStats::default() };
+    publisher.send_data(vec![
+        Amf0Value::Utf8String("onClientStats".to_string()),
+        amf0_value(&serde_json::to_value(&stats).unwrap()),
+    ]).await;
+    eventually(|| async { stream.client_stats.read().await.is_some() }).await;
+
+    let received = stream.client_stats.read().await.clone().unwrap();
+    assert_eq!(received.encoded_fps, 59.5);
+    assert_eq!(received.target_bitrate, 6000);
+    assert_eq!(received.dropped_frames, 3);
+
+    drop(publisher);
+    eventually(|| async { stream_manager.get_stream("game").await.is_none() }).await;
+}
+
+/// 超过过载水位后拒绝新的推流 (onStatus NetStream.Publish.Rejected) 和新的观看者 (503 和 Retry-After)，
+/// 已有的推流和观看者继续工作
+#[cfg(feature = "webrtc")]
+#[tokio::test]
+async fn overload_rejects_only_new_sessions() {
+    let overload = OverloadConfig { max_streams: 1, max_viewers: 1, retry_after: 7, ..OverloadConfig::default() };
+    let (addr, stream_manager, _) = start_server(overload.clone()).await;
+    let router = http_router(stream_manager.clone(), ServerConfig { overload, ..ServerConfig::default() }).await;
+
+    let mut publisher = Publisher::connect(addr).await;
+    publisher.publish("first").await.unwrap();
+    let stream = stream_manager.get_stream("first").await.unwrap();
+    let mut viewer = stream.add_viewer(ViewerConnection {
+        id: uuid::Uuid::new_v4(),
+        remote_addr: SocketAddr::from(([127, 0, 0, 1], 40001)),
+        connected_at: chrono::Utc::now(),
+        protocol: ViewProtocol::WebRtc,
+        stream_key: "first".to_string(),
+        user_agent: None,
+        rendition: Rendition::default(),
+    }).await;
+
+    // 流数量达到上限
+    let mut rejected = Publisher::connect(addr).await;
+    assert_eq!(rejected.publish("second").await, Err("NetStream.Publish.Rejected".to_string()));
+    assert!(stream_manager.get_stream("second").await.is_none());
+
+    // 观看者数量达到上限
+    let offer = WebRtcSignal::Offer { stream_key: "first".to_string(), sdp: String::new(), rendition: Rendition::default(), access: None };
+    let response = post_signal(&router, &offer).await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers()[header::RETRY_AFTER], "7");
+
+    // 已有的推流和观看者不受影响
+    publisher.send_video(&[0x27, 1, 0, 0, 0, 0xaa], 40).await;
+    let packet = tokio::time::timeout(Duration::from_secs(5), recv_media(&mut viewer)).await.unwrap().unwrap();
+    assert!(matches!(packet.as_ref(), MediaPacket::Video { timestamp: 40, is_keyframe: false, .. }));
+}
+
+/// 观看者在 "input" 数据通道上发送的输入按每秒上限转发，推流端以 onRemoteInput 命令收到
+#[cfg(feature = "webrtc")]
+#[tokio::test]
+async fn remote_input_reaches_publisher_rate_limited() {
+    let (addr, stream_manager, _) = start_server(OverloadConfig::default()).await;
+    let mut config = ServerConfig::default();
+    config.webrtc.remote_input = RemoteInputRelayConfig { enabled: true, max_events_per_second: 5, ..config.webrtc.remote_input };
+    let router = http_router(stream_manager.clone(), config).await;
+
+    let mut publisher = Publisher::connect(addr).await;
+    publisher.publish("game").await.unwrap();
+    let (_peer, channel, viewer_id) = open_data_channel(&router, "game", "input", None).await;
+
+    // 等当前的限速窗口过去，之后的一批事件都落在同一个窗口里
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    for _ in 0..12 {
+        let event = InputEvent::Key { key: "w".to_string(), pressed: true };
+        channel.send_text(serde_json::to_string(&event).unwrap()).await.unwrap();
+    }
+
+    let inputs = remote_inputs(publisher.collect_commands("onRemoteInput", Duration::from_millis(700)).await);
+    assert_eq!(inputs.len(), 5, "events beyond max_events_per_second are dropped");
+    assert!(inputs.iter().all(|input| input.viewer_id == viewer_id));
+    assert!(matches!(&inputs[0].event, InputEvent::Key { key, pressed: true } if key == "w"));
+}
+
+/// "gamepad" 通道：时钟同步请求立即回复，旧快照 (含序列号回绕) 丢弃，
+/// 转发的 sent_at 按 onClockSync 测得的时钟差换算为推流端时钟
+#[cfg(feature = "webrtc")]
+#[tokio::test]
+async fn gamepad_clock_sync_and_sequence() {
+    let (addr, stream_manager, _) = start_server(OverloadConfig::default()).await;
+    let mut config = ServerConfig::default();
+    config.webrtc.remote_input = RemoteInputRelayConfig { enabled: true, gamepad_tick_rate: 1000, ..config.webrtc.remote_input };
+    let router = http_router(stream_manager.clone(), config).await;
+
+    // 推流端时钟比服务器慢 5 秒
+    let mut publisher = Publisher::connect(addr).await;
+    publisher.publish("game").await.unwrap();
+    let sync = publisher.collect_commands("onClockSync", Duration::from_millis(500)).await;
+    let Some([Amf0Value::Number(sync_time), ..]) = sync.first().map(Vec::as_slice) else {
+        panic!("expected an onClockSync command, got {:?}", sync);
+    };
+    let replied = chrono::Utc::now().timestamp_millis() as f64;
+    assert!((replied - sync_time).abs() < 1000.0);
+    publisher.send_data(vec![
+        Amf0Value::Utf8String("onClockSync".to_string()),
+        Amf0Value::Number(*sync_time),
+        Amf0Value::Number(sync_time - 5000.0),
+    ]).await;
+
+    let (_peer, channel, viewer_id) = open_data_channel(&router, "game", "gamepad", None).await;
+    let (reply_tx, mut replies) = tokio::sync::mpsc::unbounded_channel();
+    channel.on_message(Box::new(move |message| {
+        let _ = reply_tx.send(serde_json::from_slice::<GamepadMessage>(&message.data).unwrap());
+        Box::pin(async {})
+    }));
+
+    let sync = GamepadMessage::ClockSync { client_time: 123, server_time: None };
+    channel.send_text(serde_json::to_string(&sync).unwrap()).await.unwrap();
+    let reply = tokio::time::timeout(Duration::from_secs(5), replies.recv()).await.unwrap().unwrap();
+    let GamepadMessage::ClockSync { client_time: 123, server_time: Some(server_time) } = reply else {
+        panic!("unexpected reply {:?}", reply);
+    };
+    assert!((chrono::Utc::now().timestamp_millis() - server_time).abs() < 1000);
+
+    let sent_at = chrono::Utc::now().timestamp_millis();
+    for seq in [u32::MAX - 1, u32::MAX - 2, 0, 0] {
+        let state = GamepadMessage::State(GamepadState { seq, sent_at, ..GamepadState::neutral(0) });
+        channel.send_text(serde_json::to_string(&state).unwrap()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    let inputs = remote_inputs(publisher.collect_commands("onRemoteInput", Duration::from_millis(500)).await);
+    let states: Vec<&GamepadState> = inputs.iter()
+        .map(|input| match &input.event {
+            InputEvent::Gamepad(state) if input.viewer_id == viewer_id => state,
+            event => panic!("unexpected input {:?}", event),
+        })
+        .collect();
+    assert_eq!(states.iter().map(|state| state.seq).collect::<Vec<_>>(), [u32::MAX - 1, 0], "stale and duplicate snapshots are dropped");
+    // 服务器假设往返对称：时钟差为 5000 毫秒加半个往返时间
+    let expected = 5000 + ((replied - sync_time) / 2.0) as i64;
+    for state in states {
+        let offset = sent_at - state.sent_at;
+        assert!((expected..expected + 20).contains(&offset), "clock offset {}, expected {}", offset, expected);
+    }
+}
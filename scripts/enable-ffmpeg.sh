@@ -52,10 +52,10 @@ enable_ffmpeg_deps() {
         echo "  ✅ 已启用 game-stream-common 中的 FFmpeg"
     fi
     
-    # game-stream-client
-    if grep -q "# ffmpeg-next" game-stream-client/Cargo.toml; then
-        sed -i.bak 's/# ffmpeg-next/ffmpeg-next/' game-stream-client/Cargo.toml
-        echo "  ✅ 已启用 game-stream-client 中的 FFmpeg"
+    # game-stream-client-core
+    if grep -q "# ffmpeg-next" game-stream-client-core/Cargo.toml; then
+        sed -i.bak 's/# ffmpeg-next/ffmpeg-next/' game-stream-client-core/Cargo.toml
+        echo "  ✅ 已启用 game-stream-client-core 中的 FFmpeg"
     fi
     
     # game-stream-server
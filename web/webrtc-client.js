@@ -4,8 +4,17 @@ class GameStreamViewer {
         this.websocket = null;
         this.isConnected = false;
         this.streamKey = '';
+        this.access = '';
         this.serverUrl = '';
         this.protocol = 'webrtc';
+        this.inputChannel = null; // 远程输入数据通道，服务器开启 [webrtc.remote_input] 时才会打开
+        this.gamepadChannel = null; // 手柄状态快照通道 (无序、不重传)
+        this.gamepadTimer = null;
+        this.gamepadSeq = [];
+        this.clockOffset = 0; // 服务器时钟减本机时钟 (ms)，取往返时间最短的一次时钟同步
+        this.clockRtt = Infinity;
+        this.viewerId = null; // 服务器在 Answer 中返回，切换画质时使用
+        this.streamEvents = null; // 观看人数、开播状态和标题的推送 (SSE)
         
         // 统计信息
         this.stats = {
@@ -17,8 +26,10 @@ class GameStreamViewer {
         // DOM 元素
         this.elements = {
             streamKey: document.getElementById('streamKey'),
+            access: document.getElementById('access'),
             serverUrl: document.getElementById('serverUrl'),
             protocol: document.getElementById('protocol'),
+            rendition: document.getElementById('rendition'),
             connectBtn: document.getElementById('connectBtn'),
             videoElement: document.getElementById('videoElement'),
             videoOverlay: document.getElementById('videoOverlay'),
@@ -40,6 +51,19 @@ class GameStreamViewer {
             this.log('info', `切换到 ${this.protocol.toUpperCase()} 协议`);
         });
         
+        // 画质切换：WebRTC 播放中立即生效，不需要重新连接
+        this.elements.rendition.addEventListener('change', (e) => {
+            if (this.isConnected && this.viewerId) {
+                this.sendSignal({
+                    SelectRendition: {
+                        viewer_id: this.viewerId,
+                        rendition: e.target.value
+                    }
+                });
+                this.log('info', `切换画质: ${e.target.value}`);
+            }
+        });
+        
         // 视频元素事件
         this.elements.videoElement.addEventListener('loadstart', () => {
             this.log('info', '开始加载视频流');
@@ -64,6 +88,119 @@ class GameStreamViewer {
             this.hideVideoOverlay();
             this.log('info', '视频开始播放');
         });
+        
+        this.initializeRemoteInput();
+    }
+    
+    // 画面获得焦点后，把键盘和鼠标事件通过远程输入通道发送 (InputEvent 的 JSON)
+    initializeRemoteInput() {
+        const video = this.elements.videoElement;
+        video.tabIndex = 0;
+        
+        const position = (e) => {
+            const rect = video.getBoundingClientRect();
+            return {
+                x: Math.min(Math.max((e.clientX - rect.left) / rect.width, 0), 1),
+                y: Math.min(Math.max((e.clientY - rect.top) / rect.height, 0), 1)
+            };
+        };
+        const buttons = ['Left', 'Middle', 'Right'];
+        
+        const key = (pressed) => (e) => {
+            if (this.sendInput({ Key: { key: remoteKeyName(e.key), pressed } })) {
+                e.preventDefault();
+            }
+        };
+        video.addEventListener('keydown', (e) => { if (!e.repeat) key(true)(e); });
+        video.addEventListener('keyup', key(false));
+        video.addEventListener('mousemove', (e) => this.sendInput({ MouseMove: position(e) }));
+        for (const [type, pressed] of [['mousedown', true], ['mouseup', false]]) {
+            video.addEventListener(type, (e) => {
+                if (buttons[e.button]) {
+                    this.sendInput({ MouseButton: { button: buttons[e.button], pressed } });
+                }
+            });
+        }
+        video.addEventListener('contextmenu', (e) => {
+            if (this.inputChannel && this.inputChannel.readyState === 'open') {
+                e.preventDefault();
+            }
+        });
+        video.addEventListener('wheel', (e) => {
+            if (this.sendInput({ Scroll: { dx: Math.sign(e.deltaX), dy: Math.sign(e.deltaY) } })) {
+                e.preventDefault();
+            }
+        }, { passive: false });
+    }
+    
+    // 按固定频率发送所有已连接手柄的完整状态，丢失的快照由下一个补上
+    startGamepads() {
+        const TICK_MS = 1000 / 60;
+        const CLOCK_SYNC_INTERVAL = 10000;
+        let lastSync = 0;
+        this.gamepadTimer = setInterval(() => {
+            const channel = this.gamepadChannel;
+            if (!channel || channel.readyState !== 'open') {
+                return;
+            }
+            const now = Date.now();
+            // 开始时连续同步几次，之后定期同步以跟上时钟漂移
+            if (now - lastSync >= (this.clockRtt === Infinity ? 200 : CLOCK_SYNC_INTERVAL)) {
+                lastSync = now;
+                channel.send(JSON.stringify({ ClockSync: { client_time: now } }));
+            }
+            const pads = Array.from(navigator.getGamepads ? navigator.getGamepads() : [])
+                .filter((pad) => pad && pad.connected && pad.mapping === 'standard');
+            pads.forEach((pad, index) => {
+                let buttons = 0;
+                pad.buttons.forEach((button, bit) => {
+                    if (button.pressed && bit !== 6 && bit !== 7) {
+                        buttons |= 1 << bit;
+                    }
+                });
+                this.gamepadSeq[index] = ((this.gamepadSeq[index] || 0) + 1) >>> 0;
+                channel.send(JSON.stringify({
+                    State: {
+                        pad: index,
+                        seq: this.gamepadSeq[index],
+                        sent_at: Math.round(now + this.clockOffset),
+                        buttons,
+                        axes: [0, 1, 2, 3].map((axis) => pad.axes[axis] || 0),
+                        triggers: [6, 7].map((bit) => (pad.buttons[bit] ? pad.buttons[bit].value : 0))
+                    }
+                }));
+            });
+        }, TICK_MS);
+    }
+    
+    handleGamepadMessage(data) {
+        const message = JSON.parse(data);
+        if (!message.ClockSync || message.ClockSync.server_time == null) {
+            return;
+        }
+        const { client_time, server_time } = message.ClockSync;
+        const rtt = Date.now() - client_time;
+        if (rtt <= this.clockRtt) {
+            this.clockRtt = rtt;
+            this.clockOffset = server_time - (client_time + rtt / 2);
+        }
+    }
+    
+    stopGamepads() {
+        clearInterval(this.gamepadTimer);
+        this.gamepadTimer = null;
+        this.gamepadChannel = null;
+        this.gamepadSeq = [];
+        this.clockRtt = Infinity;
+    }
+    
+    // 远程输入通道打开时发送，返回是否已发送
+    sendInput(event) {
+        if (!this.inputChannel || this.inputChannel.readyState !== 'open') {
+            return false;
+        }
+        this.inputChannel.send(JSON.stringify(event));
+        return true;
     }
     
     async toggleConnection() {
@@ -76,6 +213,7 @@ class GameStreamViewer {
     
     async connect() {
         this.streamKey = this.elements.streamKey.value.trim();
+        this.access = this.elements.access.value;
         this.serverUrl = this.elements.serverUrl.value.trim();
         
         if (!this.streamKey) {
@@ -91,6 +229,7 @@ class GameStreamViewer {
         try {
             this.updateConnectionStatus('connecting');
             this.elements.connectBtn.disabled = true;
+            this.subscribeStreamEvents();
             
             if (this.protocol === 'webrtc') {
                 await this.connectWebRTC();
@@ -105,6 +244,35 @@ class GameStreamViewer {
         }
     }
     
+    // 私有直播的观看密码或邀请，附加到播放地址
+    accessQuery() {
+        return this.access ? `?access=${encodeURIComponent(this.access)}` : '';
+    }
+    
+    // 服务器推送的观看人数、开播/下播和标题变化，不需要轮询
+    subscribeStreamEvents() {
+        if (this.streamEvents) {
+            this.streamEvents.close();
+        }
+        const url = `${this.serverUrl.replace('ws', 'http')}/api/streams/${encodeURIComponent(this.streamKey)}/events${this.accessQuery()}`;
+        this.streamEvents = new EventSource(url);
+        
+        this.streamEvents.addEventListener('viewers', (e) => {
+            this.updateStats({ viewerCount: JSON.parse(e.data).viewer_count });
+        });
+        this.streamEvents.addEventListener('status', (e) => {
+            const { live } = JSON.parse(e.data);
+            if (!live) {
+                this.showVideoOverlay('主播未开播');
+            }
+            this.log('info', live ? '直播进行中' : '直播未开始或已结束');
+        });
+        this.streamEvents.addEventListener('info', (e) => {
+            const { title } = JSON.parse(e.data);
+            document.title = title ? `${title} - 游戏直播` : document.title;
+        });
+    }
+    
     async connectWebRTC() {
         this.log('info', '正在建立 WebRTC 连接...');
         
@@ -178,6 +346,13 @@ class GameStreamViewer {
                 }
             };
             
+            // 远程输入：主播授权后，在画面上的键盘和鼠标操作会转发给推流端
+            this.inputChannel = this.peerConnection.createDataChannel('input', { ordered: true });
+            this.inputChannel.onopen = () => this.log('info', '远程输入通道已打开，主播授权后点击画面即可操作');
+            this.gamepadChannel = this.peerConnection.createDataChannel('gamepad', { ordered: false, maxRetransmits: 0 });
+            this.gamepadChannel.onopen = () => this.startGamepads();
+            this.gamepadChannel.onmessage = (e) => this.handleGamepadMessage(e.data);
+            
             // 创建 Offer
             const offer = await this.peerConnection.createOffer({
                 offerToReceiveVideo: true,
@@ -190,7 +365,9 @@ class GameStreamViewer {
             this.sendSignal({
                 Offer: {
                     stream_key: this.streamKey,
-                    sdp: offer.sdp
+                    sdp: offer.sdp,
+                    rendition: this.elements.rendition.value,
+                    access: this.access || null
                 }
             });
             
@@ -203,7 +380,7 @@ class GameStreamViewer {
     async connectHLS() {
         this.log('info', '正在连接 HLS 流...');
         
-        const hlsUrl = `${this.serverUrl.replace('ws', 'http')}/hls/${this.streamKey}/playlist.m3u8`;
+        const hlsUrl = `${this.serverUrl.replace('ws', 'http')}/hls/${this.streamKey}/playlist.m3u8${this.accessQuery()}`;
         
         if (this.elements.videoElement.canPlayType('application/vnd.apple.mpegurl')) {
             // 原生 HLS 支持 (Safari)
@@ -251,6 +428,7 @@ class GameStreamViewer {
             this.log('debug', `接收信令: ${JSON.stringify(signal)}`);
 
             if (signal.Answer) {
+                this.viewerId = signal.Answer.viewer_id;
                 await this.peerConnection.setRemoteDescription({
                     type: 'answer',
                     sdp: signal.Answer.sdp
@@ -277,6 +455,12 @@ class GameStreamViewer {
         this.isConnected = false;
         this.stopStatsCollection();
         
+        this.inputChannel = null;
+        this.stopGamepads();
+        if (this.streamEvents) {
+            this.streamEvents.close();
+            this.streamEvents = null;
+        }
         if (this.peerConnection) {
             this.peerConnection.close();
             this.peerConnection = null;
@@ -426,6 +610,15 @@ class GameStreamViewer {
     }
 }
 
+// 浏览器的按键名称转换为快捷键配置的写法
+function remoteKeyName(key) {
+    const names = {
+        ' ': 'Space', ArrowUp: 'Up', ArrowDown: 'Down', ArrowLeft: 'Left', ArrowRight: 'Right',
+        Control: 'Ctrl', Meta: 'Super', Backspace: 'BackSpace', PrintScreen: 'Print'
+    };
+    return names[key] || key;
+}
+
 // 全局实例
 let viewer;
 